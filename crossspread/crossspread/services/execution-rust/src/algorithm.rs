@@ -0,0 +1,181 @@
+//! Execution algorithms
+//!
+//! An algorithm decides how much each slice of a sliced order takes. The
+//! slicer drives it: it asks for one slice at a time, sends it, and keeps
+//! to what the venue and the risk limits require whatever the algorithm
+//! asks for — the slice notional cap, size jitter, the contract step, the
+//! minimum notional, and folding a remainder too small to send on its own
+//! into the last slice. Slices go out in waves of `max_parallel`, and the
+//! algorithm is told how the last wave filled before sizing the next.
+//!
+//! Fixed-percent and adaptive slicing are built in and picked with
+//! `SlicingConfig::strategy`; fixed-percent slices with an interval between
+//! them make a TWAP. Algorithms are also registered by name in an
+//! `AlgorithmRegistry`, the built-ins as `fixed` and `adaptive`, and picked
+//! with `SlicingConfig::algorithm`; one can be handed to a single slicer
+//! with `OrderSlicer::with_algorithm`.
+//!
+//! There is no percent-of-volume algorithm: no adapter reads the traded
+//! volume such an algorithm would keep pace with, only quotes and books.
+//! One can be registered once a venue's trade feed is.
+
+use anyhow::Result;
+use rust_decimal::Decimal;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::debug;
+
+use crate::slicer::{SlicingConfig, SlicingStrategy};
+
+/// Progress of a sliced order, as the algorithm sees it before each slice
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExecState {
+    /// In contracts, as are the other quantities
+    pub total_quantity: Decimal,
+    /// Not yet sent in any slice
+    pub unplaced: Decimal,
+    pub filled: Decimal,
+    /// Price the order was sized at, zero if unknown
+    pub reference_price: Decimal,
+    pub slices_sent: usize,
+    /// Waves finished so far
+    pub waves: usize,
+    /// Whether the last wave filled in full, inside the touch, before its
+    /// timeout. `None` before the first wave finishes.
+    pub last_wave_clean: Option<bool>,
+}
+
+/// What an algorithm asks the slicer to send next
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SliceInstruction {
+    /// Before the slicer's limits are applied
+    pub quantity: Decimal,
+}
+
+pub trait ExecutionAlgorithm: Send {
+    /// The next slice to send, or `None` to send nothing more and leave the
+    /// rest of the order unfilled
+    fn next_slice(&mut self, state: &ExecState) -> Option<SliceInstruction>;
+}
+
+/// Builds a fresh algorithm for each order a slicer works or plans
+pub type AlgorithmFactory = Arc<dyn Fn(&SlicingConfig) -> Box<dyn ExecutionAlgorithm> + Send + Sync>;
+
+/// Algorithms registered by name, for `SlicingConfig::algorithm` to pick
+#[derive(Clone)]
+pub struct AlgorithmRegistry {
+    factories: HashMap<String, AlgorithmFactory>,
+}
+
+impl Default for AlgorithmRegistry {
+    /// The built-in algorithms
+    fn default() -> Self {
+        let mut registry = Self {
+            factories: HashMap::new(),
+        };
+        registry.register("fixed", |config| Box::new(FixedPercent::new(config)));
+        registry.register("adaptive", |config| Box::new(Adaptive::new(config)));
+        registry
+    }
+}
+
+impl AlgorithmRegistry {
+    /// Make the algorithms `factory` builds available as `name`
+    pub fn register<F>(&mut self, name: &str, factory: F)
+    where
+        F: Fn(&SlicingConfig) -> Box<dyn ExecutionAlgorithm> + Send + Sync + 'static,
+    {
+        self.factories.insert(name.to_string(), Arc::new(factory));
+    }
+
+    /// The factory `config` picks, `None` where it keeps to its strategy
+    pub fn get(&self, config: &SlicingConfig) -> Option<AlgorithmFactory> {
+        self.factories.get(config.algorithm.as_deref()?).cloned()
+    }
+
+    /// Fail unless every algorithm `configs` pick is registered
+    pub fn check<'a>(&self, configs: impl IntoIterator<Item = &'a SlicingConfig>) -> Result<()> {
+        let mut unknown: Vec<&str> = configs
+            .into_iter()
+            .filter_map(|config| config.algorithm.as_deref())
+            .filter(|name| !self.factories.contains_key(*name))
+            .collect();
+        unknown.sort_unstable();
+        unknown.dedup();
+        if !unknown.is_empty() {
+            anyhow::bail!("Unknown execution algorithm {}", unknown.join(", "));
+        }
+        Ok(())
+    }
+}
+
+/// The built-in algorithm for `config.strategy`
+pub fn for_config(config: &SlicingConfig) -> Box<dyn ExecutionAlgorithm> {
+    match config.strategy {
+        SlicingStrategy::Fixed => Box::new(FixedPercent::new(config)),
+        SlicingStrategy::Adaptive => Box::new(Adaptive::new(config)),
+    }
+}
+
+/// Every slice is `slice_percent` of the total
+pub struct FixedPercent {
+    slice_percent: Decimal,
+}
+
+impl FixedPercent {
+    pub fn new(config: &SlicingConfig) -> Self {
+        Self {
+            slice_percent: Decimal::try_from(config.slice_percent).unwrap_or(Decimal::ONE),
+        }
+    }
+}
+
+impl ExecutionAlgorithm for FixedPercent {
+    fn next_slice(&mut self, state: &ExecState) -> Option<SliceInstruction> {
+        Some(SliceInstruction {
+            quantity: state.total_quantity * self.slice_percent,
+        })
+    }
+}
+
+/// Starts at `slice_percent` of the total, multiplying it by
+/// `adaptive_factor` after each wave that filled cleanly and dividing by it
+/// after one that didn't, within `min_slice_percent` and `max_slice_percent`
+pub struct Adaptive {
+    slice_percent: Decimal,
+    factor: Decimal,
+    min: Decimal,
+    max: Decimal,
+    /// Waves already adapted to
+    waves: usize,
+}
+
+impl Adaptive {
+    pub fn new(config: &SlicingConfig) -> Self {
+        Self {
+            slice_percent: Decimal::try_from(config.slice_percent).unwrap_or(Decimal::ONE),
+            factor: Decimal::try_from(config.adaptive_factor).unwrap_or(Decimal::ONE),
+            min: Decimal::try_from(config.min_slice_percent).unwrap_or_default(),
+            max: Decimal::try_from(config.max_slice_percent).unwrap_or(Decimal::ONE),
+            waves: 0,
+        }
+    }
+}
+
+impl ExecutionAlgorithm for Adaptive {
+    fn next_slice(&mut self, state: &ExecState) -> Option<SliceInstruction> {
+        if let Some(clean) = state.last_wave_clean.filter(|_| state.waves > self.waves) {
+            self.waves = state.waves;
+            let next = if clean || self.factor.is_zero() {
+                self.slice_percent * self.factor
+            } else {
+                self.slice_percent / self.factor
+            };
+            self.slice_percent = next.max(self.min).min(self.max);
+            debug!("Adaptive slice fraction is now {}", self.slice_percent);
+        }
+        Some(SliceInstruction {
+            quantity: state.total_quantity * self.slice_percent,
+        })
+    }
+}
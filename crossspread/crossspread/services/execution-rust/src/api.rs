@@ -0,0 +1,507 @@
+//! HTTP request interface
+//!
+//! An alternative to the request stream for integrators that would rather
+//! not speak Redis. `POST /execute` takes the same JSON as the stream and
+//! runs it through the same execution paths, answering with the
+//! `ExecutionResult` once the trade finishes. Long TWAPs can outlast an HTTP
+//! client's patience, so with `?wait=false` the request is answered straight
+//! away with 202 and a `/results/{trade_id}` URL to poll instead.
+//!
+//! `POST /estimate` projects the cost of one order against the current book
+//! without trading, with the slice plan execution would follow.
+//!
+//! `GET /positions` lists the positions live entries left open, with their
+//! unrealized PnL as of the last mark, and `/positions/{trade_id}` reads one.
+//! `GET /orders` lists the orders this process has resting right now.
+//!
+//! Every route but `/healthz` and `/metrics` wants an `Authorization:
+//! Bearer` header carrying `HTTP_API_TOKEN`. A trade id is only executed
+//! once at a time, so a client retrying an `/execute` that is still running
+//! gets 409 rather than a second trade.
+
+use anyhow::{Context, Result};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, Request, StatusCode};
+use axum::middleware::{self, Next};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use serde::Deserialize;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::health;
+use crate::order::{parse_request, EstimateRequest, ExecutionResult, ExecutionServer};
+
+/// How long the result of a trade submitted without waiting can be polled
+const RESULT_RETENTION: Duration = Duration::from_secs(3600);
+
+/// Trades submitted without waiting, `None` until they finish, with when
+/// each was submitted
+type Results = HashMap<Uuid, (Option<ExecutionResult>, Instant)>;
+
+#[derive(Clone)]
+struct Api {
+    server: Arc<ExecutionServer>,
+    results: Arc<RwLock<Results>>,
+    /// Trades executing right now, whether or not their client waits
+    in_flight: Arc<Mutex<HashSet<Uuid>>>,
+    token: Arc<str>,
+}
+
+/// A trade id held in flight, released when dropped
+struct InFlight {
+    trade_id: Uuid,
+    in_flight: Arc<Mutex<HashSet<Uuid>>>,
+}
+
+impl Api {
+    /// Hold `trade_id` in flight, or `None` if it already is
+    fn start(&self, trade_id: Uuid) -> Option<InFlight> {
+        self.in_flight.lock().unwrap().insert(trade_id).then(|| InFlight {
+            trade_id,
+            in_flight: self.in_flight.clone(),
+        })
+    }
+}
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        self.in_flight.lock().unwrap().remove(&self.trade_id);
+    }
+}
+
+#[derive(Deserialize)]
+struct ExecuteParams {
+    /// Answer once the trade finishes, rather than straight away
+    #[serde(default = "default_wait")]
+    wait: bool,
+}
+
+fn default_wait() -> bool {
+    true
+}
+
+/// Accept execution requests on `addr` from clients bearing `token`, until
+/// the listener fails
+pub async fn serve(addr: impl Into<SocketAddr>, token: String, server: Arc<ExecutionServer>) -> Result<()> {
+    let addr = addr.into();
+    let listener = std::net::TcpListener::bind(addr)
+        .with_context(|| format!("Failed to listen for HTTP requests on {}", addr))?;
+    info!("Serving POST /execute on {}", addr);
+    serve_on(listener, token, server).await
+}
+
+async fn serve_on(listener: std::net::TcpListener, token: String, server: Arc<ExecutionServer>) -> Result<()> {
+    let health = health::router(server.clock(), server.maintenance(), server.adapters());
+    let api = Api {
+        server,
+        results: Arc::new(RwLock::new(HashMap::new())),
+        in_flight: Arc::new(Mutex::new(HashSet::new())),
+        token: token.into(),
+    };
+    let router = Router::new()
+        .route("/execute", post(execute))
+        .route("/results/:trade_id", get(result))
+        .route("/estimate", post(estimate))
+        .route("/positions", get(positions))
+        .route("/positions/:trade_id", get(position))
+        .route("/orders", get(orders))
+        .route_layer(middleware::from_fn_with_state(api.clone(), authorize))
+        .with_state(api)
+        .merge(health);
+
+    axum::Server::from_tcp(listener)?
+        .serve(router.into_make_service())
+        .await?;
+    Ok(())
+}
+
+/// Turn away requests without the bearer token
+async fn authorize<B>(State(api): State<Api>, request: Request<B>, next: Next<B>) -> Response {
+    if bearer_matches(request.headers(), &api.token) {
+        next.run(request).await
+    } else {
+        (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")], "unauthorized\n").into_response()
+    }
+}
+
+/// Whether `headers` carry `token` as a bearer token, compared in constant
+/// time. An empty token matches nothing.
+fn bearer_matches(headers: &HeaderMap, token: &str) -> bool {
+    let Some(presented) = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+    else {
+        return false;
+    };
+    !token.is_empty()
+        && presented.len() == token.len()
+        && presented.bytes().zip(token.bytes()).fold(0, |diff, (a, b)| diff | (a ^ b)) == 0
+}
+
+async fn execute(State(api): State<Api>, Query(params): Query<ExecuteParams>, body: String) -> Response {
+    let request = match parse_request(&body) {
+        Ok(request) => request,
+        Err((trade_id, error)) => {
+            warn!("Rejecting HTTP request: {}", error);
+            let result = ExecutionResult::failed(trade_id, error);
+            return (StatusCode::BAD_REQUEST, Json(result)).into_response();
+        }
+    };
+
+    let trade_id = request.trade_id();
+    let conflict = |error: String| (StatusCode::CONFLICT, Json(ExecutionResult::failed(trade_id, error))).into_response();
+    let Some(in_flight) = api.start(trade_id) else {
+        return conflict(format!("Trade {} is already executing", trade_id));
+    };
+    if params.wait {
+        // Run on its own task so a client hanging up doesn't cancel the trade
+        let server = api.server.clone();
+        let execution = tokio::spawn(async move {
+            let result = server.execute(request).await;
+            drop(in_flight);
+            result
+        });
+        return match execution.await {
+            Ok(result) => Json(result).into_response(),
+            Err(e) => {
+                let result = ExecutionResult::failed(trade_id, format!("Execution task failed: {}", e));
+                (StatusCode::INTERNAL_SERVER_ERROR, Json(result)).into_response()
+            }
+        };
+    }
+
+    {
+        let mut results = api.results.write().await;
+        results.retain(|_, (_, submitted)| submitted.elapsed() < RESULT_RETENTION);
+        if results.contains_key(&trade_id) {
+            return conflict(format!("Trade {} was already submitted", trade_id));
+        }
+        results.insert(trade_id, (None, Instant::now()));
+    }
+
+    let results = api.results.clone();
+    tokio::spawn(async move {
+        let result = api.server.execute(request).await;
+        drop(in_flight);
+        if let Some((slot, _)) = results.write().await.get_mut(&trade_id) {
+            *slot = Some(result);
+        }
+    });
+
+    let url = format!("/results/{}", trade_id);
+    let body = serde_json::json!({ "trade_id": trade_id, "result_url": url });
+    (StatusCode::ACCEPTED, [(header::LOCATION, url)], Json(body)).into_response()
+}
+
+/// The result of a trade submitted without waiting: 202 while it executes,
+/// 404 if it is unknown or no longer kept
+async fn result(State(api): State<Api>, Path(trade_id): Path<Uuid>) -> Response {
+    match api.results.read().await.get(&trade_id) {
+        Some((Some(result), _)) => Json(result.clone()).into_response(),
+        Some((None, _)) => {
+            let body = serde_json::json!({ "trade_id": trade_id, "status": "executing" });
+            (StatusCode::ACCEPTED, Json(body)).into_response()
+        }
+        None => (StatusCode::NOT_FOUND, "unknown trade\n").into_response(),
+    }
+}
+
+async fn estimate(State(api): State<Api>, body: String) -> Response {
+    let request: EstimateRequest = match serde_json::from_str(&body) {
+        Ok(request) => request,
+        Err(e) => return (StatusCode::BAD_REQUEST, format!("Invalid estimate request: {}\n", e)).into_response(),
+    };
+    match api.server.estimate(&request).await {
+        Ok(estimate) => Json(estimate).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, format!("{:#}\n", e)).into_response(),
+    }
+}
+
+async fn positions(State(api): State<Api>) -> Response {
+    Json(api.server.positions().all().await).into_response()
+}
+
+async fn position(State(api): State<Api>, Path(trade_id): Path<Uuid>) -> Response {
+    match api.server.positions().get(trade_id).await {
+        Some(position) => Json(position).into_response(),
+        None => (StatusCode::NOT_FOUND, "no open position\n").into_response(),
+    }
+}
+
+async fn orders(State(api): State<Api>) -> Response {
+    Json(api.server.orders().live()).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::credentials::CredentialSource;
+    use crate::exchange::mock::{credentials, MockAdapter};
+    use crate::exchange::{Credentials, ExchangeAdapter};
+    use async_trait::async_trait;
+    use rust_decimal_macros::dec;
+
+    struct StaticCredentials;
+
+    #[async_trait]
+    impl CredentialSource for StaticCredentials {
+        async fn load(&self, _api_key_id: Uuid) -> Result<Credentials> {
+            Ok(credentials())
+        }
+    }
+
+    fn entry(trade_id: Uuid) -> String {
+        serde_json::json!({
+            "trade_id": trade_id,
+            "user_id": Uuid::new_v4(),
+            "spread_id": Uuid::new_v4(),
+            "size_in_coins": "1",
+            "slicing": { "slice_size_coins": null, "slice_interval_ms": null },
+            "mode": "live",
+            "long_exchange_id": "long",
+            "long_symbol": "BTCUSDT",
+            "long_api_key_id": Uuid::new_v4(),
+            "short_exchange_id": "short",
+            "short_symbol": "BTCUSDT",
+            "short_api_key_id": Uuid::new_v4(),
+        })
+        .to_string()
+    }
+
+    #[tokio::test]
+    async fn test_execute_over_http() {
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+            Box::new(MockAdapter::new("long", dec!(100), dec!(101))),
+            Box::new(MockAdapter::new("short", dec!(102), dec!(103))),
+        ];
+        let server = ExecutionServer::new(adapters, Config::for_tests())
+            .with_credential_source(Box::new(StaticCredentials));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve_on(listener, "secret".to_string(), Arc::new(server)));
+        let client = authorized_client("secret");
+
+        // Answered once the trade finishes
+        let trade_id = Uuid::new_v4();
+        let resp = client
+            .post(format!("{}/execute", base))
+            .body(entry(trade_id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        let result: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(result["trade_id"], trade_id.to_string());
+        assert_eq!(result["success"], true);
+
+        // The entry is now an open position
+        let resp = client
+            .get(format!("{}/positions/{}", base, trade_id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 200);
+        let position: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(position["legs"][0]["side"], "buy");
+        assert_eq!(position["legs"][1]["side"], "sell");
+        let open: Vec<serde_json::Value> = client
+            .get(format!("{}/positions", base))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(open.len(), 1);
+
+        // Both legs filled on placement, so nothing is resting
+        let resting: Vec<serde_json::Value> = client
+            .get(format!("{}/orders", base))
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        assert!(resting.is_empty());
+
+        // Answered straight away, then polled
+        let trade_id = Uuid::new_v4();
+        let resp = client
+            .post(format!("{}/execute?wait=false", base))
+            .body(entry(trade_id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 202);
+        let url = format!("/results/{}", trade_id);
+        assert_eq!(resp.headers()[header::LOCATION], url.as_str());
+
+        let result = loop {
+            let resp = client.get(format!("{}{}", base, url)).send().await.unwrap();
+            if resp.status() == 200 {
+                break resp.json::<serde_json::Value>().await.unwrap();
+            }
+            assert_eq!(resp.status(), 202);
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        };
+        assert_eq!(result["success"], true);
+
+        let resp = client
+            .post(format!("{}/execute?wait=false", base))
+            .body(entry(trade_id))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 409);
+
+        // Estimates need a book to walk, which the mock doesn't serve
+        let estimate = serde_json::json!({
+            "exchange_id": "long",
+            "symbol": "BTCUSDT",
+            "side": "buy",
+            "size_in_coins": "1",
+        });
+        let resp = client
+            .post(format!("{}/estimate", base))
+            .body(estimate.to_string())
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 400);
+
+        // Requests that can't be read are rejected with the same error the stream gives
+        let resp = client
+            .post(format!("{}/execute", base))
+            .body("not json")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(resp.status(), 400);
+        let result: serde_json::Value = resp.json().await.unwrap();
+        assert!(result["error"].as_str().unwrap().starts_with("Malformed request"));
+
+        let unknown = client
+            .get(format!("{}/results/{}", base, Uuid::new_v4()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unknown.status(), 404);
+        let unknown = client
+            .get(format!("{}/positions/{}", base, Uuid::new_v4()))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(unknown.status(), 404);
+    }
+
+    fn authorized_client(token: &str) -> reqwest::Client {
+        let mut headers = reqwest::header::HeaderMap::new();
+        headers.insert(header::AUTHORIZATION, format!("Bearer {}", token).parse().unwrap());
+        reqwest::Client::builder().default_headers(headers).build().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_requests_without_the_token_are_refused_but_health_is_open() {
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+            Box::new(MockAdapter::new("long", dec!(100), dec!(101))),
+            Box::new(MockAdapter::new("short", dec!(102), dec!(103))),
+        ];
+        let server = ExecutionServer::new(adapters, Config::for_tests())
+            .with_credential_source(Box::new(StaticCredentials));
+        let server = Arc::new(server);
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve_on(listener, "secret".to_string(), server.clone()));
+
+        for client in [reqwest::Client::new(), authorized_client("wrong")] {
+            let resp = client
+                .post(format!("{}/execute", base))
+                .body(entry(Uuid::new_v4()))
+                .send()
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), 401);
+            let resp = client.get(format!("{}/positions", base)).send().await.unwrap();
+            assert_eq!(resp.status(), 401);
+        }
+        // Nothing was traded
+        assert!(server.positions().all().await.is_empty());
+
+        let client = reqwest::Client::new();
+        for path in ["/healthz", "/metrics"] {
+            let resp = client.get(format!("{}{}", base, path)).send().await.unwrap();
+            assert_eq!(resp.status(), 200, "{}", path);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_a_retry_of_an_executing_trade_is_refused() {
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+            Box::new(MockAdapter::new("long", dec!(100), dec!(101)).with_price_delay(Duration::from_millis(300))),
+            Box::new(MockAdapter::new("short", dec!(102), dec!(103))),
+        ];
+        let server = ExecutionServer::new(adapters, Config::for_tests())
+            .with_credential_source(Box::new(StaticCredentials));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve_on(listener, "secret".to_string(), Arc::new(server)));
+        let client = authorized_client("secret");
+
+        let trade_id = Uuid::new_v4();
+        let submit = || client.post(format!("{}/execute", base)).body(entry(trade_id)).send();
+        let first = tokio::spawn(submit());
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        let retry = submit().await.unwrap();
+        assert_eq!(retry.status(), 409);
+        let result: serde_json::Value = retry.json().await.unwrap();
+        assert!(result["error"].as_str().unwrap().contains("already executing"), "{}", result);
+
+        let first = first.await.unwrap().unwrap();
+        assert_eq!(first.status(), 200);
+        let result: serde_json::Value = first.json().await.unwrap();
+        assert_eq!(result["success"], true);
+    }
+
+    #[tokio::test]
+    async fn test_a_waiting_trade_finishes_after_the_client_hangs_up() {
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+            Box::new(MockAdapter::new("long", dec!(100), dec!(101)).with_price_delay(Duration::from_millis(300))),
+            Box::new(MockAdapter::new("short", dec!(102), dec!(103))),
+        ];
+        let server = ExecutionServer::new(adapters, Config::for_tests())
+            .with_credential_source(Box::new(StaticCredentials));
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(serve_on(listener, "secret".to_string(), Arc::new(server)));
+        let client = authorized_client("secret");
+
+        let trade_id = Uuid::new_v4();
+        let hung_up = client
+            .post(format!("{}/execute", base))
+            .body(entry(trade_id))
+            .timeout(Duration::from_millis(100))
+            .send()
+            .await;
+        assert!(hung_up.unwrap_err().is_timeout());
+
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            let resp = client.get(format!("{}/positions/{}", base, trade_id)).send().await.unwrap();
+            if resp.status() == 200 {
+                break;
+            }
+            assert!(Instant::now() < deadline, "trade was cancelled with the request");
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+}
@@ -0,0 +1,147 @@
+//! Cancel-on-disconnect
+//!
+//! OKX and Bybit can cancel an account's open orders by themselves once they
+//! stop hearing from us, which covers the service dying mid-trade with slices
+//! still resting. Each account is registered the first time it trades and
+//! the registration is refreshed on a timer from then on: OKX counts down
+//! from each refresh, while Bybit watches a private WebSocket that the
+//! adapter keeps open, so refreshing it changes nothing. Other venues don't
+//! offer this; they are warned about once and skipped.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::exchange::{Credentials, ExchangeAdapter};
+
+/// An account: its venue and credentials
+type Account = (Arc<dyn ExchangeAdapter>, Credentials);
+
+pub struct CancelOnDisconnect {
+    /// Zero disables registration
+    timeout_secs: u64,
+    /// Accounts seen by exchange and API key. Those on venues without
+    /// support are kept too, so they are only warned about once.
+    accounts: RwLock<HashMap<(String, String), Account>>,
+}
+
+impl CancelOnDisconnect {
+    pub fn new(timeout_secs: u64) -> Self {
+        Self {
+            timeout_secs,
+            accounts: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Register the account behind `credentials` unless it already is
+    pub async fn arm(&self, adapter: Arc<dyn ExchangeAdapter>, credentials: &Credentials) {
+        if self.timeout_secs == 0 {
+            return;
+        }
+        let key = (adapter.id().to_string(), credentials.api_key.clone());
+        if self.accounts.read().await.contains_key(&key) {
+            return;
+        }
+
+        // Kept even if this fails, so the next refresh tries again
+        self.register(adapter.as_ref(), credentials, self.timeout_secs).await;
+        self.accounts
+            .write()
+            .await
+            .insert(key, (adapter, credentials.clone()));
+    }
+
+    /// Renew every registration
+    pub async fn refresh(&self) {
+        for (adapter, credentials) in self.registered().await {
+            self.register(adapter.as_ref(), &credentials, self.timeout_secs).await;
+        }
+    }
+
+    /// Refresh often enough that a registration never lapses while we run
+    pub async fn run(self: Arc<Self>) {
+        if self.timeout_secs == 0 {
+            return;
+        }
+        let interval = Duration::from_secs(self.timeout_secs).div_f64(3.0);
+        loop {
+            tokio::time::sleep(interval).await;
+            self.refresh().await;
+        }
+    }
+
+    /// On a graceful shutdown either leave the registrations to fire, which
+    /// cancels every resting order once the timeout passes, or clear them so
+    /// resting orders survive the restart
+    pub async fn shutdown(&self, cancel_orders: bool) {
+        let accounts = self.registered().await;
+        if accounts.is_empty() {
+            return;
+        }
+        if cancel_orders {
+            info!(
+                "Leaving cancel-on-disconnect armed on {} accounts, open orders cancel in {} s",
+                accounts.len(),
+                self.timeout_secs
+            );
+            return;
+        }
+        for (adapter, credentials) in accounts {
+            self.register(adapter.as_ref(), &credentials, 0).await;
+        }
+    }
+
+    /// Accounts on venues that actually registered
+    async fn registered(&self) -> Vec<Account> {
+        self.accounts
+            .read()
+            .await
+            .values()
+            .filter(|(adapter, _)| adapter.supports_cancel_on_disconnect())
+            .cloned()
+            .collect()
+    }
+
+    async fn register(&self, adapter: &dyn ExchangeAdapter, credentials: &Credentials, timeout_secs: u64) {
+        if let Err(e) = adapter.set_cancel_on_disconnect(credentials, timeout_secs).await {
+            warn!("Cancel-on-disconnect on {} failed: {:#}", adapter.id(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{credentials, MockAdapter};
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn test_accounts_are_registered_once_then_refreshed() {
+        let supported = Arc::new(MockAdapter::new("okx", dec!(100), dec!(101)).with_cancel_on_disconnect());
+        let unsupported = Arc::new(MockAdapter::new("mexc", dec!(100), dec!(101)));
+        let switch = CancelOnDisconnect::new(60);
+
+        switch.arm(supported.clone(), &credentials()).await;
+        switch.arm(supported.clone(), &credentials()).await;
+        switch.arm(unsupported.clone(), &credentials()).await;
+        assert_eq!(supported.cancel_on_disconnect_calls(), [60]);
+
+        switch.refresh().await;
+        assert_eq!(supported.cancel_on_disconnect_calls(), [60, 60]);
+
+        // Left to fire, nothing is sent; otherwise it is cleared
+        switch.shutdown(true).await;
+        assert_eq!(supported.cancel_on_disconnect_calls(), [60, 60]);
+        switch.shutdown(false).await;
+        assert_eq!(supported.cancel_on_disconnect_calls(), [60, 60, 0]);
+        assert!(unsupported.cancel_on_disconnect_calls().is_empty());
+
+        // Disabled
+        let disabled = CancelOnDisconnect::new(0);
+        let adapter = Arc::new(MockAdapter::new("okx", dec!(100), dec!(101)).with_cancel_on_disconnect());
+        disabled.arm(adapter.clone(), &credentials()).await;
+        assert!(adapter.cancel_on_disconnect_calls().is_empty());
+    }
+}
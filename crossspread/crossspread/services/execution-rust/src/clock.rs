@@ -0,0 +1,110 @@
+//! Exchange clock skew
+//!
+//! Signed requests carry a local timestamp that exchanges reject once it
+//! falls outside their receive window. Each exchange's clock is sampled
+//! periodically so drift shows up in health checks before orders start
+//! failing.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, warn};
+
+use crate::exchange::ExchangeAdapter;
+
+/// One reading of an exchange's clock
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct ClockSample {
+    /// Exchange time minus local time, positive when the exchange is ahead
+    pub offset_ms: i64,
+    pub round_trip_ms: i64,
+    pub measured_at: DateTime<Utc>,
+}
+
+pub struct ClockMonitor {
+    samples: RwLock<HashMap<String, ClockSample>>,
+    /// Offsets beyond this are logged as warnings
+    max_skew_ms: i64,
+}
+
+impl ClockMonitor {
+    pub fn new(max_skew_ms: u64) -> Self {
+        Self {
+            samples: RwLock::new(HashMap::new()),
+            max_skew_ms: i64::try_from(max_skew_ms).unwrap_or(i64::MAX),
+        }
+    }
+
+    /// Read the exchange clock once and record the offset. The exchange is
+    /// assumed to have stamped its time halfway through the round trip.
+    pub async fn measure(&self, adapter: &dyn ExchangeAdapter) -> Result<ClockSample> {
+        let sent = Utc::now();
+        let server_ms = adapter.get_server_time().await?;
+        let received = Utc::now();
+
+        let round_trip_ms = (received - sent).num_milliseconds();
+        let sample = ClockSample {
+            offset_ms: server_ms - (sent.timestamp_millis() + round_trip_ms / 2),
+            round_trip_ms,
+            measured_at: received,
+        };
+
+        if sample.offset_ms.abs() > self.max_skew_ms {
+            warn!(
+                "Clock skew against {} is {} ms, beyond the {} ms margin",
+                adapter.id(),
+                sample.offset_ms,
+                self.max_skew_ms
+            );
+        }
+
+        self.samples
+            .write()
+            .await
+            .insert(adapter.id().to_string(), sample);
+        Ok(sample)
+    }
+
+    /// Measure every adapter each `interval`, forever
+    pub async fn run(self: Arc<Self>, adapters: Vec<Arc<dyn ExchangeAdapter>>, interval: Duration) {
+        loop {
+            for adapter in &adapters {
+                if let Err(e) = self.measure(adapter.as_ref()).await {
+                    debug!("Clock skew unavailable for {}: {}", adapter.id(), e);
+                }
+            }
+            tokio::time::sleep(interval).await;
+        }
+    }
+
+    /// Latest sample for each exchange that reports its time
+    pub async fn samples(&self) -> HashMap<String, ClockSample> {
+        self.samples.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::MockAdapter;
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn test_offset_is_recorded_per_exchange() {
+        let monitor = ClockMonitor::new(5000);
+        let ahead = MockAdapter::new("ahead", dec!(100), dec!(101)).with_clock_offset(7000);
+        let unsupported = MockAdapter::new("unsupported", dec!(100), dec!(101));
+
+        let sample = monitor.measure(&ahead).await.unwrap();
+        assert!((6900..=7000).contains(&sample.offset_ms), "{}", sample.offset_ms);
+        assert!(monitor.measure(&unsupported).await.is_err());
+
+        let samples = monitor.samples().await;
+        assert_eq!(samples.len(), 1);
+        assert_eq!(samples["ahead"].offset_ms, sample.offset_ms);
+    }
+}
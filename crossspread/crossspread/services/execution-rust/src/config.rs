@@ -1,7 +1,16 @@
 //! Configuration module
 
 use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::collections::HashMap;
 use std::env;
+use std::fmt::Display;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use crate::exchange::{ExchangeAdapter, ReferencePriceSource};
+use crate::slicer::SlicingConfig;
+use crate::symbol_policy::SymbolPolicy;
 
 #[derive(Clone, Debug)]
 pub struct Config {
@@ -10,9 +19,138 @@ pub struct Config {
     pub database_url: String,
     pub encryption_key: Vec<u8>,
     pub exchanges: Vec<ExchangeConfig>,
-    pub default_slice_percent: f64,
-    pub default_slice_interval_ms: u64,
-    pub max_parallel_slices: usize,
+    /// Slicing defaults, which each request's slicing parameters override
+    pub slicing: SlicingConfig,
+    /// Slicing defaults for particular symbols, keyed by upper-case native
+    /// or `BASE/QUOTE` symbol, or by base asset (see `slicing_for`)
+    pub symbol_profiles: HashMap<String, SlicingConfig>,
+    /// Entries whose notional exceeds this are rejected before any order is placed
+    pub max_notional_usd: f64,
+    /// Move a symbol to a higher risk limit tier when an entry leg wouldn't
+    /// fit its current one, where the venue allows it, rather than rejecting
+    /// the entry. Higher tiers allow less leverage.
+    pub raise_risk_limits: bool,
+    /// Work the short leg only after the long leg has finished, for venue
+    /// pairs where interleaving orders causes problems
+    pub sequential_legs: bool,
+    /// Where exchange API keys are loaded from
+    pub credential_source: CredentialSourceConfig,
+    /// Where every order sent and its outcome are journaled, if anywhere
+    pub order_journal: Option<JournalSink>,
+    /// Keep every result and its slices in Postgres as well as on the
+    /// result stream
+    pub record_trade_history: bool,
+    /// How reads and cancels spread across an account's additional API keys
+    pub read_key_selection: KeySelection,
+    /// Results that could not be published are kept here until the next start
+    pub dead_letter_path: String,
+    /// Minimum gap between entries on the same exchange and symbol, 0 for none
+    pub symbol_cooldown_ms: u64,
+    /// Exchange clock skew beyond this is logged. Signed requests use a
+    /// 5000 ms receive window, so skew near that makes them fail.
+    pub max_clock_skew_ms: u64,
+    /// Orders one account may have resting at once on one exchange and
+    /// symbol, across all trades
+    pub max_open_orders_per_symbol: usize,
+    /// Entries whose legs, rounded to each venue's contract size, would
+    /// differ in coins by more than this share of the size are rejected
+    pub max_leg_residual_bps: f64,
+    /// Price each leg's slippage and notional are measured against, fetched
+    /// from its venue before slicing
+    pub reference_price_source: ReferencePriceSource,
+    /// Venues that support it cancel an account's open orders once we go
+    /// this long without refreshing, 0 to not register
+    pub cancel_on_disconnect_secs: u64,
+    /// On a graceful shutdown leave cancel-on-disconnect to fire rather
+    /// than clearing it, so no order outlives the service
+    pub cancel_orders_on_shutdown: bool,
+    /// At startup cancel orders carrying our client order id prefix that an
+    /// earlier run left resting, on the warm-up symbols and those with open
+    /// positions. Another instance sharing the prefix would lose its orders.
+    pub cleanup_on_start: bool,
+    /// A trade still executing after this long is stopped, so one stuck
+    /// exchange call can't hold up the requests behind it. 0 for no limit.
+    pub trade_timeout_secs: u64,
+    /// Also accept requests over HTTP on this port, alongside the request
+    /// stream
+    pub http_api_port: Option<u16>,
+    /// Address the HTTP API listens on. Loopback by default; anything wider
+    /// exposes order placement to whoever can reach it and holds the token.
+    pub http_api_bind: IpAddr,
+    /// Bearer token every HTTP API request but `/healthz` and `/metrics`
+    /// must carry. Required when `http_api_port` is set.
+    pub http_api_token: Option<String>,
+    /// How often open positions are marked to their reference price, 0 to
+    /// not mark them
+    pub position_mark_interval_ms: u64,
+    /// How often an exchange found in maintenance is checked for its return.
+    /// Trades on it are held until then. 0 to keep trading through
+    /// maintenance errors.
+    pub maintenance_probe_secs: u64,
+    /// Symbols this deployment may trade; requests for others are rejected
+    /// before any exchange call
+    pub symbol_policy: SymbolPolicy,
+    /// Trades from the request stream executed at once. Further requests
+    /// wait in the stream until one finishes. Requests for the same trade
+    /// run one after another whatever the limit.
+    pub max_concurrent_trades: usize,
+    /// Times an entry leg that fell short on transient errors, such as rate
+    /// limits, works its shortfall again before the trade counts as failed
+    /// and the other legs are unwound
+    pub leg_entry_max_retries: u32,
+    /// Exchanges and symbols whose symbol info, clock offset and leverage
+    /// are fetched at startup rather than by the first trade on them
+    pub warmup_symbols: Vec<(String, String)>,
+    /// Key the warm-up loads and reads leverage with. Without one leverage
+    /// is left to the first trade.
+    pub warmup_api_key_id: Option<uuid::Uuid>,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum KeySelection {
+    /// Every call uses the key named in the request
+    #[default]
+    Primary,
+    /// Reads and cancels rotate through all of the account's keys
+    RoundRobin,
+    /// Reads and cancels use a key picked at random
+    Random,
+}
+
+impl std::str::FromStr for KeySelection {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "primary" => Ok(KeySelection::Primary),
+            "round_robin" => Ok(KeySelection::RoundRobin),
+            "random" => Ok(KeySelection::Random),
+            other => anyhow::bail!("Unknown key selection: {}", other),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum JournalSink {
+    /// JSON lines appended to a file
+    File(String),
+    /// Entries added to a Redis stream
+    RedisStream(String),
+}
+
+#[derive(Clone, Debug)]
+pub enum CredentialSourceConfig {
+    /// Encrypted rows in the backend's `api_keys` table
+    Database,
+    /// Encrypted JSON file of credentials keyed by API key id
+    File { path: String },
+    /// HashiCorp Vault KV v2 engine at `mount`, one secret per key under `path`
+    Vault {
+        addr: String,
+        token: String,
+        mount: String,
+        path: String,
+    },
 }
 
 #[derive(Clone, Debug)]
@@ -20,15 +158,77 @@ pub struct ExchangeConfig {
     pub id: String,
     pub rest_url: String,
     pub ws_url: String,
+    /// WebSocket API orders can be placed over, for venues that have one
+    pub trade_ws_url: Option<String>,
     pub testnet: bool,
+    /// Maker fee in basis points (negative for a rebate)
+    pub maker_fee_bps: f64,
+    /// Taker fee in basis points
+    pub taker_fee_bps: f64,
+    /// Margin mode sent with each order on venues that take one (OKX `tdMode`).
+    /// Detected from the account when unset.
+    pub trade_mode: Option<TradeMode>,
+    /// Sent as the User-Agent header, some venues throttle generic clients
+    pub user_agent: Option<String>,
+    /// HTTP(S) proxy for REST requests
+    pub proxy: Option<String>,
+    /// Log every REST request and raw response body at trace level, with
+    /// keys and signatures redacted
+    pub log_raw_http: bool,
+    /// Place slices over the trading WebSocket rather than REST
+    pub ws_orders: bool,
+    /// Broker or channel tag sent with each order, for rebates and
+    /// reporting. OKX takes it as the order's `tag` and Bybit as a `Referer`
+    /// header. Binance has no separate tag and needs the broker code in the
+    /// client order id prefix instead; other venues ignore it.
+    pub order_tag: Option<String>,
+    /// Start of every client order id, `cs_` when unset. Binance's broker
+    /// program expects `x-<code>`.
+    pub client_order_id_prefix: Option<String>,
+    /// When a placement is turned away because its client order id is
+    /// already taken, return the order holding that id rather than failing,
+    /// so a resent placement does not error on the order the first one made
+    pub adopt_duplicate_orders: bool,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TradeMode {
+    Cross,
+    Isolated,
+    /// Non-margined, spot-style trading
+    Cash,
+}
+
+impl TradeMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TradeMode::Cross => "cross",
+            TradeMode::Isolated => "isolated",
+            TradeMode::Cash => "cash",
+        }
+    }
+}
+
+impl std::str::FromStr for TradeMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "cross" => Ok(TradeMode::Cross),
+            "isolated" => Ok(TradeMode::Isolated),
+            "cash" => Ok(TradeMode::Cash),
+            other => anyhow::bail!("Unknown trade mode: {}", other),
+        }
+    }
 }
 
 impl Config {
+    /// Read the configuration from the environment. Values that don't parse
+    /// are reported together with the problems `validate` finds, in one error.
     pub fn from_env() -> Result<Self> {
-        let port = env::var("EXEC_SERVICE_PORT")
-            .unwrap_or_else(|_| "9000".to_string())
-            .parse()
-            .context("Invalid EXEC_SERVICE_PORT")?;
+        let mut problems = Vec::new();
+
+        let port = parse_var("EXEC_SERVICE_PORT", "9000", &mut problems);
 
         let redis_host = env::var("REDIS_HOST").unwrap_or_else(|_| "localhost".to_string());
         let redis_port = env::var("REDIS_PORT").unwrap_or_else(|_| "6379".to_string());
@@ -44,51 +244,854 @@ impl Config {
             db_user, db_pass, db_host, db_port, db_name
         );
 
-        let encryption_key_b64 = env::var("ENCRYPTION_KEY_BASE64")
-            .context("ENCRYPTION_KEY_BASE64 must be set")?;
-        let encryption_key = base64::decode(&encryption_key_b64)
-            .context("Invalid base64 in ENCRYPTION_KEY_BASE64")?;
+        let encryption_key = env::var("ENCRYPTION_KEY_BASE64")
+            .context("ENCRYPTION_KEY_BASE64 must be set")
+            .and_then(|key| base64::decode(key).context("Invalid base64 in ENCRYPTION_KEY_BASE64"));
+        // A key that is missing or not base64 is reported as such, not also
+        // for its length
+        let encryption_key = recorded(encryption_key, &mut problems).unwrap_or_else(|| vec![0u8; 32]);
+
+        let max_notional_usd = parse_var("MAX_NOTIONAL_USD", "100000", &mut problems);
+        let raise_risk_limits = env::var("RAISE_RISK_LIMITS")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        let slicing = SlicingConfig {
+            slice_percent: parse_var("SLICE_PERCENT", "0.05", &mut problems),
+            interval_ms: parse_var("SLICE_INTERVAL_MS", "100", &mut problems),
+            max_parallel: parse_var("MAX_PARALLEL_SLICES", "1", &mut problems),
+            size_jitter_percent: parse_var("SLICE_SIZE_JITTER_PERCENT", "0", &mut problems),
+            interval_jitter_percent: parse_var("SLICE_INTERVAL_JITTER_PERCENT", "0", &mut problems),
+            max_slice_notional_usd: Some(parse_var("MAX_SLICE_NOTIONAL_USD", "10000", &mut problems)),
+            price_rounding: parse_opt_var("PRICE_ROUNDING", &mut problems).unwrap_or_default(),
+            on_price_failure: parse_opt_var("ON_PRICE_FAILURE", &mut problems).unwrap_or_default(),
+            max_cross_bps: Some(parse_var("MAX_CROSS_BPS", "50", &mut problems)),
+            max_quote_age_ms: parse_var("MAX_QUOTE_AGE_MS", "1000", &mut problems),
+            on_crossed_book: parse_opt_var("ON_CROSSED_BOOK", &mut problems).unwrap_or_default(),
+            crossed_book_wait_ms: parse_var("CROSSED_BOOK_WAIT_MS", "500", &mut problems),
+            spread_target_wait_ms: parse_var("SPREAD_TARGET_WAIT_MS", "60000", &mut problems),
+            post_only_retries: parse_var("POST_ONLY_RETRIES", "2", &mut problems),
+            signature_expiry_retries: parse_var("SIGNATURE_EXPIRY_RETRIES", "1", &mut problems),
+            fees_from_fills: env::var("FEES_FROM_FILLS")
+                .map(|v| v == "true" || v == "1")
+                .unwrap_or(false),
+            emergency_improvement_ms: parse_var("EMERGENCY_IMPROVEMENT_MS", "0", &mut problems),
+            algorithm: env::var("SLICING_ALGORITHM").ok().filter(|name| !name.is_empty()),
+            native_algo: recorded(
+                env::var("NATIVE_ALGO")
+                    .ok()
+                    .filter(|kind| !kind.is_empty())
+                    .map(|kind| kind.parse())
+                    .transpose()
+                    .context("Invalid NATIVE_ALGO"),
+                &mut problems,
+            )
+            .flatten(),
+            ..SlicingConfig::default()
+        };
+
+        let symbol_profiles = recorded(symbol_profiles(&slicing), &mut problems).unwrap_or_default();
+
+        let sequential_legs = env::var("SEQUENTIAL_LEGS")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        let credential_source = match env::var("CREDENTIAL_SOURCE").as_deref() {
+            Err(_) | Ok("database") => Ok(CredentialSourceConfig::Database),
+            Ok("file") => env::var("CREDENTIALS_FILE")
+                .context("CREDENTIALS_FILE must be set for the file credential source")
+                .map(|path| CredentialSourceConfig::File { path }),
+            Ok("vault") => match (env::var("VAULT_ADDR"), env::var("VAULT_TOKEN")) {
+                (Ok(addr), Ok(token)) => Ok(CredentialSourceConfig::Vault {
+                    addr,
+                    token,
+                    mount: env::var("VAULT_MOUNT").unwrap_or_else(|_| "secret".to_string()),
+                    path: env::var("VAULT_PATH").unwrap_or_else(|_| "crossspread/api-keys".to_string()),
+                }),
+                (Err(_), _) => Err(anyhow::anyhow!("VAULT_ADDR must be set")),
+                (_, Err(_)) => Err(anyhow::anyhow!("VAULT_TOKEN must be set")),
+            },
+            Ok(other) => Err(anyhow::anyhow!("Unknown CREDENTIAL_SOURCE: {}", other)),
+        };
+        let credential_source =
+            recorded(credential_source, &mut problems).unwrap_or(CredentialSourceConfig::Database);
+
+        let record_trade_history = env::var("RECORD_TRADE_HISTORY")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        let order_journal = match (env::var("ORDER_JOURNAL_FILE"), env::var("ORDER_JOURNAL_STREAM")) {
+            (Ok(path), _) => Some(JournalSink::File(path)),
+            (_, Ok(stream)) => Some(JournalSink::RedisStream(stream)),
+            _ => None,
+        };
+
+        let dead_letter_path = env::var("RESULT_DEAD_LETTER_FILE")
+            .unwrap_or_else(|_| "execution-results.deadletter.jsonl".to_string());
+
+        let read_key_selection = parse_opt_var("READ_KEY_SELECTION", &mut problems).unwrap_or_default();
+
+        let symbol_cooldown_ms = parse_var("SYMBOL_COOLDOWN_MS", "0", &mut problems);
+
+        let max_clock_skew_ms = parse_var("MAX_CLOCK_SKEW_MS", "1000", &mut problems);
+
+        let max_open_orders_per_symbol = parse_var("MAX_OPEN_ORDERS_PER_SYMBOL", "200", &mut problems);
+
+        let max_leg_residual_bps = parse_var("MAX_LEG_RESIDUAL_BPS", "10", &mut problems);
+
+        let reference_price_source = parse_opt_var("REFERENCE_PRICE_SOURCE", &mut problems).unwrap_or_default();
+
+        let cancel_on_disconnect_secs = parse_var("CANCEL_ON_DISCONNECT_SECS", "0", &mut problems);
+        let cancel_orders_on_shutdown = env::var("CANCEL_ORDERS_ON_SHUTDOWN")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(true);
+        let cleanup_on_start = env::var("CLEANUP_ON_START")
+            .map(|v| v == "true" || v == "1")
+            .unwrap_or(false);
+
+        let trade_timeout_secs = parse_var("TRADE_TIMEOUT_SECS", "600", &mut problems);
+
+        let http_api_port = parse_opt_var("HTTP_API_PORT", &mut problems);
+
+        let http_api_bind = parse_var("HTTP_API_BIND", "127.0.0.1", &mut problems);
+
+        let http_api_token = env::var("HTTP_API_TOKEN").ok().filter(|token| !token.is_empty());
+
+        let position_mark_interval_ms = parse_var("POSITION_MARK_INTERVAL_MS", "5000", &mut problems);
+
+        let maintenance_probe_secs = parse_var("MAINTENANCE_PROBE_SECS", "30", &mut problems);
+
+        let symbol_policy = SymbolPolicy {
+            allowed: recorded(symbol_list("ALLOWED_SYMBOLS"), &mut problems).unwrap_or_default(),
+            denied: recorded(symbol_list("DENIED_SYMBOLS"), &mut problems).unwrap_or_default(),
+        };
+
+        let max_concurrent_trades = parse_var("MAX_CONCURRENT_TRADES", "4", &mut problems);
+
+        let leg_entry_max_retries = parse_var("LEG_ENTRY_MAX_RETRIES", "2", &mut problems);
+
+        let warmup_symbols = env::var("WARMUP_SYMBOLS")
+            .map(|list| parse_warmup_symbols(&list))
+            .unwrap_or_else(|_| Ok(Vec::new()))
+            .context("Invalid WARMUP_SYMBOLS");
+        let warmup_symbols = recorded(warmup_symbols, &mut problems).unwrap_or_default();
+
+        let warmup_api_key_id = parse_opt_var("WARMUP_API_KEY_ID", &mut problems);
+
+        let okx_trade_mode = parse_opt_var("OKX_TD_MODE", &mut problems);
 
         // Configure supported exchanges
-        let exchanges = vec![
+        let mut exchanges = vec![
             ExchangeConfig {
                 id: "binance".to_string(),
                 rest_url: "https://fapi.binance.com".to_string(),
                 ws_url: "wss://fstream.binance.com".to_string(),
+                trade_ws_url: Some("wss://ws-fapi.binance.com/ws-fapi/v1".to_string()),
                 testnet: false,
+                maker_fee_bps: 2.0,
+                taker_fee_bps: 5.0,
+                trade_mode: None,
+                user_agent: None,
+                proxy: None,
+                log_raw_http: false,
+                ws_orders: false,
+                order_tag: None,
+                client_order_id_prefix: None,
+                adopt_duplicate_orders: false,
             },
             ExchangeConfig {
                 id: "bybit".to_string(),
                 rest_url: "https://api.bybit.com".to_string(),
                 ws_url: "wss://stream.bybit.com".to_string(),
+                trade_ws_url: Some("wss://stream.bybit.com/v5/trade".to_string()),
                 testnet: false,
+                maker_fee_bps: 2.0,
+                taker_fee_bps: 5.0,
+                trade_mode: None,
+                user_agent: None,
+                proxy: None,
+                log_raw_http: false,
+                ws_orders: false,
+                order_tag: None,
+                client_order_id_prefix: None,
+                adopt_duplicate_orders: false,
             },
             ExchangeConfig {
                 id: "okx".to_string(),
                 rest_url: "https://www.okx.com".to_string(),
                 ws_url: "wss://ws.okx.com:8443".to_string(),
+                trade_ws_url: None,
                 testnet: false,
+                maker_fee_bps: 2.0,
+                taker_fee_bps: 5.0,
+                trade_mode: okx_trade_mode,
+                user_agent: None,
+                proxy: None,
+                log_raw_http: false,
+                ws_orders: false,
+                order_tag: None,
+                client_order_id_prefix: None,
+                adopt_duplicate_orders: false,
             },
             ExchangeConfig {
                 id: "kucoin".to_string(),
                 rest_url: "https://api-futures.kucoin.com".to_string(),
                 ws_url: "wss://ws-api-futures.kucoin.com".to_string(),
+                trade_ws_url: None,
                 testnet: false,
+                maker_fee_bps: 2.0,
+                taker_fee_bps: 5.0,
+                trade_mode: None,
+                user_agent: None,
+                proxy: None,
+                log_raw_http: false,
+                ws_orders: false,
+                order_tag: None,
+                client_order_id_prefix: None,
+                adopt_duplicate_orders: false,
             },
         ];
 
-        Ok(Config {
+        // <ID>_REST_URL points an exchange at another host, such as a
+        // regional endpoint or a local mock. One User-Agent and proxy apply
+        // to all exchanges, with <ID>_USER_AGENT overrides and
+        // PROXY_DISABLED_EXCHANGES for venues reached directly. LOG_RAW_HTTP,
+        // WS_ORDERS and ADOPT_DUPLICATE_ORDERS are "true" for every exchange
+        // or a list of exchange ids.
+        // <ID>_ORDER_TAG and <ID>_CLIENT_ORDER_ID_PREFIX set broker codes.
+        let user_agent = env::var("HTTP_USER_AGENT").ok();
+        let proxy = env::var("HTTPS_PROXY").or_else(|_| env::var("ALL_PROXY")).ok();
+        let proxy_disabled: Vec<String> = env::var("PROXY_DISABLED_EXCHANGES")
+            .map(|ids| ids.split(',').map(|id| id.trim().to_lowercase()).collect())
+            .unwrap_or_default();
+        let log_raw_http: Vec<String> = env::var("LOG_RAW_HTTP")
+            .map(|ids| ids.split(',').map(|id| id.trim().to_lowercase()).collect())
+            .unwrap_or_default();
+        let ws_orders: Vec<String> = env::var("WS_ORDERS")
+            .map(|ids| ids.split(',').map(|id| id.trim().to_lowercase()).collect())
+            .unwrap_or_default();
+        let adopt_duplicate_orders: Vec<String> = env::var("ADOPT_DUPLICATE_ORDERS")
+            .map(|ids| ids.split(',').map(|id| id.trim().to_lowercase()).collect())
+            .unwrap_or_default();
+        for exchange in &mut exchanges {
+            let url_var = format!("{}_REST_URL", exchange.id.to_uppercase());
+            if let Ok(url) = env::var(&url_var) {
+                let url = parse_rest_url(&url).with_context(|| format!("Invalid {}", url_var));
+                if let Some(url) = recorded(url, &mut problems) {
+                    exchange.rest_url = url;
+                }
+            }
+            exchange.user_agent = env::var(format!("{}_USER_AGENT", exchange.id.to_uppercase()))
+                .ok()
+                .or_else(|| user_agent.clone());
+            if !proxy_disabled.contains(&exchange.id) {
+                exchange.proxy = proxy.clone();
+            }
+            exchange.log_raw_http = log_raw_http
+                .iter()
+                .any(|id| id == "true" || id == "1" || *id == exchange.id);
+            exchange.ws_orders = ws_orders
+                .iter()
+                .any(|id| id == "true" || id == "1" || *id == exchange.id);
+            exchange.adopt_duplicate_orders = adopt_duplicate_orders
+                .iter()
+                .any(|id| id == "true" || id == "1" || *id == exchange.id);
+            exchange.order_tag = env::var(format!("{}_ORDER_TAG", exchange.id.to_uppercase())).ok();
+            exchange.client_order_id_prefix =
+                env::var(format!("{}_CLIENT_ORDER_ID_PREFIX", exchange.id.to_uppercase())).ok();
+        }
+
+        let config = Config {
             port,
             redis_url,
             database_url,
             encryption_key,
             exchanges,
-            default_slice_percent: 0.05, // 5%
-            default_slice_interval_ms: 100,
-            max_parallel_slices: 5,
+            slicing,
+            symbol_profiles,
+            max_notional_usd,
+            raise_risk_limits,
+            sequential_legs,
+            credential_source,
+            order_journal,
+            record_trade_history,
+            read_key_selection,
+            dead_letter_path,
+            symbol_cooldown_ms,
+            max_clock_skew_ms,
+            max_open_orders_per_symbol,
+            max_leg_residual_bps,
+            reference_price_source,
+            cancel_on_disconnect_secs,
+            cancel_orders_on_shutdown,
+            cleanup_on_start,
+            trade_timeout_secs,
+            http_api_port,
+            http_api_bind,
+            http_api_token,
+            position_mark_interval_ms,
+            maintenance_probe_secs,
+            symbol_policy,
+            max_concurrent_trades,
+            leg_entry_max_retries,
+            warmup_symbols,
+            warmup_api_key_id,
+        };
+        problems.extend(config.problems());
+        report(problems)?;
+        Ok(config)
+    }
+
+    /// Values that would otherwise only fail once in use, each described
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if self.port == 0 {
+            problems.push("EXEC_SERVICE_PORT must be between 1 and 65535".to_string());
+        }
+        match self.http_api_port {
+            Some(0) => problems.push("HTTP_API_PORT must be between 1 and 65535".to_string()),
+            Some(port) if port == self.port => {
+                problems.push("HTTP_API_PORT must differ from EXEC_SERVICE_PORT".to_string())
+            }
+            _ => {}
+        }
+        if self.http_api_port.is_some() && self.http_api_token.is_none() {
+            problems.push("HTTP_API_TOKEN must be set to serve the HTTP API".to_string());
+        }
+        if self.encryption_key.len() != 32 {
+            problems.push(format!(
+                "ENCRYPTION_KEY_BASE64 must decode to 32 bytes, got {}",
+                self.encryption_key.len()
+            ));
+        }
+        if let Err(e) = check_url(&self.redis_url, &["redis", "rediss"]) {
+            problems.push(format!("Invalid Redis URL: {}", e));
+        }
+        if let Err(e) = check_url(&self.database_url, &["postgres", "postgresql"]) {
+            // The URL carries the password, so it is left out
+            problems.push(format!("Invalid database URL: {}", e));
+        }
+        if !(self.slicing.slice_percent > 0.0 && self.slicing.slice_percent <= 1.0) {
+            problems.push(format!(
+                "SLICE_PERCENT must be in (0, 1], got {}",
+                self.slicing.slice_percent
+            ));
+        }
+        if self.slicing.max_parallel == 0 {
+            problems.push("MAX_PARALLEL_SLICES must be at least 1".to_string());
+        }
+        for (name, jitter) in [
+            ("SLICE_SIZE_JITTER_PERCENT", self.slicing.size_jitter_percent),
+            ("SLICE_INTERVAL_JITTER_PERCENT", self.slicing.interval_jitter_percent),
+        ] {
+            if !(0.0..1.0).contains(&jitter) {
+                problems.push(format!("{} must be in [0, 1), got {}", name, jitter));
+            }
+        }
+        if self.max_concurrent_trades == 0 {
+            problems.push("MAX_CONCURRENT_TRADES must be at least 1".to_string());
+        }
+        if self.cancel_on_disconnect_secs != 0 {
+            // Each venue takes only its own range of timeouts
+            for (venue, range) in [("okx", 10..=120), ("bybit", 3..=300)] {
+                let configured = self.exchanges.iter().any(|exchange| exchange.id == venue);
+                if configured && !range.contains(&self.cancel_on_disconnect_secs) {
+                    problems.push(format!(
+                        "CANCEL_ON_DISCONNECT_SECS must be between {} and {} for {}, got {}",
+                        range.start(),
+                        range.end(),
+                        venue,
+                        self.cancel_on_disconnect_secs
+                    ));
+                }
+            }
+        }
+        let mut profiled: Vec<_> = self.symbol_profiles.iter().collect();
+        profiled.sort_by(|a, b| a.0.cmp(b.0));
+        for (key, profile) in profiled {
+            if !(profile.slice_percent > 0.0 && profile.slice_percent <= 1.0) {
+                problems.push(format!("{} profile slice_percent must be in (0, 1], got {}", key, profile.slice_percent));
+            }
+            if profile.max_parallel == 0 {
+                problems.push(format!("{} profile max_parallel must be at least 1", key));
+            }
+            for (name, bound) in [
+                ("min_slice_percent", profile.min_slice_percent),
+                ("max_slice_percent", profile.max_slice_percent),
+            ] {
+                if !(bound > 0.0 && bound <= 1.0) {
+                    problems.push(format!("{} profile {} must be in (0, 1], got {}", key, name, bound));
+                }
+            }
+            if profile.min_slice_percent > profile.max_slice_percent {
+                problems.push(format!(
+                    "{} profile min_slice_percent {} exceeds max_slice_percent {}",
+                    key, profile.min_slice_percent, profile.max_slice_percent
+                ));
+            }
+            for (name, bps) in [
+                ("price_tolerance_bps", Some(profile.price_tolerance_bps)),
+                ("max_cross_bps", profile.max_cross_bps),
+            ] {
+                if let Some(bps) = bps.filter(|bps| *bps < 0.0) {
+                    problems.push(format!("{} profile {} must not be negative, got {}", key, name, bps));
+                }
+            }
+            if profile.slice_timeout_secs == 0 {
+                problems.push(format!("{} profile slice_timeout_secs must be at least 1", key));
+            }
+            if let Some(notional) = profile.max_slice_notional_usd.filter(|notional| *notional <= 0.0) {
+                problems.push(format!("{} profile max_slice_notional_usd must be positive, got {}", key, notional));
+            }
+            for (name, jitter) in [
+                ("size_jitter_percent", profile.size_jitter_percent),
+                ("interval_jitter_percent", profile.interval_jitter_percent),
+            ] {
+                if !(0.0..1.0).contains(&jitter) {
+                    problems.push(format!("{} profile {} must be in [0, 1), got {}", key, name, jitter));
+                }
+            }
+        }
+
+        problems
+    }
+
+    /// Slicing defaults for `symbol` on `adapter`'s venue: the profile of the
+    /// symbol itself, by native or `BASE/QUOTE` name, else that of its base
+    /// asset, else the service-wide defaults
+    pub fn slicing_for(&self, adapter: &dyn ExchangeAdapter, symbol: &str) -> &SlicingConfig {
+        if self.symbol_profiles.is_empty() {
+            return &self.slicing;
+        }
+        let native = adapter.native_symbol(symbol);
+        let mut keys = vec![native.to_uppercase()];
+        if let Some(canonical) = adapter.to_canonical_symbol(&native).map(|c| c.to_uppercase()) {
+            keys.extend(canonical.split('/').next().map(str::to_string));
+            keys.insert(1, canonical);
+        }
+        keys.iter()
+            .find_map(|key| self.symbol_profiles.get(key))
+            .unwrap_or(&self.slicing)
+    }
+}
+
+/// What a symbol profile may change of the slicing defaults
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SlicingProfile {
+    slice_percent: Option<f64>,
+    interval_ms: Option<u64>,
+    max_parallel: Option<usize>,
+    price_tolerance_bps: Option<f64>,
+    max_cross_bps: Option<f64>,
+    slice_timeout_secs: Option<u64>,
+    max_slice_notional_usd: Option<f64>,
+    min_slice_percent: Option<f64>,
+    max_slice_percent: Option<f64>,
+    size_jitter_percent: Option<f64>,
+    interval_jitter_percent: Option<f64>,
+    algorithm: Option<String>,
+}
+
+impl SlicingProfile {
+    fn apply(self, defaults: &SlicingConfig) -> SlicingConfig {
+        SlicingConfig {
+            slice_percent: self.slice_percent.unwrap_or(defaults.slice_percent),
+            interval_ms: self.interval_ms.unwrap_or(defaults.interval_ms),
+            max_parallel: self.max_parallel.unwrap_or(defaults.max_parallel),
+            price_tolerance_bps: self.price_tolerance_bps.unwrap_or(defaults.price_tolerance_bps),
+            max_cross_bps: self.max_cross_bps.or(defaults.max_cross_bps),
+            slice_timeout_secs: self.slice_timeout_secs.unwrap_or(defaults.slice_timeout_secs),
+            max_slice_notional_usd: self.max_slice_notional_usd.or(defaults.max_slice_notional_usd),
+            min_slice_percent: self.min_slice_percent.unwrap_or(defaults.min_slice_percent),
+            max_slice_percent: self.max_slice_percent.unwrap_or(defaults.max_slice_percent),
+            size_jitter_percent: self.size_jitter_percent.unwrap_or(defaults.size_jitter_percent),
+            interval_jitter_percent: self.interval_jitter_percent.unwrap_or(defaults.interval_jitter_percent),
+            algorithm: self.algorithm.or_else(|| defaults.algorithm.clone()),
+            ..defaults.clone()
+        }
+    }
+}
+
+/// Fail with every problem found, if there are any
+fn report(problems: Vec<String>) -> Result<()> {
+    if !problems.is_empty() {
+        anyhow::bail!("Invalid configuration:\n  {}", problems.join("\n  "));
+    }
+    Ok(())
+}
+
+/// Variable `name` parsed, or `default` when it is unset. A value that
+/// doesn't parse is added to `problems` and the default used in its place,
+/// so reading goes on to find any others.
+fn parse_var<T>(name: &str, default: &str, problems: &mut Vec<String>) -> T
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let parsed = env::var(name).ok().and_then(|value| match value.parse() {
+        Ok(parsed) => Some(parsed),
+        Err(e) => {
+            problems.push(format!("Invalid {}: {}", name, e));
+            None
+        }
+    });
+    parsed.unwrap_or_else(|| match default.parse() {
+        Ok(default) => default,
+        Err(e) => panic!("Default {} of {} doesn't parse: {}", default, name, e),
+    })
+}
+
+/// Variable `name` parsed, `None` when it is unset or, added to `problems`,
+/// when it doesn't parse
+fn parse_opt_var<T>(name: &str, problems: &mut Vec<String>) -> Option<T>
+where
+    T: FromStr,
+    T::Err: Display,
+{
+    let value = env::var(name).ok()?;
+    match value.parse() {
+        Ok(parsed) => Some(parsed),
+        Err(e) => {
+            problems.push(format!("Invalid {}: {}", name, e));
+            None
+        }
+    }
+}
+
+/// The value of `result`, or `None` with its error added to `problems`
+fn recorded<T>(result: Result<T>, problems: &mut Vec<String>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            problems.push(format!("{:#}", e));
+            None
+        }
+    }
+}
+
+/// Symbol profiles from the JSON in `SYMBOL_PROFILES`, or in the file named
+/// by `SYMBOL_PROFILES_FILE`
+fn symbol_profiles(defaults: &SlicingConfig) -> Result<HashMap<String, SlicingConfig>> {
+    let json = match (env::var("SYMBOL_PROFILES"), env::var("SYMBOL_PROFILES_FILE")) {
+        (Ok(json), _) => json,
+        (Err(_), Ok(path)) => std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read SYMBOL_PROFILES_FILE {}", path))?,
+        (Err(_), Err(_)) => return Ok(HashMap::new()),
+    };
+    parse_symbol_profiles(&json, defaults).context("Invalid SYMBOL_PROFILES")
+}
+
+/// Profiles keyed by symbol or base asset, each over `defaults`, e.g.
+/// `{"BTC": {"slice_percent": 0.2}, "PEPE/USDT": {"slice_percent": 0.02}}`
+fn parse_symbol_profiles(json: &str, defaults: &SlicingConfig) -> Result<HashMap<String, SlicingConfig>> {
+    let profiles: HashMap<String, SlicingProfile> = serde_json::from_str(json)?;
+    Ok(profiles
+        .into_iter()
+        .map(|(key, profile)| (key.trim().to_uppercase(), profile.apply(defaults)))
+        .collect())
+}
+
+/// Check that `url` parses, with a host and one of `schemes`
+fn check_url(url: &str, schemes: &[&str]) -> Result<()> {
+    let parsed = reqwest::Url::parse(url)?;
+    if !schemes.contains(&parsed.scheme()) {
+        anyhow::bail!("expected a {} URL", schemes.join(" or "));
+    }
+    if parsed.host().is_none() {
+        anyhow::bail!("no host");
+    }
+    Ok(())
+}
+
+/// Symbol patterns from `var`, a comma-separated list, and from the file
+/// named by `<var>_FILE`, one or more per line
+fn symbol_list(var: &str) -> Result<Vec<String>> {
+    let mut patterns = env::var(var).map(|list| SymbolPolicy::parse_list(&list)).unwrap_or_default();
+    let file_var = format!("{}_FILE", var);
+    if let Ok(path) = env::var(&file_var) {
+        let list = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {} {}", file_var, path))?;
+        patterns.extend(SymbolPolicy::parse_list(&list));
+    }
+    Ok(patterns)
+}
+
+/// `exchange:symbol` pairs from a comma-separated list, e.g.
+/// `binance:BTCUSDT,okx:BTC-USDT-SWAP`
+fn parse_warmup_symbols(list: &str) -> Result<Vec<(String, String)>> {
+    list.split(',')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| match entry.split_once(':') {
+            Some((exchange, symbol)) if !exchange.is_empty() && !symbol.is_empty() => {
+                Ok((exchange.trim().to_lowercase(), symbol.trim().to_string()))
+            }
+            _ => anyhow::bail!("expected exchange:symbol, got {}", entry),
         })
+        .collect()
+}
+
+/// Check a REST base URL override, returning it without a trailing slash
+/// since adapters append paths that start with one
+fn parse_rest_url(url: &str) -> Result<String> {
+    let parsed = reqwest::Url::parse(url)?;
+    if !matches!(parsed.scheme(), "http" | "https") || parsed.host().is_none() {
+        anyhow::bail!("{} is not an http(s) URL", url);
+    }
+    Ok(url.trim_end_matches('/').to_string())
+}
+
+#[cfg(test)]
+impl Config {
+    /// Check values as `from_env` does, reporting every problem at once
+    pub fn validate(&self) -> Result<()> {
+        report(self.problems())
+    }
+
+    /// Config with no exchanges and local endpoints
+    pub fn for_tests() -> Self {
+        Config {
+            port: 9000,
+            redis_url: "redis://localhost:6379".to_string(),
+            database_url: "postgres://localhost/crossspread".to_string(),
+            encryption_key: vec![0u8; 32],
+            exchanges: Vec::new(),
+            slicing: SlicingConfig {
+                slice_percent: 0.5,
+                interval_ms: 0,
+                max_parallel: 1,
+                max_slice_notional_usd: Some(1_000_000.0),
+                price_rounding: crate::rounding::PriceRounding::Passive,
+                ..SlicingConfig::default()
+            },
+            symbol_profiles: HashMap::new(),
+            max_notional_usd: 1_000_000.0,
+            raise_risk_limits: false,
+            sequential_legs: false,
+            credential_source: CredentialSourceConfig::Database,
+            order_journal: None,
+            record_trade_history: false,
+            read_key_selection: KeySelection::Primary,
+            dead_letter_path: std::env::temp_dir()
+                .join(format!("execution-results-{}.jsonl", uuid::Uuid::new_v4()))
+                .display()
+                .to_string(),
+            symbol_cooldown_ms: 0,
+            max_clock_skew_ms: 1000,
+            max_open_orders_per_symbol: 200,
+            max_leg_residual_bps: 10.0,
+            reference_price_source: ReferencePriceSource::Mid,
+            cancel_on_disconnect_secs: 0,
+            cancel_orders_on_shutdown: true,
+            cleanup_on_start: false,
+            trade_timeout_secs: 0,
+            http_api_port: None,
+            http_api_bind: IpAddr::from([127, 0, 0, 1]),
+            http_api_token: None,
+            position_mark_interval_ms: 0,
+            maintenance_probe_secs: 30,
+            symbol_policy: SymbolPolicy::default(),
+            max_concurrent_trades: 4,
+            leg_entry_max_retries: 2,
+            warmup_symbols: Vec::new(),
+            warmup_api_key_id: None,
+        }
     }
 }
 
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD as base64;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::exchange_config;
+
+    #[test]
+    fn test_valid_config_passes() {
+        Config::for_tests().validate().unwrap();
+    }
+
+    #[test]
+    fn test_symbol_profiles_match_the_symbol_then_its_base_asset() {
+        use crate::exchange::mock::MockAdapter;
+        use rust_decimal_macros::dec;
+
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101));
+        let mut config = Config::for_tests();
+        config.symbol_profiles = parse_symbol_profiles(
+            r#"{
+                "btc": {"slice_percent": 0.2},
+                "BTC/USDC": {"slice_percent": 0.1, "interval_ms": 500},
+                "PEPEUSDT": {"slice_percent": 0.02}
+            }"#,
+            &config.slicing,
+        )
+        .unwrap();
+        config.validate().unwrap();
+
+        // An exact symbol beats its base asset, and takes the rest from the defaults
+        assert_eq!(config.slicing_for(&adapter, "BTCUSDT").slice_percent, 0.2);
+        let btc_usdc = config.slicing_for(&adapter, "BTC/USDC");
+        assert_eq!((btc_usdc.slice_percent, btc_usdc.interval_ms), (0.1, 500));
+        assert_eq!(btc_usdc.max_parallel, config.slicing.max_parallel);
+        assert_eq!(config.slicing_for(&adapter, "PEPE/USDT").slice_percent, 0.02);
+        // Unprofiled symbols keep the defaults
+        assert_eq!(config.slicing_for(&adapter, "ETHUSDT").slice_percent, 0.5);
+
+        let err = parse_symbol_profiles(r#"{"BTC": {"slice_pct": 0.2}}"#, &config.slicing).unwrap_err();
+        assert!(err.to_string().contains("unknown field `slice_pct`"), "{}", err);
+        config.symbol_profiles = parse_symbol_profiles(r#"{"BTC": {"slice_percent": 1.5}}"#, &config.slicing).unwrap();
+        let err = config.validate().unwrap_err();
+        assert!(err.to_string().contains("BTC profile slice_percent must be in (0, 1], got 1.5"), "{}", err);
+        config.symbol_profiles = parse_symbol_profiles(
+            r#"{"BTC": {"min_slice_percent": 0.3, "max_slice_percent": 0.2, "slice_timeout_secs": 0, "max_cross_bps": -5}}"#,
+            &config.slicing,
+        )
+        .unwrap();
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("BTC profile min_slice_percent 0.3 exceeds max_slice_percent 0.2"), "{}", err);
+        assert!(err.contains("BTC profile slice_timeout_secs must be at least 1"), "{}", err);
+        assert!(err.contains("BTC profile max_cross_bps must not be negative, got -5"), "{}", err);
+    }
+
+    fn slicing(slice_percent: f64, max_parallel: usize) -> Config {
+        let mut config = Config::for_tests();
+        config.slicing.slice_percent = slice_percent;
+        config.slicing.max_parallel = max_parallel;
+        config
+    }
+
+    #[test]
+    fn test_each_invalid_value_is_reported() {
+        let cases = [
+            (Config { port: 0, ..Config::for_tests() }, "EXEC_SERVICE_PORT must be between 1 and 65535"),
+            (
+                Config {
+                    http_api_port: Some(9000),
+                    http_api_token: Some("token".to_string()),
+                    ..Config::for_tests()
+                },
+                "HTTP_API_PORT must differ from EXEC_SERVICE_PORT",
+            ),
+            (
+                Config { http_api_port: Some(9001), ..Config::for_tests() },
+                "HTTP_API_TOKEN must be set to serve the HTTP API",
+            ),
+            (
+                Config { encryption_key: vec![0u8; 16], ..Config::for_tests() },
+                "ENCRYPTION_KEY_BASE64 must decode to 32 bytes, got 16",
+            ),
+            (
+                Config { redis_url: "redis://localhost:6379x".to_string(), ..Config::for_tests() },
+                "Invalid Redis URL: invalid port number",
+            ),
+            (
+                Config { redis_url: "http://localhost:6379".to_string(), ..Config::for_tests() },
+                "Invalid Redis URL: expected a redis or rediss URL",
+            ),
+            (
+                Config { database_url: "localhost/crossspread".to_string(), ..Config::for_tests() },
+                "Invalid database URL: relative URL without a base",
+            ),
+            (slicing(0.0, 1), "SLICE_PERCENT must be in (0, 1], got 0"),
+            (slicing(1.5, 1), "SLICE_PERCENT must be in (0, 1], got 1.5"),
+            (slicing(0.5, 0), "MAX_PARALLEL_SLICES must be at least 1"),
+            (
+                Config {
+                    slicing: SlicingConfig { size_jitter_percent: 1.0, ..SlicingConfig::default() },
+                    ..Config::for_tests()
+                },
+                "SLICE_SIZE_JITTER_PERCENT must be in [0, 1), got 1",
+            ),
+            (
+                Config {
+                    slicing: SlicingConfig { interval_jitter_percent: -0.1, ..SlicingConfig::default() },
+                    ..Config::for_tests()
+                },
+                "SLICE_INTERVAL_JITTER_PERCENT must be in [0, 1), got -0.1",
+            ),
+            (
+                Config {
+                    cancel_on_disconnect_secs: 5,
+                    exchanges: vec![exchange_config("okx", String::new()), exchange_config("bybit", String::new())],
+                    ..Config::for_tests()
+                },
+                "CANCEL_ON_DISCONNECT_SECS must be between 10 and 120 for okx, got 5",
+            ),
+            (
+                Config {
+                    cancel_on_disconnect_secs: 600,
+                    exchanges: vec![exchange_config("bybit", String::new())],
+                    ..Config::for_tests()
+                },
+                "CANCEL_ON_DISCONNECT_SECS must be between 3 and 300 for bybit, got 600",
+            ),
+        ];
+        for (config, problem) in cases {
+            let err = config.validate().unwrap_err().to_string();
+            assert_eq!(err, format!("Invalid configuration:\n  {}", problem));
+        }
+    }
+
+    #[test]
+    fn test_every_bad_variable_is_reported_at_once() {
+        // No other test reads these
+        env::set_var("EXEC_SERVICE_PORT", "90000");
+        env::set_var("MAX_QUOTE_AGE_MS", "soon");
+        env::set_var("SLICE_PERCENT", "2");
+        env::remove_var("ENCRYPTION_KEY_BASE64");
+        let err = Config::from_env().unwrap_err().to_string();
+        for var in ["EXEC_SERVICE_PORT", "MAX_QUOTE_AGE_MS", "SLICE_PERCENT"] {
+            env::remove_var(var);
+        }
+
+        // Parse failures come first, then what validation finds
+        let problems: Vec<_> = err.lines().skip(1).map(str::trim).collect();
+        assert_eq!(
+            problems,
+            [
+                "Invalid EXEC_SERVICE_PORT: number too large to fit in target type",
+                "ENCRYPTION_KEY_BASE64 must be set: environment variable not found",
+                "Invalid MAX_QUOTE_AGE_MS: invalid digit found in string",
+                "SLICE_PERCENT must be in (0, 1], got 2",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_problems_are_aggregated() {
+        let config = Config {
+            port: 0,
+            encryption_key: Vec::new(),
+            ..slicing(f64::NAN, 1)
+        };
+        let err = config.validate().unwrap_err().to_string();
+        assert_eq!(err.lines().count(), 4, "{}", err);
+        assert!(err.contains("EXEC_SERVICE_PORT"));
+        assert!(err.contains("got 0"));
+        assert!(err.contains("got NaN"));
+    }
+
+    #[test]
+    fn test_rest_url_override_is_validated() {
+        assert_eq!(parse_rest_url("https://fapi1.binance.com/").unwrap(), "https://fapi1.binance.com");
+        assert_eq!(parse_rest_url("http://127.0.0.1:8080").unwrap(), "http://127.0.0.1:8080");
+        assert!(parse_rest_url("fapi.binance.com").is_err());
+        assert!(parse_rest_url("wss://fstream.binance.com").is_err());
+    }
+
+    #[test]
+    fn test_warmup_symbols_are_parsed_by_exchange() {
+        assert_eq!(
+            parse_warmup_symbols("Binance:BTCUSDT, okx:BTC-USDT-SWAP,").unwrap(),
+            [
+                ("binance".to_string(), "BTCUSDT".to_string()),
+                ("okx".to_string(), "BTC-USDT-SWAP".to_string()),
+            ]
+        );
+        let err = parse_warmup_symbols("binance:BTCUSDT,ETHUSDT").unwrap_err();
+        assert_eq!(err.to_string(), "expected exchange:symbol, got ETHUSDT");
+    }
+}
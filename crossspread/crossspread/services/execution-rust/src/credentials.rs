@@ -0,0 +1,316 @@
+//! Credential sources
+//!
+//! Exchange API keys are loaded by `api_key_id` from whichever store the
+//! deployment uses: the backend's Postgres table, an encrypted local file or
+//! HashiCorp Vault. Files and Vault can also name the sub-account a key
+//! trades for, see `Credentials::sub_account`.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::Deserialize;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+use crate::config::{Config, CredentialSourceConfig};
+use crate::crypto::{decrypt, decrypt_credentials};
+use crate::exchange::Credentials;
+use crate::key_pool::KeyPool;
+
+/// Associated data for the credentials file, which is encrypted as a whole
+const CREDENTIALS_FILE_AAD: &[u8] = b"crossspread:credentials-file";
+
+/// Store of exchange API credentials
+#[async_trait]
+pub trait CredentialSource: Send + Sync {
+    /// Decrypted credentials for an API key
+    async fn load(&self, api_key_id: Uuid) -> Result<Credentials>;
+
+    /// The API key together with any additional keys registered on the same
+    /// account for reads and cancels
+    async fn load_pool(&self, api_key_id: Uuid) -> Result<KeyPool> {
+        Ok(KeyPool::single(self.load(api_key_id).await?))
+    }
+}
+
+/// Create the credential source selected in the config
+pub fn create_credential_source(config: &Config) -> Result<Box<dyn CredentialSource>> {
+    match &config.credential_source {
+        CredentialSourceConfig::Database => Ok(Box::new(DbCredentialSource::new(
+            &config.database_url,
+            config.encryption_key.clone(),
+        )?)),
+        CredentialSourceConfig::File { path } => Ok(Box::new(FileCredentialSource::new(
+            path,
+            config.encryption_key.clone(),
+        ))),
+        CredentialSourceConfig::Vault { addr, token, mount, path } => {
+            Ok(Box::new(VaultCredentialSource::new(addr, token, mount, path)?))
+        }
+    }
+}
+
+/// Credentials as stored in files and Vault
+#[derive(Debug, Deserialize)]
+struct StoredCredentials {
+    api_key: String,
+    api_secret: String,
+    #[serde(default)]
+    passphrase: Option<String>,
+    #[serde(default)]
+    wallet_key: Option<String>,
+    #[serde(default)]
+    sub_account: Option<String>,
+    /// Further keys on the same account, used for reads and cancels
+    #[serde(default)]
+    read_keys: Vec<StoredCredentials>,
+}
+
+impl From<StoredCredentials> for KeyPool {
+    fn from(mut stored: StoredCredentials) -> Self {
+        let read_keys = std::mem::take(&mut stored.read_keys);
+        let primary: Credentials = stored.into();
+        // Read keys are on the same account, so route them alike
+        let read_keys = read_keys
+            .into_iter()
+            .map(|key| {
+                let mut key = Credentials::from(key);
+                key.sub_account = key.sub_account.or_else(|| primary.sub_account.clone());
+                key
+            })
+            .collect();
+        KeyPool { primary, read_keys }
+    }
+}
+
+impl From<StoredCredentials> for Credentials {
+    fn from(stored: StoredCredentials) -> Self {
+        Credentials {
+            api_key: stored.api_key,
+            api_secret: stored.api_secret,
+            passphrase: stored.passphrase,
+            wallet_key: stored.wallet_key,
+            sub_account: stored.sub_account,
+        }
+    }
+}
+
+/// Encrypted columns of the backend's `api_keys` table
+pub struct DbCredentialSource {
+    pool: PgPool,
+    encryption_key: Vec<u8>,
+}
+
+impl DbCredentialSource {
+    pub fn new(database_url: &str, encryption_key: Vec<u8>) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect_lazy(database_url)
+            .context("Invalid database URL")?;
+        Ok(Self { pool, encryption_key })
+    }
+}
+
+#[async_trait]
+impl CredentialSource for DbCredentialSource {
+    async fn load(&self, api_key_id: Uuid) -> Result<Credentials> {
+        let row: Option<(Vec<u8>, Vec<u8>, Option<Vec<u8>>)> = sqlx::query_as(
+            "SELECT api_key_encrypted, api_secret_encrypted, passphrase_encrypted \
+             FROM api_keys WHERE id = $1",
+        )
+        .bind(api_key_id)
+        .fetch_optional(&self.pool)
+        .await
+        .context("Failed to query api_keys")?;
+
+        let (api_key, api_secret, passphrase) =
+            row.ok_or_else(|| anyhow::anyhow!("API key {} not found", api_key_id))?;
+        let (api_key, api_secret, passphrase) = decrypt_credentials(
+            &self.encryption_key,
+            api_key_id.as_bytes(),
+            &api_key,
+            &api_secret,
+            passphrase.as_deref(),
+        )?;
+
+        Ok(Credentials {
+            api_key,
+            api_secret,
+            passphrase,
+            wallet_key: None,
+            sub_account: None,
+        })
+    }
+}
+
+/// Local file holding a JSON object of credentials keyed by `api_key_id`,
+/// encrypted as a whole with the service encryption key. The file is re-read
+/// on every load so keys can be rotated without a restart.
+pub struct FileCredentialSource {
+    path: PathBuf,
+    encryption_key: Vec<u8>,
+}
+
+impl FileCredentialSource {
+    pub fn new(path: impl Into<PathBuf>, encryption_key: Vec<u8>) -> Self {
+        Self {
+            path: path.into(),
+            encryption_key,
+        }
+    }
+}
+
+impl FileCredentialSource {
+    async fn stored(&self, api_key_id: Uuid) -> Result<StoredCredentials> {
+        let encrypted = tokio::fs::read(&self.path)
+            .await
+            .with_context(|| format!("Failed to read {}", self.path.display()))?;
+        let plaintext = decrypt(&self.encryption_key, &encrypted, CREDENTIALS_FILE_AAD)
+            .with_context(|| format!("Failed to decrypt {}", self.path.display()))?;
+
+        let mut entries: HashMap<Uuid, StoredCredentials> = serde_json::from_slice(&plaintext)
+            .with_context(|| format!("Invalid credentials file {}", self.path.display()))?;
+        entries
+            .remove(&api_key_id)
+            .ok_or_else(|| anyhow::anyhow!("API key {} not found in credentials file", api_key_id))
+    }
+}
+
+#[async_trait]
+impl CredentialSource for FileCredentialSource {
+    async fn load(&self, api_key_id: Uuid) -> Result<Credentials> {
+        Ok(self.stored(api_key_id).await?.into())
+    }
+
+    async fn load_pool(&self, api_key_id: Uuid) -> Result<KeyPool> {
+        Ok(self.stored(api_key_id).await?.into())
+    }
+}
+
+/// HashiCorp Vault KV v2 secrets at `<mount>/data/<path>/<api_key_id>`
+pub struct VaultCredentialSource {
+    client: Client,
+    addr: String,
+    token: String,
+    mount: String,
+    path: String,
+}
+
+impl VaultCredentialSource {
+    pub fn new(addr: &str, token: &str, mount: &str, path: &str) -> Result<Self> {
+        let client = Client::builder()
+            .timeout(std::time::Duration::from_secs(10))
+            .build()?;
+
+        Ok(Self {
+            client,
+            addr: addr.trim_end_matches('/').to_string(),
+            token: token.to_string(),
+            mount: mount.trim_matches('/').to_string(),
+            path: path.trim_matches('/').to_string(),
+        })
+    }
+
+    fn secret_url(&self, api_key_id: Uuid) -> String {
+        format!("{}/v1/{}/data/{}/{}", self.addr, self.mount, self.path, api_key_id)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct VaultSecret {
+    data: VaultSecretData,
+}
+
+#[derive(Debug, Deserialize)]
+struct VaultSecretData {
+    data: StoredCredentials,
+}
+
+impl VaultCredentialSource {
+    async fn stored(&self, api_key_id: Uuid) -> Result<StoredCredentials> {
+        let response = self.client
+            .get(self.secret_url(api_key_id))
+            .header("X-Vault-Token", &self.token)
+            .send()
+            .await
+            .context("Failed to reach Vault")?;
+
+        let status = response.status();
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            anyhow::bail!("Vault read for API key {} failed: {}", api_key_id, status);
+        }
+
+        let secret: VaultSecret = serde_json::from_str(&body)
+            .with_context(|| format!("Invalid Vault secret for API key {}", api_key_id))?;
+        Ok(secret.data.data)
+    }
+}
+
+#[async_trait]
+impl CredentialSource for VaultCredentialSource {
+    async fn load(&self, api_key_id: Uuid) -> Result<Credentials> {
+        Ok(self.stored(api_key_id).await?.into())
+    }
+
+    async fn load_pool(&self, api_key_id: Uuid) -> Result<KeyPool> {
+        Ok(self.stored(api_key_id).await?.into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crypto::encrypt;
+
+    #[tokio::test]
+    async fn test_file_source_loads_by_api_key_id() {
+        let key = vec![7u8; 32];
+        let api_key_id = Uuid::new_v4();
+        let contents = serde_json::json!({
+            api_key_id.to_string(): {
+                "api_key": "key",
+                "api_secret": "secret",
+                "passphrase": "phrase",
+                "sub_account": "3",
+                "read_keys": [{ "api_key": "read", "api_secret": "read-secret" }],
+            }
+        });
+        let path = std::env::temp_dir().join(format!("credentials-{}.enc", Uuid::new_v4()));
+        let encrypted = encrypt(&key, contents.to_string().as_bytes(), CREDENTIALS_FILE_AAD).unwrap();
+        std::fs::write(&path, encrypted).unwrap();
+
+        let source = FileCredentialSource::new(&path, key);
+        let credentials = source.load(api_key_id).await.unwrap();
+        let pool = source.load_pool(api_key_id).await.unwrap();
+        let missing = source.load(Uuid::new_v4()).await;
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(credentials.api_key, "key");
+        assert_eq!(credentials.api_secret, "secret");
+        assert_eq!(credentials.passphrase.as_deref(), Some("phrase"));
+        assert_eq!(credentials.wallet_key, None);
+        assert_eq!(credentials.sub_account.as_deref(), Some("3"));
+        assert_eq!(pool.primary.api_key, "key");
+        assert_eq!(pool.read_keys.len(), 1);
+        assert_eq!(pool.read_keys[0].api_key, "read");
+        assert_eq!(pool.read_keys[0].sub_account.as_deref(), Some("3"));
+        assert!(missing.unwrap_err().to_string().contains("not found"));
+    }
+
+    #[test]
+    fn test_vault_secret_url() {
+        let source =
+            VaultCredentialSource::new("https://vault:8200/", "token", "secret", "/crossspread/api-keys/")
+                .unwrap();
+        let id = Uuid::nil();
+
+        assert_eq!(
+            source.secret_url(id),
+            "https://vault:8200/v1/secret/data/crossspread/api-keys/00000000-0000-0000-0000-000000000000"
+        );
+    }
+}
@@ -1,7 +1,7 @@
 //! Cryptographic utilities for API key encryption/decryption
 
 use aes_gcm::{
-    aead::{Aead, KeyInit},
+    aead::{Aead, KeyInit, Payload},
     Aes256Gcm, Nonce,
 };
 use anyhow::{Context, Result};
@@ -9,8 +9,12 @@ use rand::Rng;
 
 const NONCE_SIZE: usize = 12;
 
-/// Encrypt plaintext using AES-256-GCM
-pub fn encrypt(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
+/// Encrypt plaintext using AES-256-GCM.
+///
+/// `aad` binds the ciphertext to its context (e.g. the owning row's id): it is
+/// authenticated but not stored, and decryption fails unless the same value is
+/// supplied again.
+pub fn encrypt(key: &[u8], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
     if key.len() != 32 {
         anyhow::bail!("Encryption key must be 32 bytes");
     }
@@ -25,7 +29,7 @@ pub fn encrypt(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
 
     // Encrypt
     let ciphertext = cipher
-        .encrypt(nonce, plaintext)
+        .encrypt(nonce, Payload { msg: plaintext, aad })
         .map_err(|e| anyhow::anyhow!("Encryption failed: {}", e))?;
 
     // Prepend nonce to ciphertext
@@ -36,8 +40,8 @@ pub fn encrypt(key: &[u8], plaintext: &[u8]) -> Result<Vec<u8>> {
     Ok(result)
 }
 
-/// Decrypt ciphertext using AES-256-GCM
-pub fn decrypt(key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
+/// Decrypt ciphertext using AES-256-GCM, with the `aad` it was encrypted under
+pub fn decrypt(key: &[u8], ciphertext: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
     if key.len() != 32 {
         anyhow::bail!("Encryption key must be 32 bytes");
     }
@@ -55,27 +59,29 @@ pub fn decrypt(key: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>> {
 
     // Decrypt
     let plaintext = cipher
-        .decrypt(nonce, encrypted)
+        .decrypt(nonce, Payload { msg: encrypted, aad })
         .map_err(|e| anyhow::anyhow!("Decryption failed: {}", e))?;
 
     Ok(plaintext)
 }
 
-/// Decrypt API credentials from database
+/// Decrypt API credentials from database. Each field is bound to the row by
+/// using its `api_key_id` as associated data.
 pub fn decrypt_credentials(
     key: &[u8],
+    aad: &[u8],
     api_key_encrypted: &[u8],
     api_secret_encrypted: &[u8],
     passphrase_encrypted: Option<&[u8]>,
 ) -> Result<(String, String, Option<String>)> {
-    let api_key = String::from_utf8(decrypt(key, api_key_encrypted)?)
+    let api_key = String::from_utf8(decrypt(key, api_key_encrypted, aad)?)
         .context("API key is not valid UTF-8")?;
     
-    let api_secret = String::from_utf8(decrypt(key, api_secret_encrypted)?)
+    let api_secret = String::from_utf8(decrypt(key, api_secret_encrypted, aad)?)
         .context("API secret is not valid UTF-8")?;
     
     let passphrase = if let Some(encrypted) = passphrase_encrypted {
-        Some(String::from_utf8(decrypt(key, encrypted)?)
+        Some(String::from_utf8(decrypt(key, encrypted, aad)?)
             .context("Passphrase is not valid UTF-8")?)
     } else {
         None
@@ -93,9 +99,23 @@ mod tests {
         let key = [0u8; 32]; // Test key
         let plaintext = b"my_secret_api_key";
 
-        let encrypted = encrypt(&key, plaintext).unwrap();
-        let decrypted = decrypt(&key, &encrypted).unwrap();
+        let encrypted = encrypt(&key, plaintext, b"").unwrap();
+        let decrypted = decrypt(&key, &encrypted, b"").unwrap();
 
         assert_eq!(plaintext.to_vec(), decrypted);
     }
+
+    #[test]
+    fn test_decrypt_fails_with_other_aad() {
+        let key = [0u8; 32];
+        let row = uuid::Uuid::new_v4();
+        let other_row = uuid::Uuid::new_v4();
+
+        let encrypted = encrypt(&key, b"api_secret", row.as_bytes()).unwrap();
+
+        assert_eq!(decrypt(&key, &encrypted, row.as_bytes()).unwrap(), b"api_secret");
+        // Secret pasted into another user's record
+        assert!(decrypt(&key, &encrypted, other_row.as_bytes()).is_err());
+        assert!(decrypt(&key, &encrypted, b"").is_err());
+    }
 }
@@ -0,0 +1,121 @@
+//! Dead-letter file
+//!
+//! Results that could not be published are appended here as JSON lines and
+//! replayed on the next startup, so the backend eventually learns the outcome
+//! of every trade even across a Redis outage.
+
+use anyhow::{Context, Result};
+use std::path::PathBuf;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+pub struct DeadLetterFile {
+    path: PathBuf,
+    /// Held by appends and rewrites alike, so neither loses the other's entries
+    lock: Mutex<()>,
+}
+
+impl DeadLetterFile {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Append one entry and flush it to disk
+    pub async fn append(&self, entry: &str) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await
+            .with_context(|| format!("Failed to open dead-letter file {}", self.path.display()))?;
+
+        file.write_all(format!("{}\n", entry).as_bytes()).await?;
+        file.sync_data().await?;
+        Ok(())
+    }
+
+    /// Every entry, oldest first. They stay in the file until `remove`d.
+    pub async fn entries(&self) -> Result<Vec<String>> {
+        let _guard = self.lock.lock().await;
+        self.read().await
+    }
+
+    /// Drop one entry, once it has been republished. The rest are written
+    /// to a new file that replaces this one, so a crash leaves either the
+    /// old contents or the new; the file is deleted once empty.
+    pub async fn remove(&self, entry: &str) -> Result<()> {
+        let _guard = self.lock.lock().await;
+        let mut entries = self.read().await?;
+        let Some(index) = entries.iter().position(|e| e == entry) else {
+            return Ok(());
+        };
+        entries.remove(index);
+
+        if entries.is_empty() {
+            return tokio::fs::remove_file(&self.path)
+                .await
+                .with_context(|| format!("Failed to remove dead-letter file {}", self.path.display()));
+        }
+        let mut staging = self.path.clone().into_os_string();
+        staging.push(".tmp");
+        let mut file = tokio::fs::File::create(&staging).await?;
+        for entry in &entries {
+            file.write_all(format!("{}\n", entry).as_bytes()).await?;
+        }
+        file.sync_data().await?;
+        tokio::fs::rename(&staging, &self.path)
+            .await
+            .with_context(|| format!("Failed to rewrite dead-letter file {}", self.path.display()))
+    }
+
+    async fn read(&self) -> Result<Vec<String>> {
+        let contents = match tokio::fs::read_to_string(&self.path).await {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => {
+                return Err(e)
+                    .with_context(|| format!("Failed to read dead-letter file {}", self.path.display()))
+            }
+        };
+
+        Ok(contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(str::to_string)
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_entries_stay_until_removed() {
+        let path = std::env::temp_dir().join(format!("dead-letter-{}.jsonl", Uuid::new_v4()));
+        let file = DeadLetterFile::new(&path);
+
+        assert!(file.entries().await.unwrap().is_empty());
+
+        file.append(r#"{"trade_id":1}"#).await.unwrap();
+        file.append(r#"{"trade_id":2}"#).await.unwrap();
+        assert_eq!(file.entries().await.unwrap(), [r#"{"trade_id":1}"#, r#"{"trade_id":2}"#]);
+        // Reading leaves them in place, as none has been republished yet
+        assert_eq!(file.entries().await.unwrap().len(), 2);
+
+        file.remove(r#"{"trade_id":2}"#).await.unwrap();
+        assert_eq!(file.entries().await.unwrap(), [r#"{"trade_id":1}"#]);
+        file.remove(r#"{"trade_id":1}"#).await.unwrap();
+        assert!(!path.exists());
+        assert!(file.entries().await.unwrap().is_empty());
+    }
+}
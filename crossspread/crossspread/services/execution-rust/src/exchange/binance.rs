@@ -7,94 +7,239 @@ use reqwest::Client;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::{Arc, OnceLock, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
-use tracing::{debug, info};
+use tokio::sync::watch;
+use tracing::{debug, info, warn};
 
-use super::{Credentials, ExchangeAdapter, OrderRequest, OrderResponse, OrderStatus, OrderType, Side};
+use super::{
+    check_unavailable, epoch_millis, mid_price, parse_json, parse_levels, position_side,
+    now_millis, post_only_rejected, reduce_only_rejected, signature_expired, Credentials,
+    ExchangeAdapter, LeverageInfo, MarginMode, OrderBook, OrderRequest, OrderResponse, OrderStatus,
+    OrderType, Position, QuantityMode, QuoteAssets, ReferencePriceSource, RiskLimit, Side, StopOrderRequest, SymbolInfo, SymbolStatus,
+    TimeInForce, Trail, TrailingStopRequest,
+};
+use super::raw_http::SendTraced;
+use super::trading_socket::TradingSocket;
 use crate::config::ExchangeConfig;
+use crate::feed::{Feed, FeedEvent, FeedHandle, FeedProtocol, FeedUpdate};
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// Cancel rejected because the order already filled, was cancelled or is unknown
+const UNKNOWN_ORDER: i64 = -2011;
+
+/// Reduce-only order rejected, as there is no position left for it to reduce
+const REDUCE_ONLY_REJECTED: i64 = -2022;
+
+/// Post-only (GTX) order rejected, as it would have executed immediately
+const POST_ONLY_REJECTED: i64 = -5022;
+
+/// Request refused as its timestamp is outside the `recvWindow`
+const TIMESTAMP_OUTSIDE_RECV_WINDOW: i64 = -1021;
+
+/// Placement rejected because another order already has the client order id
+const DUPLICATE_CLIENT_ORDER_ID: i64 = -4116;
+
+/// Soonest a GTD order may expire, as `goodTillDate` must be more than 600 s out
+const GTD_MIN_LEAD_MS: i64 = 600_000;
+
 pub struct BinanceAdapter {
     config: ExchangeConfig,
     client: Client,
+    /// Hedge mode per API key, as last read or set
+    hedge_mode: RwLock<HashMap<String, bool>>,
+    /// WebSocket API connection per API key
+    trading_sockets: RwLock<HashMap<String, Arc<TradingSocket>>>,
+    /// Best prices streamed from `ws_url`, started on the first price read
+    book_tickers: OnceLock<BookTickerFeed>,
+    /// Order updates per API key, started on the key's first placement
+    user_streams: RwLock<HashMap<String, Arc<UserDataStream>>>,
+    quote_assets: QuoteAssets,
 }
 
 impl BinanceAdapter {
     pub async fn new(config: ExchangeConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .build()?;
+        let client = super::http_client(&config)?;
 
-        Ok(Self { config, client })
+        Ok(Self {
+            config,
+            client,
+            hedge_mode: RwLock::new(HashMap::new()),
+            trading_sockets: RwLock::new(HashMap::new()),
+            book_tickers: OnceLock::new(),
+            user_streams: RwLock::new(HashMap::new()),
+            quote_assets: QuoteAssets::default(),
+        })
     }
 
-    fn sign(&self, secret: &str, query: &str) -> String {
-        let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
-            .expect("HMAC can take key of any size");
-        mac.update(query.as_bytes());
-        hex::encode(mac.finalize().into_bytes())
+    /// Book ticker stream, unless no market data WebSocket is configured
+    fn book_tickers(&self) -> Option<&BookTickerFeed> {
+        if self.config.ws_url.is_empty() {
+            return None;
+        }
+        Some(self.book_tickers.get_or_init(|| {
+            BookTickerFeed::spawn(BookTickerProtocol {
+                ws_url: self.config.ws_url.clone(),
+                rest_url: self.config.rest_url.clone(),
+                client: self.client.clone(),
+                log_raw_http: self.config.log_raw_http,
+            })
+        }))
     }
 
-    fn timestamp() -> u64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
+    /// User-data stream of `credentials`' key, started if it isn't running
+    /// yet, unless no market data WebSocket is configured
+    fn start_user_stream(&self, credentials: &Credentials) -> Option<Arc<UserDataStream>> {
+        if self.config.ws_url.is_empty() {
+            return None;
+        }
+        if let Some(stream) = self.user_stream(credentials) {
+            return Some(stream);
+        }
+        let stream = self
+            .user_streams
+            .write()
             .unwrap()
-            .as_millis() as u64
+            .entry(credentials.api_key.clone())
+            .or_insert_with(|| {
+                Arc::new(UserDataStream::spawn(
+                    self.config.ws_url.clone(),
+                    self.listen_key_manager(credentials),
+                ))
+            })
+            .clone();
+        Some(stream)
     }
-}
 
-#[async_trait]
-impl ExchangeAdapter for BinanceAdapter {
-    fn id(&self) -> &str {
-        "binance"
+    /// User-data stream of `credentials`' key, if one was started
+    fn user_stream(&self, credentials: &Credentials) -> Option<Arc<UserDataStream>> {
+        self.user_streams.read().unwrap().get(&credentials.api_key).cloned()
     }
 
-    async fn place_order(
+    /// WebSocket API connection of `credentials`' key, when one is configured
+    fn trading_socket(&self, credentials: &Credentials) -> Option<Arc<TradingSocket>> {
+        let url = self.config.trade_ws_url.as_ref()?;
+        if let Some(socket) = self.trading_sockets.read().unwrap().get(&credentials.api_key) {
+            return Some(socket.clone());
+        }
+        let socket = self
+            .trading_sockets
+            .write()
+            .unwrap()
+            .entry(credentials.api_key.clone())
+            .or_insert_with(|| Arc::new(TradingSocket::new(url, "id")))
+            .clone();
+        Some(socket)
+    }
+
+    /// The order on native `symbol` whose `id_param` (`orderId` or
+    /// `origClientOrderId`) is `id`
+    async fn query_order(
         &self,
         credentials: &Credentials,
-        request: &OrderRequest,
+        symbol: &str,
+        id_param: &str,
+        id: &str,
     ) -> Result<OrderResponse> {
-        let timestamp = Self::timestamp();
-        
+        let query = format!(
+            "symbol={}&{}={}&timestamp={}",
+            symbol, id_param, id, Self::timestamp()
+        );
+        let signature = sign(&credentials.api_secret, &query);
+        let full_query = format!("{}&signature={}", query, signature);
+
+        let url = format!("{}/fapi/v1/order?{}", self.config.rest_url, full_query);
+
+        let response = self.client
+            .get(&url)
+            .header("X-MBX-APIKEY", &credentials.api_key)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let body = response.text().await?;
+        let order: BinanceOrderResponse = parse_json(&body)?;
+
+        Ok(order_response(order))
+    }
+
+    /// Order params common to REST and the WebSocket API, before the
+    /// timestamp and signature. Quote amounts go out as `quoteOrderQty`,
+    /// which Binance only takes on market orders.
+    fn order_params(&self, credentials: &Credentials, request: &OrderRequest) -> Result<Vec<(&'static str, String)>> {
+        let quantity = match (request.quantity_mode, request.order_type) {
+            (QuantityMode::Base, _) => ("quantity", request.quantity.to_string()),
+            (QuantityMode::Quote, OrderType::Market) => ("quoteOrderQty", request.quantity.to_string()),
+            (QuantityMode::Quote, OrderType::Limit) => {
+                anyhow::bail!("Binance limit orders can't be sized in the quote asset")
+            }
+        };
         let mut params = vec![
-            format!("symbol={}", request.symbol),
-            format!("side={}", match request.side {
+            ("symbol", self.native_symbol(&request.symbol)),
+            ("side", match request.side {
                 Side::Buy => "BUY",
                 Side::Sell => "SELL",
-            }),
-            format!("type={}", match request.order_type {
+            }.to_string()),
+            ("type", match request.order_type {
                 OrderType::Limit => "LIMIT",
                 OrderType::Market => "MARKET",
-            }),
-            format!("quantity={}", request.quantity),
-            format!("newClientOrderId={}", request.client_order_id),
-            format!("timestamp={}", timestamp),
+            }.to_string()),
+            quantity,
+            ("newClientOrderId", request.client_order_id.clone()),
         ];
 
         if request.order_type == OrderType::Limit {
             if let Some(price) = &request.price {
-                params.push(format!("price={}", price));
-                params.push("timeInForce=GTC".to_string());
+                params.push(("price", price.to_string()));
+                params.push(("timeInForce", match request.time_in_force {
+                    TimeInForce::Gtc => "GTC",
+                    TimeInForce::Ioc => "IOC",
+                    TimeInForce::PostOnly => "GTX",
+                    TimeInForce::Gtd { .. } => "GTD",
+                }.to_string()));
+                if let TimeInForce::Gtd { expire_at_ms } = request.time_in_force {
+                    params.push(("goodTillDate", expire_at_ms.to_string()));
+                }
             }
         }
 
-        if request.reduce_only {
-            params.push("reduceOnly=true".to_string());
+        // In hedge mode the position side scopes the order and reduceOnly is rejected
+        if self.is_hedge_mode(&credentials.api_key) {
+            params.push(("positionSide", match position_side(request.side, request.reduce_only) {
+                Side::Buy => "LONG",
+                Side::Sell => "SHORT",
+            }.to_string()));
+        } else if request.reduce_only {
+            params.push(("reduceOnly", "true".to_string()));
         }
+        Ok(params)
+    }
+
+    /// Place an order over REST
+    async fn post_order(&self, credentials: &Credentials, request: &OrderRequest) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(&request.symbol);
+        let timestamp = Self::timestamp();
+
+        let mut params: Vec<String> = self
+            .order_params(credentials, request)?
+            .into_iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        params.push(format!("timestamp={}", timestamp));
 
         let query = params.join("&");
-        let signature = self.sign(&credentials.api_secret, &query);
+        let signature = sign(&credentials.api_secret, &query);
         let full_query = format!("{}&signature={}", query, signature);
 
         let url = format!("{}/fapi/v1/order?{}", self.config.rest_url, full_query);
         
-        debug!("Placing Binance order: {}", request.symbol);
+        debug!("Placing Binance order: {}", symbol);
 
         let response = self.client
             .post(&url)
             .header("X-MBX-APIKEY", &credentials.api_key)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await
             .context("Failed to send order request")?;
 
@@ -102,10 +247,28 @@ impl ExchangeAdapter for BinanceAdapter {
         let body = response.text().await?;
 
         if !status.is_success() {
+            check_unavailable(self.id(), status, &body)?;
+            let code = parse_json::<BinanceError>(&body).map(|e| e.code).ok();
+            if code == Some(REDUCE_ONLY_REJECTED) {
+                return Err(reduce_only_rejected(self.id(), &body));
+            }
+            if code == Some(POST_ONLY_REJECTED) {
+                return Err(post_only_rejected(self.id(), &body));
+            }
+            if code == Some(TIMESTAMP_OUTSIDE_RECV_WINDOW) {
+                return Err(signature_expired(self.id(), &body));
+            }
+            if self.config.adopt_duplicate_orders && code == Some(DUPLICATE_CLIENT_ORDER_ID) {
+                // An earlier attempt of this placement got through
+                info!("Binance order {} already exists, adopting it", request.client_order_id);
+                return self
+                    .query_order(credentials, &symbol, "origClientOrderId", &request.client_order_id)
+                    .await;
+            }
             anyhow::bail!("Binance order failed: {} - {}", status, body);
         }
 
-        let order: BinanceOrderResponse = serde_json::from_str(&body)
+        let order: BinanceOrderResponse = parse_json(&body)
             .context("Failed to parse order response")?;
 
         info!("Binance order placed: {} status={}", order.order_id, order.status);
@@ -127,23 +290,250 @@ impl ExchangeAdapter for BinanceAdapter {
             filled_quantity: order.executed_qty.parse().unwrap_or_default(),
             avg_fill_price: order.avg_price.parse().ok(),
             status: parse_binance_status(&order.status),
-            timestamp: order.update_time,
+            timestamp: epoch_millis(order.update_time),
         })
     }
 
+    fn is_hedge_mode(&self, api_key: &str) -> bool {
+        self.hedge_mode.read().unwrap().get(api_key).copied().unwrap_or(false)
+    }
+
+    /// Place a conditional order that only closes: `params` are its own
+    /// fields, and the side, position side or reduceOnly flag are added here
+    async fn post_close_order(
+        &self,
+        credentials: &Credentials,
+        side: Side,
+        mut params: Vec<String>,
+        what: &str,
+    ) -> Result<BinanceOrderResponse> {
+        params.push(format!("side={}", match side {
+            Side::Buy => "BUY",
+            Side::Sell => "SELL",
+        }));
+        if self.is_hedge_mode(&credentials.api_key) {
+            params.push(format!("positionSide={}", match position_side(side, true) {
+                Side::Buy => "LONG",
+                Side::Sell => "SHORT",
+            }));
+        } else {
+            params.push("reduceOnly=true".to_string());
+        }
+        params.push(format!("timestamp={}", Self::timestamp()));
+
+        let query = params.join("&");
+        let signature = sign(&credentials.api_secret, &query);
+        let url = format!(
+            "{}/fapi/v1/order?{}&signature={}",
+            self.config.rest_url, query, signature
+        );
+
+        let response = self.client
+            .post(&url)
+            .header("X-MBX-APIKEY", &credentials.api_key)
+            .send_traced(self.config.log_raw_http)
+            .await
+            .with_context(|| format!("Failed to send {} request", what))?;
+
+        let status = response.status();
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            anyhow::bail!("Binance {} failed: {} - {}", what, status, body);
+        }
+
+        parse_json(&body).with_context(|| format!("Failed to parse {} response", what))
+    }
+
+    /// Listen key manager for the user-data stream of `credentials`
+    pub fn listen_key_manager(&self, credentials: &Credentials) -> ListenKeyManager {
+        ListenKeyManager::new(&self.config, self.client.clone(), credentials)
+    }
+
+    fn timestamp() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+    }
+}
+
+#[async_trait]
+impl ExchangeAdapter for BinanceAdapter {
+    fn id(&self) -> &str {
+        "binance"
+    }
+
+    fn to_native_symbol(&self, base: &str, quote: &str) -> String {
+        format!("{}{}", base, quote)
+    }
+
+    fn to_canonical_symbol(&self, native: &str) -> Option<String> {
+        self.quote_assets.canonical(native)
+    }
+
+    async fn load_quote_assets(&self) -> Result<()> {
+        let url = format!("{}/fapi/v1/exchangeInfo", self.config.rest_url);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Symbol {
+            quote_asset: String,
+        }
+
+        #[derive(Deserialize)]
+        struct ExchangeInfo {
+            symbols: Vec<Symbol>,
+        }
+
+        let info: ExchangeInfo = parse_json(&body).context("Failed to parse exchange info")?;
+        self.quote_assets.set(info.symbols.into_iter().map(|s| s.quote_asset));
+        Ok(())
+    }
+
+    async fn place_order(
+        &self,
+        credentials: &Credentials,
+        request: &OrderRequest,
+    ) -> Result<OrderResponse> {
+        let stream = self.start_user_stream(credentials);
+        let session = stream.as_ref().and_then(|stream| stream.session());
+        let order = self.post_order(credentials, request).await?;
+        if let Some(stream) = stream {
+            stream.placed(session, &order);
+        }
+        Ok(order.canonical(self))
+    }
+
+    async fn place_order_ws(
+        &self,
+        credentials: &Credentials,
+        request: &OrderRequest,
+    ) -> Result<OrderResponse> {
+        let Some(socket) = self.trading_socket(credentials) else {
+            return self.place_order(credentials, request).await;
+        };
+        let stream = self.start_user_stream(credentials);
+        let session = stream.as_ref().and_then(|stream| stream.session());
+
+        // Signed over every other param, sorted by name
+        let mut params = self.order_params(credentials, request)?;
+        params.push(("apiKey", credentials.api_key.clone()));
+        params.push(("timestamp", Self::timestamp().to_string()));
+        params.sort();
+        let payload: Vec<String> = params
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value))
+            .collect();
+        let signature = sign(&credentials.api_secret, &payload.join("&"));
+        let mut fields: serde_json::Map<String, serde_json::Value> = params
+            .into_iter()
+            .map(|(name, value)| (name.to_string(), value.into()))
+            .collect();
+        fields.insert("signature".to_string(), signature.into());
+
+        let frame = serde_json::json!({ "method": "order.place", "params": fields });
+        let answer = match socket.send(frame).await {
+            Ok(answer) => answer,
+            Err(e) => {
+                warn!("Binance trading socket unavailable, placing over REST: {:#}", e);
+                return self.place_order(credentials, request).await;
+            }
+        };
+        let answer: BinanceWsAnswer<BinanceOrderResponse> = serde_json::from_value(answer.wait().await?)
+            .context("Failed to parse order answer")?;
+        if let Some(error) = answer.error {
+            if error.code == REDUCE_ONLY_REJECTED {
+                return Err(reduce_only_rejected(self.id(), &error.msg));
+            }
+            if error.code == POST_ONLY_REJECTED {
+                return Err(post_only_rejected(self.id(), &error.msg));
+            }
+            if error.code == TIMESTAMP_OUTSIDE_RECV_WINDOW {
+                return Err(signature_expired(self.id(), &error.msg));
+            }
+            anyhow::bail!("Binance order failed: {} - {}", error.code, error.msg);
+        }
+        let order = answer
+            .result
+            .ok_or_else(|| anyhow::anyhow!("No result in order answer"))?;
+
+        info!("Binance order placed over WebSocket: {} status={}", order.order_id, order.status);
+        let order = order_response(order);
+        if let Some(stream) = stream {
+            stream.placed(session, &order);
+        }
+        Ok(order.canonical(self))
+    }
+
+    // callbackRate is a percentage from 0.1 to 10 in steps of 0.1
+    fn supports_trailing_stop(&self, trail: Trail) -> bool {
+        match trail {
+            Trail::CallbackRateBps(bps) => {
+                bps >= Decimal::TEN && bps <= Decimal::ONE_THOUSAND && (bps % Decimal::TEN).is_zero()
+            }
+            Trail::Distance(_) => false,
+        }
+    }
+
+    async fn place_trailing_stop(
+        &self,
+        credentials: &Credentials,
+        request: &TrailingStopRequest,
+    ) -> Result<OrderResponse> {
+        let Trail::CallbackRateBps(bps) = request.trail else {
+            anyhow::bail!("Binance trailing stops take a callback rate, not a distance");
+        };
+        let params = vec![
+            format!("symbol={}", self.native_symbol(&request.symbol)),
+            "type=TRAILING_STOP_MARKET".to_string(),
+            format!("quantity={}", request.quantity),
+            format!("callbackRate={}", (bps / Decimal::ONE_HUNDRED).normalize()),
+            format!("newClientOrderId={}", request.client_order_id),
+        ];
+        let order = self.post_close_order(credentials, request.side, params, "trailing stop").await?;
+        info!("Binance trailing stop placed: {} ({} bps)", order.order_id, bps);
+        Ok(order_response(order).canonical(self))
+    }
+
+    fn supports_stop_order(&self) -> bool {
+        true
+    }
+
+    async fn place_stop_order(
+        &self,
+        credentials: &Credentials,
+        request: &StopOrderRequest,
+    ) -> Result<OrderResponse> {
+        let params = vec![
+            format!("symbol={}", self.native_symbol(&request.symbol)),
+            "type=STOP_MARKET".to_string(),
+            format!("quantity={}", request.quantity),
+            format!("stopPrice={}", request.stop_price),
+            format!("newClientOrderId={}", request.client_order_id),
+        ];
+        let order = self.post_close_order(credentials, request.side, params, "stop order").await?;
+        debug!("Binance stop order placed: {} @ {}", order.order_id, request.stop_price);
+        Ok(order_response(order).canonical(self))
+    }
+
     async fn cancel_order(
         &self,
         credentials: &Credentials,
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
         let timestamp = Self::timestamp();
         
         let query = format!(
             "symbol={}&orderId={}&timestamp={}",
             symbol, order_id, timestamp
         );
-        let signature = self.sign(&credentials.api_secret, &query);
+        let signature = sign(&credentials.api_secret, &query);
         let full_query = format!("{}&signature={}", query, signature);
 
         let url = format!("{}/fapi/v1/order?{}", self.config.rest_url, full_query);
@@ -151,16 +541,26 @@ impl ExchangeAdapter for BinanceAdapter {
         let response = self.client
             .delete(&url)
             .header("X-MBX-APIKEY", &credentials.api_key)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
+        let status = response.status();
         let body = response.text().await?;
-        let order: BinanceOrderResponse = serde_json::from_str(&body)?;
+        if !status.is_success() {
+            // Lost the race with a fill or an earlier cancel: report where it ended up
+            if parse_json::<BinanceError>(&body).is_ok_and(|e| e.code == UNKNOWN_ORDER) {
+                debug!("Binance order {} is no longer open, fetching it", order_id);
+                return self.get_order(credentials, &symbol, order_id).await;
+            }
+            check_unavailable(self.id(), status, &body)?;
+            anyhow::bail!("Binance cancel failed: {} - {}", status, body);
+        }
+        let order: BinanceOrderResponse = parse_json(&body)?;
 
         Ok(OrderResponse {
             exchange_order_id: order.order_id.to_string(),
             client_order_id: order.client_order_id,
-            symbol: order.symbol,
+            symbol: self.canonical_symbol(&order.symbol),
             side: match order.side.as_str() {
                 "BUY" => Side::Buy,
                 _ => Side::Sell,
@@ -171,7 +571,7 @@ impl ExchangeAdapter for BinanceAdapter {
             filled_quantity: order.executed_qty.parse().unwrap_or_default(),
             avg_fill_price: order.avg_price.parse().ok(),
             status: parse_binance_status(&order.status),
-            timestamp: order.update_time,
+            timestamp: epoch_millis(order.update_time),
         })
     }
 
@@ -181,83 +581,799 @@ impl ExchangeAdapter for BinanceAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
-        let timestamp = Self::timestamp();
-        
+        let symbol = self.native_symbol(symbol);
+        if let Some(order) = self.user_stream(credentials).and_then(|stream| stream.order(&symbol, order_id)) {
+            return Ok(order.canonical(self));
+        }
+        Ok(self.query_order(credentials, &symbol, "orderId", order_id).await?.canonical(self))
+    }
+
+    // allOrders returns every order from `orderId` onwards, so one call
+    // starting at the oldest outstanding id covers all of them
+    async fn get_orders_batch(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_ids: &[String],
+    ) -> Result<Vec<OrderResponse>> {
+        let Some(oldest) = order_ids.iter().filter_map(|id| id.parse::<i64>().ok()).min() else {
+            return Ok(Vec::new());
+        };
+
+        let symbol = self.native_symbol(symbol);
+        if let Some(stream) = self.user_stream(credentials) {
+            let streamed: Option<Vec<OrderResponse>> = order_ids.iter().map(|id| stream.order(&symbol, id)).collect();
+            if let Some(orders) = streamed {
+                return Ok(orders.into_iter().map(|order| order.canonical(self)).collect());
+            }
+        }
+
         let query = format!(
-            "symbol={}&orderId={}&timestamp={}",
-            symbol, order_id, timestamp
+            "symbol={}&orderId={}&limit=1000&timestamp={}",
+            symbol, oldest, Self::timestamp()
+        );
+        let signature = sign(&credentials.api_secret, &query);
+        let url = format!(
+            "{}/fapi/v1/allOrders?{}&signature={}",
+            self.config.rest_url, query, signature
         );
-        let signature = self.sign(&credentials.api_secret, &query);
-        let full_query = format!("{}&signature={}", query, signature);
 
-        let url = format!("{}/fapi/v1/order?{}", self.config.rest_url, full_query);
+        let response = self.client
+            .get(&url)
+            .header("X-MBX-APIKEY", &credentials.api_key)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            anyhow::bail!("Binance order query failed: {} - {}", status, body);
+        }
+
+        let orders: Vec<BinanceOrderResponse> = parse_json(&body)?;
+        Ok(orders
+            .into_iter()
+            .filter(|o| order_ids.contains(&o.order_id.to_string()))
+            .map(|order| order_response(order).canonical(self))
+            .collect())
+    }
+
+    async fn get_open_orders(&self, credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        let symbol = self.native_symbol(symbol);
+        let query = format!("symbol={}&timestamp={}", symbol, Self::timestamp());
+        let signature = sign(&credentials.api_secret, &query);
+        let url = format!(
+            "{}/fapi/v1/openOrders?{}&signature={}",
+            self.config.rest_url, query, signature
+        );
 
         let response = self.client
             .get(&url)
             .header("X-MBX-APIKEY", &credentials.api_key)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
+        let status = response.status();
         let body = response.text().await?;
-        let order: BinanceOrderResponse = serde_json::from_str(&body)?;
+        if !status.is_success() {
+            check_unavailable(self.id(), status, &body)?;
+            anyhow::bail!("Binance open orders query failed: {} - {}", status, body);
+        }
 
-        Ok(OrderResponse {
-            exchange_order_id: order.order_id.to_string(),
-            client_order_id: order.client_order_id,
-            symbol: order.symbol,
-            side: match order.side.as_str() {
-                "BUY" => Side::Buy,
-                _ => Side::Sell,
+        let orders: Vec<BinanceOrderResponse> = parse_json(&body)?;
+        Ok(orders.into_iter().map(|order| order_response(order).canonical(self)).collect())
+    }
+
+    async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
+        let symbol = self.native_symbol(symbol);
+        if let Some(quote) = self.book_tickers().and_then(|feed| feed.quote(&book_ticker_topic(&symbol))) {
+            return Ok(quote);
+        }
+        rest_book_ticker(&self.client, &self.config.rest_url, &symbol, self.config.log_raw_http).await
+    }
+
+    async fn get_reference_price(&self, symbol: &str, source: ReferencePriceSource) -> Result<Decimal> {
+        let path = match source {
+            ReferencePriceSource::Mid => return mid_price(self, symbol).await,
+            ReferencePriceSource::Last => "ticker/price",
+            ReferencePriceSource::Mark | ReferencePriceSource::Index => "premiumIndex",
+        };
+        let symbol = self.native_symbol(symbol);
+        let url = format!("{}/fapi/v1/{}?symbol={}", self.config.rest_url, path, symbol);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Prices {
+            price: Option<String>,
+            mark_price: Option<String>,
+            index_price: Option<String>,
+        }
+
+        let prices: Prices = parse_json(&body)?;
+        let price = match source {
+            ReferencePriceSource::Last => prices.price,
+            ReferencePriceSource::Mark => prices.mark_price,
+            _ => prices.index_price,
+        };
+        price
+            .ok_or_else(|| anyhow::anyhow!("No {} price for {}: {}", source, symbol, body))?
+            .parse()
+            .context("Invalid price")
+    }
+
+    fn supports_gtd(&self, expire_at_ms: i64) -> bool {
+        expire_at_ms > now_millis() + GTD_MIN_LEAD_MS
+    }
+
+    async fn get_server_time(&self) -> Result<i64> {
+        let url = format!("{}/fapi/v1/time", self.config.rest_url);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let status = response.status();
+        let body = response.text().await?;
+        check_unavailable(self.id(), status, &body)?;
+
+        #[derive(Deserialize)]
+        struct ServerTime {
+            #[serde(rename = "serverTime")]
+            server_time: i64,
+        }
+
+        let time: ServerTime = parse_json(&body)?;
+        Ok(time.server_time)
+    }
+
+    async fn get_symbol_info(&self, symbol: &str) -> Result<SymbolInfo> {
+        let symbol = self.native_symbol(symbol);
+        // exchangeInfo has no per-symbol filter on futures
+        let url = format!("{}/fapi/v1/exchangeInfo", self.config.rest_url);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Filter {
+            filter_type: String,
+            step_size: Option<String>,
+            tick_size: Option<String>,
+            notional: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct Symbol {
+            symbol: String,
+            status: String,
+            #[serde(default)]
+            filters: Vec<Filter>,
+        }
+
+        #[derive(Deserialize)]
+        struct ExchangeInfo {
+            symbols: Vec<Symbol>,
+        }
+
+        let info: ExchangeInfo = parse_json(&body)
+            .context("Failed to parse exchange info")?;
+        let listed = info
+            .symbols
+            .into_iter()
+            .find(|s| s.symbol == symbol);
+        let filter = |filter_type: &str| {
+            listed
+                .as_ref()
+                .and_then(|s| s.filters.iter().find(|f| f.filter_type == filter_type))
+        };
+        let quantity_step = filter("LOT_SIZE")
+            .and_then(|f| f.step_size.as_ref())
+            .and_then(|step| step.parse().ok())
+            .unwrap_or_default();
+        let tick_size = filter("PRICE_FILTER")
+            .and_then(|f| f.tick_size.as_ref())
+            .and_then(|tick| tick.parse().ok())
+            .unwrap_or_default();
+        let min_notional = filter("MIN_NOTIONAL")
+            .and_then(|f| f.notional.as_ref())
+            .and_then(|notional| notional.parse().ok())
+            .unwrap_or_default();
+
+        Ok(SymbolInfo {
+            status: match listed.as_ref().map(|s| s.status.as_str()) {
+                Some("TRADING") => SymbolStatus::Trading,
+                Some("PENDING_TRADING") => SymbolStatus::PreLaunch,
+                Some("CLOSE") | Some("DELIVERED") | None => SymbolStatus::Delisted,
+                // PRE_DELIVERING, DELIVERING, PRE_SETTLE, SETTLING
+                Some(_) => SymbolStatus::Maintenance,
             },
-            order_type: OrderType::Limit,
-            price: order.price.parse().ok(),
-            quantity: order.orig_qty.parse().unwrap_or_default(),
-            filled_quantity: order.executed_qty.parse().unwrap_or_default(),
-            avg_fill_price: order.avg_price.parse().ok(),
-            status: parse_binance_status(&order.status),
-            timestamp: order.update_time,
+            symbol,
+            // USDⓈ-M quantities are in coins
+            contract_size: Decimal::ONE,
+            quantity_step,
+            tick_size,
+            min_notional,
         })
     }
 
-    async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
+    async fn get_order_book(&self, symbol: &str, depth: usize) -> Result<OrderBook> {
+        let symbol = self.native_symbol(symbol);
+        // Only these limits are accepted, so request the smallest that covers `depth`
+        let limit = [5, 10, 20, 50, 100, 500, 1000]
+            .into_iter()
+            .find(|&limit| limit >= depth)
+            .unwrap_or(1000);
         let url = format!(
-            "{}/fapi/v1/ticker/bookTicker?symbol={}",
-            self.config.rest_url, symbol
+            "{}/fapi/v1/depth?symbol={}&limit={}",
+            self.config.rest_url, symbol, limit
         );
 
-        let response = self.client.get(&url).send().await?;
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
         let body = response.text().await?;
 
         #[derive(Deserialize)]
-        struct BookTicker {
-            #[serde(rename = "bidPrice")]
-            bid_price: String,
-            #[serde(rename = "askPrice")]
-            ask_price: String,
+        struct Depth {
+            bids: Vec<[String; 2]>,
+            asks: Vec<[String; 2]>,
+            /// Transaction time
+            #[serde(rename = "T")]
+            time: i64,
         }
 
-        let ticker: BookTicker = serde_json::from_str(&body)?;
-        
-        Ok((
-            ticker.bid_price.parse()?,
-            ticker.ask_price.parse()?,
+        let book: Depth = parse_json(&body)
+            .with_context(|| format!("Failed to parse depth: {}", body))?;
+
+        Ok(OrderBook::from_levels(
+            parse_levels(&book.bids)?,
+            parse_levels(&book.asks)?,
+            depth,
+            book.time,
         ))
     }
 
+    // Orders go over REST, so only a book ticker stream, once started, can drop
     fn is_connected(&self) -> bool {
-        true // REST adapter is always "connected"
+        self.book_tickers.get().is_none_or(|feed| feed.handle.is_connected())
     }
-}
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct BinanceOrderResponse {
-    order_id: i64,
-    symbol: String,
+    async fn get_position_mode(&self, credentials: &Credentials) -> Result<bool> {
+        let query = format!("timestamp={}", Self::timestamp());
+        let signature = sign(&credentials.api_secret, &query);
+        let url = format!(
+            "{}/fapi/v1/positionSide/dual?{}&signature={}",
+            self.config.rest_url, query, signature
+        );
+
+        let response = self.client
+            .get(&url)
+            .header("X-MBX-APIKEY", &credentials.api_key)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            anyhow::bail!("Binance position mode query failed: {} - {}", status, body);
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct PositionMode {
+            dual_side_position: bool,
+        }
+
+        let mode: PositionMode = parse_json(&body)?;
+        self.hedge_mode
+            .write()
+            .unwrap()
+            .insert(credentials.api_key.clone(), mode.dual_side_position);
+        Ok(mode.dual_side_position)
+    }
+
+    async fn get_leverage(&self, credentials: &Credentials, symbol: &str) -> Result<LeverageInfo> {
+        let symbol = self.native_symbol(symbol);
+        let query = format!("symbol={}&timestamp={}", symbol, Self::timestamp());
+        let signature = sign(&credentials.api_secret, &query);
+        let url = format!(
+            "{}/fapi/v2/positionRisk?{}&signature={}",
+            self.config.rest_url, query, signature
+        );
+
+        let response = self.client
+            .get(&url)
+            .header("X-MBX-APIKEY", &credentials.api_key)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            anyhow::bail!("Binance position risk query failed: {} - {}", status, body);
+        }
+
+        // One entry per position side, all sharing the symbol's settings
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct PositionRisk {
+            leverage: String,
+            margin_type: String,
+        }
+
+        let positions: Vec<PositionRisk> = parse_json(&body)?;
+        let position = positions
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("No position risk for {}", symbol))?;
+        Ok(LeverageInfo {
+            leverage: position.leverage.parse().context("Invalid Binance leverage")?,
+            margin_mode: match position.margin_type.as_str() {
+                "isolated" => MarginMode::Isolated,
+                _ => MarginMode::Cross,
+            },
+        })
+    }
+
+    async fn get_risk_limit(&self, credentials: &Credentials, symbol: &str) -> Result<RiskLimit> {
+        let symbol = self.native_symbol(symbol);
+        let query = format!("symbol={}&timestamp={}", symbol, Self::timestamp());
+        let signature = sign(&credentials.api_secret, &query);
+        let url = format!(
+            "{}/fapi/v2/positionRisk?{}&signature={}",
+            self.config.rest_url, query, signature
+        );
+
+        let response = self.client
+            .get(&url)
+            .header("X-MBX-APIKEY", &credentials.api_key)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            anyhow::bail!("Binance position risk query failed: {} - {}", status, body);
+        }
+
+        // The bracket follows from the leverage, so there is no tier to pick
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct PositionRisk {
+            max_notional_value: String,
+        }
+
+        let positions: Vec<PositionRisk> = parse_json(&body)?;
+        let position = positions
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("No position risk for {}", symbol))?;
+        Ok(RiskLimit {
+            max_notional_usd: position.max_notional_value.parse().context("Invalid Binance maxNotionalValue")?,
+            tier: None,
+        })
+    }
+
+    async fn get_positions(&self, credentials: &Credentials) -> Result<Vec<Position>> {
+        let query = format!("timestamp={}", Self::timestamp());
+        let signature = sign(&credentials.api_secret, &query);
+        let url = format!(
+            "{}/fapi/v2/positionRisk?{}&signature={}",
+            self.config.rest_url, query, signature
+        );
+
+        let response = self.client
+            .get(&url)
+            .header("X-MBX-APIKEY", &credentials.api_key)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            anyhow::bail!("Binance position risk query failed: {} - {}", status, body);
+        }
+
+        // Every symbol is listed, flat or not. In one-way mode the amount
+        // is signed; in hedge mode each side has its own entry.
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct PositionRisk {
+            symbol: String,
+            position_amt: String,
+            entry_price: String,
+        }
+
+        let positions: Vec<PositionRisk> = parse_json(&body)?;
+        let mut open = Vec::new();
+        for position in positions {
+            let amount: Decimal = position.position_amt.parse().context("Invalid Binance position amount")?;
+            if amount.is_zero() {
+                continue;
+            }
+            open.push(Position {
+                symbol: position.symbol,
+                side: if amount > Decimal::ZERO { Side::Buy } else { Side::Sell },
+                quantity: amount.abs(),
+                entry_price: position.entry_price.parse().context("Invalid Binance entry price")?,
+            });
+        }
+        Ok(open)
+    }
+
+    async fn set_position_mode(&self, credentials: &Credentials, hedge: bool) -> Result<()> {
+        let query = format!("dualSidePosition={}&timestamp={}", hedge, Self::timestamp());
+        let signature = sign(&credentials.api_secret, &query);
+        let url = format!(
+            "{}/fapi/v1/positionSide/dual?{}&signature={}",
+            self.config.rest_url, query, signature
+        );
+
+        let response = self.client
+            .post(&url)
+            .header("X-MBX-APIKEY", &credentials.api_key)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+
+        // -4059: already in the requested mode
+        if !status.is_success() && !body.contains("-4059") {
+            anyhow::bail!("Binance position mode change failed: {} - {}", status, body);
+        }
+
+        info!("Binance position mode set to {}", if hedge { "hedge" } else { "one-way" });
+        self.hedge_mode
+            .write()
+            .unwrap()
+            .insert(credentials.api_key.clone(), hedge);
+        Ok(())
+    }
+}
+
+/// Listen keys lapse after 60 minutes without a keepalive
+pub const LISTEN_KEY_KEEPALIVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30 * 60);
+
+/// Lifecycle of the listen key a user-data stream subscribes with. The key
+/// is created on first use, kept alive on a timer, replaced when Binance
+/// reports it expired, and closed on shutdown. Consumers watch
+/// [`ListenKeyManager::subscribe`] and reconnect whenever the key changes.
+pub struct ListenKeyManager {
+    client: Client,
+    rest_url: String,
+    api_key: String,
+    log_raw_http: bool,
+    key: watch::Sender<Option<String>>,
+}
+
+impl ListenKeyManager {
+    pub fn new(config: &ExchangeConfig, client: Client, credentials: &Credentials) -> Self {
+        Self {
+            client,
+            rest_url: config.rest_url.clone(),
+            api_key: credentials.api_key.clone(),
+            log_raw_http: config.log_raw_http,
+            key: watch::channel(None).0,
+        }
+    }
+
+    /// Current key, `None` until one is created and after `close`
+    pub fn subscribe(&self) -> watch::Receiver<Option<String>> {
+        self.key.subscribe()
+    }
+
+    /// Create a key, or return the account's active one. Binance hands back
+    /// the same key while it is alive, so this is also how one is recreated
+    /// after expiry.
+    pub async fn create(&self) -> Result<String> {
+        let body = self.request(reqwest::Method::POST).await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ListenKey {
+            listen_key: String,
+        }
+
+        let key = parse_json::<ListenKey>(&body)
+            .with_context(|| format!("Unexpected Binance listenKey response: {}", body))?
+            .listen_key;
+        self.key.send_if_modified(|current| {
+            let changed = current.as_deref() != Some(key.as_str());
+            *current = Some(key.clone());
+            changed
+        });
+        Ok(key)
+    }
+
+    /// Extend the key's validity, recreating it if it has already expired
+    pub async fn keepalive(&self) -> Result<()> {
+        match self.request(reqwest::Method::PUT).await {
+            Ok(_) => Ok(()),
+            // -1125: this listenKey does not exist
+            Err(e) if e.to_string().contains("-1125") => {
+                warn!("Binance listen key expired, recreating");
+                self.expired().await
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Replace a key the stream reported as expired (`listenKeyExpired`)
+    pub async fn expired(&self) -> Result<()> {
+        self.key.send_replace(None);
+        self.create().await.map(|_| ())
+    }
+
+    /// Close the key so the stream stops
+    pub async fn close(&self) -> Result<()> {
+        if self.key.send_replace(None).is_none() {
+            return Ok(());
+        }
+        self.request(reqwest::Method::DELETE).await.map(|_| ())
+    }
+
+    /// Create the key and keep it alive every `interval` until `shutdown`
+    /// resolves, then close it. Failures are retried at the next tick.
+    pub async fn run(&self, interval: std::time::Duration, shutdown: impl std::future::Future<Output = ()>) {
+        tokio::pin!(shutdown);
+        if let Err(e) = self.create().await {
+            warn!("Failed to create Binance listen key: {:#}", e);
+        }
+
+        loop {
+            tokio::select! {
+                _ = &mut shutdown => break,
+                _ = tokio::time::sleep(interval) => {}
+            }
+            let result = if self.key.borrow().is_some() {
+                self.keepalive().await
+            } else {
+                self.create().await.map(|_| ())
+            };
+            if let Err(e) = result {
+                warn!("Binance listen key keepalive failed: {:#}", e);
+            }
+        }
+
+        if let Err(e) = self.close().await {
+            warn!("Failed to close Binance listen key: {:#}", e);
+        }
+    }
+
+    async fn request(&self, method: reqwest::Method) -> Result<String> {
+        let url = format!("{}/fapi/v1/listenKey", self.rest_url);
+        let response = self
+            .client
+            .request(method.clone(), &url)
+            .header("X-MBX-APIKEY", &self.api_key)
+            .send_traced(self.log_raw_http)
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            anyhow::bail!("Binance listenKey {} failed: {} - {}", method, status, body);
+        }
+        Ok(body)
+    }
+}
+
+/// Binance pings user-data streams every 3 minutes, so a quiet account is
+/// only presumed disconnected well after that
+const USER_STREAM_STALE_AFTER: std::time::Duration = std::time::Duration::from_secs(10 * 60);
+
+/// The user-data stream at `{ws_url}/ws/{listenKey}`. It needs no
+/// subscription, and carries no sequence worth tracking.
+struct UserDataProtocol {
+    ws_url: String,
+    listen_key: watch::Receiver<Option<String>>,
+}
+
+#[async_trait]
+impl FeedProtocol for UserDataProtocol {
+    fn url(&self) -> String {
+        format!("{}/ws/{}", self.ws_url, self.listen_key.borrow().as_deref().unwrap_or_default())
+    }
+
+    fn subscribe_message(&self, _topics: &[String]) -> String {
+        String::new()
+    }
+
+    fn parse(&self, text: &str) -> Option<FeedUpdate> {
+        let payload: serde_json::Value = serde_json::from_str(text).ok()?;
+        let topic = payload["e"].as_str()?.to_string();
+        Some(FeedUpdate {
+            topic,
+            sequence: None,
+            prev_sequence: None,
+            payload,
+        })
+    }
+}
+
+/// Order states pushed over the user-data stream of one API key, so order
+/// reads don't have to go to REST. An order is only served from here if the
+/// stream was up when it was placed and has stayed up since; a disconnect
+/// forgets everything, as updates may have been missed meanwhile.
+struct UserDataStream {
+    /// Number of the connection the stream is on, or None while it is down
+    session: Arc<RwLock<Option<u64>>>,
+    /// By native symbol and order id
+    orders: Arc<RwLock<HashMap<(String, String), OrderResponse>>>,
+}
+
+impl UserDataStream {
+    /// Keep `manager`'s listen key alive and follow its stream at `ws_url`
+    fn spawn(ws_url: String, manager: ListenKeyManager) -> Self {
+        let manager = Arc::new(manager);
+        let session = Arc::new(RwLock::new(None));
+        let orders = Arc::new(RwLock::new(HashMap::new()));
+
+        tokio::spawn({
+            let manager = manager.clone();
+            async move { manager.run(LISTEN_KEY_KEEPALIVE_INTERVAL, std::future::pending()).await }
+        });
+        tokio::spawn(follow_user_stream(ws_url, manager, session.clone(), orders.clone()));
+        Self { session, orders }
+    }
+
+    /// Connection in use now, to pass to [`Self::placed`]
+    fn session(&self) -> Option<u64> {
+        *self.session.read().unwrap()
+    }
+
+    /// Start tracking an order placed while the stream was on `session`.
+    /// Updates that beat the placement response are kept.
+    fn placed(&self, session: Option<u64>, order: &OrderResponse) {
+        let current = self.session.read().unwrap();
+        if session.is_none() || *current != session {
+            return;
+        }
+        merge_order(&mut self.orders.write().unwrap(), order.clone());
+    }
+
+    /// Latest state of order `order_id` on native `symbol`, if it is tracked
+    fn order(&self, symbol: &str, order_id: &str) -> Option<OrderResponse> {
+        self.session()?;
+        self.orders
+            .read()
+            .unwrap()
+            .get(&(symbol.to_string(), order_id.to_string()))
+            .cloned()
+    }
+}
+
+/// Apply the user-data stream's events to `orders` until the stream ends
+async fn follow_user_stream(
+    ws_url: String,
+    manager: Arc<ListenKeyManager>,
+    session: Arc<RwLock<Option<u64>>>,
+    orders: Arc<RwLock<HashMap<(String, String), OrderResponse>>>,
+) {
+    let mut listen_key = manager.subscribe();
+    if listen_key.wait_for(|key| key.is_some()).await.is_err() {
+        return;
+    }
+    let protocol = UserDataProtocol { ws_url, listen_key: listen_key.clone() };
+    let (handle, mut events) = Feed::new(protocol).with_stale_after(USER_STREAM_STALE_AFTER).spawn();
+    let mut connections = 0;
+
+    loop {
+        tokio::select! {
+            event = events.recv() => {
+                let Some(event) = event else { return };
+                match event {
+                    FeedEvent::Connected => {
+                        connections += 1;
+                        let mut current = session.write().unwrap();
+                        orders.write().unwrap().clear();
+                        *current = Some(connections);
+                    }
+                    FeedEvent::Disconnected => {
+                        let mut current = session.write().unwrap();
+                        orders.write().unwrap().clear();
+                        *current = None;
+                    }
+                    FeedEvent::Snapshot(update) | FeedEvent::Update(update) => match update.topic.as_str() {
+                        "ORDER_TRADE_UPDATE" => {
+                            match serde_json::from_value::<BinanceOrderUpdate>(update.payload["o"].clone()) {
+                                Ok(update) => merge_order(&mut orders.write().unwrap(), update.into()),
+                                Err(e) => warn!("Unexpected Binance order update: {}", e),
+                            }
+                        }
+                        "listenKeyExpired" => {
+                            warn!("Binance listen key expired, recreating");
+                            if let Err(e) = manager.expired().await {
+                                warn!("Failed to recreate Binance listen key: {:#}", e);
+                            }
+                        }
+                        _ => {}
+                    },
+                }
+            }
+            changed = listen_key.changed() => {
+                if changed.is_err() {
+                    return;
+                }
+                if listen_key.borrow_and_update().is_some() {
+                    handle.reconnect();
+                }
+            }
+        }
+    }
+}
+
+/// Record `update` unless what is held for the order is already further along
+fn merge_order(orders: &mut HashMap<(String, String), OrderResponse>, update: OrderResponse) {
+    let key = (update.symbol.clone(), update.exchange_order_id.clone());
+    let outdated = orders.get(&key).is_some_and(|held| {
+        held.filled_quantity > update.filled_quantity
+            || (held.status.is_terminal() && !update.status.is_terminal())
+    });
+    if !outdated {
+        orders.insert(key, update);
+    }
+}
+
+/// `o` of an `ORDER_TRADE_UPDATE` event
+#[derive(Debug, Deserialize)]
+struct BinanceOrderUpdate {
+    #[serde(rename = "i")]
+    order_id: i64,
+    #[serde(rename = "s")]
+    symbol: String,
+    #[serde(rename = "X")]
+    status: String,
+    #[serde(rename = "c")]
+    client_order_id: String,
+    #[serde(rename = "p")]
+    price: String,
+    #[serde(rename = "q")]
+    orig_qty: String,
+    /// Cumulative filled quantity
+    #[serde(rename = "z")]
+    executed_qty: String,
+    #[serde(rename = "ap")]
+    avg_price: String,
+    #[serde(rename = "S")]
+    side: String,
+    #[serde(rename = "o")]
+    order_type: String,
+    #[serde(rename = "T")]
+    update_time: i64,
+}
+
+impl From<BinanceOrderUpdate> for OrderResponse {
+    fn from(update: BinanceOrderUpdate) -> Self {
+        order_response(BinanceOrderResponse {
+            order_id: update.order_id,
+            symbol: update.symbol,
+            status: update.status,
+            client_order_id: update.client_order_id,
+            price: update.price,
+            orig_qty: update.orig_qty,
+            executed_qty: update.executed_qty,
+            avg_price: update.avg_price,
+            side: update.side,
+            order_type: update.order_type,
+            update_time: update.update_time,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BinanceOrderResponse {
+    order_id: i64,
+    symbol: String,
     status: String,
     client_order_id: String,
     price: String,
     orig_qty: String,
+    /// Cumulative filled quantity
     executed_qty: String,
     avg_price: String,
     side: String,
@@ -266,6 +1382,42 @@ struct BinanceOrderResponse {
     update_time: i64,
 }
 
+/// Answer to a WebSocket API request
+#[derive(Debug, Deserialize)]
+struct BinanceWsAnswer<T> {
+    result: Option<T>,
+    error: Option<BinanceError>,
+}
+
+/// Error body, over REST or the WebSocket API
+#[derive(Debug, Deserialize)]
+struct BinanceError {
+    code: i64,
+    msg: String,
+}
+
+fn order_response(order: BinanceOrderResponse) -> OrderResponse {
+    OrderResponse {
+        exchange_order_id: order.order_id.to_string(),
+        client_order_id: order.client_order_id,
+        symbol: order.symbol,
+        side: match order.side.as_str() {
+            "BUY" => Side::Buy,
+            _ => Side::Sell,
+        },
+        order_type: match order.order_type.as_str() {
+            "LIMIT" => OrderType::Limit,
+            _ => OrderType::Market,
+        },
+        price: order.price.parse().ok(),
+        quantity: order.orig_qty.parse().unwrap_or_default(),
+        filled_quantity: order.executed_qty.parse().unwrap_or_default(),
+        avg_fill_price: order.avg_price.parse().ok(),
+        status: parse_binance_status(&order.status),
+        timestamp: epoch_millis(order.update_time),
+    }
+}
+
 fn parse_binance_status(status: &str) -> OrderStatus {
     match status {
         "NEW" => OrderStatus::Open,
@@ -277,3 +1429,560 @@ fn parse_binance_status(status: &str) -> OrderStatus {
         _ => OrderStatus::Pending,
     }
 }
+
+/// Best bid and ask of native `symbol` over REST
+async fn rest_book_ticker(client: &Client, rest_url: &str, symbol: &str, log_raw_http: bool) -> Result<(Decimal, Decimal)> {
+    let url = format!("{}/fapi/v1/ticker/bookTicker?symbol={}", rest_url, symbol);
+
+    let response = client.get(&url).send_traced(log_raw_http).await?;
+    let body = response.text().await?;
+
+    #[derive(Deserialize)]
+    struct BookTicker {
+        #[serde(rename = "bidPrice")]
+        bid_price: String,
+        #[serde(rename = "askPrice")]
+        ask_price: String,
+    }
+
+    let ticker: BookTicker = parse_json(&body)?;
+    Ok((ticker.bid_price.parse()?, ticker.ask_price.parse()?))
+}
+
+fn book_ticker_topic(native_symbol: &str) -> String {
+    format!("{}@bookTicker", native_symbol.to_lowercase())
+}
+
+/// `<symbol>@bookTicker` streams, resynced from the REST book ticker. Their
+/// update ids are not consecutive per symbol, so no sequence is tracked.
+struct BookTickerProtocol {
+    ws_url: String,
+    rest_url: String,
+    client: Client,
+    log_raw_http: bool,
+}
+
+#[async_trait]
+impl FeedProtocol for BookTickerProtocol {
+    fn url(&self) -> String {
+        format!("{}/ws", self.ws_url)
+    }
+
+    fn subscribe_message(&self, topics: &[String]) -> String {
+        serde_json::json!({ "method": "SUBSCRIBE", "params": topics, "id": 1 }).to_string()
+    }
+
+    fn parse(&self, text: &str) -> Option<FeedUpdate> {
+        let payload: serde_json::Value = serde_json::from_str(text).ok()?;
+        if payload["e"] != "bookTicker" {
+            return None;
+        }
+        Some(FeedUpdate {
+            topic: book_ticker_topic(payload["s"].as_str()?),
+            sequence: None,
+            prev_sequence: None,
+            payload,
+        })
+    }
+
+    async fn snapshot(&self, topic: &str) -> Result<FeedUpdate> {
+        let symbol = topic.trim_end_matches("@bookTicker").to_uppercase();
+        let (bid, ask) = rest_book_ticker(&self.client, &self.rest_url, &symbol, self.log_raw_http).await?;
+        Ok(FeedUpdate {
+            topic: topic.to_string(),
+            sequence: None,
+            prev_sequence: None,
+            payload: serde_json::json!({ "b": bid.to_string(), "a": ask.to_string() }),
+        })
+    }
+}
+
+/// Latest best bid and ask of every symbol read so far, kept by a book
+/// ticker feed
+struct BookTickerFeed {
+    handle: FeedHandle,
+    /// By topic, and emptied while the feed is disconnected
+    quotes: Arc<RwLock<HashMap<String, (Decimal, Decimal)>>>,
+}
+
+impl BookTickerFeed {
+    fn spawn(protocol: BookTickerProtocol) -> Self {
+        let (handle, mut events) = Feed::new(protocol).spawn();
+        let quotes = Arc::new(RwLock::new(HashMap::new()));
+        tokio::spawn({
+            let quotes = quotes.clone();
+            async move {
+                while let Some(event) = events.recv().await {
+                    match event {
+                        FeedEvent::Snapshot(update) | FeedEvent::Update(update) => {
+                            let quote = (|| {
+                                let bid = update.payload["b"].as_str()?.parse().ok()?;
+                                let ask = update.payload["a"].as_str()?.parse().ok()?;
+                                Some((bid, ask))
+                            })();
+                            if let Some(quote) = quote {
+                                quotes.write().unwrap().insert(update.topic, quote);
+                            }
+                        }
+                        FeedEvent::Disconnected => quotes.write().unwrap().clear(),
+                        FeedEvent::Connected => {}
+                    }
+                }
+            }
+        });
+        Self { handle, quotes }
+    }
+
+    /// Streamed best bid and ask of `topic`, subscribing to it the first
+    /// time. None until the stream has delivered one, and while it is down.
+    fn quote(&self, topic: &str) -> Option<(Decimal, Decimal)> {
+        let quote = self.quotes.read().unwrap().get(topic).copied();
+        if quote.is_none() {
+            self.handle.subscribe(topic);
+        }
+        quote.filter(|_| self.handle.is_connected())
+    }
+}
+
+/// HMAC-SHA256 of the query string or form body, hex
+pub fn sign(secret: &str, query: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(query.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::serve_http;
+    use crate::exchange::ExchangeError;
+    use rust_decimal_macros::dec;
+
+    const ORDER: &str = r#"{"orderId":7,"symbol":"BTCUSDT","status":"NEW","clientOrderId":"cs1","price":"100","origQty":"1","executedQty":"0","avgPrice":"0","side":"BUY","type":"LIMIT","updateTime":1}"#;
+
+    fn config(rest_url: String, trade_ws_url: String) -> ExchangeConfig {
+        ExchangeConfig {
+            id: "binance".to_string(),
+            rest_url,
+            ws_url: String::new(),
+            trade_ws_url: Some(trade_ws_url),
+            testnet: false,
+            maker_fee_bps: 0.0,
+            taker_fee_bps: 0.0,
+            trade_mode: None,
+            user_agent: None,
+            proxy: None,
+            log_raw_http: false,
+            ws_orders: true,
+            order_tag: None,
+            client_order_id_prefix: None,
+            adopt_duplicate_orders: false,
+        }
+    }
+
+    fn order_request() -> OrderRequest {
+        OrderRequest {
+            client_order_id: "cs1".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some(Decimal::ONE_HUNDRED),
+            quantity: Decimal::ONE,
+            reduce_only: false,
+            time_in_force: TimeInForce::Gtc,
+            quantity_mode: QuantityMode::Base,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_ws_order_is_signed_and_falls_back_to_rest() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}", listener.local_addr().unwrap());
+        let socket_server = tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let frame = socket.next().await.unwrap().unwrap().into_text().unwrap();
+            let request: serde_json::Value = serde_json::from_str(&frame).unwrap();
+            let result: serde_json::Value = serde_json::from_str(ORDER).unwrap();
+            let answer = serde_json::json!({ "id": request["id"], "status": 200, "result": result });
+            socket.send(Message::Text(answer.to_string())).await.unwrap();
+            request
+        });
+
+        let credentials = crate::exchange::mock::credentials();
+        let adapter = BinanceAdapter::new(config(String::new(), ws_url)).await.unwrap();
+        let order = adapter.place_order_ws(&credentials, &order_request()).await.unwrap();
+        assert_eq!(order.exchange_order_id, "7");
+        assert_eq!(order.status, OrderStatus::Open);
+
+        let request = socket_server.await.unwrap();
+        assert_eq!(request["method"], "order.place");
+        let params = request["params"].as_object().unwrap();
+        assert_eq!(params["apiKey"], credentials.api_key.as_str());
+        assert_eq!(params["newClientOrderId"], "cs1");
+        let payload: Vec<String> = params
+            .iter()
+            .filter(|(name, _)| *name != "signature")
+            .map(|(name, value)| format!("{}={}", name, value.as_str().unwrap()))
+            .collect();
+        assert_eq!(params["signature"], sign(&credentials.api_secret, &payload.join("&")));
+
+        // With the socket unreachable the order goes over REST
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}", closed.local_addr().unwrap());
+        drop(closed);
+        let (rest_url, rest_server) = serve_http(vec![("200 OK", ORDER)]).await;
+        let adapter = BinanceAdapter::new(config(rest_url, ws_url)).await.unwrap();
+        let order = adapter.place_order_ws(&credentials, &order_request()).await.unwrap();
+        assert_eq!(order.exchange_order_id, "7");
+        let requests = rest_server.await.unwrap();
+        assert!(requests[0].starts_with("POST /fapi/v1/order?"), "{:?}", requests);
+    }
+
+    #[tokio::test]
+    async fn test_best_price_is_streamed_once_the_feed_is_up() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}", listener.local_addr().unwrap());
+        let (subscribed_tx, subscribed) = tokio::sync::oneshot::channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            let frame = socket.next().await.unwrap().unwrap().into_text().unwrap();
+            subscribed_tx.send(frame).unwrap();
+            let ticker = r#"{"e":"bookTicker","u":400900217,"s":"BTCUSDT","b":"101","B":"2","a":"102","A":"3"}"#;
+            socket.send(Message::Text(ticker.to_string())).await.unwrap();
+            // Held open for the rest of the test
+            while socket.next().await.is_some() {}
+        });
+        let ticker = r#"{"symbol":"BTCUSDT","bidPrice":"99","askPrice":"100"}"#;
+        let (rest_url, _rest) = serve_http(vec![("200 OK", ticker), ("200 OK", ticker)]).await;
+        let adapter = BinanceAdapter::new(ExchangeConfig {
+            ws_url,
+            ..config(rest_url, String::new())
+        })
+        .await
+        .unwrap();
+
+        // Connected over REST alone, and once the stream is up
+        assert!(adapter.is_connected());
+
+        // The first read goes over REST and starts the stream
+        assert_eq!(adapter.get_best_price("BTCUSDT").await.unwrap(), (dec!(99), dec!(100)));
+        let subscribe: serde_json::Value = serde_json::from_str(&subscribed.await.unwrap()).unwrap();
+        assert_eq!(subscribe["method"], "SUBSCRIBE");
+        assert_eq!(subscribe["params"], serde_json::json!(["btcusdt@bookTicker"]));
+
+        let streamed = async {
+            loop {
+                if let Ok(quote) = adapter.get_best_price("BTCUSDT").await {
+                    if quote == (dec!(101), dec!(102)) {
+                        return;
+                    }
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(5), streamed).await.unwrap();
+        assert!(adapter.is_connected());
+    }
+
+    #[tokio::test]
+    async fn test_orders_are_read_from_the_user_data_stream() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::handshake::server::Request;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}", listener.local_addr().unwrap());
+        let (path_tx, path) = tokio::sync::oneshot::channel();
+        let (fill_tx, fill) = tokio::sync::oneshot::channel::<()>();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut path_tx = Some(path_tx);
+            // The error type is tungstenite's, not ours
+            #[allow(clippy::result_large_err)]
+            let callback = |request: &Request, response| {
+                path_tx.take().unwrap().send(request.uri().path().to_string()).unwrap();
+                Ok(response)
+            };
+            let mut socket = tokio_tungstenite::accept_hdr_async(stream, callback).await.unwrap();
+            fill.await.unwrap();
+            let update = r#"{"e":"ORDER_TRADE_UPDATE","E":3,"T":3,"o":{"s":"BTCUSDT","c":"cs1","S":"BUY","o":"LIMIT","f":"GTC","q":"1","p":"100","ap":"100","sp":"0","x":"TRADE","X":"FILLED","i":7,"l":"1","z":"1","L":"100","T":3}}"#;
+            socket.send(Message::Text(update.to_string())).await.unwrap();
+            // Held open for the rest of the test
+            while socket.next().await.is_some() {}
+        });
+        // The listen key is created alongside the first placement, so this
+        // body answers both requests in whichever order they arrive
+        let body = r#"{"listenKey":"key1","orderId":7,"symbol":"BTCUSDT","status":"NEW","clientOrderId":"cs1","price":"100","origQty":"1","executedQty":"0","avgPrice":"0","side":"BUY","type":"LIMIT","updateTime":1}"#;
+        let (rest_url, rest) = serve_http(vec![("200 OK", body), ("200 OK", body), ("200 OK", body)]).await;
+        let adapter = BinanceAdapter::new(ExchangeConfig {
+            ws_url,
+            ..config(rest_url, String::new())
+        })
+        .await
+        .unwrap();
+        let credentials = crate::exchange::mock::credentials();
+
+        // Placed before the stream is up, so not tracked by it
+        adapter.place_order(&credentials, &order_request()).await.unwrap();
+        assert_eq!(path.await.unwrap(), "/ws/key1");
+        let stream = adapter.user_stream(&credentials).unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while stream.session().is_none() {
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+
+        adapter.place_order(&credentials, &order_request()).await.unwrap();
+        let requests = rest.await.unwrap();
+        assert_eq!(requests.iter().filter(|r| r.starts_with("POST /fapi/v1/listenKey")).count(), 1, "{:?}", requests);
+
+        // REST is gone from here on, so reads can only be served by the stream
+        let order = adapter.get_order(&credentials, "BTCUSDT", "7").await.unwrap();
+        assert_eq!(order.status, OrderStatus::Open);
+        fill_tx.send(()).unwrap();
+        let filled = async {
+            loop {
+                let order = adapter.get_order(&credentials, "BTCUSDT", "7").await.unwrap();
+                if order.status == OrderStatus::Filled {
+                    return order;
+                }
+                tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+            }
+        };
+        let order = tokio::time::timeout(std::time::Duration::from_secs(5), filled).await.unwrap();
+        assert_eq!(order.filled_quantity, Decimal::ONE);
+        assert_eq!(order.avg_fill_price, Some(dec!(100)));
+        assert!(adapter.get_order(&credentials, "BTCUSDT", "8").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancelling_a_filled_order_reports_its_state() {
+        let filled = r#"{"orderId":7,"symbol":"BTCUSDT","status":"FILLED","clientOrderId":"cs1","price":"100","origQty":"1","executedQty":"1","avgPrice":"100","side":"BUY","type":"LIMIT","updateTime":2}"#;
+        let (url, server) = serve_http(vec![
+            ("400 Bad Request", r#"{"code":-2011,"msg":"Unknown order sent."}"#),
+            ("200 OK", filled),
+            ("400 Bad Request", r#"{"code":-1021,"msg":"Timestamp for this request is outside of the recvWindow."}"#),
+        ])
+        .await;
+        let adapter = BinanceAdapter::new(config(url, String::new())).await.unwrap();
+        let credentials = crate::exchange::mock::credentials();
+
+        let order = adapter.cancel_order(&credentials, "BTCUSDT", "7").await.unwrap();
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert_eq!(order.filled_quantity, Decimal::ONE);
+
+        // Other rejections are still errors
+        let err = adapter.cancel_order(&credentials, "BTCUSDT", "7").await.unwrap_err();
+        assert!(err.to_string().contains("-1021"), "{}", err);
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("DELETE /fapi/v1/order?"), "{:?}", requests);
+        assert!(requests[1].starts_with("GET /fapi/v1/order?"), "{:?}", requests);
+    }
+
+    #[tokio::test]
+    async fn test_gtd_orders_carry_their_expiry() {
+        let adapter = BinanceAdapter::new(config(String::new(), String::new())).await.unwrap();
+        let expire_at_ms = now_millis() + 3_600_000;
+        let request = OrderRequest {
+            time_in_force: TimeInForce::Gtd { expire_at_ms },
+            ..order_request()
+        };
+
+        let params = adapter.order_params(&crate::exchange::mock::credentials(), &request).unwrap();
+        assert!(params.contains(&("timeInForce", "GTD".to_string())));
+        assert!(params.contains(&("goodTillDate", expire_at_ms.to_string())));
+
+        // Binance turns down expiries under ten minutes out, so those are emulated
+        assert!(adapter.supports_gtd(expire_at_ms));
+        assert!(!adapter.supports_gtd(now_millis() + 60_000));
+    }
+
+    #[tokio::test]
+    async fn test_quote_market_orders_send_quote_order_qty() {
+        let adapter = BinanceAdapter::new(config(String::new(), String::new())).await.unwrap();
+        let credentials = crate::exchange::mock::credentials();
+        let market = OrderRequest {
+            order_type: OrderType::Market,
+            price: None,
+            quantity: dec!(250),
+            quantity_mode: QuantityMode::Quote,
+            ..order_request()
+        };
+
+        let params = adapter.order_params(&credentials, &market).unwrap();
+        assert!(params.contains(&("quoteOrderQty", "250".to_string())));
+        assert!(!params.iter().any(|(name, _)| *name == "quantity"));
+
+        let params = adapter.order_params(&credentials, &order_request()).unwrap();
+        assert!(params.contains(&("quantity", "1".to_string())));
+        assert!(!params.iter().any(|(name, _)| *name == "quoteOrderQty"));
+
+        // Limit orders are only sized in contracts
+        let limit = OrderRequest { quantity_mode: QuantityMode::Quote, ..order_request() };
+        assert!(adapter.order_params(&credentials, &limit).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_maintenance_responses_are_reported_as_maintenance() {
+        let (url, server) = serve_http(vec![
+            ("503 Service Unavailable", "<html><body>Service Unavailable</body></html>"),
+            ("503 Service Unavailable", ""),
+            ("200 OK", r#"{"serverTime":1700000000000}"#),
+            ("400 Bad Request", r#"{"code":-2019,"msg":"Margin is insufficient."}"#),
+        ])
+        .await;
+        let adapter = BinanceAdapter::new(config(url, String::new())).await.unwrap();
+        let credentials = crate::exchange::mock::credentials();
+
+        let err = adapter.place_order(&credentials, &order_request()).await.unwrap_err();
+        assert!(ExchangeError::is_maintenance(&err), "{:#}", err);
+        let err = adapter.get_server_time().await.unwrap_err();
+        assert!(ExchangeError::is_maintenance(&err), "{:#}", err);
+        assert_eq!(adapter.get_server_time().await.unwrap(), 1700000000000);
+
+        // Ordinary rejections are not
+        let err = adapter.place_order(&credentials, &order_request()).await.unwrap_err();
+        assert!(!ExchangeError::is_maintenance(&err), "{:#}", err);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_order_book_is_parsed_best_first() {
+        let (url, server) = serve_http(vec![(
+            "200 OK",
+            r#"{"lastUpdateId":1027024,"E":1589436922972,"T":1589436922959,"bids":[["4.00000000","431.00000000"],["3.99000000","9.00000000"],["3.98000000","1.00000000"]],"asks":[["4.00000200","12.00000000"],["4.01000000","18.00000000"],["4.02000000","5.00000000"]]}"#,
+        )])
+        .await;
+        let adapter = BinanceAdapter::new(config(url, String::new())).await.unwrap();
+
+        let book = adapter.get_order_book("BTCUSDT", 2).await.unwrap();
+
+        assert_eq!(book.bids.len(), 2);
+        assert_eq!(book.bids[0].price, Decimal::new(4, 0));
+        assert_eq!(book.bids[1].quantity, Decimal::new(9, 0));
+        assert_eq!(book.asks[0].price, Decimal::new(4000002, 6));
+        assert_eq!(book.asks[1].price, Decimal::new(401, 2));
+        assert_eq!(book.timestamp, 1589436922959);
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /fapi/v1/depth?symbol=BTCUSDT&limit=5"), "{:?}", requests);
+    }
+
+    #[tokio::test]
+    async fn test_reduce_only_rejection_is_reported_by_kind() {
+        let (url, server) = serve_http(vec![
+            ("400 Bad Request", r#"{"code":-2022,"msg":"ReduceOnly Order is rejected."}"#),
+            ("400 Bad Request", r#"{"code":-2019,"msg":"Margin is insufficient."}"#),
+            (
+                "400 Bad Request",
+                r#"{"code":-5022,"msg":"Due to the order could not be executed as maker, the Post Only order will be rejected."}"#,
+            ),
+            ("400 Bad Request", r#"{"code":-1021,"msg":"Timestamp for this request is outside of the recvWindow."}"#),
+        ])
+        .await;
+        let adapter = BinanceAdapter::new(config(url, String::new())).await.unwrap();
+        let credentials = crate::exchange::mock::credentials();
+        let request = OrderRequest {
+            reduce_only: true,
+            ..order_request()
+        };
+
+        let err = adapter.place_order(&credentials, &request).await.unwrap_err();
+        assert!(ExchangeError::is_reduce_only_rejected(&err), "{:#}", err);
+        assert!(err.to_string().contains("ReduceOnly Order is rejected"), "{}", err);
+
+        let err = adapter.place_order(&credentials, &request).await.unwrap_err();
+        assert!(!ExchangeError::is_reduce_only_rejected(&err), "{:#}", err);
+
+        let request = OrderRequest {
+            time_in_force: TimeInForce::PostOnly,
+            ..order_request()
+        };
+        let err = adapter.place_order(&credentials, &request).await.unwrap_err();
+        assert!(ExchangeError::is_post_only_rejected(&err), "{:#}", err);
+
+        let err = adapter.place_order(&credentials, &order_request()).await.unwrap_err();
+        assert!(ExchangeError::is_signature_expired(&err), "{:#}", err);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_listen_key_is_recreated_after_expiry_and_closed() {
+        let (url, server) = serve_http(vec![
+            ("200 OK", r#"{"listenKey":"first"}"#),
+            ("200 OK", "{}"),
+            ("400 Bad Request", r#"{"code":-1125,"msg":"This listenKey does not exist."}"#),
+            ("200 OK", r#"{"listenKey":"second"}"#),
+            ("200 OK", "{}"),
+        ])
+        .await;
+        let config = ExchangeConfig {
+            id: "binance".to_string(),
+            rest_url: url,
+            ws_url: String::new(),
+            trade_ws_url: None,
+            testnet: false,
+            maker_fee_bps: 0.0,
+            taker_fee_bps: 0.0,
+            trade_mode: None,
+            user_agent: None,
+            proxy: None,
+            log_raw_http: false,
+            ws_orders: false,
+            order_tag: None,
+            client_order_id_prefix: None,
+            adopt_duplicate_orders: false,
+        };
+        let credentials = crate::exchange::mock::credentials();
+        let manager = ListenKeyManager::new(&config, Client::new(), &credentials);
+        let mut key = manager.subscribe();
+
+        assert_eq!(manager.create().await.unwrap(), "first");
+        assert_eq!(key.borrow_and_update().as_deref(), Some("first"));
+        manager.keepalive().await.unwrap();
+        assert!(!key.has_changed().unwrap());
+
+        manager.keepalive().await.unwrap();
+        assert_eq!(key.borrow_and_update().as_deref(), Some("second"));
+
+        manager.close().await.unwrap();
+        assert_eq!(*key.borrow(), None);
+        // Already closed, nothing more is sent
+        manager.close().await.unwrap();
+
+        assert_eq!(
+            server.await.unwrap(),
+            [
+                "POST /fapi/v1/listenKey HTTP/1.1",
+                "PUT /fapi/v1/listenKey HTTP/1.1",
+                "PUT /fapi/v1/listenKey HTTP/1.1",
+                "POST /fapi/v1/listenKey HTTP/1.1",
+                "DELETE /fapi/v1/listenKey HTTP/1.1",
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_order_timestamp_is_in_millis() {
+        let order = r#"{"orderId":7,"symbol":"BTCUSDT","status":"FILLED","clientOrderId":"cs1","price":"100","origQty":"1","executedQty":"1","avgPrice":"100","side":"BUY","type":"LIMIT","updateTime":1566818724722}"#;
+        let (url, _server) = serve_http(vec![("200 OK", order)]).await;
+        let adapter = BinanceAdapter::new(config(url, String::new())).await.unwrap();
+
+        let order = adapter
+            .get_order(&crate::exchange::mock::credentials(), "BTCUSDT", "7")
+            .await
+            .unwrap();
+        assert_eq!(order.timestamp, 1566818724722);
+    }
+}
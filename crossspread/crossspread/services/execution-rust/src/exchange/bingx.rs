@@ -10,7 +10,11 @@ use sha2::Sha256;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{debug, info};
 
-use super::{Credentials, ExchangeAdapter, OrderRequest, OrderResponse, OrderStatus, OrderType, Side};
+use super::{
+    canonical_from_separated, epoch_millis, parse_json, parse_level_rows, Credentials, ExchangeAdapter, OrderBook, OrderRequest,
+    OrderResponse, OrderStatus, OrderType, Side, TimeInForce,
+};
+use super::raw_http::SendTraced;
 use crate::config::ExchangeConfig;
 
 type HmacSha256 = Hmac<Sha256>;
@@ -22,9 +26,7 @@ pub struct BingxAdapter {
 
 impl BingxAdapter {
     pub async fn new(config: ExchangeConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .build()?;
+        let client = super::http_client(&config)?;
 
         Ok(Self { config, client })
     }
@@ -35,13 +37,6 @@ impl BingxAdapter {
             .unwrap()
             .as_millis() as i64
     }
-
-    fn sign(&self, secret: &str, query: &str) -> String {
-        let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
-            .expect("HMAC can take key of any size");
-        mac.update(query.as_bytes());
-        hex::encode(mac.finalize().into_bytes())
-    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -69,6 +64,7 @@ struct BingxOrder {
     price: Option<String>,
     #[serde(rename = "origQty")]
     orig_qty: String,
+    /// Filled quantity as reported by BingX
     #[serde(rename = "executedQty")]
     executed_qty: String,
     #[serde(rename = "avgPrice")]
@@ -83,15 +79,24 @@ impl ExchangeAdapter for BingxAdapter {
         "bingx"
     }
 
+    fn to_native_symbol(&self, base: &str, quote: &str) -> String {
+        format!("{}-{}", base, quote)
+    }
+
+    fn to_canonical_symbol(&self, native: &str) -> Option<String> {
+        canonical_from_separated(native, '-')
+    }
+
     async fn place_order(
         &self,
         credentials: &Credentials,
         request: &OrderRequest,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(&request.symbol);
         let timestamp = Self::timestamp();
         
         let mut params = vec![
-            ("symbol", request.symbol.clone()),
+            ("symbol", symbol.clone()),
             ("side", match request.side {
                 Side::Buy => "BUY".to_string(),
                 Side::Sell => "SELL".to_string(),
@@ -106,6 +111,11 @@ impl ExchangeAdapter for BingxAdapter {
 
         if let Some(price) = request.price {
             params.push(("price", price.to_string()));
+            params.push(("timeInForce", match request.time_in_force {
+                TimeInForce::Gtc | TimeInForce::Gtd { .. } => "GTC".to_string(),
+                TimeInForce::Ioc => "IOC".to_string(),
+                TimeInForce::PostOnly => "PostOnly".to_string(),
+            }));
         }
         if !request.client_order_id.is_empty() {
             params.push(("clientOrderId", request.client_order_id.clone()));
@@ -117,17 +127,17 @@ impl ExchangeAdapter for BingxAdapter {
             .collect::<Vec<_>>()
             .join("&");
 
-        let signature = self.sign(&credentials.api_secret, &query_string);
+        let signature = sign(&credentials.api_secret, &query_string);
         let final_query = format!("{}&signature={}", query_string, signature);
 
-        debug!("Placing BingX order: {}", request.symbol);
+        debug!("Placing BingX order: {}", symbol);
 
         let url = format!("{}/openApi/swap/v2/trade/order?{}", self.config.rest_url, final_query);
         let response = self.client
             .post(&url)
             .header("X-BX-APIKEY", &credentials.api_key)
             .header("Content-Type", "application/json")
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await
             .context("Failed to send order request")?;
 
@@ -138,7 +148,7 @@ impl ExchangeAdapter for BingxAdapter {
             anyhow::bail!("BingX order failed: {} - {}", status, body);
         }
 
-        let resp: BingxResponse<BingxOrderResponse> = serde_json::from_str(&body)
+        let resp: BingxResponse<BingxOrderResponse> = parse_json(&body)
             .context("Failed to parse order response")?;
 
         if resp.code != 0 {
@@ -152,7 +162,7 @@ impl ExchangeAdapter for BingxAdapter {
         Ok(OrderResponse {
             exchange_order_id: order.order_id,
             client_order_id: order.client_order_id.unwrap_or_default(),
-            symbol: order.symbol,
+            symbol: self.canonical_symbol(&order.symbol),
             side: match order.side.as_str() {
                 "BUY" => Side::Buy,
                 _ => Side::Sell,
@@ -166,7 +176,7 @@ impl ExchangeAdapter for BingxAdapter {
             filled_quantity: order.executed_qty.parse().unwrap_or_default(),
             avg_fill_price: order.avg_price.and_then(|p| p.parse().ok()),
             status: parse_bingx_status(&order.status),
-            timestamp: order.time,
+            timestamp: epoch_millis(order.time),
         })
     }
 
@@ -176,28 +186,29 @@ impl ExchangeAdapter for BingxAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
         let timestamp = Self::timestamp();
         
         let query_string = format!("orderId={}&symbol={}&timestamp={}", order_id, symbol, timestamp);
-        let signature = self.sign(&credentials.api_secret, &query_string);
+        let signature = sign(&credentials.api_secret, &query_string);
         let final_query = format!("{}&signature={}", query_string, signature);
 
         let url = format!("{}/openApi/swap/v2/trade/order?{}", self.config.rest_url, final_query);
         let response = self.client
             .delete(&url)
             .header("X-BX-APIKEY", &credentials.api_key)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
         let body = response.text().await?;
-        let resp: BingxResponse<BingxOrderResponse> = serde_json::from_str(&body)?;
+        let resp: BingxResponse<BingxOrderResponse> = parse_json(&body)?;
 
         let order = resp.data.ok_or_else(|| anyhow::anyhow!("No order data"))?.order;
 
         Ok(OrderResponse {
             exchange_order_id: order.order_id,
             client_order_id: order.client_order_id.unwrap_or_default(),
-            symbol: order.symbol,
+            symbol: self.canonical_symbol(&order.symbol),
             side: match order.side.as_str() {
                 "BUY" => Side::Buy,
                 _ => Side::Sell,
@@ -208,7 +219,7 @@ impl ExchangeAdapter for BingxAdapter {
             filled_quantity: order.executed_qty.parse().unwrap_or_default(),
             avg_fill_price: order.avg_price.and_then(|p| p.parse().ok()),
             status: OrderStatus::Cancelled,
-            timestamp: order.time,
+            timestamp: epoch_millis(order.time),
         })
     }
 
@@ -218,49 +229,63 @@ impl ExchangeAdapter for BingxAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
         let timestamp = Self::timestamp();
         
         let query_string = format!("orderId={}&symbol={}&timestamp={}", order_id, symbol, timestamp);
-        let signature = self.sign(&credentials.api_secret, &query_string);
+        let signature = sign(&credentials.api_secret, &query_string);
         let final_query = format!("{}&signature={}", query_string, signature);
 
         let url = format!("{}/openApi/swap/v2/trade/order?{}", self.config.rest_url, final_query);
         let response = self.client
             .get(&url)
             .header("X-BX-APIKEY", &credentials.api_key)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
         let body = response.text().await?;
-        let resp: BingxResponse<BingxOrderResponse> = serde_json::from_str(&body)?;
+        let resp: BingxResponse<BingxOrderResponse> = parse_json(&body)?;
 
         let order = resp.data.ok_or_else(|| anyhow::anyhow!("No order data"))?.order;
 
-        Ok(OrderResponse {
-            exchange_order_id: order.order_id,
-            client_order_id: order.client_order_id.unwrap_or_default(),
-            symbol: order.symbol,
-            side: match order.side.as_str() {
-                "BUY" => Side::Buy,
-                _ => Side::Sell,
-            },
-            order_type: match order.order_type.as_str() {
-                "LIMIT" => OrderType::Limit,
-                _ => OrderType::Market,
-            },
-            price: order.price.and_then(|p| p.parse().ok()),
-            quantity: order.orig_qty.parse().unwrap_or_default(),
-            filled_quantity: order.executed_qty.parse().unwrap_or_default(),
-            avg_fill_price: order.avg_price.and_then(|p| p.parse().ok()),
-            status: parse_bingx_status(&order.status),
-            timestamp: order.time,
-        })
+        Ok(order_response(order).canonical(self))
+    }
+
+    async fn get_open_orders(&self, credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        let symbol = self.native_symbol(symbol);
+        let timestamp = Self::timestamp();
+
+        let query_string = format!("symbol={}&timestamp={}", symbol, timestamp);
+        let signature = sign(&credentials.api_secret, &query_string);
+        let final_query = format!("{}&signature={}", query_string, signature);
+
+        let url = format!("{}/openApi/swap/v2/trade/openOrders?{}", self.config.rest_url, final_query);
+        let response = self.client
+            .get(&url)
+            .header("X-BX-APIKEY", &credentials.api_key)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        #[derive(Deserialize)]
+        struct OpenOrders {
+            orders: Vec<BingxOrder>,
+        }
+
+        let body = response.text().await?;
+        let resp: BingxResponse<OpenOrders> = parse_json(&body)?;
+        if resp.code != 0 {
+            anyhow::bail!("BingX open orders error: {} - {}", resp.code, resp.msg.unwrap_or_default());
+        }
+
+        let listed = resp.data.map(|d| d.orders).unwrap_or_default();
+        Ok(listed.into_iter().map(|order| order_response(order).canonical(self)).collect())
     }
 
     async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
+        let symbol = self.native_symbol(symbol);
         let url = format!("{}/openApi/swap/v2/quote/ticker?symbol={}", self.config.rest_url, symbol);
         
-        let response = self.client.get(&url).send().await?;
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
         let body = response.text().await?;
         
         #[derive(Deserialize)]
@@ -271,7 +296,7 @@ impl ExchangeAdapter for BingxAdapter {
             ask_price: String,
         }
         
-        let resp: BingxResponse<TickerData> = serde_json::from_str(&body)?;
+        let resp: BingxResponse<TickerData> = parse_json(&body)?;
         let ticker = resp.data.ok_or_else(|| anyhow::anyhow!("No ticker data"))?;
 
         Ok((
@@ -280,11 +305,71 @@ impl ExchangeAdapter for BingxAdapter {
         ))
     }
 
+    async fn get_order_book(&self, symbol: &str, depth: usize) -> Result<OrderBook> {
+        let symbol = self.native_symbol(symbol);
+        // Only these limits are accepted, so request the smallest that covers `depth`
+        let limit = [5, 10, 20, 50, 100, 500, 1000]
+            .into_iter()
+            .find(|&limit| limit >= depth)
+            .unwrap_or(1000);
+        let url = format!(
+            "{}/openApi/swap/v2/quote/depth?symbol={}&limit={}",
+            self.config.rest_url, symbol, limit
+        );
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        // Asks are listed from the highest price down
+        #[derive(Deserialize)]
+        struct Depth {
+            bids: Vec<Vec<serde_json::Value>>,
+            asks: Vec<Vec<serde_json::Value>>,
+            #[serde(rename = "T")]
+            time: i64,
+        }
+
+        let resp: BingxResponse<Depth> = parse_json(&body)?;
+        if resp.code != 0 {
+            anyhow::bail!("BingX order book error: {} - {}", resp.code, resp.msg.unwrap_or_default());
+        }
+        let book = resp.data.ok_or_else(|| anyhow::anyhow!("No order book data"))?;
+
+        Ok(OrderBook::from_levels(
+            parse_level_rows(&book.bids)?,
+            parse_level_rows(&book.asks)?,
+            depth,
+            book.time,
+        ))
+    }
+
     fn is_connected(&self) -> bool {
         true
     }
 }
 
+fn order_response(order: BingxOrder) -> OrderResponse {
+    OrderResponse {
+        exchange_order_id: order.order_id,
+        client_order_id: order.client_order_id.unwrap_or_default(),
+        symbol: order.symbol,
+        side: match order.side.as_str() {
+            "BUY" => Side::Buy,
+            _ => Side::Sell,
+        },
+        order_type: match order.order_type.as_str() {
+            "LIMIT" => OrderType::Limit,
+            _ => OrderType::Market,
+        },
+        price: order.price.and_then(|p| p.parse().ok()),
+        quantity: order.orig_qty.parse().unwrap_or_default(),
+        filled_quantity: order.executed_qty.parse().unwrap_or_default(),
+        avg_fill_price: order.avg_price.and_then(|p| p.parse().ok()),
+        status: parse_bingx_status(&order.status),
+        timestamp: epoch_millis(order.time),
+    }
+}
+
 fn parse_bingx_status(status: &str) -> OrderStatus {
     match status {
         "NEW" | "PENDING" => OrderStatus::Open,
@@ -294,3 +379,52 @@ fn parse_bingx_status(status: &str) -> OrderStatus {
         _ => OrderStatus::Pending,
     }
 }
+
+/// HMAC-SHA256 of the sorted query string, hex
+pub fn sign(secret: &str, query: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(query.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{credentials, exchange_config, serve_http};
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn test_order_book_is_parsed_best_first() {
+        let (url, server) = serve_http(vec![(
+            "200 OK",
+            r#"{"code":0,"msg":"","data":{"T":1702719530104,"bids":[["42500.1","1.2"],["42500.0","0.5"]],"asks":[["42502.0","3"],["42501.5","0.8"],["42501.2","0.1"]],"bidsCoin":[],"asksCoin":[]}}"#,
+        )])
+        .await;
+        let adapter = BingxAdapter::new(exchange_config("bingx", url)).await.unwrap();
+
+        let book = adapter.get_order_book("BTC-USDT", 2).await.unwrap();
+
+        assert_eq!(book.bids[0].price, dec!(42500.1));
+        assert_eq!(book.bids[1].quantity, dec!(0.5));
+        // Reordered lowest first and cut to depth
+        let asks: Vec<_> = book.asks.iter().map(|l| l.price).collect();
+        assert_eq!(asks, [dec!(42501.2), dec!(42501.5)]);
+        assert_eq!(book.timestamp, 1702719530104);
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /openApi/swap/v2/quote/depth?symbol=BTC-USDT&limit=5"), "{:?}", requests);
+    }
+
+    #[tokio::test]
+    async fn test_order_timestamp_is_in_millis() {
+        let (url, _server) = serve_http(vec![(
+            "200 OK",
+            r#"{"code":0,"msg":"","data":{"order":{"orderId":"1","symbol":"BTC-USDT","clientOrderId":"cs1","side":"BUY","type":"LIMIT","price":"100","origQty":"2","executedQty":"2","avgPrice":"100","status":"FILLED","time":1702719530104}}}"#,
+        )])
+        .await;
+        let adapter = BingxAdapter::new(exchange_config("bingx", url)).await.unwrap();
+
+        let order = adapter.get_order(&credentials(), "BTC-USDT", "1").await.unwrap();
+        assert_eq!(order.timestamp, 1702719530104);
+    }
+}
@@ -8,26 +8,45 @@ use reqwest::Client;
 use rust_decimal::Decimal;
 use serde::Deserialize;
 use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::RwLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{debug, info};
 
-use super::{Credentials, ExchangeAdapter, OrderRequest, OrderResponse, OrderStatus, OrderType, Side};
+use super::{
+    epoch_millis, parse_json, parse_level_rows, position_side, Credentials, ExchangeAdapter, OrderBook,
+    OrderRequest, OrderResponse, OrderStatus, OrderType, QuoteAssets, Side, TimeInForce,
+};
+use super::raw_http::SendTraced;
 use crate::config::ExchangeConfig;
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// Most orders one page of pending orders holds
+const PENDING_PAGE_SIZE: usize = 100;
+
 pub struct BitgetAdapter {
     config: ExchangeConfig,
     client: Client,
+    /// Hedge mode per API key, as last read or set
+    hedge_mode: RwLock<HashMap<String, bool>>,
+    quote_assets: QuoteAssets,
 }
 
 impl BitgetAdapter {
     pub async fn new(config: ExchangeConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .build()?;
+        let client = super::http_client(&config)?;
 
-        Ok(Self { config, client })
+        Ok(Self {
+            config,
+            client,
+            hedge_mode: RwLock::new(HashMap::new()),
+            quote_assets: QuoteAssets::default(),
+        })
+    }
+
+    fn is_hedge_mode(&self, api_key: &str) -> bool {
+        self.hedge_mode.read().unwrap().get(api_key).copied().unwrap_or(false)
     }
 
     fn timestamp() -> String {
@@ -37,14 +56,6 @@ impl BitgetAdapter {
             .as_millis()
             .to_string()
     }
-
-    fn sign(&self, secret: &str, timestamp: &str, method: &str, path: &str, body: &str) -> String {
-        let prehash = format!("{}{}{}{}", timestamp, method.to_uppercase(), path, body);
-        let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
-            .expect("HMAC can take key of any size");
-        mac.update(prehash.as_bytes());
-        STANDARD.encode(mac.finalize().into_bytes())
-    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -66,10 +77,13 @@ struct BitgetOrderData {
     order_type: String,
     price: String,
     size: String,
-    #[serde(rename = "filledQty")]
+    /// Absent until the first fill. Pending order lists name it `baseVolume`.
+    #[serde(rename = "filledQty", alias = "baseVolume")]
     filled_qty: Option<String>,
     #[serde(rename = "priceAvg")]
     price_avg: Option<String>,
+    /// `status` in pending order lists
+    #[serde(alias = "status")]
     state: String,
     #[serde(rename = "cTime")]
     c_time: String,
@@ -81,38 +95,49 @@ impl ExchangeAdapter for BitgetAdapter {
         "bitget"
     }
 
+    fn to_native_symbol(&self, base: &str, quote: &str) -> String {
+        format!("{}{}", base, quote)
+    }
+
+    fn to_canonical_symbol(&self, native: &str) -> Option<String> {
+        self.quote_assets.canonical(native)
+    }
+
+    async fn load_quote_assets(&self) -> Result<()> {
+        let url = format!("{}/api/v2/mix/market/contracts?productType=USDT-FUTURES", self.config.rest_url);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct Contract {
+            #[serde(rename = "quoteCoin")]
+            quote_coin: String,
+        }
+
+        let resp: BitgetResponse<Vec<Contract>> = parse_json(&body)?;
+        if resp.code != "00000" {
+            anyhow::bail!("Bitget contracts error: {} - {}", resp.code, resp.msg);
+        }
+        self.quote_assets.set(resp.data.unwrap_or_default().into_iter().map(|c| c.quote_coin));
+        Ok(())
+    }
+
     async fn place_order(
         &self,
         credentials: &Credentials,
         request: &OrderRequest,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(&request.symbol);
         let timestamp = Self::timestamp();
         let path = "/api/v2/mix/order/place-order";
         
-        let body = serde_json::json!({
-            "symbol": request.symbol,
-            "productType": "USDT-FUTURES",
-            "marginMode": "crossed",
-            "marginCoin": "USDT",
-            "side": match request.side {
-                Side::Buy => "buy",
-                Side::Sell => "sell",
-            },
-            "tradeSide": "open",
-            "orderType": match request.order_type {
-                OrderType::Limit => "limit",
-                OrderType::Market => "market",
-            },
-            "size": request.quantity.to_string(),
-            "price": request.price.map(|p| p.to_string()),
-            "clientOid": request.client_order_id,
-            "reduceOnly": request.reduce_only,
-        }).to_string();
+        let body = order_body(&symbol, request, self.is_hedge_mode(&credentials.api_key)).to_string();
 
-        let signature = self.sign(&credentials.api_secret, &timestamp, "POST", path, &body);
+        let signature = sign(&credentials.api_secret, &timestamp, "POST", path, &body);
         let passphrase = credentials.passphrase.as_deref().unwrap_or("");
 
-        debug!("Placing Bitget order: {}", request.symbol);
+        debug!("Placing Bitget order: {}", symbol);
 
         let url = format!("{}{}", self.config.rest_url, path);
         let response = self.client
@@ -123,7 +148,7 @@ impl ExchangeAdapter for BitgetAdapter {
             .header("ACCESS-PASSPHRASE", passphrase)
             .header("Content-Type", "application/json")
             .body(body)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await
             .context("Failed to send order request")?;
 
@@ -134,7 +159,7 @@ impl ExchangeAdapter for BitgetAdapter {
             anyhow::bail!("Bitget order failed: {} - {}", status, body);
         }
 
-        let resp: BitgetResponse<BitgetOrderData> = serde_json::from_str(&body)
+        let resp: BitgetResponse<BitgetOrderData> = parse_json(&body)
             .context("Failed to parse order response")?;
 
         if resp.code != "00000" {
@@ -148,7 +173,7 @@ impl ExchangeAdapter for BitgetAdapter {
         Ok(OrderResponse {
             exchange_order_id: order.order_id,
             client_order_id: order.client_oid.unwrap_or_default(),
-            symbol: order.symbol,
+            symbol: self.canonical_symbol(&order.symbol),
             side: match order.side.as_str() {
                 "buy" => Side::Buy,
                 _ => Side::Sell,
@@ -162,7 +187,7 @@ impl ExchangeAdapter for BitgetAdapter {
             filled_quantity: order.filled_qty.and_then(|s| s.parse().ok()).unwrap_or_default(),
             avg_fill_price: order.price_avg.and_then(|s| s.parse().ok()),
             status: parse_bitget_status(&order.state),
-            timestamp: order.c_time.parse().unwrap_or(0),
+            timestamp: epoch_millis(order.c_time.parse().unwrap_or(0)),
         })
     }
 
@@ -172,6 +197,7 @@ impl ExchangeAdapter for BitgetAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
         let timestamp = Self::timestamp();
         let path = "/api/v2/mix/order/cancel-order";
         
@@ -181,7 +207,7 @@ impl ExchangeAdapter for BitgetAdapter {
             "orderId": order_id,
         }).to_string();
 
-        let signature = self.sign(&credentials.api_secret, &timestamp, "POST", path, &body);
+        let signature = sign(&credentials.api_secret, &timestamp, "POST", path, &body);
         let passphrase = credentials.passphrase.as_deref().unwrap_or("");
 
         let url = format!("{}{}", self.config.rest_url, path);
@@ -193,18 +219,18 @@ impl ExchangeAdapter for BitgetAdapter {
             .header("ACCESS-PASSPHRASE", passphrase)
             .header("Content-Type", "application/json")
             .body(body)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
         let body = response.text().await?;
-        let resp: BitgetResponse<BitgetOrderData> = serde_json::from_str(&body)?;
+        let resp: BitgetResponse<BitgetOrderData> = parse_json(&body)?;
 
         let order = resp.data.ok_or_else(|| anyhow::anyhow!("No order data"))?;
 
         Ok(OrderResponse {
             exchange_order_id: order.order_id,
             client_order_id: order.client_oid.unwrap_or_default(),
-            symbol: order.symbol,
+            symbol: self.canonical_symbol(&order.symbol),
             side: match order.side.as_str() {
                 "buy" => Side::Buy,
                 _ => Side::Sell,
@@ -215,7 +241,7 @@ impl ExchangeAdapter for BitgetAdapter {
             filled_quantity: order.filled_qty.and_then(|s| s.parse().ok()).unwrap_or_default(),
             avg_fill_price: order.price_avg.and_then(|s| s.parse().ok()),
             status: OrderStatus::Cancelled,
-            timestamp: order.c_time.parse().unwrap_or(0),
+            timestamp: epoch_millis(order.c_time.parse().unwrap_or(0)),
         })
     }
 
@@ -225,10 +251,11 @@ impl ExchangeAdapter for BitgetAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
         let timestamp = Self::timestamp();
         let path = format!("/api/v2/mix/order/detail?symbol={}&productType=USDT-FUTURES&orderId={}", symbol, order_id);
         
-        let signature = self.sign(&credentials.api_secret, &timestamp, "GET", &path, "");
+        let signature = sign(&credentials.api_secret, &timestamp, "GET", &path, "");
         let passphrase = credentials.passphrase.as_deref().unwrap_or("");
 
         let url = format!("{}{}", self.config.rest_url, path);
@@ -238,40 +265,77 @@ impl ExchangeAdapter for BitgetAdapter {
             .header("ACCESS-SIGN", &signature)
             .header("ACCESS-TIMESTAMP", &timestamp)
             .header("ACCESS-PASSPHRASE", passphrase)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
         let body = response.text().await?;
-        let resp: BitgetResponse<BitgetOrderData> = serde_json::from_str(&body)?;
+        let resp: BitgetResponse<BitgetOrderData> = parse_json(&body)?;
 
         let order = resp.data.ok_or_else(|| anyhow::anyhow!("No order data"))?;
 
-        Ok(OrderResponse {
-            exchange_order_id: order.order_id,
-            client_order_id: order.client_oid.unwrap_or_default(),
-            symbol: order.symbol,
-            side: match order.side.as_str() {
-                "buy" => Side::Buy,
-                _ => Side::Sell,
-            },
-            order_type: match order.order_type.as_str() {
-                "limit" => OrderType::Limit,
-                _ => OrderType::Market,
-            },
-            price: order.price.parse().ok(),
-            quantity: order.size.parse().unwrap_or_default(),
-            filled_quantity: order.filled_qty.and_then(|s| s.parse().ok()).unwrap_or_default(),
-            avg_fill_price: order.price_avg.and_then(|s| s.parse().ok()),
-            status: parse_bitget_status(&order.state),
-            timestamp: order.c_time.parse().unwrap_or(0),
-        })
+        Ok(order_response(order).canonical(self))
+    }
+
+    // Paged backwards from the newest order, each page ending at `endId`
+    async fn get_open_orders(&self, credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        let symbol = self.native_symbol(symbol);
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct PendingOrders {
+            entrusted_list: Option<Vec<BitgetOrderData>>,
+            end_id: Option<String>,
+        }
+
+        let mut orders = Vec::new();
+        let mut before = String::new();
+        loop {
+            let timestamp = Self::timestamp();
+            let mut path = format!(
+                "/api/v2/mix/order/orders-pending?symbol={}&productType=USDT-FUTURES&limit={}",
+                symbol, PENDING_PAGE_SIZE
+            );
+            if !before.is_empty() {
+                path.push_str(&format!("&idLessThan={}", before));
+            }
+
+            let signature = sign(&credentials.api_secret, &timestamp, "GET", &path, "");
+            let passphrase = credentials.passphrase.as_deref().unwrap_or("");
+
+            let url = format!("{}{}", self.config.rest_url, path);
+            let response = self.client
+                .get(&url)
+                .header("ACCESS-KEY", &credentials.api_key)
+                .header("ACCESS-SIGN", &signature)
+                .header("ACCESS-TIMESTAMP", &timestamp)
+                .header("ACCESS-PASSPHRASE", passphrase)
+                .send_traced(self.config.log_raw_http)
+                .await?;
+
+            let body = response.text().await?;
+            let resp: BitgetResponse<PendingOrders> = parse_json(&body)?;
+            if resp.code != "00000" {
+                anyhow::bail!("Bitget open orders error: {} - {}", resp.code, resp.msg);
+            }
+
+            let Some(page) = resp.data else { break };
+            let listed = page.entrusted_list.unwrap_or_default();
+            let last = listed.len() < PENDING_PAGE_SIZE;
+            orders.extend(listed.into_iter().map(|order| order_response(order).canonical(self)));
+            match page.end_id {
+                Some(end_id) if !last => before = end_id,
+                _ => break,
+            }
+        }
+        Ok(orders)
     }
 
     async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
+        let symbol = self.native_symbol(symbol);
         let url = format!("{}/api/v2/mix/market/ticker?symbol={}&productType=USDT-FUTURES", 
             self.config.rest_url, symbol);
         
-        let response = self.client.get(&url).send().await?;
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
         let body = response.text().await?;
         
         #[derive(Deserialize)]
@@ -282,7 +346,7 @@ impl ExchangeAdapter for BitgetAdapter {
             best_ask: String,
         }
         
-        let resp: BitgetResponse<Vec<Ticker>> = serde_json::from_str(&body)?;
+        let resp: BitgetResponse<Vec<Ticker>> = parse_json(&body)?;
         let tickers = resp.data.ok_or_else(|| anyhow::anyhow!("No ticker data"))?;
         let ticker = tickers.into_iter().next()
             .ok_or_else(|| anyhow::anyhow!("No ticker"))?;
@@ -293,17 +357,334 @@ impl ExchangeAdapter for BitgetAdapter {
         ))
     }
 
+    async fn get_order_book(&self, symbol: &str, depth: usize) -> Result<OrderBook> {
+        let symbol = self.native_symbol(symbol);
+        // Only these limits are accepted, so request the smallest that covers `depth`
+        let limit = [1, 5, 15, 50]
+            .into_iter()
+            .find(|&limit| limit >= depth)
+            .map_or_else(|| "max".to_string(), |limit| limit.to_string());
+        let url = format!(
+            "{}/api/v2/mix/market/merge-depth?symbol={}&productType=USDT-FUTURES&limit={}",
+            self.config.rest_url, symbol, limit
+        );
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct Depth {
+            bids: Vec<Vec<serde_json::Value>>,
+            asks: Vec<Vec<serde_json::Value>>,
+            ts: String,
+        }
+
+        let resp: BitgetResponse<Depth> = parse_json(&body)?;
+        if resp.code != "00000" {
+            anyhow::bail!("Bitget order book error: {} - {}", resp.code, resp.msg);
+        }
+        let book = resp.data.ok_or_else(|| anyhow::anyhow!("No order book data"))?;
+
+        Ok(OrderBook::from_levels(
+            parse_level_rows(&book.bids)?,
+            parse_level_rows(&book.asks)?,
+            depth,
+            book.ts.parse()?,
+        ))
+    }
+
     fn is_connected(&self) -> bool {
         true
     }
+
+    async fn get_position_mode(&self, credentials: &Credentials) -> Result<bool> {
+        let timestamp = Self::timestamp();
+        let path = "/api/v2/mix/account/accounts?productType=USDT-FUTURES";
+
+        let signature = sign(&credentials.api_secret, &timestamp, "GET", path, "");
+        let passphrase = credentials.passphrase.as_deref().unwrap_or("");
+
+        let url = format!("{}{}", self.config.rest_url, path);
+        let response = self.client
+            .get(&url)
+            .header("ACCESS-KEY", &credentials.api_key)
+            .header("ACCESS-SIGN", &signature)
+            .header("ACCESS-TIMESTAMP", &timestamp)
+            .header("ACCESS-PASSPHRASE", passphrase)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct Account {
+            #[serde(rename = "marginCoin")]
+            margin_coin: String,
+            #[serde(rename = "posMode")]
+            pos_mode: String,
+        }
+
+        let resp: BitgetResponse<Vec<Account>> = parse_json(&body)?;
+        if resp.code != "00000" {
+            anyhow::bail!("Bitget position mode query failed: {} - {}", resp.code, resp.msg);
+        }
+        let account = resp
+            .data
+            .unwrap_or_default()
+            .into_iter()
+            .find(|account| account.margin_coin == "USDT")
+            .ok_or_else(|| anyhow::anyhow!("No USDT futures account"))?;
+
+        let hedge = account.pos_mode == "hedge_mode";
+        self.hedge_mode
+            .write()
+            .unwrap()
+            .insert(credentials.api_key.clone(), hedge);
+        Ok(hedge)
+    }
+
+    async fn set_position_mode(&self, credentials: &Credentials, hedge: bool) -> Result<()> {
+        let timestamp = Self::timestamp();
+        let path = "/api/v2/mix/account/set-position-mode";
+
+        let body = serde_json::json!({
+            "productType": "USDT-FUTURES",
+            "posMode": if hedge { "hedge_mode" } else { "one_way_mode" },
+        }).to_string();
+
+        let signature = sign(&credentials.api_secret, &timestamp, "POST", path, &body);
+        let passphrase = credentials.passphrase.as_deref().unwrap_or("");
+
+        let url = format!("{}{}", self.config.rest_url, path);
+        let response = self.client
+            .post(&url)
+            .header("ACCESS-KEY", &credentials.api_key)
+            .header("ACCESS-SIGN", &signature)
+            .header("ACCESS-TIMESTAMP", &timestamp)
+            .header("ACCESS-PASSPHRASE", passphrase)
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let body = response.text().await?;
+        let resp: BitgetResponse<serde_json::Value> = parse_json(&body)?;
+        if resp.code != "00000" {
+            anyhow::bail!("Bitget position mode change failed: {} - {}", resp.code, resp.msg);
+        }
+
+        info!("Bitget position mode set to {}", if hedge { "hedge" } else { "one-way" });
+        self.hedge_mode
+            .write()
+            .unwrap()
+            .insert(credentials.api_key.clone(), hedge);
+        Ok(())
+    }
+}
+
+/// Body of an order on `symbol`. In hedge mode `tradeSide` says whether the
+/// order opens or closes, and `side` names the position it acts on, so a
+/// reduce-only sell closes the long with `buy`/`close`. One-way mode takes
+/// neither and goes by `reduceOnly` instead.
+fn order_body(symbol: &str, request: &OrderRequest, hedge: bool) -> serde_json::Value {
+    let side = if hedge {
+        position_side(request.side, request.reduce_only)
+    } else {
+        request.side
+    };
+    let mut body = serde_json::json!({
+        "symbol": symbol,
+        "productType": "USDT-FUTURES",
+        "marginMode": "crossed",
+        "marginCoin": "USDT",
+        "side": match side {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        },
+        "orderType": match request.order_type {
+            OrderType::Limit => "limit",
+            OrderType::Market => "market",
+        },
+        "size": request.quantity.to_string(),
+        "price": request.price.map(|p| p.to_string()),
+        "force": match request.time_in_force {
+            TimeInForce::Gtc | TimeInForce::Gtd { .. } => "gtc",
+            TimeInForce::Ioc => "ioc",
+            TimeInForce::PostOnly => "post_only",
+        },
+        "clientOid": request.client_order_id,
+    });
+    if hedge {
+        body["tradeSide"] = (if request.reduce_only { "close" } else { "open" }).into();
+    } else {
+        body["reduceOnly"] = (if request.reduce_only { "YES" } else { "NO" }).into();
+    }
+    body
+}
+
+fn order_response(order: BitgetOrderData) -> OrderResponse {
+    OrderResponse {
+        exchange_order_id: order.order_id,
+        client_order_id: order.client_oid.unwrap_or_default(),
+        symbol: order.symbol,
+        side: match order.side.as_str() {
+            "buy" => Side::Buy,
+            _ => Side::Sell,
+        },
+        order_type: match order.order_type.as_str() {
+            "limit" => OrderType::Limit,
+            _ => OrderType::Market,
+        },
+        price: order.price.parse().ok(),
+        quantity: order.size.parse().unwrap_or_default(),
+        filled_quantity: order.filled_qty.and_then(|s| s.parse().ok()).unwrap_or_default(),
+        avg_fill_price: order.price_avg.and_then(|s| s.parse().ok()),
+        status: parse_bitget_status(&order.state),
+        timestamp: epoch_millis(order.c_time.parse().unwrap_or(0)),
+    }
 }
 
 fn parse_bitget_status(state: &str) -> OrderStatus {
     match state {
-        "new" | "init" => OrderStatus::Open,
+        "new" | "init" | "live" => OrderStatus::Open,
         "partial-fill" | "partially_filled" => OrderStatus::Partial,
         "full-fill" | "filled" => OrderStatus::Filled,
         "cancelled" | "canceled" => OrderStatus::Cancelled,
         _ => OrderStatus::Pending,
     }
 }
+
+/// HMAC-SHA256 of `timestamp + METHOD + path + body`, base64. `path`
+/// includes any query string.
+pub fn sign(secret: &str, timestamp: &str, method: &str, path: &str, body: &str) -> String {
+    let prehash = format!("{}{}{}{}", timestamp, method.to_uppercase(), path, body);
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(prehash.as_bytes());
+    STANDARD.encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{credentials, exchange_config, serve_http};
+    use crate::exchange::QuantityMode;
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn test_order_book_is_parsed_best_first() {
+        let (url, server) = serve_http(vec![(
+            "200 OK",
+            r#"{"code":"00000","msg":"success","requestTime":1695870968987,"data":{"asks":[[26347.5,0.25],[26348.0,0.16]],"bids":[[26346.5,0.16],[26346.0,0.32]],"ts":"1695870968804","scale":"0.1","precision":"scale0","isMaxPrecision":"NO"}}"#,
+        )])
+        .await;
+        let adapter = BitgetAdapter::new(exchange_config("bitget", url)).await.unwrap();
+
+        let book = adapter.get_order_book("BTCUSDT", 2).await.unwrap();
+
+        assert_eq!(book.bids[0].price, dec!(26346.5));
+        assert_eq!(book.bids[1].quantity, dec!(0.32));
+        assert_eq!(book.asks[0].price, dec!(26347.5));
+        assert_eq!(book.asks[1].price, dec!(26348));
+        assert_eq!(book.timestamp, 1695870968804);
+        let requests = server.await.unwrap();
+        assert!(
+            requests[0].starts_with("GET /api/v2/mix/market/merge-depth?symbol=BTCUSDT&productType=USDT-FUTURES&limit=5"),
+            "{:?}",
+            requests
+        );
+    }
+
+    #[tokio::test]
+    async fn test_pending_orders_read_list_field_names() {
+        let (url, server) = serve_http(vec![(
+            "200 OK",
+            r#"{"code":"00000","msg":"success","requestTime":1695870968987,"data":{"entrustedList":[{"orderId":"1","clientOid":"cs1","symbol":"BTCUSDT","side":"buy","orderType":"limit","price":"26000","size":"0.5","baseVolume":"0.1","priceAvg":"26000","status":"partially_filled","cTime":"1695870968804"}],"endId":"1"}}"#,
+        )])
+        .await;
+        let adapter = BitgetAdapter::new(exchange_config("bitget", url)).await.unwrap();
+
+        let orders = adapter.get_open_orders(&credentials(), "BTCUSDT").await.unwrap();
+
+        assert_eq!(orders.len(), 1);
+        assert_eq!(orders[0].client_order_id, "cs1");
+        assert_eq!(orders[0].filled_quantity, dec!(0.1));
+        assert_eq!(orders[0].status, OrderStatus::Partial);
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(
+            requests[0].starts_with("GET /api/v2/mix/order/orders-pending?symbol=BTCUSDT&productType=USDT-FUTURES&limit=100"),
+            "{:?}",
+            requests
+        );
+    }
+
+    #[test]
+    fn test_trade_side_follows_the_position_mode() {
+        let request = OrderRequest {
+            client_order_id: "cs1".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: Side::Sell,
+            order_type: OrderType::Limit,
+            price: Some(dec!(100)),
+            quantity: dec!(2),
+            reduce_only: false,
+            time_in_force: TimeInForce::Gtc,
+            quantity_mode: QuantityMode::Base,
+        };
+        let close = OrderRequest { reduce_only: true, ..request.clone() };
+
+        // One-way mode has no trade side
+        let open = order_body("BTCUSDT", &request, false);
+        assert!(open.get("tradeSide").is_none());
+        assert_eq!(open["side"], "sell");
+        assert_eq!(open["reduceOnly"], "NO");
+        let reduce = order_body("BTCUSDT", &close, false);
+        assert!(reduce.get("tradeSide").is_none());
+        assert_eq!(reduce["side"], "sell");
+        assert_eq!(reduce["reduceOnly"], "YES");
+
+        // Hedge mode opens the short, and closes the long by naming it
+        let open = order_body("BTCUSDT", &request, true);
+        assert_eq!(open["tradeSide"], "open");
+        assert_eq!(open["side"], "sell");
+        assert!(open.get("reduceOnly").is_none());
+        let reduce = order_body("BTCUSDT", &close, true);
+        assert_eq!(reduce["tradeSide"], "close");
+        assert_eq!(reduce["side"], "buy");
+        assert!(reduce.get("reduceOnly").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_position_mode_is_read_per_key() {
+        let (url, server) = serve_http(vec![(
+            "200 OK",
+            r#"{"code":"00000","msg":"success","data":[{"marginCoin":"USDT","locked":"0","available":"1000","posMode":"hedge_mode","assetMode":"single"}]}"#,
+        )])
+        .await;
+        let adapter = BitgetAdapter::new(exchange_config("bitget", url)).await.unwrap();
+        assert!(!adapter.is_hedge_mode(&credentials().api_key));
+
+        assert!(adapter.get_position_mode(&credentials()).await.unwrap());
+        assert!(adapter.is_hedge_mode(&credentials().api_key));
+        let requests = server.await.unwrap();
+        assert!(
+            requests[0].starts_with("GET /api/v2/mix/account/accounts?productType=USDT-FUTURES"),
+            "{:?}",
+            requests
+        );
+    }
+
+    #[tokio::test]
+    async fn test_order_timestamp_is_in_millis() {
+        let (url, _server) = serve_http(vec![(
+            "200 OK",
+            r#"{"code":"00000","msg":"success","data":{"orderId":"1","clientOid":"cs1","symbol":"BTCUSDT","side":"buy","orderType":"limit","price":"100","size":"2","filledQty":"2","priceAvg":"100","state":"filled","cTime":"1695806875837"}}"#,
+        )])
+        .await;
+        let adapter = BitgetAdapter::new(exchange_config("bitget", url)).await.unwrap();
+
+        let order = adapter.get_order(&credentials(), "BTCUSDT", "1").await.unwrap();
+        assert_eq!(order.timestamp, 1695806875837);
+    }
+}
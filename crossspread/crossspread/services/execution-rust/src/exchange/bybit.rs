@@ -7,61 +7,246 @@ use reqwest::Client;
 use rust_decimal::Decimal;
 use serde::Deserialize;
 use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
 use std::time::{SystemTime, UNIX_EPOCH};
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
-use super::{Credentials, ExchangeAdapter, OrderRequest, OrderResponse, OrderStatus, OrderType, Side};
+use super::{
+    check_unavailable, epoch_millis, insufficient_margin, mid_price, parse_json, parse_levels,
+    position_side, post_only_rejected, reduce_only_rejected, risk_limit, signature_expired, Credentials,
+    ExchangeAdapter, LeverageInfo, MarginMode, OrderBook, OrderRequest, OrderResponse, OrderStatus,
+    OrderType, Position, QuoteAssets, ReferencePriceSource, RiskLimit, Side, SymbolInfo, SymbolStatus, TimeInForce, Trail,
+    TrailingStopRequest,
+};
+use super::raw_http::SendTraced;
+use super::trading_socket::TradingSocket;
 use crate::config::ExchangeConfig;
+use crate::feed::{Feed, FeedEvent, FeedHandle, FeedProtocol, FeedUpdate};
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// Cancel rejected because the order already filled, was cancelled or is unknown
+const ORDER_NOT_FOUND: i32 = 110001;
+
+/// Reduce-only order rejected, as the position is already zero
+const REDUCE_ONLY_REJECTED: i64 = 110017;
+/// Wallet, available balance or available margin too low for the order
+const INSUFFICIENT_MARGIN: [i64; 5] = [110004, 110007, 110012, 110044, 110045];
+/// The position would exceed what its risk limit tier allows at the
+/// current leverage. Bybit caps position size through these tiers.
+const RISK_LIMIT_EXCEEDED: i64 = 110090;
+/// Placement rejected because another order already has the `orderLinkId`
+const DUPLICATE_CLIENT_ORDER_ID: i64 = 110072;
+/// Post-only order rejected, as it would have taken liquidity
+const POST_ONLY_REJECTED: i64 = 110079;
+/// Request refused as its timestamp is outside the receive window
+const REQUEST_EXPIRED: i64 = 10002;
+
+/// Private stream topic that arms disconnection protection for derivatives
+const DCP_TOPIC: &str = "dcp.future";
+
 pub struct BybitAdapter {
     config: ExchangeConfig,
     client: Client,
+    /// Hedge mode per API key, as last read or set
+    hedge_mode: RwLock<HashMap<String, bool>>,
+    /// Trade stream connection per API key
+    trading_sockets: RwLock<HashMap<String, Arc<TradingSocket>>>,
+    /// DCP window per API key, with the private stream that holds it
+    dcp_streams: RwLock<HashMap<String, (u64, FeedHandle)>>,
+    quote_assets: QuoteAssets,
 }
 
 impl BybitAdapter {
     pub async fn new(config: ExchangeConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .build()?;
+        let client = super::http_client(&config)?;
 
-        Ok(Self { config, client })
+        Ok(Self {
+            config,
+            client,
+            hedge_mode: RwLock::new(HashMap::new()),
+            trading_sockets: RwLock::new(HashMap::new()),
+            dcp_streams: RwLock::new(HashMap::new()),
+            quote_assets: QuoteAssets::default(),
+        })
     }
 
-    fn sign(&self, secret: &str, timestamp: u64, api_key: &str, recv_window: u64, query: &str) -> String {
-        let sign_str = format!("{}{}{}{}", timestamp, api_key, recv_window, query);
-        let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
-            .expect("HMAC can take key of any size");
-        mac.update(sign_str.as_bytes());
-        hex::encode(mac.finalize().into_bytes())
+    /// Open a private stream for `credentials` subscribed to the DCP topic.
+    /// It reconnects and pings on its own, and runs until the process ends.
+    fn spawn_dcp_stream(&self, credentials: &Credentials) -> FeedHandle {
+        let protocol = DcpProtocol {
+            url: format!("{}/v5/private", self.config.ws_url),
+            api_key: credentials.api_key.clone(),
+            api_secret: credentials.api_secret.clone(),
+        };
+        let (handle, mut events) = Feed::new(protocol).spawn();
+        handle.subscribe(DCP_TOPIC);
+
+        tokio::spawn(async move {
+            while let Some(event) = events.recv().await {
+                if let FeedEvent::Disconnected = event {
+                    warn!("Bybit DCP stream dropped, orders are cancelled unless it is back within the window");
+                }
+            }
+        });
+        handle
     }
 
-    fn timestamp() -> u64 {
-        SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap()
-            .as_millis() as u64
+    /// Set the DCP window of `credentials`' account
+    async fn set_dcp_window(&self, credentials: &Credentials, timeout_secs: u64) -> Result<()> {
+        let timestamp = Self::timestamp();
+        let recv_window = 5000u64;
+        let body = serde_json::json!({
+            "product": "DERIVATIVES",
+            "timeWindow": timeout_secs,
+        });
+
+        let body_str = serde_json::to_string(&body)?;
+        let signature = sign(
+            &credentials.api_secret,
+            timestamp,
+            &credentials.api_key,
+            recv_window,
+            &body_str,
+        );
+
+        let url = format!("{}/v5/order/disconnected-cancel-all", self.config.rest_url);
+
+        let response = self.client
+            .post(&url)
+            .header("X-BAPI-API-KEY", &credentials.api_key)
+            .header("X-BAPI-SIGN", &signature)
+            .header("X-BAPI-TIMESTAMP", timestamp.to_string())
+            .header("X-BAPI-RECV-WINDOW", recv_window.to_string())
+            .header("Content-Type", "application/json")
+            .body(body_str)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let body = response.text().await?;
+        let resp: BybitResponse<serde_json::Value> = parse_json(&body)?;
+        if resp.ret_code != 0 {
+            anyhow::bail!("Bybit DCP error: {} - {}", resp.ret_code, resp.ret_msg);
+        }
+        Ok(())
     }
-}
 
-#[async_trait]
-impl ExchangeAdapter for BybitAdapter {
-    fn id(&self) -> &str {
-        "bybit"
+    /// Trade stream connection of `credentials`' key, when one is configured.
+    /// It authenticates with a signature over `GET/realtime` and an expiry.
+    fn trading_socket(&self, credentials: &Credentials) -> Option<Arc<TradingSocket>> {
+        let url = self.config.trade_ws_url.as_ref()?;
+        if let Some(socket) = self.trading_sockets.read().unwrap().get(&credentials.api_key) {
+            return Some(socket.clone());
+        }
+
+        let api_key = credentials.api_key.clone();
+        let api_secret = credentials.api_secret.clone();
+        let login = move || {
+            let expires = Self::timestamp() + 10_000;
+            let signature = sign_login(&api_secret, expires);
+            serde_json::json!({ "op": "auth", "args": [api_key, expires, signature] })
+        };
+        let socket = self
+            .trading_sockets
+            .write()
+            .unwrap()
+            .entry(credentials.api_key.clone())
+            .or_insert_with(|| Arc::new(TradingSocket::new(url, "reqId").with_login(login, check_ws_answer)))
+            .clone();
+        Some(socket)
     }
 
-    async fn place_order(
+    /// The order on native `symbol` whose `id_param` (`orderId` or
+    /// `orderLinkId`) is `id`
+    async fn query_order(
         &self,
         credentials: &Credentials,
-        request: &OrderRequest,
+        symbol: &str,
+        id_param: &str,
+        id: &str,
     ) -> Result<OrderResponse> {
         let timestamp = Self::timestamp();
         let recv_window = 5000u64;
 
-        let body = serde_json::json!({
+        let query = format!("category=linear&symbol={}&{}={}", symbol, id_param, id);
+        let signature = sign(
+            &credentials.api_secret,
+            timestamp,
+            &credentials.api_key,
+            recv_window,
+            &query,
+        );
+
+        let url = format!("{}/v5/order/realtime?{}", self.config.rest_url, query);
+
+        let response = self.client
+            .get(&url)
+            .header("X-BAPI-API-KEY", &credentials.api_key)
+            .header("X-BAPI-SIGN", &signature)
+            .header("X-BAPI-TIMESTAMP", timestamp.to_string())
+            .header("X-BAPI-RECV-WINDOW", recv_window.to_string())
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let body = response.text().await?;
+        let resp: BybitResponse<BybitOrderListResult> = parse_json(&body)?;
+
+        let result = resp.result.ok_or_else(|| anyhow::anyhow!("No result"))?;
+        let order = result.list.first().ok_or_else(|| anyhow::anyhow!("Order not found"))?;
+
+        Ok(order_response(order))
+    }
+
+    /// Open orders on native `symbol`, up to one page
+    async fn open_orders(&self, credentials: &Credentials, native: &str) -> Result<Vec<BybitOrder>> {
+        let timestamp = Self::timestamp();
+        let recv_window = 5000u64;
+
+        let query = format!("category=linear&symbol={}&limit=50", native);
+        let signature = sign(
+            &credentials.api_secret,
+            timestamp,
+            &credentials.api_key,
+            recv_window,
+            &query,
+        );
+
+        let url = format!("{}/v5/order/realtime?{}", self.config.rest_url, query);
+
+        let response = self.client
+            .get(&url)
+            .header("X-BAPI-API-KEY", &credentials.api_key)
+            .header("X-BAPI-SIGN", &signature)
+            .header("X-BAPI-TIMESTAMP", timestamp.to_string())
+            .header("X-BAPI-RECV-WINDOW", recv_window.to_string())
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let body = response.text().await?;
+        let resp: BybitResponse<BybitOrderListResult> = parse_json(&body)?;
+        if resp.ret_code != 0 {
+            anyhow::bail!("Bybit error: {} - {}", resp.ret_code, resp.ret_msg);
+        }
+
+        Ok(resp.result.map(|r| r.list).unwrap_or_default())
+    }
+
+    /// Order body common to REST and the trade stream
+    fn order_body(&self, credentials: &Credentials, request: &OrderRequest) -> serde_json::Value {
+        // 0 = one-way, 1 = hedge-mode long position, 2 = hedge-mode short position
+        let position_idx = if self.is_hedge_mode(&credentials.api_key) {
+            match position_side(request.side, request.reduce_only) {
+                Side::Buy => 1,
+                Side::Sell => 2,
+            }
+        } else {
+            0
+        };
+
+        serde_json::json!({
             "category": "linear",
-            "symbol": request.symbol,
+            "symbol": self.native_symbol(&request.symbol),
             "side": match request.side {
                 Side::Buy => "Buy",
                 Side::Sell => "Sell",
@@ -72,13 +257,96 @@ impl ExchangeAdapter for BybitAdapter {
             },
             "qty": request.quantity.to_string(),
             "price": request.price.map(|p| p.to_string()),
-            "timeInForce": "GTC",
+            "timeInForce": match request.time_in_force {
+                TimeInForce::Gtc | TimeInForce::Gtd { .. } => "GTC",
+                TimeInForce::Ioc => "IOC",
+                TimeInForce::PostOnly => "PostOnly",
+            },
             "orderLinkId": request.client_order_id,
             "reduceOnly": request.reduce_only,
-        });
+            "positionIdx": position_idx,
+        })
+    }
+
+    fn is_hedge_mode(&self, api_key: &str) -> bool {
+        self.hedge_mode.read().unwrap().get(api_key).copied().unwrap_or(false)
+    }
+
+    fn timestamp() -> u64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64
+    }
+}
+
+#[async_trait]
+impl ExchangeAdapter for BybitAdapter {
+    fn id(&self) -> &str {
+        "bybit"
+    }
+
+    fn to_native_symbol(&self, base: &str, quote: &str) -> String {
+        format!("{}{}", base, quote)
+    }
+
+    fn to_canonical_symbol(&self, native: &str) -> Option<String> {
+        self.quote_assets.canonical(native)
+    }
+
+    async fn load_quote_assets(&self) -> Result<()> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Instrument {
+            quote_coin: String,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct InstrumentPage {
+            list: Vec<Instrument>,
+            #[serde(default)]
+            next_page_cursor: String,
+        }
+
+        let mut quotes = Vec::new();
+        // The cursor comes back already percent-encoded
+        let mut cursor = String::new();
+        loop {
+            let url = format!(
+                "{}/v5/market/instruments-info?category=linear&limit=1000&cursor={}",
+                self.config.rest_url, cursor
+            );
+            let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+            let body = response.text().await?;
+
+            let resp: BybitResponse<InstrumentPage> = parse_json(&body)?;
+            if resp.ret_code != 0 {
+                anyhow::bail!("Bybit error: {} - {}", resp.ret_code, resp.ret_msg);
+            }
+            let Some(page) = resp.result else { break };
+            quotes.extend(page.list.into_iter().map(|i| i.quote_coin));
+            if page.next_page_cursor.is_empty() {
+                break;
+            }
+            cursor = page.next_page_cursor;
+        }
+        self.quote_assets.set(quotes);
+        Ok(())
+    }
+
+    async fn place_order(
+        &self,
+        credentials: &Credentials,
+        request: &OrderRequest,
+    ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(&request.symbol);
+        let timestamp = Self::timestamp();
+        let recv_window = 5000u64;
 
+        let body = self.order_body(credentials, request);
         let body_str = serde_json::to_string(&body)?;
-        let signature = self.sign(
+        let signature = sign(
             &credentials.api_secret,
             timestamp,
             &credentials.api_key,
@@ -88,17 +356,22 @@ impl ExchangeAdapter for BybitAdapter {
 
         let url = format!("{}/v5/order/create", self.config.rest_url);
         
-        debug!("Placing Bybit order: {}", request.symbol);
+        debug!("Placing Bybit order: {}", symbol);
 
-        let response = self.client
+        let mut builder = self.client
             .post(&url)
             .header("X-BAPI-API-KEY", &credentials.api_key)
             .header("X-BAPI-SIGN", &signature)
             .header("X-BAPI-TIMESTAMP", timestamp.to_string())
             .header("X-BAPI-RECV-WINDOW", recv_window.to_string())
-            .header("Content-Type", "application/json")
+            .header("Content-Type", "application/json");
+        // Broker orders are attributed by header rather than in the body
+        if let Some(tag) = &self.config.order_tag {
+            builder = builder.header("Referer", tag);
+        }
+        let response = builder
             .body(body_str)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await
             .context("Failed to send order request")?;
 
@@ -106,32 +379,157 @@ impl ExchangeAdapter for BybitAdapter {
         let body = response.text().await?;
 
         if !status.is_success() {
+            check_unavailable(self.id(), status, &body)?;
             anyhow::bail!("Bybit order failed: {} - {}", status, body);
         }
 
-        let resp: BybitResponse<BybitOrderResult> = serde_json::from_str(&body)
+        // Rejections answer with an empty result, so the code comes first
+        let resp: BybitResponse<serde_json::Value> = parse_json(&body)
             .context("Failed to parse order response")?;
 
+        if let Some(e) = order_rejection(self.id(), resp.ret_code.into(), &resp.ret_msg) {
+            return Err(e);
+        }
+        if self.config.adopt_duplicate_orders && i64::from(resp.ret_code) == DUPLICATE_CLIENT_ORDER_ID {
+            // An earlier attempt of this placement got through
+            info!("Bybit order {} already exists, adopting it", request.client_order_id);
+            return Ok(self
+                .query_order(credentials, &symbol, "orderLinkId", &request.client_order_id)
+                .await?
+                .canonical(self));
+        }
         if resp.ret_code != 0 {
+            check_unavailable(self.id(), status, &body)?;
             anyhow::bail!("Bybit error: {} - {}", resp.ret_code, resp.ret_msg);
         }
 
         let result = resp.result.ok_or_else(|| anyhow::anyhow!("No result in response"))?;
+        let result: BybitOrderResult = serde_json::from_value(result)
+            .context("Failed to parse order response")?;
 
         info!("Bybit order placed: {}", result.order_id);
 
+        Ok(placed_order(request, symbol, result, timestamp).canonical(self))
+    }
+
+    async fn place_order_ws(
+        &self,
+        credentials: &Credentials,
+        request: &OrderRequest,
+    ) -> Result<OrderResponse> {
+        let Some(socket) = self.trading_socket(credentials) else {
+            return self.place_order(credentials, request).await;
+        };
+
+        let timestamp = Self::timestamp();
+        let mut frame = serde_json::json!({
+            "op": "order.create",
+            "header": {
+                "X-BAPI-TIMESTAMP": timestamp.to_string(),
+                "X-BAPI-RECV-WINDOW": "5000",
+            },
+            "args": [self.order_body(credentials, request)],
+        });
+        if let Some(tag) = &self.config.order_tag {
+            frame["header"]["Referer"] = tag.as_str().into();
+        }
+        let answer = match socket.send(frame).await {
+            Ok(answer) => answer,
+            Err(e) => {
+                warn!("Bybit trading socket unavailable, placing over REST: {:#}", e);
+                return self.place_order(credentials, request).await;
+            }
+        };
+        let answer = answer.wait().await?;
+        let code = answer["retCode"].as_i64().unwrap_or_default();
+        if let Some(e) = order_rejection(self.id(), code, answer["retMsg"].as_str().unwrap_or_default()) {
+            return Err(e);
+        }
+        check_ws_answer(&answer)?;
+        let result: BybitOrderResult = serde_json::from_value(answer["data"].clone())
+            .context("Failed to parse order answer")?;
+
+        info!("Bybit order placed over WebSocket: {}", result.order_id);
+        Ok(placed_order(request, self.native_symbol(&request.symbol), result, timestamp).canonical(self))
+    }
+
+    fn supports_trailing_stop(&self, trail: Trail) -> bool {
+        matches!(trail, Trail::Distance(_))
+    }
+
+    // Bybit trails the whole position rather than placing a separate order, so
+    // the stop closes the position in full and has no order id
+    async fn place_trailing_stop(
+        &self,
+        credentials: &Credentials,
+        request: &TrailingStopRequest,
+    ) -> Result<OrderResponse> {
+        let Trail::Distance(distance) = request.trail else {
+            anyhow::bail!("Bybit trailing stops take a distance, not a callback rate");
+        };
+        let symbol = self.native_symbol(&request.symbol);
+        let timestamp = Self::timestamp();
+        let recv_window = 5000u64;
+
+        // positionIdx 1 is the long side in hedge mode, 2 the short, 0 one-way
+        let position_idx = if self.is_hedge_mode(&credentials.api_key) {
+            match position_side(request.side, true) {
+                Side::Buy => 1,
+                Side::Sell => 2,
+            }
+        } else {
+            0
+        };
+        let body = serde_json::json!({
+            "category": "linear",
+            "symbol": symbol,
+            "tpslMode": "Full",
+            "trailingStop": distance.to_string(),
+            "positionIdx": position_idx,
+        });
+
+        let body_str = serde_json::to_string(&body)?;
+        let signature = sign(
+            &credentials.api_secret,
+            timestamp,
+            &credentials.api_key,
+            recv_window,
+            &body_str,
+        );
+
+        let url = format!("{}/v5/position/trading-stop", self.config.rest_url);
+
+        let response = self.client
+            .post(&url)
+            .header("X-BAPI-API-KEY", &credentials.api_key)
+            .header("X-BAPI-SIGN", &signature)
+            .header("X-BAPI-TIMESTAMP", timestamp.to_string())
+            .header("X-BAPI-RECV-WINDOW", recv_window.to_string())
+            .header("Content-Type", "application/json")
+            .body(body_str)
+            .send_traced(self.config.log_raw_http)
+            .await
+            .context("Failed to send trailing stop request")?;
+
+        let body = response.text().await?;
+        let resp: BybitResponse<serde_json::Value> = parse_json(&body)?;
+        if resp.ret_code != 0 {
+            anyhow::bail!("Bybit trailing stop failed: {} - {}", resp.ret_code, resp.ret_msg);
+        }
+
+        info!("Bybit trailing stop set on {} ({})", symbol, distance);
         Ok(OrderResponse {
-            exchange_order_id: result.order_id,
-            client_order_id: result.order_link_id,
-            symbol: request.symbol.clone(),
+            exchange_order_id: String::new(),
+            client_order_id: request.client_order_id.clone(),
+            symbol: self.canonical_symbol(&symbol),
             side: request.side,
-            order_type: request.order_type,
-            price: request.price,
+            order_type: OrderType::Market,
+            price: None,
             quantity: request.quantity,
             filled_quantity: Decimal::ZERO,
             avg_fill_price: None,
             status: OrderStatus::Open,
-            timestamp: timestamp as i64,
+            timestamp: chrono::Utc::now().timestamp_millis(),
         })
     }
 
@@ -141,6 +539,7 @@ impl ExchangeAdapter for BybitAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
         let timestamp = Self::timestamp();
         let recv_window = 5000u64;
 
@@ -151,7 +550,7 @@ impl ExchangeAdapter for BybitAdapter {
         });
 
         let body_str = serde_json::to_string(&body)?;
-        let signature = self.sign(
+        let signature = sign(
             &credentials.api_secret,
             timestamp,
             &credentials.api_key,
@@ -169,18 +568,36 @@ impl ExchangeAdapter for BybitAdapter {
             .header("X-BAPI-RECV-WINDOW", recv_window.to_string())
             .header("Content-Type", "application/json")
             .body(body_str)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
+        let status = response.status();
         let body = response.text().await?;
-        let resp: BybitResponse<BybitOrderResult> = serde_json::from_str(&body)?;
+        if !status.is_success() {
+            check_unavailable(self.id(), status, &body)?;
+        }
+        // Failed cancels answer with an empty result, so the code comes first
+        let resp: BybitResponse<serde_json::Value> = parse_json(&body)?;
+        if resp.ret_code == ORDER_NOT_FOUND {
+            // Lost the race with a fill or an earlier cancel: report where it ended up
+            debug!("Bybit order {} is no longer open, fetching it", order_id);
+            return self.get_order(credentials, &symbol, order_id).await;
+        }
+        if resp.ret_code != 0 {
+            check_unavailable(self.id(), status, &body)?;
+            anyhow::bail!("Bybit cancel failed: {} - {}", resp.ret_code, resp.ret_msg);
+        }
 
-        let result = resp.result.ok_or_else(|| anyhow::anyhow!("No result"))?;
+        let result: BybitOrderResult = resp
+            .result
+            .map(serde_json::from_value)
+            .transpose()?
+            .ok_or_else(|| anyhow::anyhow!("No result"))?;
 
         Ok(OrderResponse {
             exchange_order_id: result.order_id,
             client_order_id: result.order_link_id,
-            symbol: symbol.to_string(),
+            symbol: self.canonical_symbol(&symbol),
             side: Side::Buy,
             order_type: OrderType::Limit,
             price: None,
@@ -198,63 +615,49 @@ impl ExchangeAdapter for BybitAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
-        let timestamp = Self::timestamp();
-        let recv_window = 5000u64;
-
-        let query = format!("category=linear&symbol={}&orderId={}", symbol, order_id);
-        let signature = self.sign(
-            &credentials.api_secret,
-            timestamp,
-            &credentials.api_key,
-            recv_window,
-            &query,
-        );
-
-        let url = format!("{}/v5/order/realtime?{}", self.config.rest_url, query);
+        Ok(self
+            .query_order(credentials, &self.native_symbol(symbol), "orderId", order_id)
+            .await?
+            .canonical(self))
+    }
 
-        let response = self.client
-            .get(&url)
-            .header("X-BAPI-API-KEY", &credentials.api_key)
-            .header("X-BAPI-SIGN", &signature)
-            .header("X-BAPI-TIMESTAMP", timestamp.to_string())
-            .header("X-BAPI-RECV-WINDOW", recv_window.to_string())
-            .send()
-            .await?;
+    // Without an orderId the realtime endpoint lists every open order on the
+    // symbol. Orders missing from it have closed and are fetched individually.
+    async fn get_orders_batch(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_ids: &[String],
+    ) -> Result<Vec<OrderResponse>> {
+        let open = self.open_orders(credentials, &self.native_symbol(symbol)).await?;
+        let mut orders: Vec<OrderResponse> = open
+            .iter()
+            .filter(|o| order_ids.contains(&o.order_id))
+            .map(|order| order_response(order).canonical(self))
+            .collect();
 
-        let body = response.text().await?;
-        let resp: BybitResponse<BybitOrderListResult> = serde_json::from_str(&body)?;
+        for order_id in order_ids {
+            if !orders.iter().any(|o| &o.exchange_order_id == order_id) {
+                orders.push(self.get_order(credentials, symbol, order_id).await?);
+            }
+        }
 
-        let result = resp.result.ok_or_else(|| anyhow::anyhow!("No result"))?;
-        let order = result.list.first().ok_or_else(|| anyhow::anyhow!("Order not found"))?;
+        Ok(orders)
+    }
 
-        Ok(OrderResponse {
-            exchange_order_id: order.order_id.clone(),
-            client_order_id: order.order_link_id.clone(),
-            symbol: order.symbol.clone(),
-            side: match order.side.as_str() {
-                "Buy" => Side::Buy,
-                _ => Side::Sell,
-            },
-            order_type: match order.order_type.as_str() {
-                "Limit" => OrderType::Limit,
-                _ => OrderType::Market,
-            },
-            price: order.price.parse().ok(),
-            quantity: order.qty.parse().unwrap_or_default(),
-            filled_quantity: order.cum_exec_qty.parse().unwrap_or_default(),
-            avg_fill_price: order.avg_price.parse().ok(),
-            status: parse_bybit_status(&order.order_status),
-            timestamp: order.updated_time.parse().unwrap_or(0),
-        })
+    async fn get_open_orders(&self, credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        let open = self.open_orders(credentials, &self.native_symbol(symbol)).await?;
+        Ok(open.iter().map(|order| order_response(order).canonical(self)).collect())
     }
 
     async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
+        let symbol = self.native_symbol(symbol);
         let url = format!(
             "{}/v5/market/tickers?category=linear&symbol={}",
             self.config.rest_url, symbol
         );
 
-        let response = self.client.get(&url).send().await?;
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
         let body = response.text().await?;
 
         #[derive(Deserialize)]
@@ -268,7 +671,7 @@ impl ExchangeAdapter for BybitAdapter {
             ask1Price: String,
         }
 
-        let resp: BybitResponse<TickerResult> = serde_json::from_str(&body)?;
+        let resp: BybitResponse<TickerResult> = parse_json(&body)?;
         let result = resp.result.ok_or_else(|| anyhow::anyhow!("No result"))?;
         let ticker = result.list.first().ok_or_else(|| anyhow::anyhow!("No ticker"))?;
 
@@ -278,14 +681,637 @@ impl ExchangeAdapter for BybitAdapter {
         ))
     }
 
-    fn is_connected(&self) -> bool {
-        true
-    }
-}
+    async fn get_reference_price(&self, symbol: &str, source: ReferencePriceSource) -> Result<Decimal> {
+        if source == ReferencePriceSource::Mid {
+            return mid_price(self, symbol).await;
+        }
+        let symbol = self.native_symbol(symbol);
+        let url = format!(
+            "{}/v5/market/tickers?category=linear&symbol={}",
+            self.config.rest_url, symbol
+        );
 
-#[derive(Debug, Deserialize)]
-#[serde(rename_all = "camelCase")]
-struct BybitResponse<T> {
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct TickerResult {
+            list: Vec<Ticker>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Ticker {
+            last_price: String,
+            mark_price: String,
+            index_price: String,
+        }
+
+        let resp: BybitResponse<TickerResult> = parse_json(&body)?;
+        if resp.ret_code != 0 {
+            anyhow::bail!("Bybit error: {} - {}", resp.ret_code, resp.ret_msg);
+        }
+        let result = resp.result.ok_or_else(|| anyhow::anyhow!("No result"))?;
+        let ticker = result.list.first().ok_or_else(|| anyhow::anyhow!("No ticker"))?;
+
+        let price = match source {
+            ReferencePriceSource::Last => &ticker.last_price,
+            ReferencePriceSource::Mark => &ticker.mark_price,
+            _ => &ticker.index_price,
+        };
+        price.parse().context("Invalid price")
+    }
+
+    async fn get_server_time(&self) -> Result<i64> {
+        let url = format!("{}/v5/market/time", self.config.rest_url);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let status = response.status();
+        let body = response.text().await?;
+        check_unavailable(self.id(), status, &body)?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct ServerTime {
+            time_nano: String,
+        }
+
+        let resp: BybitResponse<ServerTime> = parse_json(&body)?;
+        let result = resp.result.ok_or_else(|| anyhow::anyhow!("No result"))?;
+        let nanos: i64 = result.time_nano.parse()?;
+        Ok(nanos / 1_000_000)
+    }
+
+    async fn get_symbol_info(&self, symbol: &str) -> Result<SymbolInfo> {
+        let symbol = self.native_symbol(symbol);
+        let url = format!(
+            "{}/v5/market/instruments-info?category=linear&symbol={}",
+            self.config.rest_url, symbol
+        );
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct LotSizeFilter {
+            qty_step: String,
+            /// Absent on inverse contracts
+            min_notional_value: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct PriceFilter {
+            tick_size: String,
+        }
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Instrument {
+            status: String,
+            lot_size_filter: Option<LotSizeFilter>,
+            price_filter: Option<PriceFilter>,
+        }
+
+        #[derive(Deserialize)]
+        struct InstrumentList {
+            list: Vec<Instrument>,
+        }
+
+        let resp: BybitResponse<InstrumentList> = parse_json(&body)?;
+        if resp.ret_code != 0 {
+            anyhow::bail!("Bybit error: {} - {}", resp.ret_code, resp.ret_msg);
+        }
+        let instrument = resp.result.and_then(|r| r.list.into_iter().next());
+
+        Ok(SymbolInfo {
+            status: match instrument.as_ref().map(|i| i.status.as_str()) {
+                Some("Trading") => SymbolStatus::Trading,
+                Some("PreLaunch") => SymbolStatus::PreLaunch,
+                Some("Closed") | None => SymbolStatus::Delisted,
+                // Delivering, Settling
+                Some(_) => SymbolStatus::Maintenance,
+            },
+            symbol,
+            // Linear quantities are in coins
+            contract_size: Decimal::ONE,
+            quantity_step: instrument
+                .as_ref()
+                .and_then(|i| i.lot_size_filter.as_ref())
+                .and_then(|f| f.qty_step.parse().ok())
+                .unwrap_or_default(),
+            tick_size: instrument
+                .as_ref()
+                .and_then(|i| i.price_filter.as_ref())
+                .and_then(|f| f.tick_size.parse().ok())
+                .unwrap_or_default(),
+            min_notional: instrument
+                .as_ref()
+                .and_then(|i| i.lot_size_filter.as_ref())
+                .and_then(|f| f.min_notional_value.as_ref())
+                .and_then(|notional| notional.parse().ok())
+                .unwrap_or_default(),
+        })
+    }
+
+    async fn get_order_book(&self, symbol: &str, depth: usize) -> Result<OrderBook> {
+        let symbol = self.native_symbol(symbol);
+        let url = format!(
+            "{}/v5/market/orderbook?category=linear&symbol={}&limit={}",
+            self.config.rest_url,
+            symbol,
+            depth.clamp(1, 500)
+        );
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct Depth {
+            b: Vec<[String; 2]>,
+            a: Vec<[String; 2]>,
+            ts: i64,
+        }
+
+        let resp: BybitResponse<Depth> = parse_json(&body)?;
+        if resp.ret_code != 0 {
+            anyhow::bail!("Bybit error: {} - {}", resp.ret_code, resp.ret_msg);
+        }
+        let book = resp.result.ok_or_else(|| anyhow::anyhow!("No result"))?;
+
+        Ok(OrderBook::from_levels(parse_levels(&book.b)?, parse_levels(&book.a)?, depth, book.ts))
+    }
+
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    // Bybit has no direct query for the mode, so it is inferred from open USDT
+    // positions: hedge-mode positions carry a non-zero positionIdx. With no open
+    // positions the mode can't be told, and that is an error rather than a guess.
+    async fn get_position_mode(&self, credentials: &Credentials) -> Result<bool> {
+        let timestamp = Self::timestamp();
+        let recv_window = 5000u64;
+
+        let query = "category=linear&settleCoin=USDT";
+        let signature = sign(
+            &credentials.api_secret,
+            timestamp,
+            &credentials.api_key,
+            recv_window,
+            query,
+        );
+
+        let url = format!("{}/v5/position/list?{}", self.config.rest_url, query);
+
+        let response = self.client
+            .get(&url)
+            .header("X-BAPI-API-KEY", &credentials.api_key)
+            .header("X-BAPI-SIGN", &signature)
+            .header("X-BAPI-TIMESTAMP", timestamp.to_string())
+            .header("X-BAPI-RECV-WINDOW", recv_window.to_string())
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Position {
+            position_idx: i32,
+        }
+
+        #[derive(Deserialize)]
+        struct PositionList {
+            list: Vec<Position>,
+        }
+
+        let resp: BybitResponse<PositionList> = parse_json(&body)?;
+        if resp.ret_code != 0 {
+            anyhow::bail!("Bybit error: {} - {}", resp.ret_code, resp.ret_msg);
+        }
+
+        let Some(positions) = resp.result.map(|r| r.list).filter(|list| !list.is_empty()) else {
+            anyhow::bail!("Bybit position mode can't be read without an open position");
+        };
+        let hedge = positions.iter().any(|p| p.position_idx != 0);
+        self.hedge_mode
+            .write()
+            .unwrap()
+            .insert(credentials.api_key.clone(), hedge);
+        Ok(hedge)
+    }
+
+    async fn get_leverage(&self, credentials: &Credentials, symbol: &str) -> Result<LeverageInfo> {
+        let symbol = self.native_symbol(symbol);
+        let timestamp = Self::timestamp();
+        let recv_window = 5000u64;
+
+        let query = format!("category=linear&symbol={}", symbol);
+        let signature = sign(
+            &credentials.api_secret,
+            timestamp,
+            &credentials.api_key,
+            recv_window,
+            &query,
+        );
+
+        let url = format!("{}/v5/position/list?{}", self.config.rest_url, query);
+
+        let response = self.client
+            .get(&url)
+            .header("X-BAPI-API-KEY", &credentials.api_key)
+            .header("X-BAPI-SIGN", &signature)
+            .header("X-BAPI-TIMESTAMP", timestamp.to_string())
+            .header("X-BAPI-RECV-WINDOW", recv_window.to_string())
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Position {
+            leverage: String,
+            /// 0 cross, 1 isolated
+            trade_mode: u8,
+        }
+
+        #[derive(Deserialize)]
+        struct PositionList {
+            list: Vec<Position>,
+        }
+
+        let body = response.text().await?;
+        let resp: BybitResponse<PositionList> = parse_json(&body)?;
+        if resp.ret_code != 0 {
+            anyhow::bail!("Bybit error: {} - {}", resp.ret_code, resp.ret_msg);
+        }
+
+        // Every position side of a symbol shares its leverage
+        let position = resp
+            .result
+            .and_then(|result| result.list.into_iter().next())
+            .ok_or_else(|| anyhow::anyhow!("No position for {}", symbol))?;
+        Ok(LeverageInfo {
+            leverage: position.leverage.parse().context("Invalid Bybit leverage")?,
+            margin_mode: match position.trade_mode {
+                1 => MarginMode::Isolated,
+                _ => MarginMode::Cross,
+            },
+        })
+    }
+
+    async fn get_risk_limit(&self, credentials: &Credentials, symbol: &str) -> Result<RiskLimit> {
+        let symbol = self.native_symbol(symbol);
+        let timestamp = Self::timestamp();
+        let recv_window = 5000u64;
+
+        let query = format!("category=linear&symbol={}", symbol);
+        let signature = sign(
+            &credentials.api_secret,
+            timestamp,
+            &credentials.api_key,
+            recv_window,
+            &query,
+        );
+
+        let url = format!("{}/v5/position/list?{}", self.config.rest_url, query);
+
+        let response = self.client
+            .get(&url)
+            .header("X-BAPI-API-KEY", &credentials.api_key)
+            .header("X-BAPI-SIGN", &signature)
+            .header("X-BAPI-TIMESTAMP", timestamp.to_string())
+            .header("X-BAPI-RECV-WINDOW", recv_window.to_string())
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Position {
+            risk_id: u64,
+            risk_limit_value: String,
+        }
+
+        #[derive(Deserialize)]
+        struct PositionList {
+            list: Vec<Position>,
+        }
+
+        let body = response.text().await?;
+        let resp: BybitResponse<PositionList> = parse_json(&body)?;
+        if resp.ret_code != 0 {
+            anyhow::bail!("Bybit error: {} - {}", resp.ret_code, resp.ret_msg);
+        }
+
+        let position = resp
+            .result
+            .and_then(|result| result.list.into_iter().next())
+            .ok_or_else(|| anyhow::anyhow!("No position for {}", symbol))?;
+        Ok(RiskLimit {
+            max_notional_usd: position.risk_limit_value.parse().context("Invalid Bybit riskLimitValue")?,
+            tier: Some(position.risk_id.to_string()),
+        })
+    }
+
+    async fn set_risk_limit(&self, credentials: &Credentials, symbol: &str, notional_usd: Decimal) -> Result<RiskLimit> {
+        let native = self.native_symbol(symbol);
+        let url = format!(
+            "{}/v5/market/risk-limit?category=linear&symbol={}",
+            self.config.rest_url, native
+        );
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Tier {
+            id: u64,
+            risk_limit_value: String,
+        }
+
+        #[derive(Deserialize)]
+        struct TierList {
+            list: Vec<Tier>,
+        }
+
+        let body = response.text().await?;
+        let resp: BybitResponse<TierList> = parse_json(&body)?;
+        if resp.ret_code != 0 {
+            anyhow::bail!("Bybit error: {} - {}", resp.ret_code, resp.ret_msg);
+        }
+        let mut tiers = Vec::new();
+        for tier in resp.result.map(|result| result.list).unwrap_or_default() {
+            let value: Decimal = tier.risk_limit_value.parse().context("Invalid Bybit riskLimitValue")?;
+            tiers.push((value, tier.id));
+        }
+        tiers.sort();
+        let (max_notional_usd, risk_id) = tiers
+            .into_iter()
+            .find(|(value, _)| *value >= notional_usd)
+            .ok_or_else(|| anyhow::anyhow!("No Bybit risk limit tier on {} allows {} USD", native, notional_usd))?;
+
+        let timestamp = Self::timestamp();
+        let recv_window = 5000u64;
+
+        // Position index 0 is the one-way position
+        let body = serde_json::json!({
+            "category": "linear",
+            "symbol": native,
+            "riskId": risk_id,
+            "positionIdx": 0,
+        });
+
+        let body_str = serde_json::to_string(&body)?;
+        let signature = sign(
+            &credentials.api_secret,
+            timestamp,
+            &credentials.api_key,
+            recv_window,
+            &body_str,
+        );
+
+        let url = format!("{}/v5/position/set-risk-limit", self.config.rest_url);
+
+        let response = self.client
+            .post(&url)
+            .header("X-BAPI-API-KEY", &credentials.api_key)
+            .header("X-BAPI-SIGN", &signature)
+            .header("X-BAPI-TIMESTAMP", timestamp.to_string())
+            .header("X-BAPI-RECV-WINDOW", recv_window.to_string())
+            .header("Content-Type", "application/json")
+            .body(body_str)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let body = response.text().await?;
+        let resp: BybitResponse<serde_json::Value> = parse_json(&body)?;
+        if resp.ret_code != 0 {
+            anyhow::bail!("Bybit error: {} - {}", resp.ret_code, resp.ret_msg);
+        }
+
+        info!("Bybit risk limit on {} raised to tier {} ({} USD)", native, risk_id, max_notional_usd);
+        Ok(RiskLimit {
+            max_notional_usd,
+            tier: Some(risk_id.to_string()),
+        })
+    }
+
+    async fn get_positions(&self, credentials: &Credentials) -> Result<Vec<Position>> {
+        let timestamp = Self::timestamp();
+        let recv_window = 5000u64;
+
+        // Listing without a symbol needs a settle coin; 200 is the page limit
+        let query = "category=linear&settleCoin=USDT&limit=200";
+        let signature = sign(
+            &credentials.api_secret,
+            timestamp,
+            &credentials.api_key,
+            recv_window,
+            query,
+        );
+
+        let url = format!("{}/v5/position/list?{}", self.config.rest_url, query);
+
+        let response = self.client
+            .get(&url)
+            .header("X-BAPI-API-KEY", &credentials.api_key)
+            .header("X-BAPI-SIGN", &signature)
+            .header("X-BAPI-TIMESTAMP", timestamp.to_string())
+            .header("X-BAPI-RECV-WINDOW", recv_window.to_string())
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct OpenPosition {
+            symbol: String,
+            /// Empty when flat
+            side: String,
+            size: String,
+            avg_price: String,
+        }
+
+        #[derive(Deserialize)]
+        struct PositionList {
+            list: Vec<OpenPosition>,
+        }
+
+        let body = response.text().await?;
+        let resp: BybitResponse<PositionList> = parse_json(&body)?;
+        if resp.ret_code != 0 {
+            anyhow::bail!("Bybit error: {} - {}", resp.ret_code, resp.ret_msg);
+        }
+
+        let mut open = Vec::new();
+        for position in resp.result.map(|r| r.list).unwrap_or_default() {
+            let quantity: Decimal = position.size.parse().context("Invalid Bybit position size")?;
+            let side = match position.side.as_str() {
+                "Buy" => Side::Buy,
+                "Sell" => Side::Sell,
+                _ => continue,
+            };
+            if quantity.is_zero() {
+                continue;
+            }
+            open.push(Position {
+                symbol: position.symbol,
+                side,
+                quantity,
+                entry_price: position.avg_price.parse().context("Invalid Bybit entry price")?,
+            });
+        }
+        Ok(open)
+    }
+
+    async fn set_position_mode(&self, credentials: &Credentials, hedge: bool) -> Result<()> {
+        let timestamp = Self::timestamp();
+        let recv_window = 5000u64;
+
+        // 0 = merged single (one-way), 3 = both sides (hedge)
+        let body = serde_json::json!({
+            "category": "linear",
+            "coin": "USDT",
+            "mode": if hedge { 3 } else { 0 },
+        });
+
+        let body_str = serde_json::to_string(&body)?;
+        let signature = sign(
+            &credentials.api_secret,
+            timestamp,
+            &credentials.api_key,
+            recv_window,
+            &body_str,
+        );
+
+        let url = format!("{}/v5/position/switch-mode", self.config.rest_url);
+
+        let response = self.client
+            .post(&url)
+            .header("X-BAPI-API-KEY", &credentials.api_key)
+            .header("X-BAPI-SIGN", &signature)
+            .header("X-BAPI-TIMESTAMP", timestamp.to_string())
+            .header("X-BAPI-RECV-WINDOW", recv_window.to_string())
+            .header("Content-Type", "application/json")
+            .body(body_str)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let body = response.text().await?;
+        let resp: BybitResponse<serde_json::Value> = parse_json(&body)?;
+
+        // 110025: already in the requested mode
+        if resp.ret_code != 0 && resp.ret_code != 110025 {
+            anyhow::bail!("Bybit error: {} - {}", resp.ret_code, resp.ret_msg);
+        }
+
+        info!("Bybit position mode set to {}", if hedge { "hedge" } else { "one-way" });
+        self.hedge_mode
+            .write()
+            .unwrap()
+            .insert(credentials.api_key.clone(), hedge);
+        Ok(())
+    }
+
+    fn supports_cancel_on_disconnect(&self) -> bool {
+        true
+    }
+
+    /// Bybit's disconnection protection (DCP) fires when the account's
+    /// private WebSocket has been down for the window, so it is held by a
+    /// private stream subscribed to `dcp.future` rather than by refreshing.
+    /// The window is only sent when it changes, which makes a refresh a no-op
+    /// while the stream runs. Bybit can't clear a window, so zero drops the
+    /// subscription instead. DCP must be enabled on the account.
+    async fn set_cancel_on_disconnect(&self, credentials: &Credentials, timeout_secs: u64) -> Result<()> {
+        if timeout_secs == 0 {
+            if let Some((_, stream)) = self.dcp_streams.write().unwrap().remove(&credentials.api_key) {
+                stream.unsubscribe(DCP_TOPIC);
+            }
+            return Ok(());
+        }
+        anyhow::ensure!(
+            (3..=300).contains(&timeout_secs),
+            "Bybit DCP takes a 3 to 300 second window, got {}",
+            timeout_secs
+        );
+
+        let held = self
+            .dcp_streams
+            .read()
+            .unwrap()
+            .get(&credentials.api_key)
+            .map(|(window, _)| *window);
+        if held == Some(timeout_secs) {
+            return Ok(());
+        }
+        self.set_dcp_window(credentials, timeout_secs).await?;
+
+        let mut streams = self.dcp_streams.write().unwrap();
+        match streams.get_mut(&credentials.api_key) {
+            Some((window, _)) => *window = timeout_secs,
+            None => {
+                let stream = self.spawn_dcp_stream(credentials);
+                streams.insert(credentials.api_key.clone(), (timeout_secs, stream));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Private stream of one API key, kept only for the DCP subscription.
+/// Pushes are not read; pongs keep it from going stale.
+struct DcpProtocol {
+    url: String,
+    api_key: String,
+    api_secret: String,
+}
+
+#[async_trait]
+impl FeedProtocol for DcpProtocol {
+    fn url(&self) -> String {
+        self.url.clone()
+    }
+
+    fn subscribe_message(&self, topics: &[String]) -> String {
+        serde_json::json!({ "op": "subscribe", "args": topics }).to_string()
+    }
+
+    fn unsubscribe_message(&self, topics: &[String]) -> Option<String> {
+        Some(serde_json::json!({ "op": "unsubscribe", "args": topics }).to_string())
+    }
+
+    fn login_message(&self) -> Option<String> {
+        let expires = BybitAdapter::timestamp() + 10_000;
+        let signature = sign_login(&self.api_secret, expires);
+        Some(serde_json::json!({ "op": "auth", "args": [self.api_key, expires, signature] }).to_string())
+    }
+
+    fn heartbeat(&self) -> Option<String> {
+        Some(serde_json::json!({ "op": "ping" }).to_string())
+    }
+
+    fn parse(&self, text: &str) -> Option<FeedUpdate> {
+        let answer: serde_json::Value = serde_json::from_str(text).ok()?;
+        if answer["success"] == false {
+            warn!("Bybit DCP stream {} failed: {}", answer["op"], answer["ret_msg"]);
+        }
+        None
+    }
+
+    // There is no state to resync
+    async fn snapshot(&self, topic: &str) -> Result<FeedUpdate> {
+        Ok(FeedUpdate {
+            topic: topic.to_string(),
+            sequence: None,
+            prev_sequence: None,
+            payload: serde_json::Value::Null,
+        })
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BybitResponse<T> {
     ret_code: i32,
     ret_msg: String,
     result: Option<T>,
@@ -298,6 +1324,55 @@ struct BybitOrderResult {
     order_link_id: String,
 }
 
+/// Order rejections callers handle by kind, as a typed `ExchangeError`:
+///
+/// | `retCode`                          | Rejection                         | Error                |
+/// |------------------------------------|-----------------------------------|----------------------|
+/// | 110017                             | Reduce-only with no position      | `ReduceOnlyRejected` |
+/// | 110004, 110007, 110012, 110044/45  | Balance or margin too low         | `InsufficientMargin` |
+/// | 110090                             | Over the risk limit tier's size   | `RiskLimit`          |
+/// | 110079                             | Post-only order would cross       | `PostOnlyRejected`   |
+/// | 10002                              | Timestamp outside receive window  | `SignatureExpired`   |
+///
+/// Other codes are left to the generic error.
+fn order_rejection(exchange: &str, code: i64, msg: &str) -> Option<anyhow::Error> {
+    let detail = format!("{} - {}", code, msg);
+    match code {
+        REDUCE_ONLY_REJECTED => Some(reduce_only_rejected(exchange, &detail)),
+        RISK_LIMIT_EXCEEDED => Some(risk_limit(exchange, &detail)),
+        POST_ONLY_REJECTED => Some(post_only_rejected(exchange, &detail)),
+        REQUEST_EXPIRED => Some(signature_expired(exchange, &detail)),
+        code if INSUFFICIENT_MARGIN.contains(&code) => Some(insufficient_margin(exchange, &detail)),
+        _ => None,
+    }
+}
+
+/// A freshly created order, which Bybit only acknowledges with its ids
+fn placed_order(request: &OrderRequest, symbol: String, result: BybitOrderResult, timestamp: u64) -> OrderResponse {
+    OrderResponse {
+        exchange_order_id: result.order_id,
+        client_order_id: result.order_link_id,
+        symbol,
+        side: request.side,
+        order_type: request.order_type,
+        price: request.price,
+        quantity: request.quantity,
+        filled_quantity: Decimal::ZERO,
+        avg_fill_price: None,
+        status: OrderStatus::Open,
+        timestamp: timestamp as i64,
+    }
+}
+
+/// Fail on a trade stream answer, login included, with a non-zero `retCode`
+fn check_ws_answer(answer: &serde_json::Value) -> Result<()> {
+    let code = answer["retCode"].as_i64().unwrap_or(-1);
+    if code != 0 {
+        anyhow::bail!("Bybit error: {} - {}", code, answer["retMsg"].as_str().unwrap_or_default());
+    }
+    Ok(())
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct BybitOrderListResult {
@@ -314,12 +1389,35 @@ struct BybitOrder {
     order_type: String,
     price: String,
     qty: String,
+    /// Cumulative executed quantity
     cum_exec_qty: String,
     avg_price: String,
     order_status: String,
     updated_time: String,
 }
 
+fn order_response(order: &BybitOrder) -> OrderResponse {
+    OrderResponse {
+        exchange_order_id: order.order_id.clone(),
+        client_order_id: order.order_link_id.clone(),
+        symbol: order.symbol.clone(),
+        side: match order.side.as_str() {
+            "Buy" => Side::Buy,
+            _ => Side::Sell,
+        },
+        order_type: match order.order_type.as_str() {
+            "Limit" => OrderType::Limit,
+            _ => OrderType::Market,
+        },
+        price: order.price.parse().ok(),
+        quantity: order.qty.parse().unwrap_or_default(),
+        filled_quantity: order.cum_exec_qty.parse().unwrap_or_default(),
+        avg_fill_price: order.avg_price.parse().ok(),
+        status: parse_bybit_status(&order.order_status),
+        timestamp: epoch_millis(order.updated_time.parse().unwrap_or(0)),
+    }
+}
+
 fn parse_bybit_status(status: &str) -> OrderStatus {
     match status {
         "New" => OrderStatus::Open,
@@ -330,3 +1428,308 @@ fn parse_bybit_status(status: &str) -> OrderStatus {
         _ => OrderStatus::Pending,
     }
 }
+
+/// HMAC-SHA256 of `timestamp + api_key + recv_window + query`, hex, where
+/// `query` is the GET query string or the POST body
+pub fn sign(secret: &str, timestamp: u64, api_key: &str, recv_window: u64, query: &str) -> String {
+    let sign_str = format!("{}{}{}{}", timestamp, api_key, recv_window, query);
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(sign_str.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+/// Trade stream login: HMAC-SHA256 of `GET/realtime{expires}`, hex
+pub fn sign_login(secret: &str, expires: u64) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(format!("GET/realtime{}", expires).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{credentials, serve_http, serve_http_raw};
+    use crate::exchange::QuantityMode;
+
+    fn config(rest_url: String) -> ExchangeConfig {
+        ExchangeConfig {
+            id: "bybit".to_string(),
+            rest_url,
+            ws_url: String::new(),
+            trade_ws_url: None,
+            testnet: false,
+            maker_fee_bps: 0.0,
+            taker_fee_bps: 0.0,
+            trade_mode: None,
+            user_agent: None,
+            proxy: None,
+            log_raw_http: false,
+            ws_orders: false,
+            order_tag: None,
+            client_order_id_prefix: None,
+            adopt_duplicate_orders: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dcp_is_held_by_the_private_stream() {
+        use futures::StreamExt;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let ws_url = format!("ws://{}", listener.local_addr().unwrap());
+        let (frames_tx, mut frames) = tokio::sync::mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = tokio_tungstenite::accept_async(stream).await.unwrap();
+            while let Some(Ok(frame)) = socket.next().await {
+                if let Message::Text(text) = frame {
+                    let frame: serde_json::Value = serde_json::from_str(&text).unwrap();
+                    if frame["op"] != "ping" {
+                        frames_tx.send(frame).unwrap();
+                    }
+                }
+            }
+        });
+        let (rest_url, server) = serve_http(vec![("200 OK", r#"{"retCode":0,"retMsg":"OK","result":{}}"#)]).await;
+        let adapter = BybitAdapter::new(ExchangeConfig { ws_url, ..config(rest_url) }).await.unwrap();
+
+        // The window is set once; a refresh leaves it to the stream
+        adapter.set_cancel_on_disconnect(&credentials(), 10).await.unwrap();
+        adapter.set_cancel_on_disconnect(&credentials(), 10).await.unwrap();
+        let requests = server.await.unwrap();
+        assert_eq!(requests, ["POST /v5/order/disconnected-cancel-all HTTP/1.1"]);
+
+        let auth = frames.recv().await.unwrap();
+        assert_eq!(auth["op"], "auth");
+        assert_eq!(auth["args"][0], credentials().api_key);
+        let subscribe = frames.recv().await.unwrap();
+        assert_eq!(subscribe, serde_json::json!({ "op": "subscribe", "args": ["dcp.future"] }));
+
+        // Clearing drops the subscription rather than sending a window
+        adapter.set_cancel_on_disconnect(&credentials(), 0).await.unwrap();
+        let unsubscribe = frames.recv().await.unwrap();
+        assert_eq!(unsubscribe, serde_json::json!({ "op": "unsubscribe", "args": ["dcp.future"] }));
+        assert!(adapter.set_cancel_on_disconnect(&credentials(), 1).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancelling_a_filled_order_reports_its_state() {
+        let filled = r#"{"retCode":0,"retMsg":"OK","result":{"list":[{"orderId":"7","orderLinkId":"cs1","symbol":"BTCUSDT","side":"Buy","orderType":"Limit","price":"100","qty":"1","cumExecQty":"1","avgPrice":"100","orderStatus":"Filled","updatedTime":"2"}]}}"#;
+        let (url, server) = serve_http(vec![
+            ("200 OK", r#"{"retCode":110001,"retMsg":"order not exists or too late to cancel","result":{}}"#),
+            ("200 OK", filled),
+            ("200 OK", r#"{"retCode":10006,"retMsg":"Too many visits!","result":{}}"#),
+        ])
+        .await;
+        let adapter = BybitAdapter::new(config(url)).await.unwrap();
+
+        let order = adapter.cancel_order(&credentials(), "BTCUSDT", "7").await.unwrap();
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert_eq!(order.filled_quantity, Decimal::ONE);
+
+        // Other rejections are still errors
+        let err = adapter.cancel_order(&credentials(), "BTCUSDT", "7").await.unwrap_err();
+        assert!(err.to_string().contains("10006"), "{}", err);
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /v5/order/cancel"), "{:?}", requests);
+        assert!(requests[1].starts_with("GET /v5/order/realtime?"), "{:?}", requests);
+    }
+
+    #[tokio::test]
+    async fn test_order_book_is_parsed_best_first() {
+        let (url, server) = serve_http(vec![(
+            "200 OK",
+            r#"{"retCode":0,"retMsg":"OK","result":{"s":"BTCUSDT","b":[["65485.47","47.081829"],["65485.46","0.001"]],"a":[["65557.7","16.606555"],["65558.1","0.25"]],"ts":1716863719031,"u":230704,"seq":1432604333,"cts":1716863718905},"time":1716863719382}"#,
+        )])
+        .await;
+        let adapter = BybitAdapter::new(config(url)).await.unwrap();
+
+        let book = adapter.get_order_book("BTCUSDT", 2).await.unwrap();
+
+        assert_eq!(book.bids[0].price, Decimal::new(6548547, 2));
+        assert_eq!(book.bids[1].quantity, Decimal::new(1, 3));
+        assert_eq!(book.asks[0].price, Decimal::new(655577, 1));
+        assert_eq!(book.asks[1].quantity, Decimal::new(25, 2));
+        assert_eq!(book.timestamp, 1716863719031);
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /v5/market/orderbook?category=linear&symbol=BTCUSDT&limit=2"), "{:?}", requests);
+    }
+
+    #[tokio::test]
+    async fn test_reduce_only_rejection_is_reported_by_kind() {
+        use crate::exchange::ExchangeError;
+
+        let (url, server) = serve_http(vec![
+            ("200 OK", r#"{"retCode":110017,"retMsg":"current position is zero, cannot fix reduce-only order qty","result":{}}"#),
+            ("200 OK", r#"{"retCode":110007,"retMsg":"ab not enough for new order","result":{}}"#),
+        ])
+        .await;
+        let adapter = BybitAdapter::new(config(url)).await.unwrap();
+        let request = OrderRequest {
+            client_order_id: "cs1".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: Side::Sell,
+            order_type: OrderType::Market,
+            price: None,
+            quantity: Decimal::ONE,
+            reduce_only: true,
+            time_in_force: TimeInForce::Ioc,
+            quantity_mode: QuantityMode::Base,
+        };
+
+        let err = adapter.place_order(&credentials(), &request).await.unwrap_err();
+        assert!(ExchangeError::is_reduce_only_rejected(&err), "{:#}", err);
+        assert!(err.to_string().contains("current position is zero"), "{}", err);
+
+        let err = adapter.place_order(&credentials(), &request).await.unwrap_err();
+        assert!(!ExchangeError::is_reduce_only_rejected(&err), "{:#}", err);
+        assert!(err.to_string().contains("110007"), "{}", err);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_margin_and_risk_limit_rejections_are_reported_by_kind() {
+        use crate::exchange::ExchangeError;
+
+        let (url, server) = serve_http(vec![
+            ("200 OK", r#"{"retCode":110007,"retMsg":"ab not enough for new order","result":{}}"#),
+            ("200 OK", r#"{"retCode":110044,"retMsg":"Available margin is insufficient","result":{}}"#),
+            (
+                "200 OK",
+                r#"{"retCode":110090,"retMsg":"Order placement failed as your position may exceed the max limit allowed at the current leverage","result":{}}"#,
+            ),
+            ("200 OK", r#"{"retCode":10001,"retMsg":"params error: qty invalid","result":{}}"#),
+        ])
+        .await;
+        let adapter = BybitAdapter::new(config(url)).await.unwrap();
+        let request = OrderRequest {
+            client_order_id: "cs1".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some(Decimal::ONE_HUNDRED),
+            quantity: Decimal::ONE,
+            reduce_only: false,
+            time_in_force: TimeInForce::Gtc,
+            quantity_mode: QuantityMode::Base,
+        };
+
+        for _ in 0..2 {
+            let err = adapter.place_order(&credentials(), &request).await.unwrap_err();
+            assert!(ExchangeError::is_insufficient_margin(&err), "{:#}", err);
+            assert!(!ExchangeError::is_risk_limit(&err), "{:#}", err);
+        }
+
+        let err = adapter.place_order(&credentials(), &request).await.unwrap_err();
+        assert!(ExchangeError::is_risk_limit(&err), "{:#}", err);
+        assert!(err.to_string().contains("110090"), "{}", err);
+
+        // Anything else stays a generic error
+        let err = adapter.place_order(&credentials(), &request).await.unwrap_err();
+        assert!(!ExchangeError::is_insufficient_margin(&err) && !ExchangeError::is_risk_limit(&err), "{:#}", err);
+        assert!(err.to_string().contains("10001"), "{}", err);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_broker_tag_is_sent_as_referer() {
+        let placed = r#"{"retCode":0,"retMsg":"OK","result":{"orderId":"7","orderLinkId":"cs1"}}"#;
+        let (url, server) = serve_http_raw(vec![("200 OK", placed), ("200 OK", placed)]).await;
+        let tagged = BybitAdapter::new(ExchangeConfig {
+            order_tag: Some("Ab000123".to_string()),
+            ..config(url.clone())
+        })
+        .await
+        .unwrap();
+        let untagged = BybitAdapter::new(config(url)).await.unwrap();
+        let request = OrderRequest {
+            client_order_id: "cs1".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some(Decimal::ONE_HUNDRED),
+            quantity: Decimal::ONE,
+            reduce_only: false,
+            time_in_force: TimeInForce::Gtc,
+            quantity_mode: QuantityMode::Base,
+        };
+
+        tagged.place_order(&credentials(), &request).await.unwrap();
+        untagged.place_order(&credentials(), &request).await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].to_lowercase().contains("\r\nreferer: ab000123\r\n"), "{:?}", requests);
+        assert!(!requests[1].to_lowercase().contains("referer"), "{:?}", requests);
+    }
+
+    #[tokio::test]
+    async fn test_risk_limit_is_raised_to_the_lowest_tier_that_fits() {
+        let (url, server) = serve_http_raw(vec![
+            (
+                "200 OK",
+                r#"{"retCode":0,"retMsg":"OK","result":{"category":"linear","list":[{"id":3,"symbol":"BTCUSDT","riskLimitValue":"6000000","maxLeverage":"80.00"},{"id":1,"symbol":"BTCUSDT","riskLimitValue":"2000000","maxLeverage":"100.00"},{"id":2,"symbol":"BTCUSDT","riskLimitValue":"4000000","maxLeverage":"90.00"}]}}"#,
+            ),
+            ("200 OK", r#"{"retCode":0,"retMsg":"OK","result":{"riskId":2,"riskLimitValue":"4000000","category":"linear"}}"#),
+        ])
+        .await;
+        let adapter = BybitAdapter::new(config(url)).await.unwrap();
+
+        let limit = adapter
+            .set_risk_limit(&credentials(), "BTCUSDT", Decimal::from(2_500_000))
+            .await
+            .unwrap();
+        assert_eq!(limit, RiskLimit { max_notional_usd: Decimal::from(4_000_000), tier: Some("2".to_string()) });
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /v5/market/risk-limit?category=linear&symbol=BTCUSDT "), "{}", requests[0]);
+        assert!(requests[1].starts_with("POST /v5/position/set-risk-limit "), "{}", requests[1]);
+        assert!(requests[1].contains(r#""riskId":2"#), "{}", requests[1]);
+    }
+
+    #[tokio::test]
+    async fn test_order_timestamp_is_in_millis() {
+        let (url, _server) = serve_http(vec![(
+            "200 OK",
+            r#"{"retCode":0,"retMsg":"OK","result":{"list":[{"orderId":"7","orderLinkId":"cs1","symbol":"BTCUSDT","side":"Buy","orderType":"Limit","price":"100","qty":"1","cumExecQty":"1","avgPrice":"100","orderStatus":"Filled","updatedTime":"1672217577714"}]}}"#,
+        )])
+        .await;
+        let adapter = BybitAdapter::new(config(url)).await.unwrap();
+
+        let order = adapter.get_order(&credentials(), "BTCUSDT", "7").await.unwrap();
+        assert_eq!(order.timestamp, 1672217577714);
+    }
+
+    #[tokio::test]
+    async fn test_orders_are_reported_under_canonical_symbols() {
+        let (url, server) = serve_http(vec![
+            (
+                "200 OK",
+                r#"{"retCode":0,"retMsg":"OK","result":{"list":[{"quoteCoin":"USDT"}],"nextPageCursor":"first%3DBTCUSDT"}}"#,
+            ),
+            ("200 OK", r#"{"retCode":0,"retMsg":"OK","result":{"list":[{"quoteCoin":"USDC"}],"nextPageCursor":""}}"#),
+            (
+                "200 OK",
+                r#"{"retCode":0,"retMsg":"OK","result":{"list":[{"orderId":"7","orderLinkId":"cs1","symbol":"ETHUSDC","side":"Buy","orderType":"Limit","price":"100","qty":"1","cumExecQty":"1","avgPrice":"100","orderStatus":"Filled","updatedTime":"1672217577714"}]}}"#,
+            ),
+        ])
+        .await;
+        let adapter = BybitAdapter::new(config(url)).await.unwrap();
+
+        adapter.load_quote_assets().await.unwrap();
+        let order = adapter.get_order(&credentials(), "ETH/USDC", "7").await.unwrap();
+
+        assert_eq!(order.symbol, "ETH/USDC");
+        let requests = server.await.unwrap();
+        assert_eq!(
+            requests[..2],
+            [
+                "GET /v5/market/instruments-info?category=linear&limit=1000&cursor= HTTP/1.1",
+                "GET /v5/market/instruments-info?category=linear&limit=1000&cursor=first%3DBTCUSDT HTTP/1.1",
+            ]
+        );
+    }
+}
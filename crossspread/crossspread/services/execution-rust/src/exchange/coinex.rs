@@ -10,23 +10,33 @@ use sha2::Sha256;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{debug, info};
 
-use super::{Credentials, ExchangeAdapter, OrderRequest, OrderResponse, OrderStatus, OrderType, Side};
+use super::{
+    epoch_millis, parse_json, parse_level_rows, Credentials, ExchangeAdapter, OrderBook, OrderRequest,
+    OrderResponse, OrderStatus, OrderType, QuoteAssets, Side, TimeInForce,
+};
+use super::raw_http::SendTraced;
 use crate::config::ExchangeConfig;
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// Most orders one page of pending orders holds
+const PENDING_PAGE_SIZE: usize = 100;
+
 pub struct CoinexAdapter {
     config: ExchangeConfig,
     client: Client,
+    quote_assets: QuoteAssets,
 }
 
 impl CoinexAdapter {
     pub async fn new(config: ExchangeConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .build()?;
+        let client = super::http_client(&config)?;
 
-        Ok(Self { config, client })
+        Ok(Self {
+            config,
+            client,
+            quote_assets: QuoteAssets::default(),
+        })
     }
 
     fn timestamp() -> i64 {
@@ -35,19 +45,6 @@ impl CoinexAdapter {
             .unwrap()
             .as_millis() as i64
     }
-
-    fn sign(&self, secret: &str, method: &str, path: &str, timestamp: i64, body: &str) -> String {
-        let prepared = format!("{}{}{}{}",
-            method.to_uppercase(),
-            path,
-            body,
-            timestamp
-        );
-        let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
-            .expect("HMAC can take key of any size");
-        mac.update(prepared.as_bytes());
-        hex::encode(mac.finalize().into_bytes()).to_lowercase()
-    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -66,6 +63,7 @@ struct CoinexOrder {
     order_type: i32,
     amount: String,
     price: String,
+    /// Filled amount, omitted by some endpoints before the first trade
     deal_amount: Option<String>,
     avg_price: Option<String>,
     status: String,
@@ -79,16 +77,44 @@ impl ExchangeAdapter for CoinexAdapter {
         "coinex"
     }
 
+    fn to_native_symbol(&self, base: &str, quote: &str) -> String {
+        format!("{}{}", base, quote)
+    }
+
+    fn to_canonical_symbol(&self, native: &str) -> Option<String> {
+        self.quote_assets.canonical(native)
+    }
+
+    async fn load_quote_assets(&self) -> Result<()> {
+        let url = format!("{}/v2/futures/market", self.config.rest_url);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct Market {
+            quote_ccy: String,
+        }
+
+        let resp: CoinexResponse<Vec<Market>> = parse_json(&body)?;
+        if resp.code != 0 {
+            anyhow::bail!("CoinEx market error: {} - {}", resp.code, resp.message);
+        }
+        self.quote_assets.set(resp.data.unwrap_or_default().into_iter().map(|m| m.quote_ccy));
+        Ok(())
+    }
+
     async fn place_order(
         &self,
         credentials: &Credentials,
         request: &OrderRequest,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(&request.symbol);
         let timestamp = Self::timestamp();
         let path = "/v2/futures/order";
         
         let body = serde_json::json!({
-            "market": request.symbol,
+            "market": symbol,
             "side": match request.side {
                 Side::Buy => 1,
                 Side::Sell => 2,
@@ -99,12 +125,14 @@ impl ExchangeAdapter for CoinexAdapter {
             },
             "amount": request.quantity.to_string(),
             "price": request.price.map(|p| p.to_string()),
+            "effect_type": if request.time_in_force == TimeInForce::Ioc { 2 } else { 1 },
+            "option": if request.time_in_force == TimeInForce::PostOnly { 1 } else { 0 },
             "client_id": request.client_order_id,
         }).to_string();
 
-        let signature = self.sign(&credentials.api_secret, "POST", path, timestamp, &body);
+        let signature = sign(&credentials.api_secret, "POST", path, timestamp, &body);
 
-        debug!("Placing CoinEx order: {}", request.symbol);
+        debug!("Placing CoinEx order: {}", symbol);
 
         let url = format!("{}{}", self.config.rest_url, path);
         let response = self.client
@@ -114,7 +142,7 @@ impl ExchangeAdapter for CoinexAdapter {
             .header("X-COINEX-TIMESTAMP", timestamp.to_string())
             .header("Content-Type", "application/json")
             .body(body)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await
             .context("Failed to send order request")?;
 
@@ -125,7 +153,7 @@ impl ExchangeAdapter for CoinexAdapter {
             anyhow::bail!("CoinEx order failed: {} - {}", status, body);
         }
 
-        let resp: CoinexResponse<CoinexOrder> = serde_json::from_str(&body)
+        let resp: CoinexResponse<CoinexOrder> = parse_json(&body)
             .context("Failed to parse order response")?;
 
         if resp.code != 0 {
@@ -139,7 +167,7 @@ impl ExchangeAdapter for CoinexAdapter {
         Ok(OrderResponse {
             exchange_order_id: order.order_id.to_string(),
             client_order_id: order.client_id.unwrap_or_default(),
-            symbol: order.market,
+            symbol: self.canonical_symbol(&order.market),
             side: match order.side {
                 1 => Side::Buy,
                 _ => Side::Sell,
@@ -153,7 +181,7 @@ impl ExchangeAdapter for CoinexAdapter {
             filled_quantity: order.deal_amount.and_then(|s| s.parse().ok()).unwrap_or_default(),
             avg_fill_price: order.avg_price.and_then(|s| s.parse().ok()),
             status: parse_coinex_status(&order.status),
-            timestamp: order.created_at,
+            timestamp: epoch_millis(order.created_at),
         })
     }
 
@@ -163,6 +191,7 @@ impl ExchangeAdapter for CoinexAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
         let timestamp = Self::timestamp();
         let path = "/v2/futures/order";
         
@@ -171,7 +200,7 @@ impl ExchangeAdapter for CoinexAdapter {
             "order_id": order_id.parse::<i64>().unwrap_or(0),
         }).to_string();
 
-        let signature = self.sign(&credentials.api_secret, "DELETE", path, timestamp, &body);
+        let signature = sign(&credentials.api_secret, "DELETE", path, timestamp, &body);
 
         let url = format!("{}{}", self.config.rest_url, path);
         let response = self.client
@@ -181,18 +210,18 @@ impl ExchangeAdapter for CoinexAdapter {
             .header("X-COINEX-TIMESTAMP", timestamp.to_string())
             .header("Content-Type", "application/json")
             .body(body)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
         let body = response.text().await?;
-        let resp: CoinexResponse<CoinexOrder> = serde_json::from_str(&body)?;
+        let resp: CoinexResponse<CoinexOrder> = parse_json(&body)?;
 
         let order = resp.data.ok_or_else(|| anyhow::anyhow!("No order data"))?;
 
         Ok(OrderResponse {
             exchange_order_id: order.order_id.to_string(),
             client_order_id: order.client_id.unwrap_or_default(),
-            symbol: order.market,
+            symbol: self.canonical_symbol(&order.market),
             side: match order.side {
                 1 => Side::Buy,
                 _ => Side::Sell,
@@ -203,7 +232,7 @@ impl ExchangeAdapter for CoinexAdapter {
             filled_quantity: order.deal_amount.and_then(|s| s.parse().ok()).unwrap_or_default(),
             avg_fill_price: order.avg_price.and_then(|s| s.parse().ok()),
             status: OrderStatus::Cancelled,
-            timestamp: order.created_at,
+            timestamp: epoch_millis(order.created_at),
         })
     }
 
@@ -213,10 +242,11 @@ impl ExchangeAdapter for CoinexAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
         let timestamp = Self::timestamp();
         let path = format!("/v2/futures/order?market={}&order_id={}", symbol, order_id);
         
-        let signature = self.sign(&credentials.api_secret, "GET", &path, timestamp, "");
+        let signature = sign(&credentials.api_secret, "GET", &path, timestamp, "");
 
         let url = format!("{}{}", self.config.rest_url, path);
         let response = self.client
@@ -224,39 +254,73 @@ impl ExchangeAdapter for CoinexAdapter {
             .header("X-COINEX-KEY", &credentials.api_key)
             .header("X-COINEX-SIGN", &signature)
             .header("X-COINEX-TIMESTAMP", timestamp.to_string())
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
         let body = response.text().await?;
-        let resp: CoinexResponse<CoinexOrder> = serde_json::from_str(&body)?;
+        let resp: CoinexResponse<CoinexOrder> = parse_json(&body)?;
 
         let order = resp.data.ok_or_else(|| anyhow::anyhow!("No order data"))?;
 
-        Ok(OrderResponse {
-            exchange_order_id: order.order_id.to_string(),
-            client_order_id: order.client_id.unwrap_or_default(),
-            symbol: order.market,
-            side: match order.side {
-                1 => Side::Buy,
-                _ => Side::Sell,
-            },
-            order_type: match order.order_type {
-                1 => OrderType::Limit,
-                _ => OrderType::Market,
-            },
-            price: order.price.parse().ok(),
-            quantity: order.amount.parse().unwrap_or_default(),
-            filled_quantity: order.deal_amount.and_then(|s| s.parse().ok()).unwrap_or_default(),
-            avg_fill_price: order.avg_price.and_then(|s| s.parse().ok()),
-            status: parse_coinex_status(&order.status),
-            timestamp: order.created_at,
-        })
+        Ok(order_response(order).canonical(self))
+    }
+
+    async fn get_open_orders(&self, credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        let symbol = self.native_symbol(symbol);
+
+        #[derive(Deserialize)]
+        struct Pagination {
+            has_next: bool,
+        }
+
+        // The page's orders sit beside the pagination, outside `data`
+        #[derive(Deserialize)]
+        struct PendingOrders {
+            code: i32,
+            message: String,
+            #[serde(default)]
+            data: Vec<CoinexOrder>,
+            pagination: Option<Pagination>,
+        }
+
+        let mut orders = Vec::new();
+        for page in 1.. {
+            let timestamp = Self::timestamp();
+            let path = format!(
+                "/v2/futures/pending-order?market={}&market_type=FUTURES&page={}&limit={}",
+                symbol, page, PENDING_PAGE_SIZE
+            );
+
+            let signature = sign(&credentials.api_secret, "GET", &path, timestamp, "");
+
+            let url = format!("{}{}", self.config.rest_url, path);
+            let response = self.client
+                .get(&url)
+                .header("X-COINEX-KEY", &credentials.api_key)
+                .header("X-COINEX-SIGN", &signature)
+                .header("X-COINEX-TIMESTAMP", timestamp.to_string())
+                .send_traced(self.config.log_raw_http)
+                .await?;
+
+            let body = response.text().await?;
+            let resp: PendingOrders = parse_json(&body)?;
+            if resp.code != 0 {
+                anyhow::bail!("CoinEx open orders error: {} - {}", resp.code, resp.message);
+            }
+
+            orders.extend(resp.data.into_iter().map(|order| order_response(order).canonical(self)));
+            if !resp.pagination.is_some_and(|p| p.has_next) {
+                break;
+            }
+        }
+        Ok(orders)
     }
 
     async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
+        let symbol = self.native_symbol(symbol);
         let url = format!("{}/v2/futures/ticker?market={}", self.config.rest_url, symbol);
         
-        let response = self.client.get(&url).send().await?;
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
         let body = response.text().await?;
         
         #[derive(Deserialize)]
@@ -265,7 +329,7 @@ impl ExchangeAdapter for CoinexAdapter {
             best_ask_price: String,
         }
         
-        let resp: CoinexResponse<TickerData> = serde_json::from_str(&body)?;
+        let resp: CoinexResponse<TickerData> = parse_json(&body)?;
         let ticker = resp.data.ok_or_else(|| anyhow::anyhow!("No ticker data"))?;
 
         Ok((
@@ -274,11 +338,74 @@ impl ExchangeAdapter for CoinexAdapter {
         ))
     }
 
+    async fn get_order_book(&self, symbol: &str, depth: usize) -> Result<OrderBook> {
+        let symbol = self.native_symbol(symbol);
+        // Only these limits are accepted, so request the smallest that covers `depth`
+        let limit = [5, 10, 20, 50]
+            .into_iter()
+            .find(|&limit| limit >= depth)
+            .unwrap_or(50);
+        let url = format!(
+            "{}/v2/futures/depth?market={}&limit={}&interval=0",
+            self.config.rest_url, symbol, limit
+        );
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct Depth {
+            bids: Vec<Vec<serde_json::Value>>,
+            asks: Vec<Vec<serde_json::Value>>,
+            updated_at: i64,
+        }
+
+        #[derive(Deserialize)]
+        struct DepthData {
+            depth: Depth,
+        }
+
+        let resp: CoinexResponse<DepthData> = parse_json(&body)?;
+        if resp.code != 0 {
+            anyhow::bail!("CoinEx order book error: {} - {}", resp.code, resp.message);
+        }
+        let book = resp.data.ok_or_else(|| anyhow::anyhow!("No order book data"))?.depth;
+
+        Ok(OrderBook::from_levels(
+            parse_level_rows(&book.bids)?,
+            parse_level_rows(&book.asks)?,
+            depth,
+            book.updated_at,
+        ))
+    }
+
     fn is_connected(&self) -> bool {
         true
     }
 }
 
+fn order_response(order: CoinexOrder) -> OrderResponse {
+    OrderResponse {
+        exchange_order_id: order.order_id.to_string(),
+        client_order_id: order.client_id.unwrap_or_default(),
+        symbol: order.market,
+        side: match order.side {
+            1 => Side::Buy,
+            _ => Side::Sell,
+        },
+        order_type: match order.order_type {
+            1 => OrderType::Limit,
+            _ => OrderType::Market,
+        },
+        price: order.price.parse().ok(),
+        quantity: order.amount.parse().unwrap_or_default(),
+        filled_quantity: order.deal_amount.and_then(|s| s.parse().ok()).unwrap_or_default(),
+        avg_fill_price: order.avg_price.and_then(|s| s.parse().ok()),
+        status: parse_coinex_status(&order.status),
+        timestamp: epoch_millis(order.created_at),
+    }
+}
+
 fn parse_coinex_status(status: &str) -> OrderStatus {
     match status {
         "open" | "not_deal" => OrderStatus::Open,
@@ -288,3 +415,58 @@ fn parse_coinex_status(status: &str) -> OrderStatus {
         _ => OrderStatus::Pending,
     }
 }
+
+/// HMAC-SHA256 of `METHOD + path + body + timestamp`, lowercase hex.
+/// `path` includes any query string.
+pub fn sign(secret: &str, method: &str, path: &str, timestamp: i64, body: &str) -> String {
+    let prepared = format!("{}{}{}{}",
+        method.to_uppercase(),
+        path,
+        body,
+        timestamp
+    );
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(prepared.as_bytes());
+    hex::encode(mac.finalize().into_bytes()).to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{credentials, exchange_config, serve_http};
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn test_order_book_is_parsed_best_first() {
+        let (url, server) = serve_http(vec![(
+            "200 OK",
+            r#"{"code":0,"data":{"depth":{"asks":[["70851.94","0.0002"],["70852.1","1.5"]],"bids":[["70848.43","0.0003"],["70848","2"]],"checksum":2335742853,"last":"70850","updated_at":1713775584128},"is_full":true,"market":"BTCUSDT"},"message":"OK"}"#,
+        )])
+        .await;
+        let adapter = CoinexAdapter::new(exchange_config("coinex", url)).await.unwrap();
+
+        let book = adapter.get_order_book("BTCUSDT", 2).await.unwrap();
+
+        assert_eq!(book.bids[0].price, dec!(70848.43));
+        assert_eq!(book.bids[1].quantity, dec!(2));
+        assert_eq!(book.asks[0].price, dec!(70851.94));
+        assert_eq!(book.asks[1].quantity, dec!(1.5));
+        assert_eq!(book.timestamp, 1713775584128);
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /v2/futures/depth?market=BTCUSDT&limit=5&interval=0"), "{:?}", requests);
+    }
+
+    #[tokio::test]
+    async fn test_order_timestamp_is_in_millis() {
+        let (url, _server) = serve_http(vec![(
+            "200 OK",
+            r#"{"code":0,"message":"OK","data":{"order_id":13400,"market":"BTCUSDT","side":1,"type":1,"amount":"2","price":"100","deal_amount":"2","avg_price":"100","status":"filled","created_at":1713775584128,"client_id":"cs1"}}"#,
+        )])
+        .await;
+        let adapter = CoinexAdapter::new(exchange_config("coinex", url)).await.unwrap();
+
+        let order = adapter.get_order(&credentials(), "BTCUSDT", "13400").await.unwrap();
+        assert_eq!(order.timestamp, 1713775584128);
+    }
+}
@@ -0,0 +1,880 @@
+//! dYdX v4 perpetuals adapter
+//!
+//! Market data and order state come from the indexer REST API; orders are
+//! placed and cancelled by broadcasting signed Cosmos transactions to a
+//! validator node's REST endpoint.
+//!
+//! Credentials: `api_key` is the dYdX address (`dydx1...`) and `wallet_key`
+//! the hex-encoded secp256k1 private key for that address. Orders are placed
+//! from the subaccount numbered `sub_account`, subaccount 0 if it is unset.
+//!
+//! Limitations on v4:
+//! - Only short-term orders are used, so a resting order expires on its own
+//!   after `SHORT_BLOCK_WINDOW` blocks.
+//! - Market orders are sent as IOC limits priced off the top of book.
+//! - There is no batch place/cancel; each order is its own transaction.
+//! - The indexer doesn't report an average fill price on orders, so fill
+//!   prices come from the order's individual fills.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use k256::ecdsa::{signature::Signer, Signature, SigningKey};
+use reqwest::Client;
+use rust_decimal::prelude::*;
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use tracing::{debug, info};
+
+use super::{
+    canonical_from_separated, now_millis, parse_json, BookLevel, Credentials, ExchangeAdapter, Fill, OrderBook,
+    OrderRequest, OrderResponse, OrderStatus, OrderType, Side, TimeInForce,
+};
+use super::raw_http::SendTraced;
+use crate::config::ExchangeConfig;
+
+const MAINNET_NODE_URL: &str = "https://dydx-ops-rest.kingnodes.com";
+const TESTNET_NODE_URL: &str = "https://test-dydx-rest.kingnodes.com";
+const MAINNET_CHAIN_ID: &str = "dydx-mainnet-1";
+const TESTNET_CHAIN_ID: &str = "dydx-testnet-4";
+
+/// Blocks a short-term order stays valid for (protocol maximum is 20)
+const SHORT_BLOCK_WINDOW: u32 = 20;
+/// Quote quantums are in units of 10^-6 USDC
+const QUOTE_QUANTUMS_ATOMIC_RESOLUTION: i32 = -6;
+/// Worst price accepted for market orders, relative to the touch
+const MARKET_ORDER_SLIPPAGE: Decimal = dec!(0.05);
+/// Most fills one page of the indexer's fills holds
+const FILLS_PAGE_SIZE: usize = 100;
+
+const MSG_PLACE_ORDER: &str = "/dydxprotocol.clob.MsgPlaceOrder";
+const MSG_CANCEL_ORDER: &str = "/dydxprotocol.clob.MsgCancelOrder";
+const SECP256K1_PUBKEY: &str = "/cosmos.crypto.secp256k1.PubKey";
+
+pub struct DydxAdapter {
+    config: ExchangeConfig,
+    client: Client,
+    node_url: String,
+    chain_id: &'static str,
+}
+
+impl DydxAdapter {
+    pub async fn new(config: ExchangeConfig) -> Result<Self> {
+        let client = super::http_client(&config)?;
+
+        let (node_url, chain_id) = if config.testnet {
+            (TESTNET_NODE_URL, TESTNET_CHAIN_ID)
+        } else {
+            (MAINNET_NODE_URL, MAINNET_CHAIN_ID)
+        };
+
+        Ok(Self {
+            config,
+            client,
+            node_url: node_url.to_string(),
+            chain_id,
+        })
+    }
+
+    async fn get_market(&self, symbol: &str) -> Result<DydxMarket> {
+        let url = format!("{}/v4/perpetualMarkets?ticker={}", self.config.rest_url, symbol);
+        let body = self.client.get(&url).send_traced(self.config.log_raw_http).await?.text().await?;
+
+        let resp: DydxMarketsResponse = parse_json(&body)
+            .context("Failed to parse dYdX market response")?;
+        resp.markets
+            .into_values()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Unknown dYdX market: {}", symbol))
+    }
+
+    async fn get_height(&self) -> Result<u32> {
+        let url = format!("{}/v4/height", self.config.rest_url);
+        let body = self.client.get(&url).send_traced(self.config.log_raw_http).await?.text().await?;
+
+        #[derive(Deserialize)]
+        struct Height {
+            height: String,
+        }
+
+        let height: Height = parse_json(&body)?;
+        Ok(height.height.parse()?)
+    }
+
+    async fn get_account(&self, address: &str) -> Result<(u64, u64)> {
+        let url = format!("{}/cosmos/auth/v1beta1/accounts/{}", self.node_url, address);
+        let body = self.client.get(&url).send_traced(self.config.log_raw_http).await?.text().await?;
+
+        #[derive(Deserialize)]
+        struct Account {
+            account_number: String,
+            sequence: String,
+        }
+
+        #[derive(Deserialize)]
+        struct AccountResponse {
+            account: Account,
+        }
+
+        let resp: AccountResponse = parse_json(&body)
+            .context("Failed to parse dYdX account response")?;
+        Ok((resp.account.account_number.parse()?, resp.account.sequence.parse()?))
+    }
+
+    /// Sign and broadcast a single-message transaction, returning the tx hash
+    async fn broadcast(&self, credentials: &Credentials, type_url: &str, msg: Vec<u8>) -> Result<String> {
+        let wallet_key = credentials
+            .wallet_key
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("dYdX requires a wallet key"))?;
+        let key_bytes = hex::decode(wallet_key.trim_start_matches("0x"))
+            .context("dYdX wallet key is not valid hex")?;
+        let signing_key = SigningKey::from_slice(&key_bytes)
+            .context("dYdX wallet key is not a valid secp256k1 key")?;
+
+        let (account_number, sequence) = self.get_account(&credentials.api_key).await?;
+        let tx_bytes = build_tx(&signing_key, self.chain_id, account_number, sequence, type_url, msg);
+
+        let body = serde_json::json!({
+            "tx_bytes": STANDARD.encode(tx_bytes),
+            "mode": "BROADCAST_MODE_SYNC",
+        }).to_string();
+
+        let url = format!("{}/cosmos/tx/v1beta1/txs", self.node_url);
+        let response = self.client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send_traced(self.config.log_raw_http)
+            .await
+            .context("Failed to broadcast dYdX transaction")?;
+
+        let status = response.status();
+        let body = response.text().await?;
+
+        if !status.is_success() {
+            anyhow::bail!("dYdX broadcast failed: {} - {}", status, body);
+        }
+
+        #[derive(Deserialize)]
+        struct TxResponse {
+            code: u32,
+            txhash: String,
+            raw_log: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct BroadcastResponse {
+            tx_response: TxResponse,
+        }
+
+        let resp: BroadcastResponse = parse_json(&body)
+            .context("Failed to parse dYdX broadcast response")?;
+
+        if resp.tx_response.code != 0 {
+            anyhow::bail!(
+                "dYdX transaction rejected: {} - {}",
+                resp.tx_response.code,
+                resp.tx_response.raw_log.unwrap_or_default()
+            );
+        }
+
+        Ok(resp.tx_response.txhash)
+    }
+
+    async fn find_order(&self, credentials: &Credentials, symbol: &str, client_id: &str) -> Result<DydxOrder> {
+        let url = format!(
+            "{}/v4/orders?address={}&subaccountNumber={}&ticker={}&limit=100",
+            self.config.rest_url,
+            credentials.api_key,
+            subaccount_number(credentials)?,
+            symbol
+        );
+        let body = self.client.get(&url).send_traced(self.config.log_raw_http).await?.text().await?;
+
+        let orders: Vec<DydxOrder> = parse_json(&body)
+            .context("Failed to parse dYdX orders response")?;
+        orders
+            .into_iter()
+            .find(|o| o.client_id == client_id)
+            .ok_or_else(|| anyhow::anyhow!("Order not found"))
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct DydxMarketsResponse {
+    markets: std::collections::HashMap<String, DydxMarket>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DydxMarket {
+    clob_pair_id: String,
+    atomic_resolution: i32,
+    quantum_conversion_exponent: i32,
+    step_base_quantums: u64,
+    subticks_per_tick: u64,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DydxOrder {
+    /// Indexer order id, which fills refer to
+    id: String,
+    client_id: String,
+    ticker: String,
+    side: String,
+    size: String,
+    /// Filled size per the indexer, which can trail the chain by a block
+    total_filled: String,
+    price: String,
+    #[serde(rename = "type")]
+    order_type: String,
+    status: String,
+    updated_at: Option<String>,
+}
+
+#[async_trait]
+impl ExchangeAdapter for DydxAdapter {
+    fn id(&self) -> &str {
+        "dydx"
+    }
+
+    // All perpetual markets are USD-quoted (USDC-settled), whatever the quote asset
+    fn to_native_symbol(&self, base: &str, _quote: &str) -> String {
+        format!("{}-USD", base)
+    }
+
+    fn to_canonical_symbol(&self, native: &str) -> Option<String> {
+        canonical_from_separated(native, '-')
+    }
+
+    async fn place_order(
+        &self,
+        credentials: &Credentials,
+        request: &OrderRequest,
+    ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(&request.symbol);
+        let market = self.get_market(&symbol).await?;
+        let height = self.get_height().await?;
+
+        let (price, time_in_force) = match request.order_type {
+            OrderType::Limit => (
+                request.price.ok_or_else(|| anyhow::anyhow!("Limit order requires a price"))?,
+                match request.time_in_force {
+                    TimeInForce::Gtc | TimeInForce::Gtd { .. } => TIME_IN_FORCE_UNSPECIFIED,
+                    TimeInForce::Ioc => TIME_IN_FORCE_IOC,
+                    TimeInForce::PostOnly => TIME_IN_FORCE_POST_ONLY,
+                },
+            ),
+            OrderType::Market => {
+                let (bid, ask) = self.get_best_price(&symbol).await?;
+                let price = match request.side {
+                    Side::Buy => ask * (Decimal::ONE + MARKET_ORDER_SLIPPAGE),
+                    Side::Sell => bid * (Decimal::ONE - MARKET_ORDER_SLIPPAGE),
+                };
+                (price, TIME_IN_FORCE_IOC)
+            }
+        };
+
+        let order = Order {
+            owner: credentials.api_key.clone(),
+            subaccount_number: subaccount_number(credentials)?,
+            client_id: client_id_for(&request.client_order_id),
+            clob_pair_id: market.clob_pair_id.parse()?,
+            side: request.side,
+            quantums: calculate_quantums(request.quantity, market.atomic_resolution, market.step_base_quantums)?,
+            subticks: calculate_subticks(
+                price,
+                market.atomic_resolution,
+                market.quantum_conversion_exponent,
+                market.subticks_per_tick,
+            )?,
+            good_til_block: height + SHORT_BLOCK_WINDOW,
+            time_in_force,
+            reduce_only: request.reduce_only,
+        };
+
+        debug!("Placing dYdX order: {}", symbol);
+
+        let tx_hash = self
+            .broadcast(credentials, MSG_PLACE_ORDER, encode_place_order(&order))
+            .await?;
+
+        info!("dYdX order placed: {} tx={}", order.client_id, tx_hash);
+
+        Ok(OrderResponse {
+            exchange_order_id: order.client_id.to_string(),
+            client_order_id: request.client_order_id.clone(),
+            symbol: self.canonical_symbol(&symbol),
+            side: request.side,
+            order_type: request.order_type,
+            price: Some(price),
+            quantity: request.quantity,
+            filled_quantity: Decimal::ZERO,
+            avg_fill_price: None,
+            status: OrderStatus::Pending,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        })
+    }
+
+    async fn cancel_order(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
+        let market = self.get_market(&symbol).await?;
+        let height = self.get_height().await?;
+
+        let msg = encode_cancel_order(
+            &credentials.api_key,
+            subaccount_number(credentials)?,
+            order_id.parse().context("Invalid dYdX client id")?,
+            market.clob_pair_id.parse()?,
+            height + SHORT_BLOCK_WINDOW,
+        );
+        self.broadcast(credentials, MSG_CANCEL_ORDER, msg).await?;
+
+        Ok(OrderResponse {
+            exchange_order_id: order_id.to_string(),
+            client_order_id: String::new(),
+            symbol: self.canonical_symbol(&symbol),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: None,
+            quantity: Decimal::ZERO,
+            filled_quantity: Decimal::ZERO,
+            avg_fill_price: None,
+            status: OrderStatus::Cancelled,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        })
+    }
+
+    async fn get_order(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
+        let order = self.find_order(credentials, &symbol, order_id).await?;
+
+        Ok(order_response(order).canonical(self))
+    }
+
+    // Orders only carry the numeric client id, so none of them match a client
+    // order id prefix; being short-term, they expire within SHORT_BLOCK_WINDOW
+    // blocks anyway
+    async fn get_open_orders(&self, credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        let symbol = self.native_symbol(symbol);
+        let url = format!(
+            "{}/v4/orders?address={}&subaccountNumber={}&ticker={}&status=OPEN&limit=100",
+            self.config.rest_url,
+            credentials.api_key,
+            subaccount_number(credentials)?,
+            symbol
+        );
+        let body = self.client.get(&url).send_traced(self.config.log_raw_http).await?.text().await?;
+
+        let orders: Vec<DydxOrder> = parse_json(&body)
+            .context("Failed to parse dYdX orders response")?;
+        Ok(orders.into_iter().map(|order| order_response(order).canonical(self)).collect())
+    }
+
+    async fn get_fills(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<Vec<Fill>> {
+        let symbol = self.native_symbol(symbol);
+        let order = self.find_order(credentials, &symbol, order_id).await?;
+        let total_filled: Decimal = order.total_filled.parse()?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct DydxFill {
+            id: String,
+            order_id: Option<String>,
+            price: String,
+            size: String,
+            fee: Option<String>,
+        }
+
+        #[derive(Deserialize)]
+        struct Fills {
+            fills: Vec<DydxFill>,
+        }
+
+        // Fills are listed newest first, so the order's can be pages back
+        // behind later trades. Pages are read until they account for what
+        // the order filled; fills that new ones pushed onto the next page
+        // are only counted once.
+        let mut seen = HashSet::new();
+        let mut fills = Vec::new();
+        let mut filled = Decimal::ZERO;
+        let mut page = 1;
+        while filled < total_filled {
+            let url = format!(
+                "{}/v4/fills?address={}&subaccountNumber={}&market={}&marketType=PERPETUAL&limit={}&page={}",
+                self.config.rest_url,
+                credentials.api_key,
+                subaccount_number(credentials)?,
+                symbol,
+                FILLS_PAGE_SIZE,
+                page
+            );
+            let body = self.client.get(&url).send_traced(self.config.log_raw_http).await?.text().await?;
+            let listed: Fills = parse_json(&body).context("Failed to parse dYdX fills response")?;
+            let last = listed.fills.len() < FILLS_PAGE_SIZE;
+            for fill in listed.fills {
+                if fill.order_id.as_deref() != Some(order.id.as_str()) || !seen.insert(fill.id) {
+                    continue;
+                }
+                let quantity: Decimal = fill.size.parse()?;
+                filled += quantity;
+                fills.push(Fill {
+                    price: fill.price.parse()?,
+                    quantity,
+                    fee: fill.fee.as_deref().map_or(Ok(Decimal::ZERO), str::parse)?,
+                    fee_asset: None,
+                });
+            }
+            if last {
+                break;
+            }
+            page += 1;
+        }
+        Ok(fills)
+    }
+
+    async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
+        let symbol = self.native_symbol(symbol);
+        let url = format!("{}/v4/orderbooks/perpetualMarket/{}", self.config.rest_url, symbol);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct Level {
+            price: String,
+        }
+
+        #[derive(Deserialize)]
+        struct Book {
+            bids: Vec<Level>,
+            asks: Vec<Level>,
+        }
+
+        let book: Book = parse_json(&body)?;
+        let bid = book.bids.first().ok_or_else(|| anyhow::anyhow!("No bid"))?;
+        let ask = book.asks.first().ok_or_else(|| anyhow::anyhow!("No ask"))?;
+
+        Ok((
+            bid.price.parse()?,
+            ask.price.parse()?,
+        ))
+    }
+
+    async fn get_order_book(&self, symbol: &str, depth: usize) -> Result<OrderBook> {
+        let symbol = self.native_symbol(symbol);
+        let url = format!("{}/v4/orderbooks/perpetualMarket/{}", self.config.rest_url, symbol);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct Level {
+            price: String,
+            size: String,
+        }
+
+        #[derive(Deserialize)]
+        struct Book {
+            bids: Vec<Level>,
+            asks: Vec<Level>,
+        }
+
+        let book: Book = parse_json(&body)?;
+        let levels = |levels: Vec<Level>| {
+            levels
+                .into_iter()
+                .map(|level| {
+                    Ok(BookLevel {
+                        price: level.price.parse()?,
+                        quantity: level.size.parse()?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+
+        // The indexer sends the whole book, unstamped
+        Ok(OrderBook::from_levels(levels(book.bids)?, levels(book.asks)?, depth, now_millis()))
+    }
+
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    // Market orders are emulated with IOC limits
+    fn supports_reduce_only_market(&self) -> bool {
+        false
+    }
+}
+
+fn order_response(order: DydxOrder) -> OrderResponse {
+    let filled_quantity: Decimal = order.total_filled.parse().unwrap_or_default();
+    OrderResponse {
+        exchange_order_id: order.client_id,
+        client_order_id: String::new(),
+        symbol: order.ticker,
+        side: match order.side.as_str() {
+            "BUY" => Side::Buy,
+            _ => Side::Sell,
+        },
+        order_type: match order.order_type.as_str() {
+            "MARKET" => OrderType::Market,
+            _ => OrderType::Limit,
+        },
+        price: order.price.parse().ok(),
+        quantity: order.size.parse().unwrap_or_default(),
+        filled_quantity,
+        avg_fill_price: None,
+        status: parse_dydx_status(&order.status, filled_quantity),
+        timestamp: order
+            .updated_at
+            .and_then(|t| chrono::DateTime::parse_from_rfc3339(&t).ok())
+            .map(|t| t.timestamp_millis())
+            .unwrap_or(0),
+    }
+}
+
+fn parse_dydx_status(status: &str, filled: Decimal) -> OrderStatus {
+    match status {
+        "OPEN" if filled > Decimal::ZERO => OrderStatus::Partial,
+        "OPEN" => OrderStatus::Open,
+        "FILLED" => OrderStatus::Filled,
+        "CANCELED" | "BEST_EFFORT_CANCELED" => OrderStatus::Cancelled,
+        _ => OrderStatus::Pending, // BEST_EFFORT_OPENED / UNTRIGGERED
+    }
+}
+
+/// dYdX client ids are u32, so derive one deterministically from our id
+fn client_id_for(client_order_id: &str) -> u32 {
+    let hash = Sha256::digest(client_order_id.as_bytes());
+    u32::from_be_bytes([hash[0], hash[1], hash[2], hash[3]])
+}
+
+/// Round `value` to the nearest multiple of `step`, never below one step
+fn round_to_step(value: Decimal, step: u64) -> Result<u64> {
+    let step = Decimal::from(step);
+    let rounded = ((value / step).round() * step).max(step);
+    rounded.to_u64().ok_or_else(|| anyhow::anyhow!("Value out of range: {}", value))
+}
+
+fn calculate_quantums(size: Decimal, atomic_resolution: i32, step_base_quantums: u64) -> Result<u64> {
+    let raw = size * pow10(-atomic_resolution);
+    round_to_step(raw, step_base_quantums)
+}
+
+fn calculate_subticks(
+    price: Decimal,
+    atomic_resolution: i32,
+    quantum_conversion_exponent: i32,
+    subticks_per_tick: u64,
+) -> Result<u64> {
+    let exponent = atomic_resolution - quantum_conversion_exponent - QUOTE_QUANTUMS_ATOMIC_RESOLUTION;
+    let raw = price * pow10(exponent);
+    round_to_step(raw, subticks_per_tick)
+}
+
+fn pow10(exponent: i32) -> Decimal {
+    let factor = Decimal::from(10u64.pow(exponent.unsigned_abs()));
+    if exponent >= 0 {
+        factor
+    } else {
+        Decimal::ONE / factor
+    }
+}
+
+// --- Protobuf encoding -------------------------------------------------------
+//
+// Only the handful of messages needed for order placement are encoded, by hand,
+// to avoid pulling in a full Cosmos SDK client.
+
+const TIME_IN_FORCE_UNSPECIFIED: u64 = 0;
+const TIME_IN_FORCE_IOC: u64 = 1;
+const TIME_IN_FORCE_POST_ONLY: u64 = 2;
+const SIGN_MODE_DIRECT: u64 = 1;
+
+struct Order {
+    owner: String,
+    subaccount_number: u32,
+    client_id: u32,
+    clob_pair_id: u32,
+    side: Side,
+    quantums: u64,
+    subticks: u64,
+    good_til_block: u32,
+    time_in_force: u64,
+    reduce_only: bool,
+}
+
+/// Subaccount the credentials trade from
+fn subaccount_number(credentials: &Credentials) -> Result<u32> {
+    match &credentials.sub_account {
+        Some(number) => number
+            .parse()
+            .with_context(|| format!("dYdX sub-account must be a subaccount number, got {}", number)),
+        None => Ok(0),
+    }
+}
+
+fn put_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8);
+}
+
+fn put_uint(buf: &mut Vec<u8>, field: u32, value: u64) {
+    if value != 0 {
+        put_varint(buf, (field as u64) << 3);
+        put_varint(buf, value);
+    }
+}
+
+fn put_fixed32(buf: &mut Vec<u8>, field: u32, value: u32) {
+    if value != 0 {
+        put_varint(buf, ((field as u64) << 3) | 5);
+        buf.extend_from_slice(&value.to_le_bytes());
+    }
+}
+
+fn put_bytes(buf: &mut Vec<u8>, field: u32, value: &[u8]) {
+    put_varint(buf, ((field as u64) << 3) | 2);
+    put_varint(buf, value.len() as u64);
+    buf.extend_from_slice(value);
+}
+
+fn encode_any(type_url: &str, value: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::new();
+    put_bytes(&mut buf, 1, type_url.as_bytes());
+    put_bytes(&mut buf, 2, value);
+    buf
+}
+
+fn encode_order_id(owner: &str, subaccount_number: u32, client_id: u32, clob_pair_id: u32) -> Vec<u8> {
+    let mut subaccount = Vec::new();
+    put_bytes(&mut subaccount, 1, owner.as_bytes());
+    // subaccount number 0 is the proto default and is omitted
+    put_uint(&mut subaccount, 2, subaccount_number as u64);
+
+    let mut buf = Vec::new();
+    put_bytes(&mut buf, 1, &subaccount);
+    put_fixed32(&mut buf, 2, client_id);
+    // order_flags 0 = short-term order
+    put_uint(&mut buf, 4, clob_pair_id as u64);
+    buf
+}
+
+fn encode_place_order(order: &Order) -> Vec<u8> {
+    let mut inner = Vec::new();
+    put_bytes(
+        &mut inner,
+        1,
+        &encode_order_id(&order.owner, order.subaccount_number, order.client_id, order.clob_pair_id),
+    );
+    put_uint(&mut inner, 2, match order.side {
+        Side::Buy => 1,
+        Side::Sell => 2,
+    });
+    put_uint(&mut inner, 3, order.quantums);
+    put_uint(&mut inner, 4, order.subticks);
+    put_uint(&mut inner, 5, order.good_til_block as u64);
+    put_uint(&mut inner, 7, order.time_in_force);
+    put_uint(&mut inner, 8, order.reduce_only as u64);
+
+    let mut buf = Vec::new();
+    put_bytes(&mut buf, 1, &inner);
+    buf
+}
+
+fn encode_cancel_order(
+    owner: &str,
+    subaccount_number: u32,
+    client_id: u32,
+    clob_pair_id: u32,
+    good_til_block: u32,
+) -> Vec<u8> {
+    let mut buf = Vec::new();
+    put_bytes(&mut buf, 1, &encode_order_id(owner, subaccount_number, client_id, clob_pair_id));
+    put_uint(&mut buf, 2, good_til_block as u64);
+    buf
+}
+
+/// Build a signed `TxRaw` (SIGN_MODE_DIRECT, zero fee) carrying a single message
+fn build_tx(
+    signing_key: &SigningKey,
+    chain_id: &str,
+    account_number: u64,
+    sequence: u64,
+    type_url: &str,
+    msg: Vec<u8>,
+) -> Vec<u8> {
+    let mut body = Vec::new();
+    put_bytes(&mut body, 1, &encode_any(type_url, &msg));
+
+    let public_key = signing_key.verifying_key().to_encoded_point(true);
+    let mut pub_key = Vec::new();
+    put_bytes(&mut pub_key, 1, public_key.as_bytes());
+
+    let mut single = Vec::new();
+    put_uint(&mut single, 1, SIGN_MODE_DIRECT);
+    let mut mode_info = Vec::new();
+    put_bytes(&mut mode_info, 1, &single);
+
+    let mut signer_info = Vec::new();
+    put_bytes(&mut signer_info, 1, &encode_any(SECP256K1_PUBKEY, &pub_key));
+    put_bytes(&mut signer_info, 2, &mode_info);
+    put_uint(&mut signer_info, 3, sequence);
+
+    let mut auth_info = Vec::new();
+    put_bytes(&mut auth_info, 1, &signer_info);
+    put_bytes(&mut auth_info, 2, &[]); // empty Fee
+
+    let mut sign_doc = Vec::new();
+    put_bytes(&mut sign_doc, 1, &body);
+    put_bytes(&mut sign_doc, 2, &auth_info);
+    put_bytes(&mut sign_doc, 3, chain_id.as_bytes());
+    put_uint(&mut sign_doc, 4, account_number);
+
+    let mut tx = Vec::new();
+    put_bytes(&mut tx, 1, &body);
+    put_bytes(&mut tx, 2, &auth_info);
+    put_bytes(&mut tx, 3, &sign_direct(signing_key, &sign_doc));
+    tx
+}
+
+/// SIGN_MODE_DIRECT signature of an encoded `SignDoc`: secp256k1 ECDSA over
+/// its SHA-256 with an RFC 6979 nonce and low S, as `r || s`
+pub fn sign_direct(signing_key: &SigningKey, sign_doc: &[u8]) -> Vec<u8> {
+    let signature: Signature = signing_key.sign(sign_doc);
+    signature.to_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_order_book_is_parsed_best_first() {
+        let (url, server) = crate::exchange::mock::serve_http(vec![(
+            "200 OK",
+            r#"{"bids":[{"price":"64990","size":"0.5"},{"price":"64991","size":"1.25"}],"asks":[{"price":"65000","size":"0.3"},{"price":"65001","size":"2"}]}"#,
+        )])
+        .await;
+        let adapter = DydxAdapter::new(crate::exchange::mock::exchange_config("dydx", url)).await.unwrap();
+
+        let book = adapter.get_order_book("BTC-USD", 1).await.unwrap();
+
+        assert_eq!(book.bids, [BookLevel { price: dec!(64991), quantity: dec!(1.25) }]);
+        assert_eq!(book.asks, [BookLevel { price: dec!(65000), quantity: dec!(0.3) }]);
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /v4/orderbooks/perpetualMarket/BTC-USD"), "{:?}", requests);
+    }
+
+    #[test]
+    fn test_quantums_and_subticks() {
+        // BTC-USD market parameters
+        assert_eq!(calculate_quantums(dec!(0.01), -10, 1_000_000).unwrap(), 100_000_000);
+        assert_eq!(calculate_subticks(dec!(50000), -10, -9, 100_000).unwrap(), 5_000_000_000);
+        // Sizes below one step round up to the minimum
+        assert_eq!(calculate_quantums(dec!(0.00000001), -10, 1_000_000).unwrap(), 1_000_000);
+    }
+
+    #[test]
+    fn test_encode_cancel_order() {
+        let msg = encode_cancel_order("dydx1abc", 0, 7, 0, 300);
+        // MsgCancelOrder { OrderId { SubaccountId { owner }, client_id (fixed32) }, good_til_block }
+        let expected = [
+            0x0a, 0x11,
+            0x0a, 0x0a, 0x0a, 0x08, b'd', b'y', b'd', b'x', b'1', b'a', b'b', b'c',
+            0x15, 0x07, 0x00, 0x00, 0x00,
+            0x10, 0xac, 0x02,
+        ];
+        assert_eq!(msg, expected);
+    }
+
+    #[tokio::test]
+    async fn test_fills_are_read_across_pages() {
+        let fill = |id: usize, order_id: &str, size: &str| {
+            format!(r#"{{"id":"f{}","orderId":"{}","price":"65000","size":"{}","fee":"0.01"}}"#, id, order_id, size)
+        };
+        // The order's first fill is behind a page of other trades
+        let mut first_page: Vec<_> = (0..FILLS_PAGE_SIZE - 1).map(|id| fill(id, "other", "1")).collect();
+        first_page.push(fill(99, "abc", "0.004"));
+        let second_page = [fill(99, "abc", "0.004"), fill(100, "abc", "0.006")];
+        let (url, server) = crate::exchange::mock::serve_http(vec![
+            (
+                "200 OK",
+                r#"[{"id":"abc","clientId":"7","ticker":"BTC-USD","side":"BUY","size":"0.01","totalFilled":"0.01","price":"65000","type":"LIMIT","status":"FILLED","updatedAt":null}]"#.to_string(),
+            ),
+            ("200 OK", format!(r#"{{"fills":[{}]}}"#, first_page.join(","))),
+            ("200 OK", format!(r#"{{"fills":[{}]}}"#, second_page.join(","))),
+        ])
+        .await;
+        let adapter = DydxAdapter::new(crate::exchange::mock::exchange_config("dydx", url)).await.unwrap();
+
+        let fills = adapter.get_fills(&crate::exchange::mock::credentials(), "BTC-USD", "7").await.unwrap();
+
+        let sizes: Vec<_> = fills.iter().map(|fill| fill.quantity).collect();
+        assert_eq!(sizes, [dec!(0.004), dec!(0.006)]);
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[1].contains("limit=100&page=1"), "{:?}", requests);
+        assert!(requests[2].contains("limit=100&page=2"), "{:?}", requests);
+    }
+
+    #[tokio::test]
+    async fn test_sub_account_is_named_in_orders_and_queries() {
+        let msg = encode_cancel_order("dydx1abc", 2, 7, 0, 300);
+        // SubaccountId now carries its number
+        let expected = [
+            0x0a, 0x13,
+            0x0a, 0x0c, 0x0a, 0x08, b'd', b'y', b'd', b'x', b'1', b'a', b'b', b'c', 0x10, 0x02,
+            0x15, 0x07, 0x00, 0x00, 0x00,
+            0x10, 0xac, 0x02,
+        ];
+        assert_eq!(msg, expected);
+
+        let (url, server) = crate::exchange::mock::serve_http(vec![(
+            "200 OK",
+            r#"[{"id":"abc","clientId":"7","ticker":"BTC-USD","side":"BUY","size":"0.01","totalFilled":"0","price":"65000","type":"LIMIT","status":"OPEN","updatedAt":null}]"#,
+        )])
+        .await;
+        let adapter = DydxAdapter::new(crate::exchange::mock::exchange_config("dydx", url)).await.unwrap();
+        let credentials = Credentials {
+            api_key: "dydx1abc".to_string(),
+            sub_account: Some("2".to_string()),
+            ..crate::exchange::mock::credentials()
+        };
+
+        adapter.get_order(&credentials, "BTC-USD", "7").await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].contains("address=dydx1abc&subaccountNumber=2&"), "{:?}", requests);
+
+        let credentials = Credentials {
+            sub_account: Some("main".to_string()),
+            ..credentials
+        };
+        assert!(subaccount_number(&credentials).is_err());
+    }
+}
@@ -8,13 +8,21 @@ use rust_decimal::Decimal;
 use serde::Deserialize;
 use sha2::Sha512;
 use std::time::{SystemTime, UNIX_EPOCH};
-use tracing::{debug, info};
-
-use super::{Credentials, ExchangeAdapter, OrderRequest, OrderResponse, OrderStatus, OrderType, Side};
+use tracing::{debug, info, warn};
+
+use super::{
+    canonical_from_separated, check_unavailable, epoch_millis_from_secs, json_decimal, mid_price, parse_json, BookLevel, Credentials, ExchangeAdapter,
+    LeverageInfo, MarginMode, OrderBook, OrderRequest, OrderResponse, OrderStatus, OrderType, Position, ReferencePriceSource, Side, SymbolInfo, SymbolStatus,
+    TimeInForce,
+};
+use super::raw_http::SendTraced;
 use crate::config::ExchangeConfig;
 
 type HmacSha512 = Hmac<Sha512>;
 
+/// Cancel rejected because the order already filled, was cancelled or is unknown
+const ORDER_NOT_FOUND: &str = "ORDER_NOT_FOUND";
+
 pub struct GateioAdapter {
     config: ExchangeConfig,
     client: Client,
@@ -22,9 +30,7 @@ pub struct GateioAdapter {
 
 impl GateioAdapter {
     pub async fn new(config: ExchangeConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .build()?;
+        let client = super::http_client(&config)?;
 
         Ok(Self { config, client })
     }
@@ -37,18 +43,85 @@ impl GateioAdapter {
             .to_string()
     }
 
-    fn sign(&self, secret: &str, method: &str, path: &str, query: &str, body: &str, timestamp: &str) -> String {
-        // Gate.io uses: sha512 of body + sha512 of (method + path + query + body_hash + timestamp)
-        use sha2::{Digest, Sha512};
-        
-        let body_hash = hex::encode(Sha512::digest(body.as_bytes()));
-        let str_to_sign = format!("{}\n{}\n{}\n{}\n{}", method.to_uppercase(), path, query, body_hash, timestamp);
-        
-        let mut mac = HmacSha512::new_from_slice(secret.as_bytes())
-            .expect("HMAC can take key of any size");
-        mac.update(str_to_sign.as_bytes());
-        hex::encode(mac.finalize().into_bytes())
+    async fn fetch_order(&self, credentials: &Credentials, order_id: &str) -> Result<GateioOrder> {
+        let timestamp = Self::timestamp();
+        let path = format!("/api/v4/futures/usdt/orders/{}", order_id);
+
+        let signature = sign(&credentials.api_secret, "GET", &path, "", "", &timestamp);
+
+        let url = format!("{}{}", self.config.rest_url, path);
+        let response = self.client
+            .get(&url)
+            .header("KEY", &credentials.api_key)
+            .header("SIGN", &signature)
+            .header("Timestamp", &timestamp)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let body = response.text().await?;
+        parse_json(&body)
     }
+
+    /// `left` on place and cancel responses can lag fills that raced the
+    /// request, so finished orders are re-read and the latest state wins
+    async fn confirm_terminal(&self, credentials: &Credentials, order: GateioOrder) -> GateioOrder {
+        if order.status != "finished" {
+            return order;
+        }
+
+        match self.fetch_order(credentials, &order.id.to_string()).await {
+            Ok(latest) => {
+                if latest.left != order.left {
+                    warn!(
+                        "Gate.io order {} reported left={} but the follow-up shows {}",
+                        order.id, order.left, latest.left
+                    );
+                }
+                latest
+            }
+            Err(e) => {
+                warn!("Failed to re-read finished Gate.io order {}: {}", order.id, e);
+                order
+            }
+        }
+    }
+}
+
+/// Query string in the form Gate.io expects it both on the URL and in the
+/// signature: parameters in the given order with keys and values URL-encoded
+fn encode_query(params: &[(&str, &str)]) -> String {
+    params
+        .iter()
+        .map(|(key, value)| format!("{}={}", urlencoding::encode(key), urlencoding::encode(value)))
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+/// APIv4 signature string: method, path, query, hex SHA512 of the body and
+/// timestamp, newline separated
+/// Gate.io reports cross margin as a leverage of 0, the cross leverage
+/// being a separate limit
+fn leverage_info(leverage: &str, cross_leverage_limit: &str) -> Result<LeverageInfo> {
+    let leverage: Decimal = leverage.parse().context("Invalid Gate.io leverage")?;
+    if leverage.is_zero() {
+        return Ok(LeverageInfo {
+            leverage: cross_leverage_limit
+                .parse()
+                .context("Invalid Gate.io cross leverage limit")?,
+            margin_mode: MarginMode::Cross,
+        });
+    }
+    Ok(LeverageInfo {
+        leverage,
+        margin_mode: MarginMode::Isolated,
+    })
+}
+
+fn signature_payload(method: &str, path: &str, query: &str, body: &str, timestamp: &str) -> String {
+    use sha2::Digest;
+
+    let body_hash = hex::encode(Sha512::digest(body.as_bytes()));
+    format!("{}\n{}\n{}\n{}\n{}", method.to_uppercase(), path, query, body_hash, timestamp)
 }
 
 #[derive(Debug, Deserialize)]
@@ -57,13 +130,17 @@ struct GateioOrder {
     contract: String,
     size: i64,
     price: String,
-    close: bool,
     #[serde(rename = "tif")]
     time_in_force: String,
     #[serde(rename = "fill_price")]
     fill_price: Option<String>,
+    /// Contracts still unfilled, signed like `size`. Futures orders have no
+    /// filled field, so fills are inferred from this.
     left: i64,
     status: String,
+    /// Why a finished order finished, e.g. `filled` or `cancelled`
+    #[serde(default)]
+    finish_as: Option<String>,
     #[serde(rename = "create_time")]
     create_time: f64,
     text: Option<String>,
@@ -75,11 +152,20 @@ impl ExchangeAdapter for GateioAdapter {
         "gateio"
     }
 
+    fn to_native_symbol(&self, base: &str, quote: &str) -> String {
+        format!("{}_{}", base, quote)
+    }
+
+    fn to_canonical_symbol(&self, native: &str) -> Option<String> {
+        canonical_from_separated(native, '_')
+    }
+
     async fn place_order(
         &self,
         credentials: &Credentials,
         request: &OrderRequest,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(&request.symbol);
         let timestamp = Self::timestamp();
         let path = "/api/v4/futures/usdt/orders";
         
@@ -90,17 +176,21 @@ impl ExchangeAdapter for GateioAdapter {
         };
 
         let body = serde_json::json!({
-            "contract": request.symbol,
+            "contract": symbol,
             "size": size,
             "price": request.price.map(|p| p.to_string()).unwrap_or_else(|| "0".to_string()),
-            "tif": if request.order_type == OrderType::Market { "ioc" } else { "gtc" },
+            "tif": match (request.order_type, request.time_in_force) {
+                (OrderType::Market, _) | (OrderType::Limit, TimeInForce::Ioc) => "ioc",
+                (OrderType::Limit, TimeInForce::Gtc | TimeInForce::Gtd { .. }) => "gtc",
+                (OrderType::Limit, TimeInForce::PostOnly) => "poc",
+            },
             "reduce_only": request.reduce_only,
             "text": request.client_order_id,
         }).to_string();
 
-        let signature = self.sign(&credentials.api_secret, "POST", path, "", &body, &timestamp);
+        let signature = sign(&credentials.api_secret, "POST", path, "", &body, &timestamp);
 
-        debug!("Placing Gate.io order: {}", request.symbol);
+        debug!("Placing Gate.io order: {}", symbol);
 
         let url = format!("{}{}", self.config.rest_url, path);
         let response = self.client
@@ -110,7 +200,7 @@ impl ExchangeAdapter for GateioAdapter {
             .header("Timestamp", &timestamp)
             .header("Content-Type", "application/json")
             .body(body)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await
             .context("Failed to send order request")?;
 
@@ -118,30 +208,17 @@ impl ExchangeAdapter for GateioAdapter {
         let body = response.text().await?;
 
         if !status.is_success() {
+            check_unavailable(self.id(), status, &body)?;
             anyhow::bail!("Gate.io order failed: {} - {}", status, body);
         }
 
-        let order: GateioOrder = serde_json::from_str(&body)
+        let order: GateioOrder = parse_json(&body)
             .context("Failed to parse order response")?;
 
         info!("Gate.io order placed: {} status={}", order.id, order.status);
 
-        Ok(OrderResponse {
-            exchange_order_id: order.id.to_string(),
-            client_order_id: order.text.unwrap_or_default(),
-            symbol: order.contract,
-            side: if order.size > 0 { Side::Buy } else { Side::Sell },
-            order_type: match order.time_in_force.as_str() {
-                "ioc" => OrderType::Market,
-                _ => OrderType::Limit,
-            },
-            price: order.price.parse().ok(),
-            quantity: Decimal::from(order.size.abs()),
-            filled_quantity: Decimal::from((order.size.abs() - order.left).abs()),
-            avg_fill_price: order.fill_price.and_then(|p| p.parse().ok()),
-            status: parse_gateio_status(&order.status),
-            timestamp: (order.create_time * 1000.0) as i64,
-        })
+        let order = self.confirm_terminal(credentials, order).await;
+        Ok(order_response(order).canonical(self))
     }
 
     async fn cancel_order(
@@ -153,7 +230,7 @@ impl ExchangeAdapter for GateioAdapter {
         let timestamp = Self::timestamp();
         let path = format!("/api/v4/futures/usdt/orders/{}", order_id);
         
-        let signature = self.sign(&credentials.api_secret, "DELETE", &path, "", "", &timestamp);
+        let signature = sign(&credentials.api_secret, "DELETE", &path, "", "", &timestamp);
 
         let url = format!("{}{}", self.config.rest_url, path);
         let response = self.client
@@ -161,72 +238,98 @@ impl ExchangeAdapter for GateioAdapter {
             .header("KEY", &credentials.api_key)
             .header("SIGN", &signature)
             .header("Timestamp", &timestamp)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
+        let status = response.status();
         let body = response.text().await?;
-        let order: GateioOrder = serde_json::from_str(&body)?;
-
-        Ok(OrderResponse {
-            exchange_order_id: order.id.to_string(),
-            client_order_id: order.text.unwrap_or_default(),
-            symbol: order.contract,
-            side: if order.size > 0 { Side::Buy } else { Side::Sell },
-            order_type: OrderType::Limit,
-            price: order.price.parse().ok(),
-            quantity: Decimal::from(order.size.abs()),
-            filled_quantity: Decimal::from((order.size.abs() - order.left).abs()),
-            avg_fill_price: order.fill_price.and_then(|p| p.parse().ok()),
-            status: OrderStatus::Cancelled,
-            timestamp: (order.create_time * 1000.0) as i64,
-        })
+        if !status.is_success() {
+            #[derive(Deserialize)]
+            struct GateioError {
+                label: String,
+            }
+
+            // Lost the race with a fill or an earlier cancel: report where it ended up
+            if parse_json::<GateioError>(&body).is_ok_and(|e| e.label == ORDER_NOT_FOUND) {
+                debug!("Gate.io order {} is no longer open, fetching it", order_id);
+                return self.get_order(credentials, symbol, order_id).await;
+            }
+            check_unavailable(self.id(), status, &body)?;
+            anyhow::bail!("Gate.io cancel failed: {} - {}", status, body);
+        }
+        let order: GateioOrder = parse_json(&body)?;
+
+        let order = self.confirm_terminal(credentials, order).await;
+        Ok(order_response(order).canonical(self))
     }
 
     async fn get_order(
         &self,
         credentials: &Credentials,
-        symbol: &str,
+        _symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
+        Ok(order_response(self.fetch_order(credentials, order_id).await?).canonical(self))
+    }
+
+    async fn get_open_orders(&self, credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        let symbol = self.native_symbol(symbol);
         let timestamp = Self::timestamp();
-        let path = format!("/api/v4/futures/usdt/orders/{}", order_id);
-        
-        let signature = self.sign(&credentials.api_secret, "GET", &path, "", "", &timestamp);
+        let path = "/api/v4/futures/usdt/orders";
+        let query = encode_query(&[("contract", &symbol), ("status", "open")]);
 
-        let url = format!("{}{}", self.config.rest_url, path);
+        let signature = sign(&credentials.api_secret, "GET", path, &query, "", &timestamp);
+
+        let url = format!("{}{}?{}", self.config.rest_url, path, query);
         let response = self.client
             .get(&url)
             .header("KEY", &credentials.api_key)
             .header("SIGN", &signature)
             .header("Timestamp", &timestamp)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
+        let status = response.status();
         let body = response.text().await?;
-        let order: GateioOrder = serde_json::from_str(&body)?;
-
-        Ok(OrderResponse {
-            exchange_order_id: order.id.to_string(),
-            client_order_id: order.text.unwrap_or_default(),
-            symbol: order.contract,
-            side: if order.size > 0 { Side::Buy } else { Side::Sell },
-            order_type: match order.time_in_force.as_str() {
-                "ioc" => OrderType::Market,
-                _ => OrderType::Limit,
-            },
-            price: order.price.parse().ok(),
-            quantity: Decimal::from(order.size.abs()),
-            filled_quantity: Decimal::from((order.size.abs() - order.left).abs()),
-            avg_fill_price: order.fill_price.and_then(|p| p.parse().ok()),
-            status: parse_gateio_status(&order.status),
-            timestamp: (order.create_time * 1000.0) as i64,
-        })
+
+        if !status.is_success() {
+            anyhow::bail!("Gate.io open orders query failed: {} - {}", status, body);
+        }
+
+        let orders: Vec<GateioOrder> = parse_json(&body)
+            .context("Failed to parse open orders")?;
+        Ok(orders.into_iter().map(|order| order_response(order).canonical(self)).collect())
+    }
+
+    // Open orders come back in one call; anything missing from it has closed
+    // and is fetched individually
+    async fn get_orders_batch(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_ids: &[String],
+    ) -> Result<Vec<OrderResponse>> {
+        let mut orders: Vec<OrderResponse> = self
+            .get_open_orders(credentials, symbol)
+            .await?
+            .into_iter()
+            .filter(|o| order_ids.contains(&o.exchange_order_id))
+            .collect();
+
+        for order_id in order_ids {
+            if !orders.iter().any(|o| &o.exchange_order_id == order_id) {
+                orders.push(self.get_order(credentials, symbol, order_id).await?);
+            }
+        }
+
+        Ok(orders)
     }
 
     async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
+        let symbol = self.native_symbol(symbol);
         let url = format!("{}/api/v4/futures/usdt/tickers?contract={}", self.config.rest_url, symbol);
         
-        let response = self.client.get(&url).send().await?;
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
         let body = response.text().await?;
         
         #[derive(Deserialize)]
@@ -235,7 +338,7 @@ impl ExchangeAdapter for GateioAdapter {
             lowest_ask: String,
         }
         
-        let tickers: Vec<Ticker> = serde_json::from_str(&body)?;
+        let tickers: Vec<Ticker> = parse_json(&body)?;
         let ticker = tickers.into_iter().next()
             .ok_or_else(|| anyhow::anyhow!("No ticker data"))?;
 
@@ -245,11 +348,273 @@ impl ExchangeAdapter for GateioAdapter {
         ))
     }
 
+    async fn get_reference_price(&self, symbol: &str, source: ReferencePriceSource) -> Result<Decimal> {
+        if source == ReferencePriceSource::Mid {
+            return mid_price(self, symbol).await;
+        }
+        let symbol = self.native_symbol(symbol);
+        let url = format!("{}/api/v4/futures/usdt/tickers?contract={}", self.config.rest_url, symbol);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct Ticker {
+            last: String,
+            mark_price: String,
+            index_price: String,
+        }
+
+        let tickers: Vec<Ticker> = parse_json(&body)?;
+        let ticker = tickers.into_iter().next()
+            .ok_or_else(|| anyhow::anyhow!("No ticker data"))?;
+
+        let price = match source {
+            ReferencePriceSource::Last => ticker.last,
+            ReferencePriceSource::Mark => ticker.mark_price,
+            _ => ticker.index_price,
+        };
+        price.parse().context("Invalid price")
+    }
+
+    async fn get_order_book(&self, symbol: &str, depth: usize) -> Result<OrderBook> {
+        let symbol = self.native_symbol(symbol);
+        let url = format!(
+            "{}/api/v4/futures/usdt/order_book?contract={}&limit={}",
+            self.config.rest_url,
+            symbol,
+            depth.clamp(1, 100)
+        );
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct Level {
+            p: String,
+            /// Contracts
+            s: serde_json::Value,
+        }
+
+        #[derive(Deserialize)]
+        struct Depth {
+            /// Unix seconds, with milliseconds as the fraction
+            current: f64,
+            bids: Vec<Level>,
+            asks: Vec<Level>,
+        }
+
+        let book: Depth = parse_json(&body)?;
+        let levels = |levels: Vec<Level>| {
+            levels
+                .into_iter()
+                .map(|level| {
+                    Ok(BookLevel {
+                        price: level.p.parse()?,
+                        quantity: json_decimal(&level.s)?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()
+        };
+
+        Ok(OrderBook::from_levels(
+            levels(book.bids)?,
+            levels(book.asks)?,
+            depth,
+            (book.current * 1000.0).round() as i64,
+        ))
+    }
+
+    async fn get_server_time(&self) -> Result<i64> {
+        let url = format!("{}/api/v4/spot/time", self.config.rest_url);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let status = response.status();
+        let body = response.text().await?;
+        check_unavailable(self.id(), status, &body)?;
+
+        #[derive(Deserialize)]
+        struct ServerTime {
+            server_time: i64,
+        }
+
+        let time: ServerTime = parse_json(&body)?;
+        Ok(time.server_time)
+    }
+
+    async fn get_leverage(&self, credentials: &Credentials, symbol: &str) -> Result<LeverageInfo> {
+        let symbol = self.native_symbol(symbol);
+        let timestamp = Self::timestamp();
+        let path = format!("/api/v4/futures/usdt/positions/{}", symbol);
+
+        let signature = sign(&credentials.api_secret, "GET", &path, "", "", &timestamp);
+
+        let url = format!("{}{}", self.config.rest_url, path);
+        let response = self.client
+            .get(&url)
+            .header("KEY", &credentials.api_key)
+            .header("SIGN", &signature)
+            .header("Timestamp", &timestamp)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            anyhow::bail!("Gate.io position query failed: {} - {}", status, body);
+        }
+
+        #[derive(Deserialize)]
+        struct Position {
+            leverage: String,
+            cross_leverage_limit: String,
+        }
+
+        let position: Position = parse_json(&body)?;
+        leverage_info(&position.leverage, &position.cross_leverage_limit)
+    }
+
+    async fn get_positions(&self, credentials: &Credentials) -> Result<Vec<Position>> {
+        let timestamp = Self::timestamp();
+        let path = "/api/v4/futures/usdt/positions";
+        let query = "holding=true";
+
+        let signature = sign(&credentials.api_secret, "GET", path, query, "", &timestamp);
+
+        let url = format!("{}{}?{}", self.config.rest_url, path, query);
+        let response = self.client
+            .get(&url)
+            .header("KEY", &credentials.api_key)
+            .header("SIGN", &signature)
+            .header("Timestamp", &timestamp)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        if !status.is_success() {
+            anyhow::bail!("Gate.io position query failed: {} - {}", status, body);
+        }
+
+        /// Size is in contracts, negative when short
+        #[derive(Deserialize)]
+        struct OpenPosition {
+            contract: String,
+            size: i64,
+            entry_price: String,
+        }
+
+        let positions: Vec<OpenPosition> = parse_json(&body)?;
+        positions
+            .into_iter()
+            .filter(|p| p.size != 0)
+            .map(|p| {
+                Ok(Position {
+                    side: if p.size > 0 { Side::Buy } else { Side::Sell },
+                    quantity: Decimal::from(p.size.abs()),
+                    entry_price: p.entry_price.parse().context("Invalid Gate.io entry price")?,
+                    symbol: p.contract,
+                })
+            })
+            .collect()
+    }
+
+    async fn get_symbol_info(&self, symbol: &str) -> Result<SymbolInfo> {
+        let symbol = self.native_symbol(symbol);
+        let url = format!("{}/api/v4/futures/usdt/contracts/{}", self.config.rest_url, symbol);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let status = response.status();
+        let body = response.text().await?;
+
+        if status == reqwest::StatusCode::NOT_FOUND {
+            return Ok(SymbolInfo {
+                symbol,
+                status: SymbolStatus::Delisted,
+                contract_size: Decimal::ONE,
+                quantity_step: Decimal::ZERO,
+                tick_size: Decimal::ZERO,
+                min_notional: Decimal::ZERO,
+            });
+        }
+        if !status.is_success() {
+            anyhow::bail!("Gate.io contract lookup failed: {} - {}", status, body);
+        }
+
+        #[derive(Deserialize)]
+        struct Contract {
+            /// Delisting contracts only accept reduce-only orders
+            in_delisting: bool,
+            /// Coins per contract
+            quanto_multiplier: String,
+            /// Price tick
+            order_price_round: String,
+        }
+
+        let contract: Contract = parse_json(&body)?;
+        Ok(SymbolInfo {
+            symbol,
+            status: if contract.in_delisting {
+                SymbolStatus::ReduceOnly
+            } else {
+                SymbolStatus::Trading
+            },
+            contract_size: contract.quanto_multiplier.parse().unwrap_or(Decimal::ONE),
+            // Orders are sized in whole contracts
+            quantity_step: Decimal::ONE,
+            tick_size: contract.order_price_round.parse().unwrap_or_default(),
+            // The minimum is a contract count, and one contract is the step
+            min_notional: Decimal::ZERO,
+        })
+    }
+
     fn is_connected(&self) -> bool {
         true
     }
 }
 
+fn order_response(order: GateioOrder) -> OrderResponse {
+    let filled = filled_size(&order);
+    // Gate.io reports every closed order as finished, whether or not it filled
+    let status = match parse_gateio_status(&order.status) {
+        OrderStatus::Filled if filled < order.size.abs() => OrderStatus::Cancelled,
+        OrderStatus::Open if filled > 0 => OrderStatus::Partial,
+        status => status,
+    };
+
+    OrderResponse {
+        exchange_order_id: order.id.to_string(),
+        client_order_id: order.text.unwrap_or_default(),
+        symbol: order.contract,
+        side: if order.size > 0 { Side::Buy } else { Side::Sell },
+        order_type: match order.time_in_force.as_str() {
+            "ioc" => OrderType::Market,
+            _ => OrderType::Limit,
+        },
+        // Market orders are sent, and reported, at price "0", as is the fill
+        // price of an order nothing filled
+        price: nonzero_price(&order.price),
+        quantity: Decimal::from(order.size.abs()),
+        filled_quantity: Decimal::from(filled),
+        avg_fill_price: order.fill_price.as_deref().and_then(nonzero_price),
+        status,
+        timestamp: epoch_millis_from_secs(order.create_time),
+    }
+}
+
+/// A price field, `None` where it is zero or unparseable
+fn nonzero_price(price: &str) -> Option<Decimal> {
+    price.parse().ok().filter(|p: &Decimal| !p.is_zero())
+}
+
+/// Filled contracts. `finish_as = filled` is trusted over `left`.
+fn filled_size(order: &GateioOrder) -> i64 {
+    if order.finish_as.as_deref() == Some("filled") {
+        return order.size.abs();
+    }
+    (order.size.abs() - order.left.abs()).max(0)
+}
+
 fn parse_gateio_status(status: &str) -> OrderStatus {
     match status {
         "open" => OrderStatus::Open,
@@ -258,3 +623,204 @@ fn parse_gateio_status(status: &str) -> OrderStatus {
         _ => OrderStatus::Pending,
     }
 }
+
+/// `query` must be the exact query string sent on the URL, see `encode_query`
+pub fn sign(secret: &str, method: &str, path: &str, query: &str, body: &str, timestamp: &str) -> String {
+    let str_to_sign = signature_payload(method, path, query, body, timestamp);
+
+    let mut mac = HmacSha512::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(str_to_sign.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    const EMPTY_BODY_HASH: &str = "cf83e1357eefb8bdf1542850d66d8007d620e4050b5715dc83f4a921d36ce9ce47d0d13c5d85f2b0ff8318d2877eec2f63b931bd47417a81a538327af927da3e";
+
+    // Examples from the Gate.io APIv4 authentication docs
+    #[test]
+    fn test_signature_payload_matches_docs() {
+        let query = encode_query(&[("contract", "BTC_USD"), ("status", "finished"), ("limit", "50")]);
+        assert_eq!(query, "contract=BTC_USD&status=finished&limit=50");
+        assert_eq!(
+            signature_payload("GET", "/api/v4/futures/orders", &query, "", "1541993715"),
+            format!(
+                "GET\n/api/v4/futures/orders\ncontract=BTC_USD&status=finished&limit=50\n{}\n1541993715",
+                EMPTY_BODY_HASH
+            )
+        );
+
+        let body = r#"{"contract":"BTC_USD","type":"limit","size":100,"price":6800,"time_in_force":"gtc"}"#;
+        assert_eq!(
+            signature_payload("POST", "/api/v4/futures/orders", "", body, "1541993715"),
+            "POST\n/api/v4/futures/orders\n\nad3c169203dc3026558f01b4df307641fa1fa361f086b2306658886d5708767b1854797c68d9e62fef2f991645aa82673622ebf417e091d0bd22bafe5d956cca\n1541993715"
+        );
+    }
+
+    fn order(size: i64, left: i64, finish_as: &str) -> GateioOrder {
+        serde_json::from_value(serde_json::json!({
+            "id": 42,
+            "contract": "BTC_USDT",
+            "size": size,
+            "price": "60000",
+            "close": false,
+            "tif": "gtc",
+            "fill_price": "60000",
+            "left": left,
+            "status": "finished",
+            "finish_as": finish_as,
+            "create_time": 1700000000.5,
+            "text": "t-abc",
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_partially_filled_then_cancelled_order() {
+        // Sell 10, 6 filled before the cancel
+        let response = order_response(order(-10, -4, "cancelled"));
+
+        assert_eq!(response.side, Side::Sell);
+        assert_eq!(response.quantity, Decimal::from(10));
+        assert_eq!(response.filled_quantity, Decimal::from(6));
+        assert_eq!(response.status, OrderStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_finish_as_filled_overrides_stale_left() {
+        let response = order_response(order(10, 4, "filled"));
+
+        assert_eq!(response.filled_quantity, Decimal::from(10));
+        assert_eq!(response.status, OrderStatus::Filled);
+    }
+
+    #[test]
+    fn test_ioc_market_order_reports_no_zero_prices() {
+        let market = |fill_price: &str, left: i64| -> GateioOrder {
+            serde_json::from_value(serde_json::json!({
+                "id": 43,
+                "contract": "BTC_USDT",
+                "size": 10,
+                "price": "0",
+                "close": false,
+                "tif": "ioc",
+                "fill_price": fill_price,
+                "left": left,
+                "status": "finished",
+                "finish_as": "ioc",
+                "create_time": 1700000000.5,
+                "text": "t-abc",
+            }))
+            .unwrap()
+        };
+
+        // 6 of 10 filled, the rest cancelled by the IOC
+        let response = order_response(market("60010.5", 4));
+        assert_eq!(response.order_type, OrderType::Market);
+        assert_eq!(response.price, None);
+        assert_eq!(response.avg_fill_price, Some(dec!(60010.5)));
+        assert_eq!(response.filled_quantity, Decimal::from(6));
+        assert_eq!(response.status, OrderStatus::Cancelled);
+
+        let response = order_response(market("0", 10));
+        assert_eq!(response.avg_fill_price, None);
+        assert_eq!(response.filled_quantity, Decimal::ZERO);
+        assert_eq!(response.status, OrderStatus::Cancelled);
+    }
+
+    #[test]
+    fn test_encode_query_escapes_values() {
+        assert_eq!(
+            encode_query(&[("contract", "BTC_USDT"), ("text", "t-a b&c")]),
+            "contract=BTC_USDT&text=t-a%20b%26c"
+        );
+    }
+
+    #[test]
+    fn test_zero_leverage_is_cross_at_the_limit() {
+        let cross = leverage_info("0", "20").unwrap();
+        assert_eq!(cross, LeverageInfo { leverage: dec!(20), margin_mode: MarginMode::Cross });
+
+        let isolated = leverage_info("5", "20").unwrap();
+        assert_eq!(isolated, LeverageInfo { leverage: dec!(5), margin_mode: MarginMode::Isolated });
+
+        assert!(leverage_info("", "20").is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancelling_a_filled_order_reports_its_state() {
+        let filled = r#"{"id":7,"contract":"BTC_USDT","size":10,"price":"100","close":false,"tif":"gtc","fill_price":"100","left":0,"status":"finished","finish_as":"filled","create_time":1.5,"text":"t-cs1"}"#;
+        let (url, server) = crate::exchange::mock::serve_http(vec![
+            ("404 Not Found", r#"{"label":"ORDER_NOT_FOUND","message":"Order not found"}"#),
+            ("200 OK", filled),
+            ("400 Bad Request", r#"{"label":"INVALID_KEY","message":"Invalid key provided"}"#),
+        ])
+        .await;
+        let adapter = GateioAdapter::new(ExchangeConfig {
+            id: "gateio".to_string(),
+            rest_url: url,
+            ws_url: String::new(),
+            trade_ws_url: None,
+            testnet: false,
+            maker_fee_bps: 0.0,
+            taker_fee_bps: 0.0,
+            trade_mode: None,
+            user_agent: None,
+            proxy: None,
+            log_raw_http: false,
+            ws_orders: false,
+            order_tag: None,
+            client_order_id_prefix: None,
+            adopt_duplicate_orders: false,
+        })
+        .await
+        .unwrap();
+        let credentials = crate::exchange::mock::credentials();
+
+        let order = adapter.cancel_order(&credentials, "BTC_USDT", "7").await.unwrap();
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert_eq!(order.filled_quantity, dec!(10));
+
+        // Other rejections are still errors
+        let err = adapter.cancel_order(&credentials, "BTC_USDT", "7").await.unwrap_err();
+        assert!(err.to_string().contains("INVALID_KEY"), "{}", err);
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("DELETE /api/v4/futures/usdt/orders/7"), "{:?}", requests);
+        assert!(requests[1].starts_with("GET /api/v4/futures/usdt/orders/7"), "{:?}", requests);
+    }
+
+    #[tokio::test]
+    async fn test_order_book_is_parsed_best_first() {
+        let (url, server) = crate::exchange::mock::serve_http(vec![(
+            "200 OK",
+            r#"{"id":123456,"current":1623898993.123,"update":1623898993.121,"asks":[{"p":"1.52","s":100},{"p":"1.53","s":40}],"bids":[{"p":"1.17","s":150},{"p":"1.16","s":"203"}]}"#,
+        )])
+        .await;
+        let adapter = GateioAdapter::new(crate::exchange::mock::exchange_config("gateio", url)).await.unwrap();
+
+        let book = adapter.get_order_book("BTC_USDT", 10).await.unwrap();
+
+        assert_eq!(book.bids[0].price, dec!(1.17));
+        assert_eq!(book.bids[1].quantity, dec!(203));
+        assert_eq!(book.asks[0], BookLevel { price: dec!(1.52), quantity: dec!(100) });
+        assert_eq!(book.asks[1].price, dec!(1.53));
+        assert_eq!(book.timestamp, 1623898993123);
+        let requests = server.await.unwrap();
+        assert!(
+            requests[0].starts_with("GET /api/v4/futures/usdt/order_book?contract=BTC_USDT&limit=10"),
+            "{:?}",
+            requests
+        );
+    }
+
+    #[test]
+    fn test_order_timestamp_is_in_millis() {
+        // Gate.io stamps orders in fractional seconds
+        assert_eq!(order_response(order(10, 0, "filled")).timestamp, 1700000000500);
+    }
+}
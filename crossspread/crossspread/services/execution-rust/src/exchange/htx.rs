@@ -11,11 +11,21 @@ use serde::Deserialize;
 use sha2::Sha256;
 use tracing::{debug, info};
 
-use super::{Credentials, ExchangeAdapter, OrderRequest, OrderResponse, OrderStatus, OrderType, Side};
+use super::{
+    canonical_from_separated, epoch_millis, parse_json, parse_level_rows, Credentials, ExchangeAdapter, OrderBook, OrderRequest,
+    OrderResponse, OrderStatus, OrderType, Side, TimeInForce,
+};
+use super::raw_http::SendTraced;
 use crate::config::ExchangeConfig;
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// Signature timestamps are UTC to the second, e.g. `2017-05-11T15:19:30`
+const TIMESTAMP_FORMAT: &str = "%Y-%m-%dT%H:%M:%S";
+
+/// Most orders one page of open orders holds
+const OPEN_ORDERS_PAGE_SIZE: u32 = 50;
+
 pub struct HtxAdapter {
     config: ExchangeConfig,
     client: Client,
@@ -23,30 +33,25 @@ pub struct HtxAdapter {
 
 impl HtxAdapter {
     pub async fn new(config: ExchangeConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .build()?;
+        let client = super::http_client(&config)?;
 
         Ok(Self { config, client })
     }
 
     fn timestamp() -> String {
-        Utc::now().format("%Y-%m-%dT%H:%M:%S").to_string()
+        Utc::now().format(TIMESTAMP_FORMAT).to_string()
     }
 
-    fn sign(&self, api_key: &str, secret: &str, method: &str, host: &str, path: &str, timestamp: &str) -> String {
-        let params = format!(
-            "AccessKeyId={}&SignatureMethod=HmacSHA256&SignatureVersion=2&Timestamp={}",
-            api_key,
-            urlencoding::encode(timestamp)
-        );
-        
-        let payload = format!("{}\n{}\n{}\n{}", method.to_uppercase(), host, path, params);
-        
-        let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
-            .expect("HMAC can take key of any size");
-        mac.update(payload.as_bytes());
-        STANDARD.encode(mac.finalize().into_bytes())
+    /// Signed query string for a request made now
+    fn signed_query(&self, credentials: &Credentials, method: &str, path: &str) -> String {
+        signed_query(
+            &credentials.api_key,
+            &credentials.api_secret,
+            method,
+            self.get_host(),
+            path,
+            &Self::timestamp(),
+        )
     }
 
     fn get_host(&self) -> &str {
@@ -71,20 +76,17 @@ struct HtxResponse<T> {
 
 #[derive(Debug, Deserialize)]
 struct HtxOrderId {
-    order_id: i64,
     order_id_str: String,
 }
 
 #[derive(Debug, Deserialize)]
 struct HtxOrderDetail {
-    order_id: i64,
     order_id_str: String,
-    symbol: String,
     contract_code: String,
     direction: String,
-    offset: String,
     price: f64,
     volume: i64,
+    /// Filled contracts
     trade_volume: i64,
     trade_avg_price: Option<f64>,
     status: i32,
@@ -98,57 +100,34 @@ impl ExchangeAdapter for HtxAdapter {
         "htx"
     }
 
+    fn to_native_symbol(&self, base: &str, quote: &str) -> String {
+        format!("{}-{}", base, quote)
+    }
+
+    fn to_canonical_symbol(&self, native: &str) -> Option<String> {
+        canonical_from_separated(native, '-')
+    }
+
     async fn place_order(
         &self,
         credentials: &Credentials,
         request: &OrderRequest,
     ) -> Result<OrderResponse> {
-        let timestamp = Self::timestamp();
+        let symbol = self.native_symbol(&request.symbol);
         let path = "/linear-swap-api/v1/swap_cross_order";
-        let host = self.get_host();
-        
-        let signature = self.sign(
-            &credentials.api_key,
-            &credentials.api_secret,
-            "POST",
-            host,
-            path,
-            &timestamp
-        );
+        let query = self.signed_query(credentials, "POST", path);
 
-        let body = serde_json::json!({
-            "contract_code": request.symbol,
-            "direction": match request.side {
-                Side::Buy => "buy",
-                Side::Sell => "sell",
-            },
-            "offset": "open",
-            "order_price_type": match request.order_type {
-                OrderType::Limit => "limit",
-                OrderType::Market => "optimal_20",
-            },
-            "volume": request.quantity.to_string().parse::<i64>().unwrap_or(1),
-            "price": request.price,
-            "lever_rate": 5,
-            "reduce_only": if request.reduce_only { 1 } else { 0 },
-        }).to_string();
+        let body = order_body(&symbol, request).to_string();
 
-        let url = format!(
-            "{}{}?AccessKeyId={}&SignatureMethod=HmacSHA256&SignatureVersion=2&Timestamp={}&Signature={}",
-            self.config.rest_url,
-            path,
-            &credentials.api_key,
-            urlencoding::encode(&timestamp),
-            urlencoding::encode(&signature)
-        );
+        let url = format!("{}{}?{}", self.config.rest_url, path, query);
 
-        debug!("Placing HTX order: {}", request.symbol);
+        debug!("Placing HTX order: {}", symbol);
 
         let response = self.client
             .post(&url)
             .header("Content-Type", "application/json")
             .body(body)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await
             .context("Failed to send order request")?;
 
@@ -159,7 +138,7 @@ impl ExchangeAdapter for HtxAdapter {
             anyhow::bail!("HTX order failed: {} - {}", status, body);
         }
 
-        let resp: HtxResponse<HtxOrderId> = serde_json::from_str(&body)
+        let resp: HtxResponse<HtxOrderId> = parse_json(&body)
             .context("Failed to parse order response")?;
 
         if resp.status != "ok" {
@@ -173,7 +152,7 @@ impl ExchangeAdapter for HtxAdapter {
         Ok(OrderResponse {
             exchange_order_id: order.order_id_str,
             client_order_id: request.client_order_id.clone(),
-            symbol: request.symbol.clone(),
+            symbol: self.canonical_symbol(&symbol),
             side: request.side.clone(),
             order_type: request.order_type.clone(),
             price: request.price,
@@ -191,38 +170,22 @@ impl ExchangeAdapter for HtxAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
-        let timestamp = Self::timestamp();
+        let symbol = self.native_symbol(symbol);
         let path = "/linear-swap-api/v1/swap_cross_cancel";
-        let host = self.get_host();
-        
-        let signature = self.sign(
-            &credentials.api_key,
-            &credentials.api_secret,
-            "POST",
-            host,
-            path,
-            &timestamp
-        );
+        let query = self.signed_query(credentials, "POST", path);
 
         let body = serde_json::json!({
             "contract_code": symbol,
             "order_id": order_id,
         }).to_string();
 
-        let url = format!(
-            "{}{}?AccessKeyId={}&SignatureMethod=HmacSHA256&SignatureVersion=2&Timestamp={}&Signature={}",
-            self.config.rest_url,
-            path,
-            &credentials.api_key,
-            urlencoding::encode(&timestamp),
-            urlencoding::encode(&signature)
-        );
+        let url = format!("{}{}?{}", self.config.rest_url, path, query);
 
         let response = self.client
             .post(&url)
             .header("Content-Type", "application/json")
             .body(body)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
         let _body = response.text().await?;
@@ -230,7 +193,7 @@ impl ExchangeAdapter for HtxAdapter {
         Ok(OrderResponse {
             exchange_order_id: order_id.to_string(),
             client_order_id: String::new(),
-            symbol: symbol.to_string(),
+            symbol: self.canonical_symbol(&symbol),
             side: Side::Buy,
             order_type: OrderType::Limit,
             price: None,
@@ -248,70 +211,85 @@ impl ExchangeAdapter for HtxAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
-        let timestamp = Self::timestamp();
+        let symbol = self.native_symbol(symbol);
         let path = "/linear-swap-api/v1/swap_cross_order_info";
-        let host = self.get_host();
-        
-        let signature = self.sign(
-            &credentials.api_key,
-            &credentials.api_secret,
-            "POST",
-            host,
-            path,
-            &timestamp
-        );
+        let query = self.signed_query(credentials, "POST", path);
 
         let body = serde_json::json!({
             "contract_code": symbol,
             "order_id": order_id,
         }).to_string();
 
-        let url = format!(
-            "{}{}?AccessKeyId={}&SignatureMethod=HmacSHA256&SignatureVersion=2&Timestamp={}&Signature={}",
-            self.config.rest_url,
-            path,
-            &credentials.api_key,
-            urlencoding::encode(&timestamp),
-            urlencoding::encode(&signature)
-        );
+        let url = format!("{}{}?{}", self.config.rest_url, path, query);
 
         let response = self.client
             .post(&url)
             .header("Content-Type", "application/json")
             .body(body)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
         let body = response.text().await?;
-        let resp: HtxResponse<Vec<HtxOrderDetail>> = serde_json::from_str(&body)?;
+        let resp: HtxResponse<Vec<HtxOrderDetail>> = parse_json(&body)?;
 
         let orders = resp.data.ok_or_else(|| anyhow::anyhow!("No order data"))?;
         let order = orders.into_iter().next()
             .ok_or_else(|| anyhow::anyhow!("Order not found"))?;
 
-        Ok(OrderResponse {
-            exchange_order_id: order.order_id_str,
-            client_order_id: order.client_order_id.map(|c| c.to_string()).unwrap_or_default(),
-            symbol: order.contract_code,
-            side: match order.direction.as_str() {
-                "buy" => Side::Buy,
-                _ => Side::Sell,
-            },
-            order_type: OrderType::Limit,
-            price: Some(Decimal::from_f64_retain(order.price).unwrap_or_default()),
-            quantity: Decimal::from(order.volume),
-            filled_quantity: Decimal::from(order.trade_volume),
-            avg_fill_price: order.trade_avg_price.and_then(Decimal::from_f64_retain),
-            status: parse_htx_status(order.status),
-            timestamp: order.created_at,
-        })
+        Ok(order_response(order).canonical(self))
+    }
+
+    async fn get_open_orders(&self, credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        let symbol = self.native_symbol(symbol);
+        let path = "/linear-swap-api/v1/swap_cross_openorders";
+
+        #[derive(Deserialize)]
+        struct OpenOrders {
+            orders: Vec<HtxOrderDetail>,
+            total_page: u32,
+        }
+
+        let mut orders = Vec::new();
+        let mut page = 1;
+        loop {
+            let query = self.signed_query(credentials, "POST", path);
+            let body = serde_json::json!({
+                "contract_code": symbol,
+                "page_index": page,
+                "page_size": OPEN_ORDERS_PAGE_SIZE,
+            }).to_string();
+
+            let url = format!("{}{}?{}", self.config.rest_url, path, query);
+
+            let response = self.client
+                .post(&url)
+                .header("Content-Type", "application/json")
+                .body(body)
+                .send_traced(self.config.log_raw_http)
+                .await?;
+
+            let body = response.text().await?;
+            let resp: HtxResponse<OpenOrders> = parse_json(&body)?;
+            if resp.status != "ok" {
+                anyhow::bail!("HTX open orders error: {:?} - {:?}", resp.err_code, resp.err_msg);
+            }
+
+            let listed = resp.data.ok_or_else(|| anyhow::anyhow!("No open orders data"))?;
+            orders.extend(listed.orders.into_iter().map(|order| order_response(order).canonical(self)));
+            if page >= listed.total_page {
+                break;
+            }
+            page += 1;
+        }
+        Ok(orders)
     }
 
     async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
+        let symbol = self.native_symbol(symbol);
         let url = format!("{}/linear-swap-ex/market/depth?contract_code={}&type=step0", 
             self.config.rest_url, symbol);
         
-        let response = self.client.get(&url).send().await?;
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
         let body = response.text().await?;
         
         #[derive(Deserialize)]
@@ -325,7 +303,7 @@ impl ExchangeAdapter for HtxAdapter {
             tick: DepthData,
         }
         
-        let resp: DepthResp = serde_json::from_str(&body)?;
+        let resp: DepthResp = parse_json(&body)?;
         
         let bid = resp.tick.bids.first()
             .ok_or_else(|| anyhow::anyhow!("No bid"))?[0];
@@ -338,11 +316,128 @@ impl ExchangeAdapter for HtxAdapter {
         ))
     }
 
+    async fn get_order_book(&self, symbol: &str, depth: usize) -> Result<OrderBook> {
+        let symbol = self.native_symbol(symbol);
+        // step0 is the unmerged book, 150 levels a side
+        let url = format!("{}/linear-swap-ex/market/depth?contract_code={}&type=step0",
+            self.config.rest_url, symbol);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        // Levels are [price, contracts]
+        #[derive(Deserialize)]
+        struct Tick {
+            bids: Vec<Vec<serde_json::Value>>,
+            asks: Vec<Vec<serde_json::Value>>,
+            ts: i64,
+        }
+
+        #[derive(Deserialize)]
+        struct DepthResp {
+            status: String,
+            tick: Option<Tick>,
+        }
+
+        let resp: DepthResp = parse_json(&body)?;
+        let book = match resp.tick {
+            Some(tick) if resp.status == "ok" => tick,
+            _ => anyhow::bail!("HTX order book error: {}", body),
+        };
+
+        Ok(OrderBook::from_levels(
+            parse_level_rows(&book.bids)?,
+            parse_level_rows(&book.asks)?,
+            depth,
+            book.ts,
+        ))
+    }
+
     fn is_connected(&self) -> bool {
         true
     }
 }
 
+/// Body of an order on `symbol`. Orders open a position unless they are
+/// reduce-only, which close one: HTX goes by the offset rather than the
+/// reduce-only flag, so a reduce-only order sent with `open` would open a
+/// position the other way.
+fn order_body(symbol: &str, request: &OrderRequest) -> serde_json::Value {
+    serde_json::json!({
+        "contract_code": symbol,
+        "direction": match request.side {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        },
+        "offset": if request.reduce_only { "close" } else { "open" },
+        "order_price_type": match (request.order_type, request.time_in_force) {
+            (OrderType::Limit, TimeInForce::Gtc | TimeInForce::Gtd { .. }) => "limit",
+            (OrderType::Limit, TimeInForce::Ioc) => "ioc",
+            (OrderType::Limit, TimeInForce::PostOnly) => "post_only",
+            (OrderType::Market, _) => "optimal_20",
+        },
+        "volume": request.quantity.to_string().parse::<i64>().unwrap_or(1),
+        "price": request.price,
+        "lever_rate": 5,
+        "reduce_only": if request.reduce_only { 1 } else { 0 },
+    })
+}
+
+/// Authentication parameters in the sorted, percent-encoded form used both in
+/// the signature payload and the request URL, so the two can never disagree
+pub fn auth_query(api_key: &str, timestamp: &str) -> String {
+    format!(
+        "AccessKeyId={}&SignatureMethod=HmacSHA256&SignatureVersion=2&Timestamp={}",
+        urlencoding::encode(api_key),
+        urlencoding::encode(timestamp)
+    )
+}
+
+/// Signature payload: method, host, path and query, one per line
+pub fn signature_payload(method: &str, host: &str, path: &str, query: &str) -> String {
+    format!("{}\n{}\n{}\n{}", method.to_uppercase(), host.to_lowercase(), path, query)
+}
+
+pub fn sign(secret: &str, payload: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(payload.as_bytes());
+    STANDARD.encode(mac.finalize().into_bytes())
+}
+
+/// Query string carrying the auth parameters and their signature
+pub fn signed_query(
+    api_key: &str,
+    secret: &str,
+    method: &str,
+    host: &str,
+    path: &str,
+    timestamp: &str,
+) -> String {
+    let query = auth_query(api_key, timestamp);
+    let signature = sign(secret, &signature_payload(method, host, path, &query));
+    format!("{}&Signature={}", query, urlencoding::encode(&signature))
+}
+
+fn order_response(order: HtxOrderDetail) -> OrderResponse {
+    OrderResponse {
+        exchange_order_id: order.order_id_str,
+        client_order_id: order.client_order_id.map(|c| c.to_string()).unwrap_or_default(),
+        symbol: order.contract_code,
+        side: match order.direction.as_str() {
+            "buy" => Side::Buy,
+            _ => Side::Sell,
+        },
+        order_type: OrderType::Limit,
+        price: Some(Decimal::from_f64_retain(order.price).unwrap_or_default()),
+        quantity: Decimal::from(order.volume),
+        filled_quantity: Decimal::from(order.trade_volume),
+        avg_fill_price: order.trade_avg_price.and_then(Decimal::from_f64_retain),
+        status: parse_htx_status(order.status),
+        timestamp: epoch_millis(order.created_at),
+    }
+}
+
 fn parse_htx_status(status: i32) -> OrderStatus {
     match status {
         1 | 2 => OrderStatus::Pending,  // Preparing / Submitted
@@ -353,3 +448,106 @@ fn parse_htx_status(status: i32) -> OrderStatus {
         _ => OrderStatus::Pending,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::QuantityMode;
+    use chrono::TimeZone;
+
+    // Credentials and timestamp from the HTX signature docs
+    const ACCESS_KEY: &str = "e2xxxxxx-99xxxxxx-84xxxxxx-7xxxx";
+    const SECRET_KEY: &str = "b0xxxxxx-c6xxxxxx-94xxxxxx-dxxxx";
+    const TIMESTAMP: &str = "2017-05-11T15:19:30";
+
+    #[tokio::test]
+    async fn test_order_book_is_parsed_best_first() {
+        let (url, server) = crate::exchange::mock::serve_http(vec![(
+            "200 OK",
+            r#"{"ch":"market.BTC-USDT.depth.step0","status":"ok","tick":{"asks":[[13084.2,168],[13085.0,2]],"bids":[[13084.0,38],[13083.9,1006]],"ch":"market.BTC-USDT.depth.step0","id":1603694838,"mrid":131471527,"ts":1603694838167,"version":1603694838},"ts":1603694838245}"#,
+        )])
+        .await;
+        let config = crate::exchange::mock::exchange_config("htx", url);
+        let adapter = HtxAdapter::new(config).await.unwrap();
+
+        let book = adapter.get_order_book("BTC-USDT", 1).await.unwrap();
+
+        assert_eq!(book.bids.len(), 1);
+        assert_eq!(book.bids[0].price, Decimal::new(130840, 1));
+        assert_eq!(book.asks.len(), 1);
+        assert_eq!(book.asks[0].quantity, Decimal::new(168, 0));
+        assert_eq!(book.timestamp, 1603694838167);
+        let requests = server.await.unwrap();
+        assert!(
+            requests[0].starts_with("GET /linear-swap-ex/market/depth?contract_code=BTC-USDT&type=step0"),
+            "{:?}",
+            requests
+        );
+    }
+
+    #[test]
+    fn test_reduce_only_orders_close_the_position() {
+        let request = OrderRequest {
+            client_order_id: "cs1".to_string(),
+            symbol: "BTC-USDT".to_string(),
+            side: Side::Sell,
+            order_type: OrderType::Limit,
+            price: Some(Decimal::ONE_HUNDRED),
+            quantity: Decimal::from(3),
+            reduce_only: false,
+            time_in_force: TimeInForce::Gtc,
+            quantity_mode: QuantityMode::Base,
+        };
+        let open = order_body("BTC-USDT", &request);
+        assert_eq!(open["offset"], "open");
+        assert_eq!(open["direction"], "sell");
+
+        let close = order_body("BTC-USDT", &OrderRequest { reduce_only: true, ..request });
+        assert_eq!(close["offset"], "close");
+        assert_eq!(close["direction"], "sell");
+        assert_eq!(close["reduce_only"], 1);
+    }
+
+    #[test]
+    fn test_timestamp_format() {
+        let time = Utc.with_ymd_and_hms(2017, 5, 11, 15, 19, 30).unwrap();
+        assert_eq!(time.format(TIMESTAMP_FORMAT).to_string(), TIMESTAMP);
+    }
+
+    #[test]
+    fn test_signed_query_matches_signature_payload() {
+        let path = "/linear-swap-api/v1/swap_cross_order";
+        let query = auth_query(ACCESS_KEY, TIMESTAMP);
+        assert_eq!(
+            query,
+            "AccessKeyId=e2xxxxxx-99xxxxxx-84xxxxxx-7xxxx&SignatureMethod=HmacSHA256&SignatureVersion=2&Timestamp=2017-05-11T15%3A19%3A30"
+        );
+
+        let payload = signature_payload("post", "API.huobi.pro", path, &query);
+        assert_eq!(payload, format!("POST\napi.huobi.pro\n{}\n{}", path, query));
+
+        // The URL carries exactly the signed parameters. The documented
+        // example itself is checked with the other signing vectors.
+        let signature = urlencoding::encode(&sign(SECRET_KEY, &payload)).into_owned();
+        assert_eq!(
+            signed_query(ACCESS_KEY, SECRET_KEY, "POST", "api.huobi.pro", path, TIMESTAMP),
+            format!("{}&Signature={}", query, signature)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_order_timestamp_is_in_millis() {
+        let (url, _server) = crate::exchange::mock::serve_http(vec![(
+            "200 OK",
+            r#"{"status":"ok","data":[{"order_id":1,"order_id_str":"1","symbol":"BTC","contract_code":"BTC-USDT","direction":"buy","offset":"open","price":100.0,"volume":2,"trade_volume":2,"trade_avg_price":100.0,"status":6,"created_at":1639107468184,"client_order_id":null}]}"#,
+        )])
+        .await;
+        let adapter = HtxAdapter::new(crate::exchange::mock::exchange_config("htx", url)).await.unwrap();
+
+        let order = adapter
+            .get_order(&crate::exchange::mock::credentials(), "BTC-USDT", "1")
+            .await
+            .unwrap();
+        assert_eq!(order.timestamp, 1639107468184);
+    }
+}
@@ -11,23 +11,33 @@ use sha2::Sha256;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{debug, info};
 
-use super::{Credentials, ExchangeAdapter, OrderRequest, OrderResponse, OrderStatus, OrderType, Side};
+use super::{
+    epoch_millis, parse_json, parse_level_rows, ContractSpec, Credentials, ExchangeAdapter, OrderBook, OrderRequest,
+    OrderResponse, OrderStatus, OrderType, QuoteAssets, Side, TimeInForce,
+};
+use super::raw_http::SendTraced;
 use crate::config::ExchangeConfig;
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// Most orders one page of open orders holds
+const OPEN_ORDERS_PAGE_SIZE: usize = 100;
+
 pub struct KucoinAdapter {
     config: ExchangeConfig,
     client: Client,
+    quote_assets: QuoteAssets,
 }
 
 impl KucoinAdapter {
     pub async fn new(config: ExchangeConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .build()?;
+        let client = super::http_client(&config)?;
 
-        Ok(Self { config, client })
+        Ok(Self {
+            config,
+            client,
+            quote_assets: QuoteAssets::default(),
+        })
     }
 
     fn timestamp() -> String {
@@ -37,21 +47,6 @@ impl KucoinAdapter {
             .as_millis()
             .to_string()
     }
-
-    fn sign(&self, secret: &str, timestamp: &str, method: &str, path: &str, body: &str) -> String {
-        let str_to_sign = format!("{}{}{}{}", timestamp, method.to_uppercase(), path, body);
-        let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
-            .expect("HMAC can take key of any size");
-        mac.update(str_to_sign.as_bytes());
-        STANDARD.encode(mac.finalize().into_bytes())
-    }
-
-    fn sign_passphrase(&self, secret: &str, passphrase: &str) -> String {
-        let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
-            .expect("HMAC can take key of any size");
-        mac.update(passphrase.as_bytes());
-        STANDARD.encode(mac.finalize().into_bytes())
-    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -78,6 +73,7 @@ struct KucoinOrderDetail {
     order_type: String,
     price: Option<String>,
     size: String,
+    /// Filled lots
     #[serde(rename = "filledSize")]
     filled_size: String,
     #[serde(rename = "dealFunds")]
@@ -93,16 +89,60 @@ impl ExchangeAdapter for KucoinAdapter {
         "kucoin"
     }
 
+    // Perpetuals are suffixed with M and BTC is listed as XBT
+    fn to_native_symbol(&self, base: &str, quote: &str) -> String {
+        let base = if base == "BTC" { "XBT" } else { base };
+        format!("{}{}M", base, quote)
+    }
+
+    fn to_canonical_symbol(&self, native: &str) -> Option<String> {
+        let canonical = self.quote_assets.canonical(native.strip_suffix('M')?)?;
+        Some(match canonical.strip_prefix("XBT/") {
+            Some(quote) => format!("BTC/{}", quote),
+            None => canonical,
+        })
+    }
+
+    /// `USDM` contracts are coin-margined, 1 USD a contract
+    fn contract_spec(&self, symbol: &str) -> ContractSpec {
+        if self.native_symbol(symbol).ends_with("USDM") {
+            ContractSpec::inverse(Decimal::ONE)
+        } else {
+            ContractSpec::default()
+        }
+    }
+
+    async fn load_quote_assets(&self) -> Result<()> {
+        let url = format!("{}/api/v1/contracts/active", self.config.rest_url);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct Contract {
+            #[serde(rename = "quoteCurrency")]
+            quote_currency: String,
+        }
+
+        let resp: KucoinResponse<Vec<Contract>> = parse_json(&body)?;
+        if resp.code != "200000" {
+            anyhow::bail!("KuCoin contracts error: {} - {}", resp.code, resp.msg.unwrap_or_default());
+        }
+        self.quote_assets.set(resp.data.unwrap_or_default().into_iter().map(|c| c.quote_currency));
+        Ok(())
+    }
+
     async fn place_order(
         &self,
         credentials: &Credentials,
         request: &OrderRequest,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(&request.symbol);
         let timestamp = Self::timestamp();
         let path = "/api/v1/orders";
         
         let body = serde_json::json!({
-            "symbol": request.symbol,
+            "symbol": symbol,
             "side": match request.side {
                 Side::Buy => "buy",
                 Side::Sell => "sell",
@@ -114,15 +154,17 @@ impl ExchangeAdapter for KucoinAdapter {
             "leverage": "5",
             "size": request.quantity.to_string(),
             "price": request.price.map(|p| p.to_string()),
+            "timeInForce": if request.time_in_force == TimeInForce::Ioc { "IOC" } else { "GTC" },
+            "postOnly": request.time_in_force == TimeInForce::PostOnly,
             "clientOid": request.client_order_id,
             "reduceOnly": request.reduce_only,
         }).to_string();
 
-        let signature = self.sign(&credentials.api_secret, &timestamp, "POST", path, &body);
+        let signature = sign(&credentials.api_secret, &timestamp, "POST", path, &body);
         let passphrase = credentials.passphrase.as_deref().unwrap_or("");
-        let signed_passphrase = self.sign_passphrase(&credentials.api_secret, passphrase);
+        let signed_passphrase = sign_passphrase(&credentials.api_secret, passphrase);
 
-        debug!("Placing KuCoin order: {}", request.symbol);
+        debug!("Placing KuCoin order: {}", symbol);
 
         let url = format!("{}{}", self.config.rest_url, path);
         let response = self.client
@@ -134,7 +176,7 @@ impl ExchangeAdapter for KucoinAdapter {
             .header("KC-API-KEY-VERSION", "2")
             .header("Content-Type", "application/json")
             .body(body)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await
             .context("Failed to send order request")?;
 
@@ -145,7 +187,7 @@ impl ExchangeAdapter for KucoinAdapter {
             anyhow::bail!("KuCoin order failed: {} - {}", status, body);
         }
 
-        let resp: KucoinResponse<KucoinOrderId> = serde_json::from_str(&body)
+        let resp: KucoinResponse<KucoinOrderId> = parse_json(&body)
             .context("Failed to parse order response")?;
 
         if resp.code != "200000" {
@@ -159,7 +201,7 @@ impl ExchangeAdapter for KucoinAdapter {
         Ok(OrderResponse {
             exchange_order_id: order_id,
             client_order_id: request.client_order_id.clone(),
-            symbol: request.symbol.clone(),
+            symbol: self.canonical_symbol(&symbol),
             side: request.side.clone(),
             order_type: request.order_type.clone(),
             price: request.price,
@@ -177,12 +219,13 @@ impl ExchangeAdapter for KucoinAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
         let timestamp = Self::timestamp();
         let path = format!("/api/v1/orders/{}", order_id);
         
-        let signature = self.sign(&credentials.api_secret, &timestamp, "DELETE", &path, "");
+        let signature = sign(&credentials.api_secret, &timestamp, "DELETE", &path, "");
         let passphrase = credentials.passphrase.as_deref().unwrap_or("");
-        let signed_passphrase = self.sign_passphrase(&credentials.api_secret, passphrase);
+        let signed_passphrase = sign_passphrase(&credentials.api_secret, passphrase);
 
         let url = format!("{}{}", self.config.rest_url, path);
         let response = self.client
@@ -192,7 +235,7 @@ impl ExchangeAdapter for KucoinAdapter {
             .header("KC-API-TIMESTAMP", &timestamp)
             .header("KC-API-PASSPHRASE", &signed_passphrase)
             .header("KC-API-KEY-VERSION", "2")
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
         let _body = response.text().await?;
@@ -200,7 +243,7 @@ impl ExchangeAdapter for KucoinAdapter {
         Ok(OrderResponse {
             exchange_order_id: order_id.to_string(),
             client_order_id: String::new(),
-            symbol: symbol.to_string(),
+            symbol: self.canonical_symbol(&symbol),
             side: Side::Buy,
             order_type: OrderType::Limit,
             price: None,
@@ -221,9 +264,9 @@ impl ExchangeAdapter for KucoinAdapter {
         let timestamp = Self::timestamp();
         let path = format!("/api/v1/orders/{}", order_id);
         
-        let signature = self.sign(&credentials.api_secret, &timestamp, "GET", &path, "");
+        let signature = sign(&credentials.api_secret, &timestamp, "GET", &path, "");
         let passphrase = credentials.passphrase.as_deref().unwrap_or("");
-        let signed_passphrase = self.sign_passphrase(&credentials.api_secret, passphrase);
+        let signed_passphrase = sign_passphrase(&credentials.api_secret, passphrase);
 
         let url = format!("{}{}", self.config.rest_url, path);
         let response = self.client
@@ -233,39 +276,72 @@ impl ExchangeAdapter for KucoinAdapter {
             .header("KC-API-TIMESTAMP", &timestamp)
             .header("KC-API-PASSPHRASE", &signed_passphrase)
             .header("KC-API-KEY-VERSION", "2")
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
         let body = response.text().await?;
-        let resp: KucoinResponse<KucoinOrderDetail> = serde_json::from_str(&body)?;
+        let resp: KucoinResponse<KucoinOrderDetail> = parse_json(&body)?;
 
         let order = resp.data.ok_or_else(|| anyhow::anyhow!("No order data"))?;
 
-        Ok(OrderResponse {
-            exchange_order_id: order.id,
-            client_order_id: order.client_oid.unwrap_or_default(),
-            symbol: order.symbol,
-            side: match order.side.as_str() {
-                "buy" => Side::Buy,
-                _ => Side::Sell,
-            },
-            order_type: match order.order_type.as_str() {
-                "limit" => OrderType::Limit,
-                _ => OrderType::Market,
-            },
-            price: order.price.and_then(|p| p.parse().ok()),
-            quantity: order.size.parse().unwrap_or_default(),
-            filled_quantity: order.filled_size.parse().unwrap_or_default(),
-            avg_fill_price: order.deal_funds.and_then(|f| f.parse().ok()),
-            status: parse_kucoin_status(&order.status),
-            timestamp: order.created_at,
-        })
+        Ok(order_response(order).canonical(self))
+    }
+
+    async fn get_open_orders(&self, credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        let symbol = self.native_symbol(symbol);
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct OrderPage {
+            total_page: u32,
+            items: Vec<KucoinOrderDetail>,
+        }
+
+        let mut orders = Vec::new();
+        let mut page = 1;
+        loop {
+            let timestamp = Self::timestamp();
+            let path = format!(
+                "/api/v1/orders?status=active&symbol={}&currentPage={}&pageSize={}",
+                symbol, page, OPEN_ORDERS_PAGE_SIZE
+            );
+
+            let signature = sign(&credentials.api_secret, &timestamp, "GET", &path, "");
+            let passphrase = credentials.passphrase.as_deref().unwrap_or("");
+            let signed_passphrase = sign_passphrase(&credentials.api_secret, passphrase);
+
+            let url = format!("{}{}", self.config.rest_url, path);
+            let response = self.client
+                .get(&url)
+                .header("KC-API-KEY", &credentials.api_key)
+                .header("KC-API-SIGN", &signature)
+                .header("KC-API-TIMESTAMP", &timestamp)
+                .header("KC-API-PASSPHRASE", &signed_passphrase)
+                .header("KC-API-KEY-VERSION", "2")
+                .send_traced(self.config.log_raw_http)
+                .await?;
+
+            let body = response.text().await?;
+            let resp: KucoinResponse<OrderPage> = parse_json(&body)?;
+            if resp.code != "200000" {
+                anyhow::bail!("KuCoin open orders error: {} - {}", resp.code, resp.msg.unwrap_or_default());
+            }
+
+            let listed = resp.data.ok_or_else(|| anyhow::anyhow!("No open orders data"))?;
+            orders.extend(listed.items.into_iter().map(|order| order_response(order).canonical(self)));
+            if page >= listed.total_page {
+                break;
+            }
+            page += 1;
+        }
+        Ok(orders)
     }
 
     async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
+        let symbol = self.native_symbol(symbol);
         let url = format!("{}/api/v1/ticker?symbol={}", self.config.rest_url, symbol);
         
-        let response = self.client.get(&url).send().await?;
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
         let body = response.text().await?;
         
         #[derive(Deserialize)]
@@ -276,7 +352,7 @@ impl ExchangeAdapter for KucoinAdapter {
             best_ask_price: String,
         }
         
-        let resp: KucoinResponse<Ticker> = serde_json::from_str(&body)?;
+        let resp: KucoinResponse<Ticker> = parse_json(&body)?;
         let ticker = resp.data.ok_or_else(|| anyhow::anyhow!("No ticker data"))?;
 
         Ok((
@@ -285,11 +361,64 @@ impl ExchangeAdapter for KucoinAdapter {
         ))
     }
 
+    async fn get_order_book(&self, symbol: &str, depth: usize) -> Result<OrderBook> {
+        let symbol = self.native_symbol(symbol);
+        // Partial books come in 20 or 100 levels
+        let levels = if depth <= 20 { 20 } else { 100 };
+        let url = format!("{}/api/v1/level2/depth{}?symbol={}", self.config.rest_url, levels, symbol);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct Depth {
+            bids: Vec<Vec<serde_json::Value>>,
+            asks: Vec<Vec<serde_json::Value>>,
+            /// Nanoseconds
+            ts: i64,
+        }
+
+        let resp: KucoinResponse<Depth> = parse_json(&body)?;
+        if resp.code != "200000" {
+            anyhow::bail!("KuCoin order book error: {} - {}", resp.code, resp.msg.unwrap_or_default());
+        }
+        let book = resp.data.ok_or_else(|| anyhow::anyhow!("No order book data"))?;
+
+        Ok(OrderBook::from_levels(
+            parse_level_rows(&book.bids)?,
+            parse_level_rows(&book.asks)?,
+            depth,
+            book.ts / 1_000_000,
+        ))
+    }
+
     fn is_connected(&self) -> bool {
         true
     }
 }
 
+fn order_response(order: KucoinOrderDetail) -> OrderResponse {
+    OrderResponse {
+        exchange_order_id: order.id,
+        client_order_id: order.client_oid.unwrap_or_default(),
+        symbol: order.symbol,
+        side: match order.side.as_str() {
+            "buy" => Side::Buy,
+            _ => Side::Sell,
+        },
+        order_type: match order.order_type.as_str() {
+            "limit" => OrderType::Limit,
+            _ => OrderType::Market,
+        },
+        price: order.price.and_then(|p| p.parse().ok()),
+        quantity: order.size.parse().unwrap_or_default(),
+        filled_quantity: order.filled_size.parse().unwrap_or_default(),
+        avg_fill_price: order.deal_funds.and_then(|f| f.parse().ok()),
+        status: parse_kucoin_status(&order.status),
+        timestamp: epoch_millis(order.created_at),
+    }
+}
+
 fn parse_kucoin_status(status: &str) -> OrderStatus {
     match status {
         "open" | "new" => OrderStatus::Open,
@@ -299,3 +428,96 @@ fn parse_kucoin_status(status: &str) -> OrderStatus {
         _ => OrderStatus::Pending,
     }
 }
+
+/// HMAC-SHA256 of `timestamp + METHOD + path + body`, base64
+pub fn sign(secret: &str, timestamp: &str, method: &str, path: &str, body: &str) -> String {
+    let str_to_sign = format!("{}{}{}{}", timestamp, method.to_uppercase(), path, body);
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(str_to_sign.as_bytes());
+    STANDARD.encode(mac.finalize().into_bytes())
+}
+
+/// Passphrase as API key version 2 sends it: HMAC-SHA256 under the
+/// secret, base64
+pub fn sign_passphrase(secret: &str, passphrase: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(passphrase.as_bytes());
+    STANDARD.encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{credentials, exchange_config, serve_http};
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn test_usd_contracts_are_inverse() {
+        let adapter = KucoinAdapter::new(exchange_config("kucoin", "http://127.0.0.1:1".to_string())).await.unwrap();
+        assert_eq!(adapter.contract_spec("BTC/USD"), ContractSpec::inverse(dec!(1)));
+        assert_eq!(adapter.contract_spec("BTC/USDT"), ContractSpec::default());
+    }
+
+    #[tokio::test]
+    async fn test_order_book_is_parsed_best_first() {
+        let (url, server) = serve_http(vec![(
+            "200 OK",
+            r#"{"code":"200000","data":{"symbol":"XBTUSDTM","sequence":100,"asks":[[5000.0,1000],[6000.0,1983]],"bids":[[3200.0,800],[3100.0,100]],"ts":1604643655040584408}}"#,
+        )])
+        .await;
+        let adapter = KucoinAdapter::new(exchange_config("kucoin", url)).await.unwrap();
+
+        let book = adapter.get_order_book("XBTUSDTM", 2).await.unwrap();
+
+        assert_eq!(book.bids[0].price, dec!(3200));
+        assert_eq!(book.bids[1].quantity, dec!(100));
+        assert_eq!(book.asks[0].price, dec!(5000));
+        assert_eq!(book.asks[1].quantity, dec!(1983));
+        assert_eq!(book.timestamp, 1604643655040);
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /api/v1/level2/depth20?symbol=XBTUSDTM"), "{:?}", requests);
+    }
+
+    #[tokio::test]
+    async fn test_order_timestamp_is_in_millis() {
+        let (url, _server) = serve_http(vec![(
+            "200 OK",
+            r#"{"code":"200000","data":{"id":"5cdfc138b21023a909e5ad55","symbol":"XBTUSDTM","clientOid":"cs1","side":"buy","type":"limit","price":"100","size":"2","filledSize":"2","dealFunds":"200","status":"done","createdAt":1558167872000}}"#,
+        )])
+        .await;
+        let adapter = KucoinAdapter::new(exchange_config("kucoin", url)).await.unwrap();
+
+        let order = adapter.get_order(&credentials(), "XBTUSDTM", "5cdfc138b21023a909e5ad55").await.unwrap();
+        assert_eq!(order.timestamp, 1558167872000);
+    }
+
+    #[tokio::test]
+    async fn test_open_orders_are_read_from_every_page() {
+        let (url, server) = serve_http(vec![
+            (
+                "200 OK",
+                r#"{"code":"200000","data":{"currentPage":1,"pageSize":100,"totalNum":2,"totalPage":2,"items":[{"id":"a1","symbol":"XBTUSDTM","clientOid":"cs1","side":"buy","type":"limit","price":"100","size":"2","filledSize":"0","dealFunds":"0","status":"open","createdAt":1558167872000}]}}"#,
+            ),
+            (
+                "200 OK",
+                r#"{"code":"200000","data":{"currentPage":2,"pageSize":100,"totalNum":2,"totalPage":2,"items":[{"id":"a2","symbol":"XBTUSDTM","clientOid":"cs2","side":"sell","type":"limit","price":"110","size":"1","filledSize":"0","dealFunds":"0","status":"open","createdAt":1558167873000}]}}"#,
+            ),
+        ])
+        .await;
+        let adapter = KucoinAdapter::new(exchange_config("kucoin", url)).await.unwrap();
+
+        let orders = adapter.get_open_orders(&credentials(), "XBTUSDTM").await.unwrap();
+
+        let ids: Vec<_> = orders.iter().map(|o| o.client_order_id.as_str()).collect();
+        assert_eq!(ids, ["cs1", "cs2"]);
+        let requests = server.await.unwrap();
+        assert!(
+            requests[0].starts_with("GET /api/v1/orders?status=active&symbol=XBTUSDTM&currentPage=1&pageSize=100"),
+            "{:?}",
+            requests
+        );
+        assert!(requests[1].contains("currentPage=2"), "{:?}", requests);
+    }
+}
@@ -10,7 +10,11 @@ use sha2::Sha256;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{debug, info};
 
-use super::{Credentials, ExchangeAdapter, OrderRequest, OrderResponse, OrderStatus, OrderType, Side};
+use super::{
+    epoch_millis, now_millis, parse_json, parse_level_rows, Credentials, ExchangeAdapter, OrderBook,
+    OrderRequest, OrderResponse, OrderStatus, OrderType, QuoteAssets, Side, TimeInForce,
+};
+use super::raw_http::SendTraced;
 use crate::config::ExchangeConfig;
 
 type HmacSha256 = Hmac<Sha256>;
@@ -18,15 +22,18 @@ type HmacSha256 = Hmac<Sha256>;
 pub struct LbankAdapter {
     config: ExchangeConfig,
     client: Client,
+    quote_assets: QuoteAssets,
 }
 
 impl LbankAdapter {
     pub async fn new(config: ExchangeConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .build()?;
+        let client = super::http_client(&config)?;
 
-        Ok(Self { config, client })
+        Ok(Self {
+            config,
+            client,
+            quote_assets: QuoteAssets::default(),
+        })
     }
 
     fn timestamp() -> String {
@@ -36,13 +43,6 @@ impl LbankAdapter {
             .as_millis()
             .to_string()
     }
-
-    fn sign(&self, secret: &str, params: &str) -> String {
-        let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
-            .expect("HMAC can take key of any size");
-        mac.update(params.as_bytes());
-        hex::encode(mac.finalize().into_bytes())
-    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -57,9 +57,9 @@ struct LbankOrder {
     order_id: String,
     symbol: String,
     direction: String,
-    offset: String,
     price: String,
     volume: String,
+    /// Traded volume, missing before the first fill
     traded_volume: Option<String>,
     avg_price: Option<String>,
     status: i32,
@@ -73,52 +73,60 @@ impl ExchangeAdapter for LbankAdapter {
         "lbank"
     }
 
+    fn to_native_symbol(&self, base: &str, quote: &str) -> String {
+        format!("{}{}", base, quote)
+    }
+
+    fn to_canonical_symbol(&self, native: &str) -> Option<String> {
+        self.quote_assets.canonical(native)
+    }
+
+    async fn load_quote_assets(&self) -> Result<()> {
+        let url = format!("{}/cfd/openApi/v1/pub/instrument?productGroup=SwapU", self.config.rest_url);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct Instrument {
+            #[serde(rename = "priceCurrency")]
+            price_currency: String,
+        }
+
+        let resp: LbankResponse<Vec<Instrument>> = parse_json(&body)?;
+        if !resp.result {
+            anyhow::bail!("LBank instrument error: {}", resp.error_code.unwrap_or_default());
+        }
+        self.quote_assets.set(resp.data.unwrap_or_default().into_iter().map(|i| i.price_currency));
+        Ok(())
+    }
+
     async fn place_order(
         &self,
         credentials: &Credentials,
         request: &OrderRequest,
     ) -> Result<OrderResponse> {
-        let timestamp = Self::timestamp();
-        
-        let mut params = vec![
-            ("api_key", credentials.api_key.clone()),
-            ("symbol", request.symbol.clone()),
-            ("direction", match request.side {
-                Side::Buy => "buy".to_string(),
-                Side::Sell => "sell".to_string(),
-            }),
-            ("offset", "open".to_string()),
-            ("type", match request.order_type {
-                OrderType::Limit => "1".to_string(),
-                OrderType::Market => "2".to_string(),
-            }),
-            ("volume", request.quantity.to_string()),
-            ("timestamp", timestamp.clone()),
-        ];
-
-        if let Some(price) = request.price {
-            params.push(("price", price.to_string()));
+        let symbol = self.native_symbol(&request.symbol);
+        if !matches!(request.time_in_force, TimeInForce::Gtc | TimeInForce::Gtd { .. }) {
+            anyhow::bail!("LBank does not support {:?} orders", request.time_in_force);
         }
-        if !request.client_order_id.is_empty() {
-            params.push(("client_order_id", request.client_order_id.clone()));
-        }
-
+        let mut params = order_params(&credentials.api_key, &symbol, request, &Self::timestamp());
         params.sort_by(|a, b| a.0.cmp(b.0));
         let params_str = params.iter()
             .map(|(k, v)| format!("{}={}", k, v))
             .collect::<Vec<_>>()
             .join("&");
 
-        let signature = self.sign(&credentials.api_secret, &params_str);
+        let signature = sign(&credentials.api_secret, &params_str);
 
-        debug!("Placing LBank order: {}", request.symbol);
+        debug!("Placing LBank order: {}", symbol);
 
         let url = format!("{}/cfd/openApi/v1/order/create", self.config.rest_url);
         let response = self.client
             .post(&url)
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(format!("{}&sign={}", params_str, signature))
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await
             .context("Failed to send order request")?;
 
@@ -129,7 +137,7 @@ impl ExchangeAdapter for LbankAdapter {
             anyhow::bail!("LBank order failed: {} - {}", status, body);
         }
 
-        let resp: LbankResponse<LbankOrder> = serde_json::from_str(&body)
+        let resp: LbankResponse<LbankOrder> = parse_json(&body)
             .context("Failed to parse order response")?;
 
         if !resp.result {
@@ -143,7 +151,7 @@ impl ExchangeAdapter for LbankAdapter {
         Ok(OrderResponse {
             exchange_order_id: order.order_id,
             client_order_id: order.client_order_id.unwrap_or_default(),
-            symbol: order.symbol,
+            symbol: self.canonical_symbol(&order.symbol),
             side: match order.direction.as_str() {
                 "buy" => Side::Buy,
                 _ => Side::Sell,
@@ -157,7 +165,7 @@ impl ExchangeAdapter for LbankAdapter {
             filled_quantity: order.traded_volume.and_then(|s| s.parse().ok()).unwrap_or_default(),
             avg_fill_price: order.avg_price.and_then(|s| s.parse().ok()),
             status: parse_lbank_status(order.status),
-            timestamp: order.create_time,
+            timestamp: epoch_millis(order.create_time),
         })
     }
 
@@ -167,9 +175,10 @@ impl ExchangeAdapter for LbankAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
         let timestamp = Self::timestamp();
         
-        let mut params = vec![
+        let mut params = [
             ("api_key", credentials.api_key.clone()),
             ("symbol", symbol.to_string()),
             ("order_id", order_id.to_string()),
@@ -182,25 +191,25 @@ impl ExchangeAdapter for LbankAdapter {
             .collect::<Vec<_>>()
             .join("&");
 
-        let signature = self.sign(&credentials.api_secret, &params_str);
+        let signature = sign(&credentials.api_secret, &params_str);
 
         let url = format!("{}/cfd/openApi/v1/order/cancel", self.config.rest_url);
         let response = self.client
             .post(&url)
             .header("Content-Type", "application/x-www-form-urlencoded")
             .body(format!("{}&sign={}", params_str, signature))
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
         let body = response.text().await?;
-        let resp: LbankResponse<LbankOrder> = serde_json::from_str(&body)?;
+        let resp: LbankResponse<LbankOrder> = parse_json(&body)?;
 
         let order = resp.data.ok_or_else(|| anyhow::anyhow!("No order data"))?;
 
         Ok(OrderResponse {
             exchange_order_id: order.order_id,
             client_order_id: order.client_order_id.unwrap_or_default(),
-            symbol: order.symbol,
+            symbol: self.canonical_symbol(&order.symbol),
             side: match order.direction.as_str() {
                 "buy" => Side::Buy,
                 _ => Side::Sell,
@@ -211,7 +220,7 @@ impl ExchangeAdapter for LbankAdapter {
             filled_quantity: order.traded_volume.and_then(|s| s.parse().ok()).unwrap_or_default(),
             avg_fill_price: order.avg_price.and_then(|s| s.parse().ok()),
             status: OrderStatus::Cancelled,
-            timestamp: order.create_time,
+            timestamp: epoch_millis(order.create_time),
         })
     }
 
@@ -221,9 +230,10 @@ impl ExchangeAdapter for LbankAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
         let timestamp = Self::timestamp();
         
-        let mut params = vec![
+        let mut params = [
             ("api_key", credentials.api_key.clone()),
             ("symbol", symbol.to_string()),
             ("order_id", order_id.to_string()),
@@ -236,40 +246,58 @@ impl ExchangeAdapter for LbankAdapter {
             .collect::<Vec<_>>()
             .join("&");
 
-        let signature = self.sign(&credentials.api_secret, &params_str);
+        let signature = sign(&credentials.api_secret, &params_str);
 
         let url = format!("{}/cfd/openApi/v1/order/detail?{}&sign={}", 
             self.config.rest_url, params_str, signature);
         
-        let response = self.client.get(&url).send().await?;
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
         let body = response.text().await?;
-        let resp: LbankResponse<LbankOrder> = serde_json::from_str(&body)?;
+        let resp: LbankResponse<LbankOrder> = parse_json(&body)?;
 
         let order = resp.data.ok_or_else(|| anyhow::anyhow!("No order data"))?;
 
-        Ok(OrderResponse {
-            exchange_order_id: order.order_id,
-            client_order_id: order.client_order_id.unwrap_or_default(),
-            symbol: order.symbol,
-            side: match order.direction.as_str() {
-                "buy" => Side::Buy,
-                _ => Side::Sell,
-            },
-            order_type: OrderType::Limit,
-            price: order.price.parse().ok(),
-            quantity: order.volume.parse().unwrap_or_default(),
-            filled_quantity: order.traded_volume.and_then(|s| s.parse().ok()).unwrap_or_default(),
-            avg_fill_price: order.avg_price.and_then(|s| s.parse().ok()),
-            status: parse_lbank_status(order.status),
-            timestamp: order.create_time,
-        })
+        Ok(order_response(order).canonical(self))
+    }
+
+    async fn get_open_orders(&self, credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        let symbol = self.native_symbol(symbol);
+        let timestamp = Self::timestamp();
+
+        let mut params = [
+            ("api_key", credentials.api_key.clone()),
+            ("symbol", symbol.to_string()),
+            ("timestamp", timestamp),
+        ];
+
+        params.sort_by(|a, b| a.0.cmp(b.0));
+        let params_str = params.iter()
+            .map(|(k, v)| format!("{}={}", k, v))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let signature = sign(&credentials.api_secret, &params_str);
+
+        let url = format!("{}/cfd/openApi/v1/order/openOrders?{}&sign={}",
+            self.config.rest_url, params_str, signature);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+        let resp: LbankResponse<Vec<LbankOrder>> = parse_json(&body)?;
+        if !resp.result {
+            anyhow::bail!("LBank open orders error: {}", resp.error_code.unwrap_or_default());
+        }
+
+        let listed = resp.data.unwrap_or_default();
+        Ok(listed.into_iter().map(|order| order_response(order).canonical(self)).collect())
     }
 
     async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
+        let symbol = self.native_symbol(symbol);
         let url = format!("{}/cfd/openApi/v1/pub/depth?symbol={}&size=1", 
             self.config.rest_url, symbol);
         
-        let response = self.client.get(&url).send().await?;
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
         let body = response.text().await?;
         
         #[derive(Deserialize)]
@@ -278,7 +306,7 @@ impl ExchangeAdapter for LbankAdapter {
             asks: Vec<Vec<String>>,
         }
         
-        let resp: LbankResponse<DepthData> = serde_json::from_str(&body)?;
+        let resp: LbankResponse<DepthData> = parse_json(&body)?;
         let depth = resp.data.ok_or_else(|| anyhow::anyhow!("No depth data"))?;
 
         let bid = depth.bids.first()
@@ -294,11 +322,93 @@ impl ExchangeAdapter for LbankAdapter {
         ))
     }
 
+    async fn get_order_book(&self, symbol: &str, depth: usize) -> Result<OrderBook> {
+        let symbol = self.native_symbol(symbol);
+        let url = format!("{}/cfd/openApi/v1/pub/depth?symbol={}&size={}",
+            self.config.rest_url, symbol, depth.max(1));
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct DepthData {
+            bids: Vec<Vec<serde_json::Value>>,
+            asks: Vec<Vec<serde_json::Value>>,
+        }
+
+        let resp: LbankResponse<DepthData> = parse_json(&body)?;
+        if !resp.result {
+            anyhow::bail!("LBank order book error: {}", resp.error_code.unwrap_or_default());
+        }
+        let book = resp.data.ok_or_else(|| anyhow::anyhow!("No depth data"))?;
+
+        // The depth isn't stamped
+        Ok(OrderBook::from_levels(
+            parse_level_rows(&book.bids)?,
+            parse_level_rows(&book.asks)?,
+            depth,
+            now_millis(),
+        ))
+    }
+
     fn is_connected(&self) -> bool {
         true
     }
 }
 
+/// Parameters of an order on `symbol`, before signing. Reduce-only orders
+/// go out with the close offset; LBank has no reduce-only flag, and an
+/// order with the open offset opens a position whatever it was meant for.
+fn order_params(
+    api_key: &str,
+    symbol: &str,
+    request: &OrderRequest,
+    timestamp: &str,
+) -> Vec<(&'static str, String)> {
+    let mut params = vec![
+        ("api_key", api_key.to_string()),
+        ("symbol", symbol.to_string()),
+        ("direction", match request.side {
+            Side::Buy => "buy".to_string(),
+            Side::Sell => "sell".to_string(),
+        }),
+        ("offset", if request.reduce_only { "close" } else { "open" }.to_string()),
+        ("type", match request.order_type {
+            OrderType::Limit => "1".to_string(),
+            OrderType::Market => "2".to_string(),
+        }),
+        ("volume", request.quantity.to_string()),
+        ("timestamp", timestamp.to_string()),
+    ];
+
+    if let Some(price) = request.price {
+        params.push(("price", price.to_string()));
+    }
+    if !request.client_order_id.is_empty() {
+        params.push(("client_order_id", request.client_order_id.clone()));
+    }
+    params
+}
+
+fn order_response(order: LbankOrder) -> OrderResponse {
+    OrderResponse {
+        exchange_order_id: order.order_id,
+        client_order_id: order.client_order_id.unwrap_or_default(),
+        symbol: order.symbol,
+        side: match order.direction.as_str() {
+            "buy" => Side::Buy,
+            _ => Side::Sell,
+        },
+        order_type: OrderType::Limit,
+        price: order.price.parse().ok(),
+        quantity: order.volume.parse().unwrap_or_default(),
+        filled_quantity: order.traded_volume.and_then(|s| s.parse().ok()).unwrap_or_default(),
+        avg_fill_price: order.avg_price.and_then(|s| s.parse().ok()),
+        status: parse_lbank_status(order.status),
+        timestamp: epoch_millis(order.create_time),
+    }
+}
+
 fn parse_lbank_status(status: i32) -> OrderStatus {
     match status {
         0 => OrderStatus::Pending,
@@ -309,3 +419,77 @@ fn parse_lbank_status(status: i32) -> OrderStatus {
         _ => OrderStatus::Pending,
     }
 }
+
+/// HMAC-SHA256 of the sorted form parameters, hex
+pub fn sign(secret: &str, params: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(params.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{credentials, exchange_config, serve_http};
+    use crate::exchange::QuantityMode;
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn test_order_book_is_parsed_best_first() {
+        let (url, server) = serve_http(vec![(
+            "200 OK",
+            r#"{"result":true,"error_code":0,"data":{"asks":[["63120.5","0.35"],["63121","1.2"]],"bids":[["63119.8","0.42"],["63119","2.5"]]}}"#,
+        )])
+        .await;
+        let adapter = LbankAdapter::new(exchange_config("lbank", url)).await.unwrap();
+        let before = now_millis();
+
+        let book = adapter.get_order_book("BTCUSDT", 2).await.unwrap();
+
+        assert_eq!(book.bids[0].price, dec!(63119.8));
+        assert_eq!(book.bids[1].quantity, dec!(2.5));
+        assert_eq!(book.asks[0].price, dec!(63120.5));
+        assert_eq!(book.asks[1].quantity, dec!(1.2));
+        assert!(book.timestamp >= before);
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /cfd/openApi/v1/pub/depth?symbol=BTCUSDT&size=2"), "{:?}", requests);
+    }
+
+    #[test]
+    fn test_reduce_only_orders_close_the_position() {
+        let request = OrderRequest {
+            client_order_id: "cs1".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Market,
+            price: None,
+            quantity: dec!(0.5),
+            reduce_only: false,
+            time_in_force: TimeInForce::Gtc,
+            quantity_mode: QuantityMode::Base,
+        };
+        let offset = |request: &OrderRequest| {
+            order_params("key", "BTCUSDT", request, "1700000000000")
+                .into_iter()
+                .find(|(name, _)| *name == "offset")
+                .map(|(_, value)| value)
+        };
+
+        assert_eq!(offset(&request).as_deref(), Some("open"));
+        assert_eq!(offset(&OrderRequest { reduce_only: true, ..request }).as_deref(), Some("close"));
+    }
+
+    #[tokio::test]
+    async fn test_order_timestamp_is_in_millis() {
+        let (url, _server) = serve_http(vec![(
+            "200 OK",
+            r#"{"result":true,"error_code":0,"data":{"order_id":"1","symbol":"BTCUSDT","direction":"buy","offset":"open","price":"100","volume":"2","traded_volume":"2","avg_price":"100","status":2,"create_time":1700000000123,"client_order_id":"cs1"}}"#,
+        )])
+        .await;
+        let adapter = LbankAdapter::new(exchange_config("lbank", url)).await.unwrap();
+
+        let order = adapter.get_order(&credentials(), "BTCUSDT", "1").await.unwrap();
+        assert_eq!(order.timestamp, 1700000000123);
+    }
+}
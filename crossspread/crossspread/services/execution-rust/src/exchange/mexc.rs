@@ -10,11 +10,18 @@ use sha2::Sha256;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{debug, info};
 
-use super::{Credentials, ExchangeAdapter, OrderRequest, OrderResponse, OrderStatus, OrderType, Side};
+use super::{
+    canonical_from_separated, epoch_millis, parse_json, parse_level_rows, Credentials, ExchangeAdapter, OrderBook, OrderRequest,
+    OrderResponse, OrderStatus, OrderType, Side, TimeInForce,
+};
+use super::raw_http::SendTraced;
 use crate::config::ExchangeConfig;
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// Most orders one page of open orders holds
+const OPEN_ORDERS_PAGE_SIZE: usize = 100;
+
 pub struct MexcAdapter {
     config: ExchangeConfig,
     client: Client,
@@ -22,9 +29,7 @@ pub struct MexcAdapter {
 
 impl MexcAdapter {
     pub async fn new(config: ExchangeConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .build()?;
+        let client = super::http_client(&config)?;
 
         Ok(Self { config, client })
     }
@@ -35,13 +40,6 @@ impl MexcAdapter {
             .unwrap()
             .as_millis() as u64
     }
-
-    fn sign(&self, secret: &str, query: &str) -> String {
-        let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
-            .expect("HMAC can take key of any size");
-        mac.update(query.as_bytes());
-        hex::encode(mac.finalize().into_bytes())
-    }
 }
 
 #[derive(Debug, Deserialize)]
@@ -63,6 +61,7 @@ struct MexcOrderData {
     order_type: i32,
     price: String,
     vol: String,
+    /// Filled contracts
     #[serde(rename = "dealVol")]
     deal_vol: String,
     #[serde(rename = "dealAvgPrice")]
@@ -78,45 +77,25 @@ impl ExchangeAdapter for MexcAdapter {
         "mexc"
     }
 
+    fn to_native_symbol(&self, base: &str, quote: &str) -> String {
+        format!("{}_{}", base, quote)
+    }
+
+    fn to_canonical_symbol(&self, native: &str) -> Option<String> {
+        canonical_from_separated(native, '_')
+    }
+
     async fn place_order(
         &self,
         credentials: &Credentials,
         request: &OrderRequest,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(&request.symbol);
         let timestamp = Self::timestamp();
-        
-        // MEXC uses different side codes for futures
-        let side = match request.side {
-            Side::Buy => 1,  // Open long
-            Side::Sell => 3, // Open short
-        };
-
-        let order_type = match request.order_type {
-            OrderType::Limit => 1,
-            OrderType::Market => 5,
-        };
-
-        let mut params = vec![
-            format!("symbol={}", request.symbol),
-            format!("side={}", side),
-            format!("openType=2"),  // Cross margin
-            format!("type={}", order_type),
-            format!("vol={}", request.quantity),
-            format!("timestamp={}", timestamp),
-        ];
-
-        if let Some(price) = &request.price {
-            params.push(format!("price={}", price));
-        }
-
-        if !request.client_order_id.is_empty() {
-            params.push(format!("externalOid={}", request.client_order_id));
-        }
-
-        let query = params.join("&");
-        let signature = self.sign(&credentials.api_secret, &query);
+        let query = order_params(&symbol, request, timestamp).join("&");
+        let signature = sign(&credentials.api_secret, &query);
 
-        debug!("Placing MEXC order: {}", request.symbol);
+        debug!("Placing MEXC order: {}", symbol);
 
         let url = format!("{}/api/v1/private/order/submit", self.config.rest_url);
         let response = self.client
@@ -127,7 +106,7 @@ impl ExchangeAdapter for MexcAdapter {
             .header("Content-Type", "application/json")
             .query(&[("signature", &signature)])
             .body(query)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await
             .context("Failed to send order request")?;
 
@@ -138,7 +117,7 @@ impl ExchangeAdapter for MexcAdapter {
             anyhow::bail!("MEXC order failed: {} - {}", status, body);
         }
 
-        let resp: MexcResponse<MexcOrderData> = serde_json::from_str(&body)
+        let resp: MexcResponse<MexcOrderData> = parse_json(&body)
             .context("Failed to parse order response")?;
 
         if resp.code != 0 {
@@ -152,7 +131,7 @@ impl ExchangeAdapter for MexcAdapter {
         Ok(OrderResponse {
             exchange_order_id: order.order_id,
             client_order_id: order.client_order_id.unwrap_or_default(),
-            symbol: order.symbol,
+            symbol: self.canonical_symbol(&order.symbol),
             side: if order.side == 1 || order.side == 2 { Side::Buy } else { Side::Sell },
             order_type: if order.order_type == 1 { OrderType::Limit } else { OrderType::Market },
             price: order.price.parse().ok(),
@@ -160,7 +139,7 @@ impl ExchangeAdapter for MexcAdapter {
             filled_quantity: order.deal_vol.parse().unwrap_or_default(),
             avg_fill_price: order.deal_avg_price.parse().ok(),
             status: parse_mexc_status(order.state),
-            timestamp: order.create_time,
+            timestamp: epoch_millis(order.create_time),
         })
     }
 
@@ -170,10 +149,11 @@ impl ExchangeAdapter for MexcAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
         let timestamp = Self::timestamp();
         
         let query = format!("symbol={}&orderId={}&timestamp={}", symbol, order_id, timestamp);
-        let signature = self.sign(&credentials.api_secret, &query);
+        let signature = sign(&credentials.api_secret, &query);
 
         let url = format!("{}/api/v1/private/order/cancel", self.config.rest_url);
         let response = self.client
@@ -183,18 +163,18 @@ impl ExchangeAdapter for MexcAdapter {
             .header("Signature", &signature)
             .query(&[("signature", &signature)])
             .body(query)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
         let body = response.text().await?;
-        let resp: MexcResponse<MexcOrderData> = serde_json::from_str(&body)?;
+        let resp: MexcResponse<MexcOrderData> = parse_json(&body)?;
 
         let order = resp.data.ok_or_else(|| anyhow::anyhow!("No order data"))?;
 
         Ok(OrderResponse {
             exchange_order_id: order.order_id,
             client_order_id: order.client_order_id.unwrap_or_default(),
-            symbol: order.symbol,
+            symbol: self.canonical_symbol(&order.symbol),
             side: if order.side == 1 || order.side == 2 { Side::Buy } else { Side::Sell },
             order_type: OrderType::Limit,
             price: order.price.parse().ok(),
@@ -202,7 +182,7 @@ impl ExchangeAdapter for MexcAdapter {
             filled_quantity: order.deal_vol.parse().unwrap_or_default(),
             avg_fill_price: order.deal_avg_price.parse().ok(),
             status: OrderStatus::Cancelled,
-            timestamp: order.create_time,
+            timestamp: epoch_millis(order.create_time),
         })
     }
 
@@ -212,10 +192,11 @@ impl ExchangeAdapter for MexcAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
         let timestamp = Self::timestamp();
         
         let query = format!("symbol={}&order_id={}&timestamp={}", symbol, order_id, timestamp);
-        let signature = self.sign(&credentials.api_secret, &query);
+        let signature = sign(&credentials.api_secret, &query);
 
         let url = format!("{}/api/v1/private/order/get/{}", self.config.rest_url, order_id);
         let response = self.client
@@ -223,33 +204,59 @@ impl ExchangeAdapter for MexcAdapter {
             .header("ApiKey", &credentials.api_key)
             .header("Request-Time", timestamp.to_string())
             .header("Signature", &signature)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
         let body = response.text().await?;
-        let resp: MexcResponse<MexcOrderData> = serde_json::from_str(&body)?;
+        let resp: MexcResponse<MexcOrderData> = parse_json(&body)?;
 
         let order = resp.data.ok_or_else(|| anyhow::anyhow!("No order data"))?;
 
-        Ok(OrderResponse {
-            exchange_order_id: order.order_id,
-            client_order_id: order.client_order_id.unwrap_or_default(),
-            symbol: order.symbol,
-            side: if order.side == 1 || order.side == 2 { Side::Buy } else { Side::Sell },
-            order_type: if order.order_type == 1 { OrderType::Limit } else { OrderType::Market },
-            price: order.price.parse().ok(),
-            quantity: order.vol.parse().unwrap_or_default(),
-            filled_quantity: order.deal_vol.parse().unwrap_or_default(),
-            avg_fill_price: order.deal_avg_price.parse().ok(),
-            status: parse_mexc_status(order.state),
-            timestamp: order.create_time,
-        })
+        Ok(order_response(order).canonical(self))
+    }
+
+    // Listed a page at a time, 100 orders being the most a page holds
+    async fn get_open_orders(&self, credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        let symbol = self.native_symbol(symbol);
+        let mut orders = Vec::new();
+        for page in 1.. {
+            let timestamp = Self::timestamp();
+            let query = format!("page_num={}&page_size={}", page, OPEN_ORDERS_PAGE_SIZE);
+            let signature = sign(&credentials.api_secret, &query);
+
+            let url = format!(
+                "{}/api/v1/private/order/list/open_orders/{}?{}",
+                self.config.rest_url, symbol, query
+            );
+            let response = self.client
+                .get(&url)
+                .header("ApiKey", &credentials.api_key)
+                .header("Request-Time", timestamp.to_string())
+                .header("Signature", &signature)
+                .send_traced(self.config.log_raw_http)
+                .await?;
+
+            let body = response.text().await?;
+            let resp: MexcResponse<Vec<MexcOrderData>> = parse_json(&body)?;
+            if resp.code != 0 {
+                anyhow::bail!("MEXC open orders error: {} - {}", resp.code, resp.msg.unwrap_or_default());
+            }
+
+            let page = resp.data.unwrap_or_default();
+            let last = page.len() < OPEN_ORDERS_PAGE_SIZE;
+            orders.extend(page.into_iter().map(|order| order_response(order).canonical(self)));
+            if last {
+                break;
+            }
+        }
+        Ok(orders)
     }
 
     async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
+        let symbol = self.native_symbol(symbol);
         let url = format!("{}/api/v1/contract/ticker?symbol={}", self.config.rest_url, symbol);
         
-        let response = self.client.get(&url).send().await?;
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
         let body = response.text().await?;
         
         #[derive(Deserialize)]
@@ -260,7 +267,7 @@ impl ExchangeAdapter for MexcAdapter {
             ask: String,
         }
         
-        let resp: MexcResponse<Ticker> = serde_json::from_str(&body)?;
+        let resp: MexcResponse<Ticker> = parse_json(&body)?;
         let ticker = resp.data.ok_or_else(|| anyhow::anyhow!("No ticker data"))?;
 
         Ok((
@@ -269,11 +276,93 @@ impl ExchangeAdapter for MexcAdapter {
         ))
     }
 
+    async fn get_order_book(&self, symbol: &str, depth: usize) -> Result<OrderBook> {
+        let symbol = self.native_symbol(symbol);
+        let url = format!("{}/api/v1/contract/depth/{}?limit={}", self.config.rest_url, symbol, depth.max(1));
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        // Levels are [price, contracts, order count]
+        #[derive(Deserialize)]
+        struct Depth {
+            bids: Vec<Vec<serde_json::Value>>,
+            asks: Vec<Vec<serde_json::Value>>,
+            timestamp: i64,
+        }
+
+        let resp: MexcResponse<Depth> = parse_json(&body)?;
+        if resp.code != 0 {
+            anyhow::bail!("MEXC order book error: {} - {}", resp.code, resp.msg.unwrap_or_default());
+        }
+        let book = resp.data.ok_or_else(|| anyhow::anyhow!("No order book data"))?;
+
+        Ok(OrderBook::from_levels(
+            parse_level_rows(&book.bids)?,
+            parse_level_rows(&book.asks)?,
+            depth,
+            book.timestamp,
+        ))
+    }
+
     fn is_connected(&self) -> bool {
         true
     }
 }
 
+/// Parameters of an order on `symbol`, before signing. MEXC folds the
+/// position effect into the side code, so reduce-only orders are sent as
+/// closes: a buy closes a short, a sell closes a long.
+fn order_params(symbol: &str, request: &OrderRequest, timestamp: u64) -> Vec<String> {
+    let side = match (request.side, request.reduce_only) {
+        (Side::Buy, false) => 1,  // Open long
+        (Side::Buy, true) => 2,   // Close short
+        (Side::Sell, false) => 3, // Open short
+        (Side::Sell, true) => 4,  // Close long
+    };
+
+    let order_type = match (request.order_type, request.time_in_force) {
+        (OrderType::Limit, TimeInForce::Gtc | TimeInForce::Gtd { .. }) => 1,
+        (OrderType::Limit, TimeInForce::PostOnly) => 2,
+        (OrderType::Limit, TimeInForce::Ioc) => 3,
+        (OrderType::Market, _) => 5,
+    };
+
+    let mut params = vec![
+        format!("symbol={}", symbol),
+        format!("side={}", side),
+        format!("openType=2"),  // Cross margin
+        format!("type={}", order_type),
+        format!("vol={}", request.quantity),
+        format!("timestamp={}", timestamp),
+    ];
+
+    if let Some(price) = &request.price {
+        params.push(format!("price={}", price));
+    }
+
+    if !request.client_order_id.is_empty() {
+        params.push(format!("externalOid={}", request.client_order_id));
+    }
+    params
+}
+
+fn order_response(order: MexcOrderData) -> OrderResponse {
+    OrderResponse {
+        exchange_order_id: order.order_id,
+        client_order_id: order.client_order_id.unwrap_or_default(),
+        symbol: order.symbol,
+        side: if order.side == 1 || order.side == 2 { Side::Buy } else { Side::Sell },
+        order_type: if order.order_type == 1 { OrderType::Limit } else { OrderType::Market },
+        price: order.price.parse().ok(),
+        quantity: order.vol.parse().unwrap_or_default(),
+        filled_quantity: order.deal_vol.parse().unwrap_or_default(),
+        avg_fill_price: order.deal_avg_price.parse().ok(),
+        status: parse_mexc_status(order.state),
+        timestamp: epoch_millis(order.create_time),
+    }
+}
+
 fn parse_mexc_status(state: i32) -> OrderStatus {
     match state {
         1 => OrderStatus::Pending,
@@ -283,3 +372,73 @@ fn parse_mexc_status(state: i32) -> OrderStatus {
         _ => OrderStatus::Pending,
     }
 }
+
+/// HMAC-SHA256 of the query string, hex
+pub fn sign(secret: &str, query: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(query.as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{credentials, exchange_config, serve_http};
+    use crate::exchange::QuantityMode;
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn test_order_book_is_parsed_best_first() {
+        let (url, server) = serve_http(vec![(
+            "200 OK",
+            r#"{"success":true,"code":0,"data":{"asks":[[6859.5,3251,1],[6859.6,1000,2]],"bids":[[6858.9,13562,3],[6858.8,500,1]],"version":96801927,"timestamp":1587442022003}}"#,
+        )])
+        .await;
+        let adapter = MexcAdapter::new(exchange_config("mexc", url)).await.unwrap();
+
+        let book = adapter.get_order_book("BTC_USDT", 2).await.unwrap();
+
+        assert_eq!(book.bids[0].price, dec!(6858.9));
+        assert_eq!(book.bids[1].quantity, dec!(500));
+        assert_eq!(book.asks[0].price, dec!(6859.5));
+        assert_eq!(book.asks[1].quantity, dec!(1000));
+        assert_eq!(book.timestamp, 1587442022003);
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /api/v1/contract/depth/BTC_USDT?limit=2"), "{:?}", requests);
+    }
+
+    #[test]
+    fn test_reduce_only_orders_use_the_close_side_codes() {
+        let request = |side, reduce_only| OrderRequest {
+            client_order_id: String::new(),
+            symbol: "BTC_USDT".to_string(),
+            side,
+            order_type: OrderType::Limit,
+            price: Some(dec!(60000)),
+            quantity: dec!(2),
+            reduce_only,
+            time_in_force: TimeInForce::Gtc,
+            quantity_mode: QuantityMode::Base,
+        };
+        let side = |side, reduce_only| order_params("BTC_USDT", &request(side, reduce_only), 0)[1].clone();
+
+        assert_eq!(side(Side::Buy, false), "side=1");
+        assert_eq!(side(Side::Sell, false), "side=3");
+        assert_eq!(side(Side::Buy, true), "side=2");
+        assert_eq!(side(Side::Sell, true), "side=4");
+    }
+
+    #[tokio::test]
+    async fn test_order_timestamp_is_in_millis() {
+        let (url, _server) = serve_http(vec![(
+            "200 OK",
+            r#"{"success":true,"code":0,"data":{"orderId":"1","clientOrderId":"cs1","symbol":"BTC_USDT","side":1,"orderType":1,"price":"100","vol":"2","dealVol":"2","dealAvgPrice":"100","state":3,"createTime":1609991674000}}"#,
+        )])
+        .await;
+        let adapter = MexcAdapter::new(exchange_config("mexc", url)).await.unwrap();
+
+        let order = adapter.get_order(&credentials(), "BTC_USDT", "1").await.unwrap();
+        assert_eq!(order.timestamp, 1609991674000);
+    }
+}
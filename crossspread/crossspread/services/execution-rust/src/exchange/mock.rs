@@ -0,0 +1,885 @@
+//! In-memory exchange adapter for tests
+//!
+//! Orders are filled by a configurable handler and kept in memory so that
+//! `get_order`/`cancel_order` report a consistent exchange-side state.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::config::ExchangeConfig;
+
+use super::{
+    maintenance, mid_price, AlgoKind, AlgoOrderRequest, BookLevel, Credentials,
+    ExchangeAdapter, Fill, OrderBook, OrderRequest, OrderResponse, OrderStatus, OrderType, Position,
+    QuoteAssets, ReferencePriceSource, RiskLimit, StopOrderRequest, SymbolInfo, SymbolStatus,
+};
+
+type PlaceHandler = Box<dyn Fn(usize, &OrderRequest) -> Result<OrderResponse> + Send + Sync>;
+type FillsHandler = Box<dyn Fn(usize, &OrderRequest) -> Vec<Fill> + Send + Sync>;
+type PriceFailures = Box<dyn Fn(usize) -> bool + Send + Sync>;
+
+/// Shortest TWAP interval the mock algo engine takes, in seconds
+pub const MOCK_MIN_TWAP_INTERVAL_SECS: u64 = 5;
+
+pub struct MockAdapter {
+    id: String,
+    prices: Mutex<(Decimal, Decimal)>,
+    /// Which `get_best_price` calls fail, by zero-based call index
+    price_failures: Option<PriceFailures>,
+    price_calls: AtomicUsize,
+    /// How long each best and reference price read takes
+    price_delay: Mutex<Duration>,
+    /// Last, mark and index prices, if published
+    reference_prices: Option<(Decimal, Decimal, Decimal)>,
+    book: Option<OrderBook>,
+    /// How long before it is read the order book was taken
+    book_age_ms: i64,
+    symbol_status: Option<SymbolStatus>,
+    /// Coins per contract and contract step reported by `get_symbol_info`
+    contract: Option<(Decimal, Decimal)>,
+    tick_size: Decimal,
+    min_notional: Decimal,
+    symbol_info_calls: AtomicUsize,
+    /// Server clock ahead of the local one by this many milliseconds
+    clock_offset_ms: Option<i64>,
+    place_handler: PlaceHandler,
+    /// Placements are recorded but never answered, like a hung connection
+    hang_placements: bool,
+    hide_avg_on_place: bool,
+    /// Never report an average fill price, like venues that only expose fills
+    hide_avg: bool,
+    fills_handler: Option<FillsHandler>,
+    fills: Mutex<HashMap<String, Vec<Fill>>>,
+    reduce_only_market: bool,
+    /// Expires good-till-date orders itself
+    gtd: bool,
+    hedge_mode: Mutex<Option<bool>>,
+    orders: Mutex<HashMap<String, OrderResponse>>,
+    placed: Mutex<Vec<OrderRequest>>,
+    cancelled: Mutex<Vec<String>>,
+    /// Symbol of every mass cancel, `None` if unsupported
+    mass_cancels: Option<Mutex<Vec<String>>>,
+    /// Listed by `get_open_orders`, whatever their state
+    open_orders: Vec<OrderResponse>,
+    get_order_calls: AtomicUsize,
+    /// API key of every place, cancel and order status call, in order
+    api_keys: Mutex<Vec<String>>,
+    batch_sizes: Mutex<Vec<usize>>,
+    /// Timeout of every cancel-on-disconnect registration, `None` if unsupported
+    cancel_on_disconnect: Option<Mutex<Vec<u64>>>,
+    /// Open positions reported by successive `get_positions` calls, the
+    /// last repeated, `None` if unsupported
+    positions: Option<Mutex<VecDeque<Vec<Position>>>>,
+    /// Current risk limit cap, `None` if unsupported
+    risk_limit: Option<Mutex<Decimal>>,
+    /// Caps of the tiers `set_risk_limit` may move to, none if it is unsupported
+    risk_tiers: Vec<Decimal>,
+    /// Placements and server time fail with a maintenance error while set
+    in_maintenance: AtomicBool,
+    /// Every native algo order placed, `None` if unsupported. They fill in
+    /// full at their price limit by the first status poll.
+    algo_orders: Option<Mutex<Vec<AlgoOrderRequest>>>,
+    /// Every stop order placed, `None` if unsupported. They rest until
+    /// `trigger_stop_orders` or a cancel.
+    stop_orders: Option<Mutex<Vec<StopOrderRequest>>>,
+    quote_assets: QuoteAssets,
+}
+
+impl MockAdapter {
+    /// Mock that fully fills every order at its limit price (or the touch for market orders)
+    pub fn new(id: &str, bid: Decimal, ask: Decimal) -> Self {
+        let mock = Self {
+            id: id.to_string(),
+            prices: Mutex::new((bid, ask)),
+            price_failures: None,
+            price_calls: AtomicUsize::new(0),
+            price_delay: Mutex::new(Duration::ZERO),
+            reference_prices: None,
+            book: None,
+            book_age_ms: 0,
+            symbol_status: None,
+            contract: None,
+            tick_size: Decimal::ZERO,
+            min_notional: Decimal::ZERO,
+            symbol_info_calls: AtomicUsize::new(0),
+            clock_offset_ms: None,
+            place_handler: Box::new(move |_, request| {
+                let price = request.price.unwrap_or(ask);
+                Ok(response_for(request, OrderStatus::Filled, request.quantity, Some(price)))
+            }),
+            hang_placements: false,
+            hide_avg_on_place: false,
+            hide_avg: false,
+            fills_handler: None,
+            fills: Mutex::new(HashMap::new()),
+            reduce_only_market: true,
+            gtd: false,
+            hedge_mode: Mutex::new(None),
+            orders: Mutex::new(HashMap::new()),
+            placed: Mutex::new(Vec::new()),
+            cancelled: Mutex::new(Vec::new()),
+            mass_cancels: None,
+            open_orders: Vec::new(),
+            get_order_calls: AtomicUsize::new(0),
+            api_keys: Mutex::new(Vec::new()),
+            batch_sizes: Mutex::new(Vec::new()),
+            cancel_on_disconnect: None,
+            positions: None,
+            risk_limit: None,
+            risk_tiers: Vec::new(),
+            in_maintenance: AtomicBool::new(false),
+            algo_orders: None,
+            stop_orders: None,
+            quote_assets: QuoteAssets::default(),
+        };
+        mock.quote_assets.set(["USDT", "USDC", "USD"].map(String::from));
+        mock
+    }
+
+    /// Replace the fill behaviour. The handler receives the zero-based placement
+    /// index and the request, and returns the exchange-side order state.
+    pub fn with_place_handler<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(usize, &OrderRequest) -> Result<OrderResponse> + Send + Sync + 'static,
+    {
+        self.place_handler = Box::new(handler);
+        self
+    }
+
+    /// Never answer a placement
+    pub fn with_hanging_placements(mut self) -> Self {
+        self.hang_placements = true;
+        self
+    }
+
+    pub fn placed(&self) -> Vec<OrderRequest> {
+        self.placed.lock().unwrap().clone()
+    }
+
+    /// Exchange order ids of every cancel, in call order
+    pub fn cancelled(&self) -> Vec<String> {
+        self.cancelled.lock().unwrap().clone()
+    }
+
+    /// Support cancelling every order on a symbol at once
+    pub fn with_mass_cancel(mut self) -> Self {
+        self.mass_cancels = Some(Mutex::new(Vec::new()));
+        self
+    }
+
+    /// Symbol of every mass cancel, in call order
+    pub fn mass_cancels(&self) -> Vec<String> {
+        self.mass_cancels
+            .as_ref()
+            .map(|calls| calls.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    /// List `orders` as resting, as an earlier run might have left them.
+    /// One listed as already closed stands for an order that closed after
+    /// the listing; cancelling it reports how it closed.
+    pub fn with_open_orders(mut self, orders: Vec<OrderResponse>) -> Self {
+        self.orders
+            .lock()
+            .unwrap()
+            .extend(orders.iter().map(|o| (o.exchange_order_id.clone(), o.clone())));
+        self.open_orders = orders;
+        self
+    }
+
+    /// Report that reduce-only market orders are not supported
+    pub fn without_reduce_only_market(mut self) -> Self {
+        self.reduce_only_market = false;
+        self
+    }
+
+    /// Accept good-till-date orders natively
+    pub fn with_gtd(mut self) -> Self {
+        self.gtd = true;
+        self
+    }
+
+    /// Support position modes, starting in hedge mode
+    pub fn with_hedge_mode(self) -> Self {
+        *self.hedge_mode.lock().unwrap() = Some(true);
+        self
+    }
+
+    /// Number of single-order `get_order` calls
+    pub fn get_order_calls(&self) -> usize {
+        self.get_order_calls.load(Ordering::SeqCst)
+    }
+
+    /// API keys used by order calls, in call order
+    pub fn api_keys_used(&self) -> Vec<String> {
+        self.api_keys.lock().unwrap().clone()
+    }
+
+    /// Number of orders requested by each `get_orders_batch` call
+    pub fn batch_sizes(&self) -> Vec<usize> {
+        self.batch_sizes.lock().unwrap().clone()
+    }
+
+    /// Stamp the order book this long before it is read
+    pub fn with_book_age(mut self, age: Duration) -> Self {
+        self.book_age_ms = age.as_millis() as i64;
+        self
+    }
+
+    /// Serve an order book of `(price, quantity)` levels, best first
+    pub fn with_order_book(mut self, bids: &[(Decimal, Decimal)], asks: &[(Decimal, Decimal)]) -> Self {
+        let levels = |side: &[(Decimal, Decimal)]| {
+            side.iter()
+                .map(|&(price, quantity)| BookLevel { price, quantity })
+                .collect()
+        };
+        self.book = Some(OrderBook {
+            bids: levels(bids),
+            asks: levels(asks),
+            timestamp: 0,
+        });
+        self
+    }
+
+    /// Report every symbol in `status`
+    pub fn with_symbol_status(mut self, status: SymbolStatus) -> Self {
+        self.symbol_status = Some(status);
+        self
+    }
+
+    /// Report contracts of `contract_size` coins, traded in multiples of
+    /// `quantity_step`
+    pub fn with_contract_size(mut self, contract_size: Decimal, quantity_step: Decimal) -> Self {
+        self.contract = Some((contract_size, quantity_step));
+        self
+    }
+
+    /// Accept cancel-on-disconnect registrations
+    pub fn with_cancel_on_disconnect(mut self) -> Self {
+        self.cancel_on_disconnect = Some(Mutex::new(Vec::new()));
+        self
+    }
+
+    /// Timeout of every cancel-on-disconnect registration so far
+    pub fn cancel_on_disconnect_calls(&self) -> Vec<u64> {
+        self.cancel_on_disconnect
+            .as_ref()
+            .map(|calls| calls.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    /// Report a price tick of `tick_size`
+    pub fn with_tick_size(mut self, tick_size: Decimal) -> Self {
+        self.tick_size = tick_size;
+        self
+    }
+
+    /// Publish last, mark and index prices alongside the book
+    pub fn with_reference_prices(mut self, last: Decimal, mark: Decimal, index: Decimal) -> Self {
+        self.reference_prices = Some((last, mark, index));
+        self
+    }
+
+    /// Report `positions` as the account's open positions
+    pub fn with_positions(self, positions: Vec<Position>) -> Self {
+        self.with_position_reads(vec![positions])
+    }
+
+    /// Report each of `reads` in turn from `get_positions`, then keep
+    /// reporting the last, like a position still changing while it is read
+    pub fn with_position_reads(mut self, reads: Vec<Vec<Position>>) -> Self {
+        self.positions = Some(Mutex::new(reads.into()));
+        self
+    }
+
+    /// Cap positions at `max_notional_usd`, and let the cap be raised to any
+    /// of `tiers`
+    pub fn with_risk_limit(mut self, max_notional_usd: Decimal, tiers: Vec<Decimal>) -> Self {
+        self.risk_limit = Some(Mutex::new(max_notional_usd));
+        self.risk_tiers = tiers;
+        self
+    }
+
+    /// Accept native TWAP and iceberg orders
+    pub fn with_algo_orders(mut self) -> Self {
+        self.algo_orders = Some(Mutex::new(Vec::new()));
+        self
+    }
+
+    /// Native algo orders placed so far
+    pub fn algo_orders(&self) -> Vec<AlgoOrderRequest> {
+        self.algo_orders
+            .as_ref()
+            .map(|orders| orders.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    /// Accept reduce-only stop orders
+    pub fn with_stop_orders(mut self) -> Self {
+        self.stop_orders = Some(Mutex::new(Vec::new()));
+        self
+    }
+
+    /// Stop orders placed so far, amended ones included
+    pub fn stop_orders(&self) -> Vec<StopOrderRequest> {
+        self.stop_orders
+            .as_ref()
+            .map(|orders| orders.lock().unwrap().clone())
+            .unwrap_or_default()
+    }
+
+    /// Fill every resting stop order in full at its stop price
+    pub fn trigger_stop_orders(&self) {
+        for order in self.orders.lock().unwrap().values_mut() {
+            if order.exchange_order_id.contains("-stop-") && !order.status.is_terminal() {
+                order.status = OrderStatus::Filled;
+                order.filled_quantity = order.quantity;
+                order.avg_fill_price = order.price;
+            }
+        }
+    }
+
+    /// Start or end a maintenance window
+    pub fn set_maintenance(&self, in_maintenance: bool) {
+        self.in_maintenance.store(in_maintenance, Ordering::SeqCst);
+    }
+
+    /// Fail the `get_best_price` calls for which `fails`, given the zero-based
+    /// call index, returns true
+    pub fn with_price_failures<F>(mut self, fails: F) -> Self
+    where
+        F: Fn(usize) -> bool + Send + Sync + 'static,
+    {
+        self.price_failures = Some(Box::new(fails));
+        self
+    }
+
+    /// Take `delay` to answer each best and reference price read
+    pub fn with_price_delay(self, delay: Duration) -> Self {
+        self.set_price_delay(delay);
+        self
+    }
+
+    /// Take `delay` to answer price reads from now on
+    pub fn set_price_delay(&self, delay: Duration) {
+        *self.price_delay.lock().unwrap() = delay;
+    }
+
+    /// Move the touch to `bid` and `ask`
+    pub fn set_prices(&self, bid: Decimal, ask: Decimal) {
+        *self.prices.lock().unwrap() = (bid, ask);
+    }
+
+    /// Report a minimum order value of `min_notional`
+    pub fn with_min_notional(mut self, min_notional: Decimal) -> Self {
+        self.min_notional = min_notional;
+        self
+    }
+
+    /// Report a server time `offset_ms` ahead of the local clock
+    pub fn with_clock_offset(mut self, offset_ms: i64) -> Self {
+        self.clock_offset_ms = Some(offset_ms);
+        self
+    }
+
+    /// Number of `get_symbol_info` calls
+    pub fn symbol_info_calls(&self) -> usize {
+        self.symbol_info_calls.load(Ordering::SeqCst)
+    }
+
+    /// Omit the average fill price from place responses (it stays visible via `get_order`)
+    pub fn hide_avg_on_place(mut self) -> Self {
+        self.hide_avg_on_place = true;
+        self
+    }
+
+    /// Report fills per order from `handler`, given the placement index and
+    /// request, and never an average fill price on the order itself
+    pub fn with_fills<F>(mut self, handler: F) -> Self
+    where
+        F: Fn(usize, &OrderRequest) -> Vec<Fill> + Send + Sync + 'static,
+    {
+        self.hide_avg = true;
+        self.fills_handler = Some(Box::new(handler));
+        self
+    }
+}
+
+/// Build an order state for a request
+pub fn response_for(
+    request: &OrderRequest,
+    status: OrderStatus,
+    filled_quantity: Decimal,
+    avg_fill_price: Option<Decimal>,
+) -> OrderResponse {
+    OrderResponse {
+        exchange_order_id: String::new(),
+        client_order_id: request.client_order_id.clone(),
+        symbol: request.symbol.clone(),
+        side: request.side,
+        order_type: request.order_type,
+        price: request.price,
+        quantity: request.quantity,
+        filled_quantity,
+        avg_fill_price,
+        status,
+        timestamp: super::now_millis(),
+    }
+}
+
+#[async_trait]
+impl ExchangeAdapter for MockAdapter {
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    fn to_native_symbol(&self, base: &str, quote: &str) -> String {
+        format!("{}{}", base, quote)
+    }
+
+    fn to_canonical_symbol(&self, native: &str) -> Option<String> {
+        self.quote_assets.canonical(native)
+    }
+
+    async fn place_order(
+        &self,
+        credentials: &Credentials,
+        request: &OrderRequest,
+    ) -> Result<OrderResponse> {
+        self.api_keys.lock().unwrap().push(credentials.api_key.clone());
+        let index = {
+            let mut placed = self.placed.lock().unwrap();
+            placed.push(request.clone());
+            placed.len() - 1
+        };
+        if self.hang_placements {
+            std::future::pending::<()>().await;
+        }
+        if self.in_maintenance.load(Ordering::SeqCst) {
+            return Err(maintenance(&self.id, "503 Service Unavailable"));
+        }
+
+        let mut order = (self.place_handler)(index, request)?;
+        order.exchange_order_id = format!("{}-{}", self.id, index);
+        if let Some(handler) = &self.fills_handler {
+            self.fills
+                .lock()
+                .unwrap()
+                .insert(order.exchange_order_id.clone(), handler(index, request));
+        }
+        if self.hide_avg {
+            order.avg_fill_price = None;
+        }
+        self.orders
+            .lock()
+            .unwrap()
+            .insert(order.exchange_order_id.clone(), order.clone());
+
+        if self.hide_avg_on_place {
+            order.avg_fill_price = None;
+        }
+        Ok(order)
+    }
+
+    async fn cancel_order(
+        &self,
+        credentials: &Credentials,
+        _symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        self.api_keys.lock().unwrap().push(credentials.api_key.clone());
+        self.cancelled.lock().unwrap().push(order_id.to_string());
+
+        let mut orders = self.orders.lock().unwrap();
+        let order = orders
+            .get_mut(order_id)
+            .ok_or_else(|| anyhow::anyhow!("Unknown order: {}", order_id))?;
+        if !order.status.is_terminal() {
+            order.status = OrderStatus::Cancelled;
+        }
+        Ok(order.clone())
+    }
+
+    async fn cancel_all_orders(&self, credentials: &Credentials, symbol: &str) -> Result<()> {
+        let calls = self
+            .mass_cancels
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Cancelling all orders is not supported by {}", self.id))?;
+        self.api_keys.lock().unwrap().push(credentials.api_key.clone());
+        calls.lock().unwrap().push(symbol.to_string());
+        for order in self.orders.lock().unwrap().values_mut() {
+            if order.symbol == symbol && !order.status.is_terminal() {
+                order.status = OrderStatus::Cancelled;
+            }
+        }
+        Ok(())
+    }
+
+    async fn get_order(
+        &self,
+        credentials: &Credentials,
+        _symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        self.api_keys.lock().unwrap().push(credentials.api_key.clone());
+        self.get_order_calls.fetch_add(1, Ordering::SeqCst);
+
+        self.orders
+            .lock()
+            .unwrap()
+            .get(order_id)
+            .cloned()
+            .ok_or_else(|| anyhow::anyhow!("Unknown order: {}", order_id))
+    }
+
+    async fn get_orders_batch(
+        &self,
+        credentials: &Credentials,
+        _symbol: &str,
+        order_ids: &[String],
+    ) -> Result<Vec<OrderResponse>> {
+        self.api_keys.lock().unwrap().push(credentials.api_key.clone());
+        self.batch_sizes.lock().unwrap().push(order_ids.len());
+
+        let orders = self.orders.lock().unwrap();
+        Ok(order_ids.iter().filter_map(|id| orders.get(id).cloned()).collect())
+    }
+
+    async fn get_open_orders(&self, credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        self.api_keys.lock().unwrap().push(credentials.api_key.clone());
+        Ok(self.open_orders.iter().filter(|o| o.symbol == symbol).cloned().collect())
+    }
+
+    async fn get_fills(
+        &self,
+        credentials: &Credentials,
+        _symbol: &str,
+        order_id: &str,
+    ) -> Result<Vec<Fill>> {
+        self.api_keys.lock().unwrap().push(credentials.api_key.clone());
+        if self.fills_handler.is_none() {
+            anyhow::bail!("Fills are not supported by {}", self.id);
+        }
+        Ok(self.fills.lock().unwrap().get(order_id).cloned().unwrap_or_default())
+    }
+
+    fn supports_algo_order(&self, _kind: AlgoKind) -> bool {
+        self.algo_orders.is_some()
+    }
+
+    fn min_algo_interval_secs(&self, kind: AlgoKind) -> u64 {
+        match kind {
+            AlgoKind::Twap => MOCK_MIN_TWAP_INTERVAL_SECS,
+            AlgoKind::Iceberg => 1,
+        }
+    }
+
+    async fn place_algo_order(
+        &self,
+        _credentials: &Credentials,
+        request: &AlgoOrderRequest,
+    ) -> Result<OrderResponse> {
+        let Some(orders) = &self.algo_orders else {
+            anyhow::bail!("Native algo orders are not supported by {}", self.id);
+        };
+        let mut orders = orders.lock().unwrap();
+        orders.push(request.clone());
+        Ok(algo_response(&format!("{}-algo-{}", self.id, orders.len() - 1), request, OrderStatus::Open))
+    }
+
+    async fn get_algo_order(
+        &self,
+        _credentials: &Credentials,
+        _symbol: &str,
+        algo_id: &str,
+    ) -> Result<OrderResponse> {
+        let request = algo_id
+            .rsplit('-')
+            .next()
+            .and_then(|index| index.parse::<usize>().ok())
+            .and_then(|index| self.algo_orders().get(index).cloned())
+            .ok_or_else(|| anyhow::anyhow!("Unknown algo order: {}", algo_id))?;
+        Ok(algo_response(algo_id, &request, OrderStatus::Filled))
+    }
+
+    fn supports_stop_order(&self) -> bool {
+        self.stop_orders.is_some()
+    }
+
+    // Amends, cancels and status reads go through the trait defaults
+    async fn place_stop_order(
+        &self,
+        _credentials: &Credentials,
+        request: &StopOrderRequest,
+    ) -> Result<OrderResponse> {
+        let Some(stops) = &self.stop_orders else {
+            anyhow::bail!("Stop orders are not supported by {}", self.id);
+        };
+        let order = {
+            let mut stops = stops.lock().unwrap();
+            stops.push(request.clone());
+            OrderResponse {
+                exchange_order_id: format!("{}-stop-{}", self.id, stops.len() - 1),
+                client_order_id: request.client_order_id.clone(),
+                symbol: request.symbol.clone(),
+                side: request.side,
+                order_type: OrderType::Market,
+                price: Some(request.stop_price),
+                quantity: request.quantity,
+                filled_quantity: Decimal::ZERO,
+                avg_fill_price: None,
+                status: OrderStatus::Open,
+                timestamp: chrono::Utc::now().timestamp_millis(),
+            }
+        };
+        self.orders
+            .lock()
+            .unwrap()
+            .insert(order.exchange_order_id.clone(), order.clone());
+        Ok(order)
+    }
+
+    async fn get_best_price(&self, _symbol: &str) -> Result<(Decimal, Decimal)> {
+        let call = self.price_calls.fetch_add(1, Ordering::SeqCst);
+        let delay = *self.price_delay.lock().unwrap();
+        tokio::time::sleep(delay).await;
+        if self.price_failures.as_ref().is_some_and(|fails| fails(call)) {
+            anyhow::bail!("Ticker request {} to {} timed out", call, self.id);
+        }
+        Ok(*self.prices.lock().unwrap())
+    }
+
+    async fn get_reference_price(&self, symbol: &str, source: ReferencePriceSource) -> Result<Decimal> {
+        let delay = *self.price_delay.lock().unwrap();
+        tokio::time::sleep(delay).await;
+        match (source, self.reference_prices) {
+            (ReferencePriceSource::Mid, _) => mid_price(self, symbol).await,
+            (ReferencePriceSource::Last, Some((last, _, _))) => Ok(last),
+            (ReferencePriceSource::Mark, Some((_, mark, _))) => Ok(mark),
+            (ReferencePriceSource::Index, Some((_, _, index))) => Ok(index),
+            (other, None) => anyhow::bail!("{} price is not supported by {}", other, self.id),
+        }
+    }
+
+    async fn get_symbol_info(&self, symbol: &str) -> Result<SymbolInfo> {
+        self.symbol_info_calls.fetch_add(1, Ordering::SeqCst);
+        if self.symbol_status.is_none() && self.contract.is_none() && self.tick_size.is_zero() && self.min_notional.is_zero() {
+            anyhow::bail!("Symbol info is not supported by {}", self.id);
+        }
+        let (contract_size, quantity_step) = self.contract.unwrap_or((Decimal::ONE, Decimal::ZERO));
+        Ok(SymbolInfo {
+            symbol: symbol.to_string(),
+            status: self.symbol_status.unwrap_or(SymbolStatus::Trading),
+            contract_size,
+            quantity_step,
+            tick_size: self.tick_size,
+            min_notional: self.min_notional,
+        })
+    }
+
+    async fn get_order_book(&self, _symbol: &str, depth: usize) -> Result<OrderBook> {
+        let book = self
+            .book
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Order book depth is not supported by {}", self.id))?;
+        Ok(OrderBook {
+            bids: book.bids.iter().take(depth).copied().collect(),
+            asks: book.asks.iter().take(depth).copied().collect(),
+            timestamp: super::now_millis() - self.book_age_ms,
+        })
+    }
+
+    async fn get_server_time(&self) -> Result<i64> {
+        if self.in_maintenance.load(Ordering::SeqCst) {
+            return Err(maintenance(&self.id, "503 Service Unavailable"));
+        }
+        let offset_ms = self
+            .clock_offset_ms
+            .ok_or_else(|| anyhow::anyhow!("Server time is not supported by {}", self.id))?;
+        Ok(chrono::Utc::now().timestamp_millis() + offset_ms)
+    }
+
+    fn is_connected(&self) -> bool {
+        true
+    }
+
+    fn supports_reduce_only_market(&self) -> bool {
+        self.reduce_only_market
+    }
+
+    fn supports_gtd(&self, _expire_at_ms: i64) -> bool {
+        self.gtd
+    }
+
+    async fn get_position_mode(&self, _credentials: &Credentials) -> Result<bool> {
+        self.hedge_mode
+            .lock()
+            .unwrap()
+            .ok_or_else(|| anyhow::anyhow!("Position mode is not supported by {}", self.id))
+    }
+
+    async fn set_position_mode(&self, _credentials: &Credentials, hedge: bool) -> Result<()> {
+        let mut mode = self.hedge_mode.lock().unwrap();
+        if mode.is_none() {
+            anyhow::bail!("Position mode is not supported by {}", self.id);
+        }
+        *mode = Some(hedge);
+        Ok(())
+    }
+
+    async fn get_positions(&self, _credentials: &Credentials) -> Result<Vec<Position>> {
+        let mut reads = self
+            .positions
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Positions are not supported by {}", self.id))?
+            .lock()
+            .unwrap();
+        if reads.len() > 1 {
+            return Ok(reads.pop_front().unwrap());
+        }
+        Ok(reads.front().cloned().unwrap_or_default())
+    }
+
+    async fn get_risk_limit(&self, _credentials: &Credentials, _symbol: &str) -> Result<RiskLimit> {
+        let limit = self
+            .risk_limit
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("Risk limits are not supported by {}", self.id))?;
+        Ok(RiskLimit {
+            max_notional_usd: *limit.lock().unwrap(),
+            tier: None,
+        })
+    }
+
+    async fn set_risk_limit(&self, _credentials: &Credentials, _symbol: &str, notional_usd: Decimal) -> Result<RiskLimit> {
+        let (Some(limit), false) = (&self.risk_limit, self.risk_tiers.is_empty()) else {
+            anyhow::bail!("Setting risk limits is not supported by {}", self.id);
+        };
+        let tier = self
+            .risk_tiers
+            .iter()
+            .copied()
+            .filter(|tier| *tier >= notional_usd)
+            .min()
+            .ok_or_else(|| anyhow::anyhow!("No tier allows {} USD", notional_usd))?;
+        *limit.lock().unwrap() = tier;
+        Ok(RiskLimit {
+            max_notional_usd: tier,
+            tier: None,
+        })
+    }
+
+    fn supports_cancel_on_disconnect(&self) -> bool {
+        self.cancel_on_disconnect.is_some()
+    }
+
+    async fn set_cancel_on_disconnect(&self, _credentials: &Credentials, timeout_secs: u64) -> Result<()> {
+        match &self.cancel_on_disconnect {
+            Some(calls) => calls.lock().unwrap().push(timeout_secs),
+            None => tracing::warn!("Cancel-on-disconnect is not supported by {}, not registered", self.id),
+        }
+        Ok(())
+    }
+}
+
+/// State of a mock algo order, filled in full at its price limit once `Filled`
+fn algo_response(algo_id: &str, request: &AlgoOrderRequest, status: OrderStatus) -> OrderResponse {
+    let filled = status == OrderStatus::Filled;
+    OrderResponse {
+        exchange_order_id: algo_id.to_string(),
+        client_order_id: request.client_order_id.clone(),
+        symbol: request.symbol.clone(),
+        side: request.side,
+        order_type: OrderType::Limit,
+        price: Some(request.price_limit),
+        quantity: request.quantity,
+        filled_quantity: if filled { request.quantity } else { Decimal::ZERO },
+        avg_fill_price: filled.then_some(request.price_limit),
+        status,
+        timestamp: 0,
+    }
+}
+
+/// Serve one canned response per connection, returning the request lines
+pub async fn serve_http<B: Into<String>>(
+    responses: Vec<(&'static str, B)>,
+) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+    let (url, server) = serve_http_raw(responses).await;
+    let lines = tokio::spawn(async move {
+        server
+            .await
+            .unwrap()
+            .iter()
+            .map(|request| request.lines().next().unwrap_or_default().to_string())
+            .collect()
+    });
+    (url, lines)
+}
+
+/// `serve_http`, returning each request as read, headers included
+pub async fn serve_http_raw<B: Into<String>>(
+    responses: Vec<(&'static str, B)>,
+) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let responses: Vec<(&'static str, String)> =
+        responses.into_iter().map(|(status, body)| (status, body.into())).collect();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let server = tokio::spawn(async move {
+        let mut requests = Vec::new();
+        for (status, body) in responses {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 4096];
+            let n = socket.read(&mut request).await.unwrap();
+            let request = String::from_utf8_lossy(&request[..n]).to_string();
+            requests.push(request);
+            let response = format!(
+                "HTTP/1.1 {}\r\nconnection: close\r\ncontent-length: {}\r\n\r\n{}",
+                status,
+                body.len(),
+                body
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+        }
+        requests
+    });
+    (url, server)
+}
+
+/// Config for a real adapter talking to `rest_url`, such as a `serve_http` server
+pub fn exchange_config(id: &str, rest_url: String) -> ExchangeConfig {
+    ExchangeConfig {
+        id: id.to_string(),
+        rest_url,
+        ws_url: String::new(),
+        trade_ws_url: None,
+        testnet: false,
+        maker_fee_bps: 0.0,
+        taker_fee_bps: 0.0,
+        trade_mode: None,
+        user_agent: None,
+        proxy: None,
+        log_raw_http: false,
+        ws_orders: false,
+        order_tag: None,
+        client_order_id_prefix: None,
+        adopt_duplicate_orders: false,
+    }
+}
+
+/// Credentials for mock adapters
+pub fn credentials() -> Credentials {
+    Credentials {
+        api_key: "key".to_string(),
+        api_secret: "secret".to_string(),
+        passphrase: None,
+        wallet_key: None,
+        sub_account: None,
+    }
+}
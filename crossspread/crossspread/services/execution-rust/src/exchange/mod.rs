@@ -1,7 +1,7 @@
 //! Exchange adapter traits and implementations
 
 use async_trait::async_trait;
-use anyhow::Result;
+use anyhow::{Context, Result};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -19,6 +19,14 @@ pub mod bingx;
 pub mod coinex;
 pub mod lbank;
 pub mod htx;
+pub mod dydx;
+pub mod raw_http;
+pub mod trading_socket;
+
+#[cfg(test)]
+pub mod mock;
+#[cfg(test)]
+mod signing_vectors;
 
 /// Order side
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -36,6 +44,111 @@ pub enum OrderType {
     Market,
 }
 
+/// Time in force for limit orders
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TimeInForce {
+    #[default]
+    Gtc,
+    Ioc,
+    /// Rejected (or expired) by the exchange instead of taking liquidity
+    PostOnly,
+    /// Rests until `expire_at_ms`, in Unix milliseconds. Venues without
+    /// native good-till-date orders rest it as GTC, and it is up to the
+    /// caller to cancel it then (see `ExchangeAdapter::supports_gtd`).
+    Gtd { expire_at_ms: i64 },
+}
+
+/// Unit an order quantity is given in
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum QuantityMode {
+    /// Contracts (coins for a linear contract with a multiplier of one)
+    #[default]
+    Base,
+    /// An amount of the quote asset to spend or receive
+    Quote,
+}
+
+/// How a contract's size and PnL are denominated
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ContractType {
+    /// Margined and settled in the quote asset (e.g. BTCUSDT); one contract
+    /// is `multiplier` coins
+    #[default]
+    Linear,
+    /// Margined and settled in the base coin (e.g. BTCUSD); one contract is
+    /// worth `multiplier` USD
+    Inverse,
+}
+
+/// Contract denomination of a symbol, used for notional, fee and PnL math
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ContractSpec {
+    pub contract_type: ContractType,
+    pub multiplier: Decimal,
+}
+
+impl Default for ContractSpec {
+    fn default() -> Self {
+        Self::linear(Decimal::ONE)
+    }
+}
+
+impl ContractSpec {
+    pub fn linear(multiplier: Decimal) -> Self {
+        Self { contract_type: ContractType::Linear, multiplier }
+    }
+
+    pub fn inverse(multiplier: Decimal) -> Self {
+        Self { contract_type: ContractType::Inverse, multiplier }
+    }
+
+    /// USD value of `quantity` contracts at `price`
+    pub fn notional_usd(&self, quantity: Decimal, price: Decimal) -> Decimal {
+        match self.contract_type {
+            ContractType::Linear => quantity * self.multiplier * price,
+            ContractType::Inverse => quantity * self.multiplier,
+        }
+    }
+
+    /// Value of `quantity` contracts in the settlement asset (quote for
+    /// linear, coin for inverse), which is what fees are charged on
+    pub fn settlement_value(&self, quantity: Decimal, price: Decimal) -> Decimal {
+        match self.contract_type {
+            ContractType::Linear => quantity * self.multiplier * price,
+            ContractType::Inverse if price.is_zero() => Decimal::ZERO,
+            ContractType::Inverse => quantity * self.multiplier / price,
+        }
+    }
+
+    /// Contracts worth `notional_usd` at `price`
+    pub fn quantity_for_notional(&self, notional_usd: Decimal, price: Decimal) -> Decimal {
+        match self.contract_type {
+            ContractType::Linear if price.is_zero() => Decimal::ZERO,
+            ContractType::Linear => notional_usd / (self.multiplier * price),
+            ContractType::Inverse => notional_usd / self.multiplier,
+        }
+    }
+
+    /// PnL in the settlement asset of a position opened with `side` at
+    /// `entry` and closed at `exit`
+    pub fn pnl(&self, side: Side, quantity: Decimal, entry: Decimal, exit: Decimal) -> Decimal {
+        let long_pnl = match self.contract_type {
+            ContractType::Linear => quantity * self.multiplier * (exit - entry),
+            ContractType::Inverse if entry.is_zero() || exit.is_zero() => Decimal::ZERO,
+            ContractType::Inverse => {
+                quantity * self.multiplier * (Decimal::ONE / entry - Decimal::ONE / exit)
+            }
+        };
+        match side {
+            Side::Buy => long_pnl,
+            Side::Sell => -long_pnl,
+        }
+    }
+}
+
 /// Order status
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
@@ -49,16 +162,160 @@ pub enum OrderStatus {
     Expired,
 }
 
+impl OrderStatus {
+    /// Whether the order can no longer receive fills
+    pub fn is_terminal(&self) -> bool {
+        matches!(
+            self,
+            OrderStatus::Filled | OrderStatus::Cancelled | OrderStatus::Rejected | OrderStatus::Expired
+        )
+    }
+}
+
 /// Order request to place on exchange
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct OrderRequest {
     pub client_order_id: String,
-    pub symbol: String,
+    pub symbol: String, // Canonical BASE/QUOTE or exchange-native
     pub side: Side,
     pub order_type: OrderType,
     pub price: Option<Decimal>,
     pub quantity: Decimal,
     pub reduce_only: bool,
+    #[serde(default)]
+    pub time_in_force: TimeInForce,
+    /// Only Binance market orders take a quote amount, sent as
+    /// `quoteOrderQty`. Everything else is sized in contracts, and the
+    /// slicer converts quote amounts before sizing slices.
+    #[serde(default)]
+    pub quantity_mode: QuantityMode,
+}
+
+/// How far a trailing stop follows behind the best price seen since it was
+/// placed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Trail {
+    /// Fraction of the best price, in basis points
+    CallbackRateBps(Decimal),
+    /// Fixed distance in price
+    Distance(Decimal),
+}
+
+/// Reduce-only trailing stop that closes `quantity` at market once hit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrailingStopRequest {
+    pub client_order_id: String,
+    pub symbol: String,
+    /// Side of the closing order, Sell for a long position
+    pub side: Side,
+    pub quantity: Decimal,
+    pub trail: Trail,
+}
+
+/// Reduce-only stop that closes `quantity` at market once the price reaches
+/// `stop_price`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StopOrderRequest {
+    pub client_order_id: String,
+    pub symbol: String,
+    /// Side of the closing order, Sell for a long position
+    pub side: Side,
+    pub quantity: Decimal,
+    pub stop_price: Decimal,
+}
+
+/// Strategy an exchange's own algo engine can work an order with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AlgoKind {
+    /// A child order of `slice_quantity` every `interval_secs`
+    Twap,
+    /// One child order of `slice_quantity` on the book at a time, replaced
+    /// as it fills
+    Iceberg,
+}
+
+impl std::str::FromStr for AlgoKind {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "twap" => Ok(AlgoKind::Twap),
+            "iceberg" => Ok(AlgoKind::Iceberg),
+            other => anyhow::bail!("Unknown algo order kind: {}", other),
+        }
+    }
+}
+
+/// Order worked by the exchange's algo engine instead of sliced here. Child
+/// orders are priced within `price_variance_bps` of the touch and never past
+/// `price_limit`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AlgoOrderRequest {
+    pub client_order_id: String,
+    pub symbol: String,
+    pub side: Side,
+    /// Total in contracts
+    pub quantity: Decimal,
+    pub kind: AlgoKind,
+    /// Size of each child order, in contracts
+    pub slice_quantity: Decimal,
+    /// Seconds between child orders, for TWAP
+    pub interval_secs: u64,
+    pub price_variance_bps: f64,
+    pub price_limit: Decimal,
+    pub reduce_only: bool,
+}
+
+/// Price a trade's slippage and notional are measured against
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ReferencePriceSource {
+    /// Last traded price
+    Last,
+    /// The venue's mark price, used for margin and liquidation
+    Mark,
+    /// Index price across spot venues
+    Index,
+    /// Halfway between the best bid and ask
+    #[default]
+    Mid,
+}
+
+impl std::str::FromStr for ReferencePriceSource {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "last" => Ok(ReferencePriceSource::Last),
+            "mark" => Ok(ReferencePriceSource::Mark),
+            "index" => Ok(ReferencePriceSource::Index),
+            "mid" => Ok(ReferencePriceSource::Mid),
+            other => anyhow::bail!("Unknown reference price source: {}", other),
+        }
+    }
+}
+
+impl std::fmt::Display for ReferencePriceSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ReferencePriceSource::Last => "last",
+            ReferencePriceSource::Mark => "mark",
+            ReferencePriceSource::Index => "index",
+            ReferencePriceSource::Mid => "mid",
+        })
+    }
+}
+
+/// One execution against an order
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Fill {
+    pub price: Decimal,
+    pub quantity: Decimal,
+    /// Fee charged on the fill, negative for a rebate
+    pub fee: Decimal,
+    /// Asset the fee was charged in, `None` for the contract's settlement asset
+    pub fee_asset: Option<String>,
 }
 
 /// Order response from exchange
@@ -66,23 +323,454 @@ pub struct OrderRequest {
 pub struct OrderResponse {
     pub exchange_order_id: String,
     pub client_order_id: String,
+    /// Canonical `BASE/QUOTE` where the adapter can split the native symbol
     pub symbol: String,
     pub side: Side,
     pub order_type: OrderType,
     pub price: Option<Decimal>,
     pub quantity: Decimal,
+    /// The exchange's own filled field wherever the venue reports one;
+    /// Gate.io is the exception and derives it from the unfilled remainder
     pub filled_quantity: Decimal,
     pub avg_fill_price: Option<Decimal>,
     pub status: OrderStatus,
+    /// Exchange time of the order's latest update, or of its creation on
+    /// venues that only report that, in Unix milliseconds
     pub timestamp: i64,
 }
 
+impl OrderResponse {
+    /// The same order, reported under `adapter`'s canonical form of its symbol
+    pub fn canonical<A: ExchangeAdapter + ?Sized>(mut self, adapter: &A) -> Self {
+        self.symbol = adapter.canonical_symbol(&self.symbol);
+        self
+    }
+}
+
+/// Price level of an order book
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BookLevel {
+    pub price: Decimal,
+    /// In the venue's order units: contracts where it sizes orders in contracts
+    pub quantity: Decimal,
+}
+
+/// Order book snapshot, best levels first: bids from the highest price
+/// down, asks from the lowest up
+#[derive(Debug, Clone, Default)]
+pub struct OrderBook {
+    pub bids: Vec<BookLevel>,
+    pub asks: Vec<BookLevel>,
+    /// When the venue took the snapshot, in Unix milliseconds, or when it
+    /// was received from venues that don't say
+    pub timestamp: i64,
+}
+
+impl OrderBook {
+    /// Up to `depth` levels a side, best first, from levels in whatever
+    /// order the venue lists them
+    pub fn from_levels(mut bids: Vec<BookLevel>, mut asks: Vec<BookLevel>, depth: usize, timestamp: i64) -> Self {
+        bids.sort_by_key(|level| std::cmp::Reverse(level.price));
+        asks.sort_by_key(|level| level.price);
+        bids.truncate(depth);
+        asks.truncate(depth);
+        Self { bids, asks, timestamp }
+    }
+
+    /// Quantity a marketable order of `quantity` would fill against the book
+    /// and its average price. Buys take the asks, sells the bids; the fill is
+    /// short when the book runs out.
+    pub fn walk(&self, side: Side, quantity: Decimal) -> (Decimal, Option<Decimal>) {
+        let levels = match side {
+            Side::Buy => &self.asks,
+            Side::Sell => &self.bids,
+        };
+
+        let mut filled = Decimal::ZERO;
+        let mut cost = Decimal::ZERO;
+        for level in levels {
+            let take = level.quantity.min(quantity - filled);
+            if take <= Decimal::ZERO {
+                break;
+            }
+            filled += take;
+            cost += take * level.price;
+        }
+
+        let avg_price = (filled > Decimal::ZERO).then(|| cost / filled);
+        (filled, avg_price)
+    }
+
+    /// Quantity resting on the side a `side` order takes, at prices no
+    /// worse than `limit`
+    pub fn depth_within(&self, side: Side, limit: Decimal) -> Decimal {
+        match side {
+            Side::Buy => self.asks.iter().take_while(|l| l.price <= limit).map(|l| l.quantity).sum(),
+            Side::Sell => self.bids.iter().take_while(|l| l.price >= limit).map(|l| l.quantity).sum(),
+        }
+    }
+}
+
+/// Trading state of a symbol
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SymbolStatus {
+    Trading,
+    /// Listed but not open for trading yet
+    PreLaunch,
+    /// Only orders that reduce a position are accepted
+    ReduceOnly,
+    /// Temporarily halted: maintenance, settlement or delivery
+    Maintenance,
+    Delisted,
+}
+
+impl SymbolStatus {
+    /// Whether new positions can be opened
+    pub fn is_tradable(self) -> bool {
+        self == SymbolStatus::Trading
+    }
+}
+
+impl std::fmt::Display for SymbolStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            SymbolStatus::Trading => "trading",
+            SymbolStatus::PreLaunch => "pre-launch",
+            SymbolStatus::ReduceOnly => "reduce-only",
+            SymbolStatus::Maintenance => "maintenance",
+            SymbolStatus::Delisted => "delisted",
+        })
+    }
+}
+
+/// How a position's margin is shared
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MarginMode {
+    Cross,
+    Isolated,
+}
+
+/// Leverage and margin mode an account trades a symbol with
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct LeverageInfo {
+    pub leverage: Decimal,
+    pub margin_mode: MarginMode,
+}
+
+/// Position size tier an account holds a symbol under. Orders that would
+/// take the position past it are rejected.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RiskLimit {
+    /// Largest position the tier allows, by value in USD
+    pub max_notional_usd: Decimal,
+    /// The venue's id for the tier, where the account picks one
+    pub tier: Option<String>,
+}
+
+/// An open position as the exchange reports it
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Position {
+    /// Native symbol
+    pub symbol: String,
+    pub side: Side,
+    /// Contracts, always positive
+    pub quantity: Decimal,
+    pub entry_price: Decimal,
+}
+
+/// Exchange listing details of a symbol
+#[derive(Debug, Clone)]
+pub struct SymbolInfo {
+    pub symbol: String,
+    pub status: SymbolStatus,
+    /// Coins per contract, 1 where orders are sized in coins. USD per
+    /// contract on inverse contracts.
+    pub contract_size: Decimal,
+    /// Smallest increment of an order's contract count, zero if unknown
+    pub quantity_step: Decimal,
+    /// Smallest price increment, zero if unknown
+    pub tick_size: Decimal,
+    /// Smallest order value accepted, in the quote asset, zero if unknown
+    pub min_notional: Decimal,
+}
+
+/// Longest piece of a non-JSON body quoted in the error
+const NON_JSON_SNIPPET_LEN: usize = 200;
+
+/// Adapter errors callers handle by kind rather than just report. They
+/// travel inside `anyhow::Error` like any other error.
+#[derive(Debug, thiserror::Error)]
+pub enum ExchangeError {
+    /// The venue is down for maintenance; its order endpoints will keep
+    /// failing until it is back
+    #[error("{exchange} is in maintenance: {detail}")]
+    Maintenance { exchange: String, detail: String },
+    /// A reduce-only order was turned away because there was no position
+    /// for it to reduce, usually because the position is already flat
+    #[error("{exchange} rejected a reduce-only order: {detail}")]
+    ReduceOnlyRejected { exchange: String, detail: String },
+    /// The account lacks the margin or balance for the order; a smaller
+    /// order may still go through
+    #[error("{exchange} rejected an order for insufficient margin: {detail}")]
+    InsufficientMargin { exchange: String, detail: String },
+    /// The order would take the position past its size or risk limit, so
+    /// more of the same will be turned away too
+    #[error("{exchange} rejected an order over the position risk limit: {detail}")]
+    RiskLimit { exchange: String, detail: String },
+    /// A post-only order was turned away because it would have taken
+    /// liquidity; a more passive price may rest
+    #[error("{exchange} rejected a post-only order that would cross: {detail}")]
+    PostOnlyRejected { exchange: String, detail: String },
+    /// The venue is throttling the account or IP; the same request should
+    /// go through once the limit window passes
+    #[error("{exchange} rate limited the request: {detail}")]
+    RateLimited { exchange: String, detail: String },
+    /// The request's timestamp was too old by the time it arrived, so its
+    /// signature was refused; the same request signed afresh may go through
+    #[error("{exchange} refused an expired request signature: {detail}")]
+    SignatureExpired { exchange: String, detail: String },
+}
+
+impl ExchangeError {
+    /// Whether `error`, or anything it wraps, is a maintenance error
+    pub fn is_maintenance(error: &anyhow::Error) -> bool {
+        error
+            .chain()
+            .any(|e| matches!(e.downcast_ref(), Some(ExchangeError::Maintenance { .. })))
+    }
+
+    /// Whether `error`, or anything it wraps, is a reduce-only rejection
+    pub fn is_reduce_only_rejected(error: &anyhow::Error) -> bool {
+        error
+            .chain()
+            .any(|e| matches!(e.downcast_ref(), Some(ExchangeError::ReduceOnlyRejected { .. })))
+    }
+
+    /// Whether `error`, or anything it wraps, is an insufficient margin rejection
+    pub fn is_insufficient_margin(error: &anyhow::Error) -> bool {
+        error
+            .chain()
+            .any(|e| matches!(e.downcast_ref(), Some(ExchangeError::InsufficientMargin { .. })))
+    }
+
+    /// Whether `error`, or anything it wraps, is a risk limit rejection
+    pub fn is_risk_limit(error: &anyhow::Error) -> bool {
+        error
+            .chain()
+            .any(|e| matches!(e.downcast_ref(), Some(ExchangeError::RiskLimit { .. })))
+    }
+
+    /// Whether `error`, or anything it wraps, is a post-only rejection
+    pub fn is_post_only_rejected(error: &anyhow::Error) -> bool {
+        error
+            .chain()
+            .any(|e| matches!(e.downcast_ref(), Some(ExchangeError::PostOnlyRejected { .. })))
+    }
+
+    /// Whether `error`, or anything it wraps, is an expired signature
+    pub fn is_signature_expired(error: &anyhow::Error) -> bool {
+        error
+            .chain()
+            .any(|e| matches!(e.downcast_ref(), Some(ExchangeError::SignatureExpired { .. })))
+    }
+
+    /// Whether `error` is transient, so the same request may succeed if sent
+    /// again shortly: a rate limit, or a request that timed out or never
+    /// connected. Rejections of the order itself, typed or not, are not.
+    pub fn is_retryable(error: &anyhow::Error) -> bool {
+        error.chain().any(|e| {
+            matches!(e.downcast_ref(), Some(ExchangeError::RateLimited { .. }))
+                || e.downcast_ref::<reqwest::Error>().is_some_and(|e| e.is_timeout() || e.is_connect())
+                || e.is::<tokio::time::error::Elapsed>()
+        })
+    }
+}
+
+/// Fail with `ExchangeError::Maintenance` when a response shows the venue is
+/// in maintenance: a 503, or a body that says so. Venues with their own
+/// maintenance error codes check those as well. A 429 fails with
+/// `ExchangeError::RateLimited`.
+pub fn check_unavailable(exchange: &str, status: reqwest::StatusCode, body: &str) -> Result<()> {
+    if status == reqwest::StatusCode::SERVICE_UNAVAILABLE || body.to_ascii_lowercase().contains("maintenance") {
+        return Err(maintenance(exchange, &format!("{} {}", status, body)));
+    }
+    if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+        return Err(rate_limited(exchange, &format!("{} {}", status, body)));
+    }
+    Ok(())
+}
+
+/// A maintenance error quoting the start of `detail`
+pub fn maintenance(exchange: &str, detail: &str) -> anyhow::Error {
+    ExchangeError::Maintenance {
+        exchange: exchange.to_string(),
+        detail: snippet(detail),
+    }
+    .into()
+}
+
+/// A reduce-only rejection quoting the start of `detail`
+pub fn reduce_only_rejected(exchange: &str, detail: &str) -> anyhow::Error {
+    ExchangeError::ReduceOnlyRejected {
+        exchange: exchange.to_string(),
+        detail: snippet(detail),
+    }
+    .into()
+}
+
+/// An insufficient margin rejection quoting the start of `detail`
+pub fn insufficient_margin(exchange: &str, detail: &str) -> anyhow::Error {
+    ExchangeError::InsufficientMargin {
+        exchange: exchange.to_string(),
+        detail: snippet(detail),
+    }
+    .into()
+}
+
+/// A post-only rejection quoting the start of `detail`
+pub fn post_only_rejected(exchange: &str, detail: &str) -> anyhow::Error {
+    ExchangeError::PostOnlyRejected {
+        exchange: exchange.to_string(),
+        detail: snippet(detail),
+    }
+    .into()
+}
+
+/// An expired signature error quoting the start of `detail`
+pub fn signature_expired(exchange: &str, detail: &str) -> anyhow::Error {
+    ExchangeError::SignatureExpired {
+        exchange: exchange.to_string(),
+        detail: snippet(detail),
+    }
+    .into()
+}
+
+/// A risk limit rejection quoting the start of `detail`
+pub fn risk_limit(exchange: &str, detail: &str) -> anyhow::Error {
+    ExchangeError::RiskLimit {
+        exchange: exchange.to_string(),
+        detail: snippet(detail),
+    }
+    .into()
+}
+
+/// A rate limit error quoting the start of `detail`
+pub fn rate_limited(exchange: &str, detail: &str) -> anyhow::Error {
+    ExchangeError::RateLimited {
+        exchange: exchange.to_string(),
+        detail: snippet(detail),
+    }
+    .into()
+}
+
+/// `body` on one line, cut to a length that fits in an error
+fn snippet(body: &str) -> String {
+    body.split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(NON_JSON_SNIPPET_LEN)
+        .collect()
+}
+
+/// Parse a response body. Rate limiters, WAFs and IP blocks in front of an
+/// exchange answer with HTML pages instead of the API's JSON, which would
+/// otherwise surface as a bare parse error.
+pub fn parse_json<T: serde::de::DeserializeOwned>(body: &str) -> Result<T> {
+    let trimmed = body.trim_start();
+    if !trimmed.starts_with('<') {
+        match serde_json::from_str(body) {
+            Ok(value) => return Ok(value),
+            Err(e) if trimmed.starts_with(['{', '[']) => return Err(e.into()),
+            Err(_) => {}
+        }
+    }
+
+    anyhow::bail!(
+        "exchange returned non-JSON (possibly rate-limited or IP-blocked): {}",
+        snippet(body)
+    )
+}
+
+/// Book levels sent as `[price, quantity]` string pairs, as most venues do
+pub fn parse_levels(levels: &[[String; 2]]) -> Result<Vec<BookLevel>> {
+    levels
+        .iter()
+        .map(|[price, quantity]| {
+            Ok(BookLevel {
+                price: price.parse()?,
+                quantity: quantity.parse()?,
+            })
+        })
+        .collect()
+}
+
+/// Levels listed as `[price, quantity, ...]` rows of strings or numbers.
+/// Columns past the quantity, like order counts, are ignored.
+pub fn parse_level_rows(rows: &[Vec<serde_json::Value>]) -> Result<Vec<BookLevel>> {
+    rows.iter()
+        .map(|row| match row.as_slice() {
+            [price, quantity, ..] => Ok(BookLevel {
+                price: json_decimal(price)?,
+                quantity: json_decimal(quantity)?,
+            }),
+            _ => anyhow::bail!("Book level without a price and quantity: {:?}", row),
+        })
+        .collect()
+}
+
+/// A decimal sent as a JSON string or number
+pub fn json_decimal(value: &serde_json::Value) -> Result<Decimal> {
+    let text = match value {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Number(number) => number.to_string(),
+        other => anyhow::bail!("Not a number: {}", other),
+    };
+    text.parse()
+        .or_else(|_| Decimal::from_scientific(&text))
+        .with_context(|| format!("Not a number: {}", text))
+}
+
+/// Current time in Unix milliseconds, for books from venues that don't
+/// stamp them
+pub fn now_millis() -> i64 {
+    chrono::Utc::now().timestamp_millis()
+}
+
+/// Unix milliseconds from a venue timestamp in seconds, milliseconds,
+/// microseconds or nanoseconds, told apart by magnitude. Seconds are
+/// recognised up to the year 5138 and anything later as milliseconds.
+pub fn epoch_millis(timestamp: i64) -> i64 {
+    let magnitude = timestamp.unsigned_abs();
+    if magnitude < 100_000_000_000 {
+        timestamp * 1000
+    } else if magnitude < 100_000_000_000_000 {
+        timestamp
+    } else if magnitude < 100_000_000_000_000_000 {
+        timestamp / 1000
+    } else {
+        timestamp / 1_000_000
+    }
+}
+
+/// Unix milliseconds from fractional Unix seconds
+pub fn epoch_millis_from_secs(seconds: f64) -> i64 {
+    (seconds * 1000.0).round() as i64
+}
+
 /// Credentials for exchange API
 #[derive(Debug, Clone)]
 pub struct Credentials {
     pub api_key: String,
     pub api_secret: String,
     pub passphrase: Option<String>, // For OKX
+    pub wallet_key: Option<String>, // Hex private key for on-chain venues (dYdX)
+    /// Sub-account orders are routed to, on venues where a request names it.
+    /// dYdX takes the subaccount number, 0 if unset. Binance, Bybit, OKX,
+    /// Bitget, Gate.io, KuCoin and the other CEX venues address a sub-account
+    /// by the API key created on it, so this is left unset there.
+    pub sub_account: Option<String>,
 }
 
 /// Exchange adapter trait
@@ -98,6 +786,17 @@ pub trait ExchangeAdapter: Send + Sync {
         request: &OrderRequest,
     ) -> Result<OrderResponse>;
 
+    /// Place an order over the venue's trading WebSocket, which skips the
+    /// HTTP round trip of REST. Venues without one, or whose socket can't be
+    /// reached, place it over REST instead.
+    async fn place_order_ws(
+        &self,
+        credentials: &Credentials,
+        request: &OrderRequest,
+    ) -> Result<OrderResponse> {
+        self.place_order(credentials, request).await
+    }
+
     /// Cancel an order
     async fn cancel_order(
         &self,
@@ -106,6 +805,43 @@ pub trait ExchangeAdapter: Send + Sync {
         order_id: &str,
     ) -> Result<OrderResponse>;
 
+    /// Cancel several orders on one symbol, in as few requests as the venue
+    /// allows. Orders already closed count as cancelled. Fills are not
+    /// reported, so read the orders back for them.
+    async fn cancel_orders_batch(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_ids: &[String],
+    ) -> Result<()> {
+        let mut failed = Vec::new();
+        for order_id in order_ids {
+            if let Err(e) = self.cancel_order(credentials, symbol, order_id).await {
+                failed.push(format!("{}: {:#}", order_id, e));
+            }
+        }
+        if !failed.is_empty() {
+            anyhow::bail!(
+                "Failed to cancel {} of {} orders: {}",
+                failed.len(),
+                order_ids.len(),
+                failed.join("; ")
+            );
+        }
+        Ok(())
+    }
+
+    /// Cancel every open order on `symbol`, ours or not
+    async fn cancel_all_orders(&self, _credentials: &Credentials, _symbol: &str) -> Result<()> {
+        anyhow::bail!("Cancelling all orders is not supported by {}", self.id())
+    }
+
+    /// Open orders on `symbol`, ours or not. Venues that page the list
+    /// return the first page.
+    async fn get_open_orders(&self, _credentials: &Credentials, _symbol: &str) -> Result<Vec<OrderResponse>> {
+        anyhow::bail!("Listing open orders is not supported by {}", self.id())
+    }
+
     /// Get order status
     async fn get_order(
         &self,
@@ -114,11 +850,291 @@ pub trait ExchangeAdapter: Send + Sync {
         order_id: &str,
     ) -> Result<OrderResponse>;
 
+    /// Get the status of several orders on one symbol, using a bulk endpoint
+    /// where the exchange has one. Orders the exchange doesn't return are
+    /// omitted, and results are not guaranteed to follow `order_ids`.
+    async fn get_orders_batch(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_ids: &[String],
+    ) -> Result<Vec<OrderResponse>> {
+        let mut orders = Vec::with_capacity(order_ids.len());
+        for order_id in order_ids {
+            orders.push(self.get_order(credentials, symbol, order_id).await?);
+        }
+        Ok(orders)
+    }
+
+    /// Whether `place_trailing_stop` handles this trail natively. Other trails
+    /// are emulated by the execution service.
+    fn supports_trailing_stop(&self, _trail: Trail) -> bool {
+        false
+    }
+
+    /// Place a native trailing stop
+    async fn place_trailing_stop(
+        &self,
+        _credentials: &Credentials,
+        _request: &TrailingStopRequest,
+    ) -> Result<OrderResponse> {
+        anyhow::bail!("Trailing stops are not supported by {}", self.id())
+    }
+
+    /// Whether `place_stop_order` can rest a stop at the exchange. Trailing
+    /// stops emulated by the execution service are backed by one where it can.
+    fn supports_stop_order(&self) -> bool {
+        false
+    }
+
+    /// Place a reduce-only stop-market order
+    async fn place_stop_order(
+        &self,
+        _credentials: &Credentials,
+        _request: &StopOrderRequest,
+    ) -> Result<OrderResponse> {
+        anyhow::bail!("Stop orders are not supported by {}", self.id())
+    }
+
+    /// Move resting stop `order_id` to `request.stop_price`. By default it is
+    /// cancelled and placed again, so the returned order may carry a new id;
+    /// if the stop filled before it could be cancelled, that order is
+    /// returned instead.
+    async fn amend_stop_order(
+        &self,
+        credentials: &Credentials,
+        order_id: &str,
+        request: &StopOrderRequest,
+    ) -> Result<OrderResponse> {
+        let cancelled = self.cancel_stop_order(credentials, &request.symbol, order_id).await?;
+        if cancelled.status == OrderStatus::Filled || cancelled.filled_quantity > Decimal::ZERO {
+            return Ok(cancelled);
+        }
+        self.place_stop_order(credentials, request).await
+    }
+
+    /// Status of a stop order
+    async fn get_stop_order(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        self.get_order(credentials, symbol, order_id).await
+    }
+
+    /// Cancel a stop order, returning its final state
+    async fn cancel_stop_order(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        self.cancel_order(credentials, symbol, order_id).await
+    }
+
+    /// Whether `place_algo_order` can hand orders of `kind` to the exchange's
+    /// algo engine. Orders are sliced by the execution service otherwise. OKX
+    /// runs both kinds; Bybit's V5 API only has price-triggered conditional
+    /// orders, so it has neither.
+    fn supports_algo_order(&self, _kind: AlgoKind) -> bool {
+        false
+    }
+
+    /// Shortest time between child orders the exchange's algo engine takes
+    /// for `kind`, in seconds
+    fn min_algo_interval_secs(&self, _kind: AlgoKind) -> u64 {
+        1
+    }
+
+    /// Place a native algo order. The response carries the algo order's id
+    /// and its fills so far.
+    async fn place_algo_order(
+        &self,
+        _credentials: &Credentials,
+        _request: &AlgoOrderRequest,
+    ) -> Result<OrderResponse> {
+        anyhow::bail!("Native algo orders are not supported by {}", self.id())
+    }
+
+    /// Status of a native algo order, with the fills of its child orders
+    async fn get_algo_order(
+        &self,
+        _credentials: &Credentials,
+        _symbol: &str,
+        _algo_id: &str,
+    ) -> Result<OrderResponse> {
+        anyhow::bail!("Native algo orders are not supported by {}", self.id())
+    }
+
+    /// Stop a native algo order. Child orders it has resting are cancelled
+    /// with it.
+    async fn cancel_algo_order(&self, _credentials: &Credentials, _symbol: &str, _algo_id: &str) -> Result<()> {
+        anyhow::bail!("Native algo orders are not supported by {}", self.id())
+    }
+
+    /// Individual fills of an order, for venues whose order status lacks an
+    /// average fill price, and for the fees each fill was charged
+    async fn get_fills(
+        &self,
+        _credentials: &Credentials,
+        _symbol: &str,
+        _order_id: &str,
+    ) -> Result<Vec<Fill>> {
+        anyhow::bail!("Fills are not supported by {}", self.id())
+    }
+
     /// Get current best bid/ask for a symbol
     async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)>;
 
+    /// Current `source` price for a symbol. Only the mid is available
+    /// unless the venue publishes the others.
+    async fn get_reference_price(&self, symbol: &str, source: ReferencePriceSource) -> Result<Decimal> {
+        match source {
+            ReferencePriceSource::Mid => mid_price(self, symbol).await,
+            other => anyhow::bail!("{} price is not supported by {}", other, self.id()),
+        }
+    }
+
+    /// Listing status of a symbol
+    async fn get_symbol_info(&self, _symbol: &str) -> Result<SymbolInfo> {
+        anyhow::bail!("Symbol info is not supported by {}", self.id())
+    }
+
+    /// Top `depth` levels of each side of the book
+    async fn get_order_book(&self, _symbol: &str, _depth: usize) -> Result<OrderBook> {
+        anyhow::bail!("Order book depth is not supported by {}", self.id())
+    }
+
+    /// Whether a `TimeInForce::Gtd` order expiring at `expire_at_ms` is
+    /// expired by the venue itself:
+    ///
+    /// | Adapter | GTD |
+    /// |---|---|
+    /// | Binance | native, `goodTillDate`, at least 10 minutes out |
+    /// | Bybit, OKX, Bitget, Gate.io, KuCoin, HTX, MEXC, BingX, CoinEx, LBank, dYdX | emulated |
+    ///
+    /// Emulated orders rest as GTC and the slicer cancels them at the expiry.
+    fn supports_gtd(&self, _expire_at_ms: i64) -> bool {
+        false
+    }
+
+    /// Exchange server time in Unix milliseconds
+    async fn get_server_time(&self) -> Result<i64> {
+        anyhow::bail!("Server time is not supported by {}", self.id())
+    }
+
     /// Check if connected
     fn is_connected(&self) -> bool;
+
+    /// Exchange-native symbol for a canonical base/quote pair
+    fn to_native_symbol(&self, base: &str, quote: &str) -> String;
+
+    /// Canonical `BASE/QUOTE` form of an exchange-native symbol
+    fn to_canonical_symbol(&self, native: &str) -> Option<String>;
+
+    /// Symbol to report in responses: canonical where the native symbol can
+    /// be split, as the venue sent it otherwise. Canonical symbols pass through.
+    fn canonical_symbol(&self, native: &str) -> String {
+        if parse_canonical_symbol(native).is_some() {
+            return native.to_string();
+        }
+        self.to_canonical_symbol(native).unwrap_or_else(|| native.to_string())
+    }
+
+    /// Learn the quote assets the venue lists, which `to_canonical_symbol`
+    /// needs to split symbols that join base and quote with no separator.
+    /// Venues whose symbols carry a separator have nothing to load.
+    async fn load_quote_assets(&self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Translate a canonical `BASE/QUOTE` symbol to native form.
+    /// Symbols without a `/` are assumed to be native already and pass through.
+    fn native_symbol(&self, symbol: &str) -> String {
+        match parse_canonical_symbol(symbol) {
+            Some((base, quote)) => self.to_native_symbol(&base.to_uppercase(), &quote.to_uppercase()),
+            None => symbol.to_string(),
+        }
+    }
+
+    /// Contract denomination of a symbol. Quantities are treated as coins on a
+    /// linear contract unless the adapter knows better.
+    fn contract_spec(&self, _symbol: &str) -> ContractSpec {
+        ContractSpec::default()
+    }
+
+    /// Whether reduce-only market orders are accepted and fill against the book.
+    /// Venues that return false get an aggressive limit for emergency exits.
+    fn supports_reduce_only_market(&self) -> bool {
+        true
+    }
+
+    /// Whether the account is in hedge mode (separate long and short positions)
+    async fn get_position_mode(&self, _credentials: &Credentials) -> Result<bool> {
+        anyhow::bail!("Position mode is not supported by {}", self.id())
+    }
+
+    /// Switch the account between one-way and hedge mode
+    async fn set_position_mode(&self, _credentials: &Credentials, _hedge: bool) -> Result<()> {
+        anyhow::bail!("Position mode is not supported by {}", self.id())
+    }
+
+    /// Current leverage and margin mode on `symbol`
+    async fn get_leverage(&self, _credentials: &Credentials, _symbol: &str) -> Result<LeverageInfo> {
+        anyhow::bail!("Leverage is not supported by {}", self.id())
+    }
+
+    /// Risk limit tier the account holds `symbol` under
+    async fn get_risk_limit(&self, _credentials: &Credentials, _symbol: &str) -> Result<RiskLimit> {
+        anyhow::bail!("Risk limits are not supported by {}", self.id())
+    }
+
+    /// Move `symbol` to the lowest tier that allows a position worth
+    /// `notional_usd`, on venues where the account picks its tier. Higher
+    /// tiers allow less leverage.
+    async fn set_risk_limit(&self, _credentials: &Credentials, _symbol: &str, _notional_usd: Decimal) -> Result<RiskLimit> {
+        anyhow::bail!("Setting risk limits is not supported by {}", self.id())
+    }
+
+    /// The account's open positions, across all symbols
+    async fn get_positions(&self, _credentials: &Credentials) -> Result<Vec<Position>> {
+        anyhow::bail!("Positions are not supported by {}", self.id())
+    }
+
+    /// Whether `set_cancel_on_disconnect` registers anything. OKX and Bybit
+    /// do; everywhere else it is a no-op.
+    fn supports_cancel_on_disconnect(&self) -> bool {
+        false
+    }
+
+    /// Have the exchange cancel the account's open orders if it is not
+    /// refreshed within `timeout_secs`, or on Bybit if its private stream is
+    /// down that long. Zero clears the registration.
+    async fn set_cancel_on_disconnect(&self, _credentials: &Credentials, _timeout_secs: u64) -> Result<()> {
+        tracing::warn!("Cancel-on-disconnect is not supported by {}, not registered", self.id());
+        Ok(())
+    }
+}
+
+/// User-Agent sent to exchanges that have none configured
+const DEFAULT_USER_AGENT: &str = concat!("crossspread-execution/", env!("CARGO_PKG_VERSION"));
+
+/// REST client for an exchange, with its User-Agent and proxy settings.
+/// Without a configured proxy requests go direct, whatever the environment says.
+pub fn http_client(config: &ExchangeConfig) -> Result<reqwest::Client> {
+    let builder = reqwest::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .user_agent(config.user_agent.as_deref().unwrap_or(DEFAULT_USER_AGENT));
+
+    let builder = match &config.proxy {
+        Some(url) => builder.proxy(
+            reqwest::Proxy::all(url).with_context(|| format!("Invalid proxy for {}: {}", config.id, url))?,
+        ),
+        None => builder.no_proxy(),
+    };
+
+    Ok(builder.build()?)
 }
 
 /// Create an exchange adapter from config
@@ -135,11 +1151,312 @@ pub async fn create_adapter(config: &ExchangeConfig) -> Result<Box<dyn ExchangeA
         "coinex" => Ok(Box::new(coinex::CoinexAdapter::new(config.clone()).await?)),
         "lbank" => Ok(Box::new(lbank::LbankAdapter::new(config.clone()).await?)),
         "htx" => Ok(Box::new(htx::HtxAdapter::new(config.clone()).await?)),
+        "dydx" => Ok(Box::new(dydx::DydxAdapter::new(config.clone()).await?)),
         _ => anyhow::bail!("Unknown exchange: {}", config.id),
     }
 }
 
-/// Generate a unique client order ID
-pub fn generate_client_order_id() -> String {
-    format!("cs_{}", Uuid::new_v4().to_string().replace("-", "")[..16].to_string())
+/// Halfway between the best bid and ask
+pub async fn mid_price<A: ExchangeAdapter + ?Sized>(adapter: &A, symbol: &str) -> Result<Decimal> {
+    let (bid, ask) = adapter.get_best_price(symbol).await?;
+    Ok((bid + ask) / Decimal::TWO)
+}
+
+/// Position an order acts on in hedge mode: opening buys and closing sells
+/// act on the long position (`Side::Buy`), the rest on the short
+pub fn position_side(side: Side, reduce_only: bool) -> Side {
+    match (side, reduce_only) {
+        (Side::Buy, false) | (Side::Sell, true) => Side::Buy,
+        (Side::Sell, false) | (Side::Buy, true) => Side::Sell,
+    }
+}
+
+/// Split a canonical `BASE/QUOTE` symbol
+pub fn parse_canonical_symbol(symbol: &str) -> Option<(&str, &str)> {
+    let (base, quote) = symbol.split_once('/')?;
+    if base.is_empty() || quote.is_empty() {
+        return None;
+    }
+    Some((base, quote))
+}
+
+/// Quote assets a venue lists, taken from its exchange info, for splitting
+/// symbols with no separator such as `BTCUSDT`
+#[derive(Default)]
+pub struct QuoteAssets(std::sync::RwLock<Vec<String>>);
+
+impl QuoteAssets {
+    /// Replace the known quote assets
+    pub fn set(&self, quotes: impl IntoIterator<Item = String>) {
+        let mut quotes: Vec<String> = quotes.into_iter().filter(|q| !q.is_empty()).collect();
+        // Longest first, so `USDC` is tried before a `C` a venue might list
+        quotes.sort_by(|a, b| b.len().cmp(&a.len()).then_with(|| a.cmp(b)));
+        quotes.dedup();
+        *self.0.write().unwrap() = quotes;
+    }
+
+    /// Canonical form of a symbol with no separator. None until the quote
+    /// assets are loaded, and for symbols that end in none of them.
+    pub fn canonical(&self, native: &str) -> Option<String> {
+        self.0.read().unwrap().iter().find_map(|quote| {
+            native
+                .strip_suffix(quote.as_str())
+                .filter(|base| !base.is_empty())
+                .map(|base| format!("{}/{}", base, quote))
+        })
+    }
+}
+
+/// Canonical form of a symbol whose base and quote are joined by `separator`,
+/// e.g. `BTC_USDT`
+pub fn canonical_from_separated(native: &str, separator: char) -> Option<String> {
+    let (base, quote) = native.split_once(separator)?;
+    if base.is_empty() || quote.is_empty() {
+        return None;
+    }
+    Some(format!("{}/{}", base, quote))
+}
+
+/// Client order id prefix for exchanges configured without one
+pub const DEFAULT_CLIENT_ORDER_ID_PREFIX: &str = "cs_";
+
+/// Generate a unique client order ID starting with `prefix`
+pub fn generate_client_order_id(prefix: &str) -> String {
+    format!("{}{}", prefix, Uuid::new_v4().to_string().replace("-", "")[..16].to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn config(id: &str) -> ExchangeConfig {
+        ExchangeConfig {
+            id: id.to_string(),
+            rest_url: String::new(),
+            ws_url: String::new(),
+            trade_ws_url: None,
+            testnet: false,
+            maker_fee_bps: 0.0,
+            taker_fee_bps: 0.0,
+            trade_mode: None,
+            user_agent: None,
+            proxy: None,
+            log_raw_http: false,
+            ws_orders: false,
+            order_tag: None,
+            client_order_id_prefix: None,
+            adopt_duplicate_orders: false,
+        }
+    }
+
+    #[test]
+    fn test_canonical_symbol_helpers() {
+        assert_eq!(parse_canonical_symbol("BTC/USDT"), Some(("BTC", "USDT")));
+        assert_eq!(parse_canonical_symbol("BTCUSDT"), None);
+        let quotes = QuoteAssets::default();
+        assert_eq!(quotes.canonical("ETHUSDC"), None);
+        quotes.set(["USD", "USDC", "USDT", "USDT"].map(String::from));
+        assert_eq!(quotes.canonical("ETHUSDC"), Some("ETH/USDC".to_string()));
+        assert_eq!(quotes.canonical("BTCUSD"), Some("BTC/USD".to_string()));
+        assert_eq!(quotes.canonical("USDT"), None);
+        assert_eq!(canonical_from_separated("BTC_USDT", '_'), Some("BTC/USDT".to_string()));
+    }
+
+    #[test]
+    fn test_linear_contract_math() {
+        // BTCUSDT with 0.01 BTC per contract
+        let spec = ContractSpec::linear(dec!(0.01));
+
+        assert_eq!(spec.notional_usd(dec!(100), dec!(60000)), dec!(60000));
+        assert_eq!(spec.settlement_value(dec!(100), dec!(60000)), dec!(60000));
+        assert_eq!(spec.quantity_for_notional(dec!(6000), dec!(60000)), dec!(10));
+        assert_eq!(spec.pnl(Side::Buy, dec!(100), dec!(60000), dec!(61000)), dec!(1000));
+        assert_eq!(spec.pnl(Side::Sell, dec!(100), dec!(60000), dec!(61000)), dec!(-1000));
+    }
+
+    #[test]
+    fn test_inverse_contract_math() {
+        // BTCUSD with 100 USD per contract, settled in BTC
+        let spec = ContractSpec::inverse(dec!(100));
+
+        assert_eq!(spec.notional_usd(dec!(100), dec!(50000)), dec!(10000));
+        assert_eq!(spec.settlement_value(dec!(100), dec!(50000)), dec!(0.2));
+        assert_eq!(spec.quantity_for_notional(dec!(5000), dec!(50000)), dec!(50));
+        assert_eq!(spec.pnl(Side::Buy, dec!(100), dec!(50000), dec!(62500)), dec!(0.04));
+        assert_eq!(spec.pnl(Side::Sell, dec!(100), dec!(50000), dec!(62500)), dec!(-0.04));
+    }
+
+    #[tokio::test]
+    async fn test_http_client_sends_configured_user_agent() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0u8; 4096];
+            let n = socket.read(&mut request).await.unwrap();
+            socket
+                .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 0\r\n\r\n")
+                .await
+                .unwrap();
+            String::from_utf8_lossy(&request[..n]).to_lowercase()
+        });
+
+        let client = http_client(&ExchangeConfig {
+            user_agent: Some("desk-7".to_string()),
+            ..config("binance")
+        })
+        .unwrap();
+        client.get(format!("http://{}/ping", addr)).send().await.unwrap();
+
+        assert!(server.await.unwrap().contains("user-agent: desk-7\r\n"));
+
+        let bad_proxy = http_client(&ExchangeConfig {
+            proxy: Some("not a proxy".to_string()),
+            ..config("binance")
+        });
+        assert!(bad_proxy.unwrap_err().to_string().starts_with("Invalid proxy for binance"));
+    }
+
+    #[test]
+    fn test_book_walk() {
+        let book = OrderBook {
+            bids: parse_levels(&[
+                ["99".to_string(), "1".to_string()],
+                ["98".to_string(), "2".to_string()],
+            ])
+            .unwrap(),
+            asks: parse_levels(&[
+                ["101".to_string(), "1".to_string()],
+                ["102".to_string(), "1".to_string()],
+            ])
+            .unwrap(),
+            timestamp: 0,
+        };
+
+        assert_eq!(book.walk(Side::Buy, dec!(1)), (dec!(1), Some(dec!(101))));
+        assert_eq!(book.walk(Side::Sell, dec!(2)), (dec!(2), Some(dec!(98.5))));
+        // Runs out of depth
+        assert_eq!(book.walk(Side::Buy, dec!(5)), (dec!(2), Some(dec!(101.5))));
+        assert_eq!(OrderBook::default().walk(Side::Sell, dec!(1)), (Decimal::ZERO, None));
+    }
+
+    #[test]
+    fn test_book_levels_are_ordered_best_first() {
+        let rows: Vec<Vec<serde_json::Value>> =
+            serde_json::from_str(r#"[["101.5", "2", "0", "3"], [100, 1.5], ["102", 1e-5]]"#).unwrap();
+        let levels = parse_level_rows(&rows).unwrap();
+        assert_eq!(levels[1], BookLevel { price: dec!(100), quantity: dec!(1.5) });
+        assert_eq!(levels[2].quantity, dec!(0.00001));
+        assert!(parse_level_rows(&[vec![serde_json::json!("100")]]).is_err());
+
+        let book = OrderBook::from_levels(levels.clone(), levels, 2, 7);
+        let prices = |levels: &[BookLevel]| levels.iter().map(|l| l.price).collect::<Vec<_>>();
+        assert_eq!(prices(&book.bids), [dec!(102), dec!(101.5)]);
+        assert_eq!(prices(&book.asks), [dec!(100), dec!(101.5)]);
+        assert_eq!(book.timestamp, 7);
+    }
+
+    #[test]
+    fn test_position_side() {
+        assert_eq!(position_side(Side::Buy, false), Side::Buy);
+        assert_eq!(position_side(Side::Sell, true), Side::Buy);
+        assert_eq!(position_side(Side::Sell, false), Side::Sell);
+        assert_eq!(position_side(Side::Buy, true), Side::Sell);
+    }
+
+    #[test]
+    fn test_non_json_bodies_are_reported_as_such() {
+        let value: serde_json::Value = parse_json(r#"{"code":0}"#).unwrap();
+        assert_eq!(value["code"], 0);
+
+        let page = "<!DOCTYPE html>\n<html>\n  <head><title>403 Forbidden</title></head>\n</html>";
+        let err = parse_json::<serde_json::Value>(page).unwrap_err().to_string();
+        assert!(err.starts_with("exchange returned non-JSON (possibly rate-limited or IP-blocked): "));
+        assert!(err.contains("<html> <head><title>403 Forbidden</title>"));
+
+        let err = parse_json::<serde_json::Value>("Too Many Requests").unwrap_err().to_string();
+        assert!(err.ends_with(": Too Many Requests"));
+        let long = "x".repeat(1000);
+        let err = parse_json::<serde_json::Value>(&long).unwrap_err().to_string();
+        assert!(err.ends_with(&"x".repeat(NON_JSON_SNIPPET_LEN)));
+        assert!(!err.contains(&"x".repeat(NON_JSON_SNIPPET_LEN + 1)));
+
+        // Malformed JSON keeps the parser's own error
+        let err = parse_json::<serde_json::Value>(r#"{"code":"#).unwrap_err().to_string();
+        assert!(!err.contains("non-JSON"));
+    }
+
+    #[test]
+    fn test_timestamps_are_normalised_to_millis() {
+        let millis = 1_700_000_000_123;
+        assert_eq!(epoch_millis(1_700_000_000), 1_700_000_000_000);
+        assert_eq!(epoch_millis(millis), millis);
+        assert_eq!(epoch_millis(millis * 1000 + 456), millis);
+        assert_eq!(epoch_millis(millis * 1_000_000 + 456_789), millis);
+        assert_eq!(epoch_millis(0), 0);
+        assert_eq!(epoch_millis_from_secs(1_700_000_000.123), millis);
+    }
+
+    #[tokio::test]
+    async fn test_only_transient_errors_are_retryable() {
+        let throttled = check_unavailable("binance", reqwest::StatusCode::TOO_MANY_REQUESTS, "{}").unwrap_err();
+        assert!(ExchangeError::is_retryable(&throttled.context("Slice failed")));
+        let elapsed = tokio::time::timeout(std::time::Duration::ZERO, std::future::pending::<()>())
+            .await
+            .unwrap_err();
+        assert!(ExchangeError::is_retryable(&elapsed.into()));
+
+        assert!(!ExchangeError::is_retryable(&insufficient_margin("bybit", "110007 - ab not enough")));
+        assert!(!ExchangeError::is_retryable(&maintenance("okx", "503")));
+        assert!(!ExchangeError::is_retryable(&anyhow::anyhow!("Invalid symbol")));
+        check_unavailable("binance", reqwest::StatusCode::BAD_REQUEST, "{}").unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_native_symbol_round_trip() {
+        // Exchange info for the venues whose symbols have no separator
+        let cases = [
+            ("binance", "BTCUSDT", Some(r#"{"symbols":[{"quoteAsset":"USDC"},{"quoteAsset":"USDT"}]}"#)),
+            ("okx", "BTC-USDT-SWAP", None),
+            ("gateio", "BTC_USDT", None),
+            ("kucoin", "XBTUSDTM", Some(r#"{"code":"200000","data":[{"quoteCurrency":"USDT"}]}"#)),
+            ("htx", "BTC-USDT", None),
+        ];
+        for (id, native, exchange_info) in cases {
+            let mut config = config(id);
+            if let Some(body) = exchange_info {
+                config.rest_url = crate::exchange::mock::serve_http(vec![("200 OK", body)]).await.0;
+            }
+            let adapter = create_adapter(&config).await.unwrap();
+            adapter.load_quote_assets().await.unwrap();
+            assert_eq!(adapter.native_symbol("btc/usdt"), native, "{}", id);
+            assert_eq!(adapter.to_canonical_symbol(native).as_deref(), Some("BTC/USDT"), "{}", id);
+            assert_eq!(adapter.canonical_symbol(native), "BTC/USDT", "{}", id);
+            // Native symbols pass through untouched
+            assert_eq!(adapter.native_symbol(native), native, "{}", id);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_quote_assets_come_from_exchange_info() {
+        let (url, _) = crate::exchange::mock::serve_http(vec![(
+            "200 OK",
+            r#"{"symbols":[{"quoteAsset":"USDT"},{"quoteAsset":"BUSD"}]}"#,
+        )])
+        .await;
+        let mut config = config("binance");
+        config.rest_url = url;
+        let adapter = create_adapter(&config).await.unwrap();
+
+        // Unsplittable until the venue's quote assets are known
+        assert_eq!(adapter.canonical_symbol("ETHBUSD"), "ETHBUSD");
+        adapter.load_quote_assets().await.unwrap();
+        assert_eq!(adapter.canonical_symbol("ETHBUSD"), "ETH/BUSD");
+        assert_eq!(adapter.canonical_symbol("ETH/BUSD"), "ETH/BUSD");
+        // Not a quote asset Binance lists
+        assert_eq!(adapter.canonical_symbol("ETHUSDC"), "ETHUSDC");
+    }
 }
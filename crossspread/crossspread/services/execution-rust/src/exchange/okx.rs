@@ -8,41 +8,502 @@ use reqwest::Client;
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::RwLock;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tracing::{debug, info};
 
-use super::{Credentials, ExchangeAdapter, OrderRequest, OrderResponse, OrderStatus, OrderType, Side};
-use crate::config::ExchangeConfig;
+use super::{
+    canonical_from_separated, check_unavailable, epoch_millis, maintenance, mid_price, parse_json, parse_level_rows, position_side, signature_expired, AlgoKind,
+    AlgoOrderRequest, ContractSpec, Credentials, ExchangeAdapter, LeverageInfo, MarginMode, OrderBook, OrderRequest, OrderResponse, OrderStatus, OrderType, Position, ReferencePriceSource, Side, StopOrderRequest,
+    SymbolInfo, SymbolStatus, TimeInForce,
+};
+use super::raw_http::SendTraced;
+use crate::config::{ExchangeConfig, TradeMode};
 
 type HmacSha256 = Hmac<Sha256>;
 
+/// Cancel rejections for orders that already filled, were cancelled or are
+/// unknown
+const ORDER_ALREADY_CLOSED: [&str; 3] = ["51400", "51401", "51402"];
+/// Placement rejected because another order already has the `clOrdId`
+const DUPLICATE_CLIENT_ORDER_ID: &str = "51016";
+/// "Service temporarily unavailable", sent while OKX is in maintenance
+const SERVICE_UNAVAILABLE: &str = "50001";
+/// "Timestamp request expired"
+const TIMESTAMP_EXPIRED: &str = "50102";
+/// Most orders `cancel-batch-orders` takes in one request
+const CANCEL_BATCH_SIZE: usize = 20;
+/// Most orders `orders-pending` returns in one page
+const PENDING_PAGE_SIZE: usize = 100;
+/// Recent orders searched for an algo order's children
+const ALGO_CHILD_PAGE_SIZE: usize = 100;
+
+/// Account settings that decide how orders must be sent
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct AccountSettings {
+    /// `acctLv`: 1 simple, 2 single-currency margin, 3 multi-currency margin,
+    /// 4 portfolio margin
+    level: u8,
+    /// `posMode` is `long_short_mode` rather than `net_mode`
+    hedge: bool,
+}
+
 pub struct OkxAdapter {
     config: ExchangeConfig,
     client: Client,
+    /// Account settings by API key, read from `/api/v5/account/config` on first use
+    accounts: RwLock<HashMap<String, AccountSettings>>,
 }
 
 impl OkxAdapter {
     pub async fn new(config: ExchangeConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(std::time::Duration::from_secs(10))
-            .build()?;
+        let client = super::http_client(&config)?;
 
-        Ok(Self { config, client })
+        Ok(Self {
+            config,
+            client,
+            accounts: RwLock::new(HashMap::new()),
+        })
     }
 
     fn timestamp_iso() -> String {
         chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string()
     }
 
-    fn sign(&self, secret: &str, timestamp: &str, method: &str, path: &str, body: &str) -> String {
-        let prehash = format!("{}{}{}{}", timestamp, method, path, body);
-        let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
-            .expect("HMAC can take key of any size");
-        mac.update(prehash.as_bytes());
-        STANDARD.encode(mac.finalize().into_bytes())
+    /// Fail with a maintenance error when OKX answered with its
+    /// maintenance code
+    fn check_maintenance_code(&self, code: &str, body: &str) -> Result<()> {
+        if code == SERVICE_UNAVAILABLE {
+            return Err(maintenance(self.id(), body));
+        }
+        Ok(())
+    }
+
+    /// Fail with an expired signature error when OKX found the request's
+    /// timestamp too old
+    fn check_expired_code(&self, code: &str, body: &str) -> Result<()> {
+        if code == TIMESTAMP_EXPIRED {
+            return Err(signature_expired(self.id(), body));
+        }
+        Ok(())
+    }
+
+    /// Cached account settings, fetched on first use for each API key
+    async fn account_settings(&self, credentials: &Credentials) -> Result<AccountSettings> {
+        if let Some(settings) = self.accounts.read().unwrap().get(&credentials.api_key) {
+            return Ok(*settings);
+        }
+        self.fetch_account_settings(credentials).await
+    }
+
+    async fn fetch_account_settings(&self, credentials: &Credentials) -> Result<AccountSettings> {
+        let timestamp = Self::timestamp_iso();
+        let path = "/api/v5/account/config";
+
+        let signature = sign(&credentials.api_secret, &timestamp, "GET", path, "");
+        let passphrase = credentials.passphrase.as_deref().unwrap_or("");
+
+        let url = format!("{}{}", self.config.rest_url, path);
+        let response = self.client
+            .get(&url)
+            .header("OK-ACCESS-KEY", &credentials.api_key)
+            .header("OK-ACCESS-SIGN", &signature)
+            .header("OK-ACCESS-TIMESTAMP", &timestamp)
+            .header("OK-ACCESS-PASSPHRASE", passphrase)
+            .send_traced(self.config.log_raw_http)
+            .await
+            .context("Failed to fetch OKX account config")?;
+
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        struct AccountConfig {
+            #[serde(rename = "acctLv")]
+            acct_lv: String,
+            #[serde(rename = "posMode")]
+            pos_mode: String,
+        }
+
+        let resp: OkxResponse<AccountConfig> = parse_json(&body)
+            .context("Failed to parse OKX account config")?;
+        if resp.code != "0" {
+            anyhow::bail!("OKX account config error: {} - {}", resp.code, resp.msg);
+        }
+
+        let account = resp.data.into_iter().next()
+            .ok_or_else(|| anyhow::anyhow!("No account config data"))?;
+        let settings = AccountSettings {
+            level: account.acct_lv.parse().context("Invalid OKX acctLv")?,
+            hedge: account.pos_mode == "long_short_mode",
+        };
+
+        debug!("OKX account level {} hedge={}", settings.level, settings.hedge);
+        self.accounts
+            .write()
+            .unwrap()
+            .insert(credentials.api_key.clone(), settings);
+        Ok(settings)
+    }
+
+    /// POST a signed JSON `body` to `path`, returning the response body
+    async fn post_signed(&self, credentials: &Credentials, path: &str, body: String) -> Result<String> {
+        let timestamp = Self::timestamp_iso();
+        let signature = sign(&credentials.api_secret, &timestamp, "POST", path, &body);
+        let passphrase = credentials.passphrase.as_deref().unwrap_or("");
+
+        let url = format!("{}{}", self.config.rest_url, path);
+        let response = self.client
+            .post(&url)
+            .header("OK-ACCESS-KEY", &credentials.api_key)
+            .header("OK-ACCESS-SIGN", &signature)
+            .header("OK-ACCESS-TIMESTAMP", &timestamp)
+            .header("OK-ACCESS-PASSPHRASE", passphrase)
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        check_unavailable(self.id(), status, &body)?;
+        Ok(body)
+    }
+
+    /// GET a signed `path`, query included, returning the response body
+    async fn get_signed(&self, credentials: &Credentials, path: &str) -> Result<String> {
+        let timestamp = Self::timestamp_iso();
+        let signature = sign(&credentials.api_secret, &timestamp, "GET", path, "");
+        let passphrase = credentials.passphrase.as_deref().unwrap_or("");
+
+        let url = format!("{}{}", self.config.rest_url, path);
+        let response = self.client
+            .get(&url)
+            .header("OK-ACCESS-KEY", &credentials.api_key)
+            .header("OK-ACCESS-SIGN", &signature)
+            .header("OK-ACCESS-TIMESTAMP", &timestamp)
+            .header("OK-ACCESS-PASSPHRASE", passphrase)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        check_unavailable(self.id(), status, &body)?;
+        Ok(body)
+    }
+
+    /// The order on native `symbol` whose `id_param` (`ordId` or `clOrdId`) is `id`
+    async fn query_order(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        id_param: &str,
+        id: &str,
+    ) -> Result<OrderResponse> {
+        let timestamp = Self::timestamp_iso();
+        let path = format!("/api/v5/trade/order?instId={}&{}={}", symbol, id_param, id);
+        
+        let signature = sign(&credentials.api_secret, &timestamp, "GET", &path, "");
+        let passphrase = credentials.passphrase.as_deref().unwrap_or("");
+
+        let url = format!("{}{}", self.config.rest_url, path);
+        let response = self.client
+            .get(&url)
+            .header("OK-ACCESS-KEY", &credentials.api_key)
+            .header("OK-ACCESS-SIGN", &signature)
+            .header("OK-ACCESS-TIMESTAMP", &timestamp)
+            .header("OK-ACCESS-PASSPHRASE", passphrase)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let body = response.text().await?;
+        let resp: OkxResponse<OkxOrderData> = parse_json(&body)?;
+
+        let order = resp.data.into_iter().next()
+            .ok_or_else(|| anyhow::anyhow!("No order data"))?;
+
+        Ok(order_response(order))
+    }
+
+    /// Contracts filled by the children of algo order `algo_id` on native
+    /// `symbol` and their average price, from the most recent page of
+    /// finished orders. Children still resting are not counted.
+    async fn algo_child_fills(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        algo_id: &str,
+    ) -> Result<(Decimal, Option<Decimal>)> {
+        let path = format!(
+            "/api/v5/trade/orders-history?instType=SWAP&instId={}&limit={}",
+            symbol, ALGO_CHILD_PAGE_SIZE
+        );
+        let body = self.get_signed(credentials, &path).await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Child {
+            #[serde(default)]
+            algo_id: String,
+            acc_fill_sz: String,
+            avg_px: String,
+        }
+
+        let resp: OkxResponse<Child> = parse_json(&body).context("Failed to parse OKX order history")?;
+        if resp.code != "0" {
+            self.check_maintenance_code(&resp.code, &body)?;
+            anyhow::bail!("OKX order history error: {} - {}", resp.code, resp.msg);
+        }
+
+        let mut filled = Decimal::ZERO;
+        let mut notional = Decimal::ZERO;
+        for child in resp.data.iter().filter(|child| child.algo_id == algo_id) {
+            let size: Decimal = child.acc_fill_sz.parse().unwrap_or_default();
+            filled += size;
+            notional += size * child.avg_px.parse::<Decimal>().unwrap_or_default();
+        }
+        let avg_price = (!filled.is_zero()).then(|| notional / filled);
+        Ok((filled, avg_price))
+    }
+
+    /// POST an algo order request, returning the algo id it acknowledges
+    async fn post_algo(&self, credentials: &Credentials, path: &str, body: String, what: &str) -> Result<String> {
+        let body = self.post_signed(credentials, path, body).await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Acknowledged {
+            algo_id: String,
+            s_code: String,
+            s_msg: String,
+        }
+
+        let resp: OkxResponse<Acknowledged> =
+            parse_json(&body).with_context(|| format!("Failed to parse OKX {} response", what))?;
+        let acknowledged = resp.data.into_iter().next();
+        if resp.code != "0" {
+            self.check_maintenance_code(&resp.code, &body)?;
+            match acknowledged {
+                Some(ack) => anyhow::bail!("OKX {} error: {} - {}", what, ack.s_code, ack.s_msg),
+                None => anyhow::bail!("OKX {} error: {} - {}", what, resp.code, resp.msg),
+            }
+        }
+        acknowledged
+            .map(|ack| ack.algo_id)
+            .ok_or_else(|| anyhow::anyhow!("No algo order data in response"))
+    }
+
+    /// Open orders on native `symbol`, up to one page
+    async fn pending_orders(&self, credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        let timestamp = Self::timestamp_iso();
+        let path = format!(
+            "/api/v5/trade/orders-pending?instType=SWAP&instId={}&limit={}",
+            symbol, PENDING_PAGE_SIZE
+        );
+        let signature = sign(&credentials.api_secret, &timestamp, "GET", &path, "");
+        let passphrase = credentials.passphrase.as_deref().unwrap_or("");
+
+        let url = format!("{}{}", self.config.rest_url, path);
+        let response = self.client
+            .get(&url)
+            .header("OK-ACCESS-KEY", &credentials.api_key)
+            .header("OK-ACCESS-SIGN", &signature)
+            .header("OK-ACCESS-TIMESTAMP", &timestamp)
+            .header("OK-ACCESS-PASSPHRASE", passphrase)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let status = response.status();
+        let body = response.text().await?;
+        check_unavailable(self.id(), status, &body)?;
+
+        let resp: OkxResponse<OkxOrderData> = parse_json(&body).context("Failed to parse OKX pending orders")?;
+        if resp.code != "0" {
+            self.check_maintenance_code(&resp.code, &body)?;
+            anyhow::bail!("OKX pending orders error: {} - {}", resp.code, resp.msg);
+        }
+        Ok(resp.data.into_iter().map(order_response).collect())
+    }
+
+    /// Configured trade mode, or the one the account level requires
+    fn trade_mode(&self, account: AccountSettings) -> TradeMode {
+        self.config.trade_mode.unwrap_or_else(|| trade_mode_for_level(account.level))
+    }
+}
+
+/// Default `tdMode` for an account level:
+///
+/// | `acctLv` | Account mode                 | `tdMode` |
+/// |----------|------------------------------|----------|
+/// | 1        | Simple (spot only)           | `cash`   |
+/// | 2        | Single-currency margin       | `cross`  |
+/// | 3        | Multi-currency margin        | `cross`  |
+/// | 4        | Portfolio margin             | `cross`  |
+///
+/// Margin accounts can also trade `isolated`, which must be configured
+/// explicitly with `OKX_TD_MODE`.
+fn trade_mode_for_level(level: u8) -> TradeMode {
+    match level {
+        1 => TradeMode::Cash,
+        _ => TradeMode::Cross,
+    }
+}
+
+/// Order body for `/api/v5/trade/order`. In long/short (hedge) mode the order
+/// is scoped by `posSide` and `reduceOnly` is not accepted. A broker `tag`
+/// is sent where configured.
+fn order_body(
+    symbol: &str,
+    request: &OrderRequest,
+    trade_mode: TradeMode,
+    hedge: bool,
+    tag: Option<&str>,
+) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "instId": symbol,
+        "tdMode": trade_mode.as_str(),
+        "side": match request.side {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        },
+        "ordType": match (request.order_type, request.time_in_force) {
+            (OrderType::Limit, TimeInForce::Gtc | TimeInForce::Gtd { .. }) => "limit",
+            (OrderType::Limit, TimeInForce::Ioc) => "ioc",
+            (OrderType::Limit, TimeInForce::PostOnly) => "post_only",
+            (OrderType::Market, _) => "market",
+        },
+        "sz": request.quantity.to_string(),
+        "px": request.price.map(|p| p.to_string()),
+        "clOrdId": request.client_order_id,
+    });
+
+    if hedge {
+        body["posSide"] = match position_side(request.side, request.reduce_only) {
+            Side::Buy => "long",
+            Side::Sell => "short",
+        }
+        .into();
+    } else {
+        body["reduceOnly"] = request.reduce_only.into();
+    }
+    if let Some(tag) = tag {
+        body["tag"] = tag.into();
+    }
+
+    body
+}
+
+/// Order body for `/api/v5/trade/order-algo`. The price variance is sent as
+/// a ratio, and `posSide`, `reduceOnly` and `tag` follow `order_body`.
+fn algo_order_body(
+    symbol: &str,
+    request: &AlgoOrderRequest,
+    trade_mode: TradeMode,
+    hedge: bool,
+    tag: Option<&str>,
+) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "instId": symbol,
+        "tdMode": trade_mode.as_str(),
+        "side": match request.side {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        },
+        "ordType": match request.kind {
+            AlgoKind::Twap => "twap",
+            AlgoKind::Iceberg => "iceberg",
+        },
+        "sz": request.quantity.to_string(),
+        "szLimit": request.slice_quantity.to_string(),
+        "pxLimit": request.price_limit.to_string(),
+        "pxVar": Decimal::try_from(request.price_variance_bps / 10000.0).unwrap_or_default().normalize().to_string(),
+        "algoClOrdId": request.client_order_id,
+    });
+
+    if request.kind == AlgoKind::Twap {
+        body["timeInterval"] = request.interval_secs.to_string().into();
+    }
+    if hedge {
+        body["posSide"] = match position_side(request.side, request.reduce_only) {
+            Side::Buy => "long",
+            Side::Sell => "short",
+        }
+        .into();
+    } else {
+        body["reduceOnly"] = request.reduce_only.into();
+    }
+    if let Some(tag) = tag {
+        body["tag"] = tag.into();
+    }
+
+    body
+}
+
+/// Body for a conditional order on `/api/v5/trade/order-algo` that closes at
+/// market once the stop-loss trigger is reached. `tdMode`, `posSide`,
+/// `reduceOnly` and `tag` follow `algo_order_body`.
+fn stop_order_body(
+    symbol: &str,
+    request: &StopOrderRequest,
+    trade_mode: TradeMode,
+    hedge: bool,
+    tag: Option<&str>,
+) -> serde_json::Value {
+    let mut body = serde_json::json!({
+        "instId": symbol,
+        "tdMode": trade_mode.as_str(),
+        "side": match request.side {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        },
+        "ordType": "conditional",
+        "sz": request.quantity.to_string(),
+        "slTriggerPx": request.stop_price.to_string(),
+        "slOrdPx": "-1",
+        "algoClOrdId": request.client_order_id,
+    });
+
+    if hedge {
+        body["posSide"] = match position_side(request.side, true) {
+            Side::Buy => "long",
+            Side::Sell => "short",
+        }
+        .into();
+    } else {
+        body["reduceOnly"] = true.into();
+    }
+    if let Some(tag) = tag {
+        body["tag"] = tag.into();
+    }
+
+    body
+}
+
+/// A stop order as just placed or amended, resting unfilled
+fn stop_order_response(algo_id: String, symbol: String, request: &StopOrderRequest) -> OrderResponse {
+    OrderResponse {
+        exchange_order_id: algo_id,
+        client_order_id: request.client_order_id.clone(),
+        symbol,
+        side: request.side,
+        order_type: OrderType::Market,
+        price: Some(request.stop_price),
+        quantity: request.quantity,
+        filled_quantity: Decimal::ZERO,
+        avg_fill_price: None,
+        status: OrderStatus::Open,
+        timestamp: chrono::Utc::now().timestamp_millis(),
     }
 }
 
+/// Body for `/api/v5/trade/cancel-batch-orders`, at most `CANCEL_BATCH_SIZE` orders
+fn cancel_batch_body(symbol: &str, order_ids: &[String]) -> serde_json::Value {
+    order_ids
+        .iter()
+        .map(|order_id| serde_json::json!({ "instId": symbol, "ordId": order_id }))
+        .collect()
+}
+
 #[derive(Debug, Deserialize)]
 struct OkxResponse<T> {
     code: String,
@@ -63,6 +524,7 @@ struct OkxOrderData {
     ord_type: String,
     px: String,
     sz: String,
+    /// Accumulated fill size
     #[serde(rename = "fillSz")]
     fill_sz: Option<String>,
     #[serde(rename = "avgPx")]
@@ -72,42 +534,71 @@ struct OkxOrderData {
     u_time: String,
 }
 
+fn order_response(order: OkxOrderData) -> OrderResponse {
+    OrderResponse {
+        exchange_order_id: order.ord_id,
+        client_order_id: order.cl_ord_id,
+        symbol: order.inst_id,
+        side: match order.side.as_str() {
+            "buy" => Side::Buy,
+            _ => Side::Sell,
+        },
+        order_type: match order.ord_type.as_str() {
+            "limit" => OrderType::Limit,
+            _ => OrderType::Market,
+        },
+        price: order.px.parse().ok(),
+        quantity: order.sz.parse().unwrap_or_default(),
+        filled_quantity: order.fill_sz.and_then(|s| s.parse().ok()).unwrap_or_default(),
+        avg_fill_price: order.avg_px.and_then(|s| s.parse().ok()),
+        status: parse_okx_status(&order.state),
+        timestamp: epoch_millis(order.u_time.parse().unwrap_or(0)),
+    }
+}
+
 #[async_trait]
 impl ExchangeAdapter for OkxAdapter {
     fn id(&self) -> &str {
         "okx"
     }
 
+    // Perpetual swaps are suffixed with -SWAP
+    fn to_native_symbol(&self, base: &str, quote: &str) -> String {
+        format!("{}-{}-SWAP", base, quote)
+    }
+
+    fn to_canonical_symbol(&self, native: &str) -> Option<String> {
+        canonical_from_separated(native.strip_suffix("-SWAP")?, '-')
+    }
+
+    /// `-USD-SWAP` instruments are coin-margined, 100 USD a contract for BTC
+    /// and 10 USD for the rest; their `ctVal` says so where it is read
+    fn contract_spec(&self, symbol: &str) -> ContractSpec {
+        match self.native_symbol(symbol).strip_suffix("-USD-SWAP") {
+            Some("BTC") => ContractSpec::inverse(Decimal::ONE_HUNDRED),
+            Some(_) => ContractSpec::inverse(Decimal::TEN),
+            None => ContractSpec::default(),
+        }
+    }
+
     async fn place_order(
         &self,
         credentials: &Credentials,
         request: &OrderRequest,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(&request.symbol);
         let timestamp = Self::timestamp_iso();
         let path = "/api/v5/trade/order";
-        
-        let body = serde_json::json!({
-            "instId": request.symbol,
-            "tdMode": "cross",
-            "side": match request.side {
-                Side::Buy => "buy",
-                Side::Sell => "sell",
-            },
-            "ordType": match request.order_type {
-                OrderType::Limit => "limit",
-                OrderType::Market => "market",
-            },
-            "sz": request.quantity.to_string(),
-            "px": request.price.map(|p| p.to_string()),
-            "clOrdId": request.client_order_id,
-            "reduceOnly": request.reduce_only,
-        }).to_string();
 
-        let signature = self.sign(&credentials.api_secret, &timestamp, "POST", path, &body);
+        let account = self.account_settings(credentials).await?;
+        let tag = self.config.order_tag.as_deref();
+        let body = order_body(&symbol, request, self.trade_mode(account), account.hedge, tag).to_string();
+
+        let signature = sign(&credentials.api_secret, &timestamp, "POST", path, &body);
 
         let passphrase = credentials.passphrase.as_deref().unwrap_or("");
         
-        debug!("Placing OKX order: {}", request.symbol);
+        debug!("Placing OKX order: {}", symbol);
 
         let url = format!("{}{}", self.config.rest_url, path);
         let response = self.client
@@ -118,7 +609,7 @@ impl ExchangeAdapter for OkxAdapter {
             .header("OK-ACCESS-PASSPHRASE", passphrase)
             .header("Content-Type", "application/json")
             .body(body)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await
             .context("Failed to send order request")?;
 
@@ -126,25 +617,43 @@ impl ExchangeAdapter for OkxAdapter {
         let body = response.text().await?;
 
         if !status.is_success() {
+            check_unavailable(self.id(), status, &body)?;
+            // Authentication failures, an expired timestamp among them, come with a 401
+            if let Ok(resp) = parse_json::<OkxResponse<serde_json::Value>>(&body) {
+                self.check_expired_code(&resp.code, &body)?;
+            }
             anyhow::bail!("OKX order failed: {} - {}", status, body);
         }
 
-        let resp: OkxResponse<OkxOrderData> = serde_json::from_str(&body)
+        // Rejections carry only an acknowledgement, so the code comes first
+        let resp: OkxResponse<serde_json::Value> = parse_json(&body)
             .context("Failed to parse order response")?;
 
         if resp.code != "0" {
+            self.check_maintenance_code(&resp.code, &body)?;
+            self.check_expired_code(&resp.code, &body)?;
+            let s_code = resp.data.first().and_then(|ack| ack["sCode"].as_str());
+            if self.config.adopt_duplicate_orders && s_code == Some(DUPLICATE_CLIENT_ORDER_ID) {
+                // An earlier attempt of this placement got through
+                info!("OKX order {} already exists, adopting it", request.client_order_id);
+                return Ok(self
+                    .query_order(credentials, &symbol, "clOrdId", &request.client_order_id)
+                    .await?
+                    .canonical(self));
+            }
             anyhow::bail!("OKX order error: {} - {}", resp.code, resp.msg);
         }
 
-        let order = resp.data.into_iter().next()
-            .ok_or_else(|| anyhow::anyhow!("No order data in response"))?;
+        let order: OkxOrderData = resp.data.into_iter().next()
+            .ok_or_else(|| anyhow::anyhow!("No order data in response"))
+            .and_then(|order| serde_json::from_value(order).context("Failed to parse order response"))?;
 
         info!("OKX order placed: {} state={}", order.ord_id, order.state);
 
         Ok(OrderResponse {
             exchange_order_id: order.ord_id,
             client_order_id: order.cl_ord_id,
-            symbol: order.inst_id,
+            symbol: self.canonical_symbol(&order.inst_id),
             side: match order.side.as_str() {
                 "buy" => Side::Buy,
                 _ => Side::Sell,
@@ -158,7 +667,7 @@ impl ExchangeAdapter for OkxAdapter {
             filled_quantity: order.fill_sz.and_then(|s| s.parse().ok()).unwrap_or_default(),
             avg_fill_price: order.avg_px.and_then(|s| s.parse().ok()),
             status: parse_okx_status(&order.state),
-            timestamp: order.u_time.parse().unwrap_or(0),
+            timestamp: epoch_millis(order.u_time.parse().unwrap_or(0)),
         })
     }
 
@@ -168,6 +677,7 @@ impl ExchangeAdapter for OkxAdapter {
         symbol: &str,
         order_id: &str,
     ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
         let timestamp = Self::timestamp_iso();
         let path = "/api/v5/trade/cancel-order";
         
@@ -176,7 +686,7 @@ impl ExchangeAdapter for OkxAdapter {
             "ordId": order_id,
         }).to_string();
 
-        let signature = self.sign(&credentials.api_secret, &timestamp, "POST", path, &body);
+        let signature = sign(&credentials.api_secret, &timestamp, "POST", path, &body);
         let passphrase = credentials.passphrase.as_deref().unwrap_or("");
 
         let url = format!("{}{}", self.config.rest_url, path);
@@ -188,109 +698,664 @@ impl ExchangeAdapter for OkxAdapter {
             .header("OK-ACCESS-PASSPHRASE", passphrase)
             .header("Content-Type", "application/json")
             .body(body)
-            .send()
+            .send_traced(self.config.log_raw_http)
             .await?;
 
+        let status = response.status();
         let body = response.text().await?;
-        let resp: OkxResponse<OkxOrderData> = serde_json::from_str(&body)?;
+        check_unavailable(self.id(), status, &body)?;
 
-        let order = resp.data.into_iter().next()
-            .ok_or_else(|| anyhow::anyhow!("No order data"))?;
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct CancelAck {
+            s_code: String,
+            s_msg: String,
+        }
 
-        Ok(OrderResponse {
-            exchange_order_id: order.ord_id,
-            client_order_id: order.cl_ord_id,
-            symbol: order.inst_id,
-            side: match order.side.as_str() {
-                "buy" => Side::Buy,
-                _ => Side::Sell,
-            },
-            order_type: OrderType::Limit,
-            price: order.px.parse().ok(),
-            quantity: order.sz.parse().unwrap_or_default(),
-            filled_quantity: order.fill_sz.and_then(|s| s.parse().ok()).unwrap_or_default(),
-            avg_fill_price: order.avg_px.and_then(|s| s.parse().ok()),
-            status: OrderStatus::Cancelled,
-            timestamp: order.u_time.parse().unwrap_or(0),
-        })
+        let resp: OkxResponse<CancelAck> = parse_json(&body)?;
+        self.check_maintenance_code(&resp.code, &body)?;
+        let ack = resp.data.into_iter().next()
+            .ok_or_else(|| anyhow::anyhow!("OKX cancel error: {} - {}", resp.code, resp.msg))?;
+        let accepted = ack.s_code == "0";
+        if !accepted && !ORDER_ALREADY_CLOSED.contains(&ack.s_code.as_str()) {
+            anyhow::bail!("OKX cancel failed: {} - {}", ack.s_code, ack.s_msg);
+        }
+        if !accepted {
+            // Lost the race with a fill or an earlier cancel
+            debug!("OKX order {} is no longer open: {}", order_id, ack.s_msg);
+        }
+
+        // The acknowledgement only carries ids, so the order is read back for
+        // its fills. An accepted cancel may not have been processed yet.
+        let mut order = self.get_order(credentials, &symbol, order_id).await?;
+        if accepted && !order.status.is_terminal() {
+            order.status = OrderStatus::Cancelled;
+        }
+        Ok(order)
     }
 
-    async fn get_order(
+    async fn cancel_orders_batch(
         &self,
         credentials: &Credentials,
         symbol: &str,
-        order_id: &str,
-    ) -> Result<OrderResponse> {
-        let timestamp = Self::timestamp_iso();
-        let path = format!("/api/v5/trade/order?instId={}&ordId={}", symbol, order_id);
-        
-        let signature = self.sign(&credentials.api_secret, &timestamp, "GET", &path, "");
-        let passphrase = credentials.passphrase.as_deref().unwrap_or("");
+        order_ids: &[String],
+    ) -> Result<()> {
+        let symbol = self.native_symbol(symbol);
+        let mut failed = Vec::new();
 
-        let url = format!("{}{}", self.config.rest_url, path);
-        let response = self.client
-            .get(&url)
-            .header("OK-ACCESS-KEY", &credentials.api_key)
-            .header("OK-ACCESS-SIGN", &signature)
-            .header("OK-ACCESS-TIMESTAMP", &timestamp)
-            .header("OK-ACCESS-PASSPHRASE", passphrase)
-            .send()
-            .await?;
+        for batch in order_ids.chunks(CANCEL_BATCH_SIZE) {
+            let body = cancel_batch_body(&symbol, batch).to_string();
+            let body = self.post_signed(credentials, "/api/v5/trade/cancel-batch-orders", body).await?;
 
-        let body = response.text().await?;
-        let resp: OkxResponse<OkxOrderData> = serde_json::from_str(&body)?;
+            #[derive(Deserialize)]
+            #[serde(rename_all = "camelCase")]
+            struct CancelAck {
+                ord_id: String,
+                s_code: String,
+                s_msg: String,
+            }
 
-        let order = resp.data.into_iter().next()
-            .ok_or_else(|| anyhow::anyhow!("No order data"))?;
+            // "1" and "2" mean all or some of the batch failed, with each
+            // order's outcome in its own acknowledgement
+            let resp: OkxResponse<CancelAck> = parse_json(&body)?;
+            self.check_maintenance_code(&resp.code, &body)?;
+            if resp.data.is_empty() && resp.code != "0" {
+                anyhow::bail!("OKX batch cancel error: {} - {}", resp.code, resp.msg);
+            }
+            for ack in resp.data {
+                if ack.s_code != "0" && !ORDER_ALREADY_CLOSED.contains(&ack.s_code.as_str()) {
+                    failed.push(format!("{}: {} - {}", ack.ord_id, ack.s_code, ack.s_msg));
+                }
+            }
+        }
 
-        Ok(OrderResponse {
-            exchange_order_id: order.ord_id,
-            client_order_id: order.cl_ord_id,
-            symbol: order.inst_id,
-            side: match order.side.as_str() {
-                "buy" => Side::Buy,
-                _ => Side::Sell,
-            },
-            order_type: match order.ord_type.as_str() {
-                "limit" => OrderType::Limit,
-                _ => OrderType::Market,
-            },
-            price: order.px.parse().ok(),
-            quantity: order.sz.parse().unwrap_or_default(),
-            filled_quantity: order.fill_sz.and_then(|s| s.parse().ok()).unwrap_or_default(),
-            avg_fill_price: order.avg_px.and_then(|s| s.parse().ok()),
-            status: parse_okx_status(&order.state),
-            timestamp: order.u_time.parse().unwrap_or(0),
-        })
+        if !failed.is_empty() {
+            anyhow::bail!(
+                "OKX batch cancel failed for {} of {} orders: {}",
+                failed.len(),
+                order_ids.len(),
+                failed.join("; ")
+            );
+        }
+        Ok(())
     }
 
-    async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
-        let url = format!("{}/api/v5/market/ticker?instId={}", self.config.rest_url, symbol);
-        
-        let response = self.client.get(&url).send().await?;
-        let body = response.text().await?;
-        
-        #[derive(Deserialize)]
-        struct Ticker {
-            #[serde(rename = "bidPx")]
-            bid_px: String,
-            #[serde(rename = "askPx")]
-            ask_px: String,
+    // OKX's mass-cancel only covers options, so swap orders are listed and
+    // cancelled in batches, a page at a time
+    async fn cancel_all_orders(&self, credentials: &Credentials, symbol: &str) -> Result<()> {
+        let symbol = self.native_symbol(symbol);
+        loop {
+            let order_ids: Vec<String> = self
+                .pending_orders(credentials, &symbol)
+                .await?
+                .into_iter()
+                .map(|order| order.exchange_order_id)
+                .collect();
+            if !order_ids.is_empty() {
+                info!("Cancelling {} open OKX orders on {}", order_ids.len(), symbol);
+                self.cancel_orders_batch(credentials, &symbol, &order_ids).await?;
+            }
+            if order_ids.len() < PENDING_PAGE_SIZE {
+                return Ok(());
+            }
         }
-        
-        let resp: OkxResponse<Ticker> = serde_json::from_str(&body)?;
-        let ticker = resp.data.into_iter().next()
-            .ok_or_else(|| anyhow::anyhow!("No ticker data"))?;
+    }
 
-        Ok((
+    async fn get_open_orders(&self, credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        let orders = self.pending_orders(credentials, &self.native_symbol(symbol)).await?;
+        Ok(orders.into_iter().map(|order| order.canonical(self)).collect())
+    }
+
+    async fn get_order(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        Ok(self
+            .query_order(credentials, &self.native_symbol(symbol), "ordId", order_id)
+            .await?
+            .canonical(self))
+    }
+
+    // TWAP and iceberg orders are both run by OKX's algo engine
+    fn supports_algo_order(&self, _kind: AlgoKind) -> bool {
+        true
+    }
+
+    // TWAP children are at least 10 s apart
+    fn min_algo_interval_secs(&self, kind: AlgoKind) -> u64 {
+        match kind {
+            AlgoKind::Twap => 10,
+            AlgoKind::Iceberg => 1,
+        }
+    }
+
+    async fn place_algo_order(
+        &self,
+        credentials: &Credentials,
+        request: &AlgoOrderRequest,
+    ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(&request.symbol);
+        let account = self.account_settings(credentials).await?;
+        let tag = self.config.order_tag.as_deref();
+        let body = algo_order_body(&symbol, request, self.trade_mode(account), account.hedge, tag).to_string();
+
+        debug!("Placing OKX {:?} order: {}", request.kind, symbol);
+        let algo_id = self.post_algo(credentials, "/api/v5/trade/order-algo", body, "algo order").await?;
+
+        info!("OKX {:?} order placed: {}", request.kind, algo_id);
+
+        Ok(OrderResponse {
+            exchange_order_id: algo_id,
+            client_order_id: request.client_order_id.clone(),
+            symbol: self.canonical_symbol(&symbol),
+            side: request.side,
+            order_type: OrderType::Limit,
+            price: Some(request.price_limit),
+            quantity: request.quantity,
+            filled_quantity: Decimal::ZERO,
+            avg_fill_price: None,
+            status: OrderStatus::Open,
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        })
+    }
+
+    async fn get_algo_order(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        algo_id: &str,
+    ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(symbol);
+        let body = self
+            .get_signed(credentials, &format!("/api/v5/trade/order-algo?algoId={}", algo_id))
+            .await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct AlgoOrder {
+            algo_id: String,
+            #[serde(default)]
+            algo_cl_ord_id: String,
+            side: String,
+            sz: String,
+            #[serde(default)]
+            px_limit: String,
+            state: String,
+            #[serde(default)]
+            u_time: String,
+        }
+
+        let resp: OkxResponse<AlgoOrder> = parse_json(&body).context("Failed to parse OKX algo order")?;
+        if resp.code != "0" {
+            self.check_maintenance_code(&resp.code, &body)?;
+            anyhow::bail!("OKX algo order error: {} - {}", resp.code, resp.msg);
+        }
+        let order = resp.data.into_iter().next()
+            .ok_or_else(|| anyhow::anyhow!("No algo order data"))?;
+        let (filled_quantity, avg_fill_price) = self.algo_child_fills(credentials, &symbol, algo_id).await?;
+
+        Ok(OrderResponse {
+            exchange_order_id: order.algo_id,
+            client_order_id: order.algo_cl_ord_id,
+            symbol: self.canonical_symbol(&symbol),
+            side: match order.side.as_str() {
+                "buy" => Side::Buy,
+                _ => Side::Sell,
+            },
+            order_type: OrderType::Limit,
+            price: order.px_limit.parse().ok(),
+            quantity: order.sz.parse().unwrap_or_default(),
+            filled_quantity,
+            avg_fill_price,
+            status: parse_okx_algo_status(&order.state),
+            timestamp: epoch_millis(order.u_time.parse().unwrap_or(0)),
+        })
+    }
+
+    async fn cancel_algo_order(&self, credentials: &Credentials, symbol: &str, algo_id: &str) -> Result<()> {
+        let symbol = self.native_symbol(symbol);
+        let body = serde_json::json!([{ "algoId": algo_id, "instId": symbol }]).to_string();
+        let body = self.post_signed(credentials, "/api/v5/trade/cancel-algos", body).await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Cancelled {
+            s_code: String,
+            s_msg: String,
+        }
+
+        let resp: OkxResponse<Cancelled> = parse_json(&body).context("Failed to parse OKX algo cancel response")?;
+        if resp.code != "0" {
+            self.check_maintenance_code(&resp.code, &body)?;
+            match resp.data.first() {
+                Some(cancelled) => anyhow::bail!("OKX algo cancel error: {} - {}", cancelled.s_code, cancelled.s_msg),
+                None => anyhow::bail!("OKX algo cancel error: {} - {}", resp.code, resp.msg),
+            }
+        }
+        Ok(())
+    }
+
+    // Conditional algo orders with a market stop-loss leg
+    fn supports_stop_order(&self) -> bool {
+        true
+    }
+
+    async fn place_stop_order(
+        &self,
+        credentials: &Credentials,
+        request: &StopOrderRequest,
+    ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(&request.symbol);
+        let account = self.account_settings(credentials).await?;
+        let tag = self.config.order_tag.as_deref();
+        let body = stop_order_body(&symbol, request, self.trade_mode(account), account.hedge, tag).to_string();
+
+        let algo_id = self.post_algo(credentials, "/api/v5/trade/order-algo", body, "stop order").await?;
+        debug!("OKX stop order placed: {} @ {}", algo_id, request.stop_price);
+        Ok(stop_order_response(algo_id, symbol, request).canonical(self))
+    }
+
+    // Amended in place, so the algo id is kept
+    async fn amend_stop_order(
+        &self,
+        credentials: &Credentials,
+        order_id: &str,
+        request: &StopOrderRequest,
+    ) -> Result<OrderResponse> {
+        let symbol = self.native_symbol(&request.symbol);
+        let body = serde_json::json!({
+            "instId": symbol,
+            "algoId": order_id,
+            "newSlTriggerPx": request.stop_price.to_string(),
+            "newSlOrdPx": "-1",
+        })
+        .to_string();
+
+        let algo_id = self.post_algo(credentials, "/api/v5/trade/amend-algos", body, "stop amend").await?;
+        Ok(stop_order_response(algo_id, symbol, request).canonical(self))
+    }
+
+    async fn get_stop_order(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        self.get_algo_order(credentials, symbol, order_id).await
+    }
+
+    async fn cancel_stop_order(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        self.cancel_algo_order(credentials, symbol, order_id).await?;
+        self.get_algo_order(credentials, symbol, order_id).await
+    }
+
+    async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
+        let symbol = self.native_symbol(symbol);
+        let url = format!("{}/api/v5/market/ticker?instId={}", self.config.rest_url, symbol);
+        
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+        
+        #[derive(Deserialize)]
+        struct Ticker {
+            #[serde(rename = "bidPx")]
+            bid_px: String,
+            #[serde(rename = "askPx")]
+            ask_px: String,
+        }
+        
+        let resp: OkxResponse<Ticker> = parse_json(&body)?;
+        let ticker = resp.data.into_iter().next()
+            .ok_or_else(|| anyhow::anyhow!("No ticker data"))?;
+
+        Ok((
             ticker.bid_px.parse()?,
             ticker.ask_px.parse()?,
         ))
     }
 
+    async fn get_reference_price(&self, symbol: &str, source: ReferencePriceSource) -> Result<Decimal> {
+        if source == ReferencePriceSource::Mid {
+            return mid_price(self, symbol).await;
+        }
+        let symbol = self.native_symbol(symbol);
+        let url = match source {
+            ReferencePriceSource::Last => {
+                format!("{}/api/v5/market/ticker?instId={}", self.config.rest_url, symbol)
+            }
+            ReferencePriceSource::Mark => format!(
+                "{}/api/v5/public/mark-price?instType=SWAP&instId={}",
+                self.config.rest_url, symbol
+            ),
+            // Indexes are named after the underlying, without the -SWAP suffix
+            _ => format!(
+                "{}/api/v5/market/index-tickers?instId={}",
+                self.config.rest_url,
+                symbol.trim_end_matches("-SWAP")
+            ),
+        };
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Prices {
+            last: Option<String>,
+            mark_px: Option<String>,
+            idx_px: Option<String>,
+        }
+
+        let resp: OkxResponse<Prices> = parse_json(&body)?;
+        if resp.code != "0" {
+            anyhow::bail!("OKX error: {} - {}", resp.code, resp.msg);
+        }
+        let prices = resp.data.into_iter().next()
+            .ok_or_else(|| anyhow::anyhow!("No {} price for {}", source, symbol))?;
+        let price = match source {
+            ReferencePriceSource::Last => prices.last,
+            ReferencePriceSource::Mark => prices.mark_px,
+            _ => prices.idx_px,
+        };
+        price
+            .ok_or_else(|| anyhow::anyhow!("No {} price for {}", source, symbol))?
+            .parse()
+            .context("Invalid price")
+    }
+
+    async fn get_server_time(&self) -> Result<i64> {
+        let url = format!("{}/api/v5/public/time", self.config.rest_url);
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let status = response.status();
+        let body = response.text().await?;
+        check_unavailable(self.id(), status, &body)?;
+
+        #[derive(Deserialize)]
+        struct ServerTime {
+            ts: String,
+        }
+
+        let resp: OkxResponse<ServerTime> = parse_json(&body)?;
+        let time = resp.data.into_iter().next()
+            .ok_or_else(|| anyhow::anyhow!("No server time"))?;
+        Ok(time.ts.parse()?)
+    }
+
+    async fn get_symbol_info(&self, symbol: &str) -> Result<SymbolInfo> {
+        let symbol = self.native_symbol(symbol);
+        let url = format!(
+            "{}/api/v5/public/instruments?instType=SWAP&instId={}",
+            self.config.rest_url, symbol
+        );
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Instrument {
+            state: String,
+            /// Coins per contract, USD on inverse contracts
+            ct_val: Option<String>,
+            lot_sz: Option<String>,
+            tick_sz: Option<String>,
+        }
+
+        let resp: OkxResponse<Instrument> = parse_json(&body)?;
+        if resp.code != "0" && resp.code != "51001" {
+            anyhow::bail!("OKX instruments error: {} - {}", resp.code, resp.msg);
+        }
+
+        // 51001 is "instrument ID does not exist"
+        let instrument = resp.data.first();
+        let parse = |value: Option<&String>| value.and_then(|v| v.parse::<Decimal>().ok());
+        Ok(SymbolInfo {
+            status: match instrument.map(|i| i.state.as_str()) {
+                Some("live") => SymbolStatus::Trading,
+                Some("preopen") => SymbolStatus::PreLaunch,
+                Some("expired") | None => SymbolStatus::Delisted,
+                // suspend, test
+                Some(_) => SymbolStatus::Maintenance,
+            },
+            symbol,
+            contract_size: parse(instrument.and_then(|i| i.ct_val.as_ref())).unwrap_or(Decimal::ONE),
+            quantity_step: parse(instrument.and_then(|i| i.lot_sz.as_ref())).unwrap_or_default(),
+            tick_size: parse(instrument.and_then(|i| i.tick_sz.as_ref())).unwrap_or_default(),
+            // Minimums are set in contracts (minSz), which the step covers
+            min_notional: Decimal::ZERO,
+        })
+    }
+
+    async fn get_order_book(&self, symbol: &str, depth: usize) -> Result<OrderBook> {
+        let symbol = self.native_symbol(symbol);
+        let url = format!(
+            "{}/api/v5/market/books?instId={}&sz={}",
+            self.config.rest_url,
+            symbol,
+            depth.clamp(1, 400)
+        );
+
+        let response = self.client.get(&url).send_traced(self.config.log_raw_http).await?;
+        let body = response.text().await?;
+
+        // Levels are [price, contracts, 0, order count]
+        #[derive(Deserialize)]
+        struct Depth {
+            bids: Vec<Vec<serde_json::Value>>,
+            asks: Vec<Vec<serde_json::Value>>,
+            ts: String,
+        }
+
+        let resp: OkxResponse<Depth> = parse_json(&body)?;
+        if resp.code != "0" {
+            anyhow::bail!("OKX order book error: {} - {}", resp.code, resp.msg);
+        }
+        let book = resp.data.into_iter().next()
+            .ok_or_else(|| anyhow::anyhow!("No order book data"))?;
+
+        Ok(OrderBook::from_levels(
+            parse_level_rows(&book.bids)?,
+            parse_level_rows(&book.asks)?,
+            depth,
+            book.ts.parse()?,
+        ))
+    }
+
     fn is_connected(&self) -> bool {
         true
     }
+
+    async fn get_position_mode(&self, credentials: &Credentials) -> Result<bool> {
+        Ok(self.fetch_account_settings(credentials).await?.hedge)
+    }
+
+    async fn get_leverage(&self, credentials: &Credentials, symbol: &str) -> Result<LeverageInfo> {
+        let symbol = self.native_symbol(symbol);
+        // Leverage is kept per margin mode, so read the one orders are sent with
+        let margin_mode = match self.trade_mode(self.account_settings(credentials).await?) {
+            TradeMode::Cross => MarginMode::Cross,
+            TradeMode::Isolated => MarginMode::Isolated,
+            TradeMode::Cash => anyhow::bail!("OKX cash trading has no leverage"),
+        };
+        let mgn_mode = match margin_mode {
+            MarginMode::Cross => "cross",
+            MarginMode::Isolated => "isolated",
+        };
+
+        let timestamp = Self::timestamp_iso();
+        let path = format!("/api/v5/account/leverage-info?instId={}&mgnMode={}", symbol, mgn_mode);
+        let signature = sign(&credentials.api_secret, &timestamp, "GET", &path, "");
+        let passphrase = credentials.passphrase.as_deref().unwrap_or("");
+
+        let url = format!("{}{}", self.config.rest_url, path);
+        let response = self.client
+            .get(&url)
+            .header("OK-ACCESS-KEY", &credentials.api_key)
+            .header("OK-ACCESS-SIGN", &signature)
+            .header("OK-ACCESS-TIMESTAMP", &timestamp)
+            .header("OK-ACCESS-PASSPHRASE", passphrase)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        #[derive(Deserialize)]
+        struct Leverage {
+            lever: String,
+        }
+
+        let body = response.text().await?;
+        let resp: OkxResponse<Leverage> = parse_json(&body)?;
+        if resp.code != "0" {
+            anyhow::bail!("OKX leverage error: {} - {}", resp.code, resp.msg);
+        }
+
+        // Isolated hedge-mode positions carry one entry per side, with the
+        // same leverage unless set apart on purpose
+        let leverage = resp
+            .data
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("No leverage for {}", symbol))?;
+        Ok(LeverageInfo {
+            leverage: leverage.lever.parse().context("Invalid OKX leverage")?,
+            margin_mode,
+        })
+    }
+
+    async fn get_positions(&self, credentials: &Credentials) -> Result<Vec<Position>> {
+        let timestamp = Self::timestamp_iso();
+        let path = "/api/v5/account/positions?instType=SWAP";
+        let signature = sign(&credentials.api_secret, &timestamp, "GET", path, "");
+        let passphrase = credentials.passphrase.as_deref().unwrap_or("");
+
+        let url = format!("{}{}", self.config.rest_url, path);
+        let response = self.client
+            .get(&url)
+            .header("OK-ACCESS-KEY", &credentials.api_key)
+            .header("OK-ACCESS-SIGN", &signature)
+            .header("OK-ACCESS-TIMESTAMP", &timestamp)
+            .header("OK-ACCESS-PASSPHRASE", passphrase)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        // In net mode `pos` is signed; in hedge mode it is positive and
+        // `posSide` says which side it is
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct OpenPosition {
+            inst_id: String,
+            pos: String,
+            pos_side: String,
+            avg_px: String,
+        }
+
+        let body = response.text().await?;
+        let resp: OkxResponse<OpenPosition> = parse_json(&body)?;
+        if resp.code != "0" {
+            anyhow::bail!("OKX positions error: {} - {}", resp.code, resp.msg);
+        }
+
+        let mut open = Vec::new();
+        for position in resp.data {
+            let pos: Decimal = position.pos.parse().context("Invalid OKX position size")?;
+            if pos.is_zero() {
+                continue;
+            }
+            let long = match position.pos_side.as_str() {
+                "long" => true,
+                "short" => false,
+                _ => pos > Decimal::ZERO,
+            };
+            open.push(Position {
+                symbol: position.inst_id,
+                side: if long { Side::Buy } else { Side::Sell },
+                quantity: pos.abs(),
+                entry_price: position.avg_px.parse().context("Invalid OKX entry price")?,
+            });
+        }
+        Ok(open)
+    }
+
+    async fn set_position_mode(&self, credentials: &Credentials, hedge: bool) -> Result<()> {
+        let timestamp = Self::timestamp_iso();
+        let path = "/api/v5/account/set-position-mode";
+        let body = serde_json::json!({
+            "posMode": if hedge { "long_short_mode" } else { "net_mode" },
+        })
+        .to_string();
+
+        let signature = sign(&credentials.api_secret, &timestamp, "POST", path, &body);
+        let passphrase = credentials.passphrase.as_deref().unwrap_or("");
+
+        let url = format!("{}{}", self.config.rest_url, path);
+        let response = self.client
+            .post(&url)
+            .header("OK-ACCESS-KEY", &credentials.api_key)
+            .header("OK-ACCESS-SIGN", &signature)
+            .header("OK-ACCESS-TIMESTAMP", &timestamp)
+            .header("OK-ACCESS-PASSPHRASE", passphrase)
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let body = response.text().await?;
+        let resp: OkxResponse<serde_json::Value> = parse_json(&body)?;
+        if resp.code != "0" {
+            anyhow::bail!("OKX position mode error: {} - {}", resp.code, resp.msg);
+        }
+
+        info!("OKX position mode set to {}", if hedge { "long/short" } else { "net" });
+        if let Some(settings) = self.accounts.write().unwrap().get_mut(&credentials.api_key) {
+            settings.hedge = hedge;
+        }
+        Ok(())
+    }
+
+    fn supports_cancel_on_disconnect(&self) -> bool {
+        true
+    }
+
+    async fn set_cancel_on_disconnect(&self, credentials: &Credentials, timeout_secs: u64) -> Result<()> {
+        // The countdown runs 10 to 120 seconds; 0 stops it
+        anyhow::ensure!(
+            timeout_secs == 0 || (10..=120).contains(&timeout_secs),
+            "OKX cancel-all-after takes 10 to 120 seconds, got {}",
+            timeout_secs
+        );
+
+        let timestamp = Self::timestamp_iso();
+        let path = "/api/v5/trade/cancel-all-after";
+        let body = serde_json::json!({ "timeOut": timeout_secs.to_string() }).to_string();
+
+        let signature = sign(&credentials.api_secret, &timestamp, "POST", path, &body);
+        let passphrase = credentials.passphrase.as_deref().unwrap_or("");
+
+        let url = format!("{}{}", self.config.rest_url, path);
+        let response = self.client
+            .post(&url)
+            .header("OK-ACCESS-KEY", &credentials.api_key)
+            .header("OK-ACCESS-SIGN", &signature)
+            .header("OK-ACCESS-TIMESTAMP", &timestamp)
+            .header("OK-ACCESS-PASSPHRASE", passphrase)
+            .header("Content-Type", "application/json")
+            .body(body)
+            .send_traced(self.config.log_raw_http)
+            .await?;
+
+        let body = response.text().await?;
+        let resp: OkxResponse<serde_json::Value> = parse_json(&body)?;
+        if resp.code != "0" {
+            anyhow::bail!("OKX cancel-all-after error: {} - {}", resp.code, resp.msg);
+        }
+        Ok(())
+    }
 }
 
 fn parse_okx_status(status: &str) -> OrderStatus {
@@ -302,3 +1367,373 @@ fn parse_okx_status(status: &str) -> OrderStatus {
         _ => OrderStatus::Pending,
     }
 }
+
+/// Order status for an algo order `state`. An `effective` order has sent
+/// all its children, which may still have filled short.
+fn parse_okx_algo_status(state: &str) -> OrderStatus {
+    match state {
+        "partially_effective" => OrderStatus::Partial,
+        "effective" => OrderStatus::Filled,
+        "canceled" | "cancelled" => OrderStatus::Cancelled,
+        "order_failed" => OrderStatus::Rejected,
+        _ => OrderStatus::Open,
+    }
+}
+
+/// HMAC-SHA256 of `timestamp + method + path + body`, base64, with the
+/// timestamp in ISO 8601 milliseconds
+pub fn sign(secret: &str, timestamp: &str, method: &str, path: &str, body: &str) -> String {
+    let prehash = format!("{}{}{}{}", timestamp, method, path, body);
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes())
+        .expect("HMAC can take key of any size");
+    mac.update(prehash.as_bytes());
+    STANDARD.encode(mac.finalize().into_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{credentials, exchange_config, serve_http};
+    use crate::exchange::QuantityMode;
+    use rust_decimal_macros::dec;
+
+    fn request(side: Side, reduce_only: bool) -> OrderRequest {
+        OrderRequest {
+            client_order_id: "cs1".to_string(),
+            symbol: "BTC/USDT".to_string(),
+            side,
+            order_type: OrderType::Limit,
+            price: Some(dec!(100)),
+            quantity: dec!(2),
+            reduce_only,
+            time_in_force: TimeInForce::Gtc,
+            quantity_mode: QuantityMode::Base,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_usd_swaps_are_inverse() {
+        let adapter = OkxAdapter::new(exchange_config("okx", "http://127.0.0.1:1".to_string())).await.unwrap();
+        assert_eq!(adapter.contract_spec("BTC/USD"), ContractSpec::inverse(dec!(100)));
+        assert_eq!(adapter.contract_spec("ETH/USD"), ContractSpec::inverse(dec!(10)));
+        assert_eq!(adapter.contract_spec("BTC/USDT"), ContractSpec::default());
+    }
+
+    #[test]
+    fn test_trade_mode_follows_account_level() {
+        assert_eq!(trade_mode_for_level(1), TradeMode::Cash);
+        assert_eq!(trade_mode_for_level(2), TradeMode::Cross);
+        assert_eq!(trade_mode_for_level(3), TradeMode::Cross);
+        assert_eq!(trade_mode_for_level(4), TradeMode::Cross);
+    }
+
+    #[test]
+    fn test_net_mode_order_is_reduce_only_without_pos_side() {
+        let body = order_body("BTC-USDT-SWAP", &request(Side::Sell, true), TradeMode::Cross, false, None);
+
+        assert_eq!(body["tdMode"], "cross");
+        assert_eq!(body["reduceOnly"], true);
+        assert!(body.get("posSide").is_none());
+    }
+
+    #[test]
+    fn test_hedge_mode_order_carries_pos_side() {
+        let open_short = order_body("BTC-USDT-SWAP", &request(Side::Sell, false), TradeMode::Isolated, true, None);
+        let close_long = order_body("BTC-USDT-SWAP", &request(Side::Sell, true), TradeMode::Isolated, true, None);
+
+        assert_eq!(open_short["tdMode"], "isolated");
+        assert_eq!(open_short["posSide"], "short");
+        assert_eq!(close_long["posSide"], "long");
+        assert!(close_long.get("reduceOnly").is_none());
+    }
+
+    #[test]
+    fn test_broker_tag_is_sent_when_configured() {
+        let tagged = order_body("BTC-USDT-SWAP", &request(Side::Buy, false), TradeMode::Cross, false, Some("b8f2e1"));
+        let untagged = order_body("BTC-USDT-SWAP", &request(Side::Buy, false), TradeMode::Cross, false, None);
+        let algo = algo_order_body("BTC-USDT-SWAP", &algo_request(AlgoKind::Twap), TradeMode::Cross, false, Some("b8f2e1"));
+
+        assert_eq!(tagged["tag"], "b8f2e1");
+        assert!(untagged.get("tag").is_none());
+        assert_eq!(algo["tag"], "b8f2e1");
+    }
+
+    fn algo_request(kind: AlgoKind) -> AlgoOrderRequest {
+        AlgoOrderRequest {
+            client_order_id: "cs2".to_string(),
+            symbol: "BTC/USDT".to_string(),
+            side: Side::Buy,
+            quantity: dec!(10),
+            kind,
+            slice_quantity: dec!(2),
+            interval_secs: 30,
+            price_variance_bps: 5.0,
+            price_limit: dec!(100.5),
+            reduce_only: false,
+        }
+    }
+
+    #[test]
+    fn test_algo_order_body_carries_the_strategy() {
+        let twap = algo_order_body("BTC-USDT-SWAP", &algo_request(AlgoKind::Twap), TradeMode::Cross, false, None);
+
+        assert_eq!(twap["ordType"], "twap");
+        assert_eq!(twap["sz"], "10");
+        assert_eq!(twap["szLimit"], "2");
+        assert_eq!(twap["pxLimit"], "100.5");
+        assert_eq!(twap["pxVar"], "0.0005");
+        assert_eq!(twap["timeInterval"], "30");
+        assert_eq!(twap["algoClOrdId"], "cs2");
+        assert_eq!(twap["reduceOnly"], false);
+
+        // Icebergs have no interval, and hedge mode scopes them by posSide
+        let iceberg = algo_order_body("BTC-USDT-SWAP", &algo_request(AlgoKind::Iceberg), TradeMode::Cross, true, None);
+        assert_eq!(iceberg["ordType"], "iceberg");
+        assert!(iceberg.get("timeInterval").is_none());
+        assert_eq!(iceberg["posSide"], "long");
+        assert!(iceberg.get("reduceOnly").is_none());
+    }
+
+    #[test]
+    fn test_stop_order_body_closes_at_market_on_trigger() {
+        let request = StopOrderRequest {
+            client_order_id: "cs3".to_string(),
+            symbol: "BTC/USDT".to_string(),
+            side: Side::Sell,
+            quantity: dec!(4),
+            stop_price: dec!(97.5),
+        };
+
+        let net = stop_order_body("BTC-USDT-SWAP", &request, TradeMode::Cross, false, None);
+        assert_eq!(net["ordType"], "conditional");
+        assert_eq!(net["sz"], "4");
+        assert_eq!(net["slTriggerPx"], "97.5");
+        assert_eq!(net["slOrdPx"], "-1");
+        assert_eq!(net["reduceOnly"], true);
+
+        let hedge = stop_order_body("BTC-USDT-SWAP", &request, TradeMode::Cross, true, None);
+        assert_eq!(hedge["posSide"], "long");
+        assert!(hedge.get("reduceOnly").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_algo_order_is_placed_polled_and_cancelled() {
+        let (url, server) = serve_http(vec![
+            ("200 OK", r#"{"code":"0","msg":"","data":[{"acctLv":"2","posMode":"net_mode"}]}"#),
+            ("200 OK", r#"{"code":"0","msg":"","data":[{"algoId":"681","algoClOrdId":"cs2","sCode":"0","sMsg":""}]}"#),
+            (
+                "200 OK",
+                r#"{"code":"0","msg":"","data":[{"algoId":"681","algoClOrdId":"cs2","instId":"BTC-USDT-SWAP","side":"buy","sz":"10","pxLimit":"100.5","state":"partially_effective","uTime":"5"}]}"#,
+            ),
+            (
+                "200 OK",
+                r#"{"code":"0","msg":"","data":[{"ordId":"1","algoId":"681","accFillSz":"2","avgPx":"100"},{"ordId":"2","algoId":"","accFillSz":"7","avgPx":"99"},{"ordId":"3","algoId":"681","accFillSz":"2","avgPx":"100.2"}]}"#,
+            ),
+            ("200 OK", r#"{"code":"0","msg":"","data":[{"algoId":"681","sCode":"0","sMsg":""}]}"#),
+        ])
+        .await;
+        let adapter = OkxAdapter::new(crate::exchange::mock::exchange_config("okx", url)).await.unwrap();
+
+        let placed = adapter.place_algo_order(&credentials(), &algo_request(AlgoKind::Twap)).await.unwrap();
+        assert_eq!(placed.exchange_order_id, "681");
+        assert_eq!(placed.status, OrderStatus::Open);
+
+        // Only the algo order's own children count towards its fills
+        let order = adapter.get_algo_order(&credentials(), "BTC/USDT", "681").await.unwrap();
+        assert_eq!(order.status, OrderStatus::Partial);
+        assert_eq!(order.filled_quantity, dec!(4));
+        assert_eq!(order.avg_fill_price, Some(dec!(100.1)));
+
+        adapter.cancel_algo_order(&credentials(), "BTC/USDT", "681").await.unwrap();
+
+        let requests = server.await.unwrap();
+        assert!(requests[1].starts_with("POST /api/v5/trade/order-algo"), "{:?}", requests);
+        assert!(requests[2].starts_with("GET /api/v5/trade/order-algo?algoId=681"), "{:?}", requests);
+        assert!(
+            requests[3].starts_with("GET /api/v5/trade/orders-history?instType=SWAP&instId=BTC-USDT-SWAP"),
+            "{:?}",
+            requests
+        );
+        assert!(requests[4].starts_with("POST /api/v5/trade/cancel-algos"), "{:?}", requests);
+    }
+
+    #[test]
+    fn test_cancel_batch_body_lists_each_order() {
+        let order_ids = ["7".to_string(), "8".to_string()];
+
+        assert_eq!(
+            cancel_batch_body("BTC-USDT-SWAP", &order_ids),
+            serde_json::json!([
+                { "instId": "BTC-USDT-SWAP", "ordId": "7" },
+                { "instId": "BTC-USDT-SWAP", "ordId": "8" },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn test_batch_cancel_is_split_into_batches_of_twenty() {
+        let (url, server) = serve_http(vec![
+            ("200 OK", r#"{"code":"0","msg":"","data":[]}"#),
+            (
+                "200 OK",
+                r#"{"code":"2","msg":"","data":[{"ordId":"20","sCode":"51400","sMsg":"filled"},{"ordId":"21","sCode":"51410","sMsg":"pending settlement"}]}"#,
+            ),
+        ])
+        .await;
+        let adapter = OkxAdapter::new(ExchangeConfig {
+            id: "okx".to_string(),
+            rest_url: url,
+            ws_url: String::new(),
+            trade_ws_url: None,
+            testnet: false,
+            maker_fee_bps: 0.0,
+            taker_fee_bps: 0.0,
+            trade_mode: None,
+            user_agent: None,
+            proxy: None,
+            log_raw_http: false,
+            ws_orders: false,
+            order_tag: None,
+            client_order_id_prefix: None,
+            adopt_duplicate_orders: false,
+        })
+        .await
+        .unwrap();
+        let order_ids: Vec<String> = (0..22).map(|id| id.to_string()).collect();
+
+        let err = adapter
+            .cancel_orders_batch(&credentials(), "BTC/USDT", &order_ids)
+            .await
+            .unwrap_err();
+
+        // Only the order still settling failed; the filled one counts as cancelled
+        assert_eq!(
+            err.to_string(),
+            "OKX batch cancel failed for 1 of 22 orders: 21: 51410 - pending settlement"
+        );
+        let requests = server.await.unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests.iter().all(|r| r.starts_with("POST /api/v5/trade/cancel-batch-orders")));
+    }
+
+    #[tokio::test]
+    async fn test_duplicate_client_order_id_adopts_the_existing_order() {
+        const ACCOUNT: &str = r#"{"code":"0","msg":"","data":[{"acctLv":"2","posMode":"net_mode"}]}"#;
+        const DUPLICATE: &str = r#"{"code":"1","msg":"All operations failed","data":[{"clOrdId":"cs1","ordId":"","sCode":"51016","sMsg":"Duplicated clOrdId"}]}"#;
+        let (url, server) = serve_http(vec![
+            ("200 OK", ACCOUNT),
+            ("200 OK", DUPLICATE),
+            (
+                "200 OK",
+                r#"{"code":"0","msg":"","data":[{"ordId":"7","clOrdId":"cs1","instId":"BTC-USDT-SWAP","side":"buy","ordType":"limit","px":"100","sz":"2","fillSz":"1","avgPx":"100","state":"partially_filled","uTime":"2"}]}"#,
+            ),
+        ])
+        .await;
+        let adapter = OkxAdapter::new(ExchangeConfig {
+            adopt_duplicate_orders: true,
+            ..exchange_config("okx", url)
+        })
+        .await
+        .unwrap();
+
+        let order = adapter.place_order(&credentials(), &request(Side::Buy, false)).await.unwrap();
+        assert_eq!(order.exchange_order_id, "7");
+        assert_eq!(order.status, OrderStatus::Partial);
+        assert_eq!(order.filled_quantity, dec!(1));
+
+        let requests = server.await.unwrap();
+        assert!(requests[1].starts_with("POST /api/v5/trade/order"), "{:?}", requests);
+        assert!(
+            requests[2].starts_with("GET /api/v5/trade/order?instId=BTC-USDT-SWAP&clOrdId=cs1"),
+            "{:?}",
+            requests
+        );
+
+        // Left off, the rejection is an error
+        let (url, _server) = serve_http(vec![("200 OK", ACCOUNT), ("200 OK", DUPLICATE)]).await;
+        let adapter = OkxAdapter::new(exchange_config("okx", url)).await.unwrap();
+        let err = adapter.place_order(&credentials(), &request(Side::Buy, false)).await.unwrap_err();
+        assert!(err.to_string().contains("OKX order error: 1"), "{}", err);
+    }
+
+    #[tokio::test]
+    async fn test_cancelling_a_filled_order_reports_its_state() {
+        let filled = r#"{"code":"0","msg":"","data":[{"ordId":"7","clOrdId":"cs1","instId":"BTC-USDT-SWAP","side":"buy","ordType":"limit","px":"100","sz":"2","fillSz":"2","avgPx":"100","state":"filled","uTime":"2"}]}"#;
+        let (url, server) = serve_http(vec![
+            (
+                "200 OK",
+                r#"{"code":"1","msg":"All operations failed","data":[{"clOrdId":"","ordId":"7","sCode":"51400","sMsg":"Order cancellation failed as the order has been filled, canceled or does not exist"}]}"#,
+            ),
+            ("200 OK", filled),
+            (
+                "200 OK",
+                r#"{"code":"1","msg":"All operations failed","data":[{"clOrdId":"","ordId":"7","sCode":"51410","sMsg":"Order cancellation failed as the order is already under cancelling status or pending settlement"}]}"#,
+            ),
+        ])
+        .await;
+        let adapter = OkxAdapter::new(ExchangeConfig {
+            id: "okx".to_string(),
+            rest_url: url,
+            ws_url: String::new(),
+            trade_ws_url: None,
+            testnet: false,
+            maker_fee_bps: 0.0,
+            taker_fee_bps: 0.0,
+            trade_mode: None,
+            user_agent: None,
+            proxy: None,
+            log_raw_http: false,
+            ws_orders: false,
+            order_tag: None,
+            client_order_id_prefix: None,
+            adopt_duplicate_orders: false,
+        })
+        .await
+        .unwrap();
+
+        let order = adapter.cancel_order(&credentials(), "BTC-USDT-SWAP", "7").await.unwrap();
+        assert_eq!(order.status, OrderStatus::Filled);
+        assert_eq!(order.filled_quantity, dec!(2));
+
+        // Other rejections are still errors
+        let err = adapter.cancel_order(&credentials(), "BTC-USDT-SWAP", "7").await.unwrap_err();
+        assert!(err.to_string().contains("51410"), "{}", err);
+
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("POST /api/v5/trade/cancel-order"), "{:?}", requests);
+        assert!(requests[1].starts_with("GET /api/v5/trade/order?"), "{:?}", requests);
+    }
+
+    #[tokio::test]
+    async fn test_order_book_is_parsed_best_first() {
+        let (url, server) = serve_http(vec![(
+            "200 OK",
+            r#"{"code":"0","msg":"","data":[{"asks":[["41006.8","0.6","0","1"],["41007.1","2","0","3"]],"bids":[["41006.3","0.3","0","2"],["41005.9","1.5","0","1"]],"ts":"1629966436396"}]}"#,
+        )])
+        .await;
+        let adapter = OkxAdapter::new(crate::exchange::mock::exchange_config("okx", url)).await.unwrap();
+
+        let book = adapter.get_order_book("BTC/USDT", 2).await.unwrap();
+
+        assert_eq!(book.bids[0].price, dec!(41006.3));
+        assert_eq!(book.bids[1].quantity, dec!(1.5));
+        assert_eq!(book.asks[0].price, dec!(41006.8));
+        assert_eq!(book.asks[1].price, dec!(41007.1));
+        assert_eq!(book.timestamp, 1629966436396);
+        let requests = server.await.unwrap();
+        assert!(requests[0].starts_with("GET /api/v5/market/books?instId=BTC-USDT-SWAP&sz=2"), "{:?}", requests);
+    }
+
+    #[tokio::test]
+    async fn test_order_timestamp_is_in_millis() {
+        let (url, _server) = serve_http(vec![(
+            "200 OK",
+            r#"{"code":"0","msg":"","data":[{"ordId":"7","clOrdId":"cs1","instId":"BTC-USDT-SWAP","side":"buy","ordType":"limit","px":"100","sz":"2","fillSz":"2","avgPx":"100","state":"filled","uTime":"1597026383085"}]}"#,
+        )])
+        .await;
+        let adapter = OkxAdapter::new(exchange_config("okx", url)).await.unwrap();
+
+        let order = adapter.get_order(&credentials(), "BTC-USDT-SWAP", "7").await.unwrap();
+        assert_eq!(order.timestamp, 1597026383085);
+    }
+}
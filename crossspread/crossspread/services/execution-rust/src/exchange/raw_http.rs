@@ -0,0 +1,162 @@
+//! Raw HTTP logging
+//!
+//! With `log_raw_http` set for an exchange, every adapter request is logged
+//! at trace level with its URL and body, followed by the raw response body.
+//! Headers are never logged since that is where most venues carry the API
+//! key, and query parameters or JSON fields named like keys, signatures or
+//! passphrases are redacted.
+
+use async_trait::async_trait;
+use reqwest::{RequestBuilder, Response, Url};
+use tracing::trace;
+
+const REDACTED: &str = "[redacted]";
+
+/// Parameter names containing any of these are never logged
+const SECRET_NAMES: &[&str] = &["sign", "key", "secret", "passphrase", "token"];
+
+#[async_trait]
+pub trait SendTraced {
+    /// Send the request like `send()`, logging it and the response body when
+    /// `log_raw` is set
+    async fn send_traced(self, log_raw: bool) -> reqwest::Result<Response>;
+}
+
+#[async_trait]
+impl SendTraced for RequestBuilder {
+    async fn send_traced(self, log_raw: bool) -> reqwest::Result<Response> {
+        if !log_raw {
+            return self.send().await;
+        }
+
+        let (client, request) = self.build_split();
+        let request = request?;
+        let method = request.method().clone();
+        let url = redact_url(request.url());
+        match request.body().and_then(|body| body.as_bytes()) {
+            Some(body) => trace!("{} {} {}", method, url, redact_body(body)),
+            None => trace!("{} {}", method, url),
+        }
+
+        let response = client.execute(request).await?;
+        let status = response.status();
+        let headers = response.headers().clone();
+        let body = response.bytes().await?;
+        trace!("{} {} -> {} {}", method, url, status, redact_body(&body));
+
+        // The body has been read, so hand the caller an equivalent response
+        let mut rebuilt = http::Response::new(body);
+        *rebuilt.status_mut() = status;
+        *rebuilt.headers_mut() = headers;
+        Ok(Response::from(rebuilt))
+    }
+}
+
+fn is_secret(name: &str) -> bool {
+    let name = name.to_ascii_lowercase();
+    SECRET_NAMES.iter().any(|secret| name.contains(secret))
+}
+
+fn redact_url(url: &Url) -> String {
+    let mut url = url.clone();
+    if url.query().is_some() {
+        let pairs: Vec<(String, String)> = url
+            .query_pairs()
+            .map(|(name, value)| {
+                let value = if is_secret(&name) { REDACTED.into() } else { value };
+                (name.into_owned(), value.into_owned())
+            })
+            .collect();
+        url.query_pairs_mut().clear().extend_pairs(pairs);
+    }
+    url.to_string()
+}
+
+/// JSON bodies have secret fields replaced at any depth, form-encoded
+/// bodies secret parameters; anything else is logged as is
+fn redact_body(body: &[u8]) -> String {
+    let text = String::from_utf8_lossy(body);
+    if let Ok(mut value) = serde_json::from_slice::<serde_json::Value>(body) {
+        redact_json(&mut value);
+        return value.to_string();
+    }
+    if text.contains('=') && !text.contains(char::is_whitespace) {
+        return text
+            .split('&')
+            .map(|pair| match pair.split_once('=') {
+                Some((name, _)) if is_secret(name) => format!("{}={}", name, REDACTED),
+                _ => pair.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("&");
+    }
+    text.into_owned()
+}
+
+fn redact_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                if is_secret(name) {
+                    *field = serde_json::Value::String(REDACTED.to_string());
+                } else {
+                    redact_json(field);
+                }
+            }
+        }
+        serde_json::Value::Array(items) => items.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    #[test]
+    fn test_secrets_are_redacted() {
+        let url = Url::parse("https://fapi.binance.com/fapi/v1/order?symbol=BTCUSDT&timestamp=1&signature=abc123")
+            .unwrap();
+        let url = redact_url(&url);
+        assert!(url.contains("symbol=BTCUSDT&timestamp=1&signature=%5Bredacted%5D"), "{}", url);
+
+        let body = redact_body(br#"{"apiKey":"k1","params":[{"sign":"s1","qty":"2"}]}"#);
+        assert!(!body.contains("k1") && !body.contains("s1"), "{}", body);
+        assert!(body.contains(r#""qty":"2""#), "{}", body);
+
+        assert_eq!(redact_body(b"api_key=k1&size=2"), "api_key=[redacted]&size=2");
+        assert_eq!(redact_body(b"not found"), "not found");
+    }
+
+    #[tokio::test]
+    async fn test_traced_response_is_passed_through() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = [0u8; 1024];
+            let _ = stream.read(&mut request).await.unwrap();
+            let body = r#"{"code":-2019,"msg":"Margin is insufficient."}"#;
+            let response = format!(
+                "HTTP/1.1 400 Bad Request\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let response = reqwest::Client::new()
+            .post(format!("http://{}/fapi/v1/order?signature=abc", addr))
+            .body("quantity=1")
+            .send_traced(true)
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        assert_eq!(
+            response.text().await.unwrap(),
+            r#"{"code":-2019,"msg":"Margin is insufficient."}"#
+        );
+    }
+}
@@ -0,0 +1,309 @@
+//! Order placement over WebSocket
+//!
+//! Binance and Bybit take orders over a WebSocket as well as REST, which
+//! saves the HTTP round trip, and any TLS handshake, of each order. A
+//! `TradingSocket` is one connection per API key. Requests carry an id that
+//! the venue echoes on its answer, so several can be in flight at once. The
+//! socket connects on first use and again on the first request after it
+//! drops, and is pinged while open so idle periods between trades don't
+//! close it.
+//!
+//! The handshake differs by venue:
+//! - Binance (`wss://ws-fapi.binance.com/ws-fapi/v1`) has no login for HMAC
+//!   keys. Every `order.place` request signs itself: its params carry
+//!   `apiKey`, `timestamp` and `signature`, the HMAC-SHA256 of the other
+//!   params sorted by name and joined as a query string.
+//! - Bybit (`wss://stream.bybit.com/v5/trade`) needs an `auth` op first,
+//!   with args `[api_key, expires, signature]` where the signature is the
+//!   HMAC-SHA256 of `GET/realtime{expires}`. Orders then go as
+//!   `order.create` ops carrying the REST body as their only arg and an
+//!   `X-BAPI-TIMESTAMP` header.
+
+use anyhow::{Context, Result};
+use futures::stream::{SplitSink, SplitStream};
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::oneshot;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tracing::{debug, info};
+
+/// Longest wait to connect, or for the answer to a request
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+const ANSWER_TIMEOUT: Duration = Duration::from_secs(5);
+/// Gap between pings while the socket is open
+const PING_INTERVAL: Duration = Duration::from_secs(20);
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+type Sink = SplitSink<Socket, Message>;
+/// Requests awaiting their answer, by id
+type Pending = Arc<Mutex<HashMap<String, oneshot::Sender<Value>>>>;
+
+/// Request sent right after connecting, and the check its answer must pass
+struct Login {
+    request: Box<dyn Fn() -> Value + Send + Sync>,
+    check: fn(&Value) -> Result<()>,
+}
+
+#[derive(Clone)]
+struct Connection {
+    sink: Arc<tokio::sync::Mutex<Sink>>,
+    pending: Pending,
+    /// Cleared once the socket drops
+    open: Arc<AtomicBool>,
+}
+
+pub struct TradingSocket {
+    url: String,
+    /// Field carrying the request id: `id` on Binance, `reqId` on Bybit
+    id_field: &'static str,
+    login: Option<Login>,
+    connection: tokio::sync::Mutex<Option<Connection>>,
+    next_id: AtomicU64,
+}
+
+/// A request sent on a trading socket, awaiting the venue's answer
+pub struct PendingAnswer {
+    id: String,
+    answer: oneshot::Receiver<Value>,
+    pending: Pending,
+}
+
+impl PendingAnswer {
+    /// The venue's answer. Once the request was sent it may have been
+    /// acted on, so a missing answer is not a reason to send it elsewhere.
+    pub async fn wait(self) -> Result<Value> {
+        match tokio::time::timeout(ANSWER_TIMEOUT, self.answer).await {
+            Ok(Ok(answer)) => Ok(answer),
+            Ok(Err(_)) => anyhow::bail!("Trading socket closed before request {} was answered", self.id),
+            Err(_) => {
+                self.pending.lock().unwrap().remove(&self.id);
+                anyhow::bail!("No answer to request {} within {:?}", self.id, ANSWER_TIMEOUT)
+            }
+        }
+    }
+}
+
+impl TradingSocket {
+    pub fn new(url: &str, id_field: &'static str) -> Self {
+        Self {
+            url: url.to_string(),
+            id_field,
+            login: None,
+            connection: tokio::sync::Mutex::new(None),
+            next_id: AtomicU64::new(1),
+        }
+    }
+
+    /// Send the request built by `request` on each connect, before anything
+    /// else, and give up on the connection unless its answer passes `check`
+    pub fn with_login<F>(mut self, request: F, check: fn(&Value) -> Result<()>) -> Self
+    where
+        F: Fn() -> Value + Send + Sync + 'static,
+    {
+        self.login = Some(Login {
+            request: Box::new(request),
+            check,
+        });
+        self
+    }
+
+    /// Send `request`, tagged with a fresh id, connecting first if needed.
+    /// An error here means nothing was sent.
+    pub async fn send(&self, mut request: Value) -> Result<PendingAnswer> {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed).to_string();
+        request[self.id_field] = Value::String(id.clone());
+
+        let connection = {
+            let mut current = self.connection.lock().await;
+            match current.as_ref().filter(|c| c.open.load(Ordering::SeqCst)) {
+                Some(connection) => connection.clone(),
+                None => {
+                    let connection = self.connect().await?;
+                    *current = Some(connection.clone());
+                    connection
+                }
+            }
+        };
+
+        let (tx, rx) = oneshot::channel();
+        connection.pending.lock().unwrap().insert(id.clone(), tx);
+        let sent = connection
+            .sink
+            .lock()
+            .await
+            .send(Message::Text(request.to_string()))
+            .await;
+        if let Err(e) = sent {
+            connection.open.store(false, Ordering::SeqCst);
+            connection.pending.lock().unwrap().remove(&id);
+            return Err(e).with_context(|| format!("Failed to send on {}", self.url));
+        }
+
+        Ok(PendingAnswer {
+            id,
+            answer: rx,
+            pending: connection.pending,
+        })
+    }
+
+    async fn connect(&self) -> Result<Connection> {
+        let (socket, _) = tokio::time::timeout(CONNECT_TIMEOUT, connect_async(self.url.as_str()))
+            .await
+            .map_err(|_| anyhow::anyhow!("Timed out connecting to {}", self.url))?
+            .with_context(|| format!("Failed to connect to {}", self.url))?;
+        let (mut sink, mut stream) = socket.split();
+
+        if let Some(login) = &self.login {
+            sink.send(Message::Text((login.request)().to_string())).await?;
+            let answer = tokio::time::timeout(ANSWER_TIMEOUT, next_json(&mut stream))
+                .await
+                .map_err(|_| anyhow::anyhow!("No answer to login on {}", self.url))??;
+            (login.check)(&answer).with_context(|| format!("Login to {} rejected", self.url))?;
+        }
+        info!("Trading socket connected to {}", self.url);
+
+        let connection = Connection {
+            sink: Arc::new(tokio::sync::Mutex::new(sink)),
+            pending: Arc::new(Mutex::new(HashMap::new())),
+            open: Arc::new(AtomicBool::new(true)),
+        };
+        tokio::spawn(route_answers(stream, self.id_field, connection.clone()));
+        tokio::spawn(keep_alive(connection.clone()));
+        Ok(connection)
+    }
+}
+
+/// The next text frame, as JSON
+async fn next_json(stream: &mut SplitStream<Socket>) -> Result<Value> {
+    while let Some(frame) = stream.next().await {
+        if let Message::Text(text) = frame? {
+            return Ok(serde_json::from_str(&text)?);
+        }
+    }
+    anyhow::bail!("Socket closed")
+}
+
+/// Hand each answer to the request it carries the id of, until the socket
+/// drops. Requests still waiting then fail rather than time out.
+async fn route_answers(mut stream: SplitStream<Socket>, id_field: &'static str, connection: Connection) {
+    loop {
+        let answer = match next_json(&mut stream).await {
+            Ok(answer) => answer,
+            Err(e) => {
+                debug!("Trading socket closed: {}", e);
+                break;
+            }
+        };
+        let id = match &answer[id_field] {
+            Value::String(id) => id.clone(),
+            Value::Number(id) => id.to_string(),
+            _ => continue,
+        };
+        if let Some(waiting) = connection.pending.lock().unwrap().remove(&id) {
+            let _ = waiting.send(answer);
+        }
+    }
+    connection.open.store(false, Ordering::SeqCst);
+    connection.pending.lock().unwrap().clear();
+}
+
+async fn keep_alive(connection: Connection) {
+    loop {
+        tokio::time::sleep(PING_INTERVAL).await;
+        if !connection.open.load(Ordering::SeqCst) {
+            return;
+        }
+        if connection.sink.lock().await.send(Message::Ping(Vec::new())).await.is_err() {
+            connection.open.store(false, Ordering::SeqCst);
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio_tungstenite::accept_async;
+
+    fn check_auth(answer: &Value) -> Result<()> {
+        if answer["retCode"] != 0 {
+            anyhow::bail!("{}", answer["retMsg"]);
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_answers_are_matched_by_id_across_reconnects() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for connection in 0..2 {
+                let (stream, _) = listener.accept().await.unwrap();
+                let mut socket = accept_async(stream).await.unwrap();
+                let next = |frame: Option<Result<Message, _>>| -> Value {
+                    serde_json::from_str(&frame.unwrap().unwrap().into_text().unwrap()).unwrap()
+                };
+
+                let login = next(socket.next().await);
+                assert_eq!(login["op"], "auth");
+                let answer = serde_json::json!({ "op": "auth", "retCode": 0, "retMsg": "OK" });
+                socket.send(Message::Text(answer.to_string())).await.unwrap();
+
+                // The first connection answers two requests in reverse order, then drops
+                let requests = if connection == 0 { 2 } else { 1 };
+                let mut received = Vec::new();
+                for _ in 0..requests {
+                    received.push(next(socket.next().await));
+                }
+                for request in received.iter().rev() {
+                    let answer = serde_json::json!({ "reqId": request["reqId"], "echo": request["n"] });
+                    socket.send(Message::Text(answer.to_string())).await.unwrap();
+                }
+                socket.close(None).await.unwrap();
+            }
+        });
+
+        let socket = TradingSocket::new(&url, "reqId")
+            .with_login(|| serde_json::json!({ "op": "auth" }), check_auth);
+        let first = socket.send(serde_json::json!({ "n": 1 })).await.unwrap();
+        let second = socket.send(serde_json::json!({ "n": 2 })).await.unwrap();
+        assert_eq!(first.wait().await.unwrap()["echo"], 1);
+        assert_eq!(second.wait().await.unwrap()["echo"], 2);
+
+        // Reconnects, and logs in again, once the server has dropped it
+        while socket.connection.lock().await.as_ref().unwrap().open.load(Ordering::SeqCst) {
+            tokio::task::yield_now().await;
+        }
+        let third = socket.send(serde_json::json!({ "n": 3 })).await.unwrap();
+        assert_eq!(third.wait().await.unwrap()["echo"], 3);
+    }
+
+    #[tokio::test]
+    async fn test_rejected_login_or_unreachable_socket_sends_nothing() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut socket = accept_async(stream).await.unwrap();
+            socket.next().await;
+            let answer = serde_json::json!({ "op": "auth", "retCode": 10004, "retMsg": "Invalid sign" });
+            socket.send(Message::Text(answer.to_string())).await.unwrap();
+        });
+
+        let socket = TradingSocket::new(&url, "reqId")
+            .with_login(|| serde_json::json!({ "op": "auth" }), check_auth);
+        let err = socket.send(serde_json::json!({})).await.err().unwrap();
+        assert!(format!("{:#}", err).contains("Invalid sign"), "{:#}", err);
+
+        let closed = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", closed.local_addr().unwrap());
+        drop(closed);
+        assert!(TradingSocket::new(&url, "id").send(serde_json::json!({})).await.is_err());
+    }
+}
@@ -0,0 +1,522 @@
+//! WebSocket market data feeds
+//!
+//! Keeps a venue feed alive across disconnects: reconnects with exponential
+//! backoff, re-subscribes every active topic, and resyncs from a REST snapshot
+//! whenever order book sequence numbers show a gap.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use futures::{SinkExt, StreamExt};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::time::{interval, sleep, Instant};
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+use tracing::{debug, info, warn};
+
+/// First delay before reconnecting, doubled after each failed attempt
+const INITIAL_RECONNECT_DELAY: Duration = Duration::from_millis(500);
+const MAX_RECONNECT_DELAY: Duration = Duration::from_secs(30);
+/// A feed that has been silent this long is treated as dead and reconnected
+const DEFAULT_STALE_AFTER: Duration = Duration::from_secs(30);
+/// Buffered events before the feed waits on the consumer
+const EVENT_BUFFER: usize = 1024;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// A data message from a feed
+#[derive(Debug, Clone)]
+pub struct FeedUpdate {
+    pub topic: String,
+    /// Sequence number, where the venue provides one
+    pub sequence: Option<u64>,
+    /// Sequence of the preceding update, for venues that chain them
+    /// (Binance `pu`, OKX `prevSeqId`). Otherwise updates must be consecutive.
+    pub prev_sequence: Option<u64>,
+    pub payload: serde_json::Value,
+}
+
+#[derive(Debug, Clone)]
+pub enum FeedEvent {
+    Connected,
+    Disconnected,
+    /// Full state of a topic from REST, replacing anything held locally
+    Snapshot(FeedUpdate),
+    /// In-sequence incremental update
+    Update(FeedUpdate),
+}
+
+/// Venue-specific framing of a WebSocket feed
+#[async_trait]
+pub trait FeedProtocol: Send + Sync {
+    fn url(&self) -> String;
+
+    /// Subscribe request for `topics`
+    fn subscribe_message(&self, topics: &[String]) -> String;
+
+    /// Unsubscribe request for `topics`. Without one a dropped topic is only
+    /// left out of later subscriptions.
+    fn unsubscribe_message(&self, _topics: &[String]) -> Option<String> {
+        None
+    }
+
+    /// Sent first on every connection, for private streams that log in over
+    /// the socket
+    fn login_message(&self) -> Option<String> {
+        None
+    }
+
+    /// Application-level ping sent on every stale check, for venues that
+    /// drop connections which don't ping them
+    fn heartbeat(&self) -> Option<String> {
+        None
+    }
+
+    /// Data carried by a text frame, or None for acks and heartbeats
+    fn parse(&self, text: &str) -> Option<FeedUpdate>;
+
+    /// REST snapshot of `topic`, used to resync after a reconnect or gap
+    async fn snapshot(&self, topic: &str) -> Result<FeedUpdate> {
+        anyhow::bail!("Snapshots are not supported for {}", topic)
+    }
+}
+
+/// Connection state shared between the feed task and its handles
+struct FeedState {
+    connected: AtomicBool,
+    last_message: Mutex<Option<Instant>>,
+    topics: Mutex<HashSet<String>>,
+}
+
+impl FeedState {
+    fn touch(&self) {
+        *self.last_message.lock().unwrap() = Some(Instant::now());
+    }
+
+    fn last_message_age(&self) -> Option<Duration> {
+        self.last_message.lock().unwrap().map(|at| at.elapsed())
+    }
+}
+
+/// Request from a handle to the feed task
+enum Command {
+    Subscribe(String),
+    Unsubscribe(String),
+    Reconnect,
+}
+
+/// Handle to a running feed
+#[derive(Clone)]
+pub struct FeedHandle {
+    state: Arc<FeedState>,
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl FeedHandle {
+    pub fn is_connected(&self) -> bool {
+        self.state.connected.load(Ordering::SeqCst)
+    }
+
+    /// Add a topic. It stays subscribed across reconnects.
+    pub fn subscribe(&self, topic: &str) {
+        let _ = self.commands.send(Command::Subscribe(topic.to_string()));
+    }
+
+    /// Drop a topic, which is then no longer subscribed on reconnect
+    pub fn unsubscribe(&self, topic: &str) {
+        let _ = self.commands.send(Command::Unsubscribe(topic.to_string()));
+    }
+
+    /// Drop the connection and open a new one, e.g. after the URL changed
+    pub fn reconnect(&self) {
+        let _ = self.commands.send(Command::Reconnect);
+    }
+}
+
+/// Builder for a self-healing WebSocket feed
+pub struct Feed<P> {
+    protocol: P,
+    topics: Vec<String>,
+    stale_after: Duration,
+}
+
+impl<P: FeedProtocol + 'static> Feed<P> {
+    pub fn new(protocol: P) -> Self {
+        Self {
+            protocol,
+            topics: Vec::new(),
+            stale_after: DEFAULT_STALE_AFTER,
+        }
+    }
+
+    /// Reconnect after `stale_after` without any frame. Streams that can
+    /// be quiet for long need more than the default.
+    pub fn with_stale_after(mut self, stale_after: Duration) -> Self {
+        self.stale_after = stale_after;
+        self
+    }
+
+    /// Start the feed. It runs until the event receiver is dropped.
+    pub fn spawn(self) -> (FeedHandle, mpsc::Receiver<FeedEvent>) {
+        let state = Arc::new(FeedState {
+            connected: AtomicBool::new(false),
+            last_message: Mutex::new(None),
+            topics: Mutex::new(self.topics.into_iter().collect()),
+        });
+        let (command_tx, command_rx) = mpsc::unbounded_channel();
+        let (event_tx, event_rx) = mpsc::channel(EVENT_BUFFER);
+
+        tokio::spawn(run_feed(
+            self.protocol,
+            state.clone(),
+            self.stale_after,
+            command_rx,
+            event_tx,
+        ));
+
+        (FeedHandle { state, commands: command_tx }, event_rx)
+    }
+}
+
+async fn run_feed<P: FeedProtocol>(
+    protocol: P,
+    state: Arc<FeedState>,
+    stale_after: Duration,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    events: mpsc::Sender<FeedEvent>,
+) {
+    let mut delay = INITIAL_RECONNECT_DELAY;
+
+    loop {
+        // Read per attempt, as it may carry a credential that gets replaced
+        let url = protocol.url();
+        match connect_async(url.as_str()).await {
+            Ok((socket, _)) => {
+                info!("Feed connected to {}", url);
+                state.connected.store(true, Ordering::SeqCst);
+                delay = INITIAL_RECONNECT_DELAY;
+
+                let outcome =
+                    run_session(&protocol, &state, stale_after, socket, &mut commands, &events).await;
+                state.connected.store(false, Ordering::SeqCst);
+
+                match outcome {
+                    Ok(()) => {
+                        debug!("Feed {} has no consumer, stopping", url);
+                        return;
+                    }
+                    Err(e) => warn!("Feed {} disconnected: {}", url, e),
+                }
+                if events.send(FeedEvent::Disconnected).await.is_err() {
+                    return;
+                }
+            }
+            Err(e) => warn!("Feed connection to {} failed: {}", url, e),
+        }
+
+        sleep(delay).await;
+        delay = (delay * 2).min(MAX_RECONNECT_DELAY);
+    }
+}
+
+/// Drive one connection. Returns Ok once the consumer has gone away and an
+/// error when the connection has to be re-established.
+async fn run_session<P: FeedProtocol>(
+    protocol: &P,
+    state: &FeedState,
+    stale_after: Duration,
+    socket: Socket,
+    commands: &mut mpsc::UnboundedReceiver<Command>,
+    events: &mpsc::Sender<FeedEvent>,
+) -> Result<()> {
+    let (mut sink, mut stream) = socket.split();
+    state.touch();
+
+    if let Some(login) = protocol.login_message() {
+        sink.send(Message::Text(login)).await?;
+    }
+    let topics: Vec<String> = state.topics.lock().unwrap().iter().cloned().collect();
+    if !topics.is_empty() {
+        sink.send(Message::Text(protocol.subscribe_message(&topics))).await?;
+    }
+    if events.send(FeedEvent::Connected).await.is_err() {
+        return Ok(());
+    }
+
+    // Anything could have been missed while disconnected
+    let mut tracker = SequenceTracker::default();
+    for topic in &topics {
+        if !resync(protocol, &mut tracker, topic, events).await {
+            return Ok(());
+        }
+    }
+
+    let mut stale_check = interval(stale_after / 4);
+    loop {
+        tokio::select! {
+            frame = stream.next() => {
+                let frame = match frame {
+                    Some(frame) => frame?,
+                    None => anyhow::bail!("Stream ended"),
+                };
+                state.touch();
+
+                // Pings are answered by tungstenite itself
+                let text = match frame {
+                    Message::Text(text) => text,
+                    Message::Close(close) => anyhow::bail!("Closed by server: {:?}", close),
+                    _ => continue,
+                };
+                let Some(update) = protocol.parse(&text) else {
+                    continue;
+                };
+
+                let delivered = match tracker.check(&update) {
+                    SequenceCheck::InOrder => events.send(FeedEvent::Update(update)).await.is_ok(),
+                    SequenceCheck::Stale => true,
+                    SequenceCheck::Gap { last, prev } => {
+                        warn!(
+                            "Sequence gap on {}: last applied {}, update follows {}, resyncing",
+                            update.topic, last, prev
+                        );
+                        resync(protocol, &mut tracker, &update.topic, events).await
+                    }
+                };
+                if !delivered {
+                    return Ok(());
+                }
+            }
+            Some(command) = commands.recv() => {
+                let topic = match command {
+                    Command::Subscribe(topic) => topic,
+                    Command::Unsubscribe(topic) => {
+                        let removed = state.topics.lock().unwrap().remove(&topic);
+                        if let Some(message) = protocol.unsubscribe_message(&[topic]).filter(|_| removed) {
+                            sink.send(Message::Text(message)).await?;
+                        }
+                        continue;
+                    }
+                    Command::Reconnect => anyhow::bail!("Reconnect requested"),
+                };
+                let added = state.topics.lock().unwrap().insert(topic.clone());
+                if added {
+                    sink.send(Message::Text(protocol.subscribe_message(std::slice::from_ref(&topic)))).await?;
+                    if !resync(protocol, &mut tracker, &topic, events).await {
+                        return Ok(());
+                    }
+                }
+            }
+            _ = stale_check.tick() => {
+                if let Some(age) = state.last_message_age().filter(|age| *age > stale_after) {
+                    anyhow::bail!("No messages for {:?}", age);
+                }
+                if let Some(ping) = protocol.heartbeat() {
+                    sink.send(Message::Text(ping)).await?;
+                }
+            }
+        }
+    }
+}
+
+/// Replace a topic's state with a REST snapshot. Returns false once the
+/// consumer has gone away.
+async fn resync<P: FeedProtocol>(
+    protocol: &P,
+    tracker: &mut SequenceTracker,
+    topic: &str,
+    events: &mpsc::Sender<FeedEvent>,
+) -> bool {
+    match protocol.snapshot(topic).await {
+        Ok(snapshot) => {
+            tracker.reset(topic, snapshot.sequence);
+            events.send(FeedEvent::Snapshot(snapshot)).await.is_ok()
+        }
+        Err(e) => {
+            // Without a snapshot the next update becomes the new baseline
+            warn!("Resync of {} failed: {}", topic, e);
+            tracker.reset(topic, None);
+            true
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SequenceCheck {
+    InOrder,
+    /// Already covered by the current snapshot
+    Stale,
+    /// The update follows `prev`, which was never applied
+    Gap { last: u64, prev: u64 },
+}
+
+/// Last applied sequence number per topic
+#[derive(Default)]
+struct SequenceTracker {
+    last: HashMap<String, u64>,
+}
+
+impl SequenceTracker {
+    fn check(&mut self, update: &FeedUpdate) -> SequenceCheck {
+        let Some(sequence) = update.sequence else {
+            return SequenceCheck::InOrder;
+        };
+        let Some(&last) = self.last.get(&update.topic) else {
+            self.last.insert(update.topic.clone(), sequence);
+            return SequenceCheck::InOrder;
+        };
+
+        if sequence <= last {
+            return SequenceCheck::Stale;
+        }
+        let prev = update.prev_sequence.unwrap_or(sequence - 1);
+        if prev > last {
+            return SequenceCheck::Gap { last, prev };
+        }
+
+        self.last.insert(update.topic.clone(), sequence);
+        SequenceCheck::InOrder
+    }
+
+    fn reset(&mut self, topic: &str, sequence: Option<u64>) {
+        match sequence {
+            Some(sequence) => self.last.insert(topic.to_string(), sequence),
+            None => self.last.remove(topic),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::AtomicU64;
+    use tokio::net::TcpListener;
+    use tokio::time::timeout;
+
+    fn update(topic: &str, sequence: u64, prev_sequence: Option<u64>) -> FeedUpdate {
+        FeedUpdate {
+            topic: topic.to_string(),
+            sequence: Some(sequence),
+            prev_sequence,
+            payload: serde_json::Value::Null,
+        }
+    }
+
+    #[test]
+    fn test_sequence_gaps() {
+        let mut tracker = SequenceTracker::default();
+        tracker.reset("book", Some(10));
+
+        assert_eq!(tracker.check(&update("book", 9, None)), SequenceCheck::Stale);
+        assert_eq!(tracker.check(&update("book", 11, None)), SequenceCheck::InOrder);
+        assert_eq!(
+            tracker.check(&update("book", 13, None)),
+            SequenceCheck::Gap { last: 11, prev: 12 }
+        );
+
+        // Chained updates only have to follow on from the last one applied,
+        // so the first update after a snapshot may straddle it
+        tracker.reset("chained", Some(100));
+        assert_eq!(tracker.check(&update("chained", 105, Some(98))), SequenceCheck::InOrder);
+        assert_eq!(tracker.check(&update("chained", 120, Some(105))), SequenceCheck::InOrder);
+        assert_eq!(
+            tracker.check(&update("chained", 140, Some(130))),
+            SequenceCheck::Gap { last: 120, prev: 130 }
+        );
+
+        // Unknown topics take their first update as the baseline
+        assert_eq!(tracker.check(&update("new", 7, None)), SequenceCheck::InOrder);
+        assert_eq!(tracker.check(&update("new", 8, None)), SequenceCheck::InOrder);
+    }
+
+    /// `{"topic": .., "seq": ..}` frames, with snapshots at 10, 20, 30, ...
+    struct TestProtocol {
+        url: String,
+        snapshots: AtomicU64,
+    }
+
+    #[async_trait]
+    impl FeedProtocol for TestProtocol {
+        fn url(&self) -> String {
+            self.url.clone()
+        }
+
+        fn subscribe_message(&self, topics: &[String]) -> String {
+            serde_json::json!({ "subscribe": topics }).to_string()
+        }
+
+        fn parse(&self, text: &str) -> Option<FeedUpdate> {
+            let value: serde_json::Value = serde_json::from_str(text).ok()?;
+            Some(update(value["topic"].as_str()?, value["seq"].as_u64()?, None))
+        }
+
+        async fn snapshot(&self, topic: &str) -> Result<FeedUpdate> {
+            let n = self.snapshots.fetch_add(1, Ordering::SeqCst) + 1;
+            Ok(update(topic, n * 10, None))
+        }
+    }
+
+    async fn next_event(events: &mut mpsc::Receiver<FeedEvent>) -> FeedEvent {
+        timeout(Duration::from_secs(5), events.recv())
+            .await
+            .expect("feed event")
+            .expect("feed running")
+    }
+
+    fn sequence_of(event: FeedEvent) -> (&'static str, u64) {
+        match event {
+            FeedEvent::Snapshot(u) => ("snapshot", u.sequence.unwrap()),
+            FeedEvent::Update(u) => ("update", u.sequence.unwrap()),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_resubscribes_and_resyncs_gaps() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}", listener.local_addr().unwrap());
+
+        let (subscriptions_tx, mut subscriptions) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            // First connection skips 12, then drops; the second carries on
+            let sessions = [vec![11, 13, 21], vec![31]];
+            for frames in sessions {
+                let (tcp, _) = listener.accept().await.unwrap();
+                let mut ws = tokio_tungstenite::accept_async(tcp).await.unwrap();
+                if let Some(Ok(Message::Text(subscribe))) = ws.next().await {
+                    subscriptions_tx.send(subscribe).unwrap();
+                }
+                for seq in frames {
+                    let frame = serde_json::json!({ "topic": "BTCUSDT", "seq": seq });
+                    ws.send(Message::Text(frame.to_string())).await.unwrap();
+                }
+                sleep(Duration::from_millis(100)).await;
+            }
+        });
+
+        let protocol = TestProtocol { url, snapshots: AtomicU64::new(0) };
+        let (handle, mut events) = Feed::new(protocol).spawn();
+        handle.subscribe("BTCUSDT");
+
+        assert!(matches!(next_event(&mut events).await, FeedEvent::Connected));
+        assert!(handle.is_connected());
+        assert_eq!(sequence_of(next_event(&mut events).await), ("snapshot", 10));
+        assert_eq!(sequence_of(next_event(&mut events).await), ("update", 11));
+        // 13 follows a missing 12
+        assert_eq!(sequence_of(next_event(&mut events).await), ("snapshot", 20));
+        assert_eq!(sequence_of(next_event(&mut events).await), ("update", 21));
+
+        assert!(matches!(next_event(&mut events).await, FeedEvent::Disconnected));
+        assert!(matches!(next_event(&mut events).await, FeedEvent::Connected));
+        assert_eq!(sequence_of(next_event(&mut events).await), ("snapshot", 30));
+        assert_eq!(sequence_of(next_event(&mut events).await), ("update", 31));
+
+        let expected = r#"{"subscribe":["BTCUSDT"]}"#;
+        assert_eq!(subscriptions.recv().await.unwrap(), expected);
+        assert_eq!(subscriptions.recv().await.unwrap(), expected);
+        assert!(handle.state.last_message_age().unwrap() < Duration::from_secs(5));
+    }
+}
@@ -0,0 +1,145 @@
+//! Health and metrics endpoints
+//!
+//! `/healthz` returns JSON, with each exchange's clock skew, whether its
+//! adapter is connected and any exchange in maintenance, and `/metrics`
+//! Prometheus text. They are served on their
+//! own port and also merged into the HTTP API's router, unauthenticated on
+//! both.
+
+use anyhow::{Context, Result};
+use axum::extract::State;
+use axum::http::header;
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Json, Router};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::clock::{ClockMonitor, ClockSample};
+use crate::exchange::ExchangeAdapter;
+use crate::maintenance::MaintenanceMonitor;
+
+/// Adapters by exchange id
+pub type Adapters = HashMap<String, Arc<dyn ExchangeAdapter>>;
+
+#[derive(Clone)]
+struct Health {
+    clock: Arc<ClockMonitor>,
+    maintenance: Arc<MaintenanceMonitor>,
+    adapters: Adapters,
+}
+
+/// Accept health and metrics requests on `port` until the listener fails
+pub async fn serve(
+    port: u16,
+    clock: Arc<ClockMonitor>,
+    maintenance: Arc<MaintenanceMonitor>,
+    adapters: Adapters,
+) -> Result<()> {
+    let listener = std::net::TcpListener::bind(("0.0.0.0", port))
+        .with_context(|| format!("Failed to listen for health checks on port {}", port))?;
+    info!("Serving /healthz and /metrics on port {}", port);
+    axum::Server::from_tcp(listener)?
+        .serve(router(clock, maintenance, adapters).into_make_service())
+        .await?;
+    Ok(())
+}
+
+/// `/healthz` and `/metrics`, to serve alone or merge into another router
+pub fn router<S>(clock: Arc<ClockMonitor>, maintenance: Arc<MaintenanceMonitor>, adapters: Adapters) -> Router<S> {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/metrics", get(prometheus))
+        .with_state(Health { clock, maintenance, adapters })
+}
+
+async fn healthz(State(health): State<Health>) -> Response {
+    let connected: BTreeMap<_, _> = health
+        .adapters
+        .iter()
+        .map(|(id, adapter)| (id.as_str(), adapter.is_connected()))
+        .collect();
+    // Still answered, so a lost stream shows up without failing the probe
+    let status = if connected.values().all(|&up| up) { "ok" } else { "degraded" };
+    Json(serde_json::json!({
+        "status": status,
+        "connected": connected,
+        "clock_skew": health.clock.samples().await,
+        "maintenance": health.maintenance.windows().await,
+    }))
+    .into_response()
+}
+
+async fn prometheus(State(health): State<Health>) -> Response {
+    let body = metrics(&health.clock.samples().await);
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body).into_response()
+}
+
+fn metrics(clocks: &HashMap<String, ClockSample>) -> String {
+    let mut exchanges: Vec<_> = clocks.iter().collect();
+    exchanges.sort_by_key(|(id, _)| id.as_str());
+
+    let mut out = String::new();
+    out.push_str("# HELP execution_clock_offset_ms Exchange server time minus local time\n");
+    out.push_str("# TYPE execution_clock_offset_ms gauge\n");
+    for (id, sample) in &exchanges {
+        out.push_str(&format!("execution_clock_offset_ms{{exchange=\"{}\"}} {}\n", id, sample.offset_ms));
+    }
+    out.push_str("# HELP execution_clock_round_trip_ms Round trip of the last server time request\n");
+    out.push_str("# TYPE execution_clock_round_trip_ms gauge\n");
+    for (id, sample) in &exchanges {
+        out.push_str(&format!(
+            "execution_clock_round_trip_ms{{exchange=\"{}\"}} {}\n",
+            id, sample.round_trip_ms
+        ));
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::MockAdapter;
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn test_routes_report_clock_skew() {
+        let clock = Arc::new(ClockMonitor::new(5000));
+        let behind = MockAdapter::new("binance", dec!(100), dec!(101)).with_clock_offset(-5000);
+        let sample = clock.measure(&behind).await.unwrap();
+        let maintenance = Arc::new(MaintenanceMonitor::default());
+        maintenance.flag("okx", "okx is in maintenance: 503").await;
+
+        let adapters: Adapters = HashMap::from([("binance".to_string(), Arc::new(behind) as Arc<dyn ExchangeAdapter>)]);
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        let server = axum::Server::from_tcp(listener)
+            .unwrap()
+            .serve(router(clock, maintenance, adapters).into_make_service());
+        tokio::spawn(server);
+        let client = reqwest::Client::new();
+
+        let resp = client.get(format!("{}/metrics", base)).send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.headers()[header::CONTENT_TYPE], "text/plain; version=0.0.4");
+        let body = resp.text().await.unwrap();
+        assert!(body.contains(&format!("execution_clock_offset_ms{{exchange=\"binance\"}} {}\n", sample.offset_ms)));
+        assert!(body.contains(&format!(
+            "execution_clock_round_trip_ms{{exchange=\"binance\"}} {}\n",
+            sample.round_trip_ms
+        )));
+
+        let resp = client.get(format!("{}/healthz", base)).send().await.unwrap();
+        assert_eq!(resp.status(), 200);
+        let health: serde_json::Value = resp.json().await.unwrap();
+        assert_eq!(health["status"], "ok");
+        assert_eq!(health["connected"]["binance"], true);
+        assert_eq!(health["clock_skew"]["binance"]["offset_ms"], sample.offset_ms);
+        assert_eq!(health["maintenance"]["okx"]["detail"], "okx is in maintenance: 503");
+
+        let resp = client.get(format!("{}/other", base)).send().await.unwrap();
+        assert_eq!(resp.status(), 404);
+    }
+}
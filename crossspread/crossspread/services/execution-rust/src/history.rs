@@ -0,0 +1,259 @@
+//! Trade history
+//!
+//! Every result is also written to Postgres, with a row per slice, so trades
+//! stay queryable after the Redis result stream has trimmed them. Results
+//! are queued for a background task that retries failed writes, so a
+//! database outage never holds up publishing a result.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use sqlx::postgres::{PgPool, PgPoolOptions};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, OnceCell};
+use tracing::{error, warn};
+
+use crate::order::{ExecutionResult, LegSlice};
+
+/// Writes of one result before it is given up on
+const WRITE_ATTEMPTS: u32 = 5;
+
+const CREATE_RESULTS: &str = "\
+CREATE TABLE IF NOT EXISTS execution_results (
+    trade_id UUID PRIMARY KEY,
+    success BOOLEAN NOT NULL,
+    error TEXT,
+    long_filled NUMERIC NOT NULL,
+    long_avg_price NUMERIC NOT NULL,
+    short_filled NUMERIC NOT NULL,
+    short_avg_price NUMERIC NOT NULL,
+    result JSONB NOT NULL,
+    recorded_at TIMESTAMPTZ NOT NULL DEFAULT now()
+)";
+
+const CREATE_SLICES: &str = "\
+CREATE TABLE IF NOT EXISTS execution_slices (
+    trade_id UUID NOT NULL REFERENCES execution_results (trade_id),
+    leg TEXT NOT NULL,
+    slice_index INTEGER NOT NULL,
+    client_order_id TEXT NOT NULL,
+    exchange_order_id TEXT,
+    quantity NUMERIC NOT NULL,
+    price NUMERIC NOT NULL,
+    tolerance_bps DOUBLE PRECISION,
+    filled_quantity NUMERIC NOT NULL,
+    avg_fill_price NUMERIC,
+    status TEXT NOT NULL,
+    arrival_ms BIGINT NOT NULL,
+    submitted_ms BIGINT NOT NULL,
+    filled_ms BIGINT,
+    PRIMARY KEY (trade_id, leg, slice_index)
+)";
+
+/// Where results are kept
+#[async_trait]
+pub trait HistoryStore: Send + Sync {
+    /// Store `result` and its slices. Storing a trade twice keeps the first.
+    async fn insert(&self, result: &ExecutionResult) -> Result<()>;
+}
+
+/// `execution_results` and `execution_slices`, created on first use
+pub struct PgHistoryStore {
+    pool: PgPool,
+    tables: OnceCell<()>,
+}
+
+impl PgHistoryStore {
+    pub fn new(database_url: &str) -> Result<Self> {
+        let pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect_lazy(database_url)
+            .context("Invalid database URL")?;
+        Ok(Self {
+            pool,
+            tables: OnceCell::new(),
+        })
+    }
+
+    async fn create_tables(&self) -> Result<()> {
+        self.tables
+            .get_or_try_init(|| async {
+                sqlx::query(CREATE_RESULTS).execute(&self.pool).await?;
+                sqlx::query(CREATE_SLICES).execute(&self.pool).await?;
+                anyhow::Ok(())
+            })
+            .await
+            .context("Failed to create the trade history tables")?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl HistoryStore for PgHistoryStore {
+    async fn insert(&self, result: &ExecutionResult) -> Result<()> {
+        self.create_tables().await?;
+
+        // Decimals are bound as text, as the crate's sqlx has no decimal support
+        let mut tx = self.pool.begin().await?;
+        let inserted = sqlx::query(
+            "INSERT INTO execution_results \
+             (trade_id, success, error, long_filled, long_avg_price, short_filled, short_avg_price, result) \
+             VALUES ($1, $2, $3, $4::numeric, $5::numeric, $6::numeric, $7::numeric, $8::jsonb) \
+             ON CONFLICT (trade_id) DO NOTHING",
+        )
+        .bind(result.trade_id)
+        .bind(result.success)
+        .bind(&result.error)
+        .bind(result.long_filled.to_string())
+        .bind(result.long_avg_price.to_string())
+        .bind(result.short_filled.to_string())
+        .bind(result.short_avg_price.to_string())
+        .bind(serde_json::to_string(result)?)
+        .execute(&mut *tx)
+        .await
+        .context("Failed to insert into execution_results")?;
+        if inserted.rows_affected() == 0 {
+            return Ok(());
+        }
+
+        for LegSlice { leg, slice } in &result.slices {
+            sqlx::query(
+                "INSERT INTO execution_slices \
+                 (trade_id, leg, slice_index, client_order_id, exchange_order_id, quantity, price, \
+                  tolerance_bps, filled_quantity, avg_fill_price, status, arrival_ms, submitted_ms, filled_ms) \
+                 VALUES ($1, $2, $3, $4, $5, $6::numeric, $7::numeric, $8, $9::numeric, $10::numeric, $11, $12, $13, $14)",
+            )
+            .bind(result.trade_id)
+            .bind(*leg)
+            .bind(slice.index as i32)
+            .bind(&slice.client_order_id)
+            .bind(&slice.exchange_order_id)
+            .bind(slice.quantity.to_string())
+            .bind(slice.price.to_string())
+            .bind(slice.tolerance_bps)
+            .bind(slice.filled_quantity.to_string())
+            .bind(slice.avg_fill_price.map(|p| p.to_string()))
+            .bind(serde_json::to_value(slice.status)?.as_str().unwrap_or_default().to_string())
+            .bind(slice.arrival_ms)
+            .bind(slice.submitted_ms)
+            .bind(slice.filled_ms)
+            .execute(&mut *tx)
+            .await
+            .context("Failed to insert into execution_slices")?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+}
+
+/// Queue of results waiting to be stored
+pub struct TradeHistory {
+    results: mpsc::UnboundedSender<ExecutionResult>,
+}
+
+impl TradeHistory {
+    /// Start the writer task. A failed write is retried after `retry_delay`,
+    /// doubling each time.
+    pub fn start(store: Arc<dyn HistoryStore>, retry_delay: Duration) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(write(store, rx, retry_delay));
+        Self { results: tx }
+    }
+
+    pub fn record(&self, result: &ExecutionResult) {
+        if self.results.send(result.clone()).is_err() {
+            error!("Trade history writer has stopped, trade {} not recorded", result.trade_id);
+        }
+    }
+}
+
+async fn write(
+    store: Arc<dyn HistoryStore>,
+    mut results: mpsc::UnboundedReceiver<ExecutionResult>,
+    retry_delay: Duration,
+) {
+    while let Some(result) = results.recv().await {
+        let mut delay = retry_delay;
+        for attempt in 1..=WRITE_ATTEMPTS {
+            match store.insert(&result).await {
+                Ok(()) => break,
+                Err(e) if attempt == WRITE_ATTEMPTS => error!(
+                    "Trade {} left out of the history after {} attempts: {:#}",
+                    result.trade_id, WRITE_ATTEMPTS, e
+                ),
+                Err(e) => {
+                    warn!(
+                        "Recording trade {} failed (attempt {}/{}): {:#}",
+                        result.trade_id, attempt, WRITE_ATTEMPTS, e
+                    );
+                    tokio::time::sleep(delay).await;
+                    delay *= 2;
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use uuid::Uuid;
+
+    /// Fails the first `failures` writes, then keeps what it is given
+    #[derive(Default)]
+    struct FlakyStore {
+        failures: Mutex<usize>,
+        stored: Mutex<Vec<Uuid>>,
+    }
+
+    #[async_trait]
+    impl HistoryStore for FlakyStore {
+        async fn insert(&self, result: &ExecutionResult) -> Result<()> {
+            let mut failures = self.failures.lock().unwrap();
+            if *failures > 0 {
+                *failures -= 1;
+                anyhow::bail!("connection refused");
+            }
+            self.stored.lock().unwrap().push(result.trade_id);
+            Ok(())
+        }
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_results_are_queued_and_retried_in_order() {
+        let store = Arc::new(FlakyStore {
+            failures: Mutex::new(2),
+            ..FlakyStore::default()
+        });
+        let history = TradeHistory::start(store.clone(), Duration::from_secs(1));
+
+        // Recording returns straight away, whatever the store is doing
+        let first = ExecutionResult::failed(Uuid::new_v4(), "rejected".to_string());
+        let second = ExecutionResult::failed(Uuid::new_v4(), "rejected".to_string());
+        history.record(&first);
+        history.record(&second);
+        assert!(store.stored.lock().unwrap().is_empty());
+
+        // Retried after 1 s and 2 s
+        tokio::time::sleep(Duration::from_millis(3500)).await;
+        assert_eq!(*store.stored.lock().unwrap(), [first.trade_id, second.trade_id]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_result_is_dropped_after_the_last_attempt() {
+        let store = Arc::new(FlakyStore {
+            failures: Mutex::new(WRITE_ATTEMPTS as usize),
+            ..FlakyStore::default()
+        });
+        let history = TradeHistory::start(store.clone(), Duration::from_secs(1));
+
+        let lost = ExecutionResult::failed(Uuid::new_v4(), "rejected".to_string());
+        let next = ExecutionResult::failed(Uuid::new_v4(), "rejected".to_string());
+        history.record(&lost);
+        history.record(&next);
+
+        tokio::time::sleep(Duration::from_secs(60)).await;
+        assert_eq!(*store.stored.lock().unwrap(), [next.trade_id]);
+    }
+}
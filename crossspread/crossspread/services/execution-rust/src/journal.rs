@@ -0,0 +1,551 @@
+//! Order journal
+//!
+//! Append-only JSON lines record of every order sent to an exchange and what
+//! came back, for reconstructing exactly what the service did during an
+//! incident. Entries are written by a background task so recording never
+//! waits on the sink.
+
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::mpsc;
+use tracing::{error, warn};
+use uuid::Uuid;
+
+use crate::config::JournalSink;
+use crate::exchange::{
+    AlgoKind, AlgoOrderRequest, ContractSpec, Credentials, ExchangeAdapter, Fill, LeverageInfo, OrderBook,
+    OrderRequest, OrderResponse, Position, ReferencePriceSource, RiskLimit, StopOrderRequest, SymbolInfo, Trail,
+    TrailingStopRequest,
+};
+
+tokio::task_local! {
+    /// Trade whose execution is making the current exchange calls
+    pub static TRADE_ID: Uuid;
+}
+
+/// One exchange call
+#[derive(Debug, Clone, Serialize)]
+pub struct JournalEntry {
+    pub timestamp: DateTime<Utc>,
+    pub trade_id: Option<Uuid>,
+    pub exchange: String,
+    /// `place` or `cancel`
+    pub action: &'static str,
+    pub request: serde_json::Value,
+    pub response: Option<OrderResponse>,
+    pub error: Option<String>,
+}
+
+pub struct OrderJournal {
+    entries: mpsc::UnboundedSender<JournalEntry>,
+}
+
+impl OrderJournal {
+    /// Open the sink and start the writer task
+    pub async fn open(sink: &JournalSink, redis_url: &str) -> Result<Self> {
+        let (tx, rx) = mpsc::unbounded_channel();
+
+        match sink {
+            JournalSink::File(path) => {
+                let file = tokio::fs::OpenOptions::new()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .await
+                    .with_context(|| format!("Failed to open order journal {}", path))?;
+                tokio::spawn(write_file(file, rx));
+            }
+            JournalSink::RedisStream(stream) => {
+                let client = redis::Client::open(redis_url)?;
+                let conn = redis::aio::ConnectionManager::new(client)
+                    .await
+                    .context("Failed to connect to Redis for the order journal")?;
+                tokio::spawn(write_stream(conn, stream.clone(), rx));
+            }
+        }
+
+        Ok(Self { entries: tx })
+    }
+
+    pub fn record(&self, entry: JournalEntry) {
+        if self.entries.send(entry).is_err() {
+            error!("Order journal writer has stopped, entry dropped");
+        }
+    }
+}
+
+async fn write_file(mut file: tokio::fs::File, mut entries: mpsc::UnboundedReceiver<JournalEntry>) {
+    while let Some(entry) = entries.recv().await {
+        let mut line = match serde_json::to_string(&entry) {
+            Ok(line) => line,
+            Err(e) => {
+                error!("Failed to serialize journal entry: {}", e);
+                continue;
+            }
+        };
+        line.push('\n');
+
+        if let Err(e) = file.write_all(line.as_bytes()).await {
+            error!("Failed to write order journal: {}", e);
+            continue;
+        }
+        if let Err(e) = file.flush().await {
+            warn!("Failed to flush order journal: {}", e);
+        }
+    }
+}
+
+async fn write_stream(
+    mut conn: redis::aio::ConnectionManager,
+    stream: String,
+    mut entries: mpsc::UnboundedReceiver<JournalEntry>,
+) {
+    while let Some(entry) = entries.recv().await {
+        let data = match serde_json::to_string(&entry) {
+            Ok(data) => data,
+            Err(e) => {
+                error!("Failed to serialize journal entry: {}", e);
+                continue;
+            }
+        };
+
+        let written: redis::RedisResult<String> = conn.xadd(&stream, "*", &[("data", data)]).await;
+        if let Err(e) = written {
+            error!("Failed to write order journal to {}: {}", stream, e);
+        }
+    }
+}
+
+/// Adapter wrapper that journals every order placement and cancellation.
+/// Everything else is passed straight through to the inner adapter.
+pub struct JournaledAdapter {
+    inner: Box<dyn ExchangeAdapter>,
+    journal: Arc<OrderJournal>,
+}
+
+impl JournaledAdapter {
+    pub fn new(inner: Box<dyn ExchangeAdapter>, journal: Arc<OrderJournal>) -> Self {
+        Self { inner, journal }
+    }
+
+    fn record(
+        &self,
+        action: &'static str,
+        request: serde_json::Value,
+        result: &Result<OrderResponse>,
+    ) {
+        self.journal.record(JournalEntry {
+            timestamp: Utc::now(),
+            trade_id: TRADE_ID.try_with(|id| *id).ok(),
+            exchange: self.inner.id().to_string(),
+            action,
+            request,
+            response: result.as_ref().ok().cloned(),
+            error: result.as_ref().err().map(|e| format!("{:#}", e)),
+        });
+    }
+}
+
+#[async_trait]
+impl ExchangeAdapter for JournaledAdapter {
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    async fn place_order(
+        &self,
+        credentials: &Credentials,
+        request: &OrderRequest,
+    ) -> Result<OrderResponse> {
+        let result = self.inner.place_order(credentials, request).await;
+        self.record(
+            "place",
+            serde_json::to_value(request).unwrap_or_default(),
+            &result,
+        );
+        result
+    }
+
+    async fn place_order_ws(
+        &self,
+        credentials: &Credentials,
+        request: &OrderRequest,
+    ) -> Result<OrderResponse> {
+        let result = self.inner.place_order_ws(credentials, request).await;
+        self.record(
+            "place",
+            serde_json::to_value(request).unwrap_or_default(),
+            &result,
+        );
+        result
+    }
+
+    async fn cancel_order(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        let result = self.inner.cancel_order(credentials, symbol, order_id).await;
+        self.record(
+            "cancel",
+            serde_json::json!({ "symbol": symbol, "order_id": order_id }),
+            &result,
+        );
+        result
+    }
+
+    async fn cancel_orders_batch(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_ids: &[String],
+    ) -> Result<()> {
+        let result = self.inner.cancel_orders_batch(credentials, symbol, order_ids).await;
+        self.journal.record(JournalEntry {
+            timestamp: Utc::now(),
+            trade_id: TRADE_ID.try_with(|id| *id).ok(),
+            exchange: self.inner.id().to_string(),
+            action: "cancel_batch",
+            request: serde_json::json!({ "symbol": symbol, "order_ids": order_ids }),
+            response: None,
+            error: result.as_ref().err().map(|e| format!("{:#}", e)),
+        });
+        result
+    }
+
+    async fn cancel_all_orders(&self, credentials: &Credentials, symbol: &str) -> Result<()> {
+        let result = self.inner.cancel_all_orders(credentials, symbol).await;
+        self.journal.record(JournalEntry {
+            timestamp: Utc::now(),
+            trade_id: TRADE_ID.try_with(|id| *id).ok(),
+            exchange: self.inner.id().to_string(),
+            action: "cancel_all",
+            request: serde_json::json!({ "symbol": symbol }),
+            response: None,
+            error: result.as_ref().err().map(|e| format!("{:#}", e)),
+        });
+        result
+    }
+
+    fn supports_trailing_stop(&self, trail: Trail) -> bool {
+        self.inner.supports_trailing_stop(trail)
+    }
+
+    async fn place_trailing_stop(
+        &self,
+        credentials: &Credentials,
+        request: &TrailingStopRequest,
+    ) -> Result<OrderResponse> {
+        let result = self.inner.place_trailing_stop(credentials, request).await;
+        self.record(
+            "place_trailing_stop",
+            serde_json::to_value(request).unwrap_or_default(),
+            &result,
+        );
+        result
+    }
+
+    fn supports_stop_order(&self) -> bool {
+        self.inner.supports_stop_order()
+    }
+
+    async fn place_stop_order(
+        &self,
+        credentials: &Credentials,
+        request: &StopOrderRequest,
+    ) -> Result<OrderResponse> {
+        let result = self.inner.place_stop_order(credentials, request).await;
+        self.record(
+            "place_stop",
+            serde_json::to_value(request).unwrap_or_default(),
+            &result,
+        );
+        result
+    }
+
+    async fn amend_stop_order(
+        &self,
+        credentials: &Credentials,
+        order_id: &str,
+        request: &StopOrderRequest,
+    ) -> Result<OrderResponse> {
+        let result = self.inner.amend_stop_order(credentials, order_id, request).await;
+        self.record(
+            "amend_stop",
+            serde_json::json!({ "order_id": order_id, "request": request }),
+            &result,
+        );
+        result
+    }
+
+    async fn get_stop_order(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        self.inner.get_stop_order(credentials, symbol, order_id).await
+    }
+
+    async fn cancel_stop_order(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        let result = self.inner.cancel_stop_order(credentials, symbol, order_id).await;
+        self.record(
+            "cancel_stop",
+            serde_json::json!({ "symbol": symbol, "order_id": order_id }),
+            &result,
+        );
+        result
+    }
+
+    fn supports_algo_order(&self, kind: AlgoKind) -> bool {
+        self.inner.supports_algo_order(kind)
+    }
+
+    fn min_algo_interval_secs(&self, kind: AlgoKind) -> u64 {
+        self.inner.min_algo_interval_secs(kind)
+    }
+
+    async fn place_algo_order(
+        &self,
+        credentials: &Credentials,
+        request: &AlgoOrderRequest,
+    ) -> Result<OrderResponse> {
+        let result = self.inner.place_algo_order(credentials, request).await;
+        self.record(
+            "place_algo",
+            serde_json::to_value(request).unwrap_or_default(),
+            &result,
+        );
+        result
+    }
+
+    async fn get_algo_order(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        algo_id: &str,
+    ) -> Result<OrderResponse> {
+        self.inner.get_algo_order(credentials, symbol, algo_id).await
+    }
+
+    async fn cancel_algo_order(&self, credentials: &Credentials, symbol: &str, algo_id: &str) -> Result<()> {
+        let result = self.inner.cancel_algo_order(credentials, symbol, algo_id).await;
+        self.journal.record(JournalEntry {
+            timestamp: Utc::now(),
+            trade_id: TRADE_ID.try_with(|id| *id).ok(),
+            exchange: self.inner.id().to_string(),
+            action: "cancel_algo",
+            request: serde_json::json!({ "symbol": symbol, "algo_id": algo_id }),
+            response: None,
+            error: result.as_ref().err().map(|e| format!("{:#}", e)),
+        });
+        result
+    }
+
+    async fn get_order(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        self.inner.get_order(credentials, symbol, order_id).await
+    }
+
+    async fn get_orders_batch(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_ids: &[String],
+    ) -> Result<Vec<OrderResponse>> {
+        self.inner.get_orders_batch(credentials, symbol, order_ids).await
+    }
+
+    async fn get_open_orders(&self, credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        self.inner.get_open_orders(credentials, symbol).await
+    }
+
+    async fn get_fills(
+        &self,
+        credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<Vec<Fill>> {
+        self.inner.get_fills(credentials, symbol, order_id).await
+    }
+
+    async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
+        self.inner.get_best_price(symbol).await
+    }
+
+    async fn get_reference_price(&self, symbol: &str, source: ReferencePriceSource) -> Result<Decimal> {
+        self.inner.get_reference_price(symbol, source).await
+    }
+
+    async fn get_symbol_info(&self, symbol: &str) -> Result<SymbolInfo> {
+        self.inner.get_symbol_info(symbol).await
+    }
+
+    async fn get_order_book(&self, symbol: &str, depth: usize) -> Result<OrderBook> {
+        self.inner.get_order_book(symbol, depth).await
+    }
+
+    async fn get_server_time(&self) -> Result<i64> {
+        self.inner.get_server_time().await
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    fn to_native_symbol(&self, base: &str, quote: &str) -> String {
+        self.inner.to_native_symbol(base, quote)
+    }
+
+    fn to_canonical_symbol(&self, native: &str) -> Option<String> {
+        self.inner.to_canonical_symbol(native)
+    }
+
+    async fn load_quote_assets(&self) -> Result<()> {
+        self.inner.load_quote_assets().await
+    }
+
+    fn native_symbol(&self, symbol: &str) -> String {
+        self.inner.native_symbol(symbol)
+    }
+
+    fn contract_spec(&self, symbol: &str) -> ContractSpec {
+        self.inner.contract_spec(symbol)
+    }
+
+    fn supports_reduce_only_market(&self) -> bool {
+        self.inner.supports_reduce_only_market()
+    }
+
+    fn supports_gtd(&self, expire_at_ms: i64) -> bool {
+        self.inner.supports_gtd(expire_at_ms)
+    }
+
+    async fn get_position_mode(&self, credentials: &Credentials) -> Result<bool> {
+        self.inner.get_position_mode(credentials).await
+    }
+
+    async fn set_position_mode(&self, credentials: &Credentials, hedge: bool) -> Result<()> {
+        self.inner.set_position_mode(credentials, hedge).await
+    }
+
+    async fn get_leverage(&self, credentials: &Credentials, symbol: &str) -> Result<LeverageInfo> {
+        self.inner.get_leverage(credentials, symbol).await
+    }
+
+    async fn get_risk_limit(&self, credentials: &Credentials, symbol: &str) -> Result<RiskLimit> {
+        self.inner.get_risk_limit(credentials, symbol).await
+    }
+
+    async fn set_risk_limit(&self, credentials: &Credentials, symbol: &str, notional_usd: Decimal) -> Result<RiskLimit> {
+        self.inner.set_risk_limit(credentials, symbol, notional_usd).await
+    }
+
+    async fn get_positions(&self, credentials: &Credentials) -> Result<Vec<Position>> {
+        self.inner.get_positions(credentials).await
+    }
+
+    fn supports_cancel_on_disconnect(&self) -> bool {
+        self.inner.supports_cancel_on_disconnect()
+    }
+
+    async fn set_cancel_on_disconnect(&self, credentials: &Credentials, timeout_secs: u64) -> Result<()> {
+        self.inner.set_cancel_on_disconnect(credentials, timeout_secs).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{credentials, MockAdapter};
+    use crate::exchange::{OrderType, QuantityMode, Side, TimeInForce};
+    use rust_decimal_macros::dec;
+    use std::time::Duration;
+
+    fn request(client_order_id: &str) -> OrderRequest {
+        OrderRequest {
+            client_order_id: client_order_id.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some(dec!(100)),
+            quantity: dec!(1),
+            reduce_only: false,
+            time_in_force: TimeInForce::Gtc,
+            quantity_mode: QuantityMode::Base,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_orders_are_journaled_to_file() {
+        let path = std::env::temp_dir().join(format!("journal-{}.jsonl", Uuid::new_v4()));
+        let sink = JournalSink::File(path.display().to_string());
+        let journal = Arc::new(OrderJournal::open(&sink, "").await.unwrap());
+
+        let adapter = JournaledAdapter::new(
+            Box::new(MockAdapter::new("mock", dec!(100), dec!(101)).with_place_handler(
+                |index, request| match index {
+                    0 => Ok(crate::exchange::mock::response_for(
+                        request,
+                        crate::exchange::OrderStatus::Filled,
+                        request.quantity,
+                        request.price,
+                    )),
+                    _ => anyhow::bail!("insufficient margin"),
+                },
+            )),
+            journal,
+        );
+
+        let trade_id = Uuid::new_v4();
+        TRADE_ID
+            .scope(trade_id, async {
+                adapter.place_order(&credentials(), &request("a")).await.unwrap();
+                adapter.place_order(&credentials(), &request("b")).await.unwrap_err();
+            })
+            .await;
+
+        // The writer runs in the background
+        let mut lines = Vec::new();
+        for _ in 0..50 {
+            let contents = tokio::fs::read_to_string(&path).await.unwrap_or_default();
+            lines = contents.lines().map(str::to_string).collect();
+            if lines.len() == 2 {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(lines.len(), 2);
+        let placed: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+        let rejected: serde_json::Value = serde_json::from_str(&lines[1]).unwrap();
+
+        assert_eq!(placed["trade_id"], trade_id.to_string());
+        assert_eq!(placed["exchange"], "mock");
+        assert_eq!(placed["action"], "place");
+        assert_eq!(placed["request"]["client_order_id"], "a");
+        assert_eq!(placed["response"]["status"], "filled");
+        assert!(placed["error"].is_null());
+
+        assert_eq!(rejected["request"]["client_order_id"], "b");
+        assert!(rejected["response"].is_null());
+        assert_eq!(rejected["error"], "insufficient margin");
+    }
+}
@@ -0,0 +1,390 @@
+//! API key pools
+//!
+//! An account can register extra API keys so that order polling and cancels,
+//! which dominate request volume while slicing, are spread over several rate
+//! limit buckets.
+//!
+//! Placement always stays on the primary key named in the request. Retrying a
+//! placement relies on the exchange rejecting a repeated client order id, and
+//! keeping every placement on one key keeps that check, the key's own order
+//! limits and any IP allowlist in one place. Cancels and reads are safe to
+//! repeat on any key. All keys in a pool must belong to the same account (not
+//! sub-accounts), or orders placed with one key are invisible to the others.
+
+use anyhow::Result;
+use async_trait::async_trait;
+use rand::Rng;
+use rust_decimal::Decimal;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::config::KeySelection;
+use crate::exchange::{
+    AlgoKind, AlgoOrderRequest, ContractSpec, Credentials, ExchangeAdapter, Fill, LeverageInfo, OrderBook,
+    OrderRequest, OrderResponse, Position, ReferencePriceSource, RiskLimit, StopOrderRequest, SymbolInfo, Trail,
+    TrailingStopRequest,
+};
+
+/// An account's keys: the one that places orders and any that may serve reads
+#[derive(Debug, Clone)]
+pub struct KeyPool {
+    pub primary: Credentials,
+    pub read_keys: Vec<Credentials>,
+}
+
+impl KeyPool {
+    pub fn single(primary: Credentials) -> Self {
+        Self {
+            primary,
+            read_keys: Vec::new(),
+        }
+    }
+}
+
+/// Adapter wrapper that sends reads and cancels through a pool of keys.
+/// Placement and account settings use the credentials passed by the caller.
+pub struct PooledAdapter {
+    inner: Arc<dyn ExchangeAdapter>,
+    keys: Vec<Credentials>,
+    selection: KeySelection,
+    next: AtomicUsize,
+}
+
+impl PooledAdapter {
+    /// Wrap `inner` when the pool has keys to spread over and the selection
+    /// policy asks for it, otherwise return it unchanged
+    pub fn wrap(
+        inner: Arc<dyn ExchangeAdapter>,
+        pool: &KeyPool,
+        selection: KeySelection,
+    ) -> Arc<dyn ExchangeAdapter> {
+        if selection == KeySelection::Primary || pool.read_keys.is_empty() {
+            return inner;
+        }
+
+        let mut keys = vec![pool.primary.clone()];
+        keys.extend(pool.read_keys.iter().cloned());
+        Arc::new(Self {
+            inner,
+            keys,
+            selection,
+            next: AtomicUsize::new(0),
+        })
+    }
+
+    fn read_key(&self) -> &Credentials {
+        let index = match self.selection {
+            KeySelection::Random => rand::thread_rng().gen_range(0..self.keys.len()),
+            _ => self.next.fetch_add(1, Ordering::Relaxed) % self.keys.len(),
+        };
+        &self.keys[index]
+    }
+}
+
+#[async_trait]
+impl ExchangeAdapter for PooledAdapter {
+    fn id(&self) -> &str {
+        self.inner.id()
+    }
+
+    async fn place_order(
+        &self,
+        credentials: &Credentials,
+        request: &OrderRequest,
+    ) -> Result<OrderResponse> {
+        self.inner.place_order(credentials, request).await
+    }
+
+    async fn place_order_ws(
+        &self,
+        credentials: &Credentials,
+        request: &OrderRequest,
+    ) -> Result<OrderResponse> {
+        self.inner.place_order_ws(credentials, request).await
+    }
+
+    fn supports_trailing_stop(&self, trail: Trail) -> bool {
+        self.inner.supports_trailing_stop(trail)
+    }
+
+    async fn place_trailing_stop(
+        &self,
+        credentials: &Credentials,
+        request: &TrailingStopRequest,
+    ) -> Result<OrderResponse> {
+        self.inner.place_trailing_stop(credentials, request).await
+    }
+
+    fn supports_stop_order(&self) -> bool {
+        self.inner.supports_stop_order()
+    }
+
+    async fn place_stop_order(
+        &self,
+        credentials: &Credentials,
+        request: &StopOrderRequest,
+    ) -> Result<OrderResponse> {
+        self.inner.place_stop_order(credentials, request).await
+    }
+
+    async fn amend_stop_order(
+        &self,
+        credentials: &Credentials,
+        order_id: &str,
+        request: &StopOrderRequest,
+    ) -> Result<OrderResponse> {
+        self.inner.amend_stop_order(credentials, order_id, request).await
+    }
+
+    async fn get_stop_order(
+        &self,
+        _credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        self.inner.get_stop_order(self.read_key(), symbol, order_id).await
+    }
+
+    async fn cancel_stop_order(
+        &self,
+        _credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        self.inner.cancel_stop_order(self.read_key(), symbol, order_id).await
+    }
+
+    fn supports_algo_order(&self, kind: AlgoKind) -> bool {
+        self.inner.supports_algo_order(kind)
+    }
+
+    fn min_algo_interval_secs(&self, kind: AlgoKind) -> u64 {
+        self.inner.min_algo_interval_secs(kind)
+    }
+
+    async fn place_algo_order(
+        &self,
+        credentials: &Credentials,
+        request: &AlgoOrderRequest,
+    ) -> Result<OrderResponse> {
+        self.inner.place_algo_order(credentials, request).await
+    }
+
+    async fn get_algo_order(
+        &self,
+        _credentials: &Credentials,
+        symbol: &str,
+        algo_id: &str,
+    ) -> Result<OrderResponse> {
+        self.inner.get_algo_order(self.read_key(), symbol, algo_id).await
+    }
+
+    async fn cancel_algo_order(&self, _credentials: &Credentials, symbol: &str, algo_id: &str) -> Result<()> {
+        self.inner.cancel_algo_order(self.read_key(), symbol, algo_id).await
+    }
+
+    async fn cancel_order(
+        &self,
+        _credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        self.inner.cancel_order(self.read_key(), symbol, order_id).await
+    }
+
+    async fn cancel_orders_batch(
+        &self,
+        _credentials: &Credentials,
+        symbol: &str,
+        order_ids: &[String],
+    ) -> Result<()> {
+        self.inner.cancel_orders_batch(self.read_key(), symbol, order_ids).await
+    }
+
+    async fn cancel_all_orders(&self, _credentials: &Credentials, symbol: &str) -> Result<()> {
+        self.inner.cancel_all_orders(self.read_key(), symbol).await
+    }
+
+    async fn get_order(
+        &self,
+        _credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<OrderResponse> {
+        self.inner.get_order(self.read_key(), symbol, order_id).await
+    }
+
+    async fn get_orders_batch(
+        &self,
+        _credentials: &Credentials,
+        symbol: &str,
+        order_ids: &[String],
+    ) -> Result<Vec<OrderResponse>> {
+        self.inner.get_orders_batch(self.read_key(), symbol, order_ids).await
+    }
+
+    async fn get_open_orders(&self, _credentials: &Credentials, symbol: &str) -> Result<Vec<OrderResponse>> {
+        self.inner.get_open_orders(self.read_key(), symbol).await
+    }
+
+    async fn get_fills(
+        &self,
+        _credentials: &Credentials,
+        symbol: &str,
+        order_id: &str,
+    ) -> Result<Vec<Fill>> {
+        self.inner.get_fills(self.read_key(), symbol, order_id).await
+    }
+
+    async fn get_best_price(&self, symbol: &str) -> Result<(Decimal, Decimal)> {
+        self.inner.get_best_price(symbol).await
+    }
+
+    async fn get_reference_price(&self, symbol: &str, source: ReferencePriceSource) -> Result<Decimal> {
+        self.inner.get_reference_price(symbol, source).await
+    }
+
+    async fn get_symbol_info(&self, symbol: &str) -> Result<SymbolInfo> {
+        self.inner.get_symbol_info(symbol).await
+    }
+
+    async fn get_order_book(&self, symbol: &str, depth: usize) -> Result<OrderBook> {
+        self.inner.get_order_book(symbol, depth).await
+    }
+
+    async fn get_server_time(&self) -> Result<i64> {
+        self.inner.get_server_time().await
+    }
+
+    fn is_connected(&self) -> bool {
+        self.inner.is_connected()
+    }
+
+    fn to_native_symbol(&self, base: &str, quote: &str) -> String {
+        self.inner.to_native_symbol(base, quote)
+    }
+
+    fn to_canonical_symbol(&self, native: &str) -> Option<String> {
+        self.inner.to_canonical_symbol(native)
+    }
+
+    async fn load_quote_assets(&self) -> Result<()> {
+        self.inner.load_quote_assets().await
+    }
+
+    fn native_symbol(&self, symbol: &str) -> String {
+        self.inner.native_symbol(symbol)
+    }
+
+    fn contract_spec(&self, symbol: &str) -> ContractSpec {
+        self.inner.contract_spec(symbol)
+    }
+
+    fn supports_reduce_only_market(&self) -> bool {
+        self.inner.supports_reduce_only_market()
+    }
+
+    fn supports_gtd(&self, expire_at_ms: i64) -> bool {
+        self.inner.supports_gtd(expire_at_ms)
+    }
+
+    async fn get_position_mode(&self, credentials: &Credentials) -> Result<bool> {
+        self.inner.get_position_mode(credentials).await
+    }
+
+    async fn set_position_mode(&self, credentials: &Credentials, hedge: bool) -> Result<()> {
+        self.inner.set_position_mode(credentials, hedge).await
+    }
+
+    async fn get_leverage(&self, _credentials: &Credentials, symbol: &str) -> Result<LeverageInfo> {
+        self.inner.get_leverage(self.read_key(), symbol).await
+    }
+
+    async fn get_risk_limit(&self, _credentials: &Credentials, symbol: &str) -> Result<RiskLimit> {
+        self.inner.get_risk_limit(self.read_key(), symbol).await
+    }
+
+    async fn set_risk_limit(&self, credentials: &Credentials, symbol: &str, notional_usd: Decimal) -> Result<RiskLimit> {
+        self.inner.set_risk_limit(credentials, symbol, notional_usd).await
+    }
+
+    async fn get_positions(&self, _credentials: &Credentials) -> Result<Vec<Position>> {
+        self.inner.get_positions(self.read_key()).await
+    }
+
+    fn supports_cancel_on_disconnect(&self) -> bool {
+        self.inner.supports_cancel_on_disconnect()
+    }
+
+    async fn set_cancel_on_disconnect(&self, credentials: &Credentials, timeout_secs: u64) -> Result<()> {
+        self.inner.set_cancel_on_disconnect(credentials, timeout_secs).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{credentials, MockAdapter};
+    use crate::exchange::{OrderType, QuantityMode, Side, TimeInForce};
+    use rust_decimal_macros::dec;
+
+    fn key(api_key: &str) -> Credentials {
+        Credentials {
+            api_key: api_key.to_string(),
+            ..credentials()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reads_rotate_while_placement_stays_on_primary() {
+        let mock = Arc::new(MockAdapter::new("mock", dec!(100), dec!(101)));
+        let pool = KeyPool {
+            primary: key("primary"),
+            read_keys: vec![key("read-1"), key("read-2")],
+        };
+        let adapter = PooledAdapter::wrap(mock.clone(), &pool, KeySelection::RoundRobin);
+
+        let request = OrderRequest {
+            client_order_id: "cs1".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some(dec!(100)),
+            quantity: dec!(1),
+            reduce_only: false,
+            time_in_force: TimeInForce::Gtc,
+            quantity_mode: QuantityMode::Base,
+        };
+        let placed = adapter.place_order(&pool.primary, &request).await.unwrap();
+        for _ in 0..3 {
+            adapter
+                .get_order(&pool.primary, "BTCUSDT", &placed.exchange_order_id)
+                .await
+                .unwrap();
+        }
+        adapter
+            .cancel_order(&pool.primary, "BTCUSDT", &placed.exchange_order_id)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            mock.api_keys_used(),
+            ["primary", "primary", "read-1", "read-2", "primary"]
+        );
+    }
+
+    #[test]
+    fn test_single_key_or_primary_policy_is_not_wrapped() {
+        let mock: Arc<dyn ExchangeAdapter> = Arc::new(MockAdapter::new("mock", dec!(100), dec!(101)));
+        let pool = KeyPool {
+            primary: key("primary"),
+            read_keys: vec![key("read-1")],
+        };
+
+        let unpooled = PooledAdapter::wrap(mock.clone(), &KeyPool::single(key("primary")), KeySelection::RoundRobin);
+        let primary_only = PooledAdapter::wrap(mock.clone(), &pool, KeySelection::Primary);
+
+        assert!(Arc::ptr_eq(&unpooled, &mock));
+        assert!(Arc::ptr_eq(&primary_only, &mock));
+    }
+}
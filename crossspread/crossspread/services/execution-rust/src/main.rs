@@ -4,20 +4,44 @@
 //! Handles sliced limit order placement across multiple exchanges.
 
 use anyhow::Result;
-use tracing::{info, Level};
-use tracing_subscriber::FmtSubscriber;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tracing::{error, info, warn};
+use tracing_subscriber::{EnvFilter, FmtSubscriber};
 
+mod algorithm;
+mod api;
+mod cancel_on_disconnect;
+mod clock;
 mod config;
+mod credentials;
 mod crypto;
+mod dead_letter;
 mod exchange;
+mod feed;
+mod health;
+mod history;
+mod journal;
+mod key_pool;
+mod maintenance;
+mod open_orders;
 mod order;
+mod order_store;
+mod positions;
+mod rounding;
 mod slicer;
+mod symbol_policy;
+mod trailing;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
+    // Initialize tracing. Raw HTTP bodies are only traced for exchanges
+    // listed in LOG_RAW_HTTP, so that module is let through at trace level.
     let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::INFO)
+        .with_env_filter(EnvFilter::new(format!(
+            "info,{}::exchange::raw_http=trace",
+            env!("CARGO_CRATE_NAME")
+        )))
         .with_target(false)
         .init();
 
@@ -27,17 +51,65 @@ async fn main() -> Result<()> {
     let config = config::Config::from_env()?;
     info!("Loaded configuration for {} exchanges", config.exchanges.len());
 
-    // Initialize exchange adapters
+    // Initialize exchange adapters. A venue that fails to initialize is marked
+    // unavailable rather than preventing the others from trading.
     let mut adapters = Vec::new();
+    let mut unavailable = HashMap::new();
     for exchange_config in &config.exchanges {
-        let adapter = exchange::create_adapter(exchange_config).await?;
-        adapters.push(adapter);
-        info!("Initialized {} adapter", exchange_config.id);
+        match exchange::create_adapter(exchange_config).await {
+            Ok(adapter) => {
+                if let Err(e) = adapter.load_quote_assets().await {
+                    warn!("{} quote assets failed to load, its symbols are reported native: {:#}", exchange_config.id, e);
+                }
+                adapters.push(adapter);
+                info!("Initialized {} adapter", exchange_config.id);
+            }
+            Err(e) => {
+                error!("Failed to initialize {} adapter: {:#}", exchange_config.id, e);
+                unavailable.insert(exchange_config.id.clone(), format!("{:#}", e));
+            }
+        }
     }
 
+    if adapters.is_empty() {
+        warn!("No exchange adapters initialized, all trades will fail");
+    }
+
+    if let Some(sink) = &config.order_journal {
+        let journal = Arc::new(journal::OrderJournal::open(sink, &config.redis_url).await?);
+        info!("Journaling orders to {:?}", sink);
+        adapters = adapters
+            .into_iter()
+            .map(|adapter| {
+                Box::new(journal::JournaledAdapter::new(adapter, journal.clone()))
+                    as Box<dyn exchange::ExchangeAdapter>
+            })
+            .collect();
+    }
+
+    let credential_source = credentials::create_credential_source(&config)?;
+    // Execution algorithms of our own are registered alongside the
+    // built-ins, for SLICING_ALGORITHM and symbol profiles to pick by name
+    let algorithms = algorithm::AlgorithmRegistry::default();
+
     // Start the order execution server
-    let server = order::ExecutionServer::new(adapters, config.clone());
-    server.run().await?;
+    let server = order::ExecutionServer::new(adapters, config.clone())
+        .with_unavailable_adapters(unavailable)
+        .with_credential_source(credential_source)
+        .with_algorithms(algorithms)?
+        .with_redis(
+            redis::Client::open(config.redis_url.as_str())?
+                .get_connection_manager()
+                .await?,
+        );
+    let server = if config.record_trade_history {
+        let store = history::PgHistoryStore::new(&config.database_url)?;
+        server.with_history(history::TradeHistory::start(Arc::new(store), std::time::Duration::from_secs(1)))
+    } else {
+        server
+    };
+    server.warmup(&config.warmup_symbols).await;
+    Arc::new(server).run().await?;
 
     Ok(())
 }
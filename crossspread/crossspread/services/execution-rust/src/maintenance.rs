@@ -0,0 +1,131 @@
+//! Exchange maintenance windows
+//!
+//! Venues take their order endpoints down for scheduled maintenance, and
+//! every order sent meanwhile fails. Adapters report those failures as
+//! `ExchangeError::Maintenance`. The exchange is then flagged, and trades on
+//! it are turned away before placing anything rather than sliced into the
+//! same error. A cheap request, the server time, is retried on a timer while
+//! the flag is up and clears it once the venue stops answering with
+//! maintenance.
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+use crate::exchange::{ExchangeAdapter, ExchangeError};
+
+/// An exchange in maintenance
+#[derive(Debug, Clone, Serialize)]
+pub struct MaintenanceWindow {
+    pub since: DateTime<Utc>,
+    /// The error that flagged it
+    pub detail: String,
+}
+
+#[derive(Default)]
+pub struct MaintenanceMonitor {
+    flagged: RwLock<HashMap<String, MaintenanceWindow>>,
+}
+
+impl MaintenanceMonitor {
+    /// Flag `exchange_id` as in maintenance, unless it already is
+    pub async fn flag(&self, exchange_id: &str, detail: &str) {
+        let mut flagged = self.flagged.write().await;
+        if flagged.contains_key(exchange_id) {
+            return;
+        }
+        warn!("{} is in maintenance, holding trades on it: {}", exchange_id, detail);
+        flagged.insert(
+            exchange_id.to_string(),
+            MaintenanceWindow {
+                since: Utc::now(),
+                detail: detail.to_string(),
+            },
+        );
+    }
+
+    /// Fail if `exchange_id` is flagged as in maintenance
+    pub async fn check(&self, exchange_id: &str) -> Result<()> {
+        if let Some(window) = self.flagged.read().await.get(exchange_id) {
+            anyhow::bail!(
+                "Exchange {} has been in maintenance since {}: {}",
+                exchange_id,
+                window.since.to_rfc3339(),
+                window.detail
+            );
+        }
+        Ok(())
+    }
+
+    /// Ask a flagged exchange for its time, and clear the flag unless it
+    /// still answers with maintenance. Other failures clear it too: if the
+    /// venue is still down the next order flags it again.
+    pub async fn probe(&self, adapter: &dyn ExchangeAdapter) {
+        if !self.flagged.read().await.contains_key(adapter.id()) {
+            return;
+        }
+        match adapter.get_server_time().await {
+            Err(e) if ExchangeError::is_maintenance(&e) => {
+                debug!("{} is still in maintenance: {:#}", adapter.id(), e);
+                return;
+            }
+            Err(e) => debug!("Maintenance probe of {} failed: {:#}", adapter.id(), e),
+            Ok(_) => {}
+        }
+        if let Some(window) = self.flagged.write().await.remove(adapter.id()) {
+            info!(
+                "{} is out of maintenance after {} s",
+                adapter.id(),
+                (Utc::now() - window.since).num_seconds()
+            );
+        }
+    }
+
+    /// Probe flagged exchanges each `interval`, forever
+    pub async fn run(self: Arc<Self>, adapters: Vec<Arc<dyn ExchangeAdapter>>, interval: Duration) {
+        loop {
+            tokio::time::sleep(interval).await;
+            for adapter in &adapters {
+                self.probe(adapter.as_ref()).await;
+            }
+        }
+    }
+
+    /// Exchanges currently in maintenance
+    pub async fn windows(&self) -> HashMap<String, MaintenanceWindow> {
+        self.flagged.read().await.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::MockAdapter;
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn test_flag_holds_until_the_probe_stops_seeing_maintenance() {
+        let monitor = MaintenanceMonitor::default();
+        let mock = MockAdapter::new("mock", dec!(100), dec!(101)).with_clock_offset(0);
+        mock.set_maintenance(true);
+
+        monitor.check("mock").await.unwrap();
+        monitor.flag("mock", "503 Service Unavailable").await;
+        let err = monitor.check("mock").await.unwrap_err();
+        assert!(err.to_string().contains("in maintenance"), "{}", err);
+        assert_eq!(monitor.windows().await["mock"].detail, "503 Service Unavailable");
+
+        monitor.probe(&mock).await;
+        assert!(monitor.check("mock").await.is_err());
+
+        mock.set_maintenance(false);
+        monitor.probe(&mock).await;
+        monitor.check("mock").await.unwrap();
+        assert!(monitor.windows().await.is_empty());
+    }
+}
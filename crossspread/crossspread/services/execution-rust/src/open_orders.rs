@@ -0,0 +1,53 @@
+//! Open order limits
+//!
+//! Exchanges cap how many orders an account may rest at once (Binance
+//! allows 200 per symbol). Each slice takes a slot for its exchange, account
+//! and symbol before it is placed and gives it back once the order is done,
+//! so concurrent trades on one symbol from one account stay under the cap
+//! together. Accounts are told apart by their API key id.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use uuid::Uuid;
+
+pub struct OpenOrderLimits {
+    max_open: usize,
+    slots: Mutex<HashMap<(String, Uuid, String), Arc<Semaphore>>>,
+}
+
+impl OpenOrderLimits {
+    pub fn new(max_open: usize) -> Self {
+        Self {
+            max_open: max_open.max(1),
+            slots: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn semaphore(&self, exchange_id: &str, api_key_id: Uuid, symbol: &str) -> Arc<Semaphore> {
+        self.slots
+            .lock()
+            .unwrap()
+            .entry((exchange_id.to_string(), api_key_id, symbol.to_string()))
+            .or_insert_with(|| Arc::new(Semaphore::new(self.max_open)))
+            .clone()
+    }
+
+    /// Take a slot if one is free. The slot is released when the permit drops.
+    pub fn try_acquire(&self, exchange_id: &str, api_key_id: Uuid, symbol: &str) -> Option<OwnedSemaphorePermit> {
+        self.semaphore(exchange_id, api_key_id, symbol).try_acquire_owned().ok()
+    }
+
+    /// Wait for a slot to free up
+    pub async fn acquire(&self, exchange_id: &str, api_key_id: Uuid, symbol: &str) -> OwnedSemaphorePermit {
+        self.semaphore(exchange_id, api_key_id, symbol)
+            .acquire_owned()
+            .await
+            .expect("open order semaphores are never closed")
+    }
+
+    /// Orders currently holding a slot
+    pub fn open_orders(&self, exchange_id: &str, api_key_id: Uuid, symbol: &str) -> usize {
+        self.max_open - self.semaphore(exchange_id, api_key_id, symbol).available_permits()
+    }
+}
@@ -3,20 +3,87 @@
 //! Handles order requests from the backend API via Redis
 
 use anyhow::Result;
+use chrono::Utc;
 use redis::aio::ConnectionManager;
 use redis::AsyncCommands;
+use rust_decimal::prelude::ToPrimitive;
 use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::{error, info, warn};
+use tokio::sync::{RwLock, Semaphore};
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
-use crate::config::Config;
-use crate::crypto::decrypt_credentials;
-use crate::exchange::{Credentials, ExchangeAdapter, Side};
-use crate::slicer::{OrderSlicer, SlicingConfig};
+use crate::algorithm::AlgorithmRegistry;
+use crate::api;
+use crate::cancel_on_disconnect::CancelOnDisconnect;
+use crate::clock::ClockMonitor;
+use crate::config::{Config, ExchangeConfig};
+use crate::credentials::CredentialSource;
+use crate::dead_letter::DeadLetterFile;
+use crate::health;
+use crate::history::TradeHistory;
+use crate::journal::TRADE_ID;
+use crate::key_pool::{KeyPool, PooledAdapter};
+use crate::maintenance::MaintenanceMonitor;
+use crate::open_orders::OpenOrderLimits;
+use crate::order_store::OrderStore;
+use crate::positions::{PositionTracker, TrackedLeg, TrackedPosition};
+use crate::exchange::{
+    generate_client_order_id, position_side, ContractSpec, ContractType, Credentials, ExchangeAdapter,
+    ExchangeError, OrderResponse, OrderStatus, ReferencePriceSource, DEFAULT_CLIENT_ORDER_ID_PREFIX, Side, SymbolInfo, Trail, TrailingStopRequest,
+};
+use crate::slicer::{
+    calculate_limit_price, OrderSlicer, PricingLadder, SlicePlan, SliceResult, SlicedOrderResult, SlicingConfig,
+    SlicingStrategy, SpreadTarget,
+};
+use crate::trailing::EmulatedTrail;
+
+/// Stream the backend publishes execution requests on
+const REQUEST_STREAM: &str = "execution:requests";
+/// Stream operators publish control messages (e.g. kill switch) on
+const CONTROL_STREAM: &str = "execution:control";
+
+/// Stream the backend reads execution results from
+const RESULT_STREAM: &str = "execution:results";
+
+/// Attempts to publish a result before it goes to the dead-letter file
+const PUBLISH_ATTEMPTS: u32 = 5;
+
+/// Wait before the first publish retry, doubled on each further retry
+const PUBLISH_BACKOFF: std::time::Duration = std::time::Duration::from_millis(200);
+/// Wait before an entry leg's first retry, doubled on each further retry
+const LEG_RETRY_BACKOFF: std::time::Duration = std::time::Duration::from_millis(500);
+/// How long loaded credentials are reused before being read again
+const CREDENTIAL_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(300);
+
+/// How long a symbol's trading status and contract size are trusted before
+/// they are re-read
+const SYMBOL_INFO_CACHE_TTL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// How often each exchange's clock is compared with ours
+const CLOCK_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
+
+/// After a trade times out, how long it is given to cancel its resting
+/// slices and return before it is abandoned
+const TRADE_TIMEOUT_GRACE: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// Keys under this prefix hold when an entry on an exchange and symbol last
+/// completed, expiring with the cooldown
+const COOLDOWN_KEY_PREFIX: &str = "execution:cooldown";
+
+/// Levels read from each side of the book when simulating fills
+const SIM_BOOK_DEPTH: usize = 50;
+
+/// Times an unwound leg's position is re-read and closed before it is left
+/// as it stands
+const MAX_FLATTEN_ROUNDS: usize = 5;
+
+/// Attempts to cancel an orphaned order on transient errors at startup
+const CLEANUP_CANCEL_ATTEMPTS: u32 = 3;
 
 /// Trade entry request from backend
 #[derive(Debug, Clone, Deserialize)]
@@ -37,22 +104,161 @@ pub struct TradeEntryRequest {
     pub short_exchange_id: String,
     pub short_symbol: String,
     pub short_api_key_id: Uuid,
+
+    /// Abort unless buying the long leg and selling the short leg at the
+    /// touch still captures at least this spread
+    #[serde(default)]
+    pub min_spread_bps: Option<Decimal>,
+
+    #[serde(default)]
+    pub pricing: EntryPricing,
 }
 
-#[derive(Debug, Clone, Deserialize)]
+/// How the two legs of an entry price their slices
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(tag = "mode", rename_all = "snake_case")]
+pub enum EntryPricing {
+    /// Each leg off its own book
+    #[default]
+    Independent,
+    /// Both legs off both books, only trading while the pair captures
+    /// `target_spread_bps`, see `SpreadTarget`
+    SpreadTargeted { target_spread_bps: Decimal },
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
 pub struct SlicingParams {
     pub slice_size_coins: Option<Decimal>,
     pub slice_interval_ms: Option<u64>,
+    /// Enter with post-only orders only
+    #[serde(default)]
+    pub maker_only: bool,
+    #[serde(default)]
+    pub strategy: SlicingStrategy,
+    /// Stop placing slices and cancel resting ones after this long
+    #[serde(default)]
+    pub total_timeout_secs: Option<u64>,
+    /// Stop placing slices at this time, in Unix milliseconds, and have
+    /// resting ones expire or be cancelled then
+    #[serde(default)]
+    pub expire_at_ms: Option<i64>,
+    /// Price slices more or less aggressively depending on top-of-book sizes
+    #[serde(default)]
+    pub use_book_imbalance: bool,
+    /// Fraction of the size that counts as filled, 0.99 when unset
+    #[serde(default)]
+    pub completion_threshold: Option<Decimal>,
+    /// Price each wave more aggressively after slices that don't fill
+    #[serde(default)]
+    pub pricing_ladder: Option<PricingLadder>,
+    /// Randomize slice sizes and waits by up to these fractions either way,
+    /// in place of the configured jitter
+    #[serde(default)]
+    pub size_jitter_percent: Option<f64>,
+    #[serde(default)]
+    pub interval_jitter_percent: Option<f64>,
+    /// Seed the jitter, so a run can be replayed slice for slice
+    #[serde(default)]
+    pub jitter_seed: Option<u64>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+impl SlicingParams {
+    /// `defaults` with these parameters applied, for an order of `size_in_coins`
+    pub fn apply(&self, defaults: &SlicingConfig, size_in_coins: Decimal) -> SlicingConfig {
+        let slice_percent = self
+            .slice_size_coins
+            .filter(|_| size_in_coins > Decimal::ZERO)
+            .and_then(|size| (size / size_in_coins).to_f64())
+            .unwrap_or(defaults.slice_percent);
+
+        SlicingConfig {
+            slice_percent,
+            interval_ms: self.slice_interval_ms.unwrap_or(defaults.interval_ms),
+            maker_only: self.maker_only,
+            strategy: self.strategy,
+            total_timeout_secs: self.total_timeout_secs,
+            expire_at_ms: self.expire_at_ms,
+            use_book_imbalance: self.use_book_imbalance,
+            completion_threshold: self.completion_threshold.unwrap_or(defaults.completion_threshold),
+            pricing_ladder: self.pricing_ladder.or(defaults.pricing_ladder),
+            size_jitter_percent: self.size_jitter_percent.unwrap_or(defaults.size_jitter_percent),
+            interval_jitter_percent: self.interval_jitter_percent.unwrap_or(defaults.interval_jitter_percent),
+            jitter_seed: self.jitter_seed.or(defaults.jitter_seed),
+            ..defaults.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ExecutionMode {
+    #[default]
     Live,
     Sim,
 }
 
-/// Trade exit request
+/// One leg of a multi-leg entry
+#[derive(Debug, Clone, Deserialize)]
+pub struct Leg {
+    pub exchange_id: String,
+    pub symbol: String,
+    pub side: Side,
+    pub size_in_coins: Decimal,
+    pub api_key_id: Uuid,
+    pub slicing: SlicingParams,
+}
+
+/// Cost estimate request for one order, answered without trading
+#[derive(Debug, Clone, Deserialize)]
+pub struct EstimateRequest {
+    pub exchange_id: String,
+    pub symbol: String,
+    pub side: Side,
+    pub size_in_coins: Decimal,
+    #[serde(default)]
+    pub slicing: SlicingParams,
+}
+
+/// Projected cost of an order against the current book
+#[derive(Debug, Clone, Serialize)]
+pub struct ExecutionEstimate {
+    pub symbol: String,
+    pub side: Side,
+    /// In contracts
+    pub quantity: Decimal,
+    /// What the visible book fills of the quantity taken at once, and at
+    /// what average price
+    pub fillable_quantity: Decimal,
+    pub vwap: Option<Decimal>,
+    /// Price the slippage is measured against, from the configured source
+    pub arrival_price: Option<Decimal>,
+    /// Positive is worse than arrival
+    pub slippage_bps: Option<Decimal>,
+    /// Maker rate for maker-only slicing, taker otherwise
+    pub fee_bps: f64,
+    /// On the fillable quantity at the VWAP, in the settlement asset
+    pub fees: Decimal,
+    pub slices: usize,
+    /// Every slice's size and send time, as execution would schedule them
+    pub plan: SlicePlan,
+    /// Fraction of the quantity the slices' limit prices reach in the
+    /// current book, assuming it refills between slices. `None` for
+    /// maker-only slicing, whose fills depend on the flow.
+    pub fill_probability: Option<Decimal>,
+}
+
+/// Entry across any number of venues, for triangular or basket trades.
+/// Legs are worked together, and if any of them fails to fill in full the
+/// ones that did fill are unwound.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MultiLegEntryRequest {
+    pub trade_id: Uuid,
+    pub user_id: Uuid,
+    pub legs: Vec<Leg>,
+}
+
+/// Trade exit request. Quantities are in each leg's venue contracts, as the
+/// entry's result reported them.
 #[derive(Debug, Clone, Deserialize)]
 pub struct TradeExitRequest {
     pub trade_id: Uuid,
@@ -70,30 +276,289 @@ pub struct TradeExitRequest {
     pub short_symbol: String,
     pub short_quantity: Decimal,
     pub short_api_key_id: Uuid,
+
+    /// Sim exits are priced against the simulated entry of `trade_id`
+    #[serde(default)]
+    pub mode: ExecutionMode,
+
+    /// Leave a trailing stop on each leg instead of closing now
+    #[serde(default)]
+    pub trailing_stop: Option<Trail>,
 }
 
-/// Execution result to send back
-#[derive(Debug, Clone, Serialize)]
+/// Control message for a running trade
+#[derive(Debug, Clone, Deserialize)]
+pub struct ControlMessage {
+    pub trade_id: Uuid,
+    pub action: ControlAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ControlAction {
+    /// Stop launching slices and cancel resting orders
+    Cancel,
+}
+
+/// Outcome of one leg of a multi-leg entry
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LegResult {
+    pub exchange_id: String,
+    pub symbol: String,
+    pub side: Side,
+    /// In the venue's contracts
+    pub filled: Decimal,
+    pub avg_price: Decimal,
+    /// Quantity flattened again after another leg failed
+    pub unwound: Decimal,
+}
+
+/// A trailing stop left on one leg of an exit
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrailingStopResult {
+    pub exchange_id: String,
+    pub symbol: String,
+    pub side: Side,
+    /// Held by the exchange, rather than emulated by this service
+    pub native: bool,
+    /// Exchange order id of a native stop, or of the stop order first placed
+    /// behind an emulated one. Bybit's position-level stops, and emulated
+    /// stops on venues without stop orders, have none.
+    pub order_id: Option<String>,
+}
+
+/// Version of the `ExecutionResult` shape that is published.
+///
+/// Within a version fields are only ever added, each optional or defaulted
+/// so that results published before it still read, and none is renamed,
+/// removed or given a new meaning; consumers should ignore fields they
+/// don't know. A change that can't be made that way bumps the version, and
+/// consumers branch on `schema_version` to read both shapes for as long as
+/// results of the old one may still arrive, from the dead-letter file or
+/// the trade history. Results from before versioning carry no
+/// `schema_version` and read as version 0, which differs from version 1
+/// only in lacking it.
+pub const RESULT_SCHEMA_VERSION: u32 = 1;
+
+/// Execution result to send back. Filled quantities and shortfalls are in
+/// each leg's venue contracts, live or simulated, so an exit can send them
+/// back unchanged; only `leg_size_residual` is in coins.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ExecutionResult {
+    /// `RESULT_SCHEMA_VERSION` when published
+    #[serde(default)]
+    pub schema_version: u32,
     pub trade_id: Uuid,
     pub success: bool,
     pub long_filled: Decimal,
     pub long_avg_price: Decimal,
     pub short_filled: Decimal,
     pub short_avg_price: Decimal,
+    /// Left unfilled on each leg, for the caller to top up if it chooses
+    #[serde(default)]
+    pub long_shortfall: Decimal,
+    #[serde(default)]
+    pub short_shortfall: Decimal,
+    /// Flattened again on each leg after the entry failed
+    #[serde(default)]
+    pub long_unwound: Decimal,
+    #[serde(default)]
+    pub short_unwound: Decimal,
+    /// Long minus short size in coins once each leg was rounded to its
+    /// venue's contracts, when they did not match exactly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub leg_size_residual: Option<Decimal>,
     pub error: Option<String>,
+    /// Execution was stopped by the kill switch
+    #[serde(default)]
+    pub aborted: bool,
+    /// Slippage of each leg against its arrival mid price. Positive is worse
+    /// than arrival (paid more on the long, received less on the short).
+    pub long_slippage_bps: Option<Decimal>,
+    pub short_slippage_bps: Option<Decimal>,
+    /// Short minus long price relative to the long price, at arrival and as filled
+    pub intended_spread_bps: Option<Decimal>,
+    pub realized_spread_bps: Option<Decimal>,
+    /// Simulated exits only: PnL of both legs against the simulated entry,
+    /// in the legs' settlement asset
+    pub realized_pnl: Option<Decimal>,
+    /// Multi-leg entries only: the outcome of each leg, in request order.
+    /// The long and short fields are left at zero.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub legs: Vec<LegResult>,
+    /// Trailing stop exits only: the stop left on each leg
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trailing_stops: Vec<TrailingStopResult>,
+    /// Every slice of a two-leg entry or exit, for the trade history. Not
+    /// published.
+    #[serde(skip)]
+    pub slices: Vec<LegSlice>,
+}
+
+/// A slice of one leg of a trade
+#[derive(Debug, Clone)]
+pub struct LegSlice {
+    /// `long` or `short`
+    pub leg: &'static str,
+    pub slice: SliceResult,
+}
+
+impl ExecutionResult {
+    /// Result for a trade that failed before any order was placed
+    pub fn failed(trade_id: Uuid, error: String) -> Self {
+        Self {
+            schema_version: RESULT_SCHEMA_VERSION,
+            trade_id,
+            success: false,
+            long_filled: Decimal::ZERO,
+            long_avg_price: Decimal::ZERO,
+            short_filled: Decimal::ZERO,
+            short_avg_price: Decimal::ZERO,
+            long_shortfall: Decimal::ZERO,
+            short_shortfall: Decimal::ZERO,
+            long_unwound: Decimal::ZERO,
+            short_unwound: Decimal::ZERO,
+            leg_size_residual: None,
+            error: Some(error),
+            aborted: false,
+            long_slippage_bps: None,
+            short_slippage_bps: None,
+            intended_spread_bps: None,
+            realized_spread_bps: None,
+            realized_pnl: None,
+            legs: Vec::new(),
+            trailing_stops: Vec::new(),
+            slices: Vec::new(),
+        }
+    }
 }
 
 /// Execution server
 pub struct ExecutionServer {
     adapters: HashMap<String, Arc<dyn ExchangeAdapter>>,
+    /// Configured exchanges whose adapter failed to initialize, with the reason
+    unavailable_adapters: HashMap<String, String>,
     config: Config,
     redis: Option<ConnectionManager>,
+    credential_source: Option<Arc<dyn CredentialSource>>,
     api_key_cache: Arc<RwLock<HashMap<Uuid, CachedCredentials>>>,
+    /// Kill switches for trades currently executing
+    kill_switches: Arc<RwLock<HashMap<Uuid, Arc<AtomicBool>>>>,
+    /// Legs and placed orders of trades currently executing
+    progress: Arc<RwLock<HashMap<Uuid, TradeProgress>>>,
+    /// Positions opened by sim entries, awaiting their sim exit
+    sim_positions: Arc<RwLock<HashMap<Uuid, SimPosition>>>,
+    /// Results that could not be published, replayed at startup
+    dead_letter: DeadLetterFile,
+    /// Symbol info by exchange and symbol, with when it was read
+    symbol_info_cache: Arc<RwLock<SymbolInfoCache>>,
+    /// When entries last completed by exchange and symbol, in Unix
+    /// milliseconds. Backs up the shared record in Redis.
+    last_entries: Arc<RwLock<HashMap<(String, String), i64>>>,
+    /// Latest clock offset of each exchange, for health checks
+    clock: Arc<ClockMonitor>,
+    /// Resting orders per exchange and symbol, capped across all trades
+    open_orders: Arc<OpenOrderLimits>,
+    /// Slices of this run still resting, cancelled on a graceful shutdown
+    order_store: Arc<OrderStore>,
+    /// Accounts registered for cancel-on-disconnect
+    cancel_on_disconnect: Arc<CancelOnDisconnect>,
+    /// Positions left open by live entries, with their unrealized PnL
+    positions: Arc<PositionTracker>,
+    /// Exchanges found in maintenance, held until they are back
+    maintenance: Arc<MaintenanceMonitor>,
+    /// One permit per trade the request stream may have executing at once
+    trade_slots: Arc<Semaphore>,
+    /// Where results are kept beyond the result stream's retention
+    history: Option<TradeHistory>,
+    /// Execution algorithms slicing config may pick by name
+    algorithms: AlgorithmRegistry,
 }
 
-struct CachedCredentials {
+/// Symbol info keyed by exchange and symbol, with when it was read
+type SymbolInfoCache = HashMap<(String, String), (SymbolInfo, std::time::Instant)>;
+
+/// Fills of a simulated entry
+#[derive(Debug, Clone, Copy)]
+struct SimPosition {
+    long_price: Decimal,
+    short_price: Decimal,
+}
+
+/// A leg ready to be worked: resolved adapter and key, and its slicing
+#[derive(Clone)]
+struct LegPlan {
+    /// Used in logs and errors, e.g. "Long"
+    name: String,
+    adapter: Arc<dyn ExchangeAdapter>,
+    credentials: Credentials,
+    symbol: String,
+    side: Side,
+    quantity: Decimal,
+    reference_price: Decimal,
+    slicing: SlicingConfig,
+    /// Key the leg trades on, which its open order cap is counted against
+    api_key_id: Uuid,
+    /// Prices the leg jointly with the other leg of a paired entry
+    spread_target: Option<SpreadTarget>,
+    /// Position the account held on the leg's symbol and side before the
+    /// leg traded, `None` if it couldn't be read. An unwind closes no more
+    /// than the growth since.
+    position_before: Option<Decimal>,
+}
+
+/// What becomes of a trade's fills if it has to be abandoned
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OnAbandon {
+    /// Keep them, as an exit's fills only reduce the position
+    Keep,
+    /// Close them again, as a failed entry's are
+    Unwind,
+}
+
+/// A running trade's legs and every order each has placed, so a trade that
+/// has to be abandoned can still be cleaned up
+struct TradeProgress {
+    legs: Vec<(LegPlan, Arc<std::sync::Mutex<Vec<OrderResponse>>>)>,
+    on_abandon: OnAbandon,
+    /// Reported on the long and short fields rather than per leg
+    paired: bool,
+}
+
+/// What a two-leg entry adds to its legs: the spread between them
+struct PairTerms {
+    min_spread_bps: Option<Decimal>,
+    pricing: EntryPricing,
+}
+
+/// An entry leg that passed its checks, with its key and prices, not yet sized
+struct ResolvedLeg {
+    adapter: Arc<dyn ExchangeAdapter>,
     credentials: Credentials,
+    info: Option<SymbolInfo>,
+    contract: ContractSpec,
+    arrival: Option<Decimal>,
+}
+
+/// How one leg of an entry went
+struct EnteredLeg {
+    result: LegResult,
+    shortfall: Decimal,
+    arrival: Option<Decimal>,
+    /// See `LegSize`
+    size_residual: Decimal,
+    slices: Vec<SliceResult>,
+}
+
+/// An entry that traded, with its legs in request order
+struct Entry {
+    legs: Vec<EnteredLeg>,
+    errors: Vec<String>,
+    aborted: bool,
+}
+
+struct CachedCredentials {
+    keys: KeyPool,
     expires_at: std::time::Instant,
 }
 
@@ -106,28 +571,438 @@ impl ExecutionServer {
         }
 
         Self {
+            dead_letter: DeadLetterFile::new(&config.dead_letter_path),
+            clock: Arc::new(ClockMonitor::new(config.max_clock_skew_ms)),
+            open_orders: Arc::new(OpenOrderLimits::new(config.max_open_orders_per_symbol)),
+            order_store: Arc::new(OrderStore::default()),
+            cancel_on_disconnect: Arc::new(CancelOnDisconnect::new(config.cancel_on_disconnect_secs)),
             adapters: adapter_map,
-            config,
+            unavailable_adapters: HashMap::new(),
             redis: None,
+            credential_source: None,
             api_key_cache: Arc::new(RwLock::new(HashMap::new())),
+            kill_switches: Arc::new(RwLock::new(HashMap::new())),
+            progress: Arc::new(RwLock::new(HashMap::new())),
+            sim_positions: Arc::new(RwLock::new(HashMap::new())),
+            symbol_info_cache: Arc::new(RwLock::new(HashMap::new())),
+            last_entries: Arc::new(RwLock::new(HashMap::new())),
+            positions: Arc::new(PositionTracker::default()),
+            maintenance: Arc::new(MaintenanceMonitor::default()),
+            trade_slots: Arc::new(Semaphore::new(config.max_concurrent_trades.max(1))),
+            history: None,
+            algorithms: AlgorithmRegistry::default(),
+            config,
+        }
+    }
+
+    /// Record exchanges that failed to initialize so trades on them fail clearly
+    pub fn with_unavailable_adapters(mut self, unavailable: HashMap<String, String>) -> Self {
+        self.unavailable_adapters = unavailable;
+        self
+    }
+
+    pub fn with_credential_source(mut self, source: Box<dyn CredentialSource>) -> Self {
+        self.credential_source = Some(Arc::from(source));
+        self
+    }
+
+    /// Share symbol cooldowns through Redis, so they hold across restarts
+    /// and instances, and keep open positions there across restarts
+    pub fn with_redis(mut self, conn: ConnectionManager) -> Self {
+        self.positions = Arc::new(PositionTracker::default().with_redis(conn.clone()));
+        self.redis = Some(conn);
+        self
+    }
+
+    /// Also keep every result, with its slices, in `history`
+    pub fn with_history(mut self, history: TradeHistory) -> Self {
+        self.history = Some(history);
+        self
+    }
+
+    /// Let the slicing defaults and symbol profiles pick from `algorithms`.
+    /// Fails if they pick one that isn't registered.
+    pub fn with_algorithms(mut self, algorithms: AlgorithmRegistry) -> Result<Self> {
+        algorithms.check(std::iter::once(&self.config.slicing).chain(self.config.symbol_profiles.values()))?;
+        self.algorithms = algorithms;
+        Ok(self)
+    }
+
+    /// Positions left open by live entries
+    pub fn positions(&self) -> &PositionTracker {
+        &self.positions
+    }
+
+    /// Orders this run has resting
+    pub fn orders(&self) -> &OrderStore {
+        &self.order_store
+    }
+
+    /// Exchange clock offsets, for `/healthz` and `/metrics`
+    pub fn clock(&self) -> Arc<ClockMonitor> {
+        self.clock.clone()
+    }
+
+    /// Exchanges found in maintenance, for `/healthz`
+    pub fn maintenance(&self) -> Arc<MaintenanceMonitor> {
+        self.maintenance.clone()
+    }
+
+    /// Every exchange's adapter, for `/healthz`
+    pub fn adapters(&self) -> health::Adapters {
+        self.adapters.clone()
+    }
+
+    /// Cancel every order this run still has resting. Failures are logged;
+    /// cancel-on-disconnect, where armed, is the backstop.
+    async fn cancel_live_orders(&self) {
+        let live = self.order_store.live();
+        if live.is_empty() {
+            return;
+        }
+        info!("Cancelling {} resting orders", live.len());
+        for order in live {
+            let Some(adapter) = self.adapters.get(&order.exchange_id) else {
+                continue;
+            };
+            match adapter
+                .cancel_order(&order.credentials, &order.symbol, &order.exchange_order_id)
+                .await
+            {
+                Ok(_) => {
+                    self.order_store.remove(&order.client_order_id);
+                }
+                Err(e) => warn!(
+                    "Failed to cancel {} on {} at shutdown: {:#}",
+                    order.client_order_id, order.exchange_id, e
+                ),
+            }
+        }
+    }
+
+    /// Cancel orders an earlier run left resting: those on `symbols` and on
+    /// the markets of open positions whose client order id carries our
+    /// prefix. Accounts are the warm-up key's and those with open positions.
+    /// Orders that close before the cancel lands are left be; other failures
+    /// are logged, and venues whose orders couldn't be listed are named at the
+    /// end. Returns how many orders were cancelled.
+    pub async fn cleanup_orphans(&self, symbols: &[(String, String)]) -> usize {
+        let mut markets: BTreeSet<(String, String)> = symbols.iter().cloned().collect();
+        markets.extend(self.positions.markets().await);
+
+        let mut accounts: Vec<(Option<String>, Uuid)> = self
+            .positions
+            .accounts()
+            .await
+            .into_iter()
+            .map(|(exchange_id, api_key_id)| (Some(exchange_id), api_key_id))
+            .collect();
+        if let Some(api_key_id) = self.config.warmup_api_key_id {
+            accounts.push((None, api_key_id));
+        }
+
+        let mut cancelled = 0;
+        let mut seen = HashSet::new();
+        let mut unchecked = BTreeSet::new();
+        for (exchange_id, api_key_id) in accounts {
+            let keys = match self.get_key_pool(api_key_id).await {
+                Ok(keys) => keys,
+                Err(e) => {
+                    warn!("Cleanup skips key {}, it failed to load: {:#}", api_key_id, e);
+                    continue;
+                }
+            };
+            let account_markets = markets
+                .iter()
+                .filter(|(exchange, _)| exchange_id.as_ref().is_none_or(|id| id == exchange));
+            for (exchange_id, symbol) in account_markets {
+                let Some(adapter) = self.adapters.get(exchange_id) else {
+                    continue;
+                };
+                let prefix = self
+                    .exchange_config(exchange_id)
+                    .and_then(|e| e.client_order_id_prefix.clone())
+                    .unwrap_or_else(|| DEFAULT_CLIENT_ORDER_ID_PREFIX.to_string());
+                let open = match adapter.get_open_orders(&keys.primary, symbol).await {
+                    Ok(open) => open,
+                    Err(e) => {
+                        warn!("Cleanup could not list orders on {} on {}: {:#}", symbol, exchange_id, e);
+                        unchecked.insert(exchange_id.clone());
+                        continue;
+                    }
+                };
+                for order in open {
+                    if !order.client_order_id.starts_with(&prefix)
+                        || !seen.insert((exchange_id.clone(), order.exchange_order_id.clone()))
+                    {
+                        continue;
+                    }
+                    if self.cancel_orphan(adapter.as_ref(), &keys.primary, symbol, &order).await {
+                        cancelled += 1;
+                    }
+                }
+            }
+        }
+        if cancelled > 0 {
+            info!("Cancelled {} orders left resting by an earlier run", cancelled);
+        }
+        if !unchecked.is_empty() {
+            warn!(
+                "Startup cleanup could not check open orders on {}",
+                unchecked.into_iter().collect::<Vec<_>>().join(", ")
+            );
+        }
+        cancelled
+    }
+
+    /// Cancel one orphaned order, retrying transient errors. An order that
+    /// can't be cancelled because it has already closed counts as done.
+    async fn cancel_orphan(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        credentials: &Credentials,
+        symbol: &str,
+        order: &OrderResponse,
+    ) -> bool {
+        let mut backoff = LEG_RETRY_BACKOFF;
+        for attempt in 1..=CLEANUP_CANCEL_ATTEMPTS {
+            let e = match adapter.cancel_order(credentials, symbol, &order.exchange_order_id).await {
+                Ok(state) if state.status == OrderStatus::Cancelled => {
+                    info!("Cancelled orphaned order {} on {}", order.client_order_id, adapter.id());
+                    return true;
+                }
+                Ok(state) => {
+                    debug!("Orphaned order {} already closed ({:?})", order.client_order_id, state.status);
+                    return false;
+                }
+                Err(e) => e,
+            };
+            if ExchangeError::is_retryable(&e) && attempt < CLEANUP_CANCEL_ATTEMPTS {
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                continue;
+            }
+            // A fill or another cancel may have got there first
+            match adapter.get_order(credentials, symbol, &order.exchange_order_id).await {
+                Ok(state) if state.status.is_terminal() => {
+                    debug!("Orphaned order {} already closed ({:?})", order.client_order_id, state.status);
+                }
+                _ => warn!(
+                    "Failed to cancel orphaned order {} on {}: {:#}",
+                    order.client_order_id,
+                    adapter.id(),
+                    e
+                ),
+            }
+            return false;
+        }
+        false
+    }
+
+    /// Fetch ahead of the first trade what it would otherwise fetch on its
+    /// critical path: each symbol's info, each exchange's clock offset and,
+    /// with a warm-up key configured, the key and its leverage on the
+    /// symbol. Failures are logged and skipped, never fatal.
+    pub async fn warmup(&self, symbols: &[(String, String)]) {
+        if symbols.is_empty() {
+            return;
+        }
+        let keys = match self.config.warmup_api_key_id {
+            Some(api_key_id) => match self.get_key_pool(api_key_id).await {
+                Ok(keys) => Some(keys),
+                Err(e) => {
+                    warn!("Warm-up skips leverage, key {} failed to load: {:#}", api_key_id, e);
+                    None
+                }
+            },
+            None => None,
+        };
+
+        let mut measured = HashSet::new();
+        for (exchange_id, symbol) in symbols {
+            let Some(adapter) = self.adapters.get(exchange_id) else {
+                warn!("Warm-up skips {} on {}: exchange not available", symbol, exchange_id);
+                continue;
+            };
+            if measured.insert(exchange_id) {
+                if let Err(e) = self.clock.measure(adapter.as_ref()).await {
+                    warn!("Warm-up could not read the {} clock: {:#}", exchange_id, e);
+                }
+            }
+            if self.symbol_info(adapter.as_ref(), symbol).await.is_none() {
+                warn!("Warm-up found no symbol info for {} on {}", symbol, exchange_id);
+            }
+            if let Some(keys) = &keys {
+                match adapter.get_leverage(&keys.primary, symbol).await {
+                    Ok(leverage) => info!(
+                        "{} on {} trades at {}x, {:?} margin",
+                        symbol, exchange_id, leverage.leverage, leverage.margin_mode
+                    ),
+                    Err(e) => warn!("Warm-up could not read leverage of {} on {}: {:#}", symbol, exchange_id, e),
+                }
+            }
+        }
+        info!("Warmed up {} symbols on {} exchanges", symbols.len(), measured.len());
+    }
+
+    /// Estimate `request` against the current book, with the same contract
+    /// sizing and slicing an entry leg would get
+    pub async fn estimate(&self, request: &EstimateRequest) -> Result<ExecutionEstimate> {
+        let adapter = self.adapter(&request.exchange_id)?;
+        let info = self.symbol_info(adapter.as_ref(), &request.symbol).await;
+        let contract = leg_contract(adapter.as_ref(), &request.symbol, info.as_ref());
+        // Coins in an inverse contract follow the price
+        let price = match contract.contract_type {
+            ContractType::Inverse => top_of_book(adapter.as_ref(), &request.symbol).await.map(|(bid, ask)| (bid + ask) / dec!(2)),
+            ContractType::Linear => None,
+        };
+        let quantity = contracts_for(request.size_in_coins, contract_lot(contract, info.as_ref(), price)).normalize();
+        let slicing = self.leg_slicing(
+            adapter.id(),
+            contract,
+            info.as_ref(),
+            request
+                .slicing
+                .apply(self.config.slicing_for(adapter.as_ref(), &request.symbol), request.size_in_coins),
+        );
+        self.estimate_execution(adapter.as_ref(), &request.symbol, request.side, quantity, &slicing)
+            .await
+    }
+
+    /// Project the VWAP, slippage, fees and fill probability of `quantity`
+    /// contracts sliced with `slicing`, by walking the current book the way
+    /// sim mode does. No order is placed.
+    pub async fn estimate_execution(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        symbol: &str,
+        side: Side,
+        quantity: Decimal,
+        slicing: &SlicingConfig,
+    ) -> Result<ExecutionEstimate> {
+        let book = adapter.get_order_book(symbol, SIM_BOOK_DEPTH).await?;
+        let (Some(best_bid), Some(best_ask)) = (book.bids.first(), book.asks.first()) else {
+            anyhow::bail!("Empty order book for {} on {}", symbol, adapter.id());
+        };
+        let (best_bid, best_ask) = (best_bid.price, best_ask.price);
+        let arrival = self.reference_price(adapter, symbol, Some((best_bid, best_ask))).await;
+
+        let (fillable_quantity, vwap) = book.walk(side, quantity);
+        let fee_bps = if slicing.maker_only {
+            slicing.maker_fee_bps
+        } else {
+            self.exchange_config(adapter.id()).map_or(0.0, |e| e.taker_fee_bps)
+        };
+        let fee_rate = Decimal::try_from(fee_bps).unwrap_or_default() / dec!(10000);
+        let fees = vwap.map_or(Decimal::ZERO, |price| {
+            slicing.contract.settlement_value(fillable_quantity, price) * fee_rate
+        });
+
+        let plan = self.slicer(slicing.clone()).plan(quantity, arrival.unwrap_or_default());
+        let fill_probability = (!slicing.maker_only && quantity > Decimal::ZERO).then(|| {
+            let limit = calculate_limit_price(side, best_bid, best_ask, slicing.price_tolerance_bps);
+            let limit = slicing.price_rounding.round(limit, slicing.tick_size, side);
+            let depth = book.depth_within(side, limit);
+            plan.slices.iter().map(|slice| slice.quantity.min(depth)).sum::<Decimal>() / quantity
+        });
+
+        Ok(ExecutionEstimate {
+            symbol: symbol.to_string(),
+            side,
+            quantity,
+            fillable_quantity,
+            vwap,
+            arrival_price: arrival,
+            slippage_bps: arrival.zip(vwap).and_then(|(arrival, vwap)| slippage_bps(side, arrival, vwap)),
+            fee_bps,
+            fees,
+            slices: plan.slices.len(),
+            plan,
+            fill_probability,
+        })
+    }
+
+    fn adapter(&self, exchange_id: &str) -> Result<Arc<dyn ExchangeAdapter>> {
+        if let Some(adapter) = self.adapters.get(exchange_id) {
+            return Ok(adapter.clone());
+        }
+        match self.unavailable_adapters.get(exchange_id) {
+            Some(reason) => anyhow::bail!("Exchange {} is unavailable: {}", exchange_id, reason),
+            None => anyhow::bail!("Unknown exchange: {}", exchange_id),
         }
     }
 
-    pub async fn run(&self) -> Result<()> {
+    pub async fn run(self: Arc<Self>) -> Result<()> {
         info!("Starting execution server on port {}", self.config.port);
 
         // Connect to Redis
         let redis_client = redis::Client::open(self.config.redis_url.as_str())?;
-        let mut conn = redis_client.get_connection_manager().await?;
+        let conn = redis_client.get_connection_manager().await?;
+        // Blocking reads hold the connection, so control messages get their own
+        let control_conn = redis_client.get_connection_manager().await?;
 
         info!("Connected to Redis, listening for execution requests");
 
-        // Listen on execution request stream
+        self.replay_dead_letters(&conn).await;
+        self.rehydrate_positions().await;
+        if self.config.cleanup_on_start {
+            self.cleanup_orphans(&self.config.warmup_symbols).await;
+        }
+
+        let adapters = self.adapters.values().cloned().collect();
+        tokio::spawn(self.clock.clone().run(adapters, CLOCK_CHECK_INTERVAL));
+        tokio::spawn(self.cancel_on_disconnect.clone().run());
+        tokio::spawn(self.clone().mark_positions());
+        if self.config.maintenance_probe_secs > 0 {
+            let interval = std::time::Duration::from_secs(self.config.maintenance_probe_secs);
+            tokio::spawn(self.maintenance.clone().run(self.adapters.values().cloned().collect(), interval));
+        }
+
+        tokio::select! {
+            served = async {
+                tokio::try_join!(
+                    self.request_loop(conn),
+                    self.control_loop(control_conn),
+                    health::serve(self.config.port, self.clock.clone(), self.maintenance.clone(), self.adapters()),
+                    async {
+                        match self.config.http_api_port {
+                            Some(port) => {
+                                let token = self.config.http_api_token.clone().unwrap_or_default();
+                                api::serve((self.config.http_api_bind, port), token, self.clone()).await
+                            }
+                            None => Ok(()),
+                        }
+                    },
+                )
+            } => {
+                served?;
+            }
+            _ = shutdown_signal() => {
+                info!("Shutting down");
+                if self.config.cancel_orders_on_shutdown {
+                    self.cancel_live_orders().await;
+                }
+                self.cancel_on_disconnect
+                    .shutdown(self.config.cancel_orders_on_shutdown)
+                    .await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Listen on the execution request stream. Each request is executed on
+    /// its own task, up to `max_concurrent_trades` at once, so a long trade
+    /// doesn't hold up the ones queued behind it. Reading stops while every
+    /// slot is taken and resumes after the last entry read, so requests
+    /// published meanwhile wait in the stream rather than being skipped.
+    async fn request_loop(self: &Arc<Self>, mut conn: ConnectionManager) -> Result<()> {
+        let mut last_id = "$".to_string();
+        let mut running = HashMap::new();
         loop {
             let result: redis::streams::StreamReadReply = conn
                 .xread_options(
-                    &["execution:requests"],
-                    &["$"],
+                    &[REQUEST_STREAM],
+                    &[&last_id],
                     &redis::streams::StreamReadOptions::default()
                         .block(5000)
                         .count(10),
@@ -135,166 +1010,3959 @@ impl ExecutionServer {
                 .await?;
 
             for stream in result.keys {
-                for id_and_data in stream.ids {
-                    self.handle_request(&mut conn, &id_and_data).await;
+                for entry in stream.ids {
+                    last_id = entry.id.clone();
+                    // Every message gets a result, even one that can't be
+                    // read, so the backend never waits on a request that was
+                    // dropped
+                    let request = read_request(&entry);
+                    let trade_id = match &request {
+                        Ok(request) => request.trade_id(),
+                        Err((trade_id, error)) => {
+                            warn!("Rejecting stream entry {}: {}", entry.id, error);
+                            *trade_id
+                        }
+                    };
+                    let server = self.clone();
+                    let mut conn = conn.clone();
+                    let work = async move {
+                        let result = match request {
+                            Ok(request) => server.execute(request).await,
+                            Err((trade_id, error)) => ExecutionResult::failed(trade_id, error),
+                        };
+                        server.publish_result(&mut conn, &result).await;
+                    };
+                    self.spawn_for_trade(&mut running, trade_id, work).await;
                 }
             }
+            running.retain(|_, handle: &mut tokio::task::JoinHandle<()>| !handle.is_finished());
         }
     }
 
-    async fn handle_request(
+    /// `spawn_in_slot`, starting `work` only once whatever `running` has
+    /// for the same trade has finished, so an exit published behind its
+    /// entry never works the legs alongside it. A request waiting on its
+    /// trade holds its slot meanwhile.
+    async fn spawn_for_trade<F>(
         &self,
-        conn: &mut ConnectionManager,
-        entry: &redis::streams::StreamId,
-    ) {
-        // Extract data from the stream entry - handle various redis Value types
-        let data: Vec<u8> = match entry.map.get("data") {
-            Some(value) => {
-                match redis::from_redis_value::<Vec<u8>>(value) {
-                    Ok(d) => d,
-                    Err(_) => {
-                        // Try as string
-                        match redis::from_redis_value::<String>(value) {
-                            Ok(s) => s.into_bytes(),
-                            Err(_) => {
-                                warn!("Invalid message format");
-                                return;
-                            }
+        running: &mut HashMap<Uuid, tokio::task::JoinHandle<()>>,
+        trade_id: Uuid,
+        work: F,
+    ) where
+        F: std::future::Future<Output = ()> + Send + 'static,
+    {
+        let previous = running.remove(&trade_id);
+        let handle = self
+            .spawn_in_slot(async move {
+                if let Some(previous) = previous {
+                    // A panic in the earlier request doesn't hold this one up
+                    let _ = previous.await;
+                }
+                work.await
+            })
+            .await;
+        running.insert(trade_id, handle);
+    }
+
+    /// Run `work` on its own task once a trade slot is free, holding the
+    /// slot until it finishes
+    async fn spawn_in_slot<F>(&self, work: F) -> tokio::task::JoinHandle<F::Output>
+    where
+        F: std::future::Future + Send + 'static,
+        F::Output: Send + 'static,
+    {
+        let slot = self
+            .trade_slots
+            .clone()
+            .acquire_owned()
+            .await
+            .expect("trade slots are never closed");
+        tokio::spawn(async move {
+            let output = work.await;
+            drop(slot);
+            output
+        })
+    }
+
+    /// Listen on the control stream, concurrently with the request loop
+    async fn control_loop(&self, mut conn: ConnectionManager) -> Result<()> {
+        // Read on from the last message seen, so a cancel sent while the
+        // previous batch was handled isn't missed
+        let mut last_id = "$".to_string();
+        loop {
+            let result: redis::streams::StreamReadReply = conn
+                .xread_options(
+                    &[CONTROL_STREAM],
+                    &[&last_id],
+                    &redis::streams::StreamReadOptions::default()
+                        .block(5000)
+                        .count(10),
+                )
+                .await?;
+
+            for stream in result.keys {
+                for entry in stream.ids {
+                    last_id = entry.id.clone();
+                    let data = match entry.get::<String>("data") {
+                        Some(d) => d,
+                        None => {
+                            warn!("No data field in control message");
+                            continue;
                         }
+                    };
+                    match serde_json::from_str::<ControlMessage>(&data) {
+                        Ok(message) => self.handle_control(message).await,
+                        Err(e) => warn!("Invalid control message: {}", e),
                     }
                 }
             }
-            None => {
-                warn!("No data field in message");
-                return;
+        }
+    }
+
+    async fn handle_control(&self, message: ControlMessage) {
+        match message.action {
+            ControlAction::Cancel => {
+                match self.kill_switches.read().await.get(&message.trade_id) {
+                    Some(kill_switch) => {
+                        warn!("Kill switch set for trade {}", message.trade_id);
+                        kill_switch.store(true, Ordering::SeqCst);
+                    }
+                    None => warn!("Cancel for unknown or finished trade {}", message.trade_id),
+                }
             }
-        };
+        }
+    }
 
-        let data_str = match std::str::from_utf8(&data) {
-            Ok(s) => s,
-            Err(_) => {
-                warn!("Invalid UTF-8 in message");
-                return;
+    /// Execute a request, whichever interface it arrived on
+    pub async fn execute(&self, request: Request) -> ExecutionResult {
+        let result = match request {
+            Request::Entry(request) => {
+                let trade_id = request.trade_id;
+                self.with_trade_timeout(trade_id, self.execute_entry(request)).await
+            }
+            Request::MultiLeg(request) => {
+                let trade_id = request.trade_id;
+                self.with_trade_timeout(trade_id, self.execute_multi_leg(request)).await
+            }
+            Request::Exit(request) => {
+                let trade_id = request.trade_id;
+                self.with_trade_timeout(trade_id, self.execute_exit(request)).await
             }
         };
+        if let Some(history) = &self.history {
+            history.record(&result);
+        }
+        result
+    }
 
-        // Try to parse as entry request
-        if let Ok(request) = serde_json::from_str::<TradeEntryRequest>(data_str) {
-            let result = self.execute_entry(request).await;
-            self.publish_result(conn, &result).await;
-            return;
+    /// Run `execution` under the configured trade timeout. Once it passes the
+    /// trade's kill switch is set, so its legs cancel their resting slices
+    /// and return what filled. A trade stuck on an exchange call that never
+    /// returns is abandoned after a grace period and cleaned up from outside,
+    /// see `abandon`.
+    async fn with_trade_timeout(
+        &self,
+        trade_id: Uuid,
+        execution: impl std::future::Future<Output = ExecutionResult>,
+    ) -> ExecutionResult {
+        let execution = TRADE_ID.scope(trade_id, execution);
+        let limit_secs = self.config.trade_timeout_secs;
+        if limit_secs == 0 {
+            return execution.await;
         }
+        tokio::pin!(execution);
 
-        // Try to parse as exit request
-        if let Ok(request) = serde_json::from_str::<TradeExitRequest>(data_str) {
-            let result = self.execute_exit(request).await;
-            self.publish_result(conn, &result).await;
-            return;
+        let limit = std::time::Duration::from_secs(limit_secs);
+        if let Ok(result) = tokio::time::timeout(limit, &mut execution).await {
+            return result;
         }
 
-        warn!("Unknown request format");
+        error!("Trade {} still executing after {} s, stopping it", trade_id, limit_secs);
+        if let Some(kill_switch) = self.kill_switches.read().await.get(&trade_id) {
+            kill_switch.store(true, Ordering::SeqCst);
+        }
+        let timed_out = format!("Trade timed out after {} s", limit_secs);
+        match tokio::time::timeout(TRADE_TIMEOUT_GRACE, &mut execution).await {
+            Ok(result) if result.success => result,
+            Ok(mut result) => {
+                result.aborted = true;
+                result.error = Some(match result.error {
+                    Some(e) => format!("{}: {}", timed_out, e),
+                    None => timed_out,
+                });
+                result
+            }
+            Err(_) => {
+                error!(
+                    "Trade {} did not stop within {} s, abandoning it",
+                    trade_id,
+                    TRADE_TIMEOUT_GRACE.as_secs()
+                );
+                self.kill_switches.write().await.remove(&trade_id);
+                let progress = self.progress.write().await.remove(&trade_id);
+                let (mut result, outcome) = match progress {
+                    Some(progress) => self.abandon(trade_id, progress).await,
+                    None => (
+                        ExecutionResult::failed(trade_id, String::new()),
+                        Ok("before placing any orders"),
+                    ),
+                };
+                result.success = false;
+                result.aborted = true;
+                result.error = Some(match outcome {
+                    Ok(done) => format!("{} and did not stop; {}", timed_out, done),
+                    Err(problems) => format!("{} and did not stop: {}", timed_out, problems),
+                });
+                result
+            }
+        }
     }
 
+    /// Enter the long and short legs of a spread trade as a two-leg entry,
+    /// with the spread between them checked and, if asked, targeted
     async fn execute_entry(&self, request: TradeEntryRequest) -> ExecutionResult {
         info!("Executing trade entry: {}", request.trade_id);
 
         if request.mode == ExecutionMode::Sim {
-            return self.simulate_entry(&request);
-        }
-
-        // Get adapters
-        let long_adapter = match self.adapters.get(&request.long_exchange_id) {
-            Some(a) => a.clone(),
-            None => {
-                return ExecutionResult {
-                    trade_id: request.trade_id,
-                    success: false,
-                    long_filled: Decimal::ZERO,
-                    long_avg_price: Decimal::ZERO,
-                    short_filled: Decimal::ZERO,
-                    short_avg_price: Decimal::ZERO,
-                    error: Some(format!("Unknown exchange: {}", request.long_exchange_id)),
-                };
-            }
-        };
+            return self.simulate_entry(&request).await;
+        }
 
-        let short_adapter = match self.adapters.get(&request.short_exchange_id) {
-            Some(a) => a.clone(),
-            None => {
-                return ExecutionResult {
-                    trade_id: request.trade_id,
-                    success: false,
-                    long_filled: Decimal::ZERO,
-                    long_avg_price: Decimal::ZERO,
-                    short_filled: Decimal::ZERO,
-                    short_avg_price: Decimal::ZERO,
-                    error: Some(format!("Unknown exchange: {}", request.short_exchange_id)),
-                };
+        let leg = |exchange_id: &str, symbol: &str, side, api_key_id| Leg {
+            exchange_id: exchange_id.to_string(),
+            symbol: symbol.to_string(),
+            side,
+            size_in_coins: request.size_in_coins,
+            api_key_id,
+            slicing: request.slicing.clone(),
+        };
+        let legs = MultiLegEntryRequest {
+            trade_id: request.trade_id,
+            user_id: request.user_id,
+            legs: vec![
+                leg(&request.long_exchange_id, &request.long_symbol, Side::Buy, request.long_api_key_id),
+                leg(&request.short_exchange_id, &request.short_symbol, Side::Sell, request.short_api_key_id),
+            ],
+        };
+        let pair = PairTerms {
+            min_spread_bps: request.min_spread_bps,
+            pricing: request.pricing,
+        };
+        let entry = match self.enter(&legs, Some(&pair)).await {
+            Ok(entry) => entry,
+            Err(e) => {
+                error!("Rejecting trade {}: {}", request.trade_id, e);
+                return ExecutionResult::failed(request.trade_id, e.to_string());
             }
         };
+        let Ok([long, short]) = <[EnteredLeg; 2]>::try_from(entry.legs) else {
+            unreachable!("one result per leg");
+        };
 
-        // TODO: Fetch credentials from database
-        // For now, return error indicating credentials needed
+        let residual = long.size_residual - short.size_residual;
+        let filled = |leg: &EnteredLeg| leg.result.filled > Decimal::ZERO;
+        let slices = long
+            .slices
+            .iter()
+            .map(|slice| LegSlice { leg: "long", slice: slice.clone() })
+            .chain(short.slices.iter().map(|slice| LegSlice { leg: "short", slice: slice.clone() }))
+            .collect();
         ExecutionResult {
-            trade_id: request.trade_id,
-            success: false,
-            long_filled: Decimal::ZERO,
-            long_avg_price: Decimal::ZERO,
-            short_filled: Decimal::ZERO,
-            short_avg_price: Decimal::ZERO,
-            error: Some("Credential loading not yet implemented".to_string()),
+            success: entry.errors.is_empty(),
+            long_filled: long.result.filled,
+            long_avg_price: long.result.avg_price,
+            short_filled: short.result.filled,
+            short_avg_price: short.result.avg_price,
+            long_shortfall: long.shortfall,
+            short_shortfall: short.shortfall,
+            long_unwound: long.result.unwound,
+            short_unwound: short.result.unwound,
+            leg_size_residual: (!residual.is_zero()).then_some(residual),
+            error: if entry.errors.is_empty() { None } else { Some(entry.errors.join("; ")) },
+            aborted: entry.aborted,
+            long_slippage_bps: long
+                .arrival
+                .filter(|_| filled(&long))
+                .and_then(|arrival| slippage_bps(Side::Buy, arrival, long.result.avg_price)),
+            short_slippage_bps: short
+                .arrival
+                .filter(|_| filled(&short))
+                .and_then(|arrival| slippage_bps(Side::Sell, arrival, short.result.avg_price)),
+            intended_spread_bps: long.arrival.zip(short.arrival).and_then(|(l, s)| spread_bps(l, s)),
+            realized_spread_bps: (filled(&long) && filled(&short))
+                .then(|| spread_bps(long.result.avg_price, short.result.avg_price))
+                .flatten(),
+            slices,
+            ..ExecutionResult::failed(request.trade_id, String::new())
         }
     }
 
-    async fn execute_exit(&self, request: TradeExitRequest) -> ExecutionResult {
+    /// Enter every leg of a multi-leg request together, see `enter`
+    async fn execute_multi_leg(&self, request: MultiLegEntryRequest) -> ExecutionResult {
         info!(
-            "Executing trade exit: {} (emergency: {})",
-            request.trade_id, request.is_emergency
+            "Executing multi-leg entry: {} ({} legs)",
+            request.trade_id,
+            request.legs.len()
         );
 
-        // Similar to entry but with reverse sides
-        ExecutionResult {
-            trade_id: request.trade_id,
-            success: false,
-            long_filled: Decimal::ZERO,
-            long_avg_price: Decimal::ZERO,
-            short_filled: Decimal::ZERO,
-            short_avg_price: Decimal::ZERO,
-            error: Some("Exit execution not yet implemented".to_string()),
+        match self.enter(&request, None).await {
+            Ok(entry) => ExecutionResult {
+                success: entry.errors.is_empty(),
+                error: if entry.errors.is_empty() { None } else { Some(entry.errors.join("; ")) },
+                aborted: entry.aborted,
+                legs: entry.legs.into_iter().map(|leg| leg.result).collect(),
+                ..ExecutionResult::failed(request.trade_id, String::new())
+            },
+            Err(e) => {
+                error!("Rejecting trade {}: {}", request.trade_id, e);
+                ExecutionResult::failed(request.trade_id, e.to_string())
+            }
         }
     }
 
-    fn simulate_entry(&self, request: &TradeEntryRequest) -> ExecutionResult {
+    /// Enter every leg of `request` together. Each leg is checked, then all
+    /// are sized in their venues' contracts so they stay in proportion, and
+    /// worked under one kill switch. If any leg does not fill in full,
+    /// whatever the legs did fill is flattened with reduce-only market
+    /// orders, so a failed entry leaves no position behind. `pair` carries
+    /// the spread terms of a two-leg entry, whose legs are the long and then
+    /// the short. An entry rejected before trading is an error.
+    async fn enter(&self, request: &MultiLegEntryRequest, pair: Option<&PairTerms>) -> Result<Entry> {
+        if request.legs.is_empty() {
+            anyhow::bail!("Entry has no legs");
+        }
+
+        let adapters = request
+            .legs
+            .iter()
+            .map(|leg| {
+                let adapter = self.adapter(&leg.exchange_id)?;
+                self.config.symbol_policy.check(adapter.as_ref(), &leg.symbol)?;
+                Ok(adapter)
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        // A delisted or halted symbol would otherwise only surface as a
+        // rejection after another leg has started trading. Prices are
+        // public, so every leg's are read meanwhile.
+        let symbols: Vec<(&dyn ExchangeAdapter, &str)> = adapters
+            .iter()
+            .zip(&request.legs)
+            .map(|(adapter, leg)| (adapter.as_ref(), leg.symbol.as_str()))
+            .collect();
+        let (tradable, prices) = tokio::join!(
+            futures::future::join_all(symbols.iter().map(|&(adapter, symbol)| self.check_tradable(adapter, symbol))),
+            self.entry_prices(&symbols),
+        );
+        tradable.into_iter().collect::<Result<()>>()?;
+        futures::future::join_all(
+            request
+                .legs
+                .iter()
+                .map(|leg| self.check_cooldown(&leg.exchange_id, &leg.symbol)),
+        )
+        .await
+        .into_iter()
+        .collect::<Result<()>>()?;
+
+        // Orders are placed with the requested key; polling and cancels may
+        // spread over the account's other keys
+        let mut resolved = Vec::with_capacity(request.legs.len());
+        for ((leg, adapter), prices) in request.legs.iter().zip(adapters).zip(&prices) {
+            let keys = self.get_key_pool(leg.api_key_id).await?;
+            let adapter = PooledAdapter::wrap(adapter, &keys, self.config.read_key_selection);
+            let info = self.symbol_info(adapter.as_ref(), &leg.symbol).await;
+            resolved.push(ResolvedLeg {
+                contract: leg_contract(adapter.as_ref(), &leg.symbol, info.as_ref()),
+                adapter,
+                credentials: keys.primary,
+                info,
+                arrival: prices.arrival,
+            });
+        }
+
+        // Venues size orders in contracts of different sizes, so each leg is
+        // converted to its own contract count before anything is checked.
+        // A leg whose prices couldn't be read has no arrival price, which the
+        // notional and spread checks reject rather than trading on an older
+        // price.
+        let sizes = reconcile_leg_sizes(
+            &request
+                .legs
+                .iter()
+                .zip(&resolved)
+                .map(|(leg, resolved)| {
+                    let lot = contract_lot(resolved.contract, resolved.info.as_ref(), resolved.arrival);
+                    (leg.size_in_coins, lot)
+                })
+                .collect::<Vec<_>>(),
+        );
+        self.check_leg_residual(&request.legs, &sizes)?;
+        self.check_notional(
+            &resolved
+                .iter()
+                .zip(&sizes)
+                .map(|(resolved, size)| (resolved.contract, size.contracts, resolved.arrival))
+                .collect::<Vec<_>>(),
+        )?;
+        if let Some(pair) = pair {
+            check_spread(pair.min_spread_bps, prices[0].book, prices[1].book)?;
+        }
+
+        let arrivals: Vec<_> = resolved.iter().map(|resolved| resolved.arrival).collect();
+        // The legs of a pair slice alike, or the hedge runs unbalanced
+        // between slices, so both take the more conservative profile
+        let mut profiles: Vec<&SlicingConfig> = request
+            .legs
+            .iter()
+            .zip(&resolved)
+            .map(|(leg, resolved)| self.config.slicing_for(resolved.adapter.as_ref(), &leg.symbol))
+            .collect();
+        if pair.is_some() {
+            let shared = conservative_profile(&profiles);
+            profiles.iter_mut().for_each(|profile| *profile = shared);
+        }
+        let plans = request.legs.iter().zip(resolved).zip(&sizes).zip(profiles).enumerate().map(
+            |(i, (((leg, resolved), size), profile))| {
+                let name = match (pair, i) {
+                    (Some(_), 0) => "Long".to_string(),
+                    (Some(_), _) => "Short".to_string(),
+                    (None, _) => format!("{} {}", leg.exchange_id, leg.symbol),
+                };
+                self.plan_leg(name, leg, resolved, size.contracts, profile)
+            },
+        );
+        let mut plans = futures::future::join_all(plans)
+            .await
+            .into_iter()
+            .collect::<Result<Vec<_>>>()?;
+        // Priced against a target spread, each leg also watches the other's book
+        if let Some(PairTerms {
+            pricing: EntryPricing::SpreadTargeted { target_spread_bps },
+            ..
+        }) = pair
+        {
+            for (leg, other) in [(0, 1), (1, 0)] {
+                plans[leg].spread_target = Some(SpreadTarget {
+                    adapter: plans[other].adapter.clone(),
+                    symbol: plans[other].symbol.clone(),
+                    target_spread_bps: *target_spread_bps,
+                });
+            }
+        }
+
+        let results = self
+            .run_leg_list(
+                request.trade_id,
+                &plans,
+                self.config.leg_entry_max_retries,
+                OnAbandon::Unwind,
+                pair.is_some(),
+            )
+            .await;
+
+        let mut errors = Vec::new();
+        let mut aborted = false;
+        let mut timed_out = false;
+        let mut maintenance = false;
+        let mut legs = Vec::with_capacity(plans.len());
+        let legs_in = request.legs.iter().zip(&plans).zip(&sizes).zip(arrivals);
+        for ((((leg, plan), size), arrival), result) in legs_in.zip(results) {
+            let (filled, avg_price, shortfall, slices) = match result {
+                Ok(r) => {
+                    aborted |= r.aborted;
+                    timed_out |= r.timed_out;
+                    if let Some(detail) = &r.maintenance {
+                        maintenance = true;
+                        errors.push(format!("{} leg stopped by exchange maintenance: {}", plan.name, detail));
+                    } else if let Some(detail) = &r.margin_rejected {
+                        errors.push(format!("{} leg stopped by the exchange's margin check: {}", plan.name, detail));
+                    } else if !r.is_complete && !r.aborted && !r.timed_out {
+                        errors.push(format!("{} leg only partially filled", plan.name));
+                    }
+                    (r.filled_quantity, r.avg_fill_price, r.shortfall, r.slices)
+                }
+                Err(e) => {
+                    errors.push(format!("{} leg failed: {}", plan.name, e));
+                    (Decimal::ZERO, Decimal::ZERO, Decimal::ZERO, Vec::new())
+                }
+            };
+            legs.push(EnteredLeg {
+                result: LegResult {
+                    exchange_id: leg.exchange_id.clone(),
+                    symbol: leg.symbol.clone(),
+                    side: leg.side,
+                    filled,
+                    avg_price,
+                    unwound: Decimal::ZERO,
+                },
+                shortfall,
+                arrival,
+                size_residual: size.residual_coins,
+                slices,
+            });
+        }
+        // When a leg hit maintenance the others were stopped with the kill switch
+        if aborted && !maintenance {
+            errors.push("Aborted by kill switch".to_string());
+        } else if timed_out {
+            errors.push("Total timeout reached before the trade filled".to_string());
+        }
+
+        if !errors.is_empty() {
+            warn!("Unwinding trade {}: {}", request.trade_id, errors.join("; "));
+            let unwinds = plans.iter().zip(&legs).map(|(plan, leg)| async move {
+                if leg.result.filled <= Decimal::ZERO {
+                    return Ok(Decimal::ZERO);
+                }
+                self.flatten_leg(plan, leg.result.filled, &leg.slices).await
+            });
+            let unwound = futures::future::join_all(unwinds).await;
+            for ((plan, leg), unwound) in plans.iter().zip(&mut legs).zip(unwound) {
+                let leg = &mut leg.result;
+                match unwound {
+                    Ok(quantity) => {
+                        if quantity > leg.filled {
+                            warn!(
+                                "{} leg of trade {} filled {} more after it finished",
+                                plan.name,
+                                request.trade_id,
+                                quantity - leg.filled
+                            );
+                            leg.filled = quantity;
+                        }
+                        leg.unwound = quantity;
+                    }
+                    Err(e) => errors.push(format!("{} leg could not be unwound: {}", plan.name, e)),
+                }
+                if leg.unwound < leg.filled {
+                    error!(
+                        "{} leg of trade {} left with {} open",
+                        plan.name,
+                        request.trade_id,
+                        leg.filled - leg.unwound
+                    );
+                }
+            }
+        }
+
+        // Whatever is left open stays tracked until an exit closes it
+        for leg in &legs {
+            if leg.result.filled > leg.result.unwound {
+                self.record_entry(&leg.result.exchange_id, &leg.result.symbol).await;
+            }
+        }
+        let open = request
+            .legs
+            .iter()
+            .zip(&plans)
+            .zip(&legs)
+            .map(|((leg, plan), entered)| TrackedLeg {
+                exchange_id: leg.exchange_id.clone(),
+                symbol: leg.symbol.clone(),
+                side: leg.side,
+                api_key_id: leg.api_key_id,
+                quantity: entered.result.filled - entered.result.unwound,
+                entry_price: entered.result.avg_price,
+                contract: plan.slicing.contract,
+                mark_price: None,
+                unrealized_pnl: None,
+            })
+            .collect();
+        self.positions.open(TrackedPosition::new(request.trade_id, open)).await;
+
+        Ok(Entry { legs, errors, aborted })
+    }
+
+    /// Close what a leg of a failed entry opened. A slice fill can land
+    /// after the leg's fills were counted, so before every close the position
+    /// is re-read and the leg's slices asked for fills taken since. Each
+    /// close is sized to the position's growth since the leg started, so
+    /// other trades' positions on the account are left alone, and never to
+    /// more than the leg has filled and not yet closed. Venues that don't
+    /// report positions get a single close of the leg's fills. Returns how
+    /// much was closed.
+    async fn flatten_leg(&self, plan: &LegPlan, filled: Decimal, slices: &[SliceResult]) -> Result<Decimal> {
+        let exit_side = match plan.side {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        };
+        let mut closed = Decimal::ZERO;
+        let mut reports_positions = true;
+        for round in 0..MAX_FLATTEN_ROUNDS {
+            let owed = filled + late_fills(plan, slices).await - closed;
+            let open = match position_size(plan.adapter.as_ref(), &plan.credentials, &plan.symbol, plan.side).await {
+                Ok(held) => owed.min(held - plan.position_before.unwrap_or_default()),
+                Err(e) if round == 0 => {
+                    debug!("Unwinding {} leg without reading its position: {:#}", plan.name, e);
+                    reports_positions = false;
+                    owed
+                }
+                Err(e) => {
+                    warn!("{} leg position could not be re-read after unwinding: {:#}", plan.name, e);
+                    return Ok(closed);
+                }
+            };
+            if open <= Decimal::ZERO {
+                return Ok(closed);
+            }
+
+            let exit = OrderSlicer::new(plan.slicing.clone())
+                .with_order_store(self.order_store.clone())
+                .execute_emergency_exit(plan.adapter.as_ref(), &plan.credentials, &plan.symbol, exit_side, open)
+                .await;
+            match exit {
+                Ok(exit) => closed += exit.filled_quantity,
+                Err(e) if closed.is_zero() => return Err(e),
+                Err(e) => {
+                    warn!("{} leg unwind stopped after closing {}: {:#}", plan.name, closed, e);
+                    return Ok(closed);
+                }
+            }
+            if !reports_positions {
+                return Ok(closed);
+            }
+        }
+        warn!(
+            "{} {} on {} still open after {} rounds of unwinding",
+            plan.name,
+            plan.symbol,
+            plan.adapter.id(),
+            MAX_FLATTEN_ROUNDS
+        );
+        Ok(closed)
+    }
+
+    /// Ready one sized leg of an entry, sliced over `profile`: put its
+    /// account in one-way mode, note the position it already holds, and
+    /// check the leg fits its risk limit
+    async fn plan_leg(
+        &self,
+        name: String,
+        leg: &Leg,
+        resolved: ResolvedLeg,
+        quantity: Decimal,
+        profile: &SlicingConfig,
+    ) -> Result<LegPlan> {
+        let ResolvedLeg {
+            adapter,
+            credentials,
+            info,
+            contract,
+            arrival,
+        } = resolved;
+
+        // Reduce-only exits only close the position they target in one-way mode
+        ensure_one_way_mode(adapter.as_ref(), &credentials).await;
+        let position_before = match position_size(adapter.as_ref(), &credentials, &leg.symbol, leg.side).await {
+            Ok(size) => Some(size),
+            Err(e) => {
+                debug!("No {} position to unwind against on {}: {:#}", leg.symbol, leg.exchange_id, e);
+                None
+            }
+        };
+
+        // The tier caps the whole position, so what's already held counts too.
+        // A leg past it would only be rejected partway through
+        let held = position_before.unwrap_or_default();
+        let notional_usd = contract.notional_usd(held + quantity, arrival.unwrap_or_default());
+        self.check_risk_limit(adapter.as_ref(), &credentials, &leg.symbol, notional_usd)
+            .await?;
+        let slicing = self.leg_slicing(
+            &leg.exchange_id,
+            contract,
+            info.as_ref(),
+            leg.slicing.apply(profile, leg.size_in_coins),
+        );
+        Ok(LegPlan {
+            name,
+            adapter,
+            credentials,
+            symbol: leg.symbol.clone(),
+            side: leg.side,
+            quantity,
+            reference_price: arrival.unwrap_or_default(),
+            slicing,
+            api_key_id: leg.api_key_id,
+            spread_target: None,
+            position_before,
+        })
+    }
+
+    /// Work every leg under one kill switch, registered for `trade_id`, and
+    /// return their results in order. Legs run concurrently unless configured
+    /// to run in sequence. A leg that fails trips the kill switch, so the
+    /// others cancel their resting slices and stop rather than leaving a
+    /// one-sided position. (try_join! would instead drop them mid-flight with
+    /// orders still live.)
+    async fn run_legs<const N: usize>(
+        &self,
+        trade_id: Uuid,
+        legs: &[LegPlan; N],
+        max_retries: u32,
+        on_abandon: OnAbandon,
+    ) -> [Result<SlicedOrderResult>; N] {
+        let results = self.run_leg_list(trade_id, legs, max_retries, on_abandon, true).await;
+        match results.try_into() {
+            Ok(results) => results,
+            Err(_) => unreachable!("one result per leg"),
+        }
+    }
+
+    async fn run_leg_list(
+        &self,
+        trade_id: Uuid,
+        legs: &[LegPlan],
+        max_retries: u32,
+        on_abandon: OnAbandon,
+        paired: bool,
+    ) -> Vec<Result<SlicedOrderResult>> {
+        let kill_switch = Arc::new(AtomicBool::new(false));
+        self.kill_switches
+            .write()
+            .await
+            .insert(trade_id, kill_switch.clone());
+        let placements: Vec<_> = legs.iter().map(|_| Arc::new(std::sync::Mutex::new(Vec::new()))).collect();
+        self.progress.write().await.insert(
+            trade_id,
+            TradeProgress {
+                legs: legs.iter().cloned().zip(placements.iter().cloned()).collect(),
+                on_abandon,
+                paired,
+            },
+        );
+
+        for leg in legs {
+            self.cancel_on_disconnect
+                .arm(leg.adapter.clone(), &leg.credentials)
+                .await;
+        }
+
+        let runs = legs.iter().zip(&placements).map(|(leg, placements)| {
+            let mut slicer = self
+                .slicer(leg.slicing.clone())
+                .with_kill_switch(kill_switch.clone())
+                .with_open_order_limits(self.open_orders.clone(), leg.api_key_id)
+                .with_order_store(self.order_store.clone())
+                .with_placements(placements.clone());
+            if let Some(target) = &leg.spread_target {
+                slicer = slicer.with_spread_target(target.clone());
+            }
+            let kill_switch = kill_switch.clone();
+            async move {
+                let mut result = slicer
+                    .execute_sliced_order(
+                        leg.adapter.as_ref(),
+                        &leg.credentials,
+                        &leg.symbol,
+                        leg.side,
+                        leg.quantity,
+                        leg.reference_price,
+                    )
+                    .await;
+                // A shortfall left by transient errors is worked again
+                // before it fails the trade
+                let mut backoff = LEG_RETRY_BACKOFF;
+                for attempt in 1..=max_retries {
+                    let shortfall = match &result {
+                        Ok(r) if r.retryable && !kill_switch.load(Ordering::SeqCst) => r.shortfall,
+                        _ => break,
+                    };
+                    warn!(
+                        "{} leg fell short by {} on transient errors, retrying ({}/{})",
+                        leg.name, shortfall, attempt, max_retries
+                    );
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    let retry = slicer
+                        .execute_sliced_order(
+                            leg.adapter.as_ref(),
+                            &leg.credentials,
+                            &leg.symbol,
+                            leg.side,
+                            shortfall,
+                            leg.reference_price,
+                        )
+                        .await;
+                    if let Ok(r) = &mut result {
+                        match retry {
+                            Ok(retry) => r.absorb_retry(retry),
+                            // What already filled is kept
+                            Err(e) => {
+                                warn!("{} leg retry failed: {}", leg.name, e);
+                                r.retryable = false;
+                            }
+                        }
+                    }
+                }
+                trip_on_failure(&result, &kill_switch, &leg.name);
+                if let Ok(SlicedOrderResult { maintenance: Some(detail), .. }) = &result {
+                    if self.config.maintenance_probe_secs > 0 {
+                        self.maintenance.flag(leg.adapter.id(), detail).await;
+                    }
+                }
+                result
+            }
+        });
+
+        // Legs are worked concurrently by default to keep the hedge balanced
+        let results = if self.config.sequential_legs {
+            let mut results = Vec::with_capacity(legs.len());
+            for run in runs {
+                results.push(run.await);
+            }
+            results
+        } else {
+            futures::future::join_all(runs).await
+        };
+
+        self.kill_switches.write().await.remove(&trade_id);
+        self.progress.write().await.remove(&trade_id);
+        results
+    }
+
+    /// Clean up after a trade abandoned mid-flight: cancel whatever its legs
+    /// left resting, count what they filled from the orders' latest states,
+    /// and close an entry's fills again. An order whose placement never
+    /// returned can't be seen; cancel-on-disconnect, where armed, is the
+    /// backstop for it. Returns the result with the legs' fills, and what
+    /// was done or the problems left.
+    async fn abandon(
+        &self,
+        trade_id: Uuid,
+        progress: TradeProgress,
+    ) -> (ExecutionResult, std::result::Result<&'static str, String>) {
+        let unwind = progress.on_abandon == OnAbandon::Unwind;
+        let settle = progress.legs.iter().map(|(plan, placements)| async move {
+            let placed = placements.lock().unwrap().clone();
+            let mut problems = Vec::new();
+            let mut filled = Decimal::ZERO;
+            let mut notional = Decimal::ZERO;
+            for order in placed {
+                let order = if order.status.is_terminal() {
+                    order
+                } else {
+                    let cancelled = tokio::time::timeout(
+                        TRADE_TIMEOUT_GRACE,
+                        plan.adapter.cancel_order(&plan.credentials, &plan.symbol, &order.exchange_order_id),
+                    )
+                    .await
+                    .unwrap_or_else(|_| Err(anyhow::anyhow!("cancel did not return")));
+                    match cancelled {
+                        Ok(state) => {
+                            self.order_store.update(&state);
+                            state
+                        }
+                        Err(e) => {
+                            problems.push(format!(
+                                "{} leg order {} may still be open: {}",
+                                plan.name, order.exchange_order_id, e
+                            ));
+                            order
+                        }
+                    }
+                };
+                filled += order.filled_quantity;
+                notional += order.filled_quantity * order.avg_fill_price.or(order.price).unwrap_or_default();
+            }
+
+            let mut unwound = Decimal::ZERO;
+            if unwind && filled > Decimal::ZERO {
+                match self.flatten_leg(plan, filled, &[]).await {
+                    Ok(closed) => unwound = closed,
+                    Err(e) => problems.push(format!("{} leg could not be unwound: {}", plan.name, e)),
+                }
+                if unwound < filled {
+                    error!("{} leg of abandoned trade {} left with {} open", plan.name, trade_id, filled - unwound);
+                    problems.push(format!("{} leg left with {} open", plan.name, filled - unwound));
+                }
+            }
+            let leg = LegResult {
+                exchange_id: plan.adapter.id().to_string(),
+                symbol: plan.symbol.clone(),
+                side: plan.side,
+                filled,
+                avg_price: if filled > Decimal::ZERO { notional / filled } else { Decimal::ZERO },
+                unwound,
+            };
+            (leg, problems)
+        });
+        let (legs, problems): (Vec<LegResult>, Vec<Vec<String>>) =
+            futures::future::join_all(settle).await.into_iter().unzip();
+
+        let mut result = ExecutionResult::failed(trade_id, String::new());
+        match (progress.paired, legs.as_slice()) {
+            (true, [long, short]) => {
+                result.long_filled = long.filled;
+                result.long_avg_price = long.avg_price;
+                result.short_filled = short.filled;
+                result.short_avg_price = short.avg_price;
+            }
+            _ => result.legs = legs,
+        }
+        let problems: Vec<String> = problems.into_iter().flatten().collect();
+        let outcome = match (problems.is_empty(), unwind) {
+            (true, true) => Ok("its orders were cancelled and its fills unwound"),
+            (true, false) => Ok("its resting orders were cancelled"),
+            (false, _) => Err(problems.join("; ")),
+        };
+        (result, outcome)
+    }
+
+    /// Enforce the per-trade notional cap on the largest leg, given as
+    /// contract, size and arrival price. Entries that cannot be priced are
+    /// rejected as well.
+    fn check_notional(&self, legs: &[(ContractSpec, Decimal, Option<Decimal>)]) -> Result<()> {
+        let mut notional = Decimal::ZERO;
+        for (contract, size, arrival) in legs {
+            let Some(price) = arrival else {
+                anyhow::bail!("No reference price to check the notional cap against");
+            };
+            notional = notional.max(contract.notional_usd(*size, *price));
+        }
+        let max_notional = Decimal::try_from(self.config.max_notional_usd).unwrap_or_default();
+        if notional > max_notional {
+            anyhow::bail!(
+                "Notional {} USD exceeds the {} USD limit per trade",
+                notional.round_dp(2),
+                max_notional
+            );
+        }
+        Ok(())
+    }
+
+    /// Check a position worth `notional_usd` once the leg fills fits the
+    /// account's risk limit tier on `symbol`, moving to a higher tier first where configured to and
+    /// the venue allows it. Venues that don't report risk limits pass.
+    async fn check_risk_limit(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        credentials: &Credentials,
+        symbol: &str,
+        notional_usd: Decimal,
+    ) -> Result<()> {
+        let limit = match adapter.get_risk_limit(credentials, symbol).await {
+            Ok(limit) => limit,
+            Err(e) => {
+                debug!("Risk limit unavailable on {}: {}", adapter.id(), e);
+                return Ok(());
+            }
+        };
+        if notional_usd <= limit.max_notional_usd {
+            return Ok(());
+        }
+        if self.config.raise_risk_limits {
+            match adapter.set_risk_limit(credentials, symbol, notional_usd).await {
+                Ok(raised) if notional_usd <= raised.max_notional_usd => {
+                    info!(
+                        "Raised the {} risk limit on {} to {} USD",
+                        adapter.id(),
+                        symbol,
+                        raised.max_notional_usd
+                    );
+                    return Ok(());
+                }
+                Ok(_) => {}
+                Err(e) => warn!("Could not raise the {} risk limit on {}: {:#}", adapter.id(), symbol, e),
+            }
+        }
+        anyhow::bail!(
+            "{} USD on {} {} exceeds the account's {} USD risk limit",
+            notional_usd.round_dp(2),
+            adapter.id(),
+            symbol,
+            limit.max_notional_usd
+        )
+    }
+
+    /// Status and contract size of `symbol`, cached for a while. `None` for
+    /// venues that can't report them, or fail to.
+    async fn symbol_info(&self, adapter: &dyn ExchangeAdapter, symbol: &str) -> Option<SymbolInfo> {
+        let key = (adapter.id().to_string(), symbol.to_string());
+        let cached = self
+            .symbol_info_cache
+            .read()
+            .await
+            .get(&key)
+            .filter(|(_, read_at)| read_at.elapsed() < SYMBOL_INFO_CACHE_TTL)
+            .map(|(info, _)| info.clone());
+        if cached.is_some() {
+            return cached;
+        }
+
+        match adapter.get_symbol_info(symbol).await {
+            Ok(info) => {
+                self.symbol_info_cache
+                    .write()
+                    .await
+                    .insert(key, (info.clone(), std::time::Instant::now()));
+                Some(info)
+            }
+            Err(e) => {
+                debug!("Symbol info unavailable on {}: {}", adapter.id(), e);
+                None
+            }
+        }
+    }
+
+    /// Fail if rounding the legs to their contract sizes leaves any of them
+    /// further out of proportion in coins than the configured tolerance
+    fn check_leg_residual(&self, legs: &[Leg], sizes: &[LegSize]) -> Result<()> {
+        let max_bps = Decimal::try_from(self.config.max_leg_residual_bps).unwrap_or_default();
+        for (leg, size) in legs.iter().zip(sizes) {
+            if size.contracts.is_zero() {
+                anyhow::bail!("Size {} is below one contract on {} {}", leg.size_in_coins, leg.exchange_id, leg.symbol);
+            }
+            let residual_bps = size.residual_coins.abs() / leg.size_in_coins * dec!(10000);
+            if residual_bps > max_bps {
+                anyhow::bail!(
+                    "{} {} is off by {} coins after rounding to contract sizes, {} bps exceeds the {} bps limit",
+                    leg.exchange_id,
+                    leg.symbol,
+                    size.residual_coins,
+                    residual_bps.round_dp(2),
+                    max_bps
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Fail unless `symbol` is open for trading and its exchange is not in
+    /// maintenance. Venues that can't report symbol status, or fail to, are
+    /// let through.
+    async fn check_tradable(&self, adapter: &dyn ExchangeAdapter, symbol: &str) -> Result<()> {
+        self.maintenance.check(adapter.id()).await?;
+        let Some(info) = self.symbol_info(adapter, symbol).await else {
+            return Ok(());
+        };
+        if !info.status.is_tradable() {
+            anyhow::bail!("{} is not tradable on {}: {}", symbol, adapter.id(), info.status);
+        }
+        Ok(())
+    }
+
+    /// Fail if an entry on `symbol` completed within the configured cooldown
+    async fn check_cooldown(&self, exchange_id: &str, symbol: &str) -> Result<()> {
+        let cooldown_ms = self.config.symbol_cooldown_ms;
+        if cooldown_ms == 0 {
+            return Ok(());
+        }
+        let Some(last) = self.last_entry_at(exchange_id, symbol).await else {
+            return Ok(());
+        };
+
+        let elapsed_ms = u64::try_from(Utc::now().timestamp_millis() - last).unwrap_or(0);
+        if elapsed_ms < cooldown_ms {
+            anyhow::bail!(
+                "{} on {} is in cooldown: last entry {} ms ago, cooldown is {} ms",
+                symbol,
+                exchange_id,
+                elapsed_ms,
+                cooldown_ms
+            );
+        }
+        Ok(())
+    }
+
+    /// When an entry on `symbol` last completed, from Redis when connected
+    /// and otherwise from this instance's own record
+    async fn last_entry_at(&self, exchange_id: &str, symbol: &str) -> Option<i64> {
+        if let Some(mut conn) = self.redis.clone() {
+            match conn.get::<_, Option<i64>>(cooldown_key(exchange_id, symbol)).await {
+                Ok(last) => return last,
+                Err(e) => warn!("Failed to read cooldown for {} on {}: {}", symbol, exchange_id, e),
+            }
+        }
+        self.last_entries
+            .read()
+            .await
+            .get(&(exchange_id.to_string(), symbol.to_string()))
+            .copied()
+    }
+
+    /// Start the cooldown for `symbol` after an entry traded on it
+    async fn record_entry(&self, exchange_id: &str, symbol: &str) {
+        let cooldown_ms = self.config.symbol_cooldown_ms;
+        if cooldown_ms == 0 {
+            return;
+        }
+        let now = Utc::now().timestamp_millis();
+        self.last_entries
+            .write()
+            .await
+            .insert((exchange_id.to_string(), symbol.to_string()), now);
+
+        if let Some(mut conn) = self.redis.clone() {
+            let written: redis::RedisResult<()> = conn
+                .pset_ex(cooldown_key(exchange_id, symbol), now, cooldown_ms)
+                .await;
+            if let Err(e) = written {
+                warn!("Failed to store cooldown for {} on {}: {}", symbol, exchange_id, e);
+            }
+        }
+    }
+
+    /// `slicing` for one leg, with its venue's maker fee and its symbol's
+    /// contract, tick, step and minimum notional
+    fn leg_slicing(
+        &self,
+        exchange_id: &str,
+        contract: ContractSpec,
+        info: Option<&SymbolInfo>,
+        slicing: SlicingConfig,
+    ) -> SlicingConfig {
+        SlicingConfig {
+            maker_fee_bps: self.maker_fee_bps(exchange_id),
+            taker_fee_bps: self.exchange_config(exchange_id).map_or(0.0, |e| e.taker_fee_bps),
+            ws_orders: self.exchange_config(exchange_id).is_some_and(|e| e.ws_orders),
+            client_order_id_prefix: self
+                .exchange_config(exchange_id)
+                .and_then(|e| e.client_order_id_prefix.clone())
+                .unwrap_or_else(|| DEFAULT_CLIENT_ORDER_ID_PREFIX.to_string()),
+            contract,
+            tick_size: info.map_or(Decimal::ZERO, |info| info.tick_size),
+            quantity_step: info.map_or(Decimal::ZERO, |info| info.quantity_step),
+            min_notional: info.map_or(Decimal::ZERO, |info| info.min_notional),
+            ..slicing
+        }
+    }
+
+    /// A slicer for `slicing`, sizing slices with the registered algorithm
+    /// it picks, if any
+    fn slicer(&self, slicing: SlicingConfig) -> OrderSlicer {
+        match self.algorithms.get(&slicing) {
+            Some(factory) => OrderSlicer::new(slicing).with_algorithm(move |config| factory(config)),
+            None => OrderSlicer::new(slicing),
+        }
+    }
+
+    /// Slicing parameters for a normal exit, from the symbol's defaults
+    fn exit_slicing_config(&self, adapter: &dyn ExchangeAdapter, symbol: &str) -> SlicingConfig {
+        SlicingConfig {
+            reduce_only: true,
+            ..self.config.slicing_for(adapter, symbol).clone()
+        }
+    }
+
+    /// Price a leg's slippage and notional are measured against, from the
+    /// configured source, falling back to the mid of `book` where the venue
+    /// doesn't publish that price
+    async fn reference_price(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        symbol: &str,
+        book: Option<(Decimal, Decimal)>,
+    ) -> Option<Decimal> {
+        let source = self.config.reference_price_source;
+        if source == ReferencePriceSource::Mid {
+            return book.map(|(bid, ask)| (bid + ask) / Decimal::TWO);
+        }
+        let published = adapter.get_reference_price(symbol, source).await;
+        published_or_mid(adapter, symbol, source, published, book)
+    }
+
+    /// Book and reference price of every leg of an entry, all read at once
+    async fn entry_prices(&self, legs: &[(&dyn ExchangeAdapter, &str)]) -> Vec<LegPrices> {
+        futures::future::join_all(legs.iter().map(|&(adapter, symbol)| self.leg_prices(adapter, symbol))).await
+    }
+
+    /// A leg's book and reference price, read concurrently instead of the
+    /// reference price waiting on the book for its fallback
+    async fn leg_prices(&self, adapter: &dyn ExchangeAdapter, symbol: &str) -> LegPrices {
+        let source = self.config.reference_price_source;
+        let published = async {
+            if source == ReferencePriceSource::Mid {
+                return None;
+            }
+            Some(adapter.get_reference_price(symbol, source).await)
+        };
+        let (book, published) = tokio::join!(top_of_book(adapter, symbol), published);
+        let arrival = match published {
+            Some(published) => published_or_mid(adapter, symbol, source, published, book),
+            None => book.map(|(bid, ask)| (bid + ask) / Decimal::TWO),
+        };
+        LegPrices { book, arrival }
+    }
+
+    fn maker_fee_bps(&self, exchange_id: &str) -> f64 {
+        self.exchange_config(exchange_id).map_or(0.0, |e| e.maker_fee_bps)
+    }
+
+    fn exchange_config(&self, exchange_id: &str) -> Option<&ExchangeConfig> {
+        self.config.exchanges.iter().find(|e| e.id == exchange_id)
+    }
+
+    /// Look up decrypted credentials for an API key, with the account's
+    /// additional read keys
+    async fn get_key_pool(&self, api_key_id: Uuid) -> Result<KeyPool> {
+        if let Some(cached) = self.api_key_cache.read().await.get(&api_key_id) {
+            if cached.expires_at > std::time::Instant::now() {
+                return Ok(cached.keys.clone());
+            }
+        }
+
+        let source = self
+            .credential_source
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("No credential source configured"))?;
+        let keys = source.load_pool(api_key_id).await?;
+
+        self.api_key_cache.write().await.insert(
+            api_key_id,
+            CachedCredentials {
+                keys: keys.clone(),
+                expires_at: std::time::Instant::now() + CREDENTIAL_CACHE_TTL,
+            },
+        );
+        Ok(keys)
+    }
+
+    async fn execute_exit(&self, request: TradeExitRequest) -> ExecutionResult {
+        info!(
+            "Executing trade exit: {} (emergency: {})",
+            request.trade_id, request.is_emergency
+        );
+
+        if request.mode == ExecutionMode::Sim {
+            if request.trailing_stop.is_some() {
+                return ExecutionResult::failed(
+                    request.trade_id,
+                    "Trailing stops are not simulated".to_string(),
+                );
+            }
+            return self.simulate_exit(&request).await;
+        }
+
+        if request.is_emergency {
+            return ExecutionResult::failed(
+                request.trade_id,
+                "Emergency exit execution not yet implemented".to_string(),
+            );
+        }
+
+        let long_adapter = match self.adapter(&request.long_exchange_id) {
+            Ok(a) => a,
+            Err(e) => return ExecutionResult::failed(request.trade_id, e.to_string()),
+        };
+        let short_adapter = match self.adapter(&request.short_exchange_id) {
+            Ok(a) => a,
+            Err(e) => return ExecutionResult::failed(request.trade_id, e.to_string()),
+        };
+
+        let policy = &self.config.symbol_policy;
+        if let Err(e) = policy
+            .check(long_adapter.as_ref(), &request.long_symbol)
+            .and(policy.check(short_adapter.as_ref(), &request.short_symbol))
+        {
+            error!("Rejecting exit {}: {}", request.trade_id, e);
+            return ExecutionResult::failed(request.trade_id, e.to_string());
+        }
+
+        let (long_maintenance, short_maintenance) = tokio::join!(
+            self.maintenance.check(&request.long_exchange_id),
+            self.maintenance.check(&request.short_exchange_id),
+        );
+        if let Err(e) = long_maintenance.and(short_maintenance) {
+            warn!("Holding exit {}: {}", request.trade_id, e);
+            return ExecutionResult::failed(request.trade_id, e.to_string());
+        }
+
+        let long_keys = match self.get_key_pool(request.long_api_key_id).await {
+            Ok(k) => k,
+            Err(e) => return ExecutionResult::failed(request.trade_id, e.to_string()),
+        };
+        let short_keys = match self.get_key_pool(request.short_api_key_id).await {
+            Ok(k) => k,
+            Err(e) => return ExecutionResult::failed(request.trade_id, e.to_string()),
+        };
+
+        let selection = self.config.read_key_selection;
+        let long_adapter = PooledAdapter::wrap(long_adapter, &long_keys, selection);
+        let short_adapter = PooledAdapter::wrap(short_adapter, &short_keys, selection);
+
+        let (long_book, short_book) = tokio::join!(
+            top_of_book(long_adapter.as_ref(), &request.long_symbol),
+            top_of_book(short_adapter.as_ref(), &request.short_symbol),
+        );
+        let (long_reference, short_reference) = tokio::join!(
+            self.reference_price(long_adapter.as_ref(), &request.long_symbol, long_book),
+            self.reference_price(short_adapter.as_ref(), &request.short_symbol, short_book),
+        );
+        let (long_info, short_info) = tokio::join!(
+            self.symbol_info(long_adapter.as_ref(), &request.long_symbol),
+            self.symbol_info(short_adapter.as_ref(), &request.short_symbol),
+        );
+
+        // Same as entry but with reverse sides, and every slice reduce-only
+        // so a late fill can never flip the position
+        let legs = [
+            LegPlan {
+                name: "Long".to_string(),
+                slicing: self.leg_slicing(
+                    &request.long_exchange_id,
+                    leg_contract(long_adapter.as_ref(), &request.long_symbol, long_info.as_ref()),
+                    long_info.as_ref(),
+                    self.exit_slicing_config(long_adapter.as_ref(), &request.long_symbol),
+                ),
+                adapter: long_adapter,
+                credentials: long_keys.primary,
+                symbol: request.long_symbol.clone(),
+                side: Side::Sell,
+                quantity: request.long_quantity,
+                reference_price: long_reference.unwrap_or_default(),
+                api_key_id: request.long_api_key_id,
+                spread_target: None,
+                position_before: None,
+            },
+            LegPlan {
+                name: "Short".to_string(),
+                slicing: self.leg_slicing(
+                    &request.short_exchange_id,
+                    leg_contract(short_adapter.as_ref(), &request.short_symbol, short_info.as_ref()),
+                    short_info.as_ref(),
+                    self.exit_slicing_config(short_adapter.as_ref(), &request.short_symbol),
+                ),
+                adapter: short_adapter,
+                credentials: short_keys.primary,
+                symbol: request.short_symbol.clone(),
+                side: Side::Buy,
+                quantity: request.short_quantity,
+                reference_price: short_reference.unwrap_or_default(),
+                api_key_id: request.short_api_key_id,
+                spread_target: None,
+                position_before: None,
+            },
+        ];
+        if let Some(trail) = request.trailing_stop {
+            return self.place_trailing_stops(request.trade_id, legs, trail).await;
+        }
+        let [mut long_result, mut short_result] = self.run_legs(request.trade_id, &legs, 0, OnAbandon::Keep).await;
+        let [long_leg, short_leg] = &legs;
+        let (long_flat, short_flat) = tokio::join!(
+            settle_reduce_only_rejection(long_leg, &mut long_result),
+            settle_reduce_only_rejection(short_leg, &mut short_result),
+        );
+
+        let result = combine_results(request.trade_id, long_result, short_result);
+        // A leg found flat is closed in full, whatever this exit filled of it
+        let closed = |flat: bool, filled: Decimal| if flat { Decimal::MAX } else { filled };
+        let positions = &self.positions;
+        tokio::join!(
+            positions.reduce(
+                request.trade_id,
+                &request.long_exchange_id,
+                &request.long_symbol,
+                Side::Buy,
+                closed(long_flat, result.long_filled),
+            ),
+            positions.reduce(
+                request.trade_id,
+                &request.short_exchange_id,
+                &request.short_symbol,
+                Side::Sell,
+                closed(short_flat, result.short_filled),
+            ),
+        );
+        result
+    }
+
+    /// Leave a trailing stop on each leg. Venues that can't hold the trail
+    /// natively are emulated in the background, behind a venue stop where
+    /// they take one, until hit or until the trade's kill switch is set.
+    async fn place_trailing_stops(
+        &self,
+        trade_id: Uuid,
+        legs: [LegPlan; 2],
+        trail: Trail,
+    ) -> ExecutionResult {
+        let mut stops = Vec::new();
+        let mut errors = Vec::new();
+        let mut emulated = Vec::new();
+        let kill_switch = Arc::new(AtomicBool::new(false));
+
+        for leg in legs {
+            let request = TrailingStopRequest {
+                client_order_id: generate_client_order_id(&leg.slicing.client_order_id_prefix),
+                symbol: leg.symbol.clone(),
+                side: leg.side,
+                quantity: leg.quantity,
+                trail,
+            };
+            let native = leg.adapter.supports_trailing_stop(trail);
+            let exchange_id = leg.adapter.id().to_string();
+            let order_id = if native {
+                match leg.adapter.place_trailing_stop(&leg.credentials, &request).await {
+                    Ok(order) => Some(order.exchange_order_id).filter(|id| !id.is_empty()),
+                    Err(e) => {
+                        errors.push(format!("{} leg trailing stop failed: {}", leg.name, e));
+                        continue;
+                    }
+                }
+            } else {
+                match EmulatedTrail::start(leg.adapter, leg.credentials, request).await {
+                    Ok(emulation) => {
+                        let order_id = emulation.order_id().map(str::to_string);
+                        emulated.push(emulation.run(OrderSlicer::new(leg.slicing), kill_switch.clone()));
+                        order_id
+                    }
+                    Err(e) => {
+                        errors.push(format!("{} leg trailing stop failed: {}", leg.name, e));
+                        continue;
+                    }
+                }
+            };
+
+            stops.push(TrailingStopResult {
+                exchange_id,
+                symbol: leg.symbol,
+                side: leg.side,
+                native,
+                order_id,
+            });
+        }
+
+        if !emulated.is_empty() {
+            self.kill_switches
+                .write()
+                .await
+                .insert(trade_id, kill_switch);
+            let kill_switches = self.kill_switches.clone();
+            tokio::spawn(TRADE_ID.scope(trade_id, async move {
+                for result in futures::future::join_all(emulated).await {
+                    match result {
+                        Ok(Some(exit)) => info!(
+                            "Trailing stop closed {} / {} @ {}",
+                            exit.filled_quantity, exit.total_quantity, exit.avg_fill_price
+                        ),
+                        Ok(None) => {}
+                        Err(e) => error!("Trailing stop exit failed: {}", e),
+                    }
+                }
+                kill_switches.write().await.remove(&trade_id);
+            }));
+        }
+
+        ExecutionResult {
+            success: errors.is_empty(),
+            error: if errors.is_empty() { None } else { Some(errors.join("; ")) },
+            trailing_stops: stops,
+            ..ExecutionResult::failed(trade_id, String::new())
+        }
+    }
+
+    /// Fill both legs against the live books without placing orders, and
+    /// remember the prices so a sim exit can be priced against them
+    async fn simulate_entry(&self, request: &TradeEntryRequest) -> ExecutionResult {
         info!("Simulating trade entry: {}", request.trade_id);
 
-        // In simulation mode, assume perfect fills at market price
-        // Real implementation would walk the orderbook
-        ExecutionResult {
-            trade_id: request.trade_id,
-            success: true,
-            long_filled: request.size_in_coins,
-            long_avg_price: Decimal::ZERO, // Would be calculated from orderbook
-            short_filled: request.size_in_coins,
-            short_avg_price: Decimal::ZERO,
-            error: None,
+        let (long_adapter, short_adapter) = match (
+            self.adapter(&request.long_exchange_id),
+            self.adapter(&request.short_exchange_id),
+        ) {
+            (Ok(long), Ok(short)) => (long, short),
+            (Err(e), _) | (_, Err(e)) => return ExecutionResult::failed(request.trade_id, e.to_string()),
+        };
+
+        let long_book = top_of_book(long_adapter.as_ref(), &request.long_symbol).await;
+        let short_book = top_of_book(short_adapter.as_ref(), &request.short_symbol).await;
+
+        // Sized in contracts as a live entry would be, which is also what
+        // the books are quoted in
+        let (long_info, short_info) = tokio::join!(
+            self.symbol_info(long_adapter.as_ref(), &request.long_symbol),
+            self.symbol_info(short_adapter.as_ref(), &request.short_symbol),
+        );
+        let lot = |adapter: &dyn ExchangeAdapter, symbol: &str, info: Option<&SymbolInfo>, book: Option<(Decimal, Decimal)>| {
+            let mid = book.map(|(bid, ask)| (bid + ask) / dec!(2));
+            contract_lot(leg_contract(adapter, symbol, info), info, mid)
+        };
+        let [long_size, short_size] = reconcile_leg_sizes(&[
+            (
+                request.size_in_coins,
+                lot(long_adapter.as_ref(), &request.long_symbol, long_info.as_ref(), long_book),
+            ),
+            (
+                request.size_in_coins,
+                lot(short_adapter.as_ref(), &request.short_symbol, short_info.as_ref(), short_book),
+            ),
+        ])[..] else {
+            unreachable!("one size per leg");
+        };
+        let residual = long_size.residual_coins - short_size.residual_coins;
+
+        let long = simulate_fill(long_adapter.as_ref(), &request.long_symbol, Side::Buy, long_size.contracts);
+        let short = simulate_fill(short_adapter.as_ref(), &request.short_symbol, Side::Sell, short_size.contracts);
+        let (long, short) = match tokio::join!(long, short) {
+            (Ok(long), Ok(short)) => (long, short),
+            (Err(e), _) | (_, Err(e)) => return ExecutionResult::failed(request.trade_id, e.to_string()),
+        };
+
+        self.sim_positions.write().await.insert(
+            request.trade_id,
+            SimPosition {
+                long_price: long.1,
+                short_price: short.1,
+            },
+        );
+
+        let filled = long.0 >= long_size.contracts && short.0 >= short_size.contracts;
+        let arrival = |book: Option<(Decimal, Decimal)>| book.map(|(bid, ask)| (bid + ask) / Decimal::TWO);
+        let (long_arrival, short_arrival) = (arrival(long_book), arrival(short_book));
+
+        ExecutionResult {
+            schema_version: RESULT_SCHEMA_VERSION,
+            trade_id: request.trade_id,
+            success: filled,
+            long_filled: long.0,
+            long_avg_price: long.1,
+            short_filled: short.0,
+            short_avg_price: short.1,
+            long_shortfall: (long_size.contracts - long.0).max(Decimal::ZERO),
+            short_shortfall: (short_size.contracts - short.0).max(Decimal::ZERO),
+            long_unwound: Decimal::ZERO,
+            short_unwound: Decimal::ZERO,
+            leg_size_residual: (!residual.is_zero()).then_some(residual),
+            error: (!filled).then(|| "Book too thin to fill the trade in full".to_string()),
+            aborted: false,
+            long_slippage_bps: long_arrival.and_then(|a| slippage_bps(Side::Buy, a, long.1)),
+            short_slippage_bps: short_arrival.and_then(|a| slippage_bps(Side::Sell, a, short.1)),
+            intended_spread_bps: long_arrival.zip(short_arrival).and_then(|(l, s)| spread_bps(l, s)),
+            realized_spread_bps: spread_bps(long.1, short.1),
+            realized_pnl: None,
+            legs: Vec::new(),
+            trailing_stops: Vec::new(),
+            slices: Vec::new(),
+        }
+    }
+
+    /// Close a simulated entry against the live books: the long leg sells into
+    /// the bids and the short leg buys from the asks. Emergency exits fill at
+    /// the aggressive limit a live emergency exit would send.
+    async fn simulate_exit(&self, request: &TradeExitRequest) -> ExecutionResult {
+        info!(
+            "Simulating trade exit: {} (emergency: {})",
+            request.trade_id, request.is_emergency
+        );
+
+        let Some(entry) = self.sim_positions.read().await.get(&request.trade_id).copied() else {
+            return ExecutionResult::failed(
+                request.trade_id,
+                format!("No simulated entry for trade {}", request.trade_id),
+            );
+        };
+
+        let (long_adapter, short_adapter) = match (
+            self.adapter(&request.long_exchange_id),
+            self.adapter(&request.short_exchange_id),
+        ) {
+            (Ok(long), Ok(short)) => (long, short),
+            (Err(e), _) | (_, Err(e)) => return ExecutionResult::failed(request.trade_id, e.to_string()),
+        };
+
+        let (long, short) = if request.is_emergency {
+            tokio::join!(
+                simulate_emergency_fill(long_adapter.as_ref(), &request.long_symbol, Side::Sell, request.long_quantity),
+                simulate_emergency_fill(short_adapter.as_ref(), &request.short_symbol, Side::Buy, request.short_quantity),
+            )
+        } else {
+            tokio::join!(
+                simulate_fill(long_adapter.as_ref(), &request.long_symbol, Side::Sell, request.long_quantity),
+                simulate_fill(short_adapter.as_ref(), &request.short_symbol, Side::Buy, request.short_quantity),
+            )
+        };
+        let (long, short) = match (long, short) {
+            (Ok(long), Ok(short)) => (long, short),
+            (Err(e), _) | (_, Err(e)) => return ExecutionResult::failed(request.trade_id, e.to_string()),
+        };
+
+        let pnl = long_adapter
+            .contract_spec(&request.long_symbol)
+            .pnl(Side::Buy, long.0, entry.long_price, long.1)
+            + short_adapter
+                .contract_spec(&request.short_symbol)
+                .pnl(Side::Sell, short.0, entry.short_price, short.1);
+
+        let filled = long.0 >= request.long_quantity && short.0 >= request.short_quantity;
+        if filled {
+            self.sim_positions.write().await.remove(&request.trade_id);
+        }
+
+        ExecutionResult {
+            schema_version: RESULT_SCHEMA_VERSION,
+            trade_id: request.trade_id,
+            success: filled,
+            long_filled: long.0,
+            long_avg_price: long.1,
+            short_filled: short.0,
+            short_avg_price: short.1,
+            long_shortfall: (request.long_quantity - long.0).max(Decimal::ZERO),
+            short_shortfall: (request.short_quantity - short.0).max(Decimal::ZERO),
+            long_unwound: Decimal::ZERO,
+            short_unwound: Decimal::ZERO,
+            leg_size_residual: None,
+            error: (!filled).then(|| "Book too thin to close the trade in full".to_string()),
+            aborted: false,
+            long_slippage_bps: None,
+            short_slippage_bps: None,
+            intended_spread_bps: None,
+            realized_spread_bps: spread_bps(long.1, short.1),
+            realized_pnl: Some(pnl),
+            legs: Vec::new(),
+            trailing_stops: Vec::new(),
+            slices: Vec::new(),
+        }
+    }
+
+    /// Publish a result, retrying with backoff. A result that still can't be
+    /// published is written to the dead-letter file for replay at startup.
+    async fn publish_result(&self, conn: &mut ConnectionManager, result: &ExecutionResult) {
+        let data = match serde_json::to_string(result) {
+            Ok(d) => d,
+            Err(e) => {
+                error!("Failed to serialize result for trade {}: {}", result.trade_id, e);
+                return;
+            }
+        };
+
+        if let Err(e) = publish_with_retry(conn, &data, PUBLISH_ATTEMPTS, PUBLISH_BACKOFF).await {
+            error!(
+                "Failed to publish result for trade {} after {} attempts: {}",
+                result.trade_id, PUBLISH_ATTEMPTS, e
+            );
+            self.dead_letter_result(&data).await;
+        }
+    }
+
+    async fn dead_letter_result(&self, data: &str) {
+        match self.dead_letter.append(data).await {
+            Ok(()) => warn!("Result written to {} for replay", self.dead_letter.path().display()),
+            // Last resort: the log is the only record left
+            Err(e) => error!("RESULT LOST, could not write dead letter ({}): {}", e, data),
+        }
+    }
+
+    /// Read open positions back after a restart, and drop legs their
+    /// exchange no longer holds. Accounts whose positions can't be read are
+    /// kept as stored.
+    async fn rehydrate_positions(&self) {
+        match self.positions.load().await {
+            Ok(0) => return,
+            Ok(count) => info!("Loaded {} open positions", count),
+            Err(e) => {
+                warn!("Open positions not restored: {:#}", e);
+                return;
+            }
+        }
+
+        for (exchange_id, api_key_id) in self.positions.accounts().await {
+            let reported = async {
+                let adapter = self.adapter(&exchange_id)?;
+                let keys = self.get_key_pool(api_key_id).await?;
+                let positions = adapter.get_positions(&keys.primary).await?;
+                anyhow::Ok((adapter, positions))
+            };
+            match reported.await {
+                Ok((adapter, reported)) => {
+                    let held = |symbol: &str, side: Side| {
+                        let native = adapter.native_symbol(symbol);
+                        reported.iter().any(|p| p.symbol == native && p.side == side)
+                    };
+                    self.positions.reconcile(&exchange_id, api_key_id, held).await;
+                }
+                Err(e) => warn!("Positions on {} not checked against the exchange: {:#}", exchange_id, e),
+            }
+        }
+    }
+
+    /// Mark open positions to their reference price on a timer
+    async fn mark_positions(self: Arc<Self>) {
+        let interval_ms = self.config.position_mark_interval_ms;
+        if interval_ms == 0 {
+            return;
+        }
+        loop {
+            tokio::time::sleep(std::time::Duration::from_millis(interval_ms)).await;
+            for (exchange_id, symbol) in self.positions.markets().await {
+                let Ok(adapter) = self.adapter(&exchange_id) else {
+                    continue;
+                };
+                let book = top_of_book(adapter.as_ref(), &symbol).await;
+                if let Some(price) = self.reference_price(adapter.as_ref(), &symbol, book).await {
+                    self.positions.mark(&exchange_id, &symbol, price).await;
+                }
+            }
+        }
+    }
+
+    /// Publish results left over from earlier runs. Each is removed from the
+    /// dead-letter file only once published, so a crash or a failed publish
+    /// leaves it there for the next run.
+    async fn replay_dead_letters(&self, conn: &ConnectionManager) {
+        let entries = match self.dead_letter.entries().await {
+            Ok(entries) => entries,
+            Err(e) => {
+                error!("Failed to read dead-lettered results: {}", e);
+                return;
+            }
+        };
+        if entries.is_empty() {
+            return;
+        }
+
+        warn!("Replaying {} dead-lettered results", entries.len());
+        let mut conn = conn.clone();
+        for data in entries {
+            match publish_with_retry(&mut conn, &data, PUBLISH_ATTEMPTS, PUBLISH_BACKOFF).await {
+                Ok(()) => {
+                    if let Err(e) = self.dead_letter.remove(&data).await {
+                        error!("Failed to remove replayed result from the dead-letter file: {}", e);
+                    }
+                }
+                Err(e) => error!("Failed to replay dead-lettered result, keeping it: {}", e),
+            }
+        }
+    }
+}
+
+/// Destination for serialized results
+#[async_trait::async_trait]
+trait ResultSink {
+    async fn send(&mut self, data: &str) -> Result<()>;
+}
+
+#[async_trait::async_trait]
+impl ResultSink for ConnectionManager {
+    async fn send(&mut self, data: &str) -> Result<()> {
+        let _: String = self.xadd(RESULT_STREAM, "*", &[("data", data)]).await?;
+        Ok(())
+    }
+}
+
+/// Send `data`, retrying with doubling backoff up to `attempts` times
+async fn publish_with_retry(
+    sink: &mut impl ResultSink,
+    data: &str,
+    attempts: u32,
+    backoff: std::time::Duration,
+) -> Result<()> {
+    let mut delay = backoff;
+    let mut attempt = 1;
+    loop {
+        match sink.send(data).await {
+            Ok(()) => return Ok(()),
+            Err(e) if attempt >= attempts => return Err(e),
+            Err(e) => {
+                warn!("Publishing result failed (attempt {}/{}): {}", attempt, attempts, e);
+                tokio::time::sleep(delay).await;
+                delay *= 2;
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// A request read off the request stream or posted to the HTTP interface
+pub enum Request {
+    Entry(TradeEntryRequest),
+    MultiLeg(MultiLegEntryRequest),
+    Exit(TradeExitRequest),
+}
+
+impl Request {
+    pub fn trade_id(&self) -> Uuid {
+        match self {
+            Request::Entry(request) => request.trade_id,
+            Request::MultiLeg(request) => request.trade_id,
+            Request::Exit(request) => request.trade_id,
+        }
+    }
+}
+
+/// Longest stretch of a rejected payload quoted back in the error
+const PAYLOAD_SNIPPET_CHARS: usize = 200;
+
+/// Extract the request from a stream entry, or the trade id (nil when it
+/// can't be read) and reason it was rejected
+fn read_request(entry: &redis::streams::StreamId) -> std::result::Result<Request, (Uuid, String)> {
+    let Some(value) = entry.map.get("data") else {
+        return Err((Uuid::nil(), "Request has no data field".to_string()));
+    };
+    // Handle the various redis Value types the payload may arrive as
+    let data = redis::from_redis_value::<Vec<u8>>(value)
+        .or_else(|_| redis::from_redis_value::<String>(value).map(String::into_bytes))
+        .map_err(|e| (Uuid::nil(), format!("Request data is not a string: {}", e)))?;
+    let data = String::from_utf8(data)
+        .map_err(|e| (Uuid::nil(), format!("Request data is not valid UTF-8: {}", e)))?;
+    parse_request(&data)
+}
+
+/// Parse a request payload. When neither request type fits, a payload that
+/// carries a field unique to one type is reported as an invalid request of
+/// that type, anything else as an unknown format with both parse errors.
+pub fn parse_request(data: &str) -> std::result::Result<Request, (Uuid, String)> {
+    let entry_error = match serde_json::from_str::<TradeEntryRequest>(data) {
+        Ok(request) => return Ok(Request::Entry(request)),
+        Err(e) => e,
+    };
+    let multi_leg_error = match serde_json::from_str::<MultiLegEntryRequest>(data) {
+        Ok(request) => return Ok(Request::MultiLeg(request)),
+        Err(e) => e,
+    };
+    let exit_error = match serde_json::from_str::<TradeExitRequest>(data) {
+        Ok(request) => return Ok(Request::Exit(request)),
+        Err(e) => e,
+    };
+
+    let snippet: String = data.chars().take(PAYLOAD_SNIPPET_CHARS).collect();
+    let value = match serde_json::from_str::<serde_json::Value>(data) {
+        Ok(value) => value,
+        Err(e) => return Err((Uuid::nil(), format!("Malformed request ({}): {}", e, snippet))),
+    };
+    let trade_id = value
+        .get("trade_id")
+        .and_then(|id| id.as_str())
+        .and_then(|id| id.parse().ok())
+        .unwrap_or_default();
+
+    let error = if value.get("spread_id").is_some() {
+        format!("Invalid entry request ({}): {}", entry_error, snippet)
+    } else if value.get("legs").is_some() {
+        format!("Invalid multi-leg entry request ({}): {}", multi_leg_error, snippet)
+    } else if value.get("position_id").is_some() {
+        format!("Invalid exit request ({}): {}", exit_error, snippet)
+    } else {
+        format!(
+            "Unknown request format (as entry: {}; as exit: {}): {}",
+            entry_error, exit_error, snippet
+        )
+    };
+    Err((trade_id, error))
+}
+
+fn cooldown_key(exchange_id: &str, symbol: &str) -> String {
+    format!("{}:{}:{}", COOLDOWN_KEY_PREFIX, exchange_id, symbol)
+}
+
+/// Merge the outcome of both legs into a single result
+fn combine_results(
+    trade_id: Uuid,
+    long: Result<SlicedOrderResult>,
+    short: Result<SlicedOrderResult>,
+) -> ExecutionResult {
+    let mut errors = Vec::new();
+    let mut stopped = None;
+    let mut slices = Vec::new();
+    let mut leg = |name: &'static str, result: Result<SlicedOrderResult>| match result {
+        Ok(r) => {
+            if let Some(detail) = &r.maintenance {
+                stopped.get_or_insert_with(|| format!("{} leg stopped by exchange maintenance: {}", name, detail));
+            } else if let Some(detail) = &r.margin_rejected {
+                stopped.get_or_insert_with(|| format!("{} leg stopped by the exchange's margin check: {}", name, detail));
+            }
+            let leg = if name == "Long" { "long" } else { "short" };
+            slices.extend(r.slices.into_iter().map(|slice| LegSlice { leg, slice }));
+            (r.filled_quantity, r.avg_fill_price, r.shortfall, r.is_complete, r.aborted, r.timed_out)
+        }
+        Err(e) => {
+            errors.push(format!("{} leg failed: {}", name, e));
+            (Decimal::ZERO, Decimal::ZERO, Decimal::ZERO, false, false, false)
+        }
+    };
+    let (long_filled, long_avg_price, long_shortfall, long_complete, long_aborted, long_timed_out) =
+        leg("Long", long);
+    let (short_filled, short_avg_price, short_shortfall, short_complete, short_aborted, short_timed_out) =
+        leg("Short", short);
+
+    // The other leg is stopped with the kill switch when one hits maintenance
+    // or the margin check
+    let aborted = long_aborted || short_aborted;
+    if let Some(stopped) = stopped {
+        errors.push(stopped);
+    } else if aborted {
+        errors.push("Aborted by kill switch".to_string());
+    } else if long_timed_out || short_timed_out {
+        errors.push("Total timeout reached before the trade filled".to_string());
+    } else if errors.is_empty() && !(long_complete && short_complete) {
+        errors.push("Trade only partially filled".to_string());
+    }
+
+    ExecutionResult {
+        schema_version: RESULT_SCHEMA_VERSION,
+        trade_id,
+        success: errors.is_empty(),
+        long_filled,
+        long_avg_price,
+        short_filled,
+        short_avg_price,
+        long_shortfall,
+        short_shortfall,
+        long_unwound: Decimal::ZERO,
+        short_unwound: Decimal::ZERO,
+        leg_size_residual: None,
+        error: if errors.is_empty() { None } else { Some(errors.join("; ")) },
+        aborted,
+        long_slippage_bps: None,
+        short_slippage_bps: None,
+        intended_spread_bps: None,
+        realized_spread_bps: None,
+        realized_pnl: None,
+        legs: Vec::new(),
+        trailing_stops: Vec::new(),
+        slices,
+    }
+}
+
+/// Settle an exit leg the exchange stopped by rejecting a reduce-only slice,
+/// which it does once there is no position left to reduce: typically another
+/// process closed it first. The leg counts as complete if the exchange
+/// confirms the position is flat, and whether it did is returned; otherwise
+/// the rejection stands as a shortfall.
+async fn settle_reduce_only_rejection(leg: &LegPlan, result: &mut Result<SlicedOrderResult>) -> bool {
+    let Ok(sliced) = result else {
+        return false;
+    };
+    if !sliced.reduce_only_rejected {
+        return false;
+    }
+
+    let native = leg.adapter.native_symbol(&leg.symbol);
+    let held = position_side(leg.side, true);
+    match leg.adapter.get_positions(&leg.credentials).await {
+        Ok(positions) => {
+            if positions
+                .iter()
+                .any(|p| p.symbol == native && p.side == held && !p.quantity.is_zero())
+            {
+                warn!(
+                    "{} leg on {} was rejected as reduce-only, yet {} is still open",
+                    leg.name,
+                    leg.adapter.id(),
+                    leg.symbol
+                );
+                return false;
+            }
+            info!(
+                "{} leg on {} has no {} position left to reduce, treating it as closed",
+                leg.name,
+                leg.adapter.id(),
+                leg.symbol
+            );
+            sliced.is_complete = true;
+            sliced.shortfall = Decimal::ZERO;
+            true
+        }
+        Err(e) => {
+            warn!(
+                "{} leg on {} was rejected as reduce-only and its position could not be checked: {:#}",
+                leg.name,
+                leg.adapter.id(),
+                e
+            );
+            false
+        }
+    }
+}
+
+fn trip_on_failure(result: &Result<SlicedOrderResult>, kill_switch: &AtomicBool, leg: &str) {
+    match result {
+        Err(e) => warn!("{} leg failed, stopping the other legs: {}", leg, e),
+        Ok(SlicedOrderResult { maintenance: Some(detail), .. }) => {
+            warn!("{} leg stopped by maintenance, stopping the other legs: {}", leg, detail)
+        }
+        Ok(SlicedOrderResult { margin_rejected: Some(detail), .. }) => {
+            warn!("{} leg stopped by the margin check, stopping the other legs: {}", leg, detail)
+        }
+        Ok(_) => return,
+    }
+    kill_switch.store(true, Ordering::SeqCst);
+}
+
+/// Switch an account out of hedge mode. Venues without position modes are left
+/// alone; if the switch fails, hedge-aware adapters still tag orders with the
+/// position side.
+async fn ensure_one_way_mode(adapter: &dyn ExchangeAdapter, credentials: &Credentials) {
+    match adapter.get_position_mode(credentials).await {
+        Ok(false) => {}
+        Ok(true) => {
+            info!("Switching {} account to one-way position mode", adapter.id());
+            if let Err(e) = adapter.set_position_mode(credentials, false).await {
+                warn!("Staying in hedge mode on {}: {}", adapter.id(), e);
+            }
+        }
+        // Such as a flat Bybit account. Venues take a switch to the mode they
+        // are already in as success, so it is sent anyway.
+        Err(e) => {
+            debug!("Position mode unreadable on {}, switching to one-way: {}", adapter.id(), e);
+            if let Err(e) = adapter.set_position_mode(credentials, false).await {
+                debug!("Position mode unavailable on {}: {}", adapter.id(), e);
+            }
+        }
+    }
+}
+
+/// Quantity and average price a marketable order would get, walking the
+/// book where the venue serves depth and at the touch otherwise
+async fn simulate_fill(
+    adapter: &dyn ExchangeAdapter,
+    symbol: &str,
+    side: Side,
+    quantity: Decimal,
+) -> Result<(Decimal, Decimal)> {
+    let (filled, avg_price) = match adapter.get_order_book(symbol, SIM_BOOK_DEPTH).await {
+        Ok(book) => book.walk(side, quantity),
+        Err(e) => {
+            debug!("Simulating {} at the touch: {}", adapter.id(), e);
+            let (bid, ask) = adapter.get_best_price(symbol).await?;
+            let touch = match side {
+                Side::Buy => ask,
+                Side::Sell => bid,
+            };
+            (quantity, Some(touch))
+        }
+    };
+
+    match avg_price {
+        Some(price) => Ok((filled, price)),
+        None => anyhow::bail!("No liquidity to simulate {} on {}", symbol, adapter.id()),
+    }
+}
+
+/// Emergency exits are assumed to fill in full at the first aggressive limit
+/// price, the worst price that order would accept
+async fn simulate_emergency_fill(
+    adapter: &dyn ExchangeAdapter,
+    symbol: &str,
+    side: Side,
+    quantity: Decimal,
+) -> Result<(Decimal, Decimal)> {
+    let (bid, ask) = adapter.get_best_price(symbol).await?;
+    let price = OrderSlicer::new(SlicingConfig::default()).emergency_price(side, bid, ask, 0)?;
+    Ok((quantity, price))
+}
+
+/// What an entry leg is checked and measured against before trading
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LegPrices {
+    /// Best bid and ask, if the book could be read
+    book: Option<(Decimal, Decimal)>,
+    /// Reference price, or `None` if neither it nor the book could be read
+    arrival: Option<Decimal>,
+}
+
+/// The venue's `source` price if it published a usable one, otherwise the
+/// mid of `book`
+fn published_or_mid(
+    adapter: &dyn ExchangeAdapter,
+    symbol: &str,
+    source: ReferencePriceSource,
+    published: Result<Decimal>,
+    book: Option<(Decimal, Decimal)>,
+) -> Option<Decimal> {
+    match published {
+        Ok(price) if price > Decimal::ZERO => return Some(price),
+        Ok(price) => warn!("Ignoring {} price {} for {} on {}", source, price, symbol, adapter.id()),
+        Err(e) => warn!("No {} price for {} on {}, using the mid: {}", source, symbol, adapter.id(), e),
+    }
+    book.map(|(bid, ask)| (bid + ask) / Decimal::TWO)
+}
+
+/// Best bid and ask before any order is placed, if the book can be read
+async fn top_of_book(adapter: &dyn ExchangeAdapter, symbol: &str) -> Option<(Decimal, Decimal)> {
+    match adapter.get_best_price(symbol).await {
+        Ok(book) => Some(book),
+        Err(e) => {
+            warn!("Failed to fetch arrival price on {}: {}", adapter.id(), e);
+            None
+        }
+    }
+}
+
+/// One leg's order size in its venue's contracts, and how far rounding to
+/// them moved it
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct LegSize {
+    contracts: Decimal,
+    /// Rounded size minus the leg's share of the entry, in coins
+    residual_coins: Decimal,
+}
+
+/// Convert each leg's size in coins to a contract count in its lot, see
+/// `contract_lot`. The leg with the coarsest lot for its size is rounded to
+/// its nearest lot first, then the others are scaled by the same ratio and
+/// matched as closely as their own lots allow, so each is off by at most
+/// half of its lot.
+fn reconcile_leg_sizes(legs: &[(Decimal, (Decimal, Decimal))]) -> Vec<LegSize> {
+    let lots: Vec<_> = legs.iter().map(|(_, lot)| *lot).collect();
+    let coarseness = |i: usize| {
+        let (size, (contract_size, step)) = (legs[i].0, lots[i]);
+        if size > Decimal::ZERO {
+            contract_size * step / size
+        } else {
+            Decimal::ZERO
+        }
+    };
+    // Ties go to the earlier leg
+    let Some(coarsest) = (0..legs.len()).rev().max_by_key(|&i| coarseness(i)) else {
+        return Vec::new();
+    };
+    let anchor = contracts_for(legs[coarsest].0, lots[coarsest]);
+    let ratio = if legs[coarsest].0 > Decimal::ZERO {
+        anchor * lots[coarsest].0 / legs[coarsest].0
+    } else {
+        Decimal::ONE
+    };
+
+    legs.iter()
+        .zip(&lots)
+        .enumerate()
+        .map(|(i, (&(size, _), &lot))| {
+            let (contracts, target) = if i == coarsest {
+                (anchor, anchor * lot.0)
+            } else {
+                (contracts_for(size * ratio, lot), size * ratio)
+            };
+            LegSize {
+                contracts: contracts.normalize(),
+                residual_coins: (contracts * lot.0 - target).normalize(),
+            }
+        })
+        .collect()
+}
+
+/// Of `profiles`, the one slicing most finely: the smallest slices, then
+/// the fewest in parallel, then the longest interval
+fn conservative_profile<'a>(profiles: &[&'a SlicingConfig]) -> &'a SlicingConfig {
+    profiles
+        .iter()
+        .copied()
+        .min_by(|a, b| {
+            a.slice_percent
+                .total_cmp(&b.slice_percent)
+                .then(a.max_parallel.cmp(&b.max_parallel))
+                .then(b.interval_ms.cmp(&a.interval_ms))
+        })
+        .expect("an entry has legs")
+}
+
+/// Size of the position `credentials` hold on `symbol` on `side`
+async fn position_size(
+    adapter: &dyn ExchangeAdapter,
+    credentials: &Credentials,
+    symbol: &str,
+    side: Side,
+) -> Result<Decimal> {
+    let native = adapter.native_symbol(symbol);
+    Ok(adapter
+        .get_positions(credentials)
+        .await?
+        .iter()
+        .filter(|p| p.symbol == native && p.side == side)
+        .map(|p| p.quantity)
+        .sum())
+}
+
+/// Fills a leg's slices now report beyond those counted when the leg
+/// finished. Slices counted as filled in full are not re-read.
+async fn late_fills(plan: &LegPlan, slices: &[SliceResult]) -> Decimal {
+    let counted: HashMap<&str, Decimal> = slices
+        .iter()
+        .filter(|slice| slice.filled_quantity < slice.quantity)
+        .filter_map(|slice| Some((slice.exchange_order_id.as_deref()?, slice.filled_quantity)))
+        .collect();
+    if counted.is_empty() {
+        return Decimal::ZERO;
+    }
+    let order_ids: Vec<String> = counted.keys().map(|id| id.to_string()).collect();
+    match plan.adapter.get_orders_batch(&plan.credentials, &plan.symbol, &order_ids).await {
+        Ok(orders) => orders
+            .iter()
+            .filter_map(|order| Some((order.filled_quantity - counted.get(order.exchange_order_id.as_str())?).max(Decimal::ZERO)))
+            .sum(),
+        Err(e) => {
+            warn!("{} leg slices could not be re-read for late fills: {:#}", plan.name, e);
+            Decimal::ZERO
+        }
+    }
+}
+
+/// Coins per contract and contract step, defaulting to unstepped coins.
+/// An inverse contract holds `price` worth of its USD value in coins, and
+/// steps in whole contracts unless the venue says otherwise; without a
+/// price it is sized in coins too.
+fn contract_lot(contract: ContractSpec, info: Option<&SymbolInfo>, price: Option<Decimal>) -> (Decimal, Decimal) {
+    match (contract.contract_type, price.filter(|price| *price > Decimal::ZERO)) {
+        (ContractType::Inverse, Some(price)) => {
+            let step = info.map_or(Decimal::ZERO, |info| info.quantity_step);
+            (contract.multiplier / price, if step > Decimal::ZERO { step } else { Decimal::ONE })
+        }
+        _ => info
+            .filter(|info| info.contract_size > Decimal::ZERO)
+            .filter(|_| contract.contract_type == ContractType::Linear)
+            .map(|info| (info.contract_size, info.quantity_step))
+            .unwrap_or((Decimal::ONE, Decimal::ZERO)),
+    }
+}
+
+/// `coins` in contracts of the given lot, to the nearest step
+fn contracts_for(coins: Decimal, (contract_size, step): (Decimal, Decimal)) -> Decimal {
+    let contracts = coins / contract_size;
+    if step > Decimal::ZERO {
+        (contracts / step).round() * step
+    } else {
+        contracts
+    }
+}
+
+/// Contract of a leg, taking its multiplier from its symbol info where the
+/// venue reported one
+fn leg_contract(adapter: &dyn ExchangeAdapter, symbol: &str, info: Option<&SymbolInfo>) -> ContractSpec {
+    let contract = adapter.contract_spec(symbol);
+    match (info, contract.contract_type) {
+        (Some(info), ContractType::Linear) => ContractSpec::linear(info.contract_size),
+        (Some(info), ContractType::Inverse) if info.contract_size > Decimal::ZERO => {
+            ContractSpec::inverse(info.contract_size)
+        }
+        _ => contract,
+    }
+}
+
+/// Resolves on Ctrl-C, or SIGTERM where there is one
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    {
+        let mut terminate = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(signal) => signal,
+            Err(e) => {
+                warn!("Failed to listen for SIGTERM: {}", e);
+                let _ = tokio::signal::ctrl_c().await;
+                return;
+            }
+        };
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+/// Enforce the request's minimum spread, taken at the prices the legs would
+/// trade at: the long ask and the short bid
+fn check_spread(
+    min_spread_bps: Option<Decimal>,
+    long_book: Option<(Decimal, Decimal)>,
+    short_book: Option<(Decimal, Decimal)>,
+) -> Result<()> {
+    let Some(min_spread) = min_spread_bps else {
+        return Ok(());
+    };
+    let (Some((_, long_ask)), Some((short_bid, _))) = (long_book, short_book) else {
+        anyhow::bail!("No prices to check the minimum spread against");
+    };
+
+    let observed = spread_bps(long_ask, short_bid)
+        .ok_or_else(|| anyhow::anyhow!("Invalid long ask {} for the spread check", long_ask))?;
+    if observed < min_spread {
+        anyhow::bail!(
+            "Spread collapsed: observed {} bps, required {} bps",
+            observed,
+            min_spread
+        );
+    }
+    Ok(())
+}
+
+/// Execution slippage in bps, signed so that a positive value is a cost
+fn slippage_bps(side: Side, arrival: Decimal, avg_price: Decimal) -> Option<Decimal> {
+    if arrival.is_zero() {
+        return None;
+    }
+    let diff = match side {
+        Side::Buy => avg_price - arrival,
+        Side::Sell => arrival - avg_price,
+    };
+    Some((diff / arrival * dec!(10000)).round_dp(2))
+}
+
+/// Spread of the short leg over the long leg in bps
+fn spread_bps(long_price: Decimal, short_price: Decimal) -> Option<Decimal> {
+    if long_price.is_zero() {
+        return None;
+    }
+    Some(((short_price - long_price) / long_price * dec!(10000)).round_dp(2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{credentials, exchange_config, response_for, serve_http, MockAdapter};
+    use crate::exchange::{OrderStatus, OrderType, Position, SymbolStatus};
+    use crate::symbol_policy::SymbolPolicy;
+
+    fn server() -> ExecutionServer {
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+            Box::new(MockAdapter::new("long", dec!(100), dec!(101))),
+            Box::new(MockAdapter::new("short", dec!(102), dec!(103))),
+        ];
+        ExecutionServer::new(adapters, Config::for_tests())
+    }
+
+    async fn seed_credentials(server: &ExecutionServer, api_key_id: Uuid) {
+        server.api_key_cache.write().await.insert(
+            api_key_id,
+            CachedCredentials {
+                keys: KeyPool::single(credentials()),
+                expires_at: std::time::Instant::now() + std::time::Duration::from_secs(60),
+            },
+        );
+    }
+
+    fn entry_request() -> TradeEntryRequest {
+        TradeEntryRequest {
+            trade_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            spread_id: Uuid::new_v4(),
+            size_in_coins: dec!(1),
+            slicing: SlicingParams {
+                slice_size_coins: None,
+                slice_interval_ms: None,
+                maker_only: false,
+                strategy: SlicingStrategy::Fixed,
+                total_timeout_secs: None,
+                expire_at_ms: None,
+                use_book_imbalance: false,
+                completion_threshold: None,
+                pricing_ladder: None,
+                size_jitter_percent: None,
+                interval_jitter_percent: None,
+                jitter_seed: None,
+            },
+            mode: ExecutionMode::Live,
+            long_exchange_id: "long".to_string(),
+            long_symbol: "BTCUSDT".to_string(),
+            long_api_key_id: Uuid::new_v4(),
+            short_exchange_id: "short".to_string(),
+            short_symbol: "BTCUSDT".to_string(),
+            short_api_key_id: Uuid::new_v4(),
+            min_spread_bps: None,
+            pricing: EntryPricing::Independent,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_entry_on_a_symbol_outside_policy_is_rejected_up_front() {
+        let long = Arc::new(MockAdapter::new("long", dec!(100), dec!(101)));
+        let mut config = Config::for_tests();
+        config.symbol_policy = SymbolPolicy {
+            allowed: vec!["BTC/*".to_string(), "ETH/*".to_string()],
+            denied: Vec::new(),
+        };
+        let mut server = ExecutionServer::new(vec![Box::new(MockAdapter::new("short", dec!(102), dec!(103)))], config);
+        server.adapters.insert("long".to_string(), long.clone());
+
+        let mut request = entry_request();
+        request.long_symbol = "SOLUSDT".to_string();
+        let result = server.execute_entry(request).await;
+
+        assert_eq!(result.error.unwrap(), "SOLUSDT on long is not in the allowed symbols");
+        assert!(long.placed().is_empty());
+        assert_eq!(long.symbol_info_calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_estimate_walks_the_book_without_trading() {
+        let adapter = MockAdapter::new("long", dec!(100), dec!(101)).with_order_book(
+            &[(dec!(100), dec!(1)), (dec!(99), dec!(1))],
+            &[(dec!(101), dec!(0.5)), (dec!(102), dec!(1)), (dec!(103), dec!(2))],
+        );
+        let server = server();
+        // Slices of 1 priced at the bid plus 1%, which reaches the first ask level
+        let slicing = SlicingConfig {
+            slice_percent: 0.5,
+            price_tolerance_bps: 100.0,
+            ..SlicingConfig::default()
+        };
+
+        let estimate = server
+            .estimate_execution(&adapter, "BTCUSDT", Side::Buy, dec!(2), &slicing)
+            .await
+            .unwrap();
+        assert_eq!(estimate.fillable_quantity, dec!(2));
+        // (0.5 * 101 + 1 * 102 + 0.5 * 103) / 2
+        assert_eq!(estimate.vwap, Some(dec!(102)));
+        assert_eq!(estimate.arrival_price, Some(dec!(100.5)));
+        assert_eq!(estimate.slippage_bps.unwrap().round_dp(2), dec!(149.25));
+        assert_eq!(estimate.slices, 2);
+        assert!(estimate.plan.slices.iter().all(|slice| slice.quantity == dec!(1)));
+        assert_eq!(estimate.fill_probability, Some(dec!(0.5)));
+
+        let maker = SlicingConfig {
+            maker_only: true,
+            maker_fee_bps: 2.0,
+            ..slicing
+        };
+        let estimate = server
+            .estimate_execution(&adapter, "BTCUSDT", Side::Buy, dec!(5), &maker)
+            .await
+            .unwrap();
+        assert_eq!(estimate.fillable_quantity, dec!(3.5));
+        // 2 bps of 0.5 * 101 + 1 * 102 + 2 * 103
+        assert_eq!(estimate.fees, dec!(0.0717));
+        assert_eq!(estimate.fill_probability, None);
+        assert!(adapter.placed().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_sim_round_trip_walks_books_and_reports_pnl() {
+        // Only the long venue serves depth; the short one is simulated at the touch
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+            Box::new(MockAdapter::new("long", dec!(100), dec!(101)).with_order_book(
+                &[(dec!(100), dec!(1)), (dec!(99), dec!(1))],
+                &[(dec!(101), dec!(0.5)), (dec!(102), dec!(1))],
+            )),
+            Box::new(MockAdapter::new("short", dec!(102), dec!(103))),
+        ];
+        let server = ExecutionServer::new(adapters, Config::for_tests());
+
+        let exit_request = |trade_id, is_emergency| TradeExitRequest {
+            trade_id,
+            position_id: Uuid::new_v4(),
+            is_emergency,
+            long_exchange_id: "long".to_string(),
+            long_symbol: "BTCUSDT".to_string(),
+            long_quantity: dec!(1),
+            long_api_key_id: Uuid::new_v4(),
+            short_exchange_id: "short".to_string(),
+            short_symbol: "BTCUSDT".to_string(),
+            short_quantity: dec!(1),
+            short_api_key_id: Uuid::new_v4(),
+            mode: ExecutionMode::Sim,
+            trailing_stop: None,
+        };
+
+        let unknown = server.execute_exit(exit_request(Uuid::new_v4(), false)).await;
+        assert!(unknown.error.unwrap().contains("No simulated entry"));
+
+        let mut request = entry_request();
+        request.mode = ExecutionMode::Sim;
+        let entry = server.execute_entry(request.clone()).await;
+        assert!(entry.success, "{:?}", entry.error);
+        assert_eq!(entry.long_avg_price, dec!(101.5));
+        assert_eq!(entry.short_avg_price, dec!(102));
+
+        let exit = server.execute_exit(exit_request(request.trade_id, false)).await;
+        assert!(exit.success, "{:?}", exit.error);
+        assert_eq!(exit.long_avg_price, dec!(100));
+        assert_eq!(exit.short_avg_price, dec!(103));
+        // Long loses 1.5 and short loses 1
+        assert_eq!(exit.realized_pnl, Some(dec!(-2.5)));
+
+        // The position is closed, so an emergency exit needs a fresh entry
+        server.execute_entry(request.clone()).await;
+        let emergency = server.execute_exit(exit_request(request.trade_id, true)).await;
+        assert!(emergency.success, "{:?}", emergency.error);
+        assert!(emergency.long_avg_price < dec!(100));
+        assert!(emergency.short_avg_price > dec!(103));
+        assert!(emergency.realized_pnl.unwrap() < dec!(-2.5));
+    }
+
+    #[tokio::test]
+    async fn test_sim_entry_is_sized_in_contracts_like_a_live_one() {
+        // 0.01 coin contracts on the long leg, whose book is quoted in them
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+            Box::new(
+                MockAdapter::new("long", dec!(100), dec!(101))
+                    .with_contract_size(dec!(0.01), dec!(1))
+                    .with_order_book(&[(dec!(100), dec!(500))], &[(dec!(101), dec!(60))]),
+            ),
+            Box::new(MockAdapter::new("short", dec!(102), dec!(103))),
+        ];
+        let server = ExecutionServer::new(adapters, Config::for_tests());
+        let mut request = entry_request();
+        request.mode = ExecutionMode::Sim;
+
+        let entry = server.execute_entry(request).await;
+
+        assert!(!entry.success);
+        assert_eq!(entry.long_filled, dec!(60));
+        assert_eq!(entry.long_shortfall, dec!(40));
+        assert_eq!(entry.short_filled, dec!(1));
+        assert_eq!(entry.short_shortfall, Decimal::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_exchange_in_maintenance_holds_trades_until_it_returns() {
+        let short = Arc::new(MockAdapter::new("short", dec!(102), dec!(103)).with_clock_offset(0));
+        let mut server = server();
+        server.adapters.insert("short".to_string(), short.clone());
+        let request = || {
+            let request = entry_request();
+            (request.long_api_key_id, request.short_api_key_id, request)
+        };
+
+        // The first placement hits maintenance and the leg stops there
+        short.set_maintenance(true);
+        let (long_key, short_key, first) = request();
+        seed_credentials(&server, long_key).await;
+        seed_credentials(&server, short_key).await;
+        let result = server.execute_entry(first).await;
+        assert!(!result.success);
+        let error = result.error.unwrap();
+        assert!(error.starts_with("Short leg stopped by exchange maintenance"), "{}", error);
+        assert_eq!(short.placed().len(), 1);
+
+        // Later trades are turned away without placing anything
+        let (long_key, short_key, second) = request();
+        seed_credentials(&server, long_key).await;
+        seed_credentials(&server, short_key).await;
+        let result = server.execute_entry(second).await;
+        let error = result.error.unwrap();
+        assert!(error.starts_with("Exchange short has been in maintenance since"), "{}", error);
+        assert_eq!(short.placed().len(), 1);
+
+        // A probe that no longer sees maintenance lets trades through again
+        server.maintenance.probe(short.as_ref()).await;
+        assert!(server.maintenance.check("short").await.is_err());
+        short.set_maintenance(false);
+        server.maintenance.probe(short.as_ref()).await;
+        let (long_key, short_key, third) = request();
+        seed_credentials(&server, long_key).await;
+        seed_credentials(&server, short_key).await;
+        let result = server.execute_entry(third).await;
+        assert!(result.success, "{:?}", result.error);
+    }
+
+    #[tokio::test]
+    async fn test_entry_on_symbol_in_maintenance_is_rejected_up_front() {
+        let long = Arc::new(MockAdapter::new("long", dec!(100), dec!(101)).with_symbol_status(SymbolStatus::Trading));
+        let short = Arc::new(
+            MockAdapter::new("short", dec!(102), dec!(103)).with_symbol_status(SymbolStatus::Maintenance),
+        );
+        let mut server = server();
+        server.adapters.insert("long".to_string(), long.clone());
+        server.adapters.insert("short".to_string(), short.clone());
+
+        let request = entry_request();
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+
+        let result = server.execute_entry(request.clone()).await;
+        assert!(!result.success);
+        assert_eq!(result.error.as_deref(), Some("BTCUSDT is not tradable on short: maintenance"));
+        assert!(long.placed().is_empty());
+        assert!(short.placed().is_empty());
+
+        // The status is cached between trades
+        server.execute_entry(request).await;
+        assert_eq!(short.symbol_info_calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_leg_slices_are_priced_on_the_symbol_tick() {
+        let long = Arc::new(MockAdapter::new("long", dec!(100), dec!(101)).with_tick_size(dec!(0.5)));
+        let mut server = server();
+        server.adapters.insert("long".to_string(), long.clone());
+
+        let request = entry_request();
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+        let result = server.execute_entry(request).await;
+
+        assert!(result.success, "{:?}", result.error);
+        let prices: Vec<_> = long.placed().iter().filter_map(|order| order.price).collect();
+        assert!(!prices.is_empty());
+        assert!(prices.iter().all(|price| (price / dec!(0.5)).fract().is_zero()), "{:?}", prices);
+    }
+
+    #[tokio::test]
+    async fn test_leg_below_the_symbol_minimum_notional_is_refused() {
+        let short = Arc::new(MockAdapter::new("short", dec!(102), dec!(103)).with_min_notional(dec!(1000)));
+        let mut server = server();
+        server.adapters.insert("short".to_string(), short.clone());
+
+        let request = entry_request();
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+        let result = server.execute_entry(request).await;
+
+        // One coin at 102 is well short of 1000
+        assert!(!result.success);
+        let error = result.error.unwrap();
+        assert!(error.contains("below the venue's 1000 minimum notional"), "{}", error);
+        assert!(short.placed().is_empty());
+    }
+
+    /// Sink that fails a set number of times before accepting
+    struct FlakySink {
+        failures: usize,
+        sent: Vec<String>,
+    }
+
+    #[async_trait::async_trait]
+    impl ResultSink for FlakySink {
+        async fn send(&mut self, data: &str) -> Result<()> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                anyhow::bail!("connection reset");
+            }
+            self.sent.push(data.to_string());
+            Ok(())
         }
     }
 
-    async fn publish_result(&self, conn: &mut ConnectionManager, result: &ExecutionResult) {
-        let data = match serde_json::to_string(result) {
-            Ok(d) => d,
-            Err(e) => {
-                error!("Failed to serialize result: {}", e);
-                return;
+    #[tokio::test]
+    async fn test_publish_retries_then_gives_up() {
+        let backoff = std::time::Duration::from_millis(1);
+
+        let mut recovers = FlakySink { failures: 2, sent: Vec::new() };
+        publish_with_retry(&mut recovers, "result", 3, backoff).await.unwrap();
+        assert_eq!(recovers.sent, ["result"]);
+
+        let mut down = FlakySink { failures: 3, sent: Vec::new() };
+        let err = publish_with_retry(&mut down, "result", 3, backoff).await.unwrap_err();
+        assert_eq!(err.to_string(), "connection reset");
+        assert!(down.sent.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unpublished_result_is_dead_lettered() {
+        let server = server();
+        let result = ExecutionResult::failed(Uuid::new_v4(), "boom".to_string());
+        let data = serde_json::to_string(&result).unwrap();
+
+        server.dead_letter_result(&data).await;
+
+        let entries = server.dead_letter.entries().await.unwrap();
+        assert_eq!(entries, [data]);
+    }
+
+    #[test]
+    fn test_results_read_across_schema_versions() {
+        // Published before versioning, with the fields of the time
+        let trade_id = Uuid::new_v4();
+        let unversioned = serde_json::json!({
+            "trade_id": trade_id,
+            "success": true,
+            "long_filled": "1",
+            "long_avg_price": "101",
+            "short_filled": "1",
+            "short_avg_price": "102",
+            "error": null,
+        });
+        let result: ExecutionResult = serde_json::from_value(unversioned).unwrap();
+        assert_eq!(result.schema_version, 0);
+        assert_eq!(result.trade_id, trade_id);
+        assert_eq!(result.long_avg_price, dec!(101));
+        assert_eq!(result.long_shortfall, Decimal::ZERO);
+        assert!(!result.aborted);
+        assert!(result.legs.is_empty() && result.realized_pnl.is_none());
+
+        // A consumer written against that shape reads a current result
+        #[derive(Deserialize)]
+        struct Unversioned {
+            trade_id: Uuid,
+            success: bool,
+            short_avg_price: Decimal,
+            error: Option<String>,
+        }
+        let current = ExecutionResult {
+            legs: vec![LegResult {
+                exchange_id: "long".to_string(),
+                symbol: "BTCUSDT".to_string(),
+                side: Side::Buy,
+                filled: dec!(1),
+                avg_price: dec!(101),
+                unwound: Decimal::ZERO,
+            }],
+            ..ExecutionResult::failed(trade_id, "rejected".to_string())
+        };
+        let published = serde_json::to_value(&current).unwrap();
+        assert_eq!(published["schema_version"], RESULT_SCHEMA_VERSION);
+        let old: Unversioned = serde_json::from_value(published.clone()).unwrap();
+        assert_eq!(old.trade_id, trade_id);
+        assert!(!old.success);
+        assert_eq!(old.short_avg_price, Decimal::ZERO);
+        assert_eq!(old.error.as_deref(), Some("rejected"));
+
+        // And this one reads a result with fields added after it
+        let mut newer = published;
+        newer["fees_by_asset"] = serde_json::json!({ "USDT": "0.1" });
+        let result: ExecutionResult = serde_json::from_value(newer).unwrap();
+        assert_eq!(result.schema_version, RESULT_SCHEMA_VERSION);
+        assert_eq!(result.legs.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_entry_fills_both_legs() {
+        let server = server();
+        let request = entry_request();
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+
+        let result = server.execute_entry(request.clone()).await;
+
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.long_filled, dec!(1));
+        assert_eq!(result.short_filled, dec!(1));
+        assert!(!result.aborted);
+        assert!(result.long_slippage_bps.is_some());
+        assert!(result.short_slippage_bps.is_some());
+        // Mids of 100.5 and 102.5
+        assert_eq!(result.intended_spread_bps, Some(dec!(199.00)));
+        assert!(server.kill_switches.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_paired_legs_slice_by_the_finer_symbol_profile() {
+        let long = Arc::new(MockAdapter::new("long", dec!(100), dec!(101)));
+        let short = Arc::new(MockAdapter::new("short", dec!(102), dec!(103)));
+        let mut config = Config::for_tests();
+        config.symbol_profiles.insert(
+            "BTC".to_string(),
+            SlicingConfig {
+                slice_percent: 0.25,
+                ..config.slicing.clone()
+            },
+        );
+        let mut server = ExecutionServer::new(Vec::new(), config);
+        server.adapters.insert("long".to_string(), long.clone());
+        server.adapters.insert("short".to_string(), short.clone());
+        let mut request = entry_request();
+        request.short_symbol = "ETHUSDT".to_string();
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+
+        let result = server.execute_entry(request).await;
+
+        // ETH would go in the default halves, but the pair slices in BTC's quarters
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(long.placed().len(), 4);
+        assert_eq!(short.placed().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_slicing_config_picks_a_registered_algorithm() {
+        use crate::algorithm::{ExecState, ExecutionAlgorithm, SliceInstruction};
+
+        /// Sends the whole order in one slice
+        struct AllAtOnce;
+
+        impl ExecutionAlgorithm for AllAtOnce {
+            fn next_slice(&mut self, state: &ExecState) -> Option<SliceInstruction> {
+                Some(SliceInstruction { quantity: state.unplaced })
+            }
+        }
+
+        let mut algorithms = AlgorithmRegistry::default();
+        algorithms.register("all_at_once", |_| Box::new(AllAtOnce));
+        let mut config = Config::for_tests();
+        config.slicing.algorithm = Some("vwap".to_string());
+        let err = ExecutionServer::new(Vec::new(), config.clone())
+            .with_algorithms(algorithms.clone())
+            .err()
+            .unwrap();
+        assert_eq!(err.to_string(), "Unknown execution algorithm vwap");
+
+        let long = Arc::new(MockAdapter::new("long", dec!(100), dec!(101)));
+        let short = Arc::new(MockAdapter::new("short", dec!(102), dec!(103)));
+        config.slicing.algorithm = Some("all_at_once".to_string());
+        let mut server = ExecutionServer::new(Vec::new(), config).with_algorithms(algorithms).unwrap();
+        server.adapters.insert("long".to_string(), long.clone());
+        server.adapters.insert("short".to_string(), short.clone());
+        let request = entry_request();
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+
+        let result = server.execute_entry(request).await;
+
+        // One slice each rather than the default halves
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(long.placed().len(), 1);
+        assert_eq!(short.placed().len(), 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_independent_trades_execute_concurrently_up_to_the_limit() {
+        // Each trade hangs on its short leg until the trade timeout gives up on it
+        let run_two = |max_concurrent_trades| async move {
+            let config = Config {
+                trade_timeout_secs: 30,
+                max_concurrent_trades,
+                ..Config::for_tests()
+            };
+            let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+                Box::new(MockAdapter::new("long", dec!(100), dec!(101))),
+                Box::new(MockAdapter::new("short", dec!(102), dec!(103)).with_hanging_placements()),
+            ];
+            let server = Arc::new(ExecutionServer::new(adapters, config));
+            let started = tokio::time::Instant::now();
+            let mut runs = Vec::new();
+            for _ in 0..2 {
+                let request = entry_request();
+                seed_credentials(&server, request.long_api_key_id).await;
+                seed_credentials(&server, request.short_api_key_id).await;
+                let executor = server.clone();
+                runs.push(server.spawn_in_slot(async move { executor.execute(Request::Entry(request)).await }).await);
+            }
+            for run in runs {
+                assert!(run.await.unwrap().aborted);
+            }
+            started.elapsed()
+        };
+        let one_trade = std::time::Duration::from_secs(30) + TRADE_TIMEOUT_GRACE;
+
+        assert_eq!(run_two(2).await, one_trade);
+        assert_eq!(run_two(1).await, one_trade * 2);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_requests_for_one_trade_run_in_order() {
+        let server = server();
+        let log = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let work = |name: &'static str, secs| {
+            let log = log.clone();
+            async move {
+                log.lock().unwrap().push(format!("start {}", name));
+                tokio::time::sleep(std::time::Duration::from_secs(secs)).await;
+                log.lock().unwrap().push(format!("end {}", name));
             }
         };
+        let (trade, other) = (Uuid::new_v4(), Uuid::new_v4());
+        let mut running = HashMap::new();
+
+        server.spawn_for_trade(&mut running, trade, work("entry", 2)).await;
+        server.spawn_for_trade(&mut running, other, work("other", 1)).await;
+        server.spawn_for_trade(&mut running, trade, work("exit", 1)).await;
+        for (_, handle) in running {
+            handle.await.unwrap();
+        }
+
+        // The other trade runs alongside; the exit waits for its entry
+        assert_eq!(
+            *log.lock().unwrap(),
+            ["start entry", "start other", "end other", "end entry", "start exit", "end exit"]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_trade_timeout_stops_the_trade() {
+        let config = Config {
+            trade_timeout_secs: 30,
+            ..Config::for_tests()
+        };
+
+        // Slices that rest without filling are cancelled once the timeout passes
+        let resting = |_: usize, request: &crate::exchange::OrderRequest| {
+            Ok(response_for(request, OrderStatus::Open, Decimal::ZERO, None))
+        };
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+            Box::new(MockAdapter::new("long", dec!(100), dec!(101)).with_place_handler(resting)),
+            Box::new(MockAdapter::new("short", dec!(102), dec!(103)).with_place_handler(resting)),
+        ];
+        let server = ExecutionServer::new(adapters, config.clone());
+        let request = entry_request();
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+
+        let result = server.execute(Request::Entry(request)).await;
+        assert!(!result.success);
+        assert!(result.aborted);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Trade timed out after 30 s: Long leg only partially filled; Short leg only partially filled")
+        );
+        assert_eq!(result.long_filled, Decimal::ZERO);
+
+        // A leg stuck on a call that never returns is abandoned, and what the
+        // other leg filled is closed again
+        let long = Arc::new(MockAdapter::new("long", dec!(100), dec!(101)));
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![Box::new(
+            MockAdapter::new("short", dec!(102), dec!(103)).with_hanging_placements(),
+        )];
+        let mut server = ExecutionServer::new(adapters, config);
+        server.adapters.insert("long".to_string(), long.clone());
+        let request = entry_request();
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+
+        let started = tokio::time::Instant::now();
+        let result = server.execute(Request::Entry(request.clone())).await;
+        assert_eq!(started.elapsed(), std::time::Duration::from_secs(30) + TRADE_TIMEOUT_GRACE);
+        assert!(!result.success);
+        assert!(result.aborted);
+        assert_eq!(result.trade_id, request.trade_id);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Trade timed out after 30 s and did not stop; its orders were cancelled and its fills unwound")
+        );
+        assert_eq!(result.long_filled, dec!(1));
+        assert_eq!(result.short_filled, Decimal::ZERO);
+        let closes: Vec<Decimal> = long
+            .placed()
+            .iter()
+            .filter(|o| o.reduce_only)
+            .map(|o| o.quantity)
+            .collect();
+        assert_eq!(closes, [dec!(1)]);
+        assert!(server.kill_switches.read().await.is_empty());
+        assert!(server.progress.read().await.is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_resting_slices_are_tracked_and_cancelled_at_shutdown() {
+        let resting = |_: usize, request: &crate::exchange::OrderRequest| {
+            Ok(response_for(request, OrderStatus::Open, Decimal::ZERO, None))
+        };
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+            Box::new(MockAdapter::new("long", dec!(100), dec!(101)).with_place_handler(resting)),
+            Box::new(MockAdapter::new("short", dec!(102), dec!(103)).with_place_handler(resting)),
+        ];
+        let server = Arc::new(ExecutionServer::new(adapters, Config::for_tests()));
+        let request = entry_request();
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+
+        let trade = tokio::spawn({
+            let server = server.clone();
+            async move { server.execute(Request::Entry(request)).await }
+        });
+        while server.orders().live().len() < 2 {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        let mut exchanges: Vec<String> = server.orders().live().into_iter().map(|o| o.exchange_id).collect();
+        exchanges.sort();
+        assert_eq!(exchanges, ["long", "short"]);
+
+        // The trade sees its slices cancelled and finishes with nothing filled
+        server.cancel_live_orders().await;
+        assert_eq!(server.orders().live().len(), 0);
+        let result = trade.await.unwrap();
+        assert!(!result.success);
+        assert_eq!(result.long_filled, Decimal::ZERO);
+        assert_eq!(server.orders().live().len(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_legs_with_different_contract_sizes_are_reconciled() {
+        // 0.01 coin contracts against 0.007 coin contracts
+        let adapters = || -> Vec<Box<dyn ExchangeAdapter>> {
+            vec![
+                Box::new(MockAdapter::new("long", dec!(100), dec!(101)).with_contract_size(dec!(0.01), dec!(1))),
+                Box::new(MockAdapter::new("short", dec!(102), dec!(103)).with_contract_size(dec!(0.007), dec!(1))),
+            ]
+        };
+        let server = ExecutionServer::new(adapters(), Config::for_tests());
+        let request = entry_request();
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+
+        let result = server.execute_entry(request.clone()).await;
+
+        // 100 long contracts are 1 coin; 143 short contracts are 1.001
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.long_filled, dec!(100));
+        assert_eq!(result.short_filled, dec!(143));
+        assert_eq!(result.leg_size_residual, Some(dec!(-0.001)));
+
+        let config = Config {
+            max_leg_residual_bps: 5.0,
+            ..Config::for_tests()
+        };
+        let server = ExecutionServer::new(adapters(), config);
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+        let result = server.execute_entry(request).await;
+        assert!(!result.success);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("short BTCUSDT is off by 0.001 coins after rounding to contract sizes, 10.00 bps exceeds the 5 bps limit")
+        );
+        assert_eq!(result.long_filled, Decimal::ZERO);
+    }
+
+    #[tokio::test]
+    async fn test_reference_price_follows_configured_source() {
+        let publishing = MockAdapter::new("long", dec!(100), dec!(102))
+            .with_reference_prices(dec!(101.5), dec!(100.8), dec!(100.6));
+        let book_only = MockAdapter::new("short", dec!(100), dec!(102));
+        let book = Some((dec!(100), dec!(102)));
+
+        let cases = [
+            (ReferencePriceSource::Last, dec!(101.5)),
+            (ReferencePriceSource::Mark, dec!(100.8)),
+            (ReferencePriceSource::Index, dec!(100.6)),
+            (ReferencePriceSource::Mid, dec!(101)),
+        ];
+        for (source, expected) in cases {
+            let config = Config {
+                reference_price_source: source,
+                ..Config::for_tests()
+            };
+            let server = ExecutionServer::new(Vec::new(), config);
+            assert_eq!(server.reference_price(&publishing, "BTCUSDT", book).await, Some(expected));
+            // Venues without the price fall back to the mid
+            assert_eq!(server.reference_price(&book_only, "BTCUSDT", book).await, Some(dec!(101)));
+        }
+    }
+
+    #[test]
+    fn test_coarser_lot_is_rounded_first() {
+        let info = |contract_size, quantity_step| SymbolInfo {
+            symbol: "BTCUSDT".to_string(),
+            status: SymbolStatus::Trading,
+            contract_size,
+            quantity_step,
+            tick_size: Decimal::ZERO,
+            min_notional: Decimal::ZERO,
+        };
+
+        // Gate.io style 0.0001 coin contracts against coins in 0.001 steps:
+        // the coin leg rounds 0.12345 to 0.123 and Gate.io matches it exactly
+        let gate = info(dec!(0.0001), dec!(1));
+        let coins = info(dec!(1), dec!(0.001));
+        let lot = |info| contract_lot(ContractSpec::default(), info, None);
+        let sizes = reconcile_leg_sizes(&[(dec!(0.12345), lot(Some(&gate))), (dec!(0.12345), lot(Some(&coins)))]);
+        let exact = |contracts| LegSize {
+            contracts,
+            residual_coins: Decimal::ZERO,
+        };
+        assert_eq!(sizes, [exact(dec!(1230)), exact(dec!(0.123))]);
+
+        // Without symbol info every leg stays in coins
+        let sizes = reconcile_leg_sizes(&[(dec!(0.5), lot(None)), (dec!(0.5), lot(None)), (dec!(0.25), lot(None))]);
+        assert_eq!(sizes, [exact(dec!(0.5)), exact(dec!(0.5)), exact(dec!(0.25))]);
+
+        // Legs of other sizes keep their proportion to the rounded one: 0.1
+        // coin lots round 1.26 to 1.3, and the half-size leg follows to 0.65
+        let tenths = info(dec!(1), dec!(0.1));
+        let sizes = reconcile_leg_sizes(&[(dec!(0.63), lot(Some(&coins))), (dec!(1.26), lot(Some(&tenths)))]);
+        assert_eq!(sizes, [exact(dec!(0.65)), exact(dec!(1.3))]);
+
+        // 100 USD inverse contracts hold 0.002 coins at 50,000, so 0.1 coins
+        // is 50 whole contracts
+        let inverse = contract_lot(ContractSpec::inverse(dec!(100)), None, Some(dec!(50000)));
+        assert_eq!(inverse, (dec!(0.002), dec!(1)));
+        let sizes = reconcile_leg_sizes(&[(dec!(0.1), lot(Some(&coins))), (dec!(0.1), inverse)]);
+        assert_eq!(sizes, [exact(dec!(0.1)), exact(dec!(50))]);
+    }
+
+    #[tokio::test]
+    async fn test_entry_within_symbol_cooldown_is_rejected() {
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+            Box::new(MockAdapter::new("long", dec!(100), dec!(101))),
+            Box::new(MockAdapter::new("short", dec!(102), dec!(103))),
+        ];
+        let server = ExecutionServer::new(
+            adapters,
+            Config {
+                symbol_cooldown_ms: 60_000,
+                ..Config::for_tests()
+            },
+        );
+        let first = entry_request();
+        let second = TradeEntryRequest {
+            trade_id: Uuid::new_v4(),
+            ..first.clone()
+        };
+        seed_credentials(&server, first.long_api_key_id).await;
+        seed_credentials(&server, first.short_api_key_id).await;
+
+        let result = server.execute_entry(first).await;
+        assert!(result.success, "{:?}", result.error);
+
+        let result = server.execute_entry(second).await;
+        assert!(!result.success);
+        assert_eq!(result.long_filled, Decimal::ZERO);
+        let error = result.error.unwrap();
+        assert!(error.starts_with("BTCUSDT on long is in cooldown"), "{}", error);
+    }
+
+    #[test]
+    fn test_unparseable_requests_are_rejected_with_reason() {
+        let trade_id = Uuid::new_v4();
+        let error = |data: &str| match parse_request(data) {
+            Err(rejected) => rejected,
+            Ok(_) => panic!("{} parsed", data),
+        };
+
+        let (id, reason) = error("not json");
+        assert_eq!(id, Uuid::nil());
+        assert!(reason.starts_with("Malformed request"), "{}", reason);
+
+        let (id, reason) = error(&format!(
+            r#"{{"trade_id":"{}","spread_id":"{}","size_in_coins":"1"}}"#,
+            trade_id,
+            Uuid::new_v4()
+        ));
+        assert_eq!(id, trade_id);
+        assert!(reason.starts_with("Invalid entry request (missing field"), "{}", reason);
+        assert!(reason.contains(r#""size_in_coins":"1""#));
+
+        let (id, reason) = error(&format!(
+            r#"{{"trade_id":"{}","position_id":"{}","is_emergency":"yes"}}"#,
+            trade_id,
+            Uuid::new_v4()
+        ));
+        assert_eq!(id, trade_id);
+        assert!(reason.starts_with("Invalid exit request"), "{}", reason);
+
+        let (id, reason) = error(r#"{"action":"noop"}"#);
+        assert_eq!(id, Uuid::nil());
+        assert!(reason.starts_with("Unknown request format (as entry:"), "{}", reason);
+        assert!(reason.contains("; as exit:"));
+
+        let no_data = redis::streams::StreamId {
+            id: "1-0".to_string(),
+            map: HashMap::new(),
+        };
+        assert!(matches!(read_request(&no_data), Err((_, reason)) if reason == "Request has no data field"));
+    }
+
+    #[tokio::test]
+    async fn test_exit_unwinds_both_legs() {
+        let server = server();
+        let entry = entry_request();
+        seed_credentials(&server, entry.long_api_key_id).await;
+        seed_credentials(&server, entry.short_api_key_id).await;
+        let request = TradeExitRequest {
+            trade_id: entry.trade_id,
+            position_id: Uuid::new_v4(),
+            is_emergency: false,
+            long_exchange_id: entry.long_exchange_id,
+            long_symbol: entry.long_symbol,
+            long_quantity: dec!(1),
+            long_api_key_id: entry.long_api_key_id,
+            short_exchange_id: entry.short_exchange_id,
+            short_symbol: entry.short_symbol,
+            short_quantity: dec!(1),
+            short_api_key_id: entry.short_api_key_id,
+            mode: ExecutionMode::Live,
+            trailing_stop: None,
+        };
+
+        let result = server.execute_exit(request).await;
 
-        let _: Result<(), _> = conn
-            .xadd(
-                "execution:results",
-                "*",
-                &[("data", data.as_str())],
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.long_filled, dec!(1));
+        assert_eq!(result.short_filled, dec!(1));
+        assert!(server.kill_switches.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_exit_of_an_already_flat_leg_succeeds_once_confirmed() {
+        let rejecting = |positions| {
+            Arc::new(
+                MockAdapter::new("short", dec!(102), dec!(103))
+                    .with_place_handler(|_, _| {
+                        Err(crate::exchange::reduce_only_rejected("short", "ReduceOnly Order is rejected."))
+                    })
+                    .with_positions(positions),
             )
+        };
+        let entry = entry_request();
+        let exit = TradeExitRequest {
+            trade_id: entry.trade_id,
+            position_id: Uuid::new_v4(),
+            is_emergency: false,
+            long_exchange_id: entry.long_exchange_id.clone(),
+            long_symbol: entry.long_symbol.clone(),
+            long_quantity: dec!(1),
+            long_api_key_id: entry.long_api_key_id,
+            short_exchange_id: entry.short_exchange_id.clone(),
+            short_symbol: entry.short_symbol.clone(),
+            short_quantity: dec!(1),
+            short_api_key_id: entry.short_api_key_id,
+            mode: ExecutionMode::Live,
+            trailing_stop: None,
+        };
+
+        // Already closed elsewhere: the rejection is a no-op
+        let short = rejecting(Vec::new());
+        let mut server = server();
+        server.adapters.insert("short".to_string(), short.clone());
+        seed_credentials(&server, entry.long_api_key_id).await;
+        seed_credentials(&server, entry.short_api_key_id).await;
+        let result = server.execute_exit(exit.clone()).await;
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.long_filled, dec!(1));
+        assert_eq!(result.short_filled, Decimal::ZERO);
+        assert_eq!(result.short_shortfall, Decimal::ZERO);
+        assert_eq!(short.placed().len(), 1);
+
+        // Still open: the rejection is a failure
+        let short = rejecting(vec![Position {
+            symbol: entry.short_symbol.clone(),
+            side: Side::Sell,
+            quantity: dec!(1),
+            entry_price: dec!(102),
+        }]);
+        server.adapters.insert("short".to_string(), short);
+        let result = server.execute_exit(exit).await;
+        assert!(!result.success);
+        assert_eq!(result.short_shortfall, dec!(1));
+    }
+
+    #[tokio::test]
+    async fn test_live_entry_is_tracked_until_its_exit() {
+        let server = server();
+        let entry = entry_request();
+        seed_credentials(&server, entry.long_api_key_id).await;
+        seed_credentials(&server, entry.short_api_key_id).await;
+        let exit = |quantity| TradeExitRequest {
+            trade_id: entry.trade_id,
+            position_id: Uuid::new_v4(),
+            is_emergency: false,
+            long_exchange_id: entry.long_exchange_id.clone(),
+            long_symbol: entry.long_symbol.clone(),
+            long_quantity: quantity,
+            long_api_key_id: entry.long_api_key_id,
+            short_exchange_id: entry.short_exchange_id.clone(),
+            short_symbol: entry.short_symbol.clone(),
+            short_quantity: quantity,
+            short_api_key_id: entry.short_api_key_id,
+            mode: ExecutionMode::Live,
+            trailing_stop: None,
+        };
+
+        let result = server.execute_entry(entry.clone()).await;
+        assert!(result.success, "{:?}", result.error);
+        let position = server.positions().get(entry.trade_id).await.unwrap();
+        let sides: Vec<_> = position.legs.iter().map(|l| (l.side, l.quantity, l.entry_price)).collect();
+        assert_eq!(sides, [(Side::Buy, dec!(1), result.long_avg_price), (Side::Sell, dec!(1), result.short_avg_price)]);
+
+        // Marked at each leg's mid
+        for (exchange_id, symbol) in server.positions().markets().await {
+            let adapter = server.adapter(&exchange_id).unwrap();
+            let book = top_of_book(adapter.as_ref(), &symbol).await;
+            let price = server.reference_price(adapter.as_ref(), &symbol, book).await.unwrap();
+            server.positions().mark(&exchange_id, &symbol, price).await;
+        }
+        let position = server.positions().get(entry.trade_id).await.unwrap();
+        let expected = (dec!(100.5) - result.long_avg_price) + (result.short_avg_price - dec!(102.5));
+        assert_eq!(position.unrealized_pnl_usd, Some(expected));
+
+        // A partial exit leaves the rest open; the remainder closes it
+        assert!(server.execute_exit(exit(dec!(0.4))).await.success);
+        let position = server.positions().get(entry.trade_id).await.unwrap();
+        assert!(position.legs.iter().all(|l| l.quantity == dec!(0.6)));
+        assert!(server.execute_exit(exit(dec!(0.6))).await.success);
+        assert!(server.positions().get(entry.trade_id).await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_trailing_stop_exit_is_emulated_until_cancelled() {
+        let server = server();
+        let entry = entry_request();
+        seed_credentials(&server, entry.long_api_key_id).await;
+        seed_credentials(&server, entry.short_api_key_id).await;
+        let request = TradeExitRequest {
+            trade_id: entry.trade_id,
+            position_id: Uuid::new_v4(),
+            is_emergency: false,
+            long_exchange_id: entry.long_exchange_id,
+            long_symbol: entry.long_symbol,
+            long_quantity: dec!(1),
+            long_api_key_id: entry.long_api_key_id,
+            short_exchange_id: entry.short_exchange_id,
+            short_symbol: entry.short_symbol,
+            short_quantity: dec!(1),
+            short_api_key_id: entry.short_api_key_id,
+            mode: ExecutionMode::Live,
+            trailing_stop: Some(Trail::Distance(dec!(5))),
+        };
+
+        let result = server.execute_exit(request).await;
+
+        assert!(result.success, "{:?}", result.error);
+        assert_eq!(result.long_filled, Decimal::ZERO);
+        assert_eq!(result.trailing_stops.len(), 2);
+        assert!(result.trailing_stops.iter().all(|stop| !stop.native && stop.order_id.is_none()));
+        assert_eq!(result.trailing_stops[0].side, Side::Sell);
+        assert_eq!(result.trailing_stops[1].side, Side::Buy);
+
+        // The emulation holds the trade's kill switch until cancelled
+        assert!(server.kill_switches.read().await.contains_key(&entry.trade_id));
+        server
+            .handle_control(ControlMessage {
+                trade_id: entry.trade_id,
+                action: ControlAction::Cancel,
+            })
+            .await;
+        tokio::time::timeout(std::time::Duration::from_secs(5), async {
+            while !server.kill_switches.read().await.is_empty() {
+                tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+            }
+        })
+        .await
+        .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_failed_leg_unwinds_the_filled_legs() {
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+            Box::new(MockAdapter::new("a", dec!(100), dec!(101))),
+            Box::new(MockAdapter::new("b", dec!(102), dec!(103))),
+            Box::new(
+                MockAdapter::new("c", dec!(104), dec!(105))
+                    .with_place_handler(|_, _| Err(anyhow::anyhow!("insufficient margin"))),
+            ),
+        ];
+        let server = ExecutionServer::new(adapters, Config::for_tests());
+        let leg = |exchange_id: &str, side| Leg {
+            exchange_id: exchange_id.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side,
+            size_in_coins: dec!(1),
+            api_key_id: Uuid::new_v4(),
+            slicing: SlicingParams {
+                slice_size_coins: Some(dec!(1)),
+                ..entry_request().slicing
+            },
+        };
+        let request = MultiLegEntryRequest {
+            trade_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            legs: vec![leg("a", Side::Buy), leg("b", Side::Sell), leg("c", Side::Sell)],
+        };
+        for leg in &request.legs {
+            seed_credentials(&server, leg.api_key_id).await;
+        }
+
+        let result = server.execute_multi_leg(request).await;
+
+        assert!(!result.success);
+        let error = result.error.unwrap();
+        assert!(error.contains("c BTCUSDT leg only partially filled"), "{}", error);
+        assert_eq!(result.legs.len(), 3);
+        for leg in &result.legs[..2] {
+            assert_eq!(leg.filled, dec!(1), "{}", leg.exchange_id);
+            assert_eq!(leg.unwound, dec!(1), "{}", leg.exchange_id);
+        }
+        assert_eq!(result.legs[2].filled, Decimal::ZERO);
+        assert_eq!(result.legs[2].unwound, Decimal::ZERO);
+        assert!(server.kill_switches.read().await.is_empty());
+    }
+
+    fn held(quantity: Decimal) -> Vec<Position> {
+        vec![Position {
+            symbol: "BTCUSDT".to_string(),
+            side: Side::Buy,
+            quantity,
+            entry_price: dec!(101),
+        }]
+    }
+
+    #[tokio::test]
+    async fn test_failed_entry_unwinds_the_leg_that_filled() {
+        let long = Arc::new(MockAdapter::new("long", dec!(100), dec!(101)));
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![Box::new(
+            MockAdapter::new("short", dec!(102), dec!(103))
+                .with_place_handler(|_, _| Err(anyhow::anyhow!("insufficient margin"))),
+        )];
+        let mut server = ExecutionServer::new(adapters, Config::for_tests());
+        server.adapters.insert("long".to_string(), long.clone());
+        let request = entry_request();
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+
+        let result = server.execute_entry(request).await;
+
+        assert!(!result.success);
+        assert_eq!(result.long_filled, dec!(1));
+        assert_eq!(result.long_unwound, dec!(1));
+        assert_eq!(result.short_filled, Decimal::ZERO);
+        let closes: Vec<Decimal> = long
+            .placed()
+            .iter()
+            .filter(|o| o.reduce_only)
+            .map(|o| o.quantity)
+            .collect();
+        assert_eq!(closes, [dec!(1)]);
+    }
+
+    #[tokio::test]
+    async fn test_unwind_leaves_a_position_held_before_the_entry() {
+        // The account already held 5 before the leg bought 1
+        let a = Arc::new(MockAdapter::new("a", dec!(100), dec!(101)).with_position_reads(vec![
+            held(dec!(5)),
+            held(dec!(6)),
+            held(dec!(5)),
+        ]));
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![Box::new(
+            MockAdapter::new("b", dec!(102), dec!(103))
+                .with_place_handler(|_, _| Err(anyhow::anyhow!("insufficient margin"))),
+        )];
+        let mut server = ExecutionServer::new(adapters, Config::for_tests());
+        server.adapters.insert("a".to_string(), a.clone());
+        let leg = |exchange_id: &str, side| Leg {
+            exchange_id: exchange_id.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side,
+            size_in_coins: dec!(1),
+            api_key_id: Uuid::new_v4(),
+            slicing: SlicingParams {
+                slice_size_coins: Some(dec!(1)),
+                ..entry_request().slicing
+            },
+        };
+        let request = MultiLegEntryRequest {
+            trade_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            legs: vec![leg("a", Side::Buy), leg("b", Side::Sell)],
+        };
+        for leg in &request.legs {
+            seed_credentials(&server, leg.api_key_id).await;
+        }
+
+        let result = server.execute_multi_leg(request).await;
+
+        assert!(!result.success);
+        let closes: Vec<Decimal> = a
+            .placed()
+            .iter()
+            .filter(|o| o.reduce_only)
+            .map(|o| o.quantity)
+            .collect();
+        assert_eq!(closes, [dec!(1)]);
+        assert_eq!(result.legs[0].unwound, dec!(1));
+    }
+
+    #[tokio::test]
+    async fn test_unwind_closes_late_fills_but_not_other_trades_growth() {
+        // A slice counted at 1 of 1.2 went on to fill in full, and another
+        // trade on the account added 1 meanwhile
+        let late = OrderResponse {
+            exchange_order_id: "a-0".to_string(),
+            client_order_id: "slice".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: Side::Buy,
+            order_type: crate::exchange::OrderType::Limit,
+            price: Some(dec!(101)),
+            quantity: dec!(1.2),
+            filled_quantity: dec!(1.2),
+            avg_fill_price: Some(dec!(101)),
+            status: OrderStatus::Filled,
+            timestamp: 0,
+        };
+        let adapter = Arc::new(
+            MockAdapter::new("a", dec!(100), dec!(101))
+                .with_open_orders(vec![late])
+                .with_position_reads(vec![held(dec!(7.2)), held(dec!(6))]),
+        );
+        let plan = LegPlan {
+            name: "a BTCUSDT".to_string(),
+            adapter: adapter.clone(),
+            credentials: credentials(),
+            symbol: "BTCUSDT".to_string(),
+            side: Side::Buy,
+            quantity: dec!(1.2),
+            reference_price: dec!(100.5),
+            slicing: SlicingConfig::default(),
+            api_key_id: Uuid::nil(),
+            spread_target: None,
+            position_before: Some(dec!(5)),
+        };
+        let slice = SliceResult {
+            index: 0,
+            client_order_id: "slice".to_string(),
+            exchange_order_id: Some("a-0".to_string()),
+            quantity: dec!(1.2),
+            price: dec!(101),
+            tolerance_bps: Some(5.0),
+            filled_quantity: dec!(1),
+            avg_fill_price: Some(dec!(101)),
+            status: OrderStatus::Cancelled,
+            arrival_ms: 0,
+            submitted_ms: 0,
+            filled_ms: None,
+        };
+        let server = server();
+
+        let closed = server.flatten_leg(&plan, dec!(1), &[slice]).await.unwrap();
+
+        // The late 0.2 is closed with the rest; the other trade's 1 is not
+        assert_eq!(closed, dec!(1.2));
+        let closes: Vec<Decimal> = adapter
+            .placed()
+            .iter()
+            .filter(|o| o.reduce_only)
+            .map(|o| o.quantity)
+            .collect();
+        assert_eq!(closes, [dec!(1.2)]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_leg_failing_on_a_rate_limit_is_retried_without_unwinding() {
+        let long = Arc::new(MockAdapter::new("long", dec!(100), dec!(101)));
+        let short = Arc::new(MockAdapter::new("short", dec!(102), dec!(103)).with_place_handler(|index, request| {
+            if index == 0 {
+                return Err(crate::exchange::rate_limited("short", "429 Too Many Requests"));
+            }
+            Ok(response_for(request, OrderStatus::Filled, request.quantity, Some(dec!(102))))
+        }));
+        let mut server = server();
+        server.adapters.insert("long".to_string(), long.clone());
+        server.adapters.insert("short".to_string(), short.clone());
+        let leg = |exchange_id: &str, side| Leg {
+            exchange_id: exchange_id.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side,
+            size_in_coins: dec!(1),
+            api_key_id: Uuid::new_v4(),
+            slicing: SlicingParams {
+                slice_size_coins: Some(dec!(1)),
+                ..entry_request().slicing
+            },
+        };
+        let request = MultiLegEntryRequest {
+            trade_id: Uuid::new_v4(),
+            user_id: Uuid::new_v4(),
+            legs: vec![leg("long", Side::Buy), leg("short", Side::Sell)],
+        };
+        for leg in &request.legs {
+            seed_credentials(&server, leg.api_key_id).await;
+        }
+
+        let result = server.execute_multi_leg(request).await;
+
+        assert!(result.success, "{:?}", result.error);
+        for leg in &result.legs {
+            assert_eq!(leg.filled, dec!(1), "{}", leg.exchange_id);
+            assert_eq!(leg.unwound, Decimal::ZERO, "{}", leg.exchange_id);
+        }
+        assert_eq!(short.placed().len(), 2);
+        // Nothing was sent to flatten the long leg
+        assert_eq!(long.placed().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_control_sets_kill_switch() {
+        let server = server();
+        let trade_id = Uuid::new_v4();
+        let kill_switch = Arc::new(AtomicBool::new(false));
+        server
+            .kill_switches
+            .write()
+            .await
+            .insert(trade_id, kill_switch.clone());
+
+        let message: ControlMessage = serde_json::from_str(&format!(
+            r#"{{"trade_id":"{}","action":"cancel"}}"#,
+            trade_id
+        ))
+        .unwrap();
+        server.handle_control(message).await;
+
+        assert!(kill_switch.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn test_entry_on_unavailable_exchange_fails_clearly() {
+        let mut unavailable = HashMap::new();
+        unavailable.insert("lbank".to_string(), "dns error".to_string());
+        let server = server().with_unavailable_adapters(unavailable);
+        let mut request = entry_request();
+        request.short_exchange_id = "lbank".to_string();
+
+        let result = server.execute_entry(request).await;
+
+        assert!(!result.success);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Exchange lbank is unavailable: dns error")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_entry_over_notional_cap_is_rejected() {
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+            Box::new(MockAdapter::new("long", dec!(100), dec!(101))),
+            Box::new(MockAdapter::new("short", dec!(102), dec!(103))),
+        ];
+        let config = Config {
+            max_notional_usd: 100.0,
+            ..Config::for_tests()
+        };
+        let server = ExecutionServer::new(adapters, config);
+        let request = entry_request();
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+
+        let result = server.execute_entry(request).await;
+
+        // 1 coin valued at the short mid of 102.5
+        assert!(!result.success);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Notional 102.50 USD exceeds the 100 USD limit per trade")
+        );
+        assert_eq!(result.long_filled, Decimal::ZERO);
+        assert!(server.kill_switches.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_entry_past_a_risk_limit_is_rejected_unless_raised() {
+        let server = |raise_risk_limits| {
+            let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+                Box::new(MockAdapter::new("long", dec!(100), dec!(101)).with_risk_limit(dec!(50), vec![dec!(50), dec!(500)])),
+                Box::new(MockAdapter::new("short", dec!(102), dec!(103))),
+            ];
+            let config = Config {
+                raise_risk_limits,
+                ..Config::for_tests()
+            };
+            ExecutionServer::new(adapters, config)
+        };
+
+        // 1 coin valued at the long mid of 100.5 is past the 50 USD tier
+        let capped = server(false);
+        let request = entry_request();
+        seed_credentials(&capped, request.long_api_key_id).await;
+        seed_credentials(&capped, request.short_api_key_id).await;
+        let result = capped.execute_entry(request).await;
+        assert!(!result.success);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("100.50 USD on long BTCUSDT exceeds the account's 50 USD risk limit")
+        );
+        assert_eq!(result.long_filled, Decimal::ZERO);
+
+        // Raised to the 500 USD tier instead
+        let raised = server(true);
+        let request = entry_request();
+        seed_credentials(&raised, request.long_api_key_id).await;
+        seed_credentials(&raised, request.short_api_key_id).await;
+        let result = raised.execute_entry(request).await;
+        assert!(result.success, "{:?}", result.error);
+        let long = raised.adapter("long").unwrap();
+        assert_eq!(long.get_risk_limit(&credentials(), "BTCUSDT").await.unwrap().max_notional_usd, dec!(500));
+    }
+
+    #[tokio::test]
+    async fn test_held_position_counts_towards_the_risk_limit() {
+        let held = Position {
+            symbol: "BTCUSDT".to_string(),
+            side: Side::Buy,
+            quantity: dec!(0.5),
+            entry_price: dec!(100),
+        };
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+            Box::new(
+                MockAdapter::new("long", dec!(100), dec!(101))
+                    .with_positions(vec![held])
+                    .with_risk_limit(dec!(120), vec![dec!(120)]),
+            ),
+            Box::new(MockAdapter::new("short", dec!(102), dec!(103))),
+        ];
+        let server = ExecutionServer::new(adapters, Config::for_tests());
+        let request = entry_request();
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+
+        // The 100.50 USD entry fits the tier alone, but not on top of the 0.5 held
+        let result = server.execute_entry(request).await;
+        assert!(!result.success);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("150.75 USD on long BTCUSDT exceeds the account's 120 USD risk limit")
+        );
+        assert_eq!(result.long_filled, Decimal::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_spread_targeted_entry_prices_legs_off_both_books() {
+        // Taking both touches captures 99 bps: short bid 102 over long ask 101
+        let entry = |pricing, timeout| {
+            let mut request = entry_request();
+            request.pricing = pricing;
+            request.slicing.total_timeout_secs = timeout;
+            request
+        };
+        let run = |request: TradeEntryRequest| async move {
+            let long = Arc::new(MockAdapter::new("long", dec!(100), dec!(101)));
+            let short = Arc::new(MockAdapter::new("short", dec!(102), dec!(103)));
+            let mut config = Config::for_tests();
+            config.slicing.price_tolerance_bps = 150.0;
+            let mut server = ExecutionServer::new(Vec::new(), config);
+            server.adapters.insert("long".to_string(), long.clone());
+            server.adapters.insert("short".to_string(), short.clone());
+            seed_credentials(&server, request.long_api_key_id).await;
+            seed_credentials(&server, request.short_api_key_id).await;
+            (server.execute_entry(request).await, long, short)
+        };
+
+        // Priced independently, both legs cross and the spread is given away
+        let (result, _, _) = run(entry(EntryPricing::Independent, None)).await;
+        assert!(result.success, "{:?}", result.error);
+        assert!(result.realized_spread_bps.unwrap() < Decimal::ZERO);
+
+        // Jointly, the legs split the 9 bps to spare and the pair keeps 90
+        let target = EntryPricing::SpreadTargeted { target_spread_bps: dec!(90) };
+        let (result, long, short) = run(entry(target, None)).await;
+        assert!(result.success, "{:?}", result.error);
+        let realized = result.realized_spread_bps.unwrap();
+        assert!(realized >= dec!(90) && realized < dec!(90.1), "{}", realized);
+        let long_price = long.placed()[0].price.unwrap();
+        let short_price = short.placed()[0].price.unwrap();
+        assert!(long_price > dec!(101) && long_price < dec!(101.1), "{}", long_price);
+        assert!(short_price < dec!(102) && short_price > dec!(101.9), "{}", short_price);
+
+        // A target the books can't reach holds every slice until the deadline
+        let target = EntryPricing::SpreadTargeted { target_spread_bps: dec!(150) };
+        let (result, long, short) = run(entry(target, Some(2))).await;
+        assert!(!result.success);
+        assert_eq!(result.long_filled, Decimal::ZERO);
+        assert_eq!(result.short_filled, Decimal::ZERO);
+        assert!(long.placed().is_empty() && short.placed().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_entry_with_collapsed_spread_is_rejected() {
+        let adapters: Vec<Box<dyn ExchangeAdapter>> = vec![
+            Box::new(MockAdapter::new("long", dec!(100), dec!(101))),
+            Box::new(MockAdapter::new("short", dec!(101.5), dec!(102))),
+        ];
+        let server = ExecutionServer::new(adapters, Config::for_tests());
+        let mut request = entry_request();
+        request.min_spread_bps = Some(dec!(100));
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+
+        let result = server.execute_entry(request).await;
+
+        // Buying at 101 and selling at 101.5 leaves 49.5 bps
+        assert!(!result.success);
+        assert_eq!(
+            result.error.as_deref(),
+            Some("Spread collapsed: observed 49.50 bps, required 100 bps")
+        );
+        assert_eq!(result.long_filled, Decimal::ZERO);
+        assert_eq!(result.short_filled, Decimal::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_both_legs_prices_are_read_at_once() {
+        let delay = std::time::Duration::from_millis(200);
+        let long = MockAdapter::new("long", dec!(100), dec!(102))
+            .with_reference_prices(dec!(101.5), dec!(100.8), dec!(100.6))
+            .with_price_delay(delay);
+        let short = MockAdapter::new("short", dec!(103), dec!(105))
+            .with_reference_prices(dec!(104.5), dec!(103.8), dec!(103.6))
+            .with_price_delay(delay);
+        let config = Config {
+            reference_price_source: ReferencePriceSource::Mark,
+            ..Config::for_tests()
+        };
+        let server = ExecutionServer::new(Vec::new(), config);
+
+        // Four reads of 200 ms each, answered in the time of one
+        let started = tokio::time::Instant::now();
+        let prices = server.entry_prices(&[(&long, "BTCUSDT"), (&short, "BTCUSDT")]).await;
+        let [long_prices, short_prices] = prices[..] else {
+            panic!("{:?}", prices);
+        };
+        assert_eq!(started.elapsed(), delay);
+        assert_eq!(
+            long_prices,
+            LegPrices {
+                book: Some((dec!(100), dec!(102))),
+                arrival: Some(dec!(100.8)),
+            }
+        );
+        assert_eq!(short_prices.arrival, Some(dec!(103.8)));
+    }
+
+    #[tokio::test]
+    async fn test_entry_without_a_legs_prices_is_rejected() {
+        let long = Arc::new(MockAdapter::new("long", dec!(100), dec!(101)));
+        let short = Arc::new(MockAdapter::new("short", dec!(102), dec!(103)).with_price_failures(|_| true));
+        let mut server = ExecutionServer::new(Vec::new(), Config::for_tests());
+        server.adapters.insert("long".to_string(), long.clone());
+        server.adapters.insert("short".to_string(), short.clone());
+        let request = entry_request();
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+
+        let result = server.execute_entry(request).await;
+
+        assert_eq!(
+            result.error.as_deref(),
+            Some("No reference price to check the notional cap against")
+        );
+        assert!(long.placed().is_empty() && short.placed().is_empty());
+    }
+
+    /// Time between the first order on each leg
+    async fn leg_start_gap(sequential_legs: bool) -> std::time::Duration {
+        let starts = Arc::new(std::sync::Mutex::new(HashMap::new()));
+        let recording = |id: &'static str| {
+            let starts = starts.clone();
+            MockAdapter::new(id, dec!(100), dec!(101)).with_place_handler(move |_, request| {
+                starts.lock().unwrap().entry(id).or_insert_with(std::time::Instant::now);
+                Ok(response_for(request, OrderStatus::Filled, request.quantity, request.price))
+            })
+        };
+        let adapters: Vec<Box<dyn ExchangeAdapter>> =
+            vec![Box::new(recording("long")), Box::new(recording("short"))];
+        // Two slices per leg, 200ms apart
+        let mut config = Config {
+            sequential_legs,
+            ..Config::for_tests()
+        };
+        config.slicing.interval_ms = 200;
+        let server = ExecutionServer::new(adapters, config);
+        let request = entry_request();
+        seed_credentials(&server, request.long_api_key_id).await;
+        seed_credentials(&server, request.short_api_key_id).await;
+
+        let result = server.execute_entry(request).await;
+        assert!(result.success, "{:?}", result.error);
+
+        let starts = starts.lock().unwrap();
+        starts["short"].duration_since(starts["long"])
+    }
+
+    #[tokio::test]
+    async fn test_legs_start_together_unless_sequential() {
+        assert!(leg_start_gap(false).await < std::time::Duration::from_millis(50));
+        assert!(leg_start_gap(true).await >= std::time::Duration::from_millis(200));
+    }
+
+    /// Source that counts loads
+    struct CountingSource(std::sync::atomic::AtomicUsize);
+
+    #[async_trait::async_trait]
+    impl CredentialSource for CountingSource {
+        async fn load(&self, _api_key_id: Uuid) -> Result<Credentials> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(credentials())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_credentials_are_loaded_once_and_cached() {
+        let source = Arc::new(CountingSource(Default::default()));
+        let mut server = server();
+        server.credential_source = Some(source.clone());
+        let api_key_id = Uuid::new_v4();
+
+        server.get_key_pool(api_key_id).await.unwrap();
+        server.get_key_pool(api_key_id).await.unwrap();
+
+        assert_eq!(source.0.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_warmup_fills_the_caches_the_first_trade_reads() {
+        let mock = Arc::new(
+            MockAdapter::new("mock", dec!(100), dec!(101))
+                .with_symbol_status(SymbolStatus::Trading)
+                .with_clock_offset(0),
+        );
+        let source = Arc::new(CountingSource(Default::default()));
+        let api_key_id = Uuid::new_v4();
+        let mut server = server();
+        server.adapters.insert("mock".to_string(), mock.clone());
+        server.credential_source = Some(source.clone());
+        server.config.warmup_api_key_id = Some(api_key_id);
+
+        // Unknown exchanges and failed leverage reads are skipped
+        server
+            .warmup(&[
+                ("mock".to_string(), "BTCUSDT".to_string()),
+                ("mock".to_string(), "ETHUSDT".to_string()),
+                ("missing".to_string(), "BTCUSDT".to_string()),
+            ])
+            .await;
+
+        assert_eq!(mock.symbol_info_calls(), 2);
+        assert!(server.clock.samples().await.contains_key("mock"));
+        server.symbol_info(mock.as_ref(), "BTCUSDT").await.unwrap();
+        server.get_key_pool(api_key_id).await.unwrap();
+        assert_eq!(mock.symbol_info_calls(), 2);
+        assert_eq!(source.0.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_cancels_only_our_orphaned_orders() {
+        let resting = |id: &str, client_order_id: &str, symbol: &str, status| OrderResponse {
+            exchange_order_id: id.to_string(),
+            client_order_id: client_order_id.to_string(),
+            symbol: symbol.to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some(dec!(99)),
+            quantity: dec!(1),
+            filled_quantity: Decimal::ZERO,
+            avg_fill_price: None,
+            status,
+            timestamp: 0,
+        };
+        let mock = Arc::new(MockAdapter::new("mock", dec!(100), dec!(101)).with_open_orders(vec![
+            resting("1", "cs_left", "BTCUSDT", OrderStatus::Open),
+            resting("2", "manual-order", "BTCUSDT", OrderStatus::Open),
+            // Filled between the listing and the cancel
+            resting("3", "cs_filled", "BTCUSDT", OrderStatus::Filled),
+            resting("4", "cs_partial", "ETHUSDT", OrderStatus::Partial),
+        ]));
+        let api_key_id = Uuid::new_v4();
+        let mut server = server();
+        server.adapters.insert("mock".to_string(), mock.clone());
+        server.config.warmup_api_key_id = Some(api_key_id);
+        seed_credentials(&server, api_key_id).await;
+
+        let cancelled = server
+            .cleanup_orphans(&[
+                ("mock".to_string(), "BTCUSDT".to_string()),
+                ("mock".to_string(), "ETHUSDT".to_string()),
+            ])
             .await;
+
+        assert_eq!(cancelled, 2);
+        // Orders without our prefix are left alone
+        assert_eq!(mock.cancelled(), ["1", "3", "4"]);
+    }
+
+    #[test]
+    fn test_slippage_sign_conventions() {
+        // Paying above arrival on the long leg is a cost
+        assert_eq!(slippage_bps(Side::Buy, dec!(100), dec!(100.1)), Some(dec!(10)));
+        // Selling below arrival on the short leg is also a cost
+        assert_eq!(slippage_bps(Side::Sell, dec!(100), dec!(99.9)), Some(dec!(10)));
+        // Selling above arrival is price improvement
+        assert_eq!(slippage_bps(Side::Sell, dec!(100), dec!(100.1)), Some(dec!(-10)));
+        assert_eq!(spread_bps(dec!(100), dec!(100.5)), Some(dec!(50)));
+    }
+
+    #[tokio::test]
+    async fn test_hedge_mode_account_is_switched_to_one_way() {
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101)).with_hedge_mode();
+
+        ensure_one_way_mode(&adapter, &credentials()).await;
+
+        assert!(!adapter.get_position_mode(&credentials()).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_account_without_a_readable_mode_is_switched_anyway() {
+        // A flat Bybit account lists no positions to tell its mode by
+        let (url, server) = serve_http(vec![
+            ("200 OK", r#"{"retCode":0,"retMsg":"OK","result":{"list":[]}}"#),
+            ("200 OK", r#"{"retCode":110025,"retMsg":"Position mode is not modified","result":{}}"#),
+        ])
+        .await;
+        let adapter = crate::exchange::bybit::BybitAdapter::new(exchange_config("bybit", url)).await.unwrap();
+
+        ensure_one_way_mode(&adapter, &credentials()).await;
+
+        assert_eq!(
+            server.await.unwrap(),
+            [
+                "GET /v5/position/list?category=linear&settleCoin=USDT HTTP/1.1",
+                "POST /v5/position/switch-mode HTTP/1.1",
+            ]
+        );
     }
 }
+
@@ -0,0 +1,155 @@
+//! Live orders of this run
+//!
+//! Every slice the service places is kept here by client order id until it
+//! fills, is cancelled or is otherwise done, so the process can always say
+//! which of its orders are resting: the HTTP interface lists them and a
+//! graceful shutdown cancels them. Nothing here is persisted; positions in
+//! Redis outlive the process, these orders are only this run's view.
+
+use rust_decimal::Decimal;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use crate::exchange::{now_millis, Credentials, OrderResponse, OrderStatus, Side};
+
+/// A resting order and the account it rests on
+#[derive(Debug, Clone, Serialize)]
+pub struct LiveOrder {
+    pub exchange_id: String,
+    pub client_order_id: String,
+    pub exchange_order_id: String,
+    pub symbol: String,
+    pub side: Side,
+    pub price: Option<Decimal>,
+    pub quantity: Decimal,
+    pub filled_quantity: Decimal,
+    pub status: OrderStatus,
+    /// When it was placed, in Unix milliseconds
+    pub placed_ms: i64,
+    /// Needed to cancel the order, never served
+    #[serde(skip)]
+    pub credentials: Credentials,
+}
+
+#[derive(Default)]
+pub struct OrderStore {
+    orders: RwLock<HashMap<String, LiveOrder>>,
+}
+
+impl OrderStore {
+    /// Track an order just placed on `exchange_id`. Orders that are already
+    /// done, like a filled taker slice, are never tracked.
+    pub fn insert(&self, exchange_id: &str, credentials: &Credentials, order: &OrderResponse) {
+        if order.status.is_terminal() {
+            return;
+        }
+        let live = LiveOrder {
+            exchange_id: exchange_id.to_string(),
+            client_order_id: order.client_order_id.clone(),
+            exchange_order_id: order.exchange_order_id.clone(),
+            symbol: order.symbol.clone(),
+            side: order.side,
+            price: order.price,
+            quantity: order.quantity,
+            filled_quantity: order.filled_quantity,
+            status: order.status,
+            placed_ms: now_millis(),
+            credentials: credentials.clone(),
+        };
+        self.orders
+            .write()
+            .unwrap()
+            .insert(order.client_order_id.clone(), live);
+    }
+
+    /// Apply the latest state of a tracked order, dropping it once done.
+    /// Orders that aren't tracked are ignored.
+    pub fn update(&self, order: &OrderResponse) {
+        let mut orders = self.orders.write().unwrap();
+        if order.status.is_terminal() {
+            orders.remove(&order.client_order_id);
+            return;
+        }
+        if let Some(live) = orders.get_mut(&order.client_order_id) {
+            live.filled_quantity = order.filled_quantity;
+            live.status = order.status;
+        }
+    }
+
+    /// Stop tracking an order
+    pub fn remove(&self, client_order_id: &str) -> Option<LiveOrder> {
+        self.orders.write().unwrap().remove(client_order_id)
+    }
+
+    /// Every tracked order, oldest first
+    pub fn live(&self) -> Vec<LiveOrder> {
+        let mut orders: Vec<LiveOrder> = self.orders.read().unwrap().values().cloned().collect();
+        orders.sort_by(|a, b| (a.placed_ms, &a.client_order_id).cmp(&(b.placed_ms, &b.client_order_id)));
+        orders
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{credentials, response_for};
+    use crate::exchange::{OrderRequest, OrderType, QuantityMode, TimeInForce};
+    use rust_decimal_macros::dec;
+
+    fn get(store: &OrderStore, client_order_id: &str) -> Option<LiveOrder> {
+        store.live().into_iter().find(|o| o.client_order_id == client_order_id)
+    }
+
+    fn order(client_order_id: &str, status: OrderStatus, filled: Decimal) -> OrderResponse {
+        let request = OrderRequest {
+            client_order_id: client_order_id.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: Side::Buy,
+            order_type: OrderType::Limit,
+            price: Some(dec!(100)),
+            quantity: dec!(1),
+            reduce_only: false,
+            time_in_force: TimeInForce::Gtc,
+            quantity_mode: QuantityMode::Base,
+        };
+        response_for(&request, status, filled, None)
+    }
+
+    #[test]
+    fn test_orders_are_tracked_until_done() {
+        let store = OrderStore::default();
+
+        store.insert("binance", &credentials(), &order("a", OrderStatus::Open, dec!(0)));
+        store.insert("okx", &credentials(), &order("b", OrderStatus::Open, dec!(0)));
+        // Done on placement, so never live
+        store.insert("okx", &credentials(), &order("c", OrderStatus::Filled, dec!(1)));
+        assert_eq!(store.live().len(), 2);
+        assert_eq!(get(&store, "a").unwrap().exchange_id, "binance");
+        assert!(get(&store, "c").is_none());
+
+        store.update(&order("a", OrderStatus::Partial, dec!(0.4)));
+        let live = get(&store, "a").unwrap();
+        assert_eq!(live.status, OrderStatus::Partial);
+        assert_eq!(live.filled_quantity, dec!(0.4));
+
+        // Updates for orders not tracked are ignored
+        store.update(&order("d", OrderStatus::Open, dec!(0)));
+        assert!(get(&store, "d").is_none());
+
+        // Terminal states drop the order
+        store.update(&order("a", OrderStatus::Filled, dec!(1)));
+        store.update(&order("b", OrderStatus::Cancelled, dec!(0)));
+        assert!(store.live().is_empty());
+
+        store.insert("okx", &credentials(), &order("e", OrderStatus::Open, dec!(0)));
+        assert_eq!(store.remove("e").unwrap().client_order_id, "e");
+        assert_eq!(store.live().len(), 0);
+
+        // Credentials stay out of what is served
+        store.insert("okx", &credentials(), &order("f", OrderStatus::Open, dec!(0)));
+        let served = serde_json::to_value(store.live()).unwrap();
+        assert_eq!(served[0]["client_order_id"], "f");
+        assert!(served[0].get("credentials").is_none());
+    }
+}
@@ -0,0 +1,392 @@
+//! Open position tracking
+//!
+//! Every live entry leaves a position open until an exit closes it. The
+//! tracker keeps each one by trade, with the entry price and size of its
+//! legs, and marks the legs to their reference price so unrealized PnL can
+//! be read at any time. Positions are written to Redis as they open and
+//! close; after a restart they are read back and checked against the
+//! positions the exchanges report, dropping legs that were closed while the
+//! service was down.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use redis::aio::ConnectionManager;
+use redis::AsyncCommands;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashMap};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::exchange::{ContractSpec, ContractType, Side};
+
+/// Hash holding every open position as JSON, by trade id
+const POSITIONS_KEY: &str = "execution:positions";
+
+/// One leg of an open position
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackedLeg {
+    pub exchange_id: String,
+    pub symbol: String,
+    /// Side the leg was entered with
+    pub side: Side,
+    /// Key the leg trades with, used to read the exchange's positions back
+    pub api_key_id: Uuid,
+    /// Contracts still open
+    pub quantity: Decimal,
+    pub entry_price: Decimal,
+    pub contract: ContractSpec,
+    /// Latest reference price, until the leg is first marked `None`
+    #[serde(default)]
+    pub mark_price: Option<Decimal>,
+    /// At the mark price, in the leg's settlement asset
+    #[serde(default)]
+    pub unrealized_pnl: Option<Decimal>,
+}
+
+impl TrackedLeg {
+    /// Mark the leg to `price` and recompute its PnL
+    pub fn mark(&mut self, price: Decimal) {
+        self.mark_price = Some(price);
+        self.unrealized_pnl = Some(self.contract.pnl(self.side, self.quantity, self.entry_price, price));
+    }
+
+    /// Unrealized PnL in USD, converting inverse legs' coin PnL at the mark
+    pub fn unrealized_pnl_usd(&self) -> Option<Decimal> {
+        let pnl = self.unrealized_pnl?;
+        match self.contract.contract_type {
+            ContractType::Linear => Some(pnl),
+            ContractType::Inverse => self.mark_price.map(|mark| pnl * mark),
+        }
+    }
+}
+
+/// An open position: the legs of one entry that are still held
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TrackedPosition {
+    pub trade_id: Uuid,
+    pub legs: Vec<TrackedLeg>,
+    /// Unix milliseconds
+    pub opened_at: i64,
+    /// Across all legs in USD, once every leg has been marked
+    #[serde(default)]
+    pub unrealized_pnl_usd: Option<Decimal>,
+}
+
+impl TrackedPosition {
+    pub fn new(trade_id: Uuid, legs: Vec<TrackedLeg>) -> Self {
+        Self {
+            trade_id,
+            legs,
+            opened_at: Utc::now().timestamp_millis(),
+            unrealized_pnl_usd: None,
+        }
+    }
+
+    fn update_total(&mut self) {
+        self.unrealized_pnl_usd = self
+            .legs
+            .iter()
+            .map(TrackedLeg::unrealized_pnl_usd)
+            .sum::<Option<Decimal>>();
+    }
+}
+
+#[derive(Default)]
+pub struct PositionTracker {
+    redis: Option<ConnectionManager>,
+    positions: RwLock<HashMap<Uuid, TrackedPosition>>,
+}
+
+impl PositionTracker {
+    /// Persist positions to Redis so they survive restarts
+    pub fn with_redis(mut self, conn: ConnectionManager) -> Self {
+        self.redis = Some(conn);
+        self
+    }
+
+    /// Start tracking a position. Legs that did not fill are left out, and a
+    /// position with no filled legs is not tracked at all.
+    pub async fn open(&self, mut position: TrackedPosition) {
+        position.legs.retain(|leg| leg.quantity > Decimal::ZERO);
+        if position.legs.is_empty() {
+            return;
+        }
+        position.update_total();
+        self.store(&position).await;
+        self.positions.write().await.insert(position.trade_id, position);
+    }
+
+    /// Take `quantity` contracts of the `side` leg on `exchange_id`'s
+    /// `symbol` out of a position, after an exit filled them. The position
+    /// is dropped once none of its legs are left open.
+    pub async fn reduce(&self, trade_id: Uuid, exchange_id: &str, symbol: &str, side: Side, quantity: Decimal) {
+        let mut positions = self.positions.write().await;
+        let Some(position) = positions.get_mut(&trade_id) else {
+            return;
+        };
+        for leg in &mut position.legs {
+            if leg.exchange_id == exchange_id && leg.symbol == symbol && leg.side == side {
+                leg.quantity = (leg.quantity - quantity).max(Decimal::ZERO);
+                if let Some(price) = leg.mark_price {
+                    leg.mark(price);
+                }
+            }
+        }
+        position.legs.retain(|leg| leg.quantity > Decimal::ZERO);
+        position.update_total();
+
+        if position.legs.is_empty() {
+            positions.remove(&trade_id);
+            drop(positions);
+            self.delete(trade_id).await;
+        } else {
+            let position = position.clone();
+            drop(positions);
+            self.store(&position).await;
+        }
+    }
+
+    /// Mark every leg on `exchange_id`'s `symbol` to `price`
+    pub async fn mark(&self, exchange_id: &str, symbol: &str, price: Decimal) {
+        for position in self.positions.write().await.values_mut() {
+            let mut marked = false;
+            for leg in &mut position.legs {
+                if leg.exchange_id == exchange_id && leg.symbol == symbol {
+                    leg.mark(price);
+                    marked = true;
+                }
+            }
+            if marked {
+                position.update_total();
+            }
+        }
+    }
+
+    /// Exchanges and symbols with an open leg
+    pub async fn markets(&self) -> BTreeSet<(String, String)> {
+        self.legs(|leg| (leg.exchange_id.clone(), leg.symbol.clone())).await
+    }
+
+    /// Exchanges and API keys with an open leg
+    pub async fn accounts(&self) -> BTreeSet<(String, Uuid)> {
+        self.legs(|leg| (leg.exchange_id.clone(), leg.api_key_id)).await
+    }
+
+    pub async fn get(&self, trade_id: Uuid) -> Option<TrackedPosition> {
+        self.positions.read().await.get(&trade_id).cloned()
+    }
+
+    /// Every open position, oldest first
+    pub async fn all(&self) -> Vec<TrackedPosition> {
+        let mut positions: Vec<_> = self.positions.read().await.values().cloned().collect();
+        positions.sort_by_key(|p| (p.opened_at, p.trade_id));
+        positions
+    }
+
+    /// Read the positions stored in Redis back in. Returns how many there were.
+    pub async fn load(&self) -> Result<usize> {
+        let Some(mut conn) = self.redis.clone() else {
+            return Ok(0);
+        };
+        let stored: HashMap<String, String> = conn
+            .hgetall(POSITIONS_KEY)
+            .await
+            .context("Failed to read positions")?;
+
+        let mut positions = self.positions.write().await;
+        for (trade_id, json) in stored {
+            match serde_json::from_str::<TrackedPosition>(&json) {
+                Ok(position) => {
+                    positions.insert(position.trade_id, position);
+                }
+                Err(e) => warn!("Skipping unreadable position {}: {}", trade_id, e),
+            }
+        }
+        Ok(positions.len())
+    }
+
+    /// Drop the legs on `exchange_id` traded with `api_key_id` that the
+    /// exchange no longer holds, going by `held(symbol, side)`. Returns how
+    /// many were dropped.
+    pub async fn reconcile<F>(&self, exchange_id: &str, api_key_id: Uuid, held: F) -> usize
+    where
+        F: Fn(&str, Side) -> bool,
+    {
+        let mut dropped = 0;
+        let mut changed = Vec::new();
+        {
+            let mut positions = self.positions.write().await;
+            for position in positions.values_mut() {
+                let before = position.legs.len();
+                position.legs.retain(|leg| {
+                    let ours = leg.exchange_id == exchange_id && leg.api_key_id == api_key_id;
+                    if ours && !held(&leg.symbol, leg.side) {
+                        info!(
+                            "Trade {} no longer holds its {:?} {} on {}, dropping the leg",
+                            position.trade_id, leg.side, leg.symbol, exchange_id
+                        );
+                        return false;
+                    }
+                    true
+                });
+                if position.legs.len() != before {
+                    dropped += before - position.legs.len();
+                    position.update_total();
+                    changed.push(position.clone());
+                }
+            }
+            positions.retain(|_, position| !position.legs.is_empty());
+        }
+
+        for position in changed {
+            if position.legs.is_empty() {
+                self.delete(position.trade_id).await;
+            } else {
+                self.store(&position).await;
+            }
+        }
+        dropped
+    }
+
+    async fn legs<T: Ord>(&self, key: impl Fn(&TrackedLeg) -> T) -> BTreeSet<T> {
+        self.positions
+            .read()
+            .await
+            .values()
+            .flat_map(|p| p.legs.iter().map(&key))
+            .collect()
+    }
+
+    async fn store(&self, position: &TrackedPosition) {
+        let Some(mut conn) = self.redis.clone() else {
+            return;
+        };
+        let json = match serde_json::to_string(position) {
+            Ok(json) => json,
+            Err(e) => {
+                warn!("Failed to serialize position {}: {}", position.trade_id, e);
+                return;
+            }
+        };
+        let written: redis::RedisResult<()> = conn
+            .hset(POSITIONS_KEY, position.trade_id.to_string(), json)
+            .await;
+        if let Err(e) = written {
+            warn!("Failed to store position {}: {}", position.trade_id, e);
+        }
+    }
+
+    async fn delete(&self, trade_id: Uuid) {
+        let Some(mut conn) = self.redis.clone() else {
+            return;
+        };
+        let deleted: redis::RedisResult<()> = conn.hdel(POSITIONS_KEY, trade_id.to_string()).await;
+        if let Err(e) = deleted {
+            warn!("Failed to delete position {}: {}", trade_id, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    fn leg(exchange_id: &str, side: Side, quantity: Decimal, entry_price: Decimal) -> TrackedLeg {
+        TrackedLeg {
+            exchange_id: exchange_id.to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side,
+            api_key_id: Uuid::nil(),
+            quantity,
+            entry_price,
+            contract: ContractSpec::linear(Decimal::ONE),
+            mark_price: None,
+            unrealized_pnl: None,
+        }
+    }
+
+    #[test]
+    fn test_long_and_short_legs_have_opposite_pnl() {
+        let mut long = leg("long", Side::Buy, dec!(2), dec!(100));
+        let mut short = leg("short", Side::Sell, dec!(2), dec!(102));
+
+        // Price rises: the long gains and the short loses
+        long.mark(dec!(110));
+        short.mark(dec!(110));
+        assert_eq!(long.unrealized_pnl, Some(dec!(20)));
+        assert_eq!(short.unrealized_pnl, Some(dec!(-16)));
+
+        // Price falls below both entries: the short gains
+        long.mark(dec!(90));
+        short.mark(dec!(90));
+        assert_eq!(long.unrealized_pnl, Some(dec!(-20)));
+        assert_eq!(short.unrealized_pnl, Some(dec!(24)));
+
+        let mut position = TrackedPosition::new(Uuid::new_v4(), vec![long, short]);
+        position.update_total();
+        assert_eq!(position.unrealized_pnl_usd, Some(dec!(4)));
+    }
+
+    #[test]
+    fn test_inverse_leg_pnl_is_converted_to_usd_at_the_mark() {
+        // 1000 contracts of $100 short from 50000, marked at 40000
+        let mut short = TrackedLeg {
+            contract: ContractSpec::inverse(dec!(100)),
+            ..leg("short", Side::Sell, dec!(1000), dec!(50000))
+        };
+        short.mark(dec!(40000));
+
+        // 100000 * (1/50000 - 1/40000) = -0.5 BTC for a long, so +0.5 short
+        assert_eq!(short.unrealized_pnl, Some(dec!(0.5)));
+        assert_eq!(short.unrealized_pnl_usd(), Some(dec!(20000)));
+
+        // Unmarked legs leave the total unknown
+        let mut position = TrackedPosition::new(
+            Uuid::new_v4(),
+            vec![short, leg("long", Side::Buy, dec!(1), dec!(50000))],
+        );
+        position.update_total();
+        assert_eq!(position.unrealized_pnl_usd, None);
+    }
+
+    #[tokio::test]
+    async fn test_positions_close_as_exits_and_reconciliation_remove_legs() {
+        let tracker = PositionTracker::default();
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        tracker
+            .open(TrackedPosition::new(
+                first,
+                vec![leg("long", Side::Buy, dec!(2), dec!(100)), leg("short", Side::Sell, dec!(2), dec!(102))],
+            ))
+            .await;
+        tracker
+            .open(TrackedPosition::new(
+                second,
+                vec![leg("long", Side::Buy, dec!(1), dec!(100)), leg("short", Side::Sell, dec!(0), dec!(0))],
+            ))
+            .await;
+        assert_eq!(tracker.get(second).await.unwrap().legs.len(), 1);
+
+        tracker.mark("long", "BTCUSDT", dec!(101)).await;
+        tracker.mark("short", "BTCUSDT", dec!(101)).await;
+        assert_eq!(tracker.get(first).await.unwrap().unrealized_pnl_usd, Some(dec!(4)));
+
+        // A partial exit shrinks the leg and its PnL; a full one closes it
+        tracker.reduce(first, "long", "BTCUSDT", Side::Buy, dec!(1)).await;
+        let position = tracker.get(first).await.unwrap();
+        assert_eq!(position.legs[0].quantity, dec!(1));
+        assert_eq!(position.unrealized_pnl_usd, Some(dec!(3)));
+        tracker.reduce(first, "long", "BTCUSDT", Side::Buy, dec!(1)).await;
+        tracker.reduce(first, "short", "BTCUSDT", Side::Sell, dec!(2)).await;
+        assert!(tracker.get(first).await.is_none());
+
+        // Legs the exchange no longer holds are dropped
+        assert_eq!(tracker.reconcile("long", Uuid::nil(), |_, _| true).await, 0);
+        assert_eq!(tracker.reconcile("long", Uuid::nil(), |_, side| side == Side::Sell).await, 1);
+        assert!(tracker.all().await.is_empty());
+    }
+}
@@ -0,0 +1,97 @@
+//! Rounding to tick and step sizes
+//!
+//! Exchanges reject prices off the symbol's tick and quantities off its step.
+//! Which way a price is rounded decides whether a limit order stays behind
+//! the touch or moves towards it, so that is configurable; quantities always
+//! round down so an order never asks for more than was intended.
+
+use anyhow::Result;
+use rust_decimal::{Decimal, RoundingStrategy};
+
+use crate::exchange::Side;
+
+/// Direction limit prices are rounded to the tick
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PriceRounding {
+    /// Away from the book: buys down, sells up
+    #[default]
+    Passive,
+    /// To the nearest tick, halves away from zero
+    Nearest,
+    /// Towards the book: buys up, sells down
+    Aggressive,
+}
+
+impl std::str::FromStr for PriceRounding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "passive" => Ok(PriceRounding::Passive),
+            "nearest" => Ok(PriceRounding::Nearest),
+            "aggressive" => Ok(PriceRounding::Aggressive),
+            other => anyhow::bail!("Unknown price rounding: {}", other),
+        }
+    }
+}
+
+impl PriceRounding {
+    /// `price` on a multiple of `tick` for an order on `side`. A zero tick
+    /// means the tick is unknown and the price is left as it is.
+    pub fn round(self, price: Decimal, tick: Decimal, side: Side) -> Decimal {
+        if tick <= Decimal::ZERO {
+            return price;
+        }
+        let strategy = match (self, side) {
+            (PriceRounding::Nearest, _) => RoundingStrategy::MidpointAwayFromZero,
+            (PriceRounding::Passive, Side::Buy) | (PriceRounding::Aggressive, Side::Sell) => {
+                RoundingStrategy::ToNegativeInfinity
+            }
+            (PriceRounding::Passive, Side::Sell) | (PriceRounding::Aggressive, Side::Buy) => {
+                RoundingStrategy::ToPositiveInfinity
+            }
+        };
+        ((price / tick).round_dp_with_strategy(0, strategy) * tick).normalize()
+    }
+}
+
+/// `quantity` rounded down to a multiple of `step`, or as it is when the
+/// step is unknown
+pub fn round_quantity(quantity: Decimal, step: Decimal) -> Decimal {
+    if step <= Decimal::ZERO {
+        return quantity;
+    }
+    ((quantity / step).floor() * step).normalize()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_price_rounding_by_side() {
+        let tick = dec!(0.5);
+        let price = dec!(100.3);
+
+        assert_eq!(PriceRounding::Passive.round(price, tick, Side::Buy), dec!(100));
+        assert_eq!(PriceRounding::Passive.round(price, tick, Side::Sell), dec!(100.5));
+        assert_eq!(PriceRounding::Aggressive.round(price, tick, Side::Buy), dec!(100.5));
+        assert_eq!(PriceRounding::Aggressive.round(price, tick, Side::Sell), dec!(100));
+        assert_eq!(PriceRounding::Nearest.round(price, tick, Side::Buy), dec!(100.5));
+        assert_eq!(PriceRounding::Nearest.round(dec!(100.2), tick, Side::Sell), dec!(100));
+
+        // Prices already on the tick, and unknown ticks, are left alone
+        assert_eq!(PriceRounding::Passive.round(dec!(0.0123), dec!(0.0001), Side::Sell), dec!(0.0123));
+        assert_eq!(PriceRounding::Passive.round(price, Decimal::ZERO, Side::Buy), price);
+    }
+
+    #[test]
+    fn test_quantity_rounds_down_to_step() {
+        assert_eq!(round_quantity(dec!(1.23456), dec!(0.001)), dec!(1.234));
+        assert_eq!(round_quantity(dec!(17.9), dec!(1)), dec!(17));
+        assert_eq!(round_quantity(dec!(0.0009), dec!(0.001)), Decimal::ZERO);
+        assert_eq!(round_quantity(dec!(5), dec!(5)), dec!(5));
+        assert_eq!(round_quantity(dec!(1.23456), Decimal::ZERO), dec!(1.23456));
+    }
+}
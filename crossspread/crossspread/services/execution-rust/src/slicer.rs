@@ -2,17 +2,135 @@
 //! 
 //! Splits large orders into smaller slices to reduce market impact and slippage.
 
-use anyhow::Result;
-use rust_decimal::Decimal;
+use anyhow::{Context, Result};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use rust_decimal::prelude::ToPrimitive;
+use rust_decimal::{Decimal, MathematicalOps, RoundingStrategy};
 use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
-use tokio::time::sleep;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio::time::{sleep, Instant};
 use tracing::{debug, info, warn};
+use uuid::Uuid;
 
+use crate::algorithm::{self, AlgorithmFactory, ExecState, ExecutionAlgorithm};
 use crate::exchange::{
-    Credentials, ExchangeAdapter, OrderRequest, OrderResponse, OrderStatus, OrderType, Side,
-    generate_client_order_id,
+    AlgoKind, AlgoOrderRequest, ContractSpec, ContractType, Credentials, ExchangeAdapter, ExchangeError, Fill,
+    OrderRequest, OrderResponse, OrderStatus, OrderType, QuantityMode, ReferencePriceSource, Side, TimeInForce,
+    DEFAULT_CLIENT_ORDER_ID_PREFIX, generate_client_order_id, now_millis, parse_canonical_symbol,
 };
+use crate::open_orders::OpenOrderLimits;
+use crate::order_store::OrderStore;
+use crate::rounding::{round_quantity, PriceRounding};
+
+/// Attempts made to flatten a position before giving up
+const EMERGENCY_MAX_ATTEMPTS: usize = 3;
+/// How long an emergency order may rest before it is cancelled and re-priced
+const EMERGENCY_FILL_TIMEOUT_SECS: u64 = 2;
+/// Longest an emergency exit waits to see whether the book improves
+const MAX_EMERGENCY_IMPROVEMENT_WAIT: Duration = Duration::from_millis(300);
+
+/// Largest multiple of the poll interval used after repeated poll failures
+const MAX_POLL_BACKOFF: u32 = 8;
+/// Price fetches for a slice before falling back to `on_price_failure`
+const QUOTE_ATTEMPTS: usize = 3;
+const QUOTE_RETRY_DELAY: Duration = Duration::from_millis(50);
+/// Smallest slice worth sending on its own
+const MIN_SLICE_SIZE: Decimal = dec!(0.001);
+/// Decimal places kept when a quote amount is converted to contracts,
+/// rounding down so the amount is never exceeded
+const CONVERTED_QUANTITY_DP: u32 = 6;
+/// Margin kept over the venue's minimum notional, since slices are sized at
+/// the reference price but may be priced on the far side of it
+const MIN_NOTIONAL_HEADROOM: Decimal = dec!(1.01);
+
+/// How slice sizes are chosen
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SlicingStrategy {
+    /// Every slice is `slice_percent` of the total
+    #[default]
+    Fixed,
+    /// Start at `slice_percent` and grow slices while they fill cleanly,
+    /// shrinking them again when fills are slow or walk the book
+    Adaptive,
+}
+
+/// What a slice does when its price can't be fetched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PriceFailure {
+    /// Abort the whole order
+    #[default]
+    Abort,
+    /// Price the slice off the last quote while it is no older than
+    /// `max_quote_age_ms`, otherwise skip it and carry on with the next
+    Continue,
+}
+
+impl std::str::FromStr for PriceFailure {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "abort" => Ok(PriceFailure::Abort),
+            "continue" => Ok(PriceFailure::Continue),
+            other => anyhow::bail!("Unknown price failure policy: {}", other),
+        }
+    }
+}
+
+/// What a slice does when the best bid is at or above the best ask, as
+/// stale tickers report in fast markets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CrossedBook {
+    /// Re-read the quote until it uncrosses, for up to
+    /// `crossed_book_wait_ms`. A book still crossed after that counts as a
+    /// price that couldn't be fetched, so `on_price_failure` applies.
+    #[default]
+    Wait,
+    /// Price the slice off the venue's mark price instead
+    MarkPrice,
+    /// Skip the slice and carry on with the next
+    Skip,
+}
+
+impl std::str::FromStr for CrossedBook {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "wait" => Ok(CrossedBook::Wait),
+            "mark_price" => Ok(CrossedBook::MarkPrice),
+            "skip" => Ok(CrossedBook::Skip),
+            other => anyhow::bail!("Unknown crossed book policy: {}", other),
+        }
+    }
+}
+
+/// Price tolerance that climbs after slices that don't fill, from passive
+/// towards crossing the spread, so an order that must complete gets there
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct PricingLadder {
+    /// Added to the tolerance after each wave of slices that didn't fill in full
+    pub step_bps: f64,
+    /// Highest tolerance the ladder climbs to, which may be past the spread
+    pub max_bps: f64,
+}
+
+impl PricingLadder {
+    /// `base_bps` after `rung` steps, within the cap. A base already above
+    /// the cap is left as it is.
+    pub fn tolerance_bps(&self, base_bps: f64, rung: u32) -> f64 {
+        (base_bps + self.step_bps * f64::from(rung)).min(self.max_bps.max(base_bps))
+    }
+}
 
 /// Configuration for order slicing
 #[derive(Debug, Clone)]
@@ -25,8 +143,120 @@ pub struct SlicingConfig {
     pub max_parallel: usize,
     /// Price tolerance in basis points for limit orders
     pub price_tolerance_bps: f64,
+    /// Raise the tolerance after slices that don't fill
+    pub pricing_ladder: Option<PricingLadder>,
+    /// Furthest a limit slice may be priced past the opposite touch, in
+    /// basis points, so a wide tolerance or ladder can't sweep the book.
+    /// Prices beyond it are capped there; `None` opts out. Emergency exits
+    /// are not sliced and cross as far as they need.
+    pub max_cross_bps: Option<f64>,
     /// Timeout for each slice in seconds
     pub slice_timeout_secs: u64,
+    /// What a slice does when its price still can't be fetched after retries
+    pub on_price_failure: PriceFailure,
+    /// Oldest a slice's quote may be when the slice goes out. The last
+    /// quote is only reused under `PriceFailure::Continue` while younger
+    /// than this. One that ages past it before the slice is sent, as while
+    /// the other leg's book is read, is fetched afresh and the slice
+    /// re-priced, or the slice skipped if that fetch fails.
+    pub max_quote_age_ms: u64,
+    /// What a slice does when the quote it would be priced off is crossed
+    /// or locked
+    pub on_crossed_book: CrossedBook,
+    /// Longest a slice waits for a crossed book under `CrossedBook::Wait`
+    pub crossed_book_wait_ms: u64,
+    /// Longest a slice is held for the spread target. The order stops there
+    /// as timed out, leaving the rest unfilled.
+    pub spread_target_wait_ms: u64,
+    /// Deadline for the whole order in seconds. Once it passes no further
+    /// slices are placed and resting ones are cancelled.
+    pub total_timeout_secs: Option<u64>,
+    /// Time every slice stops resting, in Unix milliseconds. Slices go out
+    /// good-till-date where the venue expires them itself, and are cancelled
+    /// here at that time otherwise. No slice is placed after it.
+    pub expire_at_ms: Option<i64>,
+    /// Interval between order status polls while a slice is resting
+    pub poll_interval_ms: u64,
+    /// Only rest passively: slices are post-only and re-priced instead of crossing
+    pub maker_only: bool,
+    /// Times a maker-only slice rejected for crossing is re-sent, each time
+    /// a tick further back from the book than its fresh price
+    pub post_only_retries: u32,
+    /// Times a slice refused for an expired timestamp or signature is sent
+    /// again, signed afresh, before it counts as failed
+    pub signature_expiry_retries: u32,
+    /// Every slice is reduce-only, for scaling out of a position
+    pub reduce_only: bool,
+    /// Maker fee in basis points, negative where the venue pays a rebate
+    pub maker_fee_bps: f64,
+    /// Taker fee in basis points, charged on estimated fees for slices
+    /// that may have crossed
+    pub taker_fee_bps: f64,
+    /// Take fees from each filled slice's fills, in the asset they were
+    /// charged in, instead of estimating them at the configured rates. Slices
+    /// whose fills the venue can't report are estimated at the maker rate
+    /// when maker-only and the taker rate otherwise.
+    pub fees_from_fills: bool,
+    /// Offset past the touch for emergency limit orders, in basis points.
+    /// Never less than the current spread width, and doubled on each retry.
+    pub emergency_cross_bps: f64,
+    /// Upper bound on the emergency offset, to stay inside exchange price bands
+    pub emergency_max_cross_bps: f64,
+    /// Before the first emergency order, wait this long and re-quote. If the
+    /// touch has moved our way the exit rests at the new touch rather than
+    /// crossing. Held to 300 ms; 0 crosses straight away.
+    pub emergency_improvement_ms: u64,
+    /// Largest notional a single slice may carry, in USD
+    pub max_slice_notional_usd: Option<f64>,
+    /// Denomination of the traded contract, for notional and fee math
+    pub contract: ContractSpec,
+    pub strategy: SlicingStrategy,
+    /// Registered algorithm sizing the slices in place of `strategy`, by name
+    pub algorithm: Option<String>,
+    /// Adaptive sizing multiplies the slice fraction by this after clean
+    /// fills and divides by it otherwise
+    pub adaptive_factor: f64,
+    /// Bounds on the adaptive slice fraction
+    pub min_slice_percent: f64,
+    pub max_slice_percent: f64,
+    /// Randomize each slice's size by up to this fraction either way
+    /// (e.g. 0.2 = ±20%) so the order leaves no regular footprint
+    pub size_jitter_percent: f64,
+    /// Randomize each wait between slices by up to this fraction either way
+    pub interval_jitter_percent: f64,
+    /// Draw both jitters from this seed, for reproducible slicing, rather
+    /// than from fresh entropy
+    pub jitter_seed: Option<u64>,
+    /// Scale the price tolerance by the size imbalance at the touch: up to
+    /// double when the book leans against us, down to zero when it leans our way
+    pub use_book_imbalance: bool,
+    /// Unit of the total quantity. A quote amount is converted to contracts
+    /// at the reference price, or the mid without one, before the slices
+    /// are sized, and every slice is sent in contracts.
+    pub quantity_mode: QuantityMode,
+    /// Fraction of the total that counts as a complete fill, in (0, 1]
+    pub completion_threshold: Decimal,
+    /// The symbol's price tick and contract step, zero where unknown
+    pub tick_size: Decimal,
+    pub quantity_step: Decimal,
+    /// Smallest order value the venue accepts, in the quote asset, zero
+    /// where unknown. Slices are grown, and small ones merged, to clear it.
+    pub min_notional: Decimal,
+    /// Direction slice prices are rounded to the tick. Emergency prices
+    /// always round towards the book.
+    pub price_rounding: PriceRounding,
+    /// Place slices over the venue's trading WebSocket where it has one
+    pub ws_orders: bool,
+    /// Start of each order's client order id
+    pub client_order_id_prefix: String,
+    /// Hand the whole order to the venue's own TWAP or iceberg engine where
+    /// it has one, sized and spaced like slices and limited to where a slice
+    /// would be priced. That saves a request per slice and keeps working
+    /// through a restart of this service, but the limit is set once at
+    /// placement rather than re-priced per slice, and adaptive sizing, the
+    /// pricing ladder, maker-only pricing and fees from fills don't apply.
+    /// Venues without one are sliced here as usual.
+    pub native_algo: Option<AlgoKind>,
 }
 
 impl Default for SlicingConfig {
@@ -36,11 +266,110 @@ impl Default for SlicingConfig {
             interval_ms: 100,
             max_parallel: 1,          // Sequential by default
             price_tolerance_bps: 5.0, // 5 bps
+            pricing_ladder: None,
+            max_cross_bps: Some(50.0),
             slice_timeout_secs: 30,
+            on_price_failure: PriceFailure::Abort,
+            max_quote_age_ms: 1000,
+            on_crossed_book: CrossedBook::Wait,
+            crossed_book_wait_ms: 500,
+            spread_target_wait_ms: 60_000,
+            total_timeout_secs: None,
+            expire_at_ms: None,
+            poll_interval_ms: 250,
+            maker_only: false,
+            post_only_retries: 2,
+            signature_expiry_retries: 1,
+            reduce_only: false,
+            maker_fee_bps: 0.0,
+            taker_fee_bps: 0.0,
+            fees_from_fills: false,
+            emergency_cross_bps: 50.0,
+            emergency_max_cross_bps: 500.0,
+            emergency_improvement_ms: 0,
+            max_slice_notional_usd: None,
+            contract: ContractSpec::default(),
+            strategy: SlicingStrategy::Fixed,
+            algorithm: None,
+            adaptive_factor: 1.5,
+            min_slice_percent: 0.01,
+            max_slice_percent: 0.25,
+            size_jitter_percent: 0.0,
+            interval_jitter_percent: 0.0,
+            jitter_seed: None,
+            use_book_imbalance: false,
+            quantity_mode: QuantityMode::Base,
+            completion_threshold: dec!(0.99),
+            tick_size: Decimal::ZERO,
+            quantity_step: Decimal::ZERO,
+            min_notional: Decimal::ZERO,
+            price_rounding: PriceRounding::default(),
+            ws_orders: false,
+            client_order_id_prefix: DEFAULT_CLIENT_ORDER_ID_PREFIX.to_string(),
+            native_algo: None,
+        }
+    }
+}
+
+/// Joint pricing for one leg of a paired entry. Slices go out only while
+/// buying the long touch and selling the short touch would capture
+/// `target_spread_bps`, and each leg is held to its share of what is spare:
+/// with `m` the geometric mean of the two touches and `t` the target as a
+/// fraction, the long leg pays at most `m / √(1 + t)` and the short leg sells
+/// for at least `m · √(1 + t)`. Priced that way by both legs off the same
+/// books, the pair captures the target even when both cross, where legs
+/// priced independently would each give away the whole edge.
+#[derive(Clone)]
+pub struct SpreadTarget {
+    /// The other leg's venue and symbol
+    pub adapter: Arc<dyn ExchangeAdapter>,
+    pub symbol: String,
+    /// Short leg over long leg, in basis points
+    pub target_spread_bps: Decimal,
+}
+
+impl SpreadTarget {
+    /// Worst price a slice on `side` may trade at given the long leg's ask
+    /// and the short leg's bid, or `None` while they are too close together
+    /// to capture the target
+    fn limit(&self, side: Side, long_ask: Decimal, short_bid: Decimal) -> Option<Decimal> {
+        let target = Decimal::ONE + self.target_spread_bps / dec!(10000);
+        if long_ask <= Decimal::ZERO || short_bid < long_ask * target {
+            return None;
         }
+        let mid = (long_ask * short_bid).sqrt()?;
+        let target = target.sqrt()?;
+        Some(match side {
+            Side::Buy => mid / target,
+            Side::Sell => mid * target,
+        })
     }
 }
 
+/// Schedule a sliced order is set to follow, worked out before anything is
+/// placed. Slices go out as planned while every wave fills cleanly; adaptive
+/// slicing shrinks them after slow fills, and the time slices rest is not
+/// counted in their send times. Native algo orders don't follow it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SlicePlan {
+    pub strategy: SlicingStrategy,
+    pub total_quantity: Decimal,
+    /// Slices resting at once
+    pub wave_size: usize,
+    pub slices: Vec<PlannedSlice>,
+}
+
+/// One slice of a `SlicePlan`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PlannedSlice {
+    pub index: usize,
+    /// Wave it goes out with, from 0
+    pub wave: usize,
+    pub quantity: Decimal,
+    /// After the first slice, from the intervals between slices alone
+    pub send_after_ms: u64,
+}
+
 /// Result of sliced order execution
 #[derive(Debug)]
 pub struct SlicedOrderResult {
@@ -48,51 +377,316 @@ pub struct SlicedOrderResult {
     pub filled_quantity: Decimal,
     pub avg_fill_price: Decimal,
     pub slices: Vec<SliceResult>,
+    /// In the contract's settlement asset: quote for linear, coin for inverse.
+    /// Fees charged in other assets are converted at current prices.
     pub total_fees: Decimal,
+    /// Fees by the asset they were charged in, before conversion
+    pub fees_by_asset: HashMap<String, Decimal>,
+    /// Some filled slice's fees were estimated from the configured rates,
+    /// or left out, rather than read from its fills
+    pub fees_estimated: bool,
     pub is_complete: bool,
+    /// Left unfilled of the total
+    pub shortfall: Decimal,
+    /// Execution was stopped early by the kill switch
+    pub aborted: bool,
+    /// Execution was stopped at the total timeout before filling in full
+    pub timed_out: bool,
+    /// Execution was stopped because the exchange went into maintenance,
+    /// with the error that said so
+    pub maintenance: Option<String>,
+    /// Execution was stopped because the exchange turned a reduce-only slice
+    /// away for want of a position to reduce
+    pub reduce_only_rejected: bool,
+    /// Execution was stopped because the exchange turned a slice away for
+    /// insufficient margin or over the position risk limit, with the error
+    /// that said so. The slices after it would be turned away as well.
+    pub margin_rejected: Option<String>,
+    /// Fell short only because slices were turned away with transient
+    /// errors, such as rate limits, so working the shortfall again may fill it
+    pub retryable: bool,
+}
+
+impl SlicedOrderResult {
+    /// Fold in the result of working this one's shortfall again. Fills,
+    /// fees and slices add up; the outcome is the retry's.
+    pub fn absorb_retry(&mut self, retry: SlicedOrderResult) {
+        let filled = self.filled_quantity + retry.filled_quantity;
+        if filled > Decimal::ZERO {
+            self.avg_fill_price = (self.avg_fill_price * self.filled_quantity
+                + retry.avg_fill_price * retry.filled_quantity)
+                / filled;
+        }
+        self.filled_quantity = filled;
+        let offset = self.slices.len();
+        self.slices.extend(retry.slices.into_iter().map(|slice| SliceResult {
+            index: slice.index + offset,
+            ..slice
+        }));
+        self.total_fees += retry.total_fees;
+        for (asset, fee) in retry.fees_by_asset {
+            *self.fees_by_asset.entry(asset).or_default() += fee;
+        }
+        self.fees_estimated |= retry.fees_estimated;
+        self.is_complete = retry.is_complete;
+        self.shortfall = retry.shortfall;
+        self.aborted = retry.aborted;
+        self.timed_out = retry.timed_out;
+        self.maintenance = retry.maintenance;
+        self.reduce_only_rejected = retry.reduce_only_rejected;
+        self.margin_rejected = retry.margin_rejected;
+        self.retryable = retry.retryable;
+    }
 }
 
 /// Result of a single slice
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct SliceResult {
     pub index: usize,
     pub client_order_id: String,
     pub exchange_order_id: Option<String>,
     pub quantity: Decimal,
     pub price: Decimal,
+    /// Tolerance the price was set with, `None` for emergency orders, which
+    /// are priced past the far touch instead
+    pub tolerance_bps: Option<f64>,
     pub filled_quantity: Decimal,
     pub avg_fill_price: Option<Decimal>,
     pub status: OrderStatus,
+    /// When the slice was decided on, before it was priced, in Unix
+    /// milliseconds as are the other times
+    pub arrival_ms: i64,
+    /// When the order was sent, or last re-sent after a post-only reject
+    pub submitted_ms: i64,
+    /// Exchange time of the order's last update, for slices that filled.
+    /// Venues that only stamp orders when created report that instead.
+    pub filled_ms: Option<i64>,
+}
+
+impl SliceResult {
+    /// Log the slice's times in one fixed format, for cost analysis
+    fn log_timing(&self, exchange: &str, symbol: &str) {
+        info!(
+            "Slice timing {} {} #{}: arrival_ms={} submitted_ms={} filled_ms={} submit_latency_ms={} fill_latency_ms={}",
+            exchange,
+            symbol,
+            self.index + 1,
+            self.arrival_ms,
+            self.submitted_ms,
+            self.filled_ms.map_or_else(|| "-".to_string(), |ms| ms.to_string()),
+            self.submitted_ms - self.arrival_ms,
+            self.filled_ms
+                .map_or_else(|| "-".to_string(), |ms| (ms - self.arrival_ms).to_string()),
+        );
+    }
+}
+
+/// A slice sent in the current wave, with what its result needs once the
+/// order is done
+struct PendingSlice {
+    index: usize,
+    client_order_id: String,
+    quantity: Decimal,
+    limit_price: Decimal,
+    tolerance_bps: f64,
+    /// Best price on the slice's side when it was priced
+    touch: Decimal,
+    arrival_ms: i64,
+    submitted_ms: i64,
+    /// Open order slot, given back when the slice is dropped
+    _slot: Option<OwnedSemaphorePermit>,
+}
+
+/// Exchange time of `order`'s last update if anything filled
+fn filled_at(order: &OrderResponse) -> Option<i64> {
+    (order.filled_quantity > Decimal::ZERO && order.timestamp > 0).then_some(order.timestamp)
 }
 
 /// Order slicer for splitting and executing orders
 pub struct OrderSlicer {
     config: SlicingConfig,
+    kill_switch: Option<Arc<AtomicBool>>,
+    /// Cap on resting orders per exchange, account and symbol, shared across
+    /// trades, with the API key id of the account this run trades on
+    open_orders: Option<(Arc<OpenOrderLimits>, Uuid)>,
+    /// Orders of this run still resting, shared across trades
+    order_store: Option<Arc<OrderStore>>,
+    /// Every order placed, as placed, whether or not it rested
+    placements: Option<Arc<Mutex<Vec<OrderResponse>>>>,
+    /// Source of size and interval jitter
+    rng: Mutex<StdRng>,
+    /// Sizes slices in place of the configured strategy
+    algorithm: Option<AlgorithmFactory>,
+    /// Prices slices jointly with the other leg of a paired entry
+    spread_target: Option<SpreadTarget>,
 }
 
 impl OrderSlicer {
     pub fn new(config: SlicingConfig) -> Self {
-        Self { config }
+        let rng = match config.jitter_seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self {
+            config,
+            kill_switch: None,
+            open_orders: None,
+            order_store: None,
+            placements: None,
+            rng: Mutex::new(rng),
+            algorithm: None,
+            spread_target: None,
+        }
     }
 
-    /// Calculate slice sizes for a given total quantity
-    pub fn calculate_slices(&self, total_quantity: Decimal) -> Vec<Decimal> {
-        let slice_size = total_quantity * Decimal::try_from(self.config.slice_percent).unwrap();
-        let min_slice = dec!(0.001); // Minimum slice size
+    /// Size slices with the algorithm `factory` builds, rather than the
+    /// configured strategy. A fresh one is built for each order.
+    pub fn with_algorithm<F>(mut self, factory: F) -> Self
+    where
+        F: Fn(&SlicingConfig) -> Box<dyn ExecutionAlgorithm> + Send + Sync + 'static,
+    {
+        self.algorithm = Some(Arc::new(factory));
+        self
+    }
+
+    fn algorithm(&self) -> Box<dyn ExecutionAlgorithm> {
+        match &self.algorithm {
+            Some(factory) => factory(&self.config),
+            None => algorithm::for_config(&self.config),
+        }
+    }
+
+    /// Abort execution once `kill_switch` is set. It is checked between slices
+    /// and while a slice is resting, which is then cancelled together with
+    /// everything else open on the symbol where the venue can mass cancel.
+    pub fn with_kill_switch(mut self, kill_switch: Arc<AtomicBool>) -> Self {
+        self.kill_switch = Some(kill_switch);
+        self
+    }
+
+    /// Hold each slice until a slot is free under `limits` for the account of
+    /// `api_key_id`, released once the slice has filled or been cancelled
+    pub fn with_open_order_limits(mut self, limits: Arc<OpenOrderLimits>, api_key_id: Uuid) -> Self {
+        self.open_orders = Some((limits, api_key_id));
+        self
+    }
+
+    /// Keep `store` up to date with each slice from placement until it is done
+    pub fn with_order_store(mut self, store: Arc<OrderStore>) -> Self {
+        self.order_store = Some(store);
+        self
+    }
+
+    /// Record every order placed in `placements`, as the exchange answered
+    /// the placement
+    pub fn with_placements(mut self, placements: Arc<Mutex<Vec<OrderResponse>>>) -> Self {
+        self.placements = Some(placements);
+        self
+    }
+
+    /// Price slices off both legs' books to capture `target`, see `SpreadTarget`
+    pub fn with_spread_target(mut self, target: SpreadTarget) -> Self {
+        self.spread_target = Some(target);
+        self
+    }
+
+    /// `price` held to the spread target against the other leg's bid and
+    /// ask, `None` while the books are too close to capture it
+    fn spread_limited(
+        &self,
+        target: &SpreadTarget,
+        side: Side,
+        best_bid: Decimal,
+        best_ask: Decimal,
+        (other_bid, other_ask): (Decimal, Decimal),
+        price: Decimal,
+    ) -> Option<Decimal> {
+        let (long_ask, short_bid) = match side {
+            Side::Buy => (best_ask, other_bid),
+            Side::Sell => (other_ask, best_bid),
+        };
+        let limit = target.limit(side, long_ask, short_bid)?;
+        let limit = PriceRounding::Passive.round(limit, self.config.tick_size, side);
+        Some(match side {
+            Side::Buy => price.min(limit),
+            Side::Sell => price.max(limit),
+        })
+    }
+
+    /// The other leg's bid and ask, retried a few times. When they still
+    /// can't be read, `on_price_failure` decides between the error, the
+    /// `last` ones if fresh enough, and `None` to skip the slice.
+    async fn other_leg_price(
+        &self,
+        target: &SpreadTarget,
+        last: &mut Option<(Instant, (Decimal, Decimal))>,
+    ) -> Result<Option<(Decimal, Decimal)>> {
+        let venue = target.adapter.id();
+        let mut attempt = 0;
+        let error = loop {
+            attempt += 1;
+            match target.adapter.get_best_price(&target.symbol).await {
+                Ok(touch) => {
+                    *last = Some((Instant::now(), touch));
+                    return Ok(Some(touch));
+                }
+                Err(e) if attempt < QUOTE_ATTEMPTS => {
+                    debug!("Other leg price fetch {} on {} failed, retrying: {:#}", attempt, venue, e);
+                    sleep(QUOTE_RETRY_DELAY).await;
+                }
+                Err(e) => break e.context(format!("No price for the other leg on {}", venue)),
+            }
+        };
+
+        match self.config.on_price_failure {
+            PriceFailure::Abort => Err(error),
+            PriceFailure::Continue => {
+                let max_age = Duration::from_millis(self.config.max_quote_age_ms);
+                match last.filter(|(at, _)| at.elapsed() <= max_age) {
+                    Some((at, touch)) => {
+                        warn!("{:#}, reusing its price from {} ms ago", error, at.elapsed().as_millis());
+                        Ok(Some(touch))
+                    }
+                    None => {
+                        warn!("{:#}, skipping the slice", error);
+                        Ok(None)
+                    }
+                }
+            }
+        }
+    }
+
+    fn track_placed(&self, adapter: &dyn ExchangeAdapter, credentials: &Credentials, order: &OrderResponse) {
+        if let Some(store) = &self.order_store {
+            store.insert(adapter.id(), credentials, order);
+        }
+        if let Some(placements) = &self.placements {
+            placements.lock().unwrap().push(order.clone());
+        }
+    }
 
-        if slice_size < min_slice {
-            return vec![total_quantity];
+    fn track_updates(&self, orders: &[OrderResponse]) {
+        if let Some(store) = &self.order_store {
+            orders.iter().for_each(|order| store.update(order));
         }
+    }
+
+    fn is_killed(&self) -> bool {
+        self.kill_switch
+            .as_ref()
+            .is_some_and(|k| k.load(Ordering::SeqCst))
+    }
+
+    /// Calculate slice sizes for a given total quantity. Slices are shrunk to
+    /// stay under the notional cap at `reference_price` when one is known.
+    pub fn calculate_slices(&self, total_quantity: Decimal, reference_price: Decimal) -> Vec<Decimal> {
+        let slice_size = total_quantity * Decimal::try_from(self.config.slice_percent).unwrap();
 
         let mut slices = Vec::new();
         let mut remaining = total_quantity;
 
         while remaining > Decimal::ZERO {
-            let slice = if remaining < slice_size {
-                remaining
-            } else {
-                slice_size
-            };
+            let slice = self.next_slice(total_quantity, reference_price, slice_size, remaining);
             slices.push(slice);
             remaining -= slice;
         }
@@ -100,239 +694,3204 @@ impl OrderSlicer {
         slices
     }
 
-    /// Execute a sliced order on an exchange
-    pub async fn execute_sliced_order(
+    /// Plan `total_quantity` out slice by slice, the way execution will send
+    /// it if every wave fills cleanly. Jitter is drawn from a copy of the
+    /// slicer's random source, so a seeded slicer still sends what it planned.
+    pub fn plan(&self, total_quantity: Decimal, reference_price: Decimal) -> SlicePlan {
+        let total_quantity = self.tradable_quantity(total_quantity);
+        let rng = self.rng.lock().unwrap().clone();
+        let mut algorithm = self.algorithm();
+        let wave_size = self.config.max_parallel.max(1);
+        let mut slices = Vec::new();
+        let mut remaining = total_quantity;
+        let mut send_after = Duration::ZERO;
+        let mut wave = 0;
+
+        'waves: while remaining > Decimal::ZERO {
+            for _ in 0..wave_size {
+                if remaining <= Decimal::ZERO {
+                    break;
+                }
+                let state = ExecState {
+                    total_quantity,
+                    unplaced: remaining,
+                    filled: total_quantity - remaining,
+                    reference_price,
+                    slices_sent: slices.len(),
+                    waves: wave,
+                    last_wave_clean: (wave > 0).then_some(true),
+                };
+                let Some(instruction) = algorithm.next_slice(&state) else {
+                    break 'waves;
+                };
+                let quantity = self.next_slice(total_quantity, reference_price, instruction.quantity, remaining);
+                remaining -= quantity;
+                slices.push(PlannedSlice {
+                    index: slices.len(),
+                    wave,
+                    quantity,
+                    send_after_ms: u64::try_from(send_after.as_millis()).unwrap_or(u64::MAX),
+                });
+                if remaining > Decimal::ZERO {
+                    send_after += self.slice_interval();
+                }
+            }
+            wave += 1;
+        }
+
+        *self.rng.lock().unwrap() = rng;
+        SlicePlan {
+            strategy: self.config.strategy,
+            total_quantity,
+            wave_size,
+            slices,
+        }
+    }
+
+    /// Size of the next slice with `remaining` still to place, asked for as
+    /// `requested`. The size is capped, jittered when configured and raised
+    /// to the venue's minimum notional, and a remainder too small to be a
+    /// slice of its own is absorbed so the slices always sum to the
+    /// tradable total exactly.
+    fn next_slice(
         &self,
-        adapter: &dyn ExchangeAdapter,
-        credentials: &Credentials,
-        symbol: &str,
-        side: Side,
         total_quantity: Decimal,
         reference_price: Decimal,
-    ) -> Result<SlicedOrderResult> {
-        let slices = self.calculate_slices(total_quantity);
-        let num_slices = slices.len();
+        requested: Decimal,
+        remaining: Decimal,
+    ) -> Decimal {
+        let slice_size = self.slice_size(total_quantity, reference_price, requested);
+        let slice = if self.config.size_jitter_percent > 0.0 {
+            let jittered = self
+                .jitter(slice_size, self.config.size_jitter_percent)
+                .round_dp(total_quantity.scale().max(3))
+                .max(MIN_SLICE_SIZE);
+            // Jitter is never a way past the notional cap
+            self.max_slice_size(reference_price).map_or(jittered, |max| jittered.min(max))
+        } else {
+            slice_size
+        };
 
-        info!(
-            "Executing sliced order: {} {} {} in {} slices",
-            side_str(side),
-            total_quantity,
-            symbol,
-            num_slices
-        );
+        // Contract slices stay on the step, and so does the remainder of a
+        // total rounded down to it
+        let step = self.config.quantity_step;
+        let floor = self.min_notional_slice(reference_price, step, total_quantity.scale().max(3));
+        let slice = round_quantity(slice, step).max(step).max(floor);
 
-        let mut results = Vec::new();
-        let mut total_filled = Decimal::ZERO;
-        let mut weighted_price_sum = Decimal::ZERO;
+        if remaining - slice < MIN_SLICE_SIZE.max(step).max(floor) {
+            remaining
+        } else {
+            slice
+        }
+    }
 
-        for (index, slice_qty) in slices.iter().enumerate() {
-            // Calculate limit price with tolerance
-            let (best_bid, best_ask) = adapter.get_best_price(symbol).await?;
-            let limit_price = calculate_limit_price(
-                side,
-                best_bid,
-                best_ask,
-                self.config.price_tolerance_bps,
-            );
+    /// Smallest slice worth at least the venue's minimum notional at
+    /// `reference_price`, rounded up to `step` or to `dp` places without one.
+    /// Zero when the minimum or the price is unknown.
+    fn min_notional_slice(&self, reference_price: Decimal, step: Decimal, dp: u32) -> Decimal {
+        if self.config.min_notional <= Decimal::ZERO {
+            return Decimal::ZERO;
+        }
+        let floor = self.config.min_notional * MIN_NOTIONAL_HEADROOM;
+        if reference_price <= Decimal::ZERO {
+            return Decimal::ZERO;
+        }
+        let quantity = self.config.contract.quantity_for_notional(floor, reference_price);
+        if step > Decimal::ZERO {
+            (quantity / step).ceil() * step
+        } else {
+            quantity.round_dp_with_strategy(dp, RoundingStrategy::AwayFromZero)
+        }
+    }
 
-            let client_order_id = generate_client_order_id();
+    /// The part of `total_quantity` the venue accepts, rounded down to the
+    /// contract step. The odd lot left over is never sent, and counts
+    /// towards the shortfall.
+    fn tradable_quantity(&self, total_quantity: Decimal) -> Decimal {
+        round_quantity(total_quantity, self.config.quantity_step)
+    }
 
-            let request = OrderRequest {
-                client_order_id: client_order_id.clone(),
-                symbol: symbol.to_string(),
-                side,
-                order_type: OrderType::Limit,
-                price: Some(limit_price),
-                quantity: *slice_qty,
-                reduce_only: false,
-            };
+    /// `amount` of the quote asset in contracts, at `reference_price` or
+    /// the current mid when there is none
+    async fn quote_to_contracts(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        symbol: &str,
+        amount: Decimal,
+        reference_price: Decimal,
+    ) -> Result<Decimal> {
+        let price = if reference_price > Decimal::ZERO {
+            reference_price
+        } else {
+            let (bid, ask) = adapter
+                .get_best_price(symbol)
+                .await
+                .with_context(|| format!("Pricing {} {} to convert it to contracts", amount, symbol))?;
+            (bid + ask) / Decimal::TWO
+        };
+        anyhow::ensure!(price > Decimal::ZERO, "No price to convert {} {} to contracts", amount, symbol);
+        Ok(self
+            .config
+            .contract
+            .quantity_for_notional(amount, price)
+            .round_dp_with_strategy(CONVERTED_QUANTITY_DP, RoundingStrategy::ToZero))
+    }
 
-            debug!(
-                "Placing slice {}/{}: {} @ {}",
-                index + 1,
-                num_slices,
-                slice_qty,
-                limit_price
-            );
+    /// `value` scaled by a uniform random factor within ±`percent`
+    fn jitter(&self, value: Decimal, percent: f64) -> Decimal {
+        let factor = 1.0 + self.rng.lock().unwrap().gen_range(-percent..=percent);
+        value * Decimal::try_from(factor).unwrap_or(Decimal::ONE)
+    }
 
-            match adapter.place_order(credentials, &request).await {
-                Ok(response) => {
-                    let slice_result = SliceResult {
-                        index,
-                        client_order_id,
-                        exchange_order_id: Some(response.exchange_order_id),
-                        quantity: *slice_qty,
-                        price: limit_price,
-                        filled_quantity: response.filled_quantity,
-                        avg_fill_price: response.avg_fill_price,
-                        status: response.status,
-                    };
+    /// Best bid, best ask and the price tolerance to use against them. With
+    /// `use_book_imbalance` the tolerance follows the top-of-book sizes,
+    /// falling back to the fixed tolerance when the book can't be read or
+    /// was taken more than `max_quote_age_ms` ago.
+    async fn quote(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        symbol: &str,
+        side: Side,
+    ) -> Result<(Decimal, Decimal, f64)> {
+        let tolerance_bps = self.config.price_tolerance_bps;
+        if self.config.use_book_imbalance {
+            match adapter.get_order_book(symbol, 1).await {
+                Ok(book) if now_millis() - book.timestamp > self.config.max_quote_age_ms as i64 => {
+                    debug!(
+                        "Order book for {} is {} ms old, using fixed tolerance",
+                        symbol,
+                        now_millis() - book.timestamp
+                    );
+                }
+                Ok(book) => {
+                    if let (Some(bid), Some(ask)) = (book.bids.first(), book.asks.first()) {
+                        let adjusted = imbalance_tolerance_bps(side, bid.quantity, ask.quantity, tolerance_bps);
+                        return Ok((bid.price, ask.price, adjusted));
+                    }
+                    debug!("Empty order book for {}, using fixed tolerance", symbol);
+                }
+                Err(e) => debug!("No order book for {}, using fixed tolerance: {}", symbol, e),
+            }
+        }
+
+        let (best_bid, best_ask) = adapter.get_best_price(symbol).await?;
+        Ok((best_bid, best_ask, tolerance_bps))
+    }
+
+    /// `quote` as it stands if the book isn't crossed or locked, otherwise
+    /// what `on_crossed_book` makes of it: a later quote that uncrossed, one
+    /// priced off the mark, or `None` to skip the slice
+    async fn uncrossed_quote(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        symbol: &str,
+        side: Side,
+        quote: (Decimal, Decimal, f64),
+    ) -> Result<Option<(Decimal, Decimal, f64)>> {
+        let (best_bid, best_ask, tolerance_bps) = quote;
+        if best_bid < best_ask {
+            return Ok(Some(quote));
+        }
 
-                    total_filled += response.filled_quantity;
-                    if let Some(avg_price) = response.avg_fill_price {
-                        weighted_price_sum += avg_price * response.filled_quantity;
+        match self.config.on_crossed_book {
+            CrossedBook::Wait => {
+                let started = Instant::now();
+                let max_wait = Duration::from_millis(self.config.crossed_book_wait_ms);
+                debug!("Book for {} is crossed at {} / {}, waiting", symbol, best_bid, best_ask);
+                let (mut best_bid, mut best_ask) = (best_bid, best_ask);
+                while started.elapsed() < max_wait {
+                    sleep(QUOTE_RETRY_DELAY.min(max_wait - started.elapsed())).await;
+                    let quote = self.quote(adapter, symbol, side).await?;
+                    if quote.0 < quote.1 {
+                        return Ok(Some(quote));
                     }
+                    (best_bid, best_ask, _) = quote;
+                }
+                anyhow::bail!(
+                    "Book for {} still crossed after {} ms: bid {} ask {}",
+                    symbol,
+                    max_wait.as_millis(),
+                    best_bid,
+                    best_ask
+                )
+            }
+            CrossedBook::MarkPrice => {
+                let mark = adapter
+                    .get_reference_price(symbol, ReferencePriceSource::Mark)
+                    .await
+                    .with_context(|| format!("Book for {} is crossed and it has no mark price", symbol))?;
+                warn!(
+                    "Book for {} is crossed at {} / {}, pricing off the mark {}",
+                    symbol, best_bid, best_ask, mark
+                );
+                Ok(Some((mark, mark, tolerance_bps)))
+            }
+            CrossedBook::Skip => {
+                warn!("Book for {} is crossed at {} / {}, skipping the slice", symbol, best_bid, best_ask);
+                Ok(None)
+            }
+        }
+    }
 
-                    results.push(slice_result);
+    /// `quote`, retried a few times and uncrossed. When it still fails,
+    /// `on_price_failure` decides between the error, the `last` quote if
+    /// fresh enough, and `None` to skip the slice.
+    async fn slice_quote(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        symbol: &str,
+        side: Side,
+        last: &mut Option<(Instant, (Decimal, Decimal, f64))>,
+    ) -> Result<Option<(Decimal, Decimal, f64)>> {
+        let mut attempt = 0;
+        let error = loop {
+            attempt += 1;
+            match self.quote(adapter, symbol, side).await {
+                // A book that stayed crossed has had its wait already
+                Ok(quote) => match self.uncrossed_quote(adapter, symbol, side, quote).await {
+                    Ok(Some(quote)) => {
+                        *last = Some((Instant::now(), quote));
+                        return Ok(Some(quote));
+                    }
+                    Ok(None) => return Ok(None),
+                    Err(e) => break e,
+                },
+                Err(e) if attempt < QUOTE_ATTEMPTS => {
+                    debug!("Price fetch {} for {} failed, retrying: {:#}", attempt, symbol, e);
+                    sleep(QUOTE_RETRY_DELAY).await;
                 }
-                Err(e) => {
-                    warn!("Slice {} failed: {}", index + 1, e);
-                    results.push(SliceResult {
-                        index,
-                        client_order_id,
-                        exchange_order_id: None,
-                        quantity: *slice_qty,
-                        price: limit_price,
-                        filled_quantity: Decimal::ZERO,
-                        avg_fill_price: None,
-                        status: OrderStatus::Rejected,
-                    });
+                Err(e) => break e,
+            }
+        };
+
+        match self.config.on_price_failure {
+            PriceFailure::Abort => Err(error),
+            PriceFailure::Continue => {
+                let max_age = Duration::from_millis(self.config.max_quote_age_ms);
+                match last.filter(|(at, _)| at.elapsed() <= max_age) {
+                    Some((at, quote)) => {
+                        warn!(
+                            "No price for {}, reusing the quote from {} ms ago: {:#}",
+                            symbol,
+                            at.elapsed().as_millis(),
+                            error
+                        );
+                        Ok(Some(quote))
+                    }
+                    None => {
+                        warn!("No price for {}, skipping the slice: {:#}", symbol, error);
+                        Ok(None)
+                    }
                 }
             }
+        }
+    }
 
-            // Wait between slices
-            if index < num_slices - 1 {
-                sleep(Duration::from_millis(self.config.interval_ms)).await;
+    /// Place a slice. Adapters sign every request as it is sent, so one
+    /// refused as expired, after a stall or on a drifting clock, is simply
+    /// sent again.
+    async fn send_slice(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        credentials: &Credentials,
+        request: &OrderRequest,
+    ) -> Result<OrderResponse> {
+        let mut resigned = 0;
+        loop {
+            let placed = if self.config.ws_orders {
+                adapter.place_order_ws(credentials, request).await
+            } else {
+                adapter.place_order(credentials, request).await
+            };
+            match placed {
+                Err(e) if ExchangeError::is_signature_expired(&e) && resigned < self.config.signature_expiry_retries => {
+                    resigned += 1;
+                    warn!("Slice {} refused as expired, re-signing: {:#}", request.client_order_id, e);
+                }
+                placed => return placed,
             }
         }
+    }
 
-        let avg_fill_price = if total_filled > Decimal::ZERO {
-            weighted_price_sum / total_filled
+    /// When `expire_at_ms` comes round on the runtime's clock
+    fn expiry(&self) -> Option<Instant> {
+        self.config
+            .expire_at_ms
+            .map(|ms| Instant::now() + Duration::from_millis(u64::try_from(ms - now_millis()).unwrap_or(0)))
+    }
+
+    /// Wait before the next slice
+    fn slice_interval(&self) -> Duration {
+        let interval = Duration::from_millis(self.config.interval_ms);
+        if self.config.interval_jitter_percent > 0.0 {
+            let factor = 1.0 + self
+                .rng
+                .lock()
+                .unwrap()
+                .gen_range(-self.config.interval_jitter_percent..=self.config.interval_jitter_percent);
+            interval.mul_f64(factor.max(0.0))
         } else {
-            Decimal::ZERO
-        };
+            interval
+        }
+    }
 
-        let is_complete = total_filled >= total_quantity * dec!(0.99); // 99% fill threshold
+    /// `requested` within the notional cap. Requests too small to send take
+    /// the whole total.
+    fn slice_size(&self, total_quantity: Decimal, reference_price: Decimal, requested: Decimal) -> Decimal {
+        let slice_size = requested;
+        if slice_size < MIN_SLICE_SIZE {
+            return total_quantity;
+        }
 
-        info!(
-            "Sliced order complete: filled {} / {} @ avg {}",
-            total_filled, total_quantity, avg_fill_price
-        );
+        match self.max_slice_size(reference_price) {
+            Some(max_size) => slice_size.min(max_size),
+            None => slice_size,
+        }
+    }
 
-        Ok(SlicedOrderResult {
-            total_quantity,
-            filled_quantity: total_filled,
-            avg_fill_price,
-            slices: results,
-            total_fees: Decimal::ZERO, // TODO: Calculate actual fees
-            is_complete,
-        })
+    /// Largest slice within the notional cap at `reference_price`, if there
+    /// is a cap and the price is known
+    fn max_slice_size(&self, reference_price: Decimal) -> Option<Decimal> {
+        let max_notional = Decimal::try_from(self.config.max_slice_notional_usd?).unwrap_or_default();
+        if reference_price <= Decimal::ZERO {
+            return None;
+        }
+        let max_size = self.config.contract.quantity_for_notional(max_notional, reference_price);
+        Some(max_size.max(MIN_SLICE_SIZE))
     }
 
-    /// Execute emergency exit with aggressive pricing
-    pub async fn execute_emergency_exit(
+    /// Execute a sliced order on an exchange
+    pub async fn execute_sliced_order(
         &self,
         adapter: &dyn ExchangeAdapter,
         credentials: &Credentials,
         symbol: &str,
         side: Side,
-        quantity: Decimal,
+        total_quantity: Decimal,
+        reference_price: Decimal,
     ) -> Result<SlicedOrderResult> {
-        info!(
-            "Executing EMERGENCY EXIT: {} {} {}",
-            side_str(side),
-            quantity,
-            symbol
+        let threshold = self.config.completion_threshold;
+        anyhow::ensure!(
+            threshold > Decimal::ZERO && threshold <= Decimal::ONE,
+            "Completion threshold must be in (0, 1], got {}",
+            threshold
         );
-
-        // Get current price
-        let (best_bid, best_ask) = adapter.get_best_price(symbol).await?;
-
-        // Use aggressive pricing (cross the spread)
-        let aggressive_price = match side {
-            Side::Buy => best_ask * dec!(1.005),  // 0.5% above ask
-            Side::Sell => best_bid * dec!(0.995), // 0.5% below bid
+        for (name, jitter) in [
+            ("Size", self.config.size_jitter_percent),
+            ("Interval", self.config.interval_jitter_percent),
+        ] {
+            anyhow::ensure!((0.0..1.0).contains(&jitter), "{} jitter must be in [0, 1), got {}", name, jitter);
+        }
+        let total_quantity = match self.config.quantity_mode {
+            QuantityMode::Base => total_quantity,
+            QuantityMode::Quote => {
+                let contracts = self.quote_to_contracts(adapter, symbol, total_quantity, reference_price).await?;
+                debug!("{} {} of quote is {} contracts", total_quantity, symbol, contracts);
+                contracts
+            }
         };
-
-        let client_order_id = generate_client_order_id();
-
-        let request = OrderRequest {
-            client_order_id: client_order_id.clone(),
-            symbol: symbol.to_string(),
-            side,
-            order_type: OrderType::Limit,
-            price: Some(aggressive_price),
-            quantity,
-            reduce_only: true,
+        // Reduce-only orders are exempt from the minimum on the venues that
+        // set one, so small positions can always be closed
+        let total_notional = match reference_price.is_zero() {
+            true => None,
+            false => Some(self.config.contract.notional_usd(total_quantity, reference_price)),
         };
+        if let Some(notional) = total_notional.filter(|_| !self.config.reduce_only) {
+            anyhow::ensure!(
+                notional >= self.config.min_notional,
+                "Order of {} {} is worth {}, below the venue's {} minimum notional",
+                total_quantity,
+                symbol,
+                notional.round_dp(2),
+                self.config.min_notional
+            );
+        }
 
-        let response = adapter.place_order(credentials, &request).await?;
+        let tradable = self.tradable_quantity(total_quantity);
+        if tradable < total_quantity {
+            warn!(
+                "{} {} is off the {} step, working {} and leaving {} unfilled",
+                total_quantity,
+                symbol,
+                self.config.quantity_step,
+                tradable,
+                total_quantity - tradable
+            );
+        }
+
+        match self.config.native_algo {
+            Some(kind) if adapter.supports_algo_order(kind) => {
+                let mut result = self
+                    .execute_native_algo(adapter, credentials, symbol, side, tradable, kind)
+                    .await?;
+                if tradable < total_quantity {
+                    result.total_quantity = total_quantity;
+                    result.shortfall += total_quantity - tradable;
+                    result.is_complete &= result.filled_quantity >= total_quantity * threshold;
+                }
+                return Ok(result);
+            }
+            Some(kind) => debug!("{} has no native {:?} orders, slicing {} here", adapter.id(), kind, symbol),
+            None => {}
+        }
+
+        let plan = self.plan(total_quantity, reference_price);
+        info!(
+            "Executing sliced order: {} {} {} ({:?} slicing, {} slices planned)",
+            side_str(side),
+            total_quantity,
+            symbol,
+            self.config.strategy,
+            plan.slices.len()
+        );
+        info!(
+            "Slice plan for {}: {}",
+            symbol,
+            serde_json::to_string(&plan).unwrap_or_default()
+        );
+
+        let mut results = Vec::new();
+        let mut total_filled = Decimal::ZERO;
+        let mut weighted_price_sum = Decimal::ZERO;
+        let mut aborted = false;
+        let mut timed_out = false;
+        let mut maintenance = None;
+        let mut reduce_only_rejected = false;
+        let mut margin_rejected = None;
+        // Whether slices were turned away with transient errors, and with others
+        let mut transient_failures = false;
+        let mut terminal_failures = false;
+        let deadline = self
+            .config
+            .total_timeout_secs
+            .map(|secs| Instant::now() + Duration::from_secs(secs));
+        // Past the expiry nothing more is placed. Resting slices are expired
+        // by venues with good-till-date orders and cancelled here elsewhere.
+        let native_gtd = self
+            .config
+            .expire_at_ms
+            .is_some_and(|expire_at_ms| !self.config.maker_only && adapter.supports_gtd(expire_at_ms));
+        let expiry = self.expiry();
+        let cancel_at = if native_gtd { deadline } else { earliest(deadline, expiry) };
+        let deadline = earliest(deadline, expiry);
+        // Negative for venues that pay a maker rebate
+        let mut total_fees = Decimal::ZERO;
+        let mut fees_by_asset: HashMap<String, Decimal> = HashMap::new();
+        let mut fees_estimated = false;
+        let maker_fee_rate =
+            Decimal::try_from(self.config.maker_fee_bps).unwrap_or_default() / dec!(10000);
+        let taker_fee_rate =
+            Decimal::try_from(self.config.taker_fee_bps).unwrap_or_default() / dec!(10000);
+        let assets = symbol_assets(adapter, symbol);
+        let settlement_asset = assets.as_ref().map(|(base, quote)| match self.config.contract.contract_type {
+            ContractType::Linear => quote.clone(),
+            ContractType::Inverse => base.clone(),
+        });
+
+        // Up to `max_parallel` slices rest at once and are polled together
+        let wave_size = self.config.max_parallel.max(1);
+        let mut algorithm = self.algorithm();
+        let mut waves = 0;
+        let mut last_wave_clean = None;
+        // The algorithm asked for no more slices
+        let mut algorithm_done = false;
+        let mut unplaced = tradable;
+        let mut index = 0;
+        // Steps climbed on the pricing ladder
+        let mut ladder_rung = 0;
+        let mut last_quote = None;
+        let mut last_other_price = None;
+
+        while unplaced > Decimal::ZERO && !algorithm_done {
+            let mut pending = Vec::new();
+            // A wave fills cleanly when every slice filled in full before the
+            // timeout without trading through the touch seen at placement
+            let mut filled_cleanly = true;
+            let mut filled_in_full = true;
+
+            for _ in 0..wave_size {
+                if unplaced <= Decimal::ZERO
+                    || timed_out
+                    || maintenance.is_some()
+                    || reduce_only_rejected
+                    || margin_rejected.is_some()
+                {
+                    break;
+                }
+
+                if self.is_killed() {
+                    warn!(
+                        "Kill switch set, aborting {} after {} slices, {} unplaced",
+                        symbol, index, unplaced
+                    );
+                    aborted = true;
+                    break;
+                }
+
+                if deadline.is_some_and(|d| Instant::now() >= d) {
+                    warn!(
+                        "Total timeout reached for {} after {} slices, {} unplaced",
+                        symbol, index, unplaced
+                    );
+                    timed_out = true;
+                    break;
+                }
+
+                // Stay under the exchange's open order cap. Once this wave has
+                // orders of its own out, wait for those instead of the slot.
+                let mut slot = match &self.open_orders {
+                    None => None,
+                    Some((limits, account)) => match limits.try_acquire(adapter.id(), *account, symbol) {
+                        Some(slot) => Some(slot),
+                        None if !pending.is_empty() => break,
+                        None => {
+                            debug!(
+                                "Open order cap reached on {} for {} with {} orders resting, waiting",
+                                adapter.id(),
+                                symbol,
+                                limits.open_orders(adapter.id(), *account, symbol)
+                            );
+                            Some(limits.acquire(adapter.id(), *account, symbol).await)
+                        }
+                    },
+                };
+
+                let state = ExecState {
+                    total_quantity,
+                    unplaced,
+                    filled: total_filled,
+                    reference_price,
+                    slices_sent: index,
+                    waves,
+                    last_wave_clean,
+                };
+                let Some(instruction) = algorithm.next_slice(&state) else {
+                    debug!("Execution algorithm for {} stopped with {} unplaced", symbol, unplaced);
+                    algorithm_done = true;
+                    break;
+                };
+                let slice_qty = self.next_slice(total_quantity, reference_price, instruction.quantity, unplaced);
+                unplaced -= slice_qty;
+                let arrival_ms = now_millis();
+
+                // Maker-only slices that would have crossed are re-priced and
+                // re-sent, a tick further back for each rejection
+                let mut attempt = 0;
+                let mut refreshed = false;
+                let mut spread_held_since = None;
+                let sent = loop {
+                    attempt += 1;
+
+                    // Calculate limit price with tolerance
+                    let quote = if refreshed {
+                        // Nothing cached to fall back on: a failed refresh
+                        // skips the slice rather than trading on the old quote
+                        let mut fresh = None;
+                        match self.slice_quote(adapter, symbol, side, &mut fresh).await {
+                            Ok(quote) => {
+                                last_quote = fresh.or(last_quote);
+                                quote
+                            }
+                            Err(e) => {
+                                warn!("No fresh price for {}, skipping slice {}: {:#}", symbol, index + 1, e);
+                                None
+                            }
+                        }
+                    } else {
+                        self.slice_quote(adapter, symbol, side, &mut last_quote).await?
+                    };
+                    let Some((best_bid, best_ask, tolerance_bps)) = quote else {
+                        break None;
+                    };
+                    let quoted_at = last_quote.map_or_else(Instant::now, |(at, _)| at);
+                    let tolerance_bps = match self.config.pricing_ladder {
+                        Some(ladder) => ladder.tolerance_bps(tolerance_bps, ladder_rung),
+                        None => tolerance_bps,
+                    };
+                    let touch = match side {
+                        Side::Buy => best_ask,
+                        Side::Sell => best_bid,
+                    };
+                    let limit_price = if self.config.maker_only {
+                        calculate_maker_price(side, best_bid, best_ask, tolerance_bps)
+                    } else {
+                        calculate_limit_price(side, best_bid, best_ask, tolerance_bps)
+                    };
+                    let limit_price = self.config.price_rounding.round(limit_price, self.config.tick_size, side);
+                    let limit_price = self.cap_cross(symbol, side, best_bid, best_ask, limit_price);
+                    let limit_price = match &self.spread_target {
+                        None => limit_price,
+                        Some(target) => {
+                            let Some(other) = self.other_leg_price(target, &mut last_other_price).await? else {
+                                break None;
+                            };
+                            match self.spread_limited(target, side, best_bid, best_ask, other, limit_price) {
+                                Some(price) => {
+                                    // Take back the slot given up while holding
+                                    if let (None, Some((limits, account))) = (&slot, &self.open_orders) {
+                                        slot = match limits.try_acquire(adapter.id(), *account, symbol) {
+                                            Some(slot) => Some(slot),
+                                            None if pending.is_empty() => {
+                                                Some(limits.acquire(adapter.id(), *account, symbol).await)
+                                            }
+                                            // Left for the next wave, once this one's orders are done
+                                            None => {
+                                                unplaced += slice_qty;
+                                                break None;
+                                            }
+                                        };
+                                    }
+                                    price
+                                }
+                                // The kill switch or the deadline ends the wait
+                                None if self.is_killed() || deadline.is_some_and(|d| Instant::now() >= d) => break None,
+                                None => {
+                                    let held_since = *spread_held_since.get_or_insert_with(Instant::now);
+                                    if held_since.elapsed() >= Duration::from_millis(self.config.spread_target_wait_ms) {
+                                        warn!(
+                                            "Spread for {} stayed short of the {} bps target for {} ms, stopping with {} unplaced",
+                                            symbol,
+                                            target.target_spread_bps,
+                                            self.config.spread_target_wait_ms,
+                                            unplaced + slice_qty
+                                        );
+                                        timed_out = true;
+                                        break None;
+                                    }
+                                    // Other orders on the account may use the slot meanwhile
+                                    slot = None;
+                                    debug!(
+                                        "Spread for {} is short of the {} bps target, holding slice {}",
+                                        symbol,
+                                        target.target_spread_bps,
+                                        index + 1
+                                    );
+                                    sleep(Duration::from_millis(self.config.poll_interval_ms)).await;
+                                    // Waiting on the spread isn't a re-price
+                                    attempt -= 1;
+                                    continue;
+                                }
+                            }
+                        }
+                    };
+                    let limit_price = if self.config.maker_only {
+                        step_back(side, limit_price, self.config.tick_size, attempt - 1)
+                    } else {
+                        limit_price
+                    };
+
+                    if quoted_at.elapsed() > Duration::from_millis(self.config.max_quote_age_ms) {
+                        if refreshed {
+                            warn!("Price for {} went stale again, skipping slice {}", symbol, index + 1);
+                            break None;
+                        }
+                        debug!(
+                            "Quote for slice {} is {} ms old, fetching a fresh one",
+                            index + 1,
+                            quoted_at.elapsed().as_millis()
+                        );
+                        refreshed = true;
+                        // Refreshing isn't a re-price
+                        attempt -= 1;
+                        continue;
+                    }
+
+                    let client_order_id = generate_client_order_id(&self.config.client_order_id_prefix);
+
+                    let request = OrderRequest {
+                        client_order_id: client_order_id.clone(),
+                        symbol: symbol.to_string(),
+                        side,
+                        order_type: OrderType::Limit,
+                        price: Some(limit_price),
+                        quantity: slice_qty,
+                        reduce_only: self.config.reduce_only,
+                        time_in_force: match self.config.expire_at_ms {
+                            _ if self.config.maker_only => TimeInForce::PostOnly,
+                            Some(expire_at_ms) if native_gtd => TimeInForce::Gtd { expire_at_ms },
+                            _ => TimeInForce::Gtc,
+                        },
+                        quantity_mode: QuantityMode::Base,
+                    };
+
+                    debug!("Placing slice {}: {} @ {}", index + 1, slice_qty, limit_price);
+
+                    let submitted_ms = now_millis();
+                    let placed = self.send_slice(adapter, credentials, &request).await;
+                    let crossed = match &placed {
+                        Ok(response) => is_post_only_reject(response),
+                        Err(e) => ExchangeError::is_post_only_rejected(e),
+                    };
+                    if self.config.maker_only && crossed && attempt <= self.config.post_only_retries as usize {
+                        debug!("Post-only slice {} would have crossed, re-pricing", index + 1);
+                        continue;
+                    }
+                    break Some((
+                        PendingSlice {
+                            index,
+                            client_order_id,
+                            quantity: slice_qty,
+                            limit_price,
+                            tolerance_bps,
+                            touch,
+                            arrival_ms,
+                            submitted_ms,
+                            _slot: slot,
+                        },
+                        placed,
+                    ));
+                };
+                // A skipped slice's size is left unfilled
+                let Some((slice, placed)) = sent else {
+                    filled_cleanly = false;
+                    filled_in_full = false;
+                    continue;
+                };
+
+                match placed {
+                    Ok(response) => {
+                        self.track_placed(adapter, credentials, &response);
+                        pending.push((slice, response));
+                    }
+                    Err(e) => {
+                        warn!("Slice {} failed: {}", index + 1, e);
+                        // Further slices would only hit the same wall
+                        if ExchangeError::is_maintenance(&e) {
+                            maintenance = Some(format!("{:#}", e));
+                        }
+                        reduce_only_rejected |= ExchangeError::is_reduce_only_rejected(&e);
+                        if ExchangeError::is_insufficient_margin(&e) || ExchangeError::is_risk_limit(&e) {
+                            margin_rejected = Some(format!("{:#}", e));
+                        }
+                        if ExchangeError::is_retryable(&e) {
+                            transient_failures = true;
+                        } else {
+                            terminal_failures = true;
+                        }
+                        filled_cleanly = false;
+                        filled_in_full = false;
+                        let result = SliceResult {
+                            index,
+                            client_order_id: slice.client_order_id,
+                            exchange_order_id: None,
+                            quantity: slice.quantity,
+                            price: slice.limit_price,
+                            tolerance_bps: Some(slice.tolerance_bps),
+                            filled_quantity: Decimal::ZERO,
+                            avg_fill_price: None,
+                            status: OrderStatus::Rejected,
+                            arrival_ms,
+                            submitted_ms: slice.submitted_ms,
+                            filled_ms: None,
+                        };
+                        result.log_timing(adapter.id(), symbol);
+                        results.push(result);
+                    }
+                }
+
+                index += 1;
+
+                // Wait between slices
+                if unplaced > Decimal::ZERO && maintenance.is_none() && !reduce_only_rejected && margin_rejected.is_none() {
+                    let interval = self.slice_interval();
+                    sleep(remaining_until(deadline).map_or(interval, |left| left.min(interval))).await;
+                }
+            }
+
+            let responses = pending.iter().map(|(_, response)| response.clone()).collect();
+            let slice_timeout = Duration::from_secs(self.config.slice_timeout_secs);
+            let orders = self
+                .await_completion(
+                    adapter,
+                    credentials,
+                    symbol,
+                    responses,
+                    remaining_until(cancel_at).map_or(slice_timeout, |left| left.min(slice_timeout)),
+                )
+                .await;
+
+            // Orders are done once awaited, so their slots go back as each is recorded
+            for (
+                PendingSlice {
+                    index,
+                    client_order_id,
+                    quantity,
+                    limit_price,
+                    tolerance_bps,
+                    touch,
+                    arrival_ms,
+                    submitted_ms,
+                    _slot,
+                },
+                order,
+            ) in pending.into_iter().map(|(slice, _)| slice).zip(orders)
+            {
+                let avg_fill_price = self
+                    .resolve_fill_price(adapter, credentials, symbol, &order, limit_price)
+                    .await;
+
+                // Every filled unit contributes its price, including partial
+                // fills on slices that were cancelled afterwards
+                total_filled += order.filled_quantity;
+                if let Some(avg_price) = avg_fill_price {
+                    weighted_price_sum += avg_price * order.filled_quantity;
+                    let fills = match self.config.fees_from_fills {
+                        true => self.fills(adapter, credentials, symbol, &order).await,
+                        false => None,
+                    };
+                    // Settlement asset fees count towards the total as they
+                    // come, others once converted below
+                    if let Some(fills) = fills {
+                        for fill in fills {
+                            let asset = fill.fee_asset.or_else(|| settlement_asset.clone());
+                            if asset == settlement_asset {
+                                total_fees += fill.fee;
+                            }
+                            if let Some(asset) = asset {
+                                *fees_by_asset.entry(asset).or_default() += fill.fee;
+                            }
+                        }
+                    } else {
+                        // Venues without fills fall back to the configured
+                        // rate, so fees from fills can be enabled everywhere.
+                        // Slices that may have crossed pay the taker rate.
+                        fees_estimated |= !order.filled_quantity.is_zero();
+                        let fee_rate = if self.config.maker_only { maker_fee_rate } else { taker_fee_rate };
+                        let traded = self.config.contract.settlement_value(order.filled_quantity, avg_price);
+                        total_fees += traded * fee_rate;
+                        if let Some(asset) = &settlement_asset {
+                            *fees_by_asset.entry(asset.clone()).or_default() += traded * fee_rate;
+                        }
+                    }
+                }
+                let through_touch = match (side, avg_fill_price) {
+                    (Side::Buy, Some(avg)) => avg > touch,
+                    (Side::Sell, Some(avg)) => avg < touch,
+                    (_, None) => true,
+                };
+                let filled_short = order.filled_quantity < quantity;
+                if filled_short || through_touch {
+                    filled_cleanly = false;
+                }
+                if filled_short {
+                    filled_in_full = false;
+                }
+
+                let result = SliceResult {
+                    index,
+                    client_order_id,
+                    exchange_order_id: Some(order.exchange_order_id.clone()),
+                    quantity,
+                    price: limit_price,
+                    tolerance_bps: Some(tolerance_bps),
+                    filled_quantity: order.filled_quantity,
+                    avg_fill_price,
+                    status: order.status,
+                    arrival_ms,
+                    submitted_ms,
+                    filled_ms: filled_at(&order),
+                };
+                result.log_timing(adapter.id(), symbol);
+                results.push(result);
+            }
+
+            if deadline.is_some_and(|d| Instant::now() >= d) && total_filled < total_quantity {
+                timed_out = true;
+            }
+            if aborted || timed_out || maintenance.is_some() || reduce_only_rejected || margin_rejected.is_some() {
+                break;
+            }
+
+            if let Some(ladder) = self.config.pricing_ladder.filter(|_| !filled_in_full) {
+                ladder_rung += 1;
+                debug!(
+                    "{} slices fell short, tolerance now {} bps",
+                    symbol,
+                    ladder.tolerance_bps(self.config.price_tolerance_bps, ladder_rung)
+                );
+            }
+
+            last_wave_clean = Some(filled_cleanly);
+            waves += 1;
+        }
+
+        results.sort_by_key(|r| r.index);
+
+        let avg_fill_price = if total_filled > Decimal::ZERO {
+            weighted_price_sum / total_filled
+        } else {
+            Decimal::ZERO
+        };
+
+        for (asset, fee) in &fees_by_asset {
+            if Some(asset) == settlement_asset.as_ref() || fee.is_zero() {
+                continue;
+            }
+            match self.fee_asset_value(adapter, symbol, assets.as_ref(), asset).await {
+                Ok(value) => total_fees += fee * value,
+                Err(e) => warn!("Leaving {} {} fees on {} out of the total: {:#}", fee, asset, symbol, e),
+            }
+        }
+
+        let is_complete = !timed_out && total_filled >= total_quantity * threshold;
+        let shortfall = (total_quantity - total_filled).max(Decimal::ZERO);
+
+        info!(
+            "Sliced order complete: filled {} / {} @ avg {}",
+            total_filled, total_quantity, avg_fill_price
+        );
+
+        Ok(SlicedOrderResult {
+            total_quantity,
+            filled_quantity: total_filled,
+            avg_fill_price,
+            slices: results,
+            total_fees,
+            fees_by_asset,
+            fees_estimated,
+            is_complete,
+            shortfall,
+            aborted,
+            timed_out,
+            retryable: transient_failures
+                && !terminal_failures
+                && !is_complete
+                && !aborted
+                && !timed_out
+                && maintenance.is_none()
+                && !reduce_only_rejected
+                && margin_rejected.is_none(),
+            maintenance,
+            reduce_only_rejected,
+            margin_rejected,
+        })
+    }
+
+    /// Work the order as one native `kind` algo order and follow it until it
+    /// finishes. It is cancelled on the kill switch or the total timeout,
+    /// keeping whatever its children filled. Children are held under the
+    /// slice notional cap at the limit price.
+    async fn execute_native_algo(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        credentials: &Credentials,
+        symbol: &str,
+        side: Side,
+        total_quantity: Decimal,
+        kind: AlgoKind,
+    ) -> Result<SlicedOrderResult> {
+        let arrival_ms = now_millis();
+        let Some((best_bid, best_ask, tolerance_bps)) = self.slice_quote(adapter, symbol, side, &mut None).await? else {
+            anyhow::bail!("No price to limit the {:?} order for {} at", kind, symbol);
+        };
+        let price_limit = calculate_limit_price(side, best_bid, best_ask, tolerance_bps);
+        let price_limit = self.config.price_rounding.round(price_limit, self.config.tick_size, side);
+        let price_limit = self.cap_cross(symbol, side, best_bid, best_ask, price_limit);
+
+        let quantity = total_quantity;
+        let slice_size = total_quantity * Decimal::try_from(self.config.slice_percent)?;
+        let slice_quantity = self.next_slice(total_quantity, price_limit, slice_size, total_quantity);
+
+        // Whole seconds, no closer together than the venue allows
+        let min_interval_secs = adapter.min_algo_interval_secs(kind);
+        let interval_secs = self.config.interval_ms.div_ceil(1000).max(min_interval_secs);
+        if kind == AlgoKind::Twap && interval_secs * 1000 != self.config.interval_ms {
+            warn!(
+                "TWAP interval of {} ms for {} on {} rounded up to {} s (venue minimum {} s)",
+                self.config.interval_ms,
+                symbol,
+                adapter.id(),
+                interval_secs,
+                min_interval_secs
+            );
+        }
+
+        let request = AlgoOrderRequest {
+            client_order_id: generate_client_order_id(&self.config.client_order_id_prefix),
+            symbol: symbol.to_string(),
+            side,
+            quantity,
+            kind,
+            slice_quantity,
+            interval_secs,
+            price_variance_bps: tolerance_bps,
+            price_limit,
+            reduce_only: self.config.reduce_only,
+        };
+        info!(
+            "Executing native {:?} order: {} {} {} in {} up to {}",
+            kind,
+            side_str(side),
+            quantity,
+            symbol,
+            slice_quantity,
+            price_limit
+        );
+
+        let mut aborted = false;
+        let mut timed_out = false;
+        let mut maintenance = None;
+        let mut reduce_only_rejected = false;
+        let submitted_ms = now_millis();
+        let mut order = match adapter.place_algo_order(credentials, &request).await {
+            Ok(order) => Some(order),
+            Err(e) if ExchangeError::is_maintenance(&e) => {
+                maintenance = Some(format!("{:#}", e));
+                None
+            }
+            Err(e) if ExchangeError::is_reduce_only_rejected(&e) => {
+                reduce_only_rejected = true;
+                None
+            }
+            Err(e) => return Err(e),
+        };
+
+        let deadline = earliest(
+            self.config
+                .total_timeout_secs
+                .map(|secs| Instant::now() + Duration::from_secs(secs)),
+            self.expiry(),
+        );
+        let poll_interval = Duration::from_millis(self.config.poll_interval_ms);
+        while let Some(current) = order.as_ref().filter(|order| !order.status.is_terminal()) {
+            let algo_id = current.exchange_order_id.clone();
+            sleep(poll_interval).await;
+
+            aborted = self.is_killed();
+            timed_out = !aborted && deadline.is_some_and(|d| Instant::now() >= d);
+            if aborted || timed_out {
+                warn!("Stopping native {:?} order {} for {}", kind, algo_id, symbol);
+                if let Err(e) = adapter.cancel_algo_order(credentials, symbol, &algo_id).await {
+                    warn!("Failed to cancel {:?} order {} for {}: {:#}", kind, algo_id, symbol, e);
+                }
+            }
+            match adapter.get_algo_order(credentials, symbol, &algo_id).await {
+                Ok(status) => order = Some(status),
+                Err(e) => warn!("Failed to poll {:?} order {} for {}: {:#}", kind, algo_id, symbol, e),
+            }
+            if aborted || timed_out {
+                break;
+            }
+        }
+
+        let filled = order.as_ref().map_or(Decimal::ZERO, |order| order.filled_quantity);
+        let avg_fill_price = order.as_ref().and_then(|order| order.avg_fill_price);
+        let threshold = self.config.completion_threshold;
+        // Children can cross, so they are charged at the taker rate
+        let (total_fees, fees_by_asset) =
+            self.taker_fees(adapter, symbol, filled, avg_fill_price.unwrap_or(price_limit));
+
+        info!(
+            "Native {:?} order complete: filled {} / {} @ avg {}",
+            kind,
+            filled,
+            total_quantity,
+            avg_fill_price.unwrap_or_default()
+        );
+
+        Ok(SlicedOrderResult {
+            total_quantity,
+            filled_quantity: filled,
+            avg_fill_price: avg_fill_price.unwrap_or_default(),
+            slices: vec![SliceResult {
+                index: 0,
+                client_order_id: request.client_order_id,
+                exchange_order_id: order.as_ref().map(|order| order.exchange_order_id.clone()),
+                quantity,
+                price: price_limit,
+                tolerance_bps: Some(tolerance_bps),
+                filled_quantity: filled,
+                avg_fill_price,
+                status: order.as_ref().map_or(OrderStatus::Rejected, |order| order.status),
+                arrival_ms,
+                submitted_ms,
+                filled_ms: order.as_ref().and_then(filled_at),
+            }],
+            total_fees,
+            fees_by_asset,
+            fees_estimated: !filled.is_zero(),
+            is_complete: !timed_out && filled >= total_quantity * threshold,
+            shortfall: (total_quantity - filled).max(Decimal::ZERO),
+            aborted,
+            timed_out,
+            maintenance,
+            reduce_only_rejected,
+            margin_rejected: None,
+            retryable: false,
+        })
+    }
+
+    /// Execute emergency exit, flattening `quantity` as reliably as possible.
+    ///
+    /// The first attempt is a reduce-only market order where the venue supports
+    /// it, otherwise an aggressive limit across the spread. Fills are verified
+    /// with `get_order`, and any unfilled remainder is re-submitted as a limit
+    /// priced progressively further through the book.
+    pub async fn execute_emergency_exit(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        credentials: &Credentials,
+        symbol: &str,
+        side: Side,
+        quantity: Decimal,
+    ) -> Result<SlicedOrderResult> {
+        info!(
+            "Executing EMERGENCY EXIT: {} {} {}",
+            side_str(side),
+            quantity,
+            symbol
+        );
+
+        let mut results = Vec::new();
+        let mut total_filled = Decimal::ZERO;
+        let mut weighted_price_sum = Decimal::ZERO;
+        let mut last_price = Decimal::ZERO;
+        let mut maintenance = None;
+        let mut reduce_only_rejected = false;
+        // A failed quote is priced off the last good one, so what already
+        // filled is never lost to a pricing error
+        let mut last_quote = None;
+
+        for attempt in 0..EMERGENCY_MAX_ATTEMPTS {
+            let remaining = quantity - total_filled;
+            if remaining <= Decimal::ZERO {
+                break;
+            }
+
+            let arrival_ms = now_millis();
+            let (best_bid, best_ask) = match (adapter.get_best_price(symbol).await, last_quote) {
+                (Ok(quote), _) => quote,
+                (Err(e), Some(quote)) => {
+                    warn!("Emergency exit attempt {} using the last quote: {:#}", attempt + 1, e);
+                    quote
+                }
+                (Err(e), None) => {
+                    warn!("Emergency exit attempt {} skipped, no quote: {:#}", attempt + 1, e);
+                    continue;
+                }
+            };
+            last_quote = Some((best_bid, best_ask));
+            let mut use_market = attempt == 0 && adapter.supports_reduce_only_market();
+
+            let mut aggressive_price = match self.emergency_price(side, best_bid, best_ask, attempt) {
+                Ok(price) => price,
+                Err(e) => {
+                    warn!("Emergency exit attempt {} skipped: {:#}", attempt + 1, e);
+                    continue;
+                }
+            };
+            if attempt == 0 {
+                if let Some(improved) = self.improved_exit_price(adapter, symbol, side, best_bid, best_ask).await {
+                    use_market = false;
+                    aggressive_price = improved;
+                }
+            }
+            last_price = aggressive_price;
+
+            let client_order_id = generate_client_order_id(&self.config.client_order_id_prefix);
+            let request = OrderRequest {
+                client_order_id: client_order_id.clone(),
+                symbol: symbol.to_string(),
+                side,
+                order_type: if use_market { OrderType::Market } else { OrderType::Limit },
+                price: if use_market { None } else { Some(aggressive_price) },
+                quantity: remaining,
+                reduce_only: true,
+                time_in_force: TimeInForce::Gtc,
+                quantity_mode: QuantityMode::Base,
+            };
+
+            let submitted_ms = now_millis();
+            let response = match adapter.place_order(credentials, &request).await {
+                Ok(response) => {
+                    self.track_placed(adapter, credentials, &response);
+                    response
+                }
+                Err(e) => {
+                    warn!("Emergency exit attempt {} failed: {}", attempt + 1, e);
+                    let result = SliceResult {
+                        index: attempt,
+                        client_order_id,
+                        exchange_order_id: None,
+                        quantity: remaining,
+                        price: aggressive_price,
+                        tolerance_bps: None,
+                        filled_quantity: Decimal::ZERO,
+                        avg_fill_price: None,
+                        status: OrderStatus::Rejected,
+                        arrival_ms,
+                        submitted_ms,
+                        filled_ms: None,
+                    };
+                    result.log_timing(adapter.id(), symbol);
+                    results.push(result);
+                    if ExchangeError::is_maintenance(&e) {
+                        maintenance = Some(format!("{:#}", e));
+                        break;
+                    }
+                    if ExchangeError::is_reduce_only_rejected(&e) {
+                        reduce_only_rejected = true;
+                        break;
+                    }
+                    continue;
+                }
+            };
+
+            let order = self
+                .await_completion(
+                    adapter,
+                    credentials,
+                    symbol,
+                    vec![response],
+                    Duration::from_secs(EMERGENCY_FILL_TIMEOUT_SECS),
+                )
+                .await
+                .remove(0);
+            let avg_fill_price = self
+                .resolve_fill_price(adapter, credentials, symbol, &order, aggressive_price)
+                .await;
+
+            total_filled += order.filled_quantity;
+            if let Some(avg_price) = avg_fill_price {
+                weighted_price_sum += avg_price * order.filled_quantity;
+            }
+
+            if order.filled_quantity < remaining {
+                warn!(
+                    "Emergency exit attempt {} filled {} / {}, retrying more aggressively",
+                    attempt + 1,
+                    order.filled_quantity,
+                    remaining
+                );
+            }
+
+            let result = SliceResult {
+                index: attempt,
+                client_order_id,
+                exchange_order_id: Some(order.exchange_order_id.clone()),
+                quantity: remaining,
+                price: aggressive_price,
+                tolerance_bps: None,
+                filled_quantity: order.filled_quantity,
+                avg_fill_price,
+                status: order.status,
+                arrival_ms,
+                submitted_ms,
+                filled_ms: filled_at(&order),
+            };
+            result.log_timing(adapter.id(), symbol);
+            results.push(result);
+        }
+
+        let avg_fill_price = if total_filled > Decimal::ZERO {
+            weighted_price_sum / total_filled
+        } else {
+            last_price
+        };
+        let (total_fees, fees_by_asset) = self.taker_fees(adapter, symbol, total_filled, avg_fill_price);
+
+        Ok(SlicedOrderResult {
+            total_quantity: quantity,
+            filled_quantity: total_filled,
+            avg_fill_price,
+            slices: results,
+            total_fees,
+            fees_by_asset,
+            fees_estimated: !total_filled.is_zero(),
+            is_complete: total_filled >= quantity,
+            shortfall: (quantity - total_filled).max(Decimal::ZERO),
+            aborted: false,
+            timed_out: false,
+            maintenance,
+            reduce_only_rejected,
+            margin_rejected: None,
+            retryable: false,
+        })
+    }
+
+    /// Fees on `filled` contracts traded at `avg_price` at the taker rate,
+    /// in the settlement asset and by asset
+    fn taker_fees(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        symbol: &str,
+        filled: Decimal,
+        avg_price: Decimal,
+    ) -> (Decimal, HashMap<String, Decimal>) {
+        let taker_fee_rate = Decimal::try_from(self.config.taker_fee_bps).unwrap_or_default() / dec!(10000);
+        let fees = self.config.contract.settlement_value(filled, avg_price) * taker_fee_rate;
+        let settlement_asset = symbol_assets(adapter, symbol).map(|(base, quote)| match self.config.contract.contract_type {
+            ContractType::Linear => quote,
+            ContractType::Inverse => base,
+        });
+        let fees_by_asset = settlement_asset
+            .filter(|_| !fees.is_zero())
+            .map(|asset| HashMap::from([(asset, fees)]))
+            .unwrap_or_default();
+        (fees, fees_by_asset)
+    }
+
+    /// `price` held to `max_cross_bps` past the opposite touch, on the tick
+    /// on the passive side of the cap
+    fn cap_cross(&self, symbol: &str, side: Side, best_bid: Decimal, best_ask: Decimal, price: Decimal) -> Decimal {
+        let Some(max_cross_bps) = self.config.max_cross_bps else {
+            return price;
+        };
+        let max_cross = Decimal::try_from(max_cross_bps / 10000.0).unwrap_or_default();
+        let cap = match side {
+            Side::Buy => best_ask * (Decimal::ONE + max_cross),
+            Side::Sell => best_bid * (Decimal::ONE - max_cross),
+        };
+        let cap = PriceRounding::Passive.round(cap, self.config.tick_size, side);
+        let past_cap = match side {
+            Side::Buy => price > cap,
+            Side::Sell => price < cap,
+        };
+        if !past_cap {
+            return price;
+        }
+        warn!(
+            "{} {} limit of {} would cross the book by more than {} bps, capping it at {}",
+            symbol,
+            side_str(side),
+            price,
+            max_cross_bps,
+            cap
+        );
+        cap
+    }
+
+    /// The new touch, when it has moved our way since the exit was decided
+    /// on, after waiting up to `emergency_improvement_ms`. Orders there fill
+    /// against the better level without crossing any further; if it moves
+    /// back the next attempt crosses as usual.
+    async fn improved_exit_price(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        symbol: &str,
+        side: Side,
+        best_bid: Decimal,
+        best_ask: Decimal,
+    ) -> Option<Decimal> {
+        if self.config.emergency_improvement_ms == 0 {
+            return None;
+        }
+        sleep(Duration::from_millis(self.config.emergency_improvement_ms).min(MAX_EMERGENCY_IMPROVEMENT_WAIT)).await;
+        let (bid, ask) = match adapter.get_best_price(symbol).await {
+            Ok(quote) => quote,
+            Err(e) => {
+                debug!("Re-quote for the emergency exit on {} failed: {}", symbol, e);
+                return None;
+            }
+        };
+        let improved = match side {
+            Side::Buy => (ask < best_ask).then_some(ask),
+            Side::Sell => (bid > best_bid).then_some(bid),
+        }?;
+        info!(
+            "Book for {} improved from {} / {} to {} / {}, exiting at {} without crossing",
+            symbol, best_bid, best_ask, bid, ask, improved
+        );
+        Some(improved)
+    }
+
+    /// Aggressive limit price for an emergency exit attempt
+    pub fn emergency_price(
+        &self,
+        side: Side,
+        best_bid: Decimal,
+        best_ask: Decimal,
+        attempt: usize,
+    ) -> Result<Decimal> {
+        let configured = Decimal::try_from(self.config.emergency_cross_bps)?;
+        let max_offset = Decimal::try_from(self.config.emergency_max_cross_bps)?;
+
+        // Wide books are usually thin or volatile, so cross by at least the spread
+        let mid = (best_bid + best_ask) / Decimal::TWO;
+        let spread_bps = if mid > Decimal::ZERO {
+            (best_ask - best_bid) / mid * dec!(10000)
+        } else {
+            Decimal::ZERO
+        };
+
+        let offset_bps = (configured.max(spread_bps) * Decimal::from(1u64 << attempt)).min(max_offset);
+        let offset = offset_bps / dec!(10000);
+        let price = match side {
+            Side::Buy => best_ask * (Decimal::ONE + offset),
+            Side::Sell => best_bid * (Decimal::ONE - offset),
+        };
+        let price = PriceRounding::Aggressive.round(price, self.config.tick_size, side);
+
+        if price <= Decimal::ZERO {
+            anyhow::bail!(
+                "Emergency price {} is not positive (bid {}, ask {}, offset {} bps)",
+                price, best_bid, best_ask, offset_bps
+            );
+        }
+        Ok(price)
+    }
+
+    /// Poll resting orders until they all reach a terminal state, cancelling
+    /// any still open once `timeout` has elapsed or the kill switch is set.
+    /// Orders are returned in the order they were given.
+    async fn await_completion(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        credentials: &Credentials,
+        symbol: &str,
+        mut orders: Vec<OrderResponse>,
+        timeout: Duration,
+    ) -> Vec<OrderResponse> {
+        let deadline = Instant::now() + timeout;
+        let poll_interval = Duration::from_millis(self.config.poll_interval_ms);
+        let mut backoff = 1;
+
+        loop {
+            self.track_updates(&orders);
+            let open: Vec<String> = orders
+                .iter()
+                .filter(|o| !o.status.is_terminal())
+                .map(|o| o.exchange_order_id.clone())
+                .collect();
+            if open.is_empty() {
+                break;
+            }
+
+            if Instant::now() >= deadline || self.is_killed() {
+                debug!("{} slices timed out or aborted, cancelling", open.len());
+                if let Err(e) = self.cancel_slices(adapter, credentials, symbol, &open).await {
+                    warn!("Failed to cancel slices: {:#}", e);
+                }
+
+                // Cancel responses don't reliably carry fill info, so re-read the orders
+                match adapter.get_orders_batch(credentials, symbol, &open).await {
+                    Ok(latest) => merge_order_updates(&mut orders, latest),
+                    Err(e) => warn!("Failed to fetch cancelled slices: {}", e),
+                }
+                for order in orders.iter_mut().filter(|o| !o.status.is_terminal()) {
+                    order.status = OrderStatus::Cancelled;
+                }
+                self.track_updates(&orders);
+                break;
+            }
+
+            sleep(poll_interval * backoff).await;
+            match adapter.get_orders_batch(credentials, symbol, &open).await {
+                Ok(latest) => {
+                    merge_order_updates(&mut orders, latest);
+                    backoff = 1;
+                }
+                Err(e) => {
+                    // Back off so a failing or rate-limiting exchange isn't hammered
+                    warn!("Failed to poll {} slices: {}", open.len(), e);
+                    backoff = (backoff * 2).min(MAX_POLL_BACKOFF);
+                }
+            }
+        }
+
+        orders
+    }
+
+    /// Cancel the `open` slices, by mass cancel once killed and by id if the
+    /// venue has no mass cancel or it fails
+    async fn cancel_slices(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        credentials: &Credentials,
+        symbol: &str,
+        open: &[String],
+    ) -> Result<()> {
+        if self.is_killed() {
+            match adapter.cancel_all_orders(credentials, symbol).await {
+                Ok(()) => return Ok(()),
+                Err(e) => debug!("Mass cancel failed, cancelling slices by id: {:#}", e),
+            }
+        }
+        adapter.cancel_orders_batch(credentials, symbol, open).await
+    }
+
+    /// Fills of a filled slice, `None` where the venue doesn't report them
+    async fn fills(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        credentials: &Credentials,
+        symbol: &str,
+        order: &OrderResponse,
+    ) -> Option<Vec<Fill>> {
+        match adapter.get_fills(credentials, symbol, &order.exchange_order_id).await {
+            Ok(fills) if !fills.is_empty() => Some(fills),
+            Ok(_) => None,
+            Err(e) => {
+                debug!("No fills for {}, estimating its fees: {}", order.exchange_order_id, e);
+                None
+            }
+        }
+    }
+
+    /// Value of one unit of fee `asset` in the contract's settlement asset,
+    /// at the current mid of `symbol` or of the asset's own market against
+    /// the quote asset on the same venue
+    async fn fee_asset_value(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        symbol: &str,
+        assets: Option<&(String, String)>,
+        asset: &str,
+    ) -> Result<Decimal> {
+        let Some((base, quote)) = assets else {
+            anyhow::bail!("{} has no known base and quote assets", symbol);
+        };
+        let mid = |(bid, ask): (Decimal, Decimal)| (bid + ask) / Decimal::TWO;
+        let symbol_mid = mid(adapter.get_best_price(symbol).await?);
+        let in_quote = if asset == quote {
+            Decimal::ONE
+        } else if asset == base {
+            symbol_mid
+        } else {
+            mid(adapter.get_best_price(&adapter.to_native_symbol(asset, quote)).await?)
+        };
+        match self.config.contract.contract_type {
+            ContractType::Linear => Ok(in_quote),
+            ContractType::Inverse if symbol_mid.is_zero() => anyhow::bail!("No price for {}", symbol),
+            ContractType::Inverse => Ok(in_quote / symbol_mid),
+        }
+    }
+
+    /// Determine the price that a slice's fills should be weighted at.
+    ///
+    /// Place and cancel responses often omit the average price, so it is fetched
+    /// via `get_order` when missing. Venues whose orders never carry it are
+    /// asked for the order's individual fills and their volume-weighted price.
+    /// Failing that, the limit price is used since fills can't be worse.
+    async fn resolve_fill_price(
+        &self,
+        adapter: &dyn ExchangeAdapter,
+        credentials: &Credentials,
+        symbol: &str,
+        order: &OrderResponse,
+        limit_price: Decimal,
+    ) -> Option<Decimal> {
+        if order.filled_quantity <= Decimal::ZERO {
+            return order.avg_fill_price;
+        }
+
+        if let Some(price) = order.avg_fill_price.filter(|p| *p > Decimal::ZERO) {
+            return Some(price);
+        }
+
+        match adapter.get_order(credentials, symbol, &order.exchange_order_id).await {
+            Ok(latest) => {
+                if let Some(price) = latest.avg_fill_price.filter(|p| *p > Decimal::ZERO) {
+                    return Some(price);
+                }
+            }
+            Err(e) => warn!("Failed to fetch fill price for {}: {}", order.exchange_order_id, e),
+        }
+
+        match adapter.get_fills(credentials, symbol, &order.exchange_order_id).await {
+            Ok(fills) => {
+                let quantity: Decimal = fills.iter().map(|f| f.quantity).sum();
+                if quantity > Decimal::ZERO {
+                    let notional: Decimal = fills.iter().map(|f| f.price * f.quantity).sum();
+                    return Some(notional / quantity);
+                }
+            }
+            Err(e) => debug!("No fills for {}: {}", order.exchange_order_id, e),
+        }
+
+        warn!(
+            "No fill price reported for {}, using limit price {}",
+            order.exchange_order_id, limit_price
+        );
+        Some(limit_price)
+    }
+}
+
+/// Base and quote assets of `symbol`, canonical or native
+fn symbol_assets(adapter: &dyn ExchangeAdapter, symbol: &str) -> Option<(String, String)> {
+    let canonical = adapter.to_canonical_symbol(&adapter.native_symbol(symbol))?;
+    let (base, quote) = parse_canonical_symbol(&canonical)?;
+    Some((base.to_string(), quote.to_string()))
+}
+
+/// Scale `tolerance_bps` by how far the top-of-book sizes lean against `side`.
+/// A buy facing a heavy bid and thin ask pays up to twice the tolerance
+/// before the price moves away; facing a heavy ask it waits at the bid.
+fn imbalance_tolerance_bps(side: Side, bid_size: Decimal, ask_size: Decimal, tolerance_bps: f64) -> f64 {
+    let total = bid_size + ask_size;
+    if total <= Decimal::ZERO {
+        return tolerance_bps;
+    }
+    let imbalance = ((bid_size - ask_size) / total).to_f64().unwrap_or(0.0);
+    let against = match side {
+        Side::Buy => imbalance,
+        Side::Sell => -imbalance,
+    };
+    tolerance_bps * (1.0 + against)
+}
+
+/// Price a maker-only slice, pulling it back to the touch if the tolerance
+/// would otherwise cross the spread
+fn calculate_maker_price(
+    side: Side,
+    best_bid: Decimal,
+    best_ask: Decimal,
+    tolerance_bps: f64,
+) -> Decimal {
+    let price = calculate_limit_price(side, best_bid, best_ask, tolerance_bps);
+    match side {
+        Side::Buy if price >= best_ask => best_bid,
+        Side::Sell if price <= best_bid => best_ask,
+        _ => price,
+    }
+}
+
+/// `price` moved `ticks` ticks away from the book: down for a buy, up for a
+/// sell. Without a known tick size the price is left as it is.
+fn step_back(side: Side, price: Decimal, tick_size: Decimal, ticks: usize) -> Decimal {
+    let step = tick_size * Decimal::from(ticks);
+    match side {
+        Side::Buy => (price - step).max(tick_size),
+        Side::Sell => price + step,
+    }
+}
+
+/// Whether a post-only order was killed by the exchange for crossing the book
+fn is_post_only_reject(order: &OrderResponse) -> bool {
+    order.filled_quantity.is_zero()
+        && matches!(order.status, OrderStatus::Rejected | OrderStatus::Expired)
+}
+
+/// The sooner of two optional deadlines
+fn earliest(a: Option<Instant>, b: Option<Instant>) -> Option<Instant> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Time left before an optional deadline, zero once it has passed
+fn remaining_until(deadline: Option<Instant>) -> Option<Duration> {
+    deadline.map(|d| d.saturating_duration_since(Instant::now()))
+}
+
+/// Replace tracked orders with any newer state reported by the exchange
+fn merge_order_updates(orders: &mut [OrderResponse], latest: Vec<OrderResponse>) {
+    for update in latest {
+        if let Some(order) = orders
+            .iter_mut()
+            .find(|o| o.exchange_order_id == update.exchange_order_id)
+        {
+            *order = update;
+        }
+    }
+}
+
+/// Calculate limit price with tolerance
+pub fn calculate_limit_price(
+    side: Side,
+    best_bid: Decimal,
+    best_ask: Decimal,
+    tolerance_bps: f64,
+) -> Decimal {
+    let tolerance = Decimal::try_from(tolerance_bps / 10000.0).unwrap();
+
+    match side {
+        Side::Buy => {
+            // For buys, place slightly above best bid to increase fill probability
+            best_bid * (Decimal::ONE + tolerance)
+        }
+        Side::Sell => {
+            // For sells, place slightly below best ask
+            best_ask * (Decimal::ONE - tolerance)
+        }
+    }
+}
+
+fn side_str(side: Side) -> &'static str {
+    match side {
+        Side::Buy => "BUY",
+        Side::Sell => "SELL",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithm::SliceInstruction;
+    use crate::exchange::mock::{credentials, response_for, MockAdapter, MOCK_MIN_TWAP_INTERVAL_SECS};
+
+    #[test]
+    fn test_calculate_slices() {
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.1, // 10%
+            ..Default::default()
+        });
+
+        let slices = slicer.calculate_slices(dec!(1.0), Decimal::ZERO);
+        assert_eq!(slices.len(), 10);
+        assert!(slices.iter().all(|s| *s == dec!(0.1)));
+    }
+
+    #[test]
+    fn test_calculate_slices_remainder() {
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.3, // 30%
+            ..Default::default()
+        });
+
+        let slices = slicer.calculate_slices(dec!(1.0), Decimal::ZERO);
+        assert_eq!(slices.len(), 4);
+        // 0.3 + 0.3 + 0.3 + 0.1 = 1.0
+    }
+
+    #[test]
+    fn test_jittered_slices_sum_to_total_within_bounds() {
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.1,
+            size_jitter_percent: 0.2,
+            interval_ms: 100,
+            interval_jitter_percent: 0.5,
+            jitter_seed: Some(7),
+            ..Default::default()
+        });
+
+        let slices = slicer.calculate_slices(dec!(1.0), Decimal::ZERO);
+        assert_eq!(slices.iter().sum::<Decimal>(), dec!(1.0));
+        // Every slice but the last, which takes the remainder, is within ±20% of 0.1
+        let (last, jittered) = slices.split_last().unwrap();
+        assert!(jittered.iter().all(|s| *s >= dec!(0.08) && *s <= dec!(0.12)));
+        assert!(jittered.windows(2).any(|pair| pair[0] != pair[1]));
+        assert!(*last > Decimal::ZERO && *last < dec!(0.12) + MIN_SLICE_SIZE);
+
+        for _ in 0..100 {
+            let interval = slicer.slice_interval();
+            assert!(interval >= Duration::from_millis(50) && interval <= Duration::from_millis(150));
+        }
+
+        // The same seed slices the same way
+        let replay = OrderSlicer::new(slicer.config.clone());
+        assert_eq!(replay.calculate_slices(dec!(1.0), Decimal::ZERO), slices);
+    }
+
+    #[test]
+    fn test_calculate_slices_notional_cap() {
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.5,
+            max_slice_notional_usd: Some(20.0),
+            ..Default::default()
+        });
+
+        // $20 at 100 is 0.2 coins per slice
+        let slices = slicer.calculate_slices(dec!(1.0), dec!(100));
+        assert_eq!(slices.len(), 5);
+        assert!(slices.iter().all(|s| *s == dec!(0.2)));
+
+        // Without a price the cap cannot be applied
+        assert_eq!(slicer.calculate_slices(dec!(1.0), Decimal::ZERO).len(), 2);
+    }
+
+    #[test]
+    fn test_jitter_stays_within_the_notional_cap() {
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.5,
+            max_slice_notional_usd: Some(20.0),
+            size_jitter_percent: 0.5,
+            jitter_seed: Some(7),
+            ..Default::default()
+        });
+
+        let slices = slicer.calculate_slices(dec!(1.0), dec!(100));
+        assert_eq!(slices.iter().sum::<Decimal>(), dec!(1.0));
+        assert!(slices.iter().all(|s| *s <= dec!(0.2)), "{:?}", slices);
+        assert!(slices.iter().any(|s| *s < dec!(0.2)), "{:?}", slices);
+    }
+
+    #[tokio::test]
+    async fn test_pricing_ladder_escalates_after_unfilled_slices() {
+        // The first two slices go unfilled, the rest fill
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101)).with_place_handler(|index, request| {
+            Ok(match index {
+                0 | 1 => response_for(request, OrderStatus::Cancelled, Decimal::ZERO, None),
+                _ => response_for(request, OrderStatus::Filled, request.quantity, request.price),
+            })
+        });
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.25,
+            interval_ms: 0,
+            price_tolerance_bps: 5.0,
+            pricing_ladder: Some(PricingLadder {
+                step_bps: 100.0,
+                max_bps: 150.0,
+            }),
+            ..Default::default()
+        });
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100.5))
+            .await
+            .unwrap();
+
+        // Climbs a step after each miss, stops at the cap, and holds once fills resume
+        let tolerances: Vec<_> = result.slices.iter().map(|s| s.tolerance_bps).collect();
+        assert_eq!(tolerances, [Some(5.0), Some(105.0), Some(150.0), Some(150.0)]);
+        // 150 bps over the bid crosses the 101 ask
+        assert_eq!(result.slices[2].price, dec!(101.5));
+    }
+
+    #[tokio::test]
+    async fn test_limit_far_past_the_touch_is_capped() {
+        // A 500 bps tolerance puts the buy limit at 105 against a 101 ask
+        let config = |max_cross_bps| SlicingConfig {
+            slice_percent: 1.0,
+            interval_ms: 0,
+            price_tolerance_bps: 500.0,
+            max_cross_bps,
+            tick_size: dec!(0.01),
+            ..SlicingConfig::default()
+        };
+        let place = |config, side| async move {
+            let adapter = MockAdapter::new("mock", dec!(100), dec!(101));
+            OrderSlicer::new(config)
+                .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", side, dec!(1), dec!(100.5))
+                .await
+                .unwrap();
+            adapter.placed()[0].price.unwrap()
+        };
+
+        // Held to 50 bps past the ask, or the bid for a sell, on the passive tick
+        assert_eq!(place(config(Some(50.0)), Side::Buy).await, dec!(101.5));
+        assert_eq!(place(config(Some(50.0)), Side::Sell).await, dec!(99.5));
+        // Callers that mean to cross can opt out
+        assert_eq!(place(config(None), Side::Buy).await, dec!(105));
+        // Limits inside the cap are left alone
+        let inside = SlicingConfig {
+            price_tolerance_bps: 5.0,
+            ..config(Some(50.0))
+        };
+        assert_eq!(place(inside, Side::Buy).await, dec!(100.05));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_native_algo_order_replaces_slicing_where_supported() {
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.25,
+            interval_ms: 10_000,
+            tick_size: dec!(0.01),
+            reduce_only: true,
+            taker_fee_bps: 5.0,
+            native_algo: Some(AlgoKind::Twap),
+            ..SlicingConfig::default()
+        });
+
+        let native = MockAdapter::new("mock", dec!(100), dec!(101)).with_algo_orders();
+        let result = slicer
+            .execute_sliced_order(&native, &credentials(), "BTCUSDT", Side::Buy, dec!(4), dec!(100.5))
+            .await
+            .unwrap();
+
+        // One TWAP of quarter-size children every 10 s, limited where a slice would be
+        assert!(native.placed().is_empty());
+        let algo_orders = native.algo_orders();
+        assert_eq!(algo_orders.len(), 1);
+        assert_eq!(algo_orders[0].kind, AlgoKind::Twap);
+        assert_eq!(algo_orders[0].quantity, dec!(4));
+        assert_eq!(algo_orders[0].slice_quantity, dec!(1));
+        assert_eq!(algo_orders[0].interval_secs, 10);
+        assert_eq!(algo_orders[0].price_limit, dec!(100.05));
+        assert!(algo_orders[0].reduce_only);
+        assert!(result.is_complete);
+        assert_eq!(result.filled_quantity, dec!(4));
+        assert_eq!(result.avg_fill_price, dec!(100.05));
+        // Children may cross, so fees are estimated at the taker rate
+        assert!(result.fees_estimated);
+        assert_eq!(result.total_fees, dec!(4) * dec!(100.05) * dec!(0.0005));
+        assert_eq!(result.fees_by_asset["USDT"], result.total_fees);
+        assert_eq!(result.slices.len(), 1);
+        assert_eq!(result.slices[0].exchange_order_id.as_deref(), Some("mock-algo-0"));
+
+        // Intervals go out in whole seconds, no shorter than the venue takes
+        for (interval_ms, interval_secs) in [(12_500, 13), (1_000, MOCK_MIN_TWAP_INTERVAL_SECS)] {
+            let native = MockAdapter::new("mock", dec!(100), dec!(101)).with_algo_orders();
+            OrderSlicer::new(SlicingConfig { interval_ms, ..slicer.config.clone() })
+                .execute_sliced_order(&native, &credentials(), "BTCUSDT", Side::Buy, dec!(4), dec!(100.5))
+                .await
+                .unwrap();
+            assert_eq!(native.algo_orders()[0].interval_secs, interval_secs, "{} ms", interval_ms);
+        }
+
+        // Venues without an algo engine are sliced here
+        let sliced = MockAdapter::new("mock", dec!(100), dec!(101));
+        let result = slicer
+            .execute_sliced_order(&sliced, &credentials(), "BTCUSDT", Side::Buy, dec!(4), dec!(100.5))
+            .await
+            .unwrap();
+        assert!(result.is_complete);
+        assert_eq!(sliced.placed().len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_slices_below_min_notional_are_merged() {
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.05,
+            quantity_step: dec!(0.001),
+            min_notional: dec!(20),
+            interval_ms: 0,
+            ..Default::default()
+        });
+
+        // 5% slices are worth $5 at 100, so each is raised to $20 plus
+        // headroom and the short remainder joins the last one
+        let slices = slicer.calculate_slices(dec!(1.0), dec!(100));
+        assert_eq!(slices, [dec!(0.202), dec!(0.202), dec!(0.202), dec!(0.394)]);
+        assert!(slices.iter().all(|s| *s * dec!(100) >= dec!(20)));
+
+        // An order worth less than the minimum is refused outright
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(100));
+        let err = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(0.1), dec!(100))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("below the venue's 20 minimum notional"), "{}", err);
+        assert!(adapter.placed().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_partial_fill_then_cancel_is_weighted() {
+        // Second slice fills 0.2 of 0.5 at 110 and is cancelled; the place
+        // response carries no average price so it has to be fetched
+        let adapter = MockAdapter::new("mock", dec!(99.9), dec!(100))
+            .with_place_handler(|index, request| {
+                Ok(match index {
+                    0 => response_for(request, OrderStatus::Filled, request.quantity, Some(dec!(100))),
+                    _ => response_for(request, OrderStatus::Cancelled, dec!(0.2), Some(dec!(110))),
+                })
+            })
+            .hide_avg_on_place();
+
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.5,
+            interval_ms: 0,
+            ..Default::default()
+        });
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1.0), dec!(100))
+            .await
+            .unwrap();
+
+        assert_eq!(result.filled_quantity, dec!(0.7));
+        assert_eq!(result.slices[1].avg_fill_price, Some(dec!(110)));
+        // (0.5 * 100 + 0.2 * 110) / 0.7
+        assert_eq!(result.avg_fill_price, dec!(72) / dec!(0.7));
+        assert!(!result.is_complete);
+    }
+
+    #[tokio::test]
+    async fn test_fill_price_is_weighted_across_fills() {
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101)).with_fills(|_, request| {
+            vec![
+                Fill { price: dec!(100), quantity: request.quantity * dec!(0.4), fee: Decimal::ZERO, fee_asset: None },
+                Fill { price: dec!(101), quantity: request.quantity * dec!(0.6), fee: Decimal::ZERO, fee_asset: None },
+            ]
+        });
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.5,
+            interval_ms: 0,
+            ..SlicingConfig::default()
+        });
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100.5))
+            .await
+            .unwrap();
+
+        assert_eq!(result.slices.len(), 2);
+        for slice in &result.slices {
+            assert_eq!(slice.avg_fill_price, Some(dec!(100.6)));
+        }
+        assert_eq!(result.avg_fill_price, dec!(100.6));
+    }
+
+    #[tokio::test]
+    async fn test_fees_are_kept_by_asset_and_totalled_in_quote() {
+        let fill = |quantity, fee, fee_asset: Option<&str>| Fill {
+            price: dec!(100),
+            quantity,
+            fee,
+            fee_asset: fee_asset.map(str::to_string),
+        };
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101)).with_fills(move |index, request| match index {
+            // A fee in the quote asset, one left to the settlement asset and a BNB discount
+            0 => vec![
+                fill(request.quantity / dec!(2), dec!(0.02), Some("USDT")),
+                fill(request.quantity / dec!(2), dec!(0.01), None),
+            ],
+            _ => vec![
+                fill(request.quantity / dec!(2), dec!(0.0002), Some("BNB")),
+                fill(request.quantity / dec!(2), dec!(0.0001), Some("BTC")),
+            ],
+        });
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.5,
+            interval_ms: 0,
+            fees_from_fills: true,
+            ..SlicingConfig::default()
+        });
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100.5))
+            .await
+            .unwrap();
+
+        assert_eq!(result.fees_by_asset.len(), 3);
+        assert_eq!(result.fees_by_asset["USDT"], dec!(0.03));
+        assert_eq!(result.fees_by_asset["BNB"], dec!(0.0002));
+        assert_eq!(result.fees_by_asset["BTC"], dec!(0.0001));
+        // The mock quotes every market at a 100.5 mid
+        assert_eq!(result.total_fees, dec!(0.03) + dec!(0.0003) * dec!(100.5));
+        assert!(!result.fees_estimated);
+    }
+
+    #[tokio::test]
+    async fn test_client_order_ids_carry_the_configured_prefix() {
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101));
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.5,
+            interval_ms: 0,
+            client_order_id_prefix: "x-Ab12Cd34".to_string(),
+            ..SlicingConfig::default()
+        });
+
+        slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100.5))
+            .await
+            .unwrap();
+
+        let placed = adapter.placed();
+        assert_eq!(placed.len(), 2);
+        assert!(placed.iter().all(|order| order.client_order_id.starts_with("x-Ab12Cd34")), "{:?}", placed);
+        assert_ne!(placed[0].client_order_id, placed[1].client_order_id);
+    }
+
+    #[tokio::test]
+    async fn test_fees_fall_back_to_the_configured_rate_without_fills() {
+        // The mock can't report fills, so every slice's fees are estimated
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101));
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.5,
+            interval_ms: 0,
+            fees_from_fills: true,
+            maker_fee_bps: 2.0,
+            taker_fee_bps: 5.0,
+            ..SlicingConfig::default()
+        });
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100.5))
+            .await
+            .unwrap();
+
+        // Both slices filled at 100.05, charged the taker rate
+        assert!(result.is_complete);
+        assert!(result.fees_estimated);
+        assert_eq!(result.total_fees, dec!(100.05) * dec!(0.0005));
+        assert_eq!(result.fees_by_asset["USDT"], result.total_fees);
+
+        // Estimated the same way when fills aren't asked for
+        let slicer = OrderSlicer::new(SlicingConfig { fees_from_fills: false, ..slicer.config.clone() });
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100.5))
+            .await
+            .unwrap();
+        assert_eq!(result.total_fees, dec!(100.05) * dec!(0.0005));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_price_failures_reuse_a_fresh_quote_or_skip_the_slice() {
+        // Every fetch for the second slice fails, retries included
+        let adapter = || MockAdapter::new("mock", dec!(100), dec!(101)).with_price_failures(|call| (1..4).contains(&call));
+        let config = |on_price_failure, max_quote_age_ms| SlicingConfig {
+            slice_percent: 0.25,
+            interval_ms: 0,
+            on_price_failure,
+            max_quote_age_ms,
+            ..SlicingConfig::default()
+        };
+        let execute = |adapter: MockAdapter, config| async move {
+            OrderSlicer::new(config)
+                .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100.5))
+                .await
+                .map(|result| (result, adapter.placed()))
+        };
 
-        let slice_result = SliceResult {
-            index: 0,
-            client_order_id,
-            exchange_order_id: Some(response.exchange_order_id),
-            quantity,
-            price: aggressive_price,
-            filled_quantity: response.filled_quantity,
-            avg_fill_price: response.avg_fill_price,
-            status: response.status,
+        let err = execute(adapter(), config(PriceFailure::Abort, 1000)).await.unwrap_err();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+
+        let (result, placed) = execute(adapter(), config(PriceFailure::Continue, 60_000)).await.unwrap();
+        assert_eq!(placed.len(), 4);
+        assert!(placed.iter().all(|order| order.price == placed[0].price));
+        assert!(result.is_complete);
+
+        // Retrying the failed fetches ages the last quote past a millisecond
+        let (result, placed) = execute(adapter(), config(PriceFailure::Continue, 1)).await.unwrap();
+        assert_eq!(placed.len(), 3);
+        assert_eq!(result.filled_quantity, dec!(0.75));
+        assert_eq!(result.shortfall, dec!(0.25));
+        assert!(!result.is_complete);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_aged_quote_is_refreshed_before_the_slice_goes_out() {
+        // The other leg's book takes 1.5 s to read, aging the quote past a second
+        let execute = |adapter: Arc<MockAdapter>, other: Arc<MockAdapter>| async move {
+            let slicer = OrderSlicer::new(SlicingConfig {
+                slice_percent: 1.0,
+                max_quote_age_ms: 1000,
+                ..SlicingConfig::default()
+            })
+            .with_spread_target(SpreadTarget {
+                adapter: other,
+                symbol: "BTCUSDT".to_string(),
+                target_spread_bps: dec!(50),
+            });
+            slicer
+                .execute_sliced_order(adapter.as_ref(), &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100.5))
+                .await
+        };
+        let legs = || {
+            (
+                Arc::new(MockAdapter::new("long", dec!(100), dec!(101))),
+                Arc::new(MockAdapter::new("short", dec!(110), dec!(111)).with_price_delay(Duration::from_millis(1500))),
+            )
         };
 
-        Ok(SlicedOrderResult {
-            total_quantity: quantity,
-            filled_quantity: response.filled_quantity,
-            avg_fill_price: response.avg_fill_price.unwrap_or(aggressive_price),
-            slices: vec![slice_result],
-            total_fees: Decimal::ZERO,
-            is_complete: response.status == OrderStatus::Filled,
+        // The price moved while the quote aged, and the re-read is quick
+        let (refreshed, other) = legs();
+        let moved = async {
+            sleep(Duration::from_millis(1000)).await;
+            refreshed.set_prices(dec!(100.2), dec!(101.2));
+            other.set_price_delay(Duration::ZERO);
+        };
+        let (result, _) = tokio::join!(execute(refreshed.clone(), other.clone()), moved);
+        assert!(result.unwrap().is_complete);
+        let placed = refreshed.placed();
+        assert_eq!(placed.len(), 1);
+        assert_eq!(placed[0].price, Some(calculate_limit_price(Side::Buy, dec!(100.2), dec!(101.2), 5.0)));
+
+        // Stale again after the refresh, no slice
+        let (stale, other) = legs();
+        let result = execute(stale.clone(), other).await.unwrap();
+        assert!(stale.placed().is_empty());
+        assert_eq!(result.shortfall, dec!(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_other_leg_price_failures_follow_the_price_failure_policy() {
+        // The other leg's second slice fetches fail, retries included
+        let execute = |on_price_failure, max_quote_age_ms| async move {
+            let adapter = MockAdapter::new("long", dec!(100), dec!(101));
+            let other = MockAdapter::new("short", dec!(110), dec!(111)).with_price_failures(|call| (1..4).contains(&call));
+            let slicer = OrderSlicer::new(SlicingConfig {
+                slice_percent: 0.5,
+                interval_ms: 0,
+                on_price_failure,
+                max_quote_age_ms,
+                ..SlicingConfig::default()
+            })
+            .with_spread_target(SpreadTarget {
+                adapter: Arc::new(other),
+                symbol: "BTCUSDT".to_string(),
+                target_spread_bps: dec!(50),
+            });
+            let result = slicer
+                .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100.5))
+                .await;
+            result.map(|result| (result, adapter.placed().len()))
+        };
+
+        let err = execute(PriceFailure::Abort, 60_000).await.unwrap_err();
+        assert!(format!("{:#}", err).contains("No price for the other leg on short"), "{:#}", err);
+
+        let (result, placed) = execute(PriceFailure::Continue, 60_000).await.unwrap();
+        assert_eq!(placed, 2);
+        assert!(result.is_complete);
+
+        // Too old to reuse after the retries, so the slice is skipped
+        let (result, placed) = execute(PriceFailure::Continue, 1).await.unwrap();
+        assert_eq!(placed, 1);
+        assert_eq!(result.shortfall, dec!(0.5));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_spread_target_hold_gives_up_its_slot_and_times_out() {
+        // The other leg's bid never clears the target
+        let adapter = MockAdapter::new("long", dec!(100), dec!(101));
+        let limits = Arc::new(OpenOrderLimits::new(1));
+        let account = Uuid::new_v4();
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 1.0,
+            spread_target_wait_ms: 5_000,
+            ..SlicingConfig::default()
         })
+        .with_open_order_limits(limits.clone(), account)
+        .with_spread_target(SpreadTarget {
+            adapter: Arc::new(MockAdapter::new("short", dec!(100.5), dec!(101.5))),
+            symbol: "BTCUSDT".to_string(),
+            target_spread_bps: dec!(50),
+        });
+
+        let credentials = credentials();
+        let held = async {
+            sleep(Duration::from_millis(1_000)).await;
+            limits.try_acquire("long", account, "BTCUSDT").is_some()
+        };
+        let (result, slot_free) = tokio::join!(
+            slicer.execute_sliced_order(&adapter, &credentials, "BTCUSDT", Side::Buy, dec!(1), dec!(100.5)),
+            held
+        );
+
+        assert!(slot_free);
+        let result = result.unwrap();
+        assert!(adapter.placed().is_empty());
+        assert!(result.timed_out);
+        assert!(!result.is_complete);
+        assert_eq!(result.shortfall, dec!(1));
     }
-}
 
-/// Calculate limit price with tolerance
-fn calculate_limit_price(
-    side: Side,
-    best_bid: Decimal,
-    best_ask: Decimal,
-    tolerance_bps: f64,
-) -> Decimal {
-    let tolerance = Decimal::try_from(tolerance_bps / 10000.0).unwrap();
+    #[test]
+    fn test_spread_target_limits_are_exact_decimals() {
+        let target = SpreadTarget {
+            adapter: Arc::new(MockAdapter::new("short", dec!(110), dec!(111))),
+            symbol: "BTCUSDT".to_string(),
+            target_spread_bps: dec!(0),
+        };
+        // No spare spread to share: both legs are held to the geometric mean
+        assert_eq!(target.limit(Side::Buy, dec!(100), dec!(121)), Some(dec!(110)));
+        assert_eq!(target.limit(Side::Sell, dec!(100), dec!(121)), Some(dec!(110)));
+        assert_eq!(target.limit(Side::Buy, dec!(121), dec!(100)), None);
+    }
 
-    match side {
-        Side::Buy => {
-            // For buys, place slightly above best bid to increase fill probability
-            best_bid * (Decimal::ONE + tolerance)
+    #[tokio::test]
+    async fn test_crossed_book_waits_prices_off_the_mark_or_skips() {
+        // Crossed from a stale ticker
+        let adapter = || MockAdapter::new("mock", dec!(101), dec!(100)).with_reference_prices(dec!(100.4), dec!(100.5), dec!(100.6));
+        let config = |on_crossed_book, crossed_book_wait_ms| SlicingConfig {
+            slice_percent: 1.0,
+            on_crossed_book,
+            crossed_book_wait_ms,
+            ..SlicingConfig::default()
+        };
+        async fn execute(adapter: &MockAdapter, config: SlicingConfig) -> Result<SlicedOrderResult> {
+            OrderSlicer::new(config)
+                .execute_sliced_order(adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100.5))
+                .await
         }
-        Side::Sell => {
-            // For sells, place slightly below best ask
-            best_ask * (Decimal::ONE - tolerance)
+
+        // Waits for the book to uncross, then prices off it
+        let uncrossing = adapter();
+        let uncross = async {
+            sleep(Duration::from_millis(100)).await;
+            uncrossing.set_prices(dec!(100), dec!(101));
+        };
+        let (result, _) = tokio::join!(execute(&uncrossing, config(CrossedBook::Wait, 5000)), uncross);
+        assert!(result.unwrap().is_complete);
+        assert_eq!(uncrossing.placed()[0].price, Some(calculate_limit_price(Side::Buy, dec!(100), dec!(101), 5.0)));
+
+        // Still crossed once the wait is up is a price failure
+        let stuck = adapter();
+        let err = execute(&stuck, config(CrossedBook::Wait, 100)).await.unwrap_err();
+        assert!(err.to_string().contains("still crossed"), "{}", err);
+        assert!(stuck.placed().is_empty());
+
+        let marked = adapter();
+        assert!(execute(&marked, config(CrossedBook::MarkPrice, 0)).await.unwrap().is_complete);
+        assert_eq!(marked.placed()[0].price, Some(calculate_limit_price(Side::Buy, dec!(100.5), dec!(100.5), 5.0)));
+
+        let skipped = adapter();
+        let result = execute(&skipped, config(CrossedBook::Skip, 0)).await.unwrap();
+        assert!(skipped.placed().is_empty());
+        assert_eq!(result.shortfall, dec!(1));
+    }
+
+    #[tokio::test]
+    async fn test_odd_lot_of_the_total_is_left_as_shortfall() {
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.3,
+            interval_ms: 0,
+            quantity_step: dec!(0.25),
+            ..SlicingConfig::default()
+        });
+
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101));
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1.1), dec!(100.5))
+            .await
+            .unwrap();
+
+        let sent: Vec<Decimal> = adapter.placed().iter().map(|o| o.quantity).collect();
+        assert_eq!(sent, [dec!(0.25); 4]);
+        assert_eq!(slicer.plan(dec!(1.1), dec!(100.5)).slices.len(), 4);
+        assert_eq!(result.filled_quantity, dec!(1));
+        assert_eq!(result.shortfall, dec!(0.1));
+        assert!(!result.is_complete);
+    }
+
+    #[tokio::test]
+    async fn test_slices_are_rounded_to_tick_and_step() {
+        let config = |price_rounding| SlicingConfig {
+            slice_percent: 0.3,
+            interval_ms: 0,
+            tick_size: dec!(0.5),
+            quantity_step: dec!(0.25),
+            price_rounding,
+            ..SlicingConfig::default()
+        };
+
+        // 0.3 of the total floors to one step; the buy limit of 100.05 rounds
+        // down to stay passive
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101));
+        OrderSlicer::new(config(PriceRounding::Passive))
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100.5))
+            .await
+            .unwrap();
+        let placed = adapter.placed();
+        assert_eq!(placed.iter().map(|o| o.quantity).collect::<Vec<_>>(), [dec!(0.25); 4]);
+        assert!(placed.iter().all(|o| o.price == Some(dec!(100))));
+
+        // The sell limit of 100.9495 rounds up to 101, or down to 100.5 when aggressive
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101));
+        OrderSlicer::new(config(PriceRounding::Passive))
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Sell, dec!(0.25), dec!(100.5))
+            .await
+            .unwrap();
+        assert_eq!(adapter.placed()[0].price, Some(dec!(101)));
+
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101));
+        OrderSlicer::new(config(PriceRounding::Aggressive))
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Sell, dec!(0.25), dec!(100.5))
+            .await
+            .unwrap();
+        assert_eq!(adapter.placed()[0].price, Some(dec!(100.5)));
+    }
+
+    #[tokio::test]
+    async fn test_completion_threshold_and_shortfall() {
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101)).with_place_handler(|_, request| {
+            Ok(response_for(request, OrderStatus::Cancelled, dec!(0.995), request.price))
+        });
+        let slicer = |threshold| {
+            OrderSlicer::new(SlicingConfig {
+                slice_percent: 1.0,
+                completion_threshold: threshold,
+                ..SlicingConfig::default()
+            })
+        };
+        let adapter = &adapter;
+        let execute = |threshold| async move {
+            slicer(threshold)
+                .execute_sliced_order(adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100.5))
+                .await
+        };
+
+        let lenient = execute(dec!(0.99)).await.unwrap();
+        assert!(lenient.is_complete);
+        assert_eq!(lenient.shortfall, dec!(0.005));
+
+        let strict = execute(dec!(0.999)).await.unwrap();
+        assert!(!strict.is_complete);
+        assert_eq!(strict.shortfall, dec!(0.005));
+
+        assert!(execute(Decimal::ZERO).await.is_err());
+        assert!(execute(dec!(1.5)).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_slices_carry_arrival_submit_and_fill_times() {
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101)).with_place_handler(|n, request| match n {
+            0 => Ok(response_for(request, OrderStatus::Filled, request.quantity, request.price)),
+            _ => anyhow::bail!("Insufficient margin"),
+        });
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.5,
+            interval_ms: 0,
+            ..SlicingConfig::default()
+        });
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100.5))
+            .await
+            .unwrap();
+
+        let filled = &result.slices[0];
+        assert!(filled.arrival_ms > 0);
+        assert!(filled.arrival_ms <= filled.submitted_ms);
+        assert!(filled.filled_ms.is_some_and(|ms| ms >= filled.submitted_ms), "{:?}", filled);
+        // Rejected slices were sent but never filled
+        let rejected = &result.slices[1];
+        assert!(rejected.submitted_ms >= filled.submitted_ms);
+        assert_eq!(rejected.filled_ms, None);
+    }
+
+    #[tokio::test]
+    async fn test_emergency_exit_uses_reduce_only_market() {
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101));
+        let slicer = OrderSlicer::new(SlicingConfig { taker_fee_bps: 5.0, ..SlicingConfig::default() });
+
+        let result = slicer
+            .execute_emergency_exit(&adapter, &credentials(), "BTCUSDT", Side::Sell, dec!(2))
+            .await
+            .unwrap();
+
+        let placed = adapter.placed();
+        assert_eq!(placed.len(), 1);
+        assert_eq!(placed[0].order_type, OrderType::Market);
+        assert!(placed[0].reduce_only);
+        assert!(result.is_complete);
+        assert!(result.fees_estimated);
+        assert!(result.avg_fill_price > Decimal::ZERO);
+        assert_eq!(result.total_fees, dec!(2) * result.avg_fill_price * dec!(0.0005));
+        assert_eq!(result.fees_by_asset["USDT"], result.total_fees);
+    }
+
+    #[tokio::test]
+    async fn test_quote_quantity_is_converted_to_contracts_before_slicing() {
+        let adapter = MockAdapter::new("mock", dec!(99), dec!(101));
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.5,
+            interval_ms: 0,
+            quantity_mode: QuantityMode::Quote,
+            ..SlicingConfig::default()
+        });
+
+        // 1000 of quote at the reference price of 100 is 10 contracts
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1000), dec!(100))
+            .await
+            .unwrap();
+
+        let placed = adapter.placed();
+        assert_eq!(placed.len(), 2);
+        assert!(placed.iter().all(|r| r.quantity_mode == QuantityMode::Base));
+        assert_eq!(placed.iter().map(|r| r.quantity).sum::<Decimal>(), dec!(10));
+        assert_eq!(result.total_quantity, dec!(10));
+        assert_eq!(result.filled_quantity, dec!(10));
+        assert!(result.is_complete);
+    }
+
+    #[tokio::test]
+    async fn test_quote_quantity_without_reference_price_converts_at_the_mid() {
+        let adapter = MockAdapter::new("mock", dec!(199), dec!(201));
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 1.0,
+            quantity_mode: QuantityMode::Quote,
+            ..SlicingConfig::default()
+        });
+
+        // Rounded down so the quote amount isn't overspent
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Sell, dec!(1000), Decimal::ZERO)
+            .await
+            .unwrap();
+
+        assert_eq!(adapter.placed()[0].quantity, dec!(5));
+        assert_eq!(result.total_quantity, dec!(5));
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Sell, dec!(100), dec!(300))
+            .await
+            .unwrap();
+        assert_eq!(result.total_quantity, dec!(0.333333));
+    }
+
+    #[tokio::test]
+    async fn test_base_quantity_is_sliced_as_given() {
+        let adapter = MockAdapter::new("mock", dec!(99), dec!(101));
+        let slicer = OrderSlicer::new(SlicingConfig { slice_percent: 1.0, ..SlicingConfig::default() });
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1000), dec!(100))
+            .await
+            .unwrap();
+
+        assert_eq!(adapter.placed()[0].quantity, dec!(1000));
+        assert_eq!(result.total_quantity, dec!(1000));
+    }
+
+    #[tokio::test]
+    async fn test_emergency_exit_resubmits_unfilled_remainder() {
+        // The market order only fills half; the remainder goes out as a limit
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(100.01))
+            .with_place_handler(|index, request| {
+                Ok(match index {
+                    0 => response_for(request, OrderStatus::Cancelled, dec!(1), Some(dec!(100))),
+                    _ => response_for(request, OrderStatus::Filled, request.quantity, request.price),
+                })
+            });
+        let slicer = OrderSlicer::new(SlicingConfig::default());
+
+        let result = slicer
+            .execute_emergency_exit(&adapter, &credentials(), "BTCUSDT", Side::Sell, dec!(2))
+            .await
+            .unwrap();
+
+        let placed = adapter.placed();
+        assert_eq!(placed.len(), 2);
+        assert_eq!(placed[1].order_type, OrderType::Limit);
+        assert_eq!(placed[1].quantity, dec!(1));
+        assert!(placed[1].reduce_only);
+        // Second attempt crosses by twice the initial offset
+        assert_eq!(placed[1].price, Some(dec!(100) * dec!(0.99)));
+        assert_eq!(result.filled_quantity, dec!(2));
+        assert!(result.is_complete);
+    }
+
+    #[tokio::test]
+    async fn test_emergency_exit_keeps_partial_fill_when_quotes_fail() {
+        // Half fills, then every later quote fails: the remainder is priced
+        // off the last good quote and the first fill is still reported
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(100.01))
+            .with_price_failures(|call| call >= 1)
+            .with_place_handler(|index, request| {
+                Ok(match index {
+                    0 => response_for(request, OrderStatus::Cancelled, dec!(1), Some(dec!(100))),
+                    _ => response_for(request, OrderStatus::Filled, request.quantity, request.price),
+                })
+            });
+        let slicer = OrderSlicer::new(SlicingConfig::default());
+
+        let result = slicer
+            .execute_emergency_exit(&adapter, &credentials(), "BTCUSDT", Side::Sell, dec!(2))
+            .await
+            .unwrap();
+
+        let placed = adapter.placed();
+        assert_eq!(placed.len(), 2);
+        assert_eq!(placed[1].price, Some(dec!(100) * dec!(0.99)));
+        assert_eq!(result.filled_quantity, dec!(2));
+        assert!(result.is_complete);
+
+        // With no quote at all nothing is placed, and the exit still returns
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(100.01)).with_price_failures(|_| true);
+        let result = slicer
+            .execute_emergency_exit(&adapter, &credentials(), "BTCUSDT", Side::Sell, dec!(2))
+            .await
+            .unwrap();
+        assert!(adapter.placed().is_empty());
+        assert_eq!(result.shortfall, dec!(2));
+    }
+
+    #[tokio::test]
+    async fn test_kill_switch_cancels_resting_slice_and_aborts() {
+        let kill_switch = Arc::new(AtomicBool::new(false));
+        let trigger = kill_switch.clone();
+        // First slice rests unfilled and the kill switch fires while it does
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101))
+            .with_place_handler(move |_, request| {
+                trigger.store(true, Ordering::SeqCst);
+                Ok(response_for(request, OrderStatus::Open, Decimal::ZERO, None))
+            });
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.5,
+            ..SlicingConfig::default()
+        })
+        .with_kill_switch(kill_switch);
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+            .await
+            .unwrap();
+
+        assert!(result.aborted);
+        assert_eq!(adapter.placed().len(), 1);
+        assert_eq!(result.slices.len(), 1);
+        assert_eq!(result.slices[0].status, OrderStatus::Cancelled);
+        assert_eq!(adapter.cancelled().len(), 1);
+        assert!(!result.is_complete);
+    }
+
+    #[tokio::test]
+    async fn test_margin_rejection_stops_placing_slices() {
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101)).with_place_handler(|index, request| match index {
+            0 => Ok(response_for(request, OrderStatus::Filled, request.quantity, request.price)),
+            _ => Err(ExchangeError::InsufficientMargin {
+                exchange: "mock".to_string(),
+                detail: "110007".to_string(),
+            }
+            .into()),
+        });
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.25,
+            ..SlicingConfig::default()
+        });
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+            .await
+            .unwrap();
+
+        assert_eq!(adapter.placed().len(), 2);
+        assert_eq!(result.filled_quantity, dec!(0.25));
+        assert!(result.margin_rejected.unwrap().contains("insufficient margin"));
+        assert!(!result.retryable);
+        assert!(!result.is_complete);
+    }
+
+    #[tokio::test]
+    async fn test_kill_switch_mass_cancels_where_supported() {
+        let kill_switch = Arc::new(AtomicBool::new(false));
+        let trigger = kill_switch.clone();
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101))
+            .with_mass_cancel()
+            .with_place_handler(move |_, request| {
+                trigger.store(true, Ordering::SeqCst);
+                Ok(response_for(request, OrderStatus::Open, Decimal::ZERO, None))
+            });
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.5,
+            ..SlicingConfig::default()
+        })
+        .with_kill_switch(kill_switch);
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+            .await
+            .unwrap();
+
+        assert!(result.aborted);
+        assert_eq!(adapter.mass_cancels(), ["BTCUSDT"]);
+        assert!(adapter.cancelled().is_empty());
+        assert_eq!(result.slices[0].status, OrderStatus::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_maker_only_slice_is_repriced_to_stay_passive() {
+        // One tick wide: the 5 bps tolerance would cross the ask
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(100.01))
+            .with_place_handler(|index, request| {
+                Ok(match index {
+                    // Book moved under the first order, so the exchange expired it
+                    0 => response_for(request, OrderStatus::Expired, Decimal::ZERO, None),
+                    _ => response_for(request, OrderStatus::Filled, request.quantity, request.price),
+                })
+            });
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 1.0,
+            maker_only: true,
+            maker_fee_bps: -1.0,
+            ..SlicingConfig::default()
+        });
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+            .await
+            .unwrap();
+
+        let placed = adapter.placed();
+        assert_eq!(placed.len(), 2);
+        for request in &placed {
+            assert_eq!(request.time_in_force, TimeInForce::PostOnly);
+            assert_eq!(request.price, Some(dec!(100)));
         }
+        assert!(result.is_complete);
+        assert_eq!(result.slices[0].status, OrderStatus::Filled);
+        // 1 bp rebate on 100 notional
+        assert_eq!(result.total_fees, dec!(-0.01));
     }
-}
 
-fn side_str(side: Side) -> &'static str {
-    match side {
-        Side::Buy => "BUY",
-        Side::Sell => "SELL",
+    #[tokio::test]
+    async fn test_post_only_rejection_steps_back_a_tick() {
+        // 2 bps over the bid is a tick inside the ask
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(100.03))
+            .with_place_handler(|index, request| match index {
+                // The ask ticked down to meet it before it arrived
+                0 => Err(crate::exchange::post_only_rejected("mock", "-5022 Post Only order will be rejected")),
+                _ => Ok(response_for(request, OrderStatus::Filled, request.quantity, request.price)),
+            });
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 1.0,
+            maker_only: true,
+            price_tolerance_bps: 2.0,
+            tick_size: dec!(0.01),
+            ..SlicingConfig::default()
+        });
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+            .await
+            .unwrap();
+
+        let placed = adapter.placed();
+        assert_eq!(placed.len(), 2);
+        assert_eq!(placed[0].price, Some(dec!(100.02)));
+        assert_eq!(placed[1].price, Some(dec!(100.01)));
+        assert_eq!(placed[1].time_in_force, TimeInForce::PostOnly);
+        assert!(result.is_complete);
+        assert_eq!(result.slices.len(), 1);
+        assert_eq!(result.slices[0].price, dec!(100.01));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    #[tokio::test]
+    async fn test_emergency_cross_offset_for_both_sides() {
+        let slicer = OrderSlicer::new(SlicingConfig {
+            emergency_cross_bps: 100.0,
+            ..SlicingConfig::default()
+        });
+
+        for (side, expected) in [(Side::Buy, dec!(101.0101)), (Side::Sell, dec!(99))] {
+            let adapter = MockAdapter::new("mock", dec!(100), dec!(100.01)).without_reduce_only_market();
+
+            slicer
+                .execute_emergency_exit(&adapter, &credentials(), "BTCUSDT", side, dec!(1))
+                .await
+                .unwrap();
+
+            let placed = adapter.placed();
+            assert_eq!(placed[0].order_type, OrderType::Limit);
+            assert_eq!(placed[0].price, Some(expected), "{:?}", side);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_emergency_exit_takes_an_improved_book() {
+        let slicer = OrderSlicer::new(SlicingConfig {
+            emergency_improvement_ms: 200,
+            ..SlicingConfig::default()
+        });
+
+        // The bid lifts while the exit waits, so it sells at the new bid
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(100.01));
+        let credentials = credentials();
+        let improve = async {
+            sleep(Duration::from_millis(50)).await;
+            adapter.set_prices(dec!(100.2), dec!(100.21));
+        };
+        let (result, _) = tokio::join!(
+            slicer.execute_emergency_exit(&adapter, &credentials, "BTCUSDT", Side::Sell, dec!(1)),
+            improve
+        );
+        assert!(result.unwrap().is_complete);
+        let placed = adapter.placed();
+        assert_eq!(placed[0].order_type, OrderType::Limit);
+        assert_eq!(placed[0].price, Some(dec!(100.2)));
+        assert!(placed[0].reduce_only);
+
+        // An unchanged book crosses as before
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(100.01));
+        slicer
+            .execute_emergency_exit(&adapter, &credentials, "BTCUSDT", Side::Sell, dec!(1))
+            .await
+            .unwrap();
+        assert_eq!(adapter.placed()[0].order_type, OrderType::Market);
+    }
 
     #[test]
-    fn test_calculate_slices() {
+    fn test_emergency_offset_is_floored_at_spread_and_capped() {
+        let slicer = OrderSlicer::new(SlicingConfig::default());
+
+        // 200 bps wide book crosses by the spread rather than the configured 50 bps
+        let price = slicer.emergency_price(Side::Sell, dec!(99), dec!(101), 0).unwrap();
+        assert_eq!(price, dec!(97.02));
+        // Escalation stops at the 500 bps cap
+        let price = slicer.emergency_price(Side::Sell, dec!(99), dec!(101), 2).unwrap();
+        assert_eq!(price, dec!(94.05));
+    }
+
+    #[tokio::test]
+    async fn test_adaptive_slices_grow_then_shrink() {
+        // The first three slices fill at the touch, the fourth only partially
+        let adapter = MockAdapter::new("mock", dec!(99.9), dec!(100))
+            .with_place_handler(|index, request| {
+                Ok(match index {
+                    0..=2 => response_for(request, OrderStatus::Filled, request.quantity, Some(dec!(100))),
+                    _ => response_for(request, OrderStatus::Cancelled, request.quantity / dec!(2), Some(dec!(100))),
+                })
+            });
         let slicer = OrderSlicer::new(SlicingConfig {
-            slice_percent: 0.1, // 10%
-            ..Default::default()
+            slice_percent: 0.05,
+            interval_ms: 0,
+            strategy: SlicingStrategy::Adaptive,
+            adaptive_factor: 2.0,
+            min_slice_percent: 0.05,
+            max_slice_percent: 0.2,
+            ..SlicingConfig::default()
         });
 
-        let slices = slicer.calculate_slices(dec!(1.0));
-        assert_eq!(slices.len(), 10);
-        assert!(slices.iter().all(|s| *s == dec!(0.1)));
+        slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+            .await
+            .unwrap();
+
+        let sizes: Vec<Decimal> = adapter.placed().iter().map(|r| r.quantity).collect();
+        // Doubles after each clean fill up to the 20% cap, then halves after
+        // each partial fill down to the 5% floor
+        assert_eq!(
+            &sizes[..6],
+            &[dec!(0.05), dec!(0.1), dec!(0.2), dec!(0.2), dec!(0.1), dec!(0.05)]
+        );
+        assert_eq!(sizes.len(), 12);
+        assert!(sizes[6..].iter().all(|s| *s == dec!(0.05)));
+        assert_eq!(sizes.iter().sum::<Decimal>(), dec!(1));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_plan_matches_the_slices_executed() {
+        for strategy in [SlicingStrategy::Fixed, SlicingStrategy::Adaptive] {
+            // Fills inside the spread are clean, so adaptive slices keep growing
+            let adapter = MockAdapter::new("mock", dec!(100), dec!(101));
+            let slicer = OrderSlicer::new(SlicingConfig {
+                slice_percent: 0.05,
+                size_jitter_percent: 0.2,
+                interval_ms: 100,
+                interval_jitter_percent: 0.5,
+                strategy,
+                adaptive_factor: 2.0,
+                max_slice_percent: 0.2,
+                jitter_seed: Some(7),
+                ..SlicingConfig::default()
+            });
+
+            let plan = slicer.plan(dec!(1), dec!(100));
+            slicer
+                .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+                .await
+                .unwrap();
+
+            let planned: Vec<Decimal> = plan.slices.iter().map(|s| s.quantity).collect();
+            let sent: Vec<Decimal> = adapter.placed().iter().map(|r| r.quantity).collect();
+            assert_eq!(planned, sent, "{:?}", strategy);
+            assert_eq!(planned.iter().sum::<Decimal>(), dec!(1));
+            assert!(plan.slices.windows(2).all(|pair| pair[0].send_after_ms <= pair[1].send_after_ms));
+        }
+    }
+
+    /// Sends half of what is left each time, three times over
+    struct Halving;
+
+    impl ExecutionAlgorithm for Halving {
+        fn next_slice(&mut self, state: &ExecState) -> Option<SliceInstruction> {
+            (state.slices_sent < 3).then(|| SliceInstruction {
+                quantity: state.unplaced / dec!(2),
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_custom_algorithm_sizes_the_slices() {
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101));
+        let slicer = OrderSlicer::new(SlicingConfig {
+            interval_ms: 0,
+            ..SlicingConfig::default()
+        })
+        .with_algorithm(|_| Box::new(Halving));
+
+        let planned: Vec<Decimal> = slicer.plan(dec!(1), dec!(100)).slices.iter().map(|s| s.quantity).collect();
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+            .await
+            .unwrap();
+
+        let sent: Vec<Decimal> = adapter.placed().iter().map(|r| r.quantity).collect();
+        assert_eq!(sent, [dec!(0.5), dec!(0.25), dec!(0.125)]);
+        assert_eq!(planned, sent);
+        // Stopping early leaves the rest unfilled
+        assert!(!result.is_complete);
+        assert_eq!(result.shortfall, dec!(0.125));
     }
 
     #[test]
-    fn test_calculate_slices_remainder() {
+    fn test_plan_schedules_waves_at_the_slice_interval() {
         let slicer = OrderSlicer::new(SlicingConfig {
-            slice_percent: 0.3, // 30%
-            ..Default::default()
+            slice_percent: 0.25,
+            interval_ms: 100,
+            max_parallel: 2,
+            ..SlicingConfig::default()
         });
 
-        let slices = slicer.calculate_slices(dec!(1.0));
-        assert_eq!(slices.len(), 4);
-        // 0.3 + 0.3 + 0.3 + 0.1 = 1.0
+        let plan = slicer.plan(dec!(1), Decimal::ZERO);
+        assert_eq!(plan.wave_size, 2);
+        let schedule: Vec<(usize, Decimal, u64)> =
+            plan.slices.iter().map(|s| (s.wave, s.quantity, s.send_after_ms)).collect();
+        assert_eq!(
+            schedule,
+            [(0, dec!(0.25), 0), (0, dec!(0.25), 100), (1, dec!(0.25), 200), (1, dec!(0.25), 300)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_total_timeout_stops_with_partial_fill() {
+        // Twenty slices 200ms apart would take four seconds
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101))
+            .with_place_handler(|_, request| {
+                Ok(response_for(request, OrderStatus::Filled, request.quantity, request.price))
+            });
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.05,
+            interval_ms: 200,
+            total_timeout_secs: Some(1),
+            ..SlicingConfig::default()
+        });
+
+        let started = Instant::now();
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+            .await
+            .unwrap();
+
+        assert!(started.elapsed() < Duration::from_millis(1500));
+        assert!(result.timed_out);
+        assert!(!result.is_complete);
+        assert!(!result.aborted);
+        assert!(result.slices.len() < 20);
+        assert!(result.filled_quantity > Decimal::ZERO);
+        assert!(result.filled_quantity < dec!(1));
+        assert_eq!(result.filled_quantity, dec!(0.05) * Decimal::from(result.slices.len()));
+    }
+
+    #[tokio::test]
+    async fn test_expired_signature_is_resigned_once() {
+        // The third slice is refused as expired, minutes into the schedule
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101)).with_place_handler(|n, request| {
+            if n == 2 {
+                return Err(crate::exchange::signature_expired("mock", "-1021 Timestamp for this request is outside of the recvWindow"));
+            }
+            Ok(response_for(request, OrderStatus::Filled, request.quantity, request.price))
+        });
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.25,
+            interval_ms: 0,
+            ..SlicingConfig::default()
+        });
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+            .await
+            .unwrap();
+
+        // Sent again as it was, and the rest of the schedule carried on
+        assert!(result.is_complete);
+        assert_eq!(result.filled_quantity, dec!(1));
+        let placed = adapter.placed();
+        assert_eq!(placed.len(), 5);
+        assert_eq!(placed[2].client_order_id, placed[3].client_order_id);
+        assert!(result.slices.iter().all(|s| s.status == OrderStatus::Filled));
+
+        // Without re-signing, the slice fails like any rejection
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101))
+            .with_place_handler(|_, _| {
+                Err(crate::exchange::signature_expired("mock", "10002 invalid request, please check your server timestamp"))
+            });
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 1.0,
+            signature_expiry_retries: 0,
+            ..SlicingConfig::default()
+        });
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+            .await
+            .unwrap();
+        assert_eq!(adapter.placed().len(), 1);
+        assert_eq!(result.filled_quantity, Decimal::ZERO);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_emulated_gtd_slice_is_cancelled_at_expiry() {
+        let resting = |adapter: MockAdapter| {
+            adapter.with_place_handler(|_, request| Ok(response_for(request, OrderStatus::Open, Decimal::ZERO, None)))
+        };
+        let expire_at_ms = now_millis() + 5000;
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 1.0,
+            slice_timeout_secs: 30,
+            expire_at_ms: Some(expire_at_ms),
+            ..SlicingConfig::default()
+        });
+
+        // No native GTD: the slice rests as GTC and is cancelled at the expiry,
+        // well before its own timeout
+        let adapter = resting(MockAdapter::new("mock", dec!(100), dec!(101)));
+        let started = Instant::now();
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+            .await
+            .unwrap();
+        let elapsed = started.elapsed();
+        assert!(elapsed > Duration::from_millis(4900) && elapsed <= Duration::from_millis(5250), "{:?}", elapsed);
+        assert_eq!(adapter.placed()[0].time_in_force, TimeInForce::Gtc);
+        assert_eq!(adapter.cancelled().len(), 1);
+        assert_eq!(result.slices[0].status, OrderStatus::Cancelled);
+        assert!(result.timed_out);
+
+        // Venues that expire it themselves are sent the expiry instead
+        let adapter = resting(MockAdapter::new("mock", dec!(100), dec!(101)).with_gtd());
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_timeout_secs: 1,
+            ..slicer.config.clone()
+        });
+        slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+            .await
+            .unwrap();
+        assert_eq!(adapter.placed()[0].time_in_force, TimeInForce::Gtd { expire_at_ms });
+
+        // Nothing is placed once it has passed
+        let adapter = resting(MockAdapter::new("mock", dec!(100), dec!(101)));
+        let slicer = OrderSlicer::new(SlicingConfig {
+            expire_at_ms: Some(now_millis() - 1),
+            ..slicer.config.clone()
+        });
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+            .await
+            .unwrap();
+        assert!(adapter.placed().is_empty());
+        assert!(result.timed_out);
+    }
+
+    #[test]
+    fn test_imbalance_tolerance_follows_book_pressure() {
+        // Bid three times the ask: buyers are crowding in
+        assert_eq!(imbalance_tolerance_bps(Side::Buy, dec!(3), dec!(1), 10.0), 15.0);
+        assert_eq!(imbalance_tolerance_bps(Side::Sell, dec!(3), dec!(1), 10.0), 5.0);
+        // Balanced or missing sizes keep the fixed tolerance
+        assert_eq!(imbalance_tolerance_bps(Side::Buy, dec!(2), dec!(2), 10.0), 10.0);
+        assert_eq!(imbalance_tolerance_bps(Side::Buy, Decimal::ZERO, Decimal::ZERO, 10.0), 10.0);
+    }
+
+    #[tokio::test]
+    async fn test_book_imbalance_skews_limit_price() {
+        let place = |adapter: MockAdapter| async move {
+            let slicer = OrderSlicer::new(SlicingConfig {
+                slice_percent: 1.0,
+                price_tolerance_bps: 10.0,
+                use_book_imbalance: true,
+                ..SlicingConfig::default()
+            });
+            slicer
+                .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+                .await
+                .unwrap();
+            adapter.placed()[0].price.unwrap()
+        };
+        let filled = |_, request: &OrderRequest| {
+            Ok(response_for(request, OrderStatus::Filled, request.quantity, request.price))
+        };
+
+        // Thin ask with a deep bid behind us: pay up before it lifts
+        let heavy_bid = MockAdapter::new("mock", dec!(100), dec!(101))
+            .with_order_book(&[(dec!(100), dec!(9))], &[(dec!(101), dec!(1))])
+            .with_place_handler(filled);
+        assert_eq!(place(heavy_bid).await, dec!(100.18));
+
+        // Deep ask: the book favours the buyer, so wait at the bid
+        let heavy_ask = MockAdapter::new("mock", dec!(100), dec!(101))
+            .with_order_book(&[(dec!(100), dec!(1))], &[(dec!(101), dec!(9))])
+            .with_place_handler(filled);
+        assert_eq!(place(heavy_ask).await, dec!(100.02));
+
+        // No book: fixed tolerance off the best price
+        let no_book = MockAdapter::new("mock", dec!(100), dec!(101)).with_place_handler(filled);
+        assert_eq!(place(no_book).await, dec!(100.10));
+
+        // A book older than the quote age limit is ignored the same way
+        let stale_book = MockAdapter::new("mock", dec!(100), dec!(101))
+            .with_order_book(&[(dec!(100), dec!(9))], &[(dec!(101), dec!(1))])
+            .with_book_age(Duration::from_secs(5))
+            .with_place_handler(filled);
+        assert_eq!(place(stale_book).await, dec!(100.10));
+    }
+
+    #[tokio::test]
+    async fn test_reduce_only_is_set_on_every_slice() {
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101));
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.25,
+            interval_ms: 0,
+            reduce_only: true,
+            ..SlicingConfig::default()
+        });
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Sell, dec!(1), dec!(100))
+            .await
+            .unwrap();
+
+        let placed = adapter.placed();
+        assert_eq!(placed.len(), 4);
+        assert!(placed.iter().all(|r| r.reduce_only && r.order_type == OrderType::Limit));
+        assert!(result.is_complete);
+    }
+
+    #[tokio::test]
+    async fn test_open_orders_stay_under_the_cap_across_trades() {
+        let limits = Arc::new(OpenOrderLimits::new(2));
+        let account = Uuid::new_v4();
+        let most_open = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let adapter = || {
+            let limits = limits.clone();
+            let most_open = most_open.clone();
+            MockAdapter::new("mock", dec!(100), dec!(101)).with_place_handler(move |_, request| {
+                most_open.fetch_max(limits.open_orders("mock", account, "BTCUSDT"), Ordering::SeqCst);
+                Ok(response_for(request, OrderStatus::Filled, request.quantity, request.price))
+            })
+        };
+        let slicer = || {
+            OrderSlicer::new(SlicingConfig {
+                slice_percent: 0.1,
+                interval_ms: 0,
+                max_parallel: 5,
+                ..SlicingConfig::default()
+            })
+            .with_open_order_limits(limits.clone(), account)
+        };
+        let (first_adapter, second_adapter) = (adapter(), adapter());
+        let (first_slicer, second_slicer) = (slicer(), slicer());
+        let credentials = credentials();
+
+        let (first, second) = tokio::join!(
+            first_slicer.execute_sliced_order(&first_adapter, &credentials, "BTCUSDT", Side::Buy, dec!(1), dec!(100)),
+            second_slicer.execute_sliced_order(&second_adapter, &credentials, "BTCUSDT", Side::Sell, dec!(1), dec!(100)),
+        );
+
+        assert!(first.unwrap().is_complete);
+        assert!(second.unwrap().is_complete);
+        assert_eq!(first_adapter.placed().len() + second_adapter.placed().len(), 20);
+        assert_eq!(most_open.load(Ordering::SeqCst), 2);
+        assert_eq!(limits.open_orders("mock", account, "BTCUSDT"), 0);
+
+        // Another account on the same symbol has a cap of its own
+        let held = [limits.try_acquire("mock", account, "BTCUSDT"), limits.try_acquire("mock", account, "BTCUSDT")];
+        assert!(held.iter().all(Option::is_some));
+        assert!(limits.try_acquire("mock", account, "BTCUSDT").is_none());
+        assert!(limits.try_acquire("mock", Uuid::new_v4(), "BTCUSDT").is_some());
+    }
+
+    #[tokio::test]
+    async fn test_parallel_slices_are_polled_in_one_batch() {
+        // Nothing fills, so the three resting slices are polled until they time out
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101))
+            .with_place_handler(|_, request| {
+                Ok(response_for(request, OrderStatus::Open, Decimal::ZERO, None))
+            });
+        let slicer = OrderSlicer::new(SlicingConfig {
+            slice_percent: 0.34,
+            interval_ms: 0,
+            max_parallel: 3,
+            slice_timeout_secs: 1,
+            ..SlicingConfig::default()
+        });
+
+        let result = slicer
+            .execute_sliced_order(&adapter, &credentials(), "BTCUSDT", Side::Buy, dec!(1), dec!(100))
+            .await
+            .unwrap();
+
+        assert_eq!(result.slices.len(), 3);
+        assert!(result.slices.iter().all(|s| s.status == OrderStatus::Cancelled));
+        let batches = adapter.batch_sizes();
+        assert!(!batches.is_empty());
+        assert!(batches.iter().all(|&size| size == 3));
+        assert_eq!(adapter.get_order_calls(), 0);
     }
 }
+
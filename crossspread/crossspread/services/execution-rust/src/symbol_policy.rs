@@ -0,0 +1,137 @@
+//! Symbol allow and deny lists
+//!
+//! A deployment can be limited to the markets it is meant to trade, so that
+//! a misconfigured backend can't send it anywhere else. Patterns match a
+//! symbol's canonical `BASE/QUOTE` form or its native one, ignoring case,
+//! and `*` stands for any run of characters: `BTC/*`, `*/USDC`, `ETH*`.
+//! A denied symbol is refused even when it is also allowed, and an empty
+//! allow list allows everything that isn't denied.
+
+use anyhow::Result;
+
+use crate::exchange::ExchangeAdapter;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SymbolPolicy {
+    pub allowed: Vec<String>,
+    pub denied: Vec<String>,
+}
+
+impl SymbolPolicy {
+    /// Patterns from a comma or newline separated list, skipping blank
+    /// entries and `#` comments
+    pub fn parse_list(list: &str) -> Vec<String> {
+        list.lines()
+            .map(|line| line.split('#').next().unwrap_or_default())
+            .flat_map(|line| line.split(','))
+            .map(|pattern| pattern.trim().to_uppercase())
+            .filter(|pattern| !pattern.is_empty())
+            .collect()
+    }
+
+    /// Fail if `symbol` on `adapter`'s venue is denied or not allowed
+    pub fn check(&self, adapter: &dyn ExchangeAdapter, symbol: &str) -> Result<()> {
+        if self.allowed.is_empty() && self.denied.is_empty() {
+            return Ok(());
+        }
+
+        let native = adapter.native_symbol(symbol);
+        let mut names = vec![native.to_uppercase()];
+        if let Some(canonical) = adapter.to_canonical_symbol(&native) {
+            names.push(canonical.to_uppercase());
+        }
+        let listed = |patterns: &[String]| {
+            patterns
+                .iter()
+                .find(|pattern| names.iter().any(|name| matches(pattern, name)))
+                .cloned()
+        };
+
+        if let Some(pattern) = listed(&self.denied) {
+            anyhow::bail!("{} on {} is denied by symbol policy ({})", symbol, adapter.id(), pattern);
+        }
+        if !self.allowed.is_empty() && listed(&self.allowed).is_none() {
+            anyhow::bail!("{} on {} is not in the allowed symbols", symbol, adapter.id());
+        }
+        Ok(())
+    }
+}
+
+/// Whether `name` matches `pattern`, where `*` matches any run of characters
+fn matches(pattern: &str, name: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = name.strip_prefix(parts.next().unwrap_or_default()) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(at) => rest = &rest[at + part.len()..],
+            None => return false,
+        }
+    }
+    rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::MockAdapter;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_patterns_match_with_wildcards() {
+        assert!(matches("BTC/USDT", "BTC/USDT"));
+        assert!(!matches("BTC/USDT", "BTC/USDTX"));
+        assert!(matches("BTC/*", "BTC/USDC"));
+        assert!(matches("*/USDT", "ETH/USDT"));
+        assert!(!matches("*/USDT", "ETH/USDC"));
+        assert!(matches("ETH*", "ETHUSDT"));
+        assert!(matches("*", "ANY"));
+        assert!(matches("B*/*T", "BTC/USDT"));
+        assert!(!matches("B*/*T", "ETH/USDT"));
+        assert_eq!(
+            SymbolPolicy::parse_list("btc/*, ETHUSDT\n# majors only\n\nsol*  # for now"),
+            ["BTC/*", "ETHUSDT", "SOL*"]
+        );
+    }
+
+    #[test]
+    fn test_allow_list_admits_only_listed_symbols() {
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101));
+        let policy = SymbolPolicy {
+            allowed: SymbolPolicy::parse_list("BTC/*,ETHUSDT"),
+            denied: Vec::new(),
+        };
+
+        policy.check(&adapter, "BTCUSDT").unwrap();
+        policy.check(&adapter, "BTC/USDC").unwrap();
+        policy.check(&adapter, "eth/usdt").unwrap();
+        let err = policy.check(&adapter, "SOLUSDT").unwrap_err();
+        assert_eq!(err.to_string(), "SOLUSDT on mock is not in the allowed symbols");
+        SymbolPolicy::default().check(&adapter, "SOLUSDT").unwrap();
+    }
+
+    #[test]
+    fn test_deny_list_overrides_the_allow_list() {
+        let adapter = MockAdapter::new("mock", dec!(100), dec!(101));
+        let policy = SymbolPolicy {
+            allowed: SymbolPolicy::parse_list("*/USDT"),
+            denied: SymbolPolicy::parse_list("DOGE*"),
+        };
+
+        policy.check(&adapter, "BTCUSDT").unwrap();
+        let err = policy.check(&adapter, "DOGEUSDT").unwrap_err();
+        assert_eq!(err.to_string(), "DOGEUSDT on mock is denied by symbol policy (DOGE*)");
+
+        let deny_only = SymbolPolicy {
+            allowed: Vec::new(),
+            denied: policy.denied.clone(),
+        };
+        deny_only.check(&adapter, "SOLUSDT").unwrap();
+        assert!(deny_only.check(&adapter, "DOGE/USDT").is_err());
+    }
+}
@@ -0,0 +1,436 @@
+//! Trailing stop exits
+//!
+//! An exit can trail a stop behind the best price instead of closing now, so
+//! a leg keeps running while the market moves its way. Binance takes a
+//! callback rate natively and Bybit a distance. Any other venue or trail is
+//! emulated here by following the touch and ratcheting the stop price.
+//!
+//! Where the venue takes stop orders (Binance and OKX), an emulated trail is
+//! backed by a reduce-only stop resting there, amended each time the stop
+//! moves, so the leg stays protected if this service goes down. Once the
+//! trail is hit here, that stop is pulled and the leg closed with reduce-only
+//! market orders. On other venues the trail lives only in this process.
+
+use anyhow::{Context, Result};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+use crate::exchange::{
+    Credentials, ExchangeAdapter, OrderResponse, Side, StopOrderRequest, Trail, TrailingStopRequest,
+};
+use crate::slicer::OrderSlicer;
+
+/// How often an emulated stop reads the touch
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Stop price that follows the best price seen, for a closing order on `side`.
+/// A Sell closes a long, so its stop trails below the highest bid; a Buy
+/// trails above the lowest ask.
+#[derive(Debug, Clone)]
+pub struct TrailingStop {
+    side: Side,
+    trail: Trail,
+    best: Option<Decimal>,
+}
+
+impl TrailingStop {
+    pub fn new(side: Side, trail: Trail) -> Self {
+        Self {
+            side,
+            trail,
+            best: None,
+        }
+    }
+
+    /// Current stop price, once a price has been seen
+    pub fn stop_price(&self) -> Option<Decimal> {
+        let best = self.best?;
+        let offset = match self.trail {
+            Trail::CallbackRateBps(bps) => best * bps / dec!(10000),
+            Trail::Distance(distance) => distance,
+        };
+        Some(match self.side {
+            Side::Sell => best - offset,
+            Side::Buy => best + offset,
+        })
+    }
+
+    /// Follow a new price, moving the stop only in the position's favour.
+    /// Returns true once the price has reached the stop.
+    pub fn update(&mut self, price: Decimal) -> bool {
+        let best = match (self.side, self.best) {
+            (_, None) => price,
+            (Side::Sell, Some(best)) => best.max(price),
+            (Side::Buy, Some(best)) => best.min(price),
+        };
+        self.best = Some(best);
+
+        match (self.side, self.stop_price()) {
+            (Side::Sell, Some(stop)) => price <= stop,
+            (Side::Buy, Some(stop)) => price >= stop,
+            (_, None) => false,
+        }
+    }
+}
+
+/// What an emulated trailing stop closed, between the venue stop and the
+/// exit run here
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrailingExit {
+    pub total_quantity: Decimal,
+    pub filled_quantity: Decimal,
+    pub avg_fill_price: Decimal,
+}
+
+/// A trailing stop followed by this service, with the venue stop backing it
+pub struct EmulatedTrail {
+    adapter: Arc<dyn ExchangeAdapter>,
+    credentials: Credentials,
+    request: TrailingStopRequest,
+    stop: TrailingStop,
+    /// Reduce-only stop resting at the venue at the current stop price
+    resting: Option<OrderResponse>,
+    /// Closed so far by venue stops that have ended, and at what notional
+    filled: Decimal,
+    notional: Decimal,
+}
+
+impl EmulatedTrail {
+    /// Start trailing from the current touch. Where the venue takes stop
+    /// orders one is placed at the first stop price, and failing to place it
+    /// fails the trail, so a leg is never left without a stop at the venue.
+    pub async fn start(
+        adapter: Arc<dyn ExchangeAdapter>,
+        credentials: Credentials,
+        request: TrailingStopRequest,
+    ) -> Result<Self> {
+        let mut trail = Self {
+            stop: TrailingStop::new(request.side, request.trail),
+            adapter,
+            credentials,
+            request,
+            resting: None,
+            filled: Decimal::ZERO,
+            notional: Decimal::ZERO,
+        };
+        if !trail.adapter.supports_stop_order() {
+            info!("Emulating trailing stop on {} {}", trail.adapter.id(), trail.request.symbol);
+            return Ok(trail);
+        }
+
+        let price = trail.touch().await.context("No price to place the stop at")?;
+        trail.stop.update(price);
+        let stop_price = trail.stop.stop_price().unwrap_or(price);
+        let order = trail
+            .adapter
+            .place_stop_order(&trail.credentials, &trail.stop_request(stop_price))
+            .await?;
+        info!(
+            "Emulating trailing stop on {} {} from a stop at {}",
+            trail.adapter.id(),
+            trail.request.symbol,
+            stop_price
+        );
+        trail.resting = Some(order);
+        Ok(trail)
+    }
+
+    /// Id of the stop resting at the venue. Venues that amend a stop by
+    /// replacing it give it a new id as it moves.
+    pub fn order_id(&self) -> Option<&str> {
+        self.resting.as_ref().map(|order| order.exchange_order_id.as_str())
+    }
+
+    /// Follow the trail until it is hit, then close what the venue stop has
+    /// not with `slicer`'s emergency exit. Returns `None` if `kill_switch` is
+    /// set first, in which case the venue stop is pulled too.
+    pub async fn run(mut self, slicer: OrderSlicer, kill_switch: Arc<AtomicBool>) -> Result<Option<TrailingExit>> {
+        loop {
+            if kill_switch.load(Ordering::SeqCst) {
+                self.withdraw().await;
+                info!("Trailing stop on {} cancelled", self.request.symbol);
+                return Ok(None);
+            }
+            if self.check_venue_stop().await {
+                info!("Trailing stop on {} filled at the venue", self.request.symbol);
+                return Ok(Some(self.exit()));
+            }
+
+            match self.touch().await {
+                Ok(price) => {
+                    if self.follow(price).await {
+                        info!(
+                            "Trailing stop on {} hit at {} (stop {:?})",
+                            self.request.symbol,
+                            price,
+                            self.stop.stop_price()
+                        );
+                        break;
+                    }
+                }
+                Err(e) => debug!("No price for trailing stop on {}: {}", self.request.symbol, e),
+            }
+            tokio::time::sleep(POLL_INTERVAL).await;
+        }
+
+        // Pulled first so that the venue can't close the leg a second time
+        self.withdraw().await;
+        let remaining = self.remaining();
+        if remaining > Decimal::ZERO {
+            let exit = slicer
+                .execute_emergency_exit(
+                    self.adapter.as_ref(),
+                    &self.credentials,
+                    &self.request.symbol,
+                    self.request.side,
+                    remaining,
+                )
+                .await?;
+            self.filled += exit.filled_quantity;
+            self.notional += exit.filled_quantity * exit.avg_fill_price;
+        }
+        Ok(Some(self.exit()))
+    }
+
+    /// Price the stop trails: the bid when selling, the ask when buying
+    async fn touch(&self) -> Result<Decimal> {
+        let (bid, ask) = self.adapter.get_best_price(&self.request.symbol).await?;
+        Ok(match self.request.side {
+            Side::Sell => bid,
+            Side::Buy => ask,
+        })
+    }
+
+    fn stop_request(&self, stop_price: Decimal) -> StopOrderRequest {
+        StopOrderRequest {
+            client_order_id: self.request.client_order_id.clone(),
+            symbol: self.request.symbol.clone(),
+            side: self.request.side,
+            quantity: self.remaining(),
+            stop_price,
+        }
+    }
+
+    fn remaining(&self) -> Decimal {
+        (self.request.quantity - self.filled).max(Decimal::ZERO)
+    }
+
+    /// Count what an ended venue stop closed
+    fn record(&mut self, order: &OrderResponse) {
+        let price = order.avg_fill_price.or(order.price).unwrap_or_default();
+        self.filled += order.filled_quantity;
+        self.notional += order.filled_quantity * price;
+    }
+
+    fn exit(&self) -> TrailingExit {
+        TrailingExit {
+            total_quantity: self.request.quantity,
+            filled_quantity: self.filled,
+            avg_fill_price: if self.filled.is_zero() {
+                Decimal::ZERO
+            } else {
+                self.notional / self.filled
+            },
+        }
+    }
+
+    /// Follow `price`, moving the venue stop whenever the stop price
+    /// improves. Returns true once the price has reached the stop.
+    async fn follow(&mut self, price: Decimal) -> bool {
+        let before = self.stop.stop_price();
+        if self.stop.update(price) {
+            return true;
+        }
+        let Some(stop_price) = self.stop.stop_price().filter(|stop| Some(*stop) != before) else {
+            return false;
+        };
+        let Some(resting) = &self.resting else {
+            return false;
+        };
+
+        let request = self.stop_request(stop_price);
+        match self
+            .adapter
+            .amend_stop_order(&self.credentials, &resting.exchange_order_id, &request)
+            .await
+        {
+            Ok(order) => {
+                debug!("Stop on {} moved to {}", self.request.symbol, stop_price);
+                self.resting = Some(order);
+            }
+            Err(e) => warn!(
+                "Failed to move stop on {} to {}, it stays at {:?}: {}",
+                self.request.symbol, stop_price, resting.price, e
+            ),
+        }
+        false
+    }
+
+    /// Read the venue stop. Returns true once it has closed the whole leg; a
+    /// stop that ended short of that is placed again for the rest.
+    async fn check_venue_stop(&mut self) -> bool {
+        let Some(resting) = &self.resting else {
+            return false;
+        };
+        let order = match self
+            .adapter
+            .get_stop_order(&self.credentials, &self.request.symbol, &resting.exchange_order_id)
+            .await
+        {
+            Ok(order) => order,
+            Err(e) => {
+                debug!("Failed to read stop on {}: {}", self.request.symbol, e);
+                return false;
+            }
+        };
+        if !order.status.is_terminal() {
+            self.resting = Some(order);
+            return false;
+        }
+
+        self.resting = None;
+        self.record(&order);
+        if self.remaining().is_zero() {
+            return true;
+        }
+
+        warn!(
+            "Stop {} on {} ended {:?} with {} left open, placing it again",
+            order.exchange_order_id,
+            self.request.symbol,
+            order.status,
+            self.remaining()
+        );
+        let stop_price = self.stop.stop_price().or(order.price).unwrap_or_default();
+        match self
+            .adapter
+            .place_stop_order(&self.credentials, &self.stop_request(stop_price))
+            .await
+        {
+            Ok(order) => self.resting = Some(order),
+            Err(e) => warn!(
+                "Failed to place stop on {} again, trailing it here only: {}",
+                self.request.symbol, e
+            ),
+        }
+        false
+    }
+
+    /// Pull the venue stop, counting anything it filled before it went
+    async fn withdraw(&mut self) {
+        let Some(resting) = self.resting.take() else {
+            return;
+        };
+        let symbol = &self.request.symbol;
+        let order = match self
+            .adapter
+            .cancel_stop_order(&self.credentials, symbol, &resting.exchange_order_id)
+            .await
+        {
+            Ok(order) => order,
+            Err(e) => {
+                warn!("Failed to cancel stop {} on {}: {}", resting.exchange_order_id, symbol, e);
+                match self
+                    .adapter
+                    .get_stop_order(&self.credentials, symbol, &resting.exchange_order_id)
+                    .await
+                {
+                    Ok(order) => order,
+                    Err(_) => return,
+                }
+            }
+        };
+        self.record(&order);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::mock::{credentials, MockAdapter};
+    use crate::slicer::SlicingConfig;
+
+    #[test]
+    fn test_emulated_stop_ratchets_then_triggers() {
+        // Closing a long: the stop follows the bid up and never back down
+        let mut stop = TrailingStop::new(Side::Sell, Trail::Distance(dec!(2)));
+        let series = [dec!(100), dec!(101), dec!(104), dec!(103), dec!(102.5)];
+        for price in series {
+            assert!(!stop.update(price), "{}", price);
+        }
+        assert_eq!(stop.stop_price(), Some(dec!(102)));
+        assert!(stop.update(dec!(102)));
+
+        // Closing a short at 1% above the lowest ask
+        let mut stop = TrailingStop::new(Side::Buy, Trail::CallbackRateBps(dec!(100)));
+        for price in [dec!(200), dec!(190), dec!(191), dec!(180)] {
+            assert!(!stop.update(price), "{}", price);
+        }
+        assert_eq!(stop.stop_price(), Some(dec!(181.8)));
+        assert!(!stop.update(dec!(181.7)));
+        assert!(stop.update(dec!(181.8)));
+    }
+
+    fn request() -> TrailingStopRequest {
+        TrailingStopRequest {
+            client_order_id: "cs1".to_string(),
+            symbol: "BTCUSDT".to_string(),
+            side: Side::Sell,
+            quantity: dec!(2),
+            trail: Trail::Distance(dec!(2)),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_venue_stop_follows_the_trail_and_is_pulled_when_hit() {
+        let adapter = Arc::new(MockAdapter::new("mock", dec!(99), dec!(100)).with_stop_orders());
+        let mut trail = EmulatedTrail::start(adapter.clone(), credentials(), request()).await.unwrap();
+        assert_eq!(trail.order_id(), Some("mock-stop-0"));
+
+        // The mock amends by replacing, so each move cancels the last stop
+        for bid in [dec!(101), dec!(100.5), dec!(103)] {
+            assert!(!trail.follow(bid).await, "{}", bid);
+        }
+        let stops = adapter.stop_orders();
+        let prices: Vec<_> = stops.iter().map(|stop| stop.stop_price).collect();
+        assert_eq!(prices, [dec!(97), dec!(99), dec!(101)]);
+        assert!(stops.iter().all(|stop| stop.side == Side::Sell && stop.quantity == dec!(2)));
+        assert_eq!(adapter.cancelled(), ["mock-stop-0", "mock-stop-1"]);
+
+        // The bid falls through the stop: it is pulled and the leg closed here
+        adapter.set_prices(dec!(100.5), dec!(101.5));
+        let slicer = OrderSlicer::new(SlicingConfig::default());
+        let exit = trail.run(slicer, Arc::new(AtomicBool::new(false))).await.unwrap().unwrap();
+
+        assert_eq!(adapter.cancelled(), ["mock-stop-0", "mock-stop-1", "mock-stop-2"]);
+        let placed = adapter.placed();
+        assert_eq!(placed.len(), 1);
+        assert!(placed[0].reduce_only);
+        assert_eq!(placed[0].quantity, dec!(2));
+        assert_eq!(exit.filled_quantity, dec!(2));
+    }
+
+    #[tokio::test]
+    async fn test_stop_filled_at_the_venue_ends_the_trail() {
+        let adapter = Arc::new(MockAdapter::new("mock", dec!(99), dec!(100)).with_stop_orders());
+        let trail = EmulatedTrail::start(adapter.clone(), credentials(), request()).await.unwrap();
+
+        adapter.trigger_stop_orders();
+        let slicer = OrderSlicer::new(SlicingConfig::default());
+        let exit = trail.run(slicer, Arc::new(AtomicBool::new(false))).await.unwrap().unwrap();
+
+        assert!(adapter.placed().is_empty());
+        assert_eq!(
+            exit,
+            TrailingExit {
+                total_quantity: dec!(2),
+                filled_quantity: dec!(2),
+                avg_fill_price: dec!(97),
+            }
+        );
+    }
+}